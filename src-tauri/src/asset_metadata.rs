@@ -0,0 +1,598 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{display_path, FsResult};
+
+// Bytes read from the start of a file to find its dimensions or stream header
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+// Bytes of an SVG searched for the root element
+const MAX_SVG_BYTES: usize = 16 * 1024;
+
+// Bytes of a font's name table read at most
+const MAX_NAME_TABLE_BYTES: usize = 64 * 1024;
+
+// Top-level boxes of an MP4 walked before giving up on finding `moov`
+const MAX_MP4_BOXES: usize = 64;
+
+// Paths described by one batch call
+pub const MAX_BATCH_PATHS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    Image,
+    Font,
+    Audio,
+    Video,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AssetMetadata {
+    pub path: String,
+    pub size: u64,
+    pub kind: AssetKind,
+    pub mime: Option<String>,
+    /// Pixels; for SVG the intrinsic size when given in px or unitless, else the viewBox size
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// SVG viewBox as written
+    pub view_box: Option<String>,
+    pub font_family: Option<String>,
+    pub duration_secs: Option<f64>,
+    /// Why only size and mime are known: an unreadable file or a header that did not parse
+    pub error: Option<String>,
+}
+
+fn kind_and_mime(path: &Path) -> (AssetKind, Option<&'static str>) {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => (AssetKind::Image, Some("image/png")),
+        "jpg" | "jpeg" => (AssetKind::Image, Some("image/jpeg")),
+        "gif" => (AssetKind::Image, Some("image/gif")),
+        "webp" => (AssetKind::Image, Some("image/webp")),
+        "svg" => (AssetKind::Image, Some("image/svg+xml")),
+        "ico" => (AssetKind::Image, Some("image/x-icon")),
+        "bmp" => (AssetKind::Image, Some("image/bmp")),
+        "ttf" => (AssetKind::Font, Some("font/ttf")),
+        "otf" => (AssetKind::Font, Some("font/otf")),
+        "woff" => (AssetKind::Font, Some("font/woff")),
+        "woff2" => (AssetKind::Font, Some("font/woff2")),
+        "wav" => (AssetKind::Audio, Some("audio/wav")),
+        "flac" => (AssetKind::Audio, Some("audio/flac")),
+        "mp3" => (AssetKind::Audio, Some("audio/mpeg")),
+        "ogg" => (AssetKind::Audio, Some("audio/ogg")),
+        "m4a" => (AssetKind::Audio, Some("audio/mp4")),
+        "mp4" | "m4v" => (AssetKind::Video, Some("video/mp4")),
+        "mov" => (AssetKind::Video, Some("video/quicktime")),
+        "webm" => (AssetKind::Video, Some("video/webm")),
+        _ => (AssetKind::Other, None),
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn le32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Up to `limit` bytes starting at `offset`
+fn read_at(file: &mut File, offset: u64, limit: usize) -> std::io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    file.take(limit as u64).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn png_size(header: &[u8]) -> Option<(u32, u32)> {
+    if !header.starts_with(b"\x89PNG\r\n\x1a\n") || header.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    Some((be32(header, 16)?, be32(header, 20)?))
+}
+
+fn gif_size(header: &[u8]) -> Option<(u32, u32)> {
+    if !header.starts_with(b"GIF87a") && !header.starts_with(b"GIF89a") {
+        return None;
+    }
+    Some((le16(header, 6)?, le16(header, 8)?))
+}
+
+fn webp_size(header: &[u8]) -> Option<(u32, u32)> {
+    if !header.starts_with(b"RIFF") || header.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    match header.get(12..16)? {
+        b"VP8 " => Some((le16(header, 26)? & 0x3fff, le16(header, 28)? & 0x3fff)),
+        b"VP8L" => {
+            let b = header.get(21..25)?;
+            let width = 1 + (b[0] as u32 | (b[1] as u32 & 0x3f) << 8);
+            let height = 1 + (b[1] as u32 >> 6 | (b[2] as u32) << 2 | (b[3] as u32 & 0x0f) << 10);
+            Some((width, height))
+        }
+        b"VP8X" => Some((1 + le24(header, 24)?, 1 + le24(header, 27)?)),
+        _ => None,
+    }
+}
+
+/// Walk JPEG markers up to the first start-of-frame, which holds the dimensions
+fn jpeg_size(header: &[u8]) -> Option<(u32, u32)> {
+    if !header.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= header.len() {
+        if header[i] != 0xFF {
+            return None;
+        }
+        let marker = header[i + 1];
+        match marker {
+            0xFF => i += 1,
+            0x01 | 0xD0..=0xD8 => i += 2,
+            0xD9 => return None,
+            // SOF markers, leaving out DHT (C4), JPG (C8) and DAC (CC)
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => return Some((be16(header, i + 7)?, be16(header, i + 5)?)),
+            _ => i += 2 + be16(header, i + 2)? as usize,
+        }
+    }
+    None
+}
+
+/// Pixels for an SVG length that is unitless or in px
+fn svg_length(value: &str) -> Option<u32> {
+    let number = value.trim().strip_suffix("px").unwrap_or(value.trim());
+    number.parse::<f64>().ok().filter(|n| *n > 0.0).map(|n| n.round() as u32)
+}
+
+fn svg_metadata(header: &[u8], metadata: &mut AssetMetadata) -> bool {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"\b(width|height|viewBox)\s*=\s*["']([^"']*)["']"#).unwrap());
+
+    let text = String::from_utf8_lossy(header);
+    let Some(start) = text.find("<svg") else { return false };
+    let Some(length) = text[start..].find('>') else { return false };
+    for capture in attribute.captures_iter(&text[start..start + length]) {
+        let value = capture[2].to_string();
+        match &capture[1] {
+            "width" => metadata.width = svg_length(&value),
+            "height" => metadata.height = svg_length(&value),
+            _ => metadata.view_box = Some(value),
+        }
+    }
+    if metadata.width.is_none() || metadata.height.is_none() {
+        let view_box: Vec<f64> = metadata
+            .view_box
+            .as_deref()
+            .unwrap_or("")
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|part| part.parse().ok())
+            .collect();
+        if let [_, _, width, height] = view_box[..] {
+            metadata.width = metadata.width.or(Some(width.round() as u32));
+            metadata.height = metadata.height.or(Some(height.round() as u32));
+        }
+    }
+    true
+}
+
+/// Family name from a TrueType or OpenType `name` table, preferring the typographic family
+/// (ID 16) over the legacy one (ID 1), and English Windows names over the rest
+fn font_family(file: &mut File, header: &[u8]) -> Option<String> {
+    // A collection's first font stands in for the rest
+    let sfnt = if header.starts_with(b"ttcf") { be32(header, 12)? as usize } else { 0 };
+    let table_count = be16(header, sfnt + 4)? as usize;
+    let (offset, length) = (0..table_count)
+        .map(|i| sfnt + 12 + i * 16)
+        .find(|&record| header.get(record..record + 4) == Some(b"name"))
+        .and_then(|record| Some((be32(header, record + 8)?, be32(header, record + 12)?)))?;
+
+    let table = read_at(file, offset as u64, (length as usize).min(MAX_NAME_TABLE_BYTES)).ok()?;
+    let count = be16(&table, 2)? as usize;
+    let strings = be16(&table, 4)? as usize;
+    let mut best: Option<(u32, String)> = None;
+    for record in (0..count).map(|i| 6 + i * 12) {
+        let (platform, language, name_id) = (be16(&table, record)?, be16(&table, record + 4)?, be16(&table, record + 6)?);
+        if name_id != 1 && name_id != 16 {
+            continue;
+        }
+        let (length, start) = (be16(&table, record + 8)? as usize, strings + be16(&table, record + 10)? as usize);
+        let Some(raw) = table.get(start..start + length) else { continue };
+        let name = match platform {
+            0 | 3 => String::from_utf16_lossy(&raw.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec<_>>()),
+            _ => String::from_utf8_lossy(raw).to_string(),
+        };
+        let rank = (name_id == 16) as u32 * 4 + (platform == 3) as u32 * 2 + (language == 0x409) as u32;
+        if best.as_ref().map(|(r, _)| rank > *r).unwrap_or(true) && !name.trim().is_empty() {
+            best = Some((rank, name.trim().to_string()));
+        }
+    }
+    best.map(|(_, name)| name)
+}
+
+fn wav_duration(header: &[u8]) -> Option<f64> {
+    if !header.starts_with(b"RIFF") || header.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let (mut byte_rate, mut i) = (None, 12);
+    while i + 8 <= header.len() {
+        let size = le32(header, i + 4)? as usize;
+        match header.get(i..i + 4)? {
+            b"fmt " => byte_rate = le32(header, i + 16),
+            b"data" => return byte_rate.filter(|r| *r > 0).map(|rate| size as f64 / rate as f64),
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        i += 8 + size + size % 2;
+    }
+    None
+}
+
+fn flac_duration(header: &[u8]) -> Option<f64> {
+    // STREAMINFO is always the first metadata block
+    if !header.starts_with(b"fLaC") || header.len() < 26 {
+        return None;
+    }
+    let sample_rate = (header[18] as u32) << 12 | (header[19] as u32) << 4 | (header[20] as u32) >> 4;
+    let samples = ((header[21] & 0x0f) as u64) << 32 | be32(header, 22)? as u64;
+    (sample_rate > 0 && samples > 0).then(|| samples as f64 / sample_rate as f64)
+}
+
+/// Duration from the `mvhd` box inside `moov`, which may sit at the end of the file
+fn mp4_duration(file: &mut File, size: u64) -> Option<f64> {
+    let mut offset = 0;
+    for _ in 0..MAX_MP4_BOXES {
+        let head = read_at(file, offset, 16).ok()?;
+        let (box_size, header_size) = match be32(&head, 0)? {
+            0 => (size - offset, 8),
+            1 => (be64(&head, 8)?, 16),
+            n => (n as u64, 8),
+        };
+        if box_size < header_size {
+            return None;
+        }
+        if head.get(4..8)? == b"moov" {
+            let moov = read_at(file, offset + header_size, (box_size - header_size).min(MAX_HEADER_BYTES as u64) as usize).ok()?;
+            let mut i = 0;
+            while i + 8 <= moov.len() {
+                let child = be32(&moov, i)? as usize;
+                if moov.get(i + 4..i + 8)? == b"mvhd" {
+                    let body = i + 8;
+                    let (timescale, duration) = match moov.get(body)? {
+                        1 => (be32(&moov, body + 20)?, be64(&moov, body + 24)?),
+                        _ => (be32(&moov, body + 12)?, be32(&moov, body + 16)? as u64),
+                    };
+                    return (timescale > 0).then(|| duration as f64 / timescale as f64);
+                }
+                if child < 8 {
+                    return None;
+                }
+                i += child;
+            }
+            return None;
+        }
+        offset += box_size;
+        if offset >= size {
+            return None;
+        }
+    }
+    None
+}
+
+/// Fill in what the header bytes say; false when the format was recognised but did not parse
+fn read_header(path: &Path, metadata: &mut AssetMetadata) -> std::io::Result<bool> {
+    let mut file = File::open(path)?;
+    let limit = if path.extension().map(|e| e.eq_ignore_ascii_case("svg")).unwrap_or(false) { MAX_SVG_BYTES } else { MAX_HEADER_BYTES };
+    let header = read_at(&mut file, 0, limit)?;
+    let size = metadata.size;
+
+    let parsed = match metadata.kind {
+        AssetKind::Image if metadata.mime.as_deref() == Some("image/svg+xml") => return Ok(svg_metadata(&header, metadata)),
+        AssetKind::Image => png_size(&header).or_else(|| jpeg_size(&header)).or_else(|| gif_size(&header)).or_else(|| webp_size(&header)).map(|(width, height)| {
+            metadata.width = Some(width);
+            metadata.height = Some(height);
+        }),
+        AssetKind::Font => font_family(&mut file, &header).map(|family| metadata.font_family = Some(family)),
+        AssetKind::Audio | AssetKind::Video => wav_duration(&header)
+            .or_else(|| flac_duration(&header))
+            .or_else(|| if header.get(4..8) == Some(b"ftyp") { mp4_duration(&mut file, size) } else { None })
+            .map(|duration| metadata.duration_secs = Some(duration)),
+        AssetKind::Other => Some(()),
+    };
+    Ok(parsed.is_some())
+}
+
+/// Size, mime type and whatever the first few kilobytes reveal: image dimensions, an SVG's
+/// intrinsic size and viewBox, a font's family name, or a WAV, FLAC or MP4 duration. Formats
+/// that need a full decode (MP3, Ogg, WebM, compressed WOFF) get size and mime only.
+pub fn asset_metadata(path: &Path) -> FsResult<AssetMetadata> {
+    let size = std::fs::metadata(path)?.len();
+    let (kind, mime) = kind_and_mime(path);
+    let mut metadata = AssetMetadata {
+        path: display_path(path),
+        size,
+        kind,
+        mime: mime.map(str::to_string),
+        width: None,
+        height: None,
+        view_box: None,
+        font_family: None,
+        duration_secs: None,
+        error: None,
+    };
+    let woff = matches!(mime, Some("font/woff") | Some("font/woff2"));
+    let decodes_only = matches!(mime, Some("audio/mpeg") | Some("audio/ogg") | Some("video/webm"));
+    if kind == AssetKind::Other || woff || decodes_only {
+        return Ok(metadata);
+    }
+    match read_header(path, &mut metadata) {
+        Ok(true) => {}
+        Ok(false) => metadata.error = Some("Unrecognised header".to_string()),
+        Err(e) => metadata.error = Some(e.to_string()),
+    }
+    Ok(metadata)
+}
+
+/// Metadata for each path in order; files that cannot be read carry the reason in `error`
+pub fn assets_metadata(paths: &[String]) -> Vec<AssetMetadata> {
+    paths
+        .iter()
+        .take(MAX_BATCH_PATHS)
+        .map(|raw| {
+            let path = crate::fs::decode_path(raw);
+            asset_metadata(&path).unwrap_or_else(|e| {
+                let (kind, mime) = kind_and_mime(&path);
+                AssetMetadata {
+                    path: display_path(&path),
+                    size: 0,
+                    kind,
+                    mime: mime.map(str::to_string),
+                    width: None,
+                    height: None,
+                    view_box: None,
+                    font_family: None,
+                    duration_secs: None,
+                    error: Some(e.to_string()),
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::test_support::temp_dir;
+
+    fn fixture(root: &Path, name: &str, bytes: &[u8]) -> PathBuf {
+        let path = root.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn metadata(root: &Path, name: &str, bytes: &[u8]) -> AssetMetadata {
+        let metadata = asset_metadata(&fixture(root, name, bytes)).unwrap();
+        assert_eq!(metadata.size, bytes.len() as u64);
+        metadata
+    }
+
+    fn assert_dimensions(metadata: &AssetMetadata, width: u32, height: u32) {
+        assert_eq!(metadata.kind, AssetKind::Image);
+        assert_eq!((metadata.width, metadata.height), (Some(width), Some(height)), "{:?}", metadata);
+        assert_eq!(metadata.error, None);
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend(width.to_be_bytes());
+        bytes.extend(height.to_be_bytes());
+        bytes.extend([8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    fn riff(form: &[u8], chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = form.to_vec();
+        for (id, data) in chunks {
+            body.extend(*id);
+            body.extend((data.len() as u32).to_le_bytes());
+            body.extend(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((8 + body.len()) as u32).to_be_bytes().to_vec();
+        bytes.extend(kind);
+        bytes.extend(body);
+        bytes
+    }
+
+    /// A TrueType file holding only a `name` table with the given (platform, name id, name) records
+    fn ttf(names: &[(u32, u32, &str)]) -> Vec<u8> {
+        let mut records = Vec::new();
+        let mut strings = Vec::new();
+        for (platform, name_id, name) in names {
+            let encoded: Vec<u8> = match *platform {
+                3 => name.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+                _ => name.as_bytes().to_vec(),
+            };
+            let language: u16 = if *platform == 3 { 0x409 } else { 0 };
+            for field in [*platform as u16, 1, language, *name_id as u16, encoded.len() as u16, strings.len() as u16] {
+                records.extend(field.to_be_bytes());
+            }
+            strings.extend(encoded);
+        }
+        let mut table = Vec::new();
+        for field in [0, names.len() as u16, (6 + records.len()) as u16] {
+            table.extend(field.to_be_bytes());
+        }
+        table.extend(records);
+        table.extend(strings);
+
+        let mut bytes = vec![0, 1, 0, 0, 0, 1, 0, 16, 0, 0, 0, 0];
+        bytes.extend(b"name");
+        bytes.extend([0; 4]);
+        bytes.extend(28u32.to_be_bytes());
+        bytes.extend((table.len() as u32).to_be_bytes());
+        bytes.extend(table);
+        bytes
+    }
+
+    #[test]
+    fn raster_images_report_their_dimensions() {
+        let (_dir, root) = temp_dir();
+        assert_dimensions(&metadata(&root, "icon.png", &png(3, 2)), 3, 2);
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend([0x40, 0x01, 0xf0, 0x00, 0, 0, 0]);
+        assert_dimensions(&metadata(&root, "spinner.gif", &gif), 320, 240);
+
+        // An APP0 segment ahead of the baseline frame header
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend([0xFF, 0xC0, 0x00, 0x11, 8, 0x01, 0xE0, 0x02, 0x80, 3]);
+        jpeg.extend([0; 9]);
+        let jpeg = metadata(&root, "photo.JPG", &jpeg);
+        assert_eq!(jpeg.mime.as_deref(), Some("image/jpeg"));
+        assert_dimensions(&jpeg, 640, 480);
+
+        let mut extended = vec![0x10, 0, 0, 0];
+        extended.extend(&99u32.to_le_bytes()[..3]);
+        extended.extend(&49u32.to_le_bytes()[..3]);
+        assert_dimensions(&metadata(&root, "extended.webp", &riff(b"WEBP", &[(b"VP8X", extended)])), 100, 50);
+
+        let mut lossless = vec![0x2f];
+        lossless.extend((4u32 | 6 << 14).to_le_bytes());
+        assert_dimensions(&metadata(&root, "lossless.webp", &riff(b"WEBP", &[(b"VP8L", lossless)])), 5, 7);
+
+        let mut lossy = vec![0x10, 0x02, 0x00, 0x9d, 0x01, 0x2a];
+        lossy.extend(17u16.to_le_bytes());
+        lossy.extend(9u16.to_le_bytes());
+        assert_dimensions(&metadata(&root, "lossy.webp", &riff(b"WEBP", &[(b"VP8 ", lossy)])), 17, 9);
+    }
+
+    #[test]
+    fn svg_reports_intrinsic_size_and_view_box() {
+        let (_dir, root) = temp_dir();
+        let sized = metadata(&root, "logo.svg", br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="24px" height="16" viewBox="0 0 48 32"><path/></svg>"#);
+        assert_dimensions(&sized, 24, 16);
+        assert_eq!(sized.view_box.as_deref(), Some("0 0 48 32"));
+
+        // Without px or unitless lengths the viewBox size stands in
+        let relative = metadata(&root, "wide.svg", br#"<svg width="10em" viewBox="0,0,100,50"></svg>"#);
+        assert_dimensions(&relative, 100, 50);
+
+        let broken = metadata(&root, "broken.svg", b"not an svg");
+        assert_eq!(broken.error.as_deref(), Some("Unrecognised header"));
+    }
+
+    #[test]
+    fn fonts_report_the_typographic_family() {
+        let (_dir, root) = temp_dir();
+        let font = metadata(&root, "Fixture.ttf", &ttf(&[(1, 1, "Mac Legacy"), (3, 1, "Fixture"), (3, 16, "Fixture Sans")]));
+        assert_eq!(font.kind, AssetKind::Font);
+        assert_eq!(font.font_family.as_deref(), Some("Fixture Sans"));
+
+        let legacy = metadata(&root, "Legacy.otf", &ttf(&[(1, 1, "Mac Legacy"), (3, 1, "Windows Legacy")]));
+        assert_eq!(legacy.font_family.as_deref(), Some("Windows Legacy"));
+
+        // Compressed web fonts are not decoded
+        let woff = metadata(&root, "Fixture.woff2", b"wOF2 compressed");
+        assert_eq!((woff.font_family, woff.error), (None, None));
+    }
+
+    #[test]
+    fn audio_and_video_report_their_duration() {
+        let (_dir, root) = temp_dir();
+        let mut format = vec![1, 0, 1, 0];
+        format.extend(8000u32.to_le_bytes());
+        format.extend(8000u32.to_le_bytes());
+        format.extend([1, 0, 8, 0]);
+        let wav = metadata(&root, "beep.wav", &riff(b"WAVE", &[(b"fmt ", format), (b"data", vec![0; 4000])]));
+        assert_eq!(wav.kind, AssetKind::Audio);
+        assert_eq!(wav.duration_secs, Some(0.5));
+
+        // STREAMINFO: 44.1 kHz, 88200 samples
+        let mut flac = b"fLaC\x80\0\0\x22".to_vec();
+        flac.extend([0; 10]);
+        flac.extend([0x0A, 0xC4, 0x42, 0xF0, 0x00, 0x01, 0x58, 0x88]);
+        flac.extend([0; 16]);
+        assert_eq!(metadata(&root, "tone.flac", &flac).duration_secs, Some(2.0));
+
+        // `moov` after the media data, as most encoders write it
+        let mut header = vec![0; 12];
+        header.extend(1000u32.to_be_bytes());
+        header.extend(1500u32.to_be_bytes());
+        header.extend([0; 80]);
+        let mut mp4 = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        mp4.extend(mp4_box(b"mdat", &[0; 32]));
+        mp4.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &header)));
+        let video = metadata(&root, "clip.mp4", &mp4);
+        assert_eq!(video.kind, AssetKind::Video);
+        assert_eq!(video.duration_secs, Some(1.5));
+
+        let mp3 = metadata(&root, "song.mp3", b"ID3\x04\0\0\0\0\0\0");
+        assert_eq!((mp3.mime.as_deref(), mp3.duration_secs, mp3.error), (Some("audio/mpeg"), None, None));
+    }
+
+    #[test]
+    fn headers_beyond_the_read_limit_are_not_searched() {
+        let (_dir, root) = temp_dir();
+        let mut format = vec![1, 0, 1, 0];
+        format.extend(8000u32.to_le_bytes());
+        format.extend(8000u32.to_le_bytes());
+        format.extend([1, 0, 8, 0]);
+        let padded = riff(b"WAVE", &[(b"fmt ", format), (b"JUNK", vec![0; MAX_HEADER_BYTES]), (b"data", vec![0; 8])]);
+        let wav = metadata(&root, "padded.wav", &padded);
+        assert_eq!(wav.duration_secs, None);
+        assert_eq!(wav.error.as_deref(), Some("Unrecognised header"));
+    }
+
+    #[test]
+    fn unknown_and_unreadable_files_fall_back_to_size_and_mime() {
+        let (_dir, root) = temp_dir();
+        let other = metadata(&root, "data.bin", &[1, 2, 3]);
+        assert_eq!((other.kind, other.mime, other.error), (AssetKind::Other, None, None));
+
+        let fake = metadata(&root, "fake.png", b"plain text");
+        assert_eq!((fake.width, fake.error.as_deref()), (None, Some("Unrecognised header")));
+
+        let good = fixture(&root, "good.png", &png(1, 1));
+        let missing = root.join("missing.gif");
+        let batch = assets_metadata(&[display_path(&good), display_path(&missing)]);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].width, Some(1));
+        assert_eq!((batch[1].size, batch[1].mime.as_deref()), (0, Some("image/gif")));
+        assert!(batch[1].error.is_some());
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+
+use crate::fs::FsResult;
+
+pub(crate) const BLOB_DIR: &str = "blobs";
+
+/// Hex-encoded SHA-256 of `content`, the name its blob is stored under
+pub fn hash_bytes(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// File contents stored by hash in the app data dir, shared by local history and checkpoints so
+/// the same bytes are kept once however many versions and checkpoints refer to them
+#[derive(Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn load(data_dir: &Path) -> Self {
+        Self { dir: data_dir.join(BLOB_DIR) }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+
+    /// Store `content` under `hash`, which must be `hash_bytes(content)`, unless it is already there
+    pub fn write(&self, hash: &str, content: &[u8]) -> FsResult<()> {
+        let blob = self.path(hash);
+        if !blob.exists() {
+            fs::create_dir_all(&self.dir)?;
+            crate::fs::write_atomic(&blob, content)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(&self, hash: &str) -> FsResult<Vec<u8>> {
+        Ok(fs::read(self.path(hash))?)
+    }
+
+    /// Delete every blob whose hash is not in `referenced`; returns how many were removed.
+    /// `referenced` must cover every user of the store.
+    pub fn retain(&self, referenced: &HashSet<String>) -> usize {
+        let Ok(blobs) = fs::read_dir(&self.dir) else { return 0 };
+        blobs
+            .filter_map(|b| b.ok())
+            .filter(|b| !referenced.contains(&b.file_name().to_string_lossy().to_string()))
+            .filter(|b| fs::remove_file(b.path()).is_ok())
+            .count()
+    }
+
+    /// Move blobs from a store's old private `dir` into this one and remove `dir`
+    pub fn adopt(&self, dir: &Path) -> std::io::Result<()> {
+        let Ok(entries) = fs::read_dir(dir) else { return Ok(()) };
+        fs::create_dir_all(&self.dir)?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let target = self.dir.join(entry.file_name());
+            if target.exists() {
+                fs::remove_file(entry.path())?;
+            } else {
+                fs::rename(entry.path(), target)?;
+            }
+        }
+        fs::remove_dir(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_contents_share_a_blob_until_unreferenced() {
+        let data = tempfile::tempdir().unwrap();
+        let blobs = BlobStore::load(data.path());
+        let (kept, dropped) = (hash_bytes(b"kept"), hash_bytes(b"dropped"));
+        blobs.write(&kept, b"kept").unwrap();
+        blobs.write(&kept, b"kept").unwrap();
+        blobs.write(&dropped, b"dropped").unwrap();
+        assert_eq!(fs::read_dir(data.path().join(BLOB_DIR)).unwrap().count(), 2);
+
+        assert_eq!(blobs.retain(&HashSet::from([kept.clone()])), 1);
+        assert_eq!(blobs.read(&kept).unwrap(), b"kept");
+        assert!(blobs.read(&dropped).is_err());
+    }
+
+    #[test]
+    fn adopt_moves_old_blobs_without_duplicates() {
+        let data = tempfile::tempdir().unwrap();
+        let blobs = BlobStore::load(data.path());
+        let (shared, only_old) = (hash_bytes(b"shared"), hash_bytes(b"only old"));
+        blobs.write(&shared, b"shared").unwrap();
+        let old = data.path().join("local-history").join(BLOB_DIR);
+        fs::create_dir_all(&old).unwrap();
+        fs::write(old.join(&shared), b"shared").unwrap();
+        fs::write(old.join(&only_old), b"only old").unwrap();
+
+        blobs.adopt(&old).unwrap();
+        assert!(!old.exists());
+        assert_eq!(blobs.read(&only_old).unwrap(), b"only old");
+        assert_eq!(blobs.read(&shared).unwrap(), b"shared");
+    }
+}
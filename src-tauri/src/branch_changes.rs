@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::display_path;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChangedPath {
+    /// Repo-relative, `/`-separated; the new path for renames
+    pub path: String,
+    pub old_path: Option<String>,
+    /// "added", "deleted", "modified", "renamed", "copied" or "typechange"
+    pub status: String,
+    /// Not tracked yet; reported apart from committed and staged additions
+    pub untracked: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ChangedDirectoryCounts {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+    pub untracked: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChangedPaths {
+    pub base_ref: String,
+    /// Where HEAD forked from `base_ref`
+    pub merge_base: String,
+    pub files: Vec<ChangedPath>,
+    /// Counts below each directory, keyed by absolute path as `list_directory` returns it
+    pub directories: BTreeMap<String, ChangedDirectoryCounts>,
+}
+
+/// Diff from the merge-base of HEAD and `base_ref` to the working tree: committed, staged and
+/// unstaged changes together. Files changed and changed back match the merge-base, so they drop out.
+pub fn changed_paths_vs_ref(repo_path: &Path, base_ref: &str) -> Result<ChangedPaths> {
+    let repo = Repository::discover(repo_path)?;
+    let workdir = crate::git::require_workdir(&repo)?.to_path_buf();
+    let head = repo.head()?.peel_to_commit()?;
+    let base = repo
+        .revparse_single(base_ref)
+        .map_err(|_| anyhow!("Cannot resolve '{}'", base_ref))?
+        .peel_to_commit()?;
+    let merge_base = repo
+        .merge_base(head.id(), base.id())
+        .map_err(|_| anyhow!("HEAD and '{}' have no common history", base_ref))?;
+    let tree = repo.find_commit(merge_base)?.tree()?;
+
+    let mut options = git2::DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let mut diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true).for_untracked(true)))?;
+
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().replace('\\', "/"));
+    let mut files = Vec::new();
+    let mut rollup: HashMap<String, ChangedDirectoryCounts> = HashMap::new();
+    for delta in diff.deltas() {
+        let Some(path) = path_of(delta.new_file()).or_else(|| path_of(delta.old_file())) else { continue };
+        let untracked = delta.status() == git2::Delta::Untracked;
+        let status = match delta.status() {
+            git2::Delta::Added | git2::Delta::Untracked => "added",
+            git2::Delta::Deleted => "deleted",
+            git2::Delta::Renamed => "renamed",
+            git2::Delta::Copied => "copied",
+            git2::Delta::Typechange => "typechange",
+            _ => "modified",
+        };
+        let old_path = matches!(delta.status(), git2::Delta::Renamed | git2::Delta::Copied).then(|| path_of(delta.old_file())).flatten();
+
+        let mut dirs: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+        dirs.push("");
+        for dir in dirs {
+            let counts = rollup.entry(dir.to_string()).or_default();
+            match (untracked, status) {
+                (true, _) => counts.untracked += 1,
+                (_, "added") | (_, "copied") => counts.added += 1,
+                (_, "deleted") => counts.deleted += 1,
+                (_, "renamed") => counts.renamed += 1,
+                _ => counts.modified += 1,
+            }
+        }
+        files.push(ChangedPath { path, old_path, status: status.to_string(), untracked });
+    }
+
+    let directories = rollup
+        .into_iter()
+        .map(|(dir, counts)| {
+            let absolute = if dir.is_empty() { workdir.clone() } else { workdir.join(&dir) };
+            let key = display_path(&absolute);
+            (key.strip_suffix(['/', '\\']).map(str::to_string).unwrap_or(key), counts)
+        })
+        .collect();
+    Ok(ChangedPaths { base_ref: base_ref.to_string(), merge_base: merge_base.to_string(), files, directories })
+}
+
+/// Results per (workspace, base ref), dropped whenever the workspace watcher reports a change.
+/// HEAD and the base tip are part of the key too, for changes made while nothing was watching.
+#[derive(Default)]
+pub struct ChangedPathsCache(Mutex<HashMap<(PathBuf, String), (String, ChangedPaths)>>);
+
+fn fingerprint(repo_path: &Path, base_ref: &str) -> Option<String> {
+    let repo = Repository::discover(repo_path).ok()?;
+    let head = repo.head().ok()?.target()?;
+    let base = repo.revparse_single(base_ref).ok()?.id();
+    let index_mtime = std::fs::metadata(repo.path().join("index")).and_then(|m| m.modified()).ok();
+    Some(format!("{}:{}:{:?}", head, base, index_mtime))
+}
+
+impl ChangedPathsCache {
+    pub fn get_or_compute(&self, repo_path: &Path, base_ref: &str) -> Result<ChangedPaths> {
+        let key = (repo_path.to_path_buf(), base_ref.to_string());
+        let print = fingerprint(repo_path, base_ref);
+        if let Some(print) = &print {
+            if let Some((cached_print, cached)) = self.0.lock().unwrap().get(&key) {
+                if cached_print == print {
+                    return Ok(cached.clone());
+                }
+            }
+        }
+        let computed = changed_paths_vs_ref(repo_path, base_ref)?;
+        if let Some(print) = print {
+            self.0.lock().unwrap().insert(key, (print, computed.clone()));
+        }
+        Ok(computed)
+    }
+
+    /// Forget every result for repositories at or under `root`
+    pub fn invalidate(&self, root: &Path) {
+        self.0.lock().unwrap().retain(|(repo_path, _), _| !repo_path.starts_with(root) && !root.starts_with(repo_path));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use ignore::WalkBuilder;
+use regex::Regex;
+
+use crate::fs::{display_path, encode_path, normalize_lexically};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternKind {
+    /// `*`, `**` and `?` become capture groups 1, 2, ... in order; a `*` or `?` in the
+    /// template stands for the next one
+    #[default]
+    Glob,
+    /// Full regex matched against the whole name; the template uses `$1` or `${name}`
+    Regex,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BulkRenameItem {
+    pub from: String,
+    pub to: String,
+    /// "duplicate_target" or "target_exists"; any conflict blocks the whole rename
+    pub conflict: Option<String>,
+    /// After applying: "renamed" or "failed"
+    pub status: Option<String>,
+    /// Set while planning for targets outside the root; such items are never applied
+    pub error: Option<String>,
+    #[cfg(feature = "git")]
+    pub index: Option<crate::git::IndexMoveResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BulkRenamePlan {
+    pub root: String,
+    pub items: Vec<BulkRenameItem>,
+    pub conflicts: usize,
+    pub applied: bool,
+}
+
+/// Translate a glob into an anchored regex with one capture group per wildcard
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // "**/" also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("((?:.*/)?)");
+                } else {
+                    pattern.push_str("(.*)");
+                }
+            }
+            '*' => pattern.push_str("([^/]*)"),
+            '?' => pattern.push_str("([^/])"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+/// Number the template's wildcards so `Captures::expand` can fill them in
+fn glob_template(template: &str) -> String {
+    let mut expanded = String::new();
+    let mut group = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' => {
+                if c == '*' && chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                group += 1;
+                expanded.push_str(&format!("${{{}}}", group));
+            }
+            '$' => expanded.push_str("$$"),
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Work out every rename under `root` without touching the filesystem. Patterns without a
+/// `/` match file names in any folder; others match the path relative to `root`.
+pub fn plan(root: &Path, match_pattern: &str, rename_template: &str, kind: PatternKind) -> Result<BulkRenamePlan> {
+    let (pattern, template) = match kind {
+        PatternKind::Glob => (glob_to_regex(match_pattern), glob_template(rename_template)),
+        PatternKind::Regex => (format!("^(?:{})$", match_pattern), rename_template.to_string()),
+    };
+    let regex = Regex::new(&pattern).map_err(|e| anyhow!("Invalid pattern: {}", e))?;
+    let whole_path = match_pattern.contains('/');
+
+    let mut items = Vec::new();
+    let walker = WalkBuilder::new(root).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else { continue };
+        // Names that are not UTF-8 cannot be matched faithfully and are left alone
+        let subject = if whole_path { relative.to_str().map(|r| r.replace('\\', "/")) } else { path.file_name().and_then(|n| n.to_str()).map(str::to_string) };
+        let Some(subject) = subject else { continue };
+        let Some(captures) = regex.captures(&subject) else { continue };
+
+        let mut renamed = String::new();
+        captures.expand(&template, &mut renamed);
+        let target = normalize_lexically(&if whole_path { root.join(&renamed) } else { path.with_file_name(&renamed) });
+        if renamed.is_empty() || target == path {
+            continue;
+        }
+        items.push((path.to_path_buf(), target));
+    }
+    items.sort();
+
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for (_, to) in &items {
+        *targets.entry(to.clone()).or_default() += 1;
+    }
+    let items: Vec<BulkRenameItem> = items
+        .into_iter()
+        .map(|(from, to)| {
+            // A template producing `../x` or an absolute path would move the file out of the workspace
+            let error = (!to.starts_with(root) || to == root).then(|| format!("'{}' is outside {}", display_path(&to), display_path(root)));
+            let conflict = if error.is_some() {
+                None
+            } else if targets[&to] > 1 {
+                Some("duplicate_target".to_string())
+            } else if to.exists() && !same_file(&from, &to) {
+                Some("target_exists".to_string())
+            } else {
+                None
+            };
+            BulkRenameItem {
+                from: encode_path(&from),
+                to: encode_path(&to),
+                conflict,
+                status: None,
+                error,
+                #[cfg(feature = "git")]
+                index: None,
+            }
+        })
+        .collect();
+
+    Ok(BulkRenamePlan {
+        root: display_path(root),
+        conflicts: items.iter().filter(|i| i.conflict.is_some()).count(),
+        items,
+        applied: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::decode_path;
+    use crate::test_support::{temp_dir, write};
+
+    #[test]
+    fn targets_outside_the_root_are_refused_per_item() {
+        let (_dir, root) = temp_dir();
+        write(&root, "notes.md", "");
+        write(&root, "docs/guide.md", "");
+
+        let plan = plan(&root, "*.md", "../*.md", PatternKind::Glob).unwrap();
+        let escaping = plan.items.iter().find(|i| i.from.ends_with("notes.md")).unwrap();
+        assert!(escaping.error.is_some(), "{:?}", escaping);
+        // docs/../guide.md stays inside and is normalized
+        let inside = plan.items.iter().find(|i| i.from.ends_with("guide.md")).unwrap();
+        assert_eq!((inside.error.as_deref(), decode_path(&inside.to)), (None, root.join("guide.md")));
+        assert_eq!(plan.conflicts, 0);
+
+        let absolute = plan_absolute(&root);
+        assert!(absolute.items.iter().all(|i| i.error.is_some()), "{:?}", absolute.items);
+    }
+
+    fn plan_absolute(root: &Path) -> BulkRenamePlan {
+        let outside = std::env::temp_dir().join("bulk-rename-escape");
+        let template = format!("{}/$1", outside.to_string_lossy().replace('\\', "/"));
+        plan(root, "(.*)\\.md", &template, PatternKind::Regex).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_folders_round_trip_through_path_tokens() {
+        use std::os::unix::ffi::OsStrExt;
+        let (_dir, root) = temp_dir();
+        let folder = root.join(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("a.md"), "").unwrap();
+
+        let plan = plan(&root, "*.md", "*.markdown", PatternKind::Glob).unwrap();
+        assert_eq!(plan.items.len(), 1);
+        assert_eq!(decode_path(&plan.items[0].from), folder.join("a.md"));
+        assert_eq!(decode_path(&plan.items[0].to), folder.join("a.markdown"));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+struct CachedFile {
+    content: String,
+    hash: String,
+    modified: Option<SystemTime>,
+    /// Length on disk alongside `modified`, which `get` compares against the file's current stat
+    size: u64,
+    last_access: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// In-memory LRU cache of file contents keyed by canonical path
+pub struct FileCache {
+    entries: HashMap<PathBuf, CachedFile>,
+    max_bytes: u64,
+    total_bytes: u64,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+pub struct FileCacheState(pub Mutex<FileCache>);
+
+/// Hex-encoded SHA-256 of the given content
+pub fn content_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn cache_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl FileCache {
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_bytes,
+            total_bytes: 0,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Return cached content if the file on disk still matches the cached size and mtime
+    pub fn get(&mut self, path: &Path) -> Option<String> {
+        let key = cache_key(path);
+        let metadata = fs::metadata(&key).ok();
+
+        let fresh = match (self.entries.get(&key), metadata.as_ref()) {
+            (Some(entry), Some(meta)) => {
+                entry.size == meta.len() && entry.modified == meta.modified().ok()
+            }
+            _ => false,
+        };
+
+        if !fresh {
+            self.remove_key(&key);
+            self.misses += 1;
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_access = tick;
+        self.hits += 1;
+        Some(entry.content.clone())
+    }
+
+    /// Cache `content` as the file described by `metadata`, which must be taken before a read
+    /// or after a write so a change in between shows up as a stale entry rather than being
+    /// masked. Without metadata the entry is only dropped.
+    pub fn insert(&mut self, path: &Path, content: &str, metadata: Option<&fs::Metadata>) {
+        let key = cache_key(path);
+        let Some(metadata) = metadata else {
+            self.remove_key(&key);
+            return;
+        };
+        let size = metadata.len();
+        let modified = metadata.modified().ok();
+        let hash = content_hash(content);
+        self.tick += 1;
+
+        // Same content as before: only refresh the stat data
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.hash == hash {
+                entry.modified = modified;
+                entry.size = size;
+                entry.last_access = self.tick;
+                return;
+            }
+        }
+
+        self.remove_key(&key);
+        let bytes = content.len() as u64;
+        if bytes > self.max_bytes {
+            return;
+        }
+
+        self.entries.insert(key, CachedFile {
+            content: content.to_string(),
+            hash,
+            modified,
+            size,
+            last_access: self.tick,
+        });
+        self.total_bytes += bytes;
+        self.evict_to_budget();
+    }
+
+    /// Drop the entry for a path, and any entries beneath it if it is a directory
+    pub fn invalidate(&mut self, path: &Path) {
+        let key = cache_key(path);
+        let nested: Vec<PathBuf> = self.entries
+            .keys()
+            .filter(|k| k.starts_with(&key) || k.starts_with(path))
+            .cloned()
+            .collect();
+        for k in nested {
+            self.remove_key(&k);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.total_bytes = 0;
+    }
+
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+        self.evict_to_budget();
+    }
+
+    pub fn stats(&self, enabled: bool) -> CacheStats {
+        CacheStats {
+            enabled,
+            entries: self.entries.len(),
+            total_bytes: self.total_bytes,
+            max_bytes: self.max_bytes,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+
+    fn remove_key(&mut self, key: &Path) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.content.len() as u64;
+        }
+    }
+
+    fn evict_to_budget(&mut self) {
+        while self.total_bytes > self.max_bytes {
+            let oldest = self.entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_access)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(key) => {
+                    self.remove_key(&key);
+                    self.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write};
+
+    #[test]
+    fn a_write_between_stat_and_read_leaves_the_entry_stale() {
+        let (_dir, root) = temp_dir();
+        let path = write(&root, "file.txt", "old");
+        let before_read = fs::metadata(&path).unwrap();
+        write(&root, "file.txt", "written meanwhile");
+
+        let mut cache = FileCache::new(1024);
+        cache.insert(&path, "old", Some(&before_read));
+        assert_eq!(cache.get(&path), None);
+
+        cache.insert(&path, "written meanwhile", fs::metadata(&path).ok().as_ref());
+        assert_eq!(cache.get(&path).as_deref(), Some("written meanwhile"));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::blobs::{hash_bytes, BlobStore};
+use crate::diff::{self, TextDiffResult};
+use crate::fs::{display_path, is_binary_content, FsError, FsResult};
+
+pub(crate) const CHECKPOINT_DIR: &str = "checkpoints";
+const MANIFEST_DIR: &str = "manifests";
+
+// Larger files are recorded by hash and size only, so they can be compared but not diffed
+const MAX_STORED_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    pub hash: String,
+    pub size: u64,
+    pub binary: bool,
+    /// False when the file was too large for its contents to be kept
+    pub stored: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Checkpoint {
+    pub id: String,
+    pub project_path: String,
+    pub label: Option<String>,
+    pub created_at: i64,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Manifest {
+    checkpoint: Checkpoint,
+    /// Keyed by workspace-relative path with forward slashes
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointFileChange {
+    pub path: String,
+    /// "added", "removed" or "modified"
+    pub status: String,
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+    /// Either side is binary; only hash and size changes are reported
+    pub binary: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointDiff {
+    pub from: String,
+    /// None when compared with the current working tree
+    pub to: Option<String>,
+    pub changes: Vec<CheckpointFileChange>,
+    pub unchanged: usize,
+}
+
+/// Workspace snapshots: a manifest of path to content hash per checkpoint, with file contents
+/// in the shared blob store so unchanged files cost nothing per checkpoint
+pub struct CheckpointStore {
+    dir: PathBuf,
+    blobs: BlobStore,
+}
+
+/// Files a checkpoint covers: everything the workspace's ignore rules keep, minus `.git`
+fn workspace_files(root: &Path) -> Vec<(String, PathBuf)> {
+    WalkBuilder::new(root)
+        .hidden(false)
+        .filter_entry(|e| e.file_name() != ".git")
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+            Some((relative, e.into_path()))
+        })
+        .collect()
+}
+
+impl CheckpointStore {
+    pub fn load(data_dir: &Path) -> Self {
+        Self { dir: data_dir.join(CHECKPOINT_DIR), blobs: BlobStore::load(data_dir) }
+    }
+
+    fn manifest_path(&self, id: &str) -> FsResult<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(FsError::NotFound);
+        }
+        Ok(self.dir.join(MANIFEST_DIR).join(format!("{}.json", id)))
+    }
+
+    fn manifest(&self, id: &str) -> FsResult<Manifest> {
+        let raw = fs::read_to_string(self.manifest_path(id)?)?;
+        serde_json::from_str(&raw).map_err(|e| FsError::Io { message: format!("Checkpoint '{}' is unreadable: {}", id, e) })
+    }
+
+    /// Entries for the working tree as it is now; with `store`, contents are kept as blobs
+    fn snapshot(&self, root: &Path, store: bool) -> FsResult<BTreeMap<String, ManifestEntry>> {
+        let mut files = BTreeMap::new();
+        for (relative, path) in workspace_files(root) {
+            let Ok(content) = fs::read(&path) else { continue };
+            let hash = hash_bytes(&content);
+            let size = content.len() as u64;
+            let stored = size <= MAX_STORED_FILE_BYTES;
+            if store && stored {
+                self.blobs.write(&hash, &content)?;
+            }
+            let binary = is_binary_content(&content[..content.len().min(8192)]);
+            files.insert(relative, ManifestEntry { hash, size, binary, stored });
+        }
+        Ok(files)
+    }
+
+    pub fn create(&self, root: &Path, label: Option<String>) -> FsResult<Checkpoint> {
+        let files = self.snapshot(root, true)?;
+        let now = chrono::Utc::now();
+        let checkpoint = Checkpoint {
+            id: format!("{}-{}", now.timestamp_millis(), &hash_bytes(display_path(root).as_bytes())[..8]),
+            project_path: display_path(root),
+            label,
+            created_at: now.timestamp(),
+            file_count: files.len(),
+            total_bytes: files.values().map(|f| f.size).sum(),
+        };
+        fs::create_dir_all(self.dir.join(MANIFEST_DIR))?;
+        let raw = serde_json::to_string(&Manifest { checkpoint: checkpoint.clone(), files })
+            .map_err(|e| FsError::Io { message: e.to_string() })?;
+        crate::fs::write_atomic(&self.manifest_path(&checkpoint.id)?, raw.as_bytes())?;
+        Ok(checkpoint)
+    }
+
+    /// Every readable manifest with the file it was read from
+    fn manifests(&self) -> impl Iterator<Item = (PathBuf, Manifest)> {
+        fs::read_dir(self.dir.join(MANIFEST_DIR))
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Some((e.path(), serde_json::from_str::<Manifest>(&fs::read_to_string(e.path()).ok()?).ok()?)))
+    }
+
+    /// Checkpoints of the workspace at `root`, newest first
+    pub fn list(&self, root: &Path) -> Vec<Checkpoint> {
+        let project = display_path(root);
+        let mut checkpoints: Vec<Checkpoint> = self
+            .manifests()
+            .map(|(_, m)| m.checkpoint)
+            .filter(|c| c.project_path == project)
+            .collect();
+        checkpoints.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+        checkpoints
+    }
+
+    fn project_manifest(&self, root: &Path, id: &str) -> FsResult<Manifest> {
+        let manifest = self.manifest(id)?;
+        if manifest.checkpoint.project_path != display_path(root) {
+            return Err(FsError::OutsideWorkspace);
+        }
+        Ok(manifest)
+    }
+
+    /// Delete checkpoints older than `max_age_days`. Their blobs are left to the shared sweep.
+    pub fn prune(&self, max_age_days: u64) -> FsResult<usize> {
+        let cutoff = chrono::Utc::now().timestamp() - (max_age_days * 24 * 60 * 60) as i64;
+        let mut removed = 0;
+        for (path, manifest) in self.manifests() {
+            if manifest.checkpoint.created_at < cutoff {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Hashes of every stored file a remaining checkpoint refers to
+    pub fn referenced_blobs(&self) -> HashSet<String> {
+        self.manifests().flat_map(|(_, m)| m.files.into_values().filter(|f| f.stored).map(|f| f.hash)).collect()
+    }
+
+    /// Files added, removed and modified going from checkpoint `from` to checkpoint `to`, or
+    /// to the current working tree when `to` is None. Compares hashes only; no contents are read
+    /// from the store.
+    pub fn diff(&self, root: &Path, from: &str, to: Option<&str>) -> FsResult<CheckpointDiff> {
+        let old = self.project_manifest(root, from)?.files;
+        let new = match to {
+            Some(to) => self.project_manifest(root, to)?.files,
+            None => self.snapshot(root, false)?,
+        };
+        let mut changes = Vec::new();
+        let mut unchanged = 0;
+        for (path, before) in &old {
+            match new.get(path) {
+                Some(after) if after.hash == before.hash => unchanged += 1,
+                after => changes.push(CheckpointFileChange {
+                    path: path.clone(),
+                    status: if after.is_some() { "modified" } else { "removed" }.to_string(),
+                    old_size: Some(before.size),
+                    new_size: after.map(|a| a.size),
+                    old_hash: Some(before.hash.clone()),
+                    new_hash: after.map(|a| a.hash.clone()),
+                    binary: before.binary || after.map(|a| a.binary).unwrap_or(false),
+                }),
+            }
+        }
+        for (path, after) in new.iter().filter(|(path, _)| !old.contains_key(*path)) {
+            changes.push(CheckpointFileChange {
+                path: path.clone(),
+                status: "added".to_string(),
+                old_size: None,
+                new_size: Some(after.size),
+                old_hash: None,
+                new_hash: Some(after.hash.clone()),
+                binary: after.binary,
+            });
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(CheckpointDiff { from: from.to_string(), to: to.map(str::to_string), changes, unchanged })
+    }
+
+    /// Line diff of `file_path` (workspace-relative) from its stored version in `checkpoint_id`
+    /// to the current file. Binary or unstored sides come back as a skipped result.
+    pub fn diff_file(&self, root: &Path, checkpoint_id: &str, file_path: &str, context_lines: usize) -> FsResult<TextDiffResult> {
+        let relative = file_path.replace('\\', "/");
+        if Path::new(&relative).is_absolute() || relative.split('/').any(|part| part == "..") {
+            return Err(FsError::OutsideWorkspace);
+        }
+        let manifest = self.project_manifest(root, checkpoint_id)?;
+        let old = match manifest.files.get(&relative) {
+            Some(entry) if !entry.stored => return Ok(TextDiffResult::skipped(entry.binary, true)),
+            Some(entry) => self.blobs.read(&entry.hash)?,
+            None => Vec::new(),
+        };
+        let current = root.join(&relative);
+        let new = match fs::metadata(&current) {
+            Ok(metadata) if metadata.len() > MAX_STORED_FILE_BYTES => return Ok(TextDiffResult::skipped(false, true)),
+            Ok(_) => fs::read(&current)?,
+            Err(_) => Vec::new(),
+        };
+        if is_binary_content(&old) || is_binary_content(&new) {
+            let mut result = TextDiffResult::skipped(true, false);
+            result.identical = old == new;
+            return Ok(result);
+        }
+        Ok(diff::diff_texts(
+            &String::from_utf8_lossy(&old),
+            &String::from_utf8_lossy(&new),
+            &format!("{} ({})", relative, checkpoint_id),
+            &relative,
+            context_lines,
+        ))
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+// Size of one chunk of serialized JSON handed to the webview
+const CHUNK_BYTES: usize = 256 * 1024;
+
+// Pending responses the frontend never collected are dropped after this long
+const RESPONSE_TTL: Duration = Duration::from_secs(120);
+
+// Upper bound on memory held by pending responses; the oldest are evicted first
+const MAX_PENDING_BYTES: usize = 256 * 1024 * 1024;
+
+/// Returned instead of the payload when it is larger than the configured threshold
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkHandle {
+    pub handle: String,
+    pub chunk_count: usize,
+    pub total_bytes: usize,
+}
+
+/// A command result: the value itself, or a handle to pull its JSON in chunks.
+/// The frontend tells them apart by the `chunked` key.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum MaybeChunked<T> {
+    Inline(T),
+    Chunked { chunked: ChunkHandle },
+}
+
+/// One chunk delivered by `stream_response`, emitted as "response-chunk" in index order
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResponseChunkEvent {
+    pub handle: String,
+    pub index: usize,
+    pub chunk_count: usize,
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ResponseStoreStats {
+    pub pending: usize,
+    pub pending_bytes: usize,
+    pub evicted: u64,
+}
+
+struct PendingResponse {
+    chunks: Vec<String>,
+    bytes: usize,
+    created: Instant,
+    /// Chunks read so far; the entry is released once every chunk was read
+    read: Vec<bool>,
+}
+
+#[derive(Default)]
+struct Pending {
+    responses: HashMap<String, PendingResponse>,
+    bytes: usize,
+}
+
+impl Pending {
+    fn remove(&mut self, handle: &str) -> Option<PendingResponse> {
+        let response = self.responses.remove(handle)?;
+        self.bytes -= response.bytes;
+        Some(response)
+    }
+
+    fn expire(&mut self, now: Instant) -> usize {
+        let expired: Vec<String> = self
+            .responses
+            .iter()
+            .filter(|(_, r)| now.duration_since(r.created) >= RESPONSE_TTL)
+            .map(|(handle, _)| handle.clone())
+            .collect();
+        for handle in &expired {
+            self.remove(handle);
+        }
+        expired.len()
+    }
+
+    fn oldest(&self) -> Option<String> {
+        self.responses.iter().min_by_key(|(_, r)| r.created).map(|(handle, _)| handle.clone())
+    }
+}
+
+/// Serialized payloads waiting to be pulled by the webview
+#[derive(Default)]
+pub struct ResponseStore {
+    pending: Mutex<Pending>,
+    next_id: AtomicU64,
+    evicted: AtomicU64,
+}
+
+/// Split on char boundaries so every chunk is valid UTF-8 on its own
+fn split_chunks(json: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < json.len() {
+        let mut end = (start + CHUNK_BYTES).min(json.len());
+        while !json.is_char_boundary(end) {
+            end -= 1;
+        }
+        chunks.push(json[start..end].to_string());
+        start = end;
+    }
+    chunks
+}
+
+impl ResponseStore {
+    /// Return `value` inline when its JSON is at most `threshold` bytes (0 disables chunking),
+    /// otherwise keep the JSON and return a handle to it
+    pub fn respond<T: Serialize>(&self, value: T, threshold: usize) -> Result<MaybeChunked<T>, String> {
+        if threshold == 0 {
+            return Ok(MaybeChunked::Inline(value));
+        }
+        let json = serde_json::to_string(&value).map_err(|e| format!("Failed to serialize response: {}", e))?;
+        if json.len() <= threshold {
+            return Ok(MaybeChunked::Inline(value));
+        }
+        Ok(MaybeChunked::Chunked { chunked: self.store(json) })
+    }
+
+    fn store(&self, json: String) -> ChunkHandle {
+        let handle = format!("response-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let total_bytes = json.len();
+        let chunks = split_chunks(&json);
+        let chunk_count = chunks.len();
+
+        let mut pending = self.pending.lock().unwrap();
+        let expired = pending.expire(Instant::now());
+        self.evicted.fetch_add(expired as u64, Ordering::Relaxed);
+        while pending.bytes + total_bytes > MAX_PENDING_BYTES {
+            let Some(oldest) = pending.oldest() else { break };
+            log::warn!("Evicting unread response {} to stay within the pending response budget", oldest);
+            pending.remove(&oldest);
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+        pending.bytes += total_bytes;
+        pending.responses.insert(
+            handle.clone(),
+            PendingResponse { read: vec![false; chunk_count], chunks, bytes: total_bytes, created: Instant::now() },
+        );
+        ChunkHandle { handle, chunk_count, total_bytes }
+    }
+
+    pub fn read_chunk(&self, handle: &str, index: usize) -> Result<String, String> {
+        let mut pending = self.pending.lock().unwrap();
+        let expired = pending.expire(Instant::now());
+        self.evicted.fetch_add(expired as u64, Ordering::Relaxed);
+        let response = pending
+            .responses
+            .get_mut(handle)
+            .ok_or_else(|| format!("Response {} expired or does not exist", handle))?;
+        let chunk = response
+            .chunks
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("Response {} has no chunk {}", handle, index))?;
+        response.read[index] = true;
+        if response.read.iter().all(|r| *r) {
+            pending.remove(handle);
+        }
+        Ok(chunk)
+    }
+
+    /// Take every chunk of `handle` for delivery as events
+    pub fn take(&self, handle: &str) -> Result<Vec<String>, String> {
+        let mut pending = self.pending.lock().unwrap();
+        pending
+            .remove(handle)
+            .map(|response| response.chunks)
+            .ok_or_else(|| format!("Response {} expired or does not exist", handle))
+    }
+
+    pub fn release(&self, handle: &str) {
+        self.pending.lock().unwrap().remove(handle);
+    }
+
+    pub fn stats(&self) -> ResponseStoreStats {
+        let pending = self.pending.lock().unwrap();
+        ResponseStoreStats {
+            pending: pending.responses.len(),
+            pending_bytes: pending.bytes,
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+
+use crate::fs::{self as file_ops, CopyProgress, FsError, FsResult};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClipboardStatus {
+    pub paths: Vec<String>,
+    pub cut: bool,
+    /// "app" when the set was copied inside the IDE, "os" when it comes from the file manager
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PasteResult {
+    pub pasted: Vec<String>,
+    pub moved: bool,
+}
+
+struct ClipboardEntry {
+    paths: Vec<PathBuf>,
+    cut: bool,
+}
+
+/// App-wide file clipboard shared by every project window
+#[derive(Default)]
+pub struct ClipboardState(Mutex<Option<ClipboardEntry>>);
+
+impl ClipboardState {
+    /// Record a copy/cut set and mirror it to the OS clipboard (best effort)
+    pub fn copy_paths(&self, paths: Vec<PathBuf>, cut: bool) {
+        if let Err(e) = write_os_file_list(&paths) {
+            log::warn!("Could not write file list to OS clipboard: {}", e);
+        }
+        *self.0.lock().unwrap() = Some(ClipboardEntry { paths, cut });
+    }
+
+    pub fn status(&self) -> ClipboardStatus {
+        let (paths, cut, source) = self.resolve();
+        ClipboardStatus {
+            paths: paths.iter().map(|p| file_ops::display_path(p)).collect(),
+            cut,
+            source: source.to_string(),
+        }
+    }
+
+    /// Copy or move the current set into `target_dir`, clearing a cut set on success
+    pub fn paste<F>(&self, target_dir: &Path, mut on_progress: F) -> FsResult<PasteResult>
+    where
+        F: FnMut(CopyProgress),
+    {
+        let (paths, cut, source) = self.resolve();
+        if paths.is_empty() {
+            return Err(FsError::NotFound);
+        }
+
+        let mut pasted = Vec::new();
+        for source_path in &paths {
+            let name = source_path.file_name().ok_or(FsError::NotFound)?;
+
+            // Cutting into the same directory is a no-op
+            if cut && source_path.parent() == Some(target_dir) {
+                pasted.push(file_ops::display_path(source_path));
+                continue;
+            }
+
+            let destination = file_ops::unique_destination(target_dir, name);
+            if cut {
+                file_ops::move_path_with_fallback(source_path, &destination, &mut on_progress)?;
+            } else {
+                file_ops::copy_path(source_path, &destination, &mut on_progress)?;
+            }
+            pasted.push(file_ops::display_path(&destination));
+        }
+
+        if cut && source == "app" {
+            *self.0.lock().unwrap() = None;
+        }
+
+        Ok(PasteResult { pasted, moved: cut })
+    }
+
+    /// Prefer the OS clipboard when it holds a different file list than the one we wrote
+    fn resolve(&self) -> (Vec<PathBuf>, bool, &'static str) {
+        let os_paths = read_os_file_list();
+        let guard = self.0.lock().unwrap();
+
+        match guard.as_ref() {
+            Some(entry) if os_paths.is_empty() || os_paths == entry.paths => {
+                (entry.paths.clone(), entry.cut, "app")
+            }
+            _ if !os_paths.is_empty() => (os_paths, false, "os"),
+            _ => (Vec::new(), false, "empty"),
+        }
+    }
+}
+
+fn percent_encode(path: &Path) -> String {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let mut out = String::new();
+    for b in raw.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(v) = u8::from_str_radix(hex, 16) {
+                out.push(v);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn run_with_stdin(program: &str, args: &[&str], input: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+fn run_for_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Paths reach the script as argv, never as source text, so no file name can end a literal early
+const OSASCRIPT_SET_FILES: &str = "on run argv
+set files to {}
+repeat with i from 1 to count of argv
+set end of files to POSIX file (item i of argv)
+end repeat
+set the clipboard to files
+end run";
+
+fn osascript_file_list_args(paths: &[PathBuf]) -> Vec<String> {
+    let mut args = vec!["-e".to_string(), OSASCRIPT_SET_FILES.to_string()];
+    args.extend(paths.iter().map(|p| p.to_string_lossy().to_string()));
+    args
+}
+
+/// Write paths to the OS clipboard in the platform's file-list format
+fn write_os_file_list(paths: &[PathBuf]) -> Result<()> {
+    if cfg!(windows) {
+        let quoted: Vec<String> = paths
+            .iter()
+            .map(|p| format!("'{}'", p.to_string_lossy().replace('\'', "''")))
+            .collect();
+        let script = format!("Set-Clipboard -Path {}", quoted.join(","));
+        run_with_stdin("powershell", &["-NoProfile", "-Command", &script], "")
+    } else if cfg!(target_os = "macos") {
+        let args = osascript_file_list_args(paths);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_with_stdin("osascript", &args, "")
+    } else {
+        let uri_list: String = paths
+            .iter()
+            .map(|p| format!("file://{}\r\n", percent_encode(p)))
+            .collect();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            run_with_stdin("wl-copy", &["--type", "text/uri-list"], &uri_list)
+        } else {
+            run_with_stdin("xclip", &["-selection", "clipboard", "-t", "text/uri-list", "-i"], &uri_list)
+        }
+    }
+}
+
+/// Read a file list from the OS clipboard; empty when unavailable or not a file list
+fn read_os_file_list() -> Vec<PathBuf> {
+    let raw = if cfg!(windows) {
+        run_for_output("powershell", &[
+            "-NoProfile",
+            "-Command",
+            "Get-Clipboard -Format FileDropList | ForEach-Object { $_.FullName }",
+        ])
+    } else if cfg!(target_os = "macos") {
+        run_for_output("osascript", &["-e", "POSIX path of (the clipboard as «class furl»)"])
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        run_for_output("wl-paste", &["--no-newline", "--type", "text/uri-list"])
+    } else {
+        run_for_output("xclip", &["-selection", "clipboard", "-t", "text/uri-list", "-o"])
+    };
+
+    raw.unwrap_or_default()
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| match l.strip_prefix("file://") {
+            Some(uri) => PathBuf::from(percent_decode(uri)),
+            None => PathBuf::from(l),
+        })
+        .filter(|p| p.is_absolute() && p.exists())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_handles_escapes_at_the_end() {
+        assert_eq!(percent_decode("a%20b"), "a b");
+        assert_eq!(percent_decode("trailing%20"), "trailing ");
+        assert_eq!(percent_decode("%41"), "A");
+        assert_eq!(percent_decode("%C3%A9t%C3%A9"), "été");
+    }
+
+    #[test]
+    fn percent_decode_keeps_incomplete_or_invalid_escapes() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("cut%4"), "cut%4");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn osascript_receives_paths_as_arguments() {
+        let tricky = PathBuf::from("/tmp/ends with \\\" & do shell script \"touch /tmp/pwned\" & \"");
+        let args = osascript_file_list_args(&[PathBuf::from("/tmp/plain.txt"), tricky.clone()]);
+
+        assert_eq!(args[..2], ["-e".to_string(), OSASCRIPT_SET_FILES.to_string()]);
+        assert_eq!(args[2], "/tmp/plain.txt");
+        assert_eq!(args[3], tricky.to_string_lossy());
+        assert!(!args[1].contains("pwned"));
+    }
+
+    #[test]
+    fn percent_encoding_round_trips() {
+        for path in ["/tmp/plain.txt", "/tmp/with space/été.md", "/tmp/100%/a#b?c"] {
+            assert_eq!(percent_decode(&percent_encode(Path::new(path))), path);
+        }
+    }
+}
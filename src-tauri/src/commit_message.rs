@@ -0,0 +1,240 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Trailers git itself writes; one of them lets a block with some prose still count as trailers
+const GIT_GENERATED_PREFIXES: [&str; 2] = ["Signed-off-by: ", "(cherry picked from commit "];
+
+pub const DEFAULT_ISSUE_TRAILER_PATTERNS: [&str; 1] =
+    [r"(?i)^(?P<key>close[sd]?|fix(?:e[sd])?|resolve[sd]?|refs?)\s+(?P<value>#\d+|[A-Z][A-Z0-9]+-\d+)$"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// A commit message split the way `git log --format=%s/%b` and `git interpret-trailers` see it
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CommitMessage {
+    /// First line
+    pub summary: String,
+    /// Everything between the summary and the trailer block
+    pub body: String,
+    pub trailers: Vec<Trailer>,
+}
+
+/// Compile the configured issue-reference patterns; each needs `key` and `value` groups.
+/// Invalid patterns are skipped so one typo does not break history.
+pub fn compile_issue_patterns(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(regex) if regex.capture_names().flatten().any(|n| n == "key") && regex.capture_names().flatten().any(|n| n == "value") => Some(regex),
+            Ok(_) => {
+                log::warn!("Issue trailer pattern '{}' needs named groups 'key' and 'value'", pattern);
+                None
+            }
+            Err(e) => {
+                log::warn!("Invalid issue trailer pattern '{}': {}", pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// `Key: value` with a token key (letters, digits and dashes), as git accepts by default
+fn key_value(line: &str) -> Option<Trailer> {
+    let (key, value) = line.split_once(':')?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some(Trailer { key: key.to_string(), value: value.trim().to_string() })
+}
+
+fn parse_trailer(line: &str, issue_patterns: &[Regex]) -> Option<Trailer> {
+    key_value(line).or_else(|| {
+        issue_patterns.iter().find_map(|pattern| {
+            let captures = pattern.captures(line.trim())?;
+            Some(Trailer { key: captures.name("key")?.as_str().to_string(), value: captures.name("value")?.as_str().to_string() })
+        })
+    })
+}
+
+/// Parse the last paragraph as a trailer block, following interpret-trailers: every line is a
+/// trailer or an indented continuation, or at least a quarter are trailers and one of them is
+/// git-generated
+fn trailer_block(lines: &[&str], issue_patterns: &[Regex]) -> Option<Vec<Trailer>> {
+    let mut trailers: Vec<Trailer> = Vec::new();
+    let mut trailer_lines = 0;
+    let mut other_lines = 0;
+    let mut git_generated = false;
+    for line in lines {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            match trailers.last_mut() {
+                Some(last) => {
+                    last.value.push(' ');
+                    last.value.push_str(line.trim());
+                }
+                None => other_lines += 1,
+            }
+            continue;
+        }
+        if GIT_GENERATED_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+            git_generated = true;
+        }
+        match parse_trailer(line, issue_patterns) {
+            Some(trailer) => {
+                trailer_lines += 1;
+                trailers.push(trailer);
+            }
+            None => other_lines += 1,
+        }
+    }
+    let is_block = trailer_lines > 0 && (other_lines == 0 || (git_generated && trailer_lines * 3 >= other_lines));
+    is_block.then_some(trailers)
+}
+
+pub fn parse(message: &str, issue_patterns: &[Regex]) -> CommitMessage {
+    let message = message.replace("\r\n", "\n");
+    let mut lines = message.lines();
+    let summary = lines.next().unwrap_or("").trim().to_string();
+    let rest: Vec<&str> = lines.collect();
+
+    // Paragraphs of the rest, as line ranges
+    let mut paragraphs: Vec<(usize, usize)> = Vec::new();
+    let mut start = None;
+    for (index, line) in rest.iter().enumerate() {
+        match (line.trim().is_empty(), start) {
+            (false, None) => start = Some(index),
+            (true, Some(s)) => {
+                paragraphs.push((s, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        paragraphs.push((s, rest.len()));
+    }
+
+    // The summary's own paragraph never holds trailers: a trailer block must follow a blank line
+    let after_blank = rest.first().map(|line| line.trim().is_empty()).unwrap_or(false);
+    let trailers = paragraphs
+        .last()
+        .filter(|(s, _)| *s > 0 || after_blank)
+        .and_then(|&(s, e)| trailer_block(&rest[s..e], issue_patterns).map(|trailers| (s, trailers)));
+
+    let body_end = trailers.as_ref().map(|(s, _)| *s).unwrap_or(rest.len());
+    let body = rest[..body_end].join("\n").trim().to_string();
+    CommitMessage { summary, body, trailers: trailers.map(|(_, t)| t).unwrap_or_default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_patterns() -> Vec<Regex> {
+        compile_issue_patterns(&DEFAULT_ISSUE_TRAILER_PATTERNS.map(String::from))
+    }
+
+    fn trailer(key: &str, value: &str) -> Trailer {
+        Trailer { key: key.to_string(), value: value.to_string() }
+    }
+
+    #[test]
+    fn splits_summary_body_and_trailers() {
+        let parsed = parse(
+            "Add login\n\nFirst paragraph.\n\nSecond paragraph.\n\nCo-authored-by: Ada <ada@example.com>\nSigned-off-by: Bob <bob@example.com>\n",
+            &[],
+        );
+        assert_eq!(parsed.summary, "Add login");
+        assert_eq!(parsed.body, "First paragraph.\n\nSecond paragraph.");
+        assert_eq!(
+            parsed.trailers,
+            vec![trailer("Co-authored-by", "Ada <ada@example.com>"), trailer("Signed-off-by", "Bob <bob@example.com>")]
+        );
+    }
+
+    #[test]
+    fn colons_in_body_paragraphs_stay_in_the_body() {
+        let parsed = parse(
+            "Fix parser\n\nNote: this changes how input is read.\nReason: the old reader dropped bytes.\n\nReviewed-by: Eve <eve@example.com>",
+            &[],
+        );
+        assert_eq!(parsed.body, "Note: this changes how input is read.\nReason: the old reader dropped bytes.");
+        assert_eq!(parsed.trailers, vec![trailer("Reviewed-by", "Eve <eve@example.com>")]);
+    }
+
+    #[test]
+    fn last_paragraph_of_prose_with_colons_is_not_a_trailer_block() {
+        let message = "Fix parser\n\nSee the docs: they explain the format.\nIt also says: keys are tokens.";
+        let parsed = parse(message, &[]);
+        assert_eq!(parsed.body, "See the docs: they explain the format.\nIt also says: keys are tokens.");
+        assert!(parsed.trailers.is_empty());
+    }
+
+    #[test]
+    fn mixed_paragraph_without_git_trailer_is_body() {
+        let parsed = parse("Fix parser\n\nThanks to everyone.\nReviewed-by: Eve <eve@example.com>", &[]);
+        assert_eq!(parsed.body, "Thanks to everyone.\nReviewed-by: Eve <eve@example.com>");
+        assert!(parsed.trailers.is_empty());
+    }
+
+    #[test]
+    fn git_generated_trailer_lets_block_hold_some_prose() {
+        let parsed = parse(
+            "Backport fix\n\nBody.\n\n(cherry picked from commit 0123abcd)\nSigned-off-by: Bob <bob@example.com>",
+            &[],
+        );
+        assert_eq!(parsed.body, "Body.");
+        assert_eq!(parsed.trailers, vec![trailer("Signed-off-by", "Bob <bob@example.com>")]);
+    }
+
+    #[test]
+    fn summary_paragraph_never_holds_trailers() {
+        let parsed = parse("Fix parser\nSigned-off-by: Bob <bob@example.com>", &[]);
+        assert_eq!(parsed.summary, "Fix parser");
+        assert_eq!(parsed.body, "Signed-off-by: Bob <bob@example.com>");
+        assert!(parsed.trailers.is_empty());
+
+        let summary_only = parse("Key: value", &[]);
+        assert_eq!(summary_only.summary, "Key: value");
+        assert!(summary_only.trailers.is_empty());
+    }
+
+    #[test]
+    fn indented_lines_continue_the_previous_trailer() {
+        let parsed = parse("Fix parser\n\nCo-authored-by: Ada Lovelace\n  <ada@example.com>\nHelped-by: Bob", &[]);
+        assert_eq!(parsed.trailers, vec![trailer("Co-authored-by", "Ada Lovelace <ada@example.com>"), trailer("Helped-by", "Bob")]);
+        assert_eq!(parsed.body, "");
+    }
+
+    #[test]
+    fn issue_references_match_configured_patterns() {
+        let parsed = parse("Fix crash\n\nBody.\n\nFixes #12\nrefs PROJ-7\nSigned-off-by: Bob <bob@example.com>", &default_patterns());
+        assert_eq!(
+            parsed.trailers,
+            vec![trailer("Fixes", "#12"), trailer("refs", "PROJ-7"), trailer("Signed-off-by", "Bob <bob@example.com>")]
+        );
+
+        let without_patterns = parse("Fix crash\n\nBody.\n\nFixes #12", &[]);
+        assert_eq!(without_patterns.body, "Body.\n\nFixes #12");
+        assert!(without_patterns.trailers.is_empty());
+    }
+
+    #[test]
+    fn comment_lines_and_crlf_are_ignored() {
+        let parsed = parse("Fix parser\r\n\r\nBody.\r\n\r\n# a comment\r\nSigned-off-by: Bob <bob@example.com>\r\n", &[]);
+        assert_eq!(parsed.body, "Body.");
+        assert_eq!(parsed.trailers, vec![trailer("Signed-off-by", "Bob <bob@example.com>")]);
+    }
+
+    #[test]
+    fn patterns_without_key_and_value_groups_are_skipped() {
+        let patterns = compile_issue_patterns(&[r"^fixes #\d+$".to_string(), "(".to_string(), DEFAULT_ISSUE_TRAILER_PATTERNS[0].to_string()]);
+        assert_eq!(patterns.len(), 1);
+    }
+}
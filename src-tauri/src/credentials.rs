@@ -0,0 +1,570 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use keyring::Entry;
+use sha2::Sha256;
+
+pub(crate) const VAULT_FILE: &str = "credentials.vault";
+pub(crate) const INDEX_FILE: &str = "credentials-index.json";
+pub(crate) const PROFILES_FILE: &str = "credential-profiles.json";
+const PBKDF2_ROUNDS: u32 = 210_000;
+
+#[derive(Debug, Clone)]
+pub enum CredentialError {
+    /// No OS keyring (e.g. no Secret Service) and the file store is not unlocked
+    CredentialStoreUnavailable { message: String },
+    FileStoreLocked,
+    WrongPassphrase,
+    Other { message: String },
+}
+
+impl CredentialError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CredentialError::CredentialStoreUnavailable { .. } => "CredentialStoreUnavailable",
+            CredentialError::FileStoreLocked => "FileStoreLocked",
+            CredentialError::WrongPassphrase => "WrongPassphrase",
+            CredentialError::Other { .. } => "Other",
+        }
+    }
+}
+
+impl std::fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialError::CredentialStoreUnavailable { message } => write!(
+                f,
+                "No credential store is available ({}). Enable the encrypted file store in settings to save credentials.",
+                message
+            ),
+            CredentialError::FileStoreLocked => write!(f, "The encrypted credential file is locked. Unlock it with your passphrase."),
+            CredentialError::WrongPassphrase => write!(f, "Incorrect passphrase for the encrypted credential file"),
+            CredentialError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+impl Serialize for CredentialError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("CredentialError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+pub type CredentialResult<T> = std::result::Result<T, CredentialError>;
+
+fn other<E: std::fmt::Display>(e: E) -> CredentialError {
+    CredentialError::Other { message: e.to_string() }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialStoreInfo {
+    /// "keyring", "encrypted-file" or "unavailable"
+    pub active_backend: String,
+    pub keyring_available: bool,
+    pub file_fallback_enabled: bool,
+    pub file_store_exists: bool,
+    pub file_store_unlocked: bool,
+    pub stored_entries: usize,
+}
+
+/// On-disk format for the vault and for exported bundles
+#[derive(Serialize, Deserialize)]
+struct SealedVault {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct UnlockedVault {
+    key: [u8; 32],
+    salt: Vec<u8>,
+    entries: BTreeMap<String, String>,
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(raw: &str) -> CredentialResult<Vec<u8>> {
+    if raw.len() % 2 != 0 || !raw.is_ascii() {
+        return Err(other("Corrupt credential file"));
+    }
+    (0..raw.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&raw[i..i + 2], 16).map_err(|_| other("Corrupt credential file")))
+        .collect()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+fn seal(key: &[u8; 32], salt: &[u8], entries: &BTreeMap<String, String>) -> CredentialResult<String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(entries).map_err(other)?;
+    let ciphertext = cipher.encrypt(&nonce, plaintext.as_ref()).map_err(|_| other("Encryption failed"))?;
+
+    serde_json::to_string_pretty(&SealedVault {
+        version: 1,
+        salt: hex_encode(salt),
+        nonce: hex_encode(&nonce),
+        ciphertext: hex_encode(&ciphertext),
+    })
+    .map_err(other)
+}
+
+fn open_sealed(raw: &str, passphrase: &str) -> CredentialResult<UnlockedVault> {
+    let sealed: SealedVault = serde_json::from_str(raw).map_err(other)?;
+    let salt = hex_decode(&sealed.salt)?;
+    let nonce = hex_decode(&sealed.nonce)?;
+    let ciphertext = hex_decode(&sealed.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(other("Corrupt credential file"));
+    }
+
+    let key = derive_key(passphrase, &salt);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| CredentialError::WrongPassphrase)?;
+    let entries = serde_json::from_slice(&plaintext).map_err(other)?;
+    Ok(UnlockedVault { key, salt, entries })
+}
+
+fn entry_key(service: &str, account: &str) -> String {
+    format!("{}\u{0}{}", service, account)
+}
+
+/// A named credential shared by every remote whose host matches `host_pattern`.
+/// Only these fields are persisted in plain text; the secret lives in the store.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialProfile {
+    pub name: String,
+    pub host_pattern: String,
+    pub username: String,
+}
+
+/// What `list_profiles` exposes of a profile; the username stays out of listings
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialProfileSummary {
+    pub name: String,
+    pub host_pattern: String,
+}
+
+fn profile_service(name: &str) -> String {
+    format!("agentic-ide:profile:{}", name)
+}
+
+/// Host and explicit port of a remote URL in https, ssh:// or scp-like (`git@host:path`) form
+#[cfg(feature = "git")]
+pub fn remote_host(url: &str) -> Option<(String, Option<u16>)> {
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        // scp-like syntax has no port; the part after ':' is the path. Like git, a drive letter
+        // or a separator before the ':' makes it a local path (`C:\repo`, `\\?\C:\repo`, `./a:b`).
+        None => {
+            let authority = url.split_once(':')?.0;
+            let drive = authority.len() == 1 && authority.chars().all(|c| c.is_ascii_alphabetic());
+            if drive || authority.contains(['/', '\\']) {
+                return None;
+            }
+            authority
+        }
+    };
+    let host_port = authority.rsplit_once('@').map(|(_, h)| h).unwrap_or(authority);
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) if url.contains("://") => (host, Some(port.parse().ok()?)),
+        _ => (host_port, None),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.trim_matches(['[', ']']).to_lowercase(), port))
+}
+
+/// Match a remote URL against a profile pattern: `github.com` (that host only),
+/// `*.example.com` (any subdomain) or either with `:port` to also require that port
+#[cfg(feature = "git")]
+pub fn host_matches(pattern: &str, url: &str) -> bool {
+    let Some((host, port)) = remote_host(url) else { return false };
+    let pattern = pattern.trim().to_lowercase();
+    let (pattern_host, pattern_port) = match pattern.rsplit_once(':') {
+        Some((h, p)) => match p.parse::<u16>() {
+            Ok(p) => (h.to_string(), Some(p)),
+            Err(_) => return false,
+        },
+        None => (pattern, None),
+    };
+    if pattern_port.is_some() && pattern_port != port {
+        return false;
+    }
+    match pattern_host.strip_prefix("*.") {
+        Some(domain) => host.len() > domain.len() && host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.'),
+        None => host == pattern_host,
+    }
+}
+
+/// Backend absence (as opposed to a missing entry) shows up as a platform/storage failure
+fn keyring_unavailable(e: &keyring::Error) -> bool {
+    matches!(e, keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_))
+}
+
+/// Secret storage backed by the OS keyring, with an opt-in passphrase-encrypted file fallback
+pub struct CredentialStore {
+    dir: PathBuf,
+    file_fallback_enabled: Mutex<bool>,
+    vault: Mutex<Option<UnlockedVault>>,
+    /// Non-secret list of (service, account) pairs we stored, since keyrings cannot be enumerated
+    index: Mutex<BTreeSet<(String, String)>>,
+}
+
+impl CredentialStore {
+    pub fn new(config_dir: &Path, file_fallback_enabled: bool) -> Self {
+        let index = fs::read_to_string(config_dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self {
+            dir: config_dir.to_path_buf(),
+            file_fallback_enabled: Mutex::new(file_fallback_enabled),
+            vault: Mutex::new(None),
+            index: Mutex::new(index),
+        }
+    }
+
+    pub fn set_file_fallback_enabled(&self, enabled: bool) {
+        *self.file_fallback_enabled.lock().unwrap() = enabled;
+        if !enabled {
+            *self.vault.lock().unwrap() = None;
+        }
+    }
+
+    fn keyring_available(&self) -> bool {
+        match Entry::new("agentic-ide:probe", "probe").and_then(|e| e.get_password()) {
+            Ok(_) => true,
+            Err(e) => !keyring_unavailable(&e),
+        }
+    }
+
+    fn use_file_store(&self) -> CredentialResult<bool> {
+        if self.keyring_available() {
+            return Ok(false);
+        }
+        if !*self.file_fallback_enabled.lock().unwrap() {
+            return Err(CredentialError::CredentialStoreUnavailable {
+                message: "the OS keyring backend did not respond".to_string(),
+            });
+        }
+        if self.vault.lock().unwrap().is_none() {
+            return Err(CredentialError::FileStoreLocked);
+        }
+        Ok(true)
+    }
+
+    /// Unlock the encrypted file store, creating it on first use
+    pub fn unlock_file_store(&self, passphrase: &str) -> CredentialResult<()> {
+        let path = self.dir.join(VAULT_FILE);
+        let vault = match fs::read_to_string(&path) {
+            Ok(raw) => open_sealed(&raw, passphrase)?,
+            // Anything but a missing file (permissions, IO) must not be papered over with a new, empty vault
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(other(e)),
+            Err(_) => {
+                let mut salt = vec![0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                let vault = UnlockedVault { key: derive_key(passphrase, &salt), salt, entries: BTreeMap::new() };
+                self.write_vault(&vault)?;
+                vault
+            }
+        };
+        *self.vault.lock().unwrap() = Some(vault);
+        Ok(())
+    }
+
+    fn write_vault(&self, vault: &UnlockedVault) -> CredentialResult<()> {
+        fs::create_dir_all(&self.dir).map_err(other)?;
+        let sealed = seal(&vault.key, &vault.salt, &vault.entries)?;
+        crate::fs::write_atomic(&self.dir.join(VAULT_FILE), sealed.as_bytes()).map_err(other)
+    }
+
+    fn update_index(&self, service: &str, account: &str, present: bool) {
+        let mut index = self.index.lock().unwrap();
+        let key = (service.to_string(), account.to_string());
+        let changed = if present { index.insert(key) } else { index.remove(&key) };
+        if changed {
+            if let Ok(raw) = serde_json::to_string_pretty(&*index) {
+                let _ = fs::create_dir_all(&self.dir);
+                let _ = fs::write(self.dir.join(INDEX_FILE), raw);
+            }
+        }
+    }
+
+    pub fn get(&self, service: &str, account: &str) -> CredentialResult<Option<String>> {
+        if self.use_file_store()? {
+            let vault = self.vault.lock().unwrap();
+            return Ok(vault.as_ref().and_then(|v| v.entries.get(&entry_key(service, account)).cloned()));
+        }
+        match Entry::new(service, account).and_then(|e| e.get_password()) {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) if keyring_unavailable(&e) => Err(CredentialError::CredentialStoreUnavailable { message: e.to_string() }),
+            Err(e) => Err(other(e)),
+        }
+    }
+
+    pub fn set(&self, service: &str, account: &str, secret: &str) -> CredentialResult<()> {
+        if self.use_file_store()? {
+            let mut guard = self.vault.lock().unwrap();
+            let vault = guard.as_mut().ok_or(CredentialError::FileStoreLocked)?;
+            vault.entries.insert(entry_key(service, account), secret.to_string());
+            self.write_vault(vault)?;
+        } else {
+            Entry::new(service, account)
+                .and_then(|e| e.set_password(secret))
+                .map_err(|e| if keyring_unavailable(&e) {
+                    CredentialError::CredentialStoreUnavailable { message: e.to_string() }
+                } else {
+                    other(e)
+                })?;
+        }
+        self.update_index(service, account, true);
+        Ok(())
+    }
+
+    pub fn delete(&self, service: &str, account: &str) -> CredentialResult<()> {
+        if self.use_file_store()? {
+            let mut guard = self.vault.lock().unwrap();
+            let vault = guard.as_mut().ok_or(CredentialError::FileStoreLocked)?;
+            if vault.entries.remove(&entry_key(service, account)).is_some() {
+                self.write_vault(vault)?;
+            }
+        } else {
+            match Entry::new(service, account).and_then(|e| e.delete_password()) {
+                Ok(_) | Err(keyring::Error::NoEntry) => {}
+                Err(e) if keyring_unavailable(&e) => {
+                    return Err(CredentialError::CredentialStoreUnavailable { message: e.to_string() })
+                }
+                Err(e) => return Err(other(e)),
+            }
+        }
+        self.update_index(service, account, false);
+        Ok(())
+    }
+
+    pub fn info(&self) -> CredentialStoreInfo {
+        let keyring_available = self.keyring_available();
+        let file_fallback_enabled = *self.file_fallback_enabled.lock().unwrap();
+        let file_store_unlocked = self.vault.lock().unwrap().is_some();
+        let active_backend = if keyring_available {
+            "keyring"
+        } else if file_fallback_enabled {
+            "encrypted-file"
+        } else {
+            "unavailable"
+        };
+
+        CredentialStoreInfo {
+            active_backend: active_backend.to_string(),
+            keyring_available,
+            file_fallback_enabled,
+            file_store_exists: self.dir.join(VAULT_FILE).exists(),
+            file_store_unlocked,
+            stored_entries: self.index.lock().unwrap().len(),
+        }
+    }
+
+    fn read_profiles(&self) -> Vec<CredentialProfile> {
+        fs::read_to_string(self.dir.join(PROFILES_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_profiles(&self, profiles: &[CredentialProfile]) -> CredentialResult<()> {
+        fs::create_dir_all(&self.dir).map_err(other)?;
+        let raw = serde_json::to_string_pretty(profiles).map_err(other)?;
+        crate::fs::write_atomic(&self.dir.join(PROFILES_FILE), raw.as_bytes()).map_err(other)
+    }
+
+    /// Create or replace a profile; the secret goes to the active backend, never to the profiles file
+    pub fn save_profile(&self, name: &str, host_pattern: &str, username: &str, secret: &str) -> CredentialResult<CredentialProfile> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().any(char::is_control) {
+            return Err(CredentialError::Other { message: "Profile name must be non-empty text".to_string() });
+        }
+        if host_pattern.trim().is_empty() {
+            return Err(CredentialError::Other { message: "Profile host pattern must not be empty".to_string() });
+        }
+
+        let mut profiles = self.read_profiles();
+        // A renamed username would otherwise leave the old secret behind
+        if let Some(previous) = profiles.iter().find(|p| p.name == name) {
+            if previous.username != username {
+                self.delete(&profile_service(name), &previous.username)?;
+            }
+        }
+        self.set(&profile_service(name), username, secret)?;
+
+        let profile = CredentialProfile {
+            name: name.to_string(),
+            host_pattern: host_pattern.trim().to_string(),
+            username: username.to_string(),
+        };
+        profiles.retain(|p| p.name != name);
+        profiles.push(profile.clone());
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        self.write_profiles(&profiles)?;
+        Ok(profile)
+    }
+
+    pub fn list_profiles(&self) -> Vec<CredentialProfileSummary> {
+        self.read_profiles()
+            .into_iter()
+            .map(|p| CredentialProfileSummary { name: p.name, host_pattern: p.host_pattern })
+            .collect()
+    }
+
+    /// Returns false when no profile has that name
+    pub fn delete_profile(&self, name: &str) -> CredentialResult<bool> {
+        let mut profiles = self.read_profiles();
+        let Some(position) = profiles.iter().position(|p| p.name == name) else { return Ok(false) };
+        let profile = profiles.remove(position);
+        self.delete(&profile_service(&profile.name), &profile.username)?;
+        self.write_profiles(&profiles)?;
+        Ok(true)
+    }
+
+    /// (username, secret) from the first profile, by name, whose host pattern matches `remote_url`
+    #[cfg(feature = "git")]
+    pub fn profile_for_url(&self, remote_url: &str) -> CredentialResult<Option<(String, String)>> {
+        for profile in self.read_profiles().iter().filter(|p| host_matches(&p.host_pattern, remote_url)) {
+            if let Some(secret) = self.get(&profile_service(&profile.name), &profile.username)? {
+                return Ok(Some((profile.username.clone(), secret)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write every indexed entry from the active backend into a bundle sealed with `passphrase`
+    pub fn export(&self, output_path: &Path, passphrase: &str) -> CredentialResult<usize> {
+        let keys: Vec<(String, String)> = self.index.lock().unwrap().iter().cloned().collect();
+        let mut entries = BTreeMap::new();
+        for (service, account) in keys {
+            if let Some(secret) = self.get(&service, &account)? {
+                entries.insert(entry_key(&service, &account), secret);
+            }
+        }
+
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let sealed = seal(&derive_key(passphrase, &salt), &salt, &entries)?;
+        crate::fs::write_atomic(output_path, sealed.as_bytes()).map_err(other)?;
+        Ok(entries.len())
+    }
+
+    /// Store every entry from an exported bundle into the active backend
+    pub fn import(&self, input_path: &Path, passphrase: &str) -> CredentialResult<usize> {
+        let raw = fs::read_to_string(input_path).map_err(other)?;
+        let bundle = open_sealed(&raw, passphrase)?;
+        for (key, secret) in &bundle.entries {
+            if let Some((service, account)) = key.split_once('\u{0}') {
+                self.set(service, account, secret)?;
+            }
+        }
+        Ok(bundle.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlock_creates_a_vault_only_when_none_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CredentialStore::new(dir.path(), true);
+        store.unlock_file_store("passphrase").unwrap();
+        assert!(dir.path().join(VAULT_FILE).is_file());
+
+        let reopened = CredentialStore::new(dir.path(), true);
+        assert!(matches!(reopened.unlock_file_store("wrong"), Err(CredentialError::WrongPassphrase)));
+        reopened.unlock_file_store("passphrase").unwrap();
+    }
+
+    #[test]
+    fn unlock_propagates_read_errors_other_than_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory where the vault file should be cannot be read as one
+        std::fs::create_dir(dir.path().join(VAULT_FILE)).unwrap();
+        let store = CredentialStore::new(dir.path(), true);
+        assert!(matches!(store.unlock_file_store("passphrase"), Err(CredentialError::Other { .. })));
+        assert!(dir.path().join(VAULT_FILE).is_dir());
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn remote_host_parses_https_ssh_and_scp_forms() {
+        let cases = [
+            ("https://github.com/org/repo.git", Some(("github.com", None))),
+            ("https://user@GitHub.com:8443/org/repo", Some(("github.com", Some(8443)))),
+            ("ssh://git@github.com/org/repo.git", Some(("github.com", None))),
+            ("ssh://git@github.com:2222/org/repo.git", Some(("github.com", Some(2222)))),
+            ("git@github.com:org/repo.git", Some(("github.com", None))),
+            ("github.com:org/repo.git", Some(("github.com", None))),
+            ("https://[::1]:8080/repo", Some(("::1", Some(8080)))),
+            ("https://github.com:notaport/repo", None),
+            ("https:///repo", None),
+            ("repo", None),
+            (r"C:\repo", None),
+            ("c:/repo", None),
+            (r"\\server\share\repo", None),
+            (r"\\?\C:\repo", None),
+            ("./local:repo", None),
+        ];
+        for (url, expected) in cases {
+            let expected = expected.map(|(host, port): (&str, Option<u16>)| (host.to_string(), port));
+            assert_eq!(remote_host(url), expected, "{}", url);
+        }
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn host_matches_exact_hosts_subdomains_and_ports() {
+        let cases = [
+            ("github.com", "https://github.com/org/repo", true),
+            ("github.com", "git@github.com:org/repo.git", true),
+            ("github.com", "ssh://git@github.com:2222/org/repo", true),
+            (" GitHub.com ", "https://GITHUB.COM/org/repo", true),
+            ("github.com", "https://gist.github.com/org/repo", false),
+            ("github.com", "https://github.com.evil.example/org/repo", false),
+            ("*.example.com", "https://git.example.com/repo", true),
+            ("*.example.com", "git@a.b.example.com:repo", true),
+            ("*.example.com", "https://example.com/repo", false),
+            ("*.example.com", "https://badexample.com/repo", false),
+            ("github.com:8443", "https://github.com:8443/org/repo", true),
+            ("github.com:8443", "https://github.com/org/repo", false),
+            ("github.com:8443", "git@github.com:org/repo", false),
+            ("*.example.com:2222", "ssh://git@git.example.com:2222/repo", true),
+            ("*.example.com:2222", "ssh://git@git.example.com/repo", false),
+            ("github.com:abc", "https://github.com/org/repo", false),
+            ("github.com", "not a remote", false),
+        ];
+        for (pattern, url, expected) in cases {
+            assert_eq!(host_matches(pattern, url), expected, "{} against {}", pattern, url);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use git2::{BranchType, Oid, Repository};
+
+use crate::recent_branches::repo_key;
+
+pub(crate) const STORE_FILE: &str = "deleted-branches.json";
+
+// Entries kept per repository, newest first
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Deletion {
+    name: String,
+    oid: String,
+    upstream: Option<String>,
+    deleted_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeletedBranch {
+    pub name: String,
+    pub oid: String,
+    pub upstream: Option<String>,
+    pub deleted_at: i64,
+    /// The recorded tip is still in the object database
+    pub commit_exists: bool,
+    /// When the tip was pruned: the newest commit the HEAD reflog saw on this branch
+    pub reflog_candidate: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestoredBranch {
+    pub name: String,
+    pub oid: String,
+    /// The recorded tip was gone and the branch was recreated at its reflog candidate
+    pub from_reflog: bool,
+    pub upstream_restored: bool,
+}
+
+/// Newest HEAD reflog entry that left `branch`, which holds the branch's tip at that moment
+fn reflog_candidate(repo: &Repository, branch: &str) -> Option<Oid> {
+    let reflog = repo.reflog("HEAD").ok()?;
+    let prefix = format!("checkout: moving from {} to ", branch);
+    reflog
+        .iter()
+        .filter(|entry| entry.message().map(|m| m.starts_with(&prefix)).unwrap_or(false))
+        .map(|entry| entry.id_old())
+        .find(|oid| repo.find_commit(*oid).is_ok())
+}
+
+/// Branches deleted through the IDE, per repository, so a deletion can be undone for a while
+/// after it happened. Persisted in the app data dir.
+pub struct DeletedBranchStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<Deletion>>>,
+}
+
+impl DeletedBranchStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<Deletion>>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    /// Journal branches just deleted from the repository at `repo_path`, as
+    /// (name, tip oid, upstream) triples
+    pub fn record(&self, repo_path: &Path, deleted: &[(String, String, Option<String>)]) {
+        if deleted.is_empty() {
+            return;
+        }
+        let Ok(repo) = Repository::discover(repo_path) else { return };
+        let now = chrono::Utc::now().timestamp();
+        let mut entries = self.entries.lock().unwrap();
+        let list = entries.entry(repo_key(&repo)).or_default();
+        for (name, oid, upstream) in deleted {
+            list.retain(|d| &d.name != name);
+            list.insert(0, Deletion { name: name.clone(), oid: oid.clone(), upstream: upstream.clone(), deleted_at: now });
+        }
+        list.truncate(MAX_ENTRIES);
+        self.persist(&entries);
+    }
+
+    /// Journaled deletions for the repository, newest first, leaving out expired ones
+    pub fn list(&self, repo_path: &Path, max_age_days: u64) -> Result<Vec<DeletedBranch>> {
+        let repo = Repository::discover(repo_path)?;
+        let cutoff = chrono::Utc::now().timestamp() - (max_age_days * 24 * 60 * 60) as i64;
+        let stored = self.entries.lock().unwrap().get(&repo_key(&repo)).cloned().unwrap_or_default();
+        Ok(stored
+            .into_iter()
+            .filter(|d| d.deleted_at >= cutoff)
+            .map(|d| {
+                let commit_exists = Oid::from_str(&d.oid).map(|oid| repo.find_commit(oid).is_ok()).unwrap_or(false);
+                let reflog_candidate = if commit_exists { None } else { reflog_candidate(&repo, &d.name).map(|oid| oid.to_string()) };
+                DeletedBranch { name: d.name, oid: d.oid, upstream: d.upstream, deleted_at: d.deleted_at, commit_exists, reflog_candidate }
+            })
+            .collect())
+    }
+
+    /// Recreate a journaled branch at its recorded tip. When that commit has been pruned the
+    /// branch is only recreated from the HEAD reflog if `use_reflog` is set.
+    pub fn restore(&self, repo_path: &Path, name: &str, use_reflog: bool) -> Result<RestoredBranch> {
+        let repo = Repository::discover(repo_path)?;
+        let key = repo_key(&repo);
+        let deletion = self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&key)
+            .and_then(|list| list.iter().find(|d| d.name == name).cloned())
+            .ok_or_else(|| anyhow!("No deleted branch named '{}' is on record", name))?;
+        if repo.find_branch(name, BranchType::Local).is_ok() {
+            return Err(anyhow!("A branch named '{}' already exists", name));
+        }
+
+        let recorded = Oid::from_str(&deletion.oid).ok().filter(|oid| repo.find_commit(*oid).is_ok());
+        let (oid, from_reflog) = match recorded {
+            Some(oid) => (oid, false),
+            None => match reflog_candidate(&repo, name) {
+                Some(oid) if use_reflog => (oid, true),
+                Some(oid) => {
+                    return Err(anyhow!(
+                        "Commit {} no longer exists; the reflog last saw '{}' at {}, restore with the reflog to use it",
+                        deletion.oid, name, oid
+                    ))
+                }
+                None => return Err(anyhow!("Commit {} no longer exists and the reflog has no trace of '{}'", deletion.oid, name)),
+            },
+        };
+
+        let commit = repo.find_commit(oid)?;
+        let mut branch = repo.branch(name, &commit, false)?;
+        let upstream_restored = deletion.upstream.as_deref().map(|upstream| branch.set_upstream(Some(upstream)).is_ok()).unwrap_or(false);
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(list) = entries.get_mut(&key) {
+            list.retain(|d| d.name != name);
+        }
+        self.persist(&entries);
+        Ok(RestoredBranch { name: name.to_string(), oid: oid.to_string(), from_reflog, upstream_restored })
+    }
+
+    /// Drop journal entries older than `max_age_days`; returns how many went
+    pub fn prune(&self, max_age_days: u64) -> usize {
+        let cutoff = chrono::Utc::now().timestamp() - (max_age_days * 24 * 60 * 60) as i64;
+        let mut entries = self.entries.lock().unwrap();
+        let mut removed = 0;
+        for list in entries.values_mut() {
+            let before = list.len();
+            list.retain(|d| d.deleted_at >= cutoff);
+            removed += before - list.len();
+        }
+        entries.retain(|_, list| !list.is_empty());
+        if removed > 0 {
+            self.persist(&entries);
+        }
+        removed
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+use crate::fs::display_path;
+
+pub(crate) const STORE_FILE: &str = "dialog-directories.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PickKind {
+    File,
+    Folder,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PickedPath {
+    pub path: String,
+    /// The pick lies outside every open workspace; the frontend should confirm before using it
+    pub outside_workspace: bool,
+}
+
+/// Last directory a file dialog was used in, per project, persisted in the app data dir
+pub struct DialogDirectoryStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl DialogDirectoryStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    /// Remembered directory for `project`, falling back to the project itself
+    pub fn start_dir(&self, project: &Path) -> PathBuf {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&display_path(project))
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| project.to_path_buf())
+    }
+
+    pub fn remember(&self, project: &Path, picked: &Path) {
+        let dir = if picked.is_dir() { picked } else { picked.parent().unwrap_or(picked) };
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(display_path(project), display_path(dir));
+        self.persist(&entries);
+    }
+
+    /// Follow a renamed directory in remembered start directories
+    pub fn remap(&self, from: &Path, to: &Path) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut changed = false;
+        for dir in entries.values_mut() {
+            if let Some(moved) = crate::fs::remap_prefix(Path::new(dir.as_str()), from, to) {
+                *dir = display_path(&moved);
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist(&entries);
+        }
+    }
+
+    fn persist(&self, entries: &HashMap<String, String>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+}
+
+/// Canonical form of a picked path; a save target may not exist yet, so its directory is resolved instead
+pub fn resolve(picked: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(picked) {
+        return path;
+    }
+    match (picked.parent(), picked.file_name()) {
+        (Some(dir), Some(name)) => fs::canonicalize(dir).map(|d| d.join(name)).unwrap_or_else(|_| picked.to_path_buf()),
+        _ => picked.to_path_buf(),
+    }
+}
+
+/// Turn ".rs", "*.rs" or "rs" into the bare extensions the platform dialogs expect
+fn normalize_extensions(filters: &[String]) -> Vec<String> {
+    let mut extensions: Vec<String> = filters
+        .iter()
+        .map(|f| f.trim().trim_start_matches('*').trim_start_matches('.').to_string())
+        .filter(|f| !f.is_empty())
+        .collect();
+    extensions.dedup();
+    extensions
+}
+
+fn builder<R: Runtime>(app: &AppHandle<R>, start_dir: &Path, filters: &[String]) -> FileDialogBuilder<R> {
+    let mut dialog = app.dialog().file().set_directory(start_dir);
+    let extensions = normalize_extensions(filters);
+    if !extensions.is_empty() {
+        let name = extensions.iter().map(|e| format!("*.{}", e)).collect::<Vec<_>>().join(", ");
+        let refs: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter(name, &refs);
+    }
+    dialog
+}
+
+/// Show an open dialog; blocks until the user answers, so call it off the main thread
+pub fn pick_open<R: Runtime>(app: &AppHandle<R>, start_dir: &Path, kind: PickKind, filters: &[String]) -> Option<PathBuf> {
+    let dialog = builder(app, start_dir, filters);
+    let picked = match kind {
+        PickKind::File => dialog.blocking_pick_file(),
+        PickKind::Folder => dialog.blocking_pick_folder(),
+    };
+    picked.and_then(|p| p.into_path().ok())
+}
+
+/// Show a save dialog; blocks until the user answers, so call it off the main thread
+pub fn pick_save<R: Runtime>(app: &AppHandle<R>, start_dir: &Path, default_name: Option<&str>, filters: &[String]) -> Option<PathBuf> {
+    let mut dialog = builder(app, start_dir, filters);
+    if let Some(name) = default_name {
+        dialog = dialog.set_file_name(name);
+    }
+    dialog.blocking_save_file().and_then(|p| p.into_path().ok())
+}
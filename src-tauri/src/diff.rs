@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use crate::fs::{self as file_ops, FsResult};
+
+// Files above this size are reported as too large instead of diffed
+const MAX_DIFF_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiffLine {
+    /// "context", "insert" or "delete"
+    pub kind: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TextDiffResult {
+    pub unified: String,
+    pub hunks: Vec<DiffHunk>,
+    pub identical: bool,
+    pub binary: bool,
+    pub too_large: bool,
+}
+
+impl TextDiffResult {
+    pub(crate) fn skipped(binary: bool, too_large: bool) -> Self {
+        Self {
+            unified: String::new(),
+            hunks: Vec::new(),
+            identical: false,
+            binary,
+            too_large,
+        }
+    }
+}
+
+/// Line diff of two texts as unified text plus structured hunks for side-by-side rendering
+pub fn diff_texts(left: &str, right: &str, left_label: &str, right_label: &str, context_lines: usize) -> TextDiffResult {
+    let diff = TextDiff::from_lines(left, right);
+    let unified = diff
+        .unified_diff()
+        .context_radius(context_lines)
+        .header(left_label, right_label)
+        .to_string();
+
+    let mut hunks = Vec::new();
+    for group in diff.grouped_ops(context_lines) {
+        let (first, last) = match (group.first(), group.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => "context",
+                    ChangeTag::Insert => "insert",
+                    ChangeTag::Delete => "delete",
+                };
+                lines.push(DiffLine {
+                    kind: kind.to_string(),
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    content: change.value().trim_end_matches('\n').trim_end_matches('\r').to_string(),
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            old_start: old_range.start + 1,
+            old_lines: old_range.len(),
+            new_start: new_range.start + 1,
+            new_lines: new_range.len(),
+            header: format!(
+                "@@ -{},{} +{},{} @@",
+                old_range.start + 1,
+                old_range.len(),
+                new_range.start + 1,
+                new_range.len()
+            ),
+            lines,
+        });
+    }
+
+    TextDiffResult {
+        identical: hunks.is_empty(),
+        unified,
+        hunks,
+        binary: false,
+        too_large: false,
+    }
+}
+
+/// Diff two files on disk, skipping binary or oversized inputs
+pub fn diff_files(left_path: &Path, right_path: &Path, context_lines: usize) -> FsResult<TextDiffResult> {
+    for path in [left_path, right_path] {
+        if fs::metadata(file_ops::long_path(path))?.len() > MAX_DIFF_BYTES {
+            return Ok(TextDiffResult::skipped(false, true));
+        }
+    }
+
+    let left = fs::read(file_ops::long_path(left_path))?;
+    let right = fs::read(file_ops::long_path(right_path))?;
+    if file_ops::is_binary_content(&left) || file_ops::is_binary_content(&right) {
+        let mut result = TextDiffResult::skipped(true, false);
+        result.identical = left == right;
+        return Ok(result);
+    }
+
+    Ok(diff_texts(
+        &String::from_utf8_lossy(&left),
+        &String::from_utf8_lossy(&right),
+        &file_ops::display_path(left_path),
+        &file_ops::display_path(right_path),
+        context_lines,
+    ))
+}
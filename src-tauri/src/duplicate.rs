@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use ignore::{WalkBuilder, WalkState};
+use walkdir::WalkDir;
+
+use crate::fs::display_path;
+use crate::gitignore::IgnoreMatcher;
+use crate::operations::CancelToken;
+
+// Emit a progress event every this many copied files
+const PROGRESS_EVERY: u64 = 200;
+
+const ALWAYS_SKIPPED: [&str; 1] = ["node_modules"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuplicateOptions {
+    /// Copy `.git` so the duplicate stays a repository; otherwise it is omitted entirely
+    pub include_git: bool,
+    /// Skip `node_modules` and anything git ignores
+    pub respect_gitignore: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuplicateProgress {
+    pub source_root: String,
+    pub target_root: String,
+    pub files_copied: u64,
+    pub bytes_copied: u64,
+    /// "copying", "verifying", "copying git data" or "done"
+    pub stage: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuplicateResult {
+    pub target_root: String,
+    pub files_copied: u64,
+    pub bytes_copied: u64,
+    pub skipped: Vec<String>,
+}
+
+#[derive(Default)]
+struct Counters {
+    files: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Copy a project to a new root for throwaway experiments. The target must not exist;
+/// it is removed again on failure or cancellation.
+pub fn duplicate_workspace<F>(source_root: &Path, target_root: &Path, options: &DuplicateOptions, cancel: CancelToken, on_progress: F) -> Result<DuplicateResult>
+where
+    F: Fn(DuplicateProgress) + Send + Sync,
+{
+    if !source_root.is_dir() {
+        return Err(anyhow!("'{}' is not a directory", source_root.display()));
+    }
+    if target_root.exists() {
+        return Err(anyhow!("Target '{}' already exists", target_root.display()));
+    }
+    if target_root.starts_with(source_root) {
+        return Err(anyhow!("Target cannot be inside the source project"));
+    }
+
+    let result = copy_tree(source_root, target_root, options, &cancel, &on_progress);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(target_root);
+    }
+    result
+}
+
+fn copy_tree<F>(source_root: &Path, target_root: &Path, options: &DuplicateOptions, cancel: &CancelToken, on_progress: &F) -> Result<DuplicateResult>
+where
+    F: Fn(DuplicateProgress) + Send + Sync,
+{
+    fs::create_dir_all(target_root)?;
+    let counters = Counters::default();
+    let skipped = Mutex::new(Vec::new());
+    let failure: Mutex<Option<String>> = Mutex::new(None);
+    let progress = |stage: &str| on_progress(DuplicateProgress {
+        source_root: display_path(source_root),
+        target_root: display_path(target_root),
+        files_copied: counters.files.load(Ordering::Relaxed),
+        bytes_copied: counters.bytes.load(Ordering::Relaxed),
+        stage: stage.to_string(),
+    });
+
+    let matcher = if options.respect_gitignore { IgnoreMatcher::new(source_root).ok() } else { None };
+    let respect_gitignore = options.respect_gitignore;
+    let mut builder = WalkBuilder::new(source_root);
+    builder.standard_filters(false).follow_links(false);
+    builder.filter_entry(move |entry| {
+        let name = entry.file_name().to_string_lossy();
+        if entry.depth() == 1 && name == ".git" {
+            return false;
+        }
+        if !respect_gitignore {
+            return true;
+        }
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if is_dir && ALWAYS_SKIPPED.iter().any(|s| *s == name) {
+            return false;
+        }
+        !matcher.as_ref().map(|m| m.is_ignored(entry.path(), is_dir)).unwrap_or(false)
+    });
+
+    progress("copying");
+    {
+        let (counters, skipped, failure, progress) = (&counters, &skipped, &failure, &progress);
+        builder.build_parallel().run(move || {
+            Box::new(move |entry| {
+                if cancel.is_cancelled() {
+                    return WalkState::Quit;
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        skipped.lock().unwrap().push(e.to_string());
+                        return WalkState::Continue;
+                    }
+                };
+                let relative = entry.path().strip_prefix(source_root).unwrap_or(entry.path());
+                let destination = target_root.join(relative);
+
+                match copy_entry(entry.path(), &destination, entry.file_type()) {
+                    Ok(Some(bytes)) => {
+                        let files = counters.files.fetch_add(1, Ordering::Relaxed) + 1;
+                        counters.bytes.fetch_add(bytes, Ordering::Relaxed);
+                        if files % PROGRESS_EVERY == 0 {
+                            progress("copying");
+                        }
+                        WalkState::Continue
+                    }
+                    Ok(None) => WalkState::Continue,
+                    Err(e) => {
+                        *failure.lock().unwrap() = Some(format!("Failed to copy '{}': {}", display_path(entry.path()), e));
+                        WalkState::Quit
+                    }
+                }
+            })
+        });
+    }
+
+    if cancel.is_cancelled() {
+        return Err(anyhow!("Duplicate cancelled"));
+    }
+    if let Some(message) = failure.into_inner().unwrap() {
+        return Err(anyhow!(message));
+    }
+
+    let expected_files = counters.files.load(Ordering::Relaxed);
+    let expected_bytes = counters.bytes.load(Ordering::Relaxed);
+
+    progress("verifying");
+    let (files, bytes) = tree_totals(target_root);
+    if files != expected_files || bytes != expected_bytes {
+        return Err(anyhow!(
+            "Verification failed: copied {} files ({} bytes) but found {} files ({} bytes) in the target",
+            expected_files, expected_bytes, files, bytes
+        ));
+    }
+
+    if options.include_git && source_root.join(".git").exists() {
+        progress("copying git data");
+        copy_git_dir(&source_root.join(".git"), &target_root.join(".git"), cancel)?;
+    }
+
+    progress("done");
+    Ok(DuplicateResult {
+        target_root: display_path(target_root),
+        files_copied: expected_files,
+        bytes_copied: expected_bytes,
+        skipped: skipped.into_inner().unwrap(),
+    })
+}
+
+/// Copy one walker entry; returns the byte count for files and None for directories
+fn copy_entry(source: &Path, destination: &Path, file_type: Option<fs::FileType>) -> Result<Option<u64>> {
+    let Some(file_type) = file_type else { return Ok(None) };
+    if file_type.is_dir() {
+        fs::create_dir_all(destination)?;
+        return Ok(None);
+    }
+    // The parallel walker may reach a file before its directory entry has been processed
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if file_type.is_symlink() {
+        return Ok(Some(copy_symlink(source, destination)?));
+    }
+    Ok(Some(fs::copy(source, destination)?))
+}
+
+/// Recreate a symlink; returns the bytes written (none for a real link)
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> Result<u64> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)?;
+    Ok(0)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, destination: &Path) -> Result<u64> {
+    // Creating symlinks needs extra privileges on Windows; copy the target instead
+    Ok(fs::copy(source, destination)?)
+}
+
+/// Files (symlinks count as empty files) and bytes under `root`, excluding `.git`
+fn tree_totals(root: &Path) -> (u64, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+    let walker = WalkDir::new(root).into_iter().filter_entry(|e| !(e.depth() == 1 && e.file_name() == ".git"));
+    for entry in walker.filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        files += 1;
+        if entry.file_type().is_file() {
+            bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+    }
+    (files, bytes)
+}
+
+fn copy_git_dir(source: &Path, destination: &Path, cancel: &CancelToken) -> Result<()> {
+    for entry in WalkDir::new(source).into_iter() {
+        if cancel.is_cancelled() {
+            return Err(anyhow!("Duplicate cancelled"));
+        }
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+        let target: PathBuf = destination.join(relative);
+        copy_entry(entry.path(), &target, Some(entry.file_type()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use globset::GlobBuilder;
+
+const FILE_NAME: &str = ".editorconfig";
+
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+struct EditorConfigFile {
+    dir: PathBuf,
+    root: bool,
+    sections: Vec<Section>,
+}
+
+fn parse(dir: &Path, raw: &str) -> EditorConfigFile {
+    let mut file = EditorConfigFile { dir: dir.to_path_buf(), root: false, sections: Vec::new() };
+    for line in raw.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            file.sections.push(Section { glob: glob.to_string(), properties: Vec::new() });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let (key, value) = (key.trim().to_lowercase(), value.trim().to_lowercase());
+        match file.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            // Only `root` is meaningful before the first section
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+    file
+}
+
+/// Globs without a `/` match the file name at any depth; others are relative to the .editorconfig
+fn section_matches(glob: &str, dir: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(dir) else { return false };
+    let pattern = if glob.contains('/') { glob.trim_start_matches('/').to_string() } else { format!("**/{}", glob) };
+    match GlobBuilder::new(&pattern).literal_separator(true).build() {
+        Ok(glob) => glob.compile_matcher().is_match(relative),
+        Err(_) => false,
+    }
+}
+
+/// Properties that apply to `path`, with keys and values lowercased. Files closer to `path`
+/// and later sections win, as in the EditorConfig spec.
+pub fn properties_for(path: &Path) -> HashMap<String, String> {
+    let mut files = Vec::new();
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if let Ok(raw) = fs::read_to_string(current.join(FILE_NAME)) {
+            let file = parse(current, &raw);
+            let root = file.root;
+            files.push(file);
+            if root {
+                break;
+            }
+        }
+        dir = current.parent();
+    }
+
+    let mut properties = HashMap::new();
+    for file in files.iter().rev() {
+        for section in file.sections.iter().filter(|s| section_matches(&s.glob, &file.dir, path)) {
+            for (key, value) in &section.properties {
+                properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    properties
+}
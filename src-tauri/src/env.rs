@@ -0,0 +1,320 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::fs::{self as file_ops, FsError, FsResult};
+
+// .env files nested deeper than this are ignored during discovery
+const MAX_SEARCH_DEPTH: usize = 4;
+
+const SKIPPED_DIRS: [&str; 5] = ["node_modules", "target", ".git", "dist", "build"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvFileInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvEntry {
+    pub key: String,
+    /// The real value when revealed, otherwise a masked preview
+    pub value: String,
+    pub masked: bool,
+    pub line: usize,
+    pub exported: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvComparison {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub common: Vec<String>,
+}
+
+/// A parsed `KEY=value` line
+struct ParsedLine {
+    key: String,
+    value: String,
+    exported: bool,
+    quote: Option<char>,
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+/// The value inside quotes up to the closing `quote`. Single quotes and backticks are literal, as
+/// in dotenv (`'C:\new'` is `C:\new`); inside double quotes `\\` and `\"` are unescaped and any
+/// other backslash is kept as written.
+fn unquote(inner: &str, quote: char) -> String {
+    if quote != '"' {
+        return inner.split(quote).next().unwrap_or_default().to_string();
+    }
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == quote || next == '\\' => value.push(next),
+                Some(next) => {
+                    value.push('\\');
+                    value.push(next);
+                }
+                None => value.push('\\'),
+            },
+            c if c == quote => break,
+            c => value.push(c),
+        }
+    }
+    value
+}
+
+fn parse_line(line: &str) -> Option<ParsedLine> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+
+    let (exported, rest) = match trimmed.strip_prefix("export ") {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, trimmed),
+    };
+    let (key, raw_value) = rest.split_once('=')?;
+    let key = key.trim();
+    if !valid_key(key) {
+        return None;
+    }
+
+    let raw_value = raw_value.trim();
+    let first = raw_value.chars().next();
+    let (value, quote) = match first {
+        Some(q @ ('"' | '\'' | '`')) => (unquote(&raw_value[1..], q), Some(q)),
+        // Unquoted values end at an inline comment
+        _ => {
+            let value = match raw_value.find(" #") {
+                Some(pos) => &raw_value[..pos],
+                None => raw_value,
+            };
+            (value.trim_end().to_string(), None)
+        }
+    };
+
+    Some(ParsedLine { key: key.to_string(), value, exported, quote })
+}
+
+fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        1..=6 => "••••••".to_string(),
+        _ => format!("{}••••••", chars[..2].iter().collect::<String>()),
+    }
+}
+
+/// Quote and escape `value` so that `parse_line` reads it back unchanged
+fn format_value(value: &str, quote: Option<char>) -> String {
+    let needs_quotes = value.is_empty()
+        || value.chars().any(|c| c.is_whitespace() || matches!(c, '#' | '"' | '\'' | '`' | '\\'));
+    let quote = match quote {
+        // Literal quotes cannot hold their own quote character; switch to double quotes
+        Some(q) if q != '"' && value.contains(q) => '"',
+        Some(q) if q != '"' => return format!("{}{}{}", q, value, q),
+        Some(q) => q,
+        None if needs_quotes => '"',
+        None => return value.to_string(),
+    };
+    let escaped = value.replace('\\', "\\\\").replace(quote, &format!("\\{}", quote));
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+/// Find `.env*` files in the project, which the regular listing hides as dotfiles
+pub fn list_env_files(project_path: &Path) -> Vec<EnvFileInfo> {
+    let walker = WalkDir::new(project_path)
+        .max_depth(MAX_SEARCH_DEPTH)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            !(e.file_type().is_dir() && SKIPPED_DIRS.iter().any(|d| *d == name))
+        });
+
+    let mut files: Vec<EnvFileInfo> = walker
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().starts_with(".env"))
+        .map(|e| EnvFileInfo {
+            name: e.file_name().to_string_lossy().to_string(),
+            path: file_ops::display_path(e.path()),
+            size: e.metadata().map(|m| m.len()).unwrap_or(0),
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+pub fn read_env_file(path: &Path, reveal_values: bool) -> FsResult<Vec<EnvEntry>> {
+    let content = file_ops::read_file_content(path)?;
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            parse_line(line).map(|parsed| EnvEntry {
+                value: if reveal_values { parsed.value.clone() } else { mask(&parsed.value) },
+                masked: !reveal_values,
+                key: parsed.key,
+                line: i + 1,
+                exported: parsed.exported,
+            })
+        })
+        .collect())
+}
+
+/// Set a key, keeping its `export` prefix and quoting; appends when the key is new
+pub fn set_env_value(path: &Path, key: &str, value: &str) -> FsResult<()> {
+    if !valid_key(key) {
+        return Err(FsError::InvalidName { message: format!("'{}' is not a valid variable name", key) });
+    }
+    if value.contains(['\n', '\r']) {
+        return Err(FsError::Io { message: format!("The value of {} cannot span several lines", key) });
+    }
+
+    let content = match file_ops::read_file_content(path) {
+        Ok(content) => content,
+        Err(FsError::NotFound) => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| match parse_line(line) {
+            Some(parsed) if parsed.key == key && !found => {
+                found = true;
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let prefix = if parsed.exported { "export " } else { "" };
+                format!("{}{}{}={}", indent, prefix, key, format_value(value, parsed.quote))
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+
+    if !found {
+        lines.push(format!("{}={}", key, format_value(value, None)));
+    }
+    write_lines(path, &lines, &content)
+}
+
+pub fn remove_env_key(path: &Path, key: &str) -> FsResult<bool> {
+    let content = file_ops::read_file_content(path)?;
+    let lines: Vec<String> = content
+        .lines()
+        .filter(|line| parse_line(line).map(|p| p.key != key).unwrap_or(true))
+        .map(String::from)
+        .collect();
+
+    let removed = lines.len() != content.lines().count();
+    if removed {
+        write_lines(path, &lines, &content)?;
+    }
+    Ok(removed)
+}
+
+fn write_lines(path: &Path, lines: &[String], original: &str) -> FsResult<()> {
+    let newline = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut output = lines.join(newline);
+    if !output.is_empty() {
+        output.push_str(newline);
+    }
+    file_ops::write_atomic(path, output.as_bytes())
+}
+
+fn env_keys(path: &Path) -> FsResult<BTreeSet<String>> {
+    let content = fs::read_to_string(file_ops::long_path(path))?;
+    Ok(content.lines().filter_map(parse_line).map(|p| p.key).collect())
+}
+
+/// Keys present in `path_b` but absent from `path_a` are "missing" (e.g. `.env` vs `.env.example`)
+pub fn compare_env_files(path_a: &Path, path_b: &Path) -> FsResult<EnvComparison> {
+    let a = env_keys(path_a)?;
+    let b = env_keys(path_b)?;
+
+    Ok(EnvComparison {
+        missing: b.difference(&a).cloned().collect(),
+        extra: a.difference(&b).cloned().collect(),
+        common: a.intersection(&b).cloned().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: &str, quote: Option<char>) -> String {
+        let line = format!("KEY={}", format_value(value, quote));
+        let parsed = parse_line(&line).unwrap_or_else(|| panic!("unparsable line {}", line));
+        assert_eq!(parsed.key, "KEY");
+        parsed.value
+    }
+
+    #[test]
+    fn formatted_values_parse_back_unchanged() {
+        let values = [
+            "plain", "", "with space", "hash # inside", "say \"hi\"", "it's", "back`tick", r"C:\path\to", r"ends\", r#"\"mixed\" 'q' `b`"#,
+            "  padded  ",
+        ];
+        for value in values {
+            for quote in [None, Some('"'), Some('\''), Some('`')] {
+                assert_eq!(round_trip(value, quote), value, "{:?} quoted with {:?}", value, quote);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_line_unescapes_quoted_values() {
+        assert_eq!(parse_line(r#"A="x \"y\" z" # note"#).unwrap().value, r#"x "y" z"#);
+        assert_eq!(parse_line(r"B='C:\new' # note").unwrap().value, r"C:\new");
+        assert_eq!(parse_line(r"B=`C:\path\`").unwrap().value, r"C:\path\");
+        assert_eq!(parse_line(r#"C="keep \n as written""#).unwrap().value, r"keep \n as written");
+        assert_eq!(parse_line("D=unquoted # comment").unwrap().value, "unquoted");
+    }
+
+    #[test]
+    fn single_quoted_values_keep_backslashes_when_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "WINDOWS='C:\\new'\nLITERAL='a\\\\b'\n").unwrap();
+
+        let value = |key: &str| read_env_file(&path, true).unwrap().into_iter().find(|e| e.key == key).map(|e| e.value);
+        assert_eq!(value("WINDOWS").as_deref(), Some(r"C:\new"));
+        assert_eq!(value("LITERAL").as_deref(), Some(r"a\\b"));
+
+        set_env_value(&path, "WINDOWS", r"D:\next").unwrap();
+        set_env_value(&path, "LITERAL", "it's").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "WINDOWS='D:\\next'\nLITERAL=\"it's\"\n");
+        assert_eq!(value("WINDOWS").as_deref(), Some(r"D:\next"));
+        assert_eq!(value("LITERAL").as_deref(), Some("it's"));
+    }
+
+    #[test]
+    fn set_env_value_round_trips_and_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "export TOKEN='old'\nOTHER=1\n").unwrap();
+
+        set_env_value(&path, "TOKEN", "it's \"new\"").unwrap();
+        set_env_value(&path, "ADDED", r"C:\dir with space").unwrap();
+        let entries = read_env_file(&path, true).unwrap();
+        let value = |key: &str| entries.iter().find(|e| e.key == key).map(|e| e.value.clone());
+        assert_eq!(value("TOKEN").as_deref(), Some("it's \"new\""));
+        assert_eq!(value("ADDED").as_deref(), Some(r"C:\dir with space"));
+        assert_eq!(value("OTHER").as_deref(), Some("1"));
+        assert!(entries.iter().find(|e| e.key == "TOKEN").unwrap().exported);
+
+        for (key, value) in [("", "x"), ("BAD KEY", "x"), ("A=B", "x"), ("KEY", "two\nlines"), ("KEY", "carriage\rreturn")] {
+            assert!(set_env_value(&path, key, value).is_err(), "{:?}={:?}", key, value);
+        }
+        assert_eq!(read_env_file(&path, true).unwrap().len(), 3);
+    }
+}
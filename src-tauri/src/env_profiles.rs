@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(any(feature = "git", feature = "process"))]
+use std::{borrow::Cow, process::Command};
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(feature = "git", feature = "process"))]
+use crate::allowed_commands::CommandOutput;
+use crate::credentials::{CredentialError, CredentialResult, CredentialStore};
+use crate::fs::display_path;
+
+pub(crate) const STORE_FILE: &str = "env-profiles.json";
+
+// Shown in place of a secret value, in resolved profiles and in redacted output
+const MASK: &str = "********";
+
+// Secret values shorter than this are not redacted from output; they would match everywhere
+#[cfg(any(feature = "git", feature = "process"))]
+const MIN_REDACTED_LENGTH: usize = 4;
+
+/// Named variables for one project. Values of `secret_keys` live in the credential store and
+/// are never part of this struct.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvProfile {
+    pub name: String,
+    /// Non-secret variables only
+    pub vars: BTreeMap<String, String>,
+    pub secret_keys: Vec<String>,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResolvedEnvVar {
+    pub key: String,
+    /// Masked for secret keys
+    pub value: String,
+    pub secret: bool,
+    /// A secret key whose value is not in the credential store
+    pub missing: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResolvedEnvProfile {
+    pub name: String,
+    /// Sorted by key
+    pub vars: Vec<ResolvedEnvVar>,
+}
+
+/// A profile ready to apply to a process: every variable with its real value, and the secret
+/// values to scrub from what the process prints
+#[cfg(any(feature = "git", feature = "process"))]
+#[derive(Default)]
+pub struct ProfileEnvironment {
+    pub vars: Vec<(String, String)>,
+    secrets: Vec<String>,
+}
+
+#[cfg(any(feature = "git", feature = "process"))]
+impl ProfileEnvironment {
+    /// Merge the profile over the environment `command` inherits
+    pub fn apply(&self, command: &mut Command) {
+        command.envs(self.vars.iter().map(|(k, v)| (k, v)));
+    }
+
+    /// `text` with every secret value masked
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for secret in self.secrets.iter().filter(|s| s.len() >= MIN_REDACTED_LENGTH) {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), MASK));
+            }
+        }
+        text
+    }
+
+    pub fn redact_output(&self, output: &mut CommandOutput) {
+        output.stdout = self.redact(&output.stdout).into_owned();
+        output.stderr = self.redact(&output.stderr).into_owned();
+    }
+}
+
+fn project_key(project: &Path) -> String {
+    display_path(&fs::canonicalize(project).unwrap_or_else(|_| project.to_path_buf()))
+}
+
+fn secret_service(project_key: &str) -> String {
+    format!("agentic-ide:env:{}", project_key)
+}
+
+fn secret_account(profile: &str, key: &str) -> String {
+    format!("{}/{}", profile, key)
+}
+
+fn invalid(message: String) -> CredentialError {
+    CredentialError::Other { message }
+}
+
+fn valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic()) && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// Environment profiles per project, persisted in the app data dir; secret values go to the
+/// credential store under a per-project service
+pub struct EnvProfileStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<EnvProfile>>>,
+}
+
+impl EnvProfileStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<EnvProfile>>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    pub fn list(&self, project: &Path) -> Vec<EnvProfile> {
+        self.entries.lock().unwrap().get(&project_key(project)).cloned().unwrap_or_default()
+    }
+
+    fn find(&self, project: &Path, name: &str) -> CredentialResult<EnvProfile> {
+        self.list(project)
+            .into_iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| invalid(format!("No environment profile named '{}'", name)))
+    }
+
+    /// Create or replace a profile. Values of `secret_keys` are written to the credential store;
+    /// a secret key left out of `vars` keeps the value it already has there.
+    pub fn save(&self, project: &Path, name: &str, vars: &BTreeMap<String, String>, secret_keys: &[String], credentials: &CredentialStore) -> CredentialResult<EnvProfile> {
+        let name = name.trim();
+        if name.is_empty() || name.contains('/') || name.chars().any(char::is_control) {
+            return Err(invalid("Profile name must be non-empty text without '/'".to_string()));
+        }
+        if let Some(key) = vars.keys().chain(secret_keys).find(|key| !valid_key(key)) {
+            return Err(invalid(format!("'{}' is not a valid environment variable name", key)));
+        }
+
+        let key = project_key(project);
+        let service = secret_service(&key);
+        let previous = self.find(project, name).ok();
+        for secret in secret_keys {
+            match vars.get(secret) {
+                Some(value) => credentials.set(&service, &secret_account(name, secret), value)?,
+                None if previous.as_ref().map(|p| p.secret_keys.contains(secret)).unwrap_or(false) => {}
+                None => return Err(invalid(format!("Secret '{}' needs a value", secret))),
+            }
+        }
+        // Keys no longer secret, or gone, leave the credential store
+        for stale in previous.iter().flat_map(|p| &p.secret_keys).filter(|k| !secret_keys.contains(k)) {
+            credentials.delete(&service, &secret_account(name, stale))?;
+        }
+
+        let mut secret_keys = secret_keys.to_vec();
+        secret_keys.sort();
+        secret_keys.dedup();
+        let profile = EnvProfile {
+            name: name.to_string(),
+            vars: vars.iter().filter(|(k, _)| !secret_keys.contains(k)).map(|(k, v)| (k.clone(), v.clone())).collect(),
+            secret_keys,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let list = entries.entry(key).or_default();
+        list.retain(|p| p.name != name);
+        list.push(profile.clone());
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        self.persist(&entries);
+        Ok(profile)
+    }
+
+    /// Returns false when no profile has that name
+    pub fn delete(&self, project: &Path, name: &str, credentials: &CredentialStore) -> CredentialResult<bool> {
+        let Ok(profile) = self.find(project, name) else { return Ok(false) };
+        let key = project_key(project);
+        let service = secret_service(&key);
+        for secret in &profile.secret_keys {
+            credentials.delete(&service, &secret_account(name, secret))?;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(list) = entries.get_mut(&key) {
+            list.retain(|p| p.name != name);
+            if list.is_empty() {
+                entries.remove(&key);
+            }
+        }
+        self.persist(&entries);
+        Ok(true)
+    }
+
+    /// Every variable of the profile for display, secrets masked
+    pub fn resolve(&self, project: &Path, name: &str, credentials: &CredentialStore) -> CredentialResult<ResolvedEnvProfile> {
+        let profile = self.find(project, name)?;
+        let service = secret_service(&project_key(project));
+        let mut vars: Vec<ResolvedEnvVar> = profile
+            .vars
+            .iter()
+            .map(|(key, value)| ResolvedEnvVar { key: key.clone(), value: value.clone(), secret: false, missing: false })
+            .collect();
+        for key in &profile.secret_keys {
+            let missing = credentials.get(&service, &secret_account(name, key))?.is_none();
+            vars.push(ResolvedEnvVar { key: key.clone(), value: if missing { String::new() } else { MASK.to_string() }, secret: true, missing });
+        }
+        vars.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(ResolvedEnvProfile { name: profile.name, vars })
+    }
+
+    /// The profile's real values for a process; fails when a secret is missing from the store
+    #[cfg(any(feature = "git", feature = "process"))]
+    pub fn environment(&self, project: &Path, name: &str, credentials: &CredentialStore) -> CredentialResult<ProfileEnvironment> {
+        let profile = self.find(project, name)?;
+        let service = secret_service(&project_key(project));
+        let mut environment = ProfileEnvironment { vars: profile.vars.into_iter().collect(), secrets: Vec::new() };
+        for key in &profile.secret_keys {
+            let value = credentials
+                .get(&service, &secret_account(name, key))?
+                .ok_or_else(|| invalid(format!("Secret '{}' of profile '{}' is not in the credential store", key, name)))?;
+            environment.secrets.push(value.clone());
+            environment.vars.push((key.clone(), value));
+        }
+        // Longest first, so a secret containing another is masked whole
+        environment.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Ok(environment)
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::fs::display_path;
+use crate::operations::CancelToken;
+
+pub(crate) const FINGERPRINT_DIR: &str = "fingerprints";
+
+// Fingerprints kept per project, newest first
+const KEEP_PER_PROJECT: usize = 5;
+
+// With content hashes enabled, files up to this size are hashed instead of compared by mtime
+const MAX_HASHED_FILE_BYTES: u64 = 64 * 1024;
+
+// Emit a progress event every this many files
+const PROGRESS_EVERY: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct FileEntry {
+    size: u64,
+    /// Unix seconds; None when the content hash stands in for it
+    modified: Option<i64>,
+    hash: Option<String>,
+}
+
+/// One directory of the fingerprint tree. Children are keyed by name, so ordering is by
+/// name bytes on every platform.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct DirNode {
+    digest: String,
+    files: BTreeMap<String, FileEntry>,
+    dirs: BTreeMap<String, DirNode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectFingerprint {
+    pub project_path: String,
+    /// Rolled-up digest of the whole tree; identical trees give identical digests
+    pub digest: String,
+    pub computed_at: i64,
+    pub file_count: usize,
+    pub content_hashes: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredFingerprint {
+    fingerprint: ProjectFingerprint,
+    tree: DirNode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FingerprintProgress {
+    pub project_path: String,
+    pub files_scanned: usize,
+    /// "scanning", "hashing" or "done"
+    pub stage: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FingerprintComparison {
+    pub previous: String,
+    pub current: ProjectFingerprint,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Directories descended into because their digests differed
+    pub directories_compared: usize,
+}
+
+fn file_digest(name: &str, entry: &FileEntry) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("f\0{}\0{}\0", name, entry.size));
+    match (&entry.hash, entry.modified) {
+        (Some(hash), _) => hasher.update(format!("h\0{}", hash)),
+        (None, modified) => hasher.update(format!("m\0{}", modified.unwrap_or(0))),
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+impl DirNode {
+    fn insert(&mut self, parts: &[&str], entry: FileEntry) {
+        match parts {
+            [] => {}
+            [name] => {
+                self.files.insert(name.to_string(), entry);
+            }
+            [dir, rest @ ..] => self.dirs.entry(dir.to_string()).or_default().insert(rest, entry),
+        }
+    }
+
+    /// Fill in every directory digest from its children's, bottom up
+    fn roll_up(&mut self) {
+        let mut hasher = Sha256::new();
+        for (name, entry) in &self.files {
+            hasher.update(format!("{}\n", file_digest(name, entry)));
+        }
+        for (name, dir) in self.dirs.iter_mut() {
+            dir.roll_up();
+            hasher.update(format!("d\0{}\0{}\n", name, dir.digest));
+        }
+        self.digest = format!("{:x}", hasher.finalize());
+    }
+
+    fn file_paths(&self, prefix: &str, out: &mut Vec<String>) {
+        out.extend(self.files.keys().map(|name| format!("{}{}", prefix, name)));
+        for (name, dir) in &self.dirs {
+            dir.file_paths(&format!("{}{}/", prefix, name), out);
+        }
+    }
+}
+
+/// Record paths that differ between `old` and `new`, descending only into directories whose
+/// digests differ
+fn compare_dirs(old: &DirNode, new: &DirNode, prefix: &str, result: &mut FingerprintComparison) {
+    if old.digest == new.digest {
+        return;
+    }
+    result.directories_compared += 1;
+    for (name, before) in &old.files {
+        match new.files.get(name) {
+            Some(after) if file_digest(name, after) != file_digest(name, before) => result.changed.push(format!("{}{}", prefix, name)),
+            Some(_) => {}
+            None => result.removed.push(format!("{}{}", prefix, name)),
+        }
+    }
+    for name in new.files.keys().filter(|name| !old.files.contains_key(*name)) {
+        result.added.push(format!("{}{}", prefix, name));
+    }
+    for (name, before) in &old.dirs {
+        let child = format!("{}{}/", prefix, name);
+        match new.dirs.get(name) {
+            Some(after) => compare_dirs(before, after, &child, result),
+            None => before.file_paths(&child, &mut result.removed),
+        }
+    }
+    for (name, after) in new.dirs.iter().filter(|(name, _)| !old.dirs.contains_key(*name)) {
+        after.file_paths(&format!("{}{}/", prefix, name), &mut result.added);
+    }
+}
+
+/// Build the tree for the non-ignored files under `root`, checking `cancel` as it goes
+fn scan<F>(root: &Path, content_hashes: bool, cancel: &CancelToken, on_progress: &F) -> Result<(DirNode, usize)>
+where
+    F: Fn(FingerprintProgress),
+{
+    let progress = |files_scanned: usize, stage: &str| on_progress(FingerprintProgress {
+        project_path: display_path(root),
+        files_scanned,
+        stage: stage.to_string(),
+    });
+
+    let mut tree = DirNode::default();
+    let mut count = 0;
+    progress(0, if content_hashes { "hashing" } else { "scanning" });
+    for entry in WalkBuilder::new(root).hidden(false).filter_entry(|e| e.file_name() != ".git").build() {
+        if cancel.is_cancelled() {
+            return Err(anyhow!("Fingerprint cancelled"));
+        }
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else { continue };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let Ok(metadata) = entry.metadata() else { continue };
+        let size = metadata.len();
+        let hash = if content_hashes && size <= MAX_HASHED_FILE_BYTES {
+            fs::read(entry.path()).ok().map(|content| format!("{:x}", Sha256::digest(&content)))
+        } else {
+            None
+        };
+        let modified = match hash {
+            Some(_) => None,
+            None => metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64),
+        };
+        tree.insert(&relative.split('/').collect::<Vec<_>>(), FileEntry { size, modified, hash });
+        count += 1;
+        if count % PROGRESS_EVERY == 0 {
+            progress(count, if content_hashes { "hashing" } else { "scanning" });
+        }
+    }
+    tree.roll_up();
+    progress(count, "done");
+    Ok((tree, count))
+}
+
+/// Project fingerprints kept in the app data dir, so a later session can tell what changed
+/// without the earlier tree in memory
+pub struct FingerprintStore {
+    dir: PathBuf,
+}
+
+impl FingerprintStore {
+    pub fn load(data_dir: &Path) -> Self {
+        Self { dir: data_dir.join(FINGERPRINT_DIR) }
+    }
+
+    fn project_dir(&self, root: &Path) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(display_path(root).as_bytes()));
+        self.dir.join(&key[..16])
+    }
+
+    fn stored(&self, root: &Path, digest: &str) -> Result<StoredFingerprint> {
+        if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("'{}' is not a fingerprint", digest));
+        }
+        let raw = fs::read_to_string(self.project_dir(root).join(format!("{}.json", digest)))
+            .map_err(|_| anyhow!("No stored fingerprint '{}' for this project", digest))?;
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    fn save(&self, stored: &StoredFingerprint, root: &Path) -> Result<()> {
+        let dir = self.project_dir(root);
+        fs::create_dir_all(&dir)?;
+        let raw = serde_json::to_string(stored)?;
+        crate::fs::write_atomic(&dir.join(format!("{}.json", stored.fingerprint.digest)), raw.as_bytes())?;
+
+        let Ok(entries) = fs::read_dir(&dir) else { return Ok(()) };
+        let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        files.sort_by_key(|f| std::cmp::Reverse(f.0));
+        for (_, path) in files.into_iter().skip(KEEP_PER_PROJECT) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    /// The most recently computed fingerprint of `root`, if any
+    pub fn latest(&self, root: &Path) -> Option<ProjectFingerprint> {
+        fs::read_dir(self.project_dir(root))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| serde_json::from_str::<StoredFingerprint>(&fs::read_to_string(e.path()).ok()?).ok())
+            .map(|stored| stored.fingerprint)
+            .max_by_key(|fingerprint| fingerprint.computed_at)
+    }
+
+    /// Fingerprint the non-ignored files under `root` by path, size and mtime, or by content
+    /// hash for small files when `content_hashes` is set, and store the result
+    pub fn compute<F>(&self, root: &Path, content_hashes: bool, cancel: &CancelToken, on_progress: F) -> Result<ProjectFingerprint>
+    where
+        F: Fn(FingerprintProgress),
+    {
+        Ok(self.compute_tree(root, content_hashes, cancel, &on_progress)?.fingerprint)
+    }
+
+    fn compute_tree<F>(&self, root: &Path, content_hashes: bool, cancel: &CancelToken, on_progress: &F) -> Result<StoredFingerprint>
+    where
+        F: Fn(FingerprintProgress),
+    {
+        let (tree, file_count) = scan(root, content_hashes, cancel, on_progress)?;
+        let stored = StoredFingerprint {
+            fingerprint: ProjectFingerprint {
+                project_path: display_path(root),
+                digest: tree.digest.clone(),
+                computed_at: chrono::Utc::now().timestamp(),
+                file_count,
+                content_hashes,
+            },
+            tree,
+        };
+        self.save(&stored, root)?;
+        Ok(stored)
+    }
+
+    /// Paths added, removed and changed since the stored fingerprint `previous`. The project is
+    /// fingerprinted again with the same options, and that fingerprint is stored as well.
+    pub fn compare<F>(&self, root: &Path, previous: &str, cancel: &CancelToken, on_progress: F) -> Result<FingerprintComparison>
+    where
+        F: Fn(FingerprintProgress),
+    {
+        let old = self.stored(root, previous)?;
+        let new = self.compute_tree(root, old.fingerprint.content_hashes, cancel, &on_progress)?;
+        let mut result = FingerprintComparison {
+            previous: previous.to_string(),
+            current: new.fingerprint,
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            directories_compared: 0,
+        };
+        compare_dirs(&old.tree, &new.tree, "", &mut result);
+        result.added.sort();
+        result.removed.sort();
+        result.changed.sort();
+        Ok(result)
+    }
+}
@@ -1,19 +1,141 @@
 use std::fs;
-use std::path::Path;
+use std::io::ErrorKind;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
 use serde::{Deserialize, Serialize};
-use notify::{Watcher, RecursiveMode, recommended_watcher};
+use notify::{Watcher, RecursiveMode, PollWatcher, recommended_watcher};
 use std::sync::mpsc::channel;
 use anyhow::Result;
+use walkdir::WalkDir;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::{HashMap, HashSet};
+
+use crate::gitignore::IgnoreMatcher;
+use crate::paths::WsPath;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FileItem {
+    /// Display name; lossy when the file name is not valid Unicode
     pub name: String,
     pub path: String,
+    /// Lossless form of `path` to pass back to commands (see `encode_path`)
+    pub raw_path: String,
     pub is_directory: bool,
     pub size: Option<u64>,
     pub modified: Option<i64>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DirectoryListing {
+    pub items: Vec<FileItem>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// File system failure categories surfaced to the frontend as `{ code, message }`
+#[derive(Debug, Clone)]
+pub enum FsError {
+    PermissionDenied,
+    ReadOnlyFilesystem,
+    NotFound,
+    AlreadyExists,
+    TooLarge,
+    OutsideWorkspace,
+    ContentsChanged { expected: u64, actual: u64 },
+    /// The file's mtime (seconds) no longer matches the one the editor loaded
+    ModifiedOnDisk { expected: i64, actual: Option<i64> },
+    InvalidName { message: String },
+    Io { message: String },
+}
+
+pub type FsResult<T> = std::result::Result<T, FsError>;
+
+impl FsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FsError::PermissionDenied => "PermissionDenied",
+            FsError::ReadOnlyFilesystem => "ReadOnlyFilesystem",
+            FsError::NotFound => "NotFound",
+            FsError::AlreadyExists => "AlreadyExists",
+            FsError::TooLarge => "TooLarge",
+            FsError::OutsideWorkspace => "OutsideWorkspace",
+            FsError::ContentsChanged { .. } => "ContentsChanged",
+            FsError::ModifiedOnDisk { .. } => "ModifiedOnDisk",
+            FsError::InvalidName { .. } => "InvalidName",
+            FsError::Io { .. } => "Io",
+        }
+    }
+}
+
+impl std::fmt::Display for FsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FsError::PermissionDenied => write!(f, "Permission denied"),
+            FsError::ReadOnlyFilesystem => write!(f, "The location is on a read-only file system"),
+            FsError::NotFound => write!(f, "File or directory not found"),
+            FsError::AlreadyExists => write!(f, "File or directory already exists"),
+            FsError::TooLarge => write!(f, "File is too large to open"),
+            FsError::OutsideWorkspace => write!(f, "Path is outside the workspace"),
+            FsError::ContentsChanged { expected, actual } => write!(
+                f,
+                "The folder changed since it was previewed ({} files expected, {} found); nothing was deleted",
+                expected, actual
+            ),
+            FsError::ModifiedOnDisk { actual: None, .. } => write!(f, "The file was deleted on disk since it was opened"),
+            FsError::ModifiedOnDisk { expected, actual: Some(actual) } => write!(
+                f,
+                "The file was changed on disk since it was opened (modified at {}, expected {})",
+                actual, expected
+            ),
+            FsError::InvalidName { message } => write!(f, "{}", message),
+            FsError::Io { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FsError {}
+
+impl Serialize for FsError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("FsError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+// OS error code for writes against a read-only volume (EROFS / ERROR_WRITE_PROTECT)
+#[cfg(windows)]
+const READ_ONLY_OS_ERROR: i32 = 19;
+#[cfg(not(windows))]
+const READ_ONLY_OS_ERROR: i32 = 30;
+
+// OS error code for a rename across filesystems (EXDEV / ERROR_NOT_SAME_DEVICE)
+#[cfg(windows)]
+const CROSS_DEVICE_OS_ERROR: i32 = 17;
+#[cfg(not(windows))]
+const CROSS_DEVICE_OS_ERROR: i32 = 18;
+
+impl From<std::io::Error> for FsError {
+    fn from(e: std::io::Error) -> Self {
+        if e.raw_os_error() == Some(READ_ONLY_OS_ERROR) {
+            return FsError::ReadOnlyFilesystem;
+        }
+        match e.kind() {
+            ErrorKind::PermissionDenied => FsError::PermissionDenied,
+            ErrorKind::NotFound => FsError::NotFound,
+            ErrorKind::AlreadyExists => FsError::AlreadyExists,
+            _ => FsError::Io { message: e.to_string() },
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FileOperation {
     pub operation: String,
@@ -22,60 +144,557 @@ pub struct FileOperation {
     pub error: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CopyProgress {
+    pub done: u64,
+    pub total: u64,
+    pub current: String,
+}
+
+struct WatchedRoot {
+    exclusions: GlobSet,
+    ignore: Option<IgnoreMatcher>,
+    patterns: Vec<String>,
+    directories: Vec<PathBuf>,
+    /// Directories created after the walk, watched recursively so their contents are covered too
+    added_directories: Vec<PathBuf>,
+    excluded_directories: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchedRootStats {
+    pub path: String,
+    pub recursive: bool,
+    pub respects_gitignore: bool,
+    pub watched_directories: usize,
+    pub excluded_directories: usize,
+    pub exclusions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatcherStats {
+    pub roots: Vec<WatchedRootStats>,
+    pub total_watched_directories: usize,
+    pub limit_reached: bool,
+}
+
+/// Emitted when the OS refuses more watches (e.g. inotify `max_user_watches`)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchLimitReached {
+    pub root: String,
+    pub watched_directories: usize,
+    pub guidance: String,
+}
+
+/// How a workspace is watched: OS notifications, or periodic mtime/size scans where
+/// notifications never arrive (some network filesystems and WSL mounts)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    Native,
+    Polling,
+}
+
+/// Emitted as `watch-mode-changed` when a workspace switches between native and polling watching
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchModeChanged {
+    pub root: String,
+    pub mode: WatchMode,
+    pub reason: String,
+}
+
 pub struct FileWatcher {
-    watcher: notify::RecommendedWatcher,
+    watcher: Box<dyn Watcher + Send>,
+    roots: HashMap<PathBuf, WatchedRoot>,
+    limit_reached: bool,
+    /// Set in polling mode: directories are always watched one by one, at most this many
+    poll_limit: Option<usize>,
     _handle: std::thread::JoinHandle<()>,
 }
 
+/// Compile exclusion patterns; bare names like `node_modules` match at any depth
+pub fn build_exclusions(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_end_matches('/');
+        if pattern.is_empty() {
+            continue;
+        }
+        let anchored = match pattern.strip_prefix('/') {
+            Some(rooted) => rooted.to_string(),
+            None => format!("**/{}", pattern),
+        };
+        builder.add(GlobBuilder::new(&anchored).literal_separator(true).build()?);
+    }
+    Ok(builder.build()?)
+}
+
 impl FileWatcher {
-    pub fn new<F>(callback: F) -> Result<Self>
+    /// `on_error` sees errors the backend reports after watching started, such as the
+    /// volume going away
+    pub fn new<F, E>(callback: F, on_error: E) -> Result<Self>
     where
         F: Fn(notify::Event) + Send + 'static,
+        E: Fn(notify::Error) + Send + 'static,
     {
         let (tx, rx) = channel();
-
         let watcher = recommended_watcher(move |res| {
-            match res {
-                Ok(event) => {
-                    let _ = tx.send(event);
-                }
-                Err(e) => println!("Watch error: {:?}", e),
-            }
+            let _ = tx.send(res);
         })?;
+        Ok(Self::start(Box::new(watcher), None, rx, callback, on_error))
+    }
+
+    /// A watcher that scans mtimes and sizes every `interval` instead of relying on OS
+    /// notifications. It reports the same `notify::Event`s, so callbacks need not care which
+    /// kind is running. At most `max_directories` are scanned per root.
+    pub fn polling<F, E>(callback: F, on_error: E, interval: std::time::Duration, max_directories: usize) -> Result<Self>
+    where
+        F: Fn(notify::Event) + Send + 'static,
+        E: Fn(notify::Error) + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let config = notify::Config::default().with_poll_interval(interval);
+        let watcher = PollWatcher::new(move |res| {
+            let _ = tx.send(res);
+        }, config)?;
+        Ok(Self::start(Box::new(watcher), Some(max_directories), rx, callback, on_error))
+    }
 
+    fn start<F, E>(watcher: Box<dyn Watcher + Send>, poll_limit: Option<usize>, rx: std::sync::mpsc::Receiver<notify::Result<notify::Event>>, callback: F, on_error: E) -> Self
+    where
+        F: Fn(notify::Event) + Send + 'static,
+        E: Fn(notify::Error) + Send + 'static,
+    {
         let handle = std::thread::spawn(move || {
             loop {
                 match rx.recv() {
-                    Ok(event) => callback(event),
+                    Ok(Ok(event)) => callback(event),
+                    Ok(Err(e)) => on_error(e),
                     Err(_) => break,
                 }
             }
         });
 
-        Ok(Self {
+        Self {
             watcher,
+            roots: HashMap::new(),
+            limit_reached: false,
+            poll_limit,
             _handle: handle,
-        })
+        }
+    }
+
+    /// Watch `root`, skipping directories matched by `patterns` or ignored by git when `ignore` is
+    /// given, by watching each kept directory individually.
+    /// Returns `Some(WatchLimitReached)` when the OS watch limit stopped the walk early.
+    pub fn watch_filtered(&mut self, root: &Path, patterns: &[String], ignore: Option<IgnoreMatcher>) -> Result<Option<WatchLimitReached>> {
+        self.unwatch(root)?;
+        let exclusions = build_exclusions(patterns)?;
+
+        if exclusions.is_empty() && ignore.is_none() && self.poll_limit.is_none() {
+            self.watcher.watch(root, RecursiveMode::Recursive)?;
+            self.roots.insert(root.to_path_buf(), WatchedRoot {
+                exclusions,
+                ignore,
+                patterns: patterns.to_vec(),
+                directories: vec![root.to_path_buf()],
+                added_directories: Vec::new(),
+                excluded_directories: 0,
+            });
+            return Ok(None);
+        }
+
+        let mut directories = Vec::new();
+        let mut excluded_directories = 0;
+        let mut limit = None;
+
+        let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
+            if !e.file_type().is_dir() {
+                return false;
+            }
+            let relative = e.path().strip_prefix(root).unwrap_or(e.path());
+            let ignored = ignore.as_ref().map(|m| m.is_ignored(e.path(), true)).unwrap_or(false);
+            if !relative.as_os_str().is_empty() && (exclusions.is_match(relative) || ignored) {
+                excluded_directories += 1;
+                return false;
+            }
+            true
+        });
+
+        for entry in walker.filter_map(|e| e.ok()) {
+            if let Some(max) = self.poll_limit.filter(|max| directories.len() >= *max) {
+                self.limit_reached = true;
+                limit = Some(WatchLimitReached {
+                    root: display_path(root),
+                    watched_directories: directories.len(),
+                    guidance: format!("Polling scans at most {} directories per workspace. Add exclusions for large folders, or raise the polling limit in settings.", max),
+                });
+                break;
+            }
+            match self.watcher.watch(entry.path(), RecursiveMode::NonRecursive) {
+                Ok(_) => directories.push(entry.path().to_path_buf()),
+                Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => {
+                    self.limit_reached = true;
+                    limit = Some(WatchLimitReached {
+                        root: display_path(root),
+                        watched_directories: directories.len(),
+                        guidance: watch_limit_guidance(),
+                    });
+                    break;
+                }
+                Err(e) => log::warn!("Watch error: {:?}", e),
+            }
+        }
+
+        self.roots.insert(root.to_path_buf(), WatchedRoot {
+            exclusions,
+            ignore,
+            patterns: patterns.to_vec(),
+            directories,
+            added_directories: Vec::new(),
+            excluded_directories,
+        });
+        Ok(limit)
+    }
+
+    /// Start watching a directory created under a filtered root, unless it is excluded. The watch
+    /// is recursive: a directory moved or unpacked into place arrives with its subdirectories
+    /// already inside, before any event for them could be seen.
+    pub fn add_directory(&mut self, dir: &Path) -> Result<()> {
+        let Some((root, watched)) = self.roots.iter_mut().find(|(root, _)| dir.starts_with(root)) else {
+            return Ok(());
+        };
+        let relative = dir.strip_prefix(root).unwrap_or(dir);
+        let filtered = !watched.exclusions.is_empty() || watched.ignore.is_some() || self.poll_limit.is_some();
+        let ignored = watched.ignore.as_ref().map(|m| m.is_ignored(dir, true)).unwrap_or(false);
+        let over_poll_limit = self.poll_limit.map(|max| watched.directories.len() >= max).unwrap_or(false);
+        if !filtered || ignored || over_poll_limit || watched.exclusions.is_match(relative) || watched.directories.iter().any(|d| d == dir) {
+            return Ok(());
+        }
+        if watched.added_directories.iter().any(|d| dir.starts_with(d)) {
+            return Ok(());
+        }
+        self.watcher.watch(dir, RecursiveMode::Recursive)?;
+        watched.directories.push(dir.to_path_buf());
+        watched.added_directories.push(dir.to_path_buf());
+        Ok(())
     }
 
-    pub fn watch(&mut self, path: &Path) -> Result<()> {
-        self.watcher.watch(path, RecursiveMode::Recursive)?;
+    /// Move watches under a renamed directory to its new location. Backends that watch by inode
+    /// would otherwise keep reporting the old paths, or nothing at all. A recursively watched
+    /// root is re-registered whole.
+    pub fn remap(&mut self, from: &Path, to: &Path) -> Result<()> {
+        for (root, watched) in self.roots.iter_mut().filter(|(root, _)| from.starts_with(root) && from != root.as_path()) {
+            let recursive = watched.exclusions.is_empty() && watched.ignore.is_none() && self.poll_limit.is_none();
+            if recursive {
+                let _ = self.watcher.unwatch(root);
+                self.watcher.watch(root, RecursiveMode::Recursive)?;
+                continue;
+            }
+            for dir in watched.directories.iter_mut() {
+                let Some(moved) = remap_prefix(dir, from, to) else { continue };
+                let mode = if watched.added_directories.contains(dir) { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+                let _ = self.watcher.unwatch(dir);
+                self.watcher.watch(&moved, mode)?;
+                *dir = moved;
+            }
+            for dir in watched.added_directories.iter_mut() {
+                if let Some(moved) = remap_prefix(dir, from, to) {
+                    *dir = moved;
+                }
+            }
+        }
         Ok(())
     }
 
     pub fn unwatch(&mut self, path: &Path) -> Result<()> {
-        self.watcher.unwatch(path)?;
+        match self.roots.remove(path) {
+            Some(watched) => {
+                for dir in watched.directories {
+                    let _ = self.watcher.unwatch(&dir);
+                }
+            }
+            None => {
+                let _ = self.watcher.unwatch(path);
+            }
+        }
         Ok(())
     }
+
+    pub fn stats(&self) -> WatcherStats {
+        let roots: Vec<WatchedRootStats> = self.roots
+            .iter()
+            .map(|(path, watched)| WatchedRootStats {
+                path: display_path(path),
+                recursive: watched.exclusions.is_empty() && watched.ignore.is_none() && self.poll_limit.is_none(),
+                respects_gitignore: watched.ignore.is_some(),
+                watched_directories: watched.directories.len(),
+                excluded_directories: watched.excluded_directories,
+                exclusions: watched.patterns.clone(),
+            })
+            .collect();
+        let total_watched_directories = roots.iter().map(|r| r.watched_directories).sum();
+
+        WatcherStats {
+            roots,
+            total_watched_directories,
+            limit_reached: self.limit_reached,
+        }
+    }
+}
+
+fn watch_limit_guidance() -> String {
+    if cfg!(target_os = "linux") {
+        "The inotify watch limit was reached. Add exclusions for large generated folders, or raise the limit: \
+         sudo sysctl fs.inotify.max_user_watches=524288".to_string()
+    } else {
+        "The OS file watch limit was reached. Add exclusions for large generated folders.".to_string()
+    }
+}
+
+/// Payload emitted to the frontend for each file system change
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FsChangeEvent {
+    /// Workspace root id, as in each path's `root`
+    pub workspace_root: String,
+    pub kind: String,
+    pub paths: Vec<WsPath>,
+}
+
+impl FsChangeEvent {
+    pub fn new<P: AsRef<Path>>(workspace_root: &Path, kind: &str, paths: &[P]) -> Self {
+        Self {
+            workspace_root: crate::paths::root_id(workspace_root),
+            kind: kind.to_string(),
+            paths: paths.iter().map(|p| WsPath::new(workspace_root, p.as_ref())).collect(),
+        }
+    }
+
+    pub fn from_event(workspace_root: &Path, event: &notify::Event) -> Self {
+        Self::new(workspace_root, &format!("{:?}", event.kind), &event.paths)
+    }
+}
+
+// Windows rejects these as file names regardless of extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Leave headroom below MAX_PATH (260) for file names appended later
+const LONG_PATH_THRESHOLD: usize = 240;
+
+// Refuse to load files larger than this into the editor
+const MAX_READ_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Add the Windows extended-length prefix to long absolute paths; no-op elsewhere
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+
+    let raw = path.to_string_lossy();
+    let normalized = path.components().all(|c| !matches!(c, Component::CurDir | Component::ParentDir));
+    if raw.len() < LONG_PATH_THRESHOLD || raw.starts_with(r"\\?\") || !path.is_absolute() || !normalized {
+        return path.to_path_buf();
+    }
+
+    let backslashed = raw.replace('/', "\\");
+    match backslashed.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", backslashed)),
+    }
+}
+
+/// Resolve `.` and `..` without touching the disk, for paths that may not exist yet
+pub fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `path` with its `from` prefix replaced by `to`, or None when it is not at or below `from`
+pub fn remap_prefix(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+    Some(if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) })
+}
+
+/// Render a path for the frontend, stripping any extended-length prefix
+pub fn display_path(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(local) = raw.strip_prefix(r"\\?\") {
+        local.to_string()
+    } else {
+        raw.to_string()
+    }
+}
+
+// Marks a path string carrying hex-encoded OS bytes; NUL never occurs in a real path
+const RAW_PATH_PREFIX: &str = "\0raw:";
+
+#[cfg(unix)]
+fn os_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(windows)]
+fn os_bytes(path: &Path) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+#[cfg(unix)]
+fn path_from_os_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+#[cfg(windows)]
+fn path_from_os_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let wide: Vec<u16> = bytes.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    Some(PathBuf::from(std::ffi::OsString::from_wide(&wide)))
+}
+
+/// String form of a path that survives a round trip through the frontend: the display path
+/// when it is valid Unicode, otherwise its raw OS bytes hex-encoded behind a NUL-led prefix
+pub fn encode_path(path: &Path) -> String {
+    if path.to_str().is_some() {
+        return display_path(path);
+    }
+    let hex: String = os_bytes(path).iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", RAW_PATH_PREFIX, hex)
+}
+
+/// Inverse of `encode_path`; ordinary path strings are returned unchanged
+pub fn decode_path(raw: &str) -> PathBuf {
+    let Some(hex) = raw.strip_prefix(RAW_PATH_PREFIX) else { return PathBuf::from(raw) };
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return PathBuf::from(raw);
+    }
+    let bytes: Option<Vec<u8>> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect();
+    bytes.and_then(path_from_os_bytes).unwrap_or_else(|| PathBuf::from(raw))
+}
+
+/// Inverse of `path_from_git_bytes`: the bytes git stores for a workdir-relative path
+#[cfg(feature = "git")]
+pub fn path_to_git_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        os_bytes(path)
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_string_lossy().replace('\\', "/").into_bytes()
+    }
 }
 
-pub fn list_directory(path: &Path) -> Result<Vec<FileItem>> {
+/// A path git reports as bytes; git stores paths as UTF-8 except on Unix, where any bytes are allowed
+#[cfg(feature = "git")]
+pub fn path_from_git_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Reject path components Windows cannot create: device names and trailing dots/spaces
+pub fn validate_windows_name(path: &Path) -> FsResult<()> {
+    let mut offending = Vec::new();
+
+    for component in path.components() {
+        let Component::Normal(name) = component else { continue };
+        let name = name.to_string_lossy();
+        let stem = name.split('.').next().unwrap_or("").trim_end();
+
+        if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+            offending.push(format!("'{}' (reserved device name)", name));
+        } else if name.ends_with('.') || name.ends_with(' ') {
+            offending.push(format!("'{}' (trailing dot or space)", name));
+        }
+    }
+
+    if offending.is_empty() {
+        Ok(())
+    } else {
+        Err(FsError::InvalidName {
+            message: format!("Invalid path component(s) for Windows: {}", offending.join(", ")),
+        })
+    }
+}
+
+pub fn check_target_name(path: &Path) -> FsResult<()> {
+    if cfg!(windows) {
+        validate_windows_name(path)?;
+    }
+    Ok(())
+}
+
+// Bytes sniffed from the start of a file when classifying it
+const SNIFF_BYTES: usize = 8192;
+
+/// Heuristic binary check: NUL bytes or a high share of control characters
+pub fn is_binary_content(bytes: &[u8]) -> bool {
+    if bytes.is_empty() {
+        return false;
+    }
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c | 0x1b))
+        .count();
+    control * 10 > bytes.len()
+}
+
+/// Classify a file on disk by sniffing its leading bytes
+pub fn is_binary_file(path: &Path) -> FsResult<bool> {
+    use std::io::Read;
+    let mut file = fs::File::open(long_path(path))?;
+    let mut buffer = vec![0; SNIFF_BYTES];
+    let read = file.read(&mut buffer)?;
+    Ok(is_binary_content(&buffer[..read]))
+}
+
+/// List a directory, collecting unreadable entries in `skipped` instead of failing
+pub fn list_directory(path: &Path) -> FsResult<DirectoryListing> {
     let mut items = Vec::new();
+    let mut skipped = Vec::new();
 
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let metadata = entry.metadata()?;
-        let path_str = entry.path().to_string_lossy().to_string();
+    for entry in fs::read_dir(long_path(path))? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                skipped.push(SkippedEntry {
+                    path: display_path(path),
+                    reason: FsError::from(e).to_string(),
+                });
+                continue;
+            }
+        };
+        let path_str = display_path(&entry.path());
+        let raw_path = encode_path(&entry.path());
         let name = entry.file_name().to_string_lossy().to_string();
 
         // Skip hidden files (starting with .)
@@ -83,9 +702,21 @@ pub fn list_directory(path: &Path) -> Result<Vec<FileItem>> {
             continue;
         }
 
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                skipped.push(SkippedEntry {
+                    path: path_str,
+                    reason: FsError::from(e).to_string(),
+                });
+                continue;
+            }
+        };
+
         items.push(FileItem {
             name,
             path: path_str,
+            raw_path,
             is_directory: metadata.is_dir(),
             size: if metadata.is_file() { Some(metadata.len()) } else { None },
             modified: metadata.modified()
@@ -104,10 +735,97 @@ pub fn list_directory(path: &Path) -> Result<Vec<FileItem>> {
         }
     });
 
-    Ok(items)
+    Ok(DirectoryListing { items, skipped })
+}
+
+// Probes per worker thread before a batch is split across threads
+const PROBES_PER_WORKER: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PathKind {
+    File,
+    Dir,
+    Symlink,
+    Missing,
+}
+
+/// What one path is, without following a final symlink. Size and mtime are left out for
+/// missing paths and directories to keep large batches small.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PathProbe {
+    /// As passed in
+    pub path: String,
+    pub exists: bool,
+    pub kind: PathKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
+    pub in_workspace: bool,
+    /// FsError code when the path could not be examined (e.g. "PermissionDenied"); the kind
+    /// is then reported as missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn probe_path(raw: &str, in_workspace: &(dyn Fn(&Path) -> bool + Sync)) -> PathProbe {
+    let path = decode_path(raw);
+    let mut probe = PathProbe {
+        path: raw.to_string(),
+        exists: false,
+        kind: PathKind::Missing,
+        size: None,
+        modified: None,
+        in_workspace: in_workspace(&path),
+        error: None,
+    };
+    match fs::symlink_metadata(long_path(&path)) {
+        Ok(metadata) => {
+            let file_type = metadata.file_type();
+            probe.exists = true;
+            probe.kind = if file_type.is_symlink() {
+                PathKind::Symlink
+            } else if file_type.is_dir() {
+                PathKind::Dir
+            } else {
+                PathKind::File
+            };
+            if probe.kind == PathKind::File {
+                probe.size = Some(metadata.len());
+                probe.modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64);
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => probe.error = Some(FsError::from(e).code().to_string()),
+    }
+    probe
+}
+
+/// Existence and type of each path, in input order. A path that can't be examined gets an
+/// error code in its probe; the batch itself never fails.
+pub fn probe_paths(paths: &[String], in_workspace: &(dyn Fn(&Path) -> bool + Sync)) -> Vec<PathProbe> {
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(8);
+    if paths.len() <= PROBES_PER_WORKER || workers == 1 {
+        return paths.iter().map(|raw| probe_path(raw, in_workspace)).collect();
+    }
+    let chunk = paths.len().div_ceil(workers).max(PROBES_PER_WORKER);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk)
+            .map(|part| scope.spawn(move || part.iter().map(|raw| probe_path(raw, in_workspace)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap_or_default()).collect()
+    })
 }
 
-pub fn create_file(file_path: &Path, content: &str) -> Result<()> {
+pub fn create_file(file_path: &Path, content: &str) -> FsResult<()> {
+    check_target_name(file_path)?;
+    let file_path = long_path(file_path);
     if let Some(parent) = file_path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -115,17 +833,77 @@ pub fn create_file(file_path: &Path, content: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn create_directory(dir_path: &Path) -> Result<()> {
-    fs::create_dir_all(dir_path)?;
+pub fn create_directory(dir_path: &Path) -> FsResult<()> {
+    check_target_name(dir_path)?;
+    fs::create_dir_all(long_path(dir_path))?;
     Ok(())
 }
 
-pub fn rename_path(from: &Path, to: &Path) -> Result<()> {
-    fs::rename(from, to)?;
+pub fn rename_path(from: &Path, to: &Path) -> FsResult<()> {
+    check_target_name(to)?;
+    fs::rename(long_path(from), long_path(to))?;
     Ok(())
 }
 
-pub fn delete_path(path: &Path) -> Result<()> {
+// Number of largest files reported by a delete preview
+const PREVIEW_LARGEST_ITEMS: usize = 10;
+
+// Files may come and go (editor swap files, build output) between preview and delete
+const DELETE_COUNT_SLACK: u64 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeletePreviewItem {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DeletePreview {
+    pub path: String,
+    pub is_directory: bool,
+    pub total_files: u64,
+    pub total_directories: u64,
+    pub total_bytes: u64,
+    pub largest_items: Vec<DeletePreviewItem>,
+    /// Tracked files with uncommitted changes that would be lost
+    pub modified_tracked: Vec<String>,
+    /// Files the frontend reported as open in an editor
+    pub open_files: Vec<String>,
+    /// The path lies inside an open, watched workspace
+    pub watched: bool,
+}
+
+/// Walk `path` and summarise what deleting it would remove; git and editor state is filled in by the caller
+pub fn preview_delete(path: &Path) -> FsResult<DeletePreview> {
+    let metadata = fs::symlink_metadata(long_path(path))?;
+    let mut preview = DeletePreview {
+        path: display_path(path),
+        is_directory: metadata.is_dir(),
+        ..Default::default()
+    };
+
+    let mut files: Vec<DeletePreviewItem> = Vec::new();
+    for entry in WalkDir::new(long_path(path)).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            if entry.depth() > 0 {
+                preview.total_directories += 1;
+            }
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        preview.total_files += 1;
+        preview.total_bytes += size;
+        files.push(DeletePreviewItem { path: display_path(entry.path()), size });
+    }
+
+    files.sort_by_key(|f| std::cmp::Reverse(f.size));
+    files.truncate(PREVIEW_LARGEST_ITEMS);
+    preview.largest_items = files;
+    Ok(preview)
+}
+
+pub fn delete_path(path: &Path) -> FsResult<()> {
+    let path = long_path(path);
     if path.is_dir() {
         fs::remove_dir_all(path)?;
     } else {
@@ -134,17 +912,347 @@ pub fn delete_path(path: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn move_path(from: &Path, to: &Path) -> Result<()> {
-    fs::rename(from, to)?;
+/// Delete `path`, refusing if its file count drifted too far from what the user approved in a preview
+pub fn delete_path_expecting(path: &Path, expected_file_count: Option<u64>) -> FsResult<()> {
+    if let Some(expected) = expected_file_count {
+        let actual = preview_delete(path)?.total_files;
+        let tolerance = (expected / 10).max(DELETE_COUNT_SLACK);
+        if actual.abs_diff(expected) > tolerance {
+            return Err(FsError::ContentsChanged { expected, actual });
+        }
+    }
+    delete_path(path)
+}
+
+pub fn move_path(from: &Path, to: &Path) -> FsResult<()> {
+    fs::rename(long_path(from), long_path(to))?;
     Ok(())
 }
 
-pub fn read_file_content(file_path: &Path) -> Result<String> {
+pub fn read_file_content(file_path: &Path) -> FsResult<String> {
+    let file_path = long_path(file_path);
+    if fs::metadata(&file_path)?.len() > MAX_READ_BYTES {
+        return Err(FsError::TooLarge);
+    }
     let content = fs::read_to_string(file_path)?;
     Ok(content)
 }
 
-pub fn write_file_content(file_path: &Path, content: &str) -> Result<()> {
-    fs::write(file_path, content)?;
+pub fn write_file_content(file_path: &Path, content: &str) -> FsResult<()> {
+    fs::write(long_path(file_path), content)?;
+    Ok(())
+}
+
+/// Write via a sibling temp file and rename, so readers never observe a half-written file
+pub fn write_atomic(file_path: &Path, content: &[u8]) -> FsResult<()> {
+    let file_path = long_path(file_path);
+    let parent = file_path.parent().ok_or(FsError::NotFound)?;
+    let file_name = file_path.file_name().ok_or(FsError::NotFound)?.to_string_lossy();
+    let temp_path = parent.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    if let Err(e) = fs::write(&temp_path, content) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    if let Err(e) = fs::rename(&temp_path, &file_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
     Ok(())
 }
+
+/// Modification time in whole seconds, as reported in `FileItem::modified`
+pub fn modified_secs(file_path: &Path) -> Option<i64> {
+    fs::metadata(long_path(file_path))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+/// `write_atomic`, refusing when the file's mtime differs from `expected_modified`
+pub fn write_checked(file_path: &Path, content: &[u8], expected_modified: Option<i64>) -> FsResult<()> {
+    if let Some(expected) = expected_modified {
+        let actual = modified_secs(file_path);
+        if actual != Some(expected) {
+            return Err(FsError::ModifiedOnDisk { expected, actual });
+        }
+    }
+    write_atomic(file_path, content)
+}
+
+/// Pick a destination in `dir` that does not exist yet: "name copy.ext", "name copy 2.ext", ...
+pub fn unique_destination(dir: &Path, file_name: &OsStr) -> PathBuf {
+    unique_destination_avoiding(dir, file_name, &HashSet::new())
+}
+
+/// `unique_destination`, also treating `taken` as occupied (targets already planned in a batch)
+pub fn unique_destination_avoiding(dir: &Path, file_name: &OsStr, taken: &HashSet<PathBuf>) -> PathBuf {
+    let free = |candidate: &Path| !candidate.exists() && !taken.contains(candidate);
+    let candidate = dir.join(file_name);
+    if free(&candidate) {
+        return candidate;
+    }
+
+    let original = Path::new(file_name);
+    let stem = original.file_stem().unwrap_or(file_name).to_string_lossy().to_string();
+    let ext = original.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let mut n = 1;
+    loop {
+        let name = if n == 1 {
+            format!("{} copy{}", stem, ext)
+        } else {
+            format!("{} copy {}{}", stem, n, ext)
+        };
+        let candidate = dir.join(name);
+        if free(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Recursively copy a file or directory, reporting progress per copied file
+pub fn copy_path<F>(from: &Path, to: &Path, mut on_progress: F) -> FsResult<()>
+where
+    F: FnMut(CopyProgress),
+{
+    check_target_name(to)?;
+    if to.starts_with(from) {
+        return Err(FsError::Io { message: "Cannot copy a directory into itself".to_string() });
+    }
+
+    if !from.is_dir() {
+        fs::copy(long_path(from), long_path(to))?;
+        on_progress(CopyProgress { done: 1, total: 1, current: display_path(from) });
+        return Ok(());
+    }
+
+    let total = WalkDir::new(from)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .count() as u64;
+    let mut done = 0;
+
+    for entry in WalkDir::new(from) {
+        let entry = entry.map_err(|e| match e.into_io_error() {
+            Some(io) => FsError::from(io),
+            None => FsError::Io { message: "Symlink loop detected".to_string() },
+        })?;
+        let relative = entry.path().strip_prefix(from).unwrap_or(entry.path());
+        let target = to.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(long_path(&target))?;
+        } else {
+            fs::copy(long_path(entry.path()), long_path(&target))?;
+            done += 1;
+            on_progress(CopyProgress { done, total, current: display_path(entry.path()) });
+        }
+    }
+
+    Ok(())
+}
+
+/// Move a path, falling back to copy + delete when a rename crosses devices
+pub fn move_path_with_fallback<F>(from: &Path, to: &Path, on_progress: F) -> FsResult<()>
+where
+    F: FnMut(CopyProgress),
+{
+    check_target_name(to)?;
+    match fs::rename(long_path(from), long_path(to)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(CROSS_DEVICE_OS_ERROR) => {
+            copy_path(from, to, on_progress)?;
+            delete_path(from)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_windows_name_rejects_reserved_names() {
+        let rejected = [
+            "CON", "con", "Con.txt", "nul", "AUX.tar.gz", "PRN.", "COM1", "com9.log", "LPT1", "lpt9.md", "CON .txt",
+            "src/aux.rs", "src/LPT3/readme.md", "name.", "name ", "dir./file.txt",
+        ];
+        for name in rejected {
+            let err = validate_windows_name(Path::new(name)).expect_err(name);
+            assert_eq!(err.code(), "InvalidName", "{}", name);
+        }
+    }
+
+    #[test]
+    fn validate_windows_name_accepts_lookalikes() {
+        let accepted = [
+            "console", "CONFIG.sys", "nullable.rs", "auxiliary", "COM0", "COM10", "LPT", "lpt10.txt", "src/com/mod.rs",
+            ".gitignore", "my con.txt", "file.txt", "a/b/c",
+        ];
+        for name in accepted {
+            assert!(validate_windows_name(Path::new(name)).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn validate_windows_name_lists_every_offending_component() {
+        let err = validate_windows_name(Path::new("con/ok/nul.txt/trailing.")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("'con'") && message.contains("'nul.txt'") && message.contains("'trailing.'"), "{}", message);
+        assert!(!message.contains("'ok'"), "{}", message);
+    }
+
+    // macOS filesystems refuse names that are not valid UTF-8
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn list_then_delete_round_trips_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let (_dir, root) = crate::test_support::temp_dir();
+        let file = root.join(std::ffi::OsStr::from_bytes(b"report-\xff.txt"));
+        let dir = root.join(std::ffi::OsStr::from_bytes(b"dir-\xfe"));
+        fs::write(&file, "x").unwrap();
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("inner.txt"), "y").unwrap();
+
+        let listing = list_directory(&root).unwrap();
+        assert_eq!(listing.items.len(), 2);
+        for item in &listing.items {
+            assert!(item.raw_path.starts_with(RAW_PATH_PREFIX), "{:?}", item.raw_path);
+            assert_eq!(encode_path(&decode_path(&item.raw_path)), item.raw_path);
+            delete_path_expecting(&decode_path(&item.raw_path), None).unwrap();
+        }
+        assert!(!file.exists() && !dir.exists());
+    }
+
+    /// Wait for an event about `path`, skipping whatever else the backend reports
+    fn wait_for_event(events: &std::sync::mpsc::Receiver<notify::Event>, path: &Path) -> bool {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while let Some(left) = deadline.checked_duration_since(std::time::Instant::now()) {
+            match events.recv_timeout(left) {
+                Ok(event) if event.paths.iter().any(|p| p == path) => return true,
+                Ok(_) => {}
+                Err(_) => return false,
+            }
+        }
+        false
+    }
+
+    fn assert_events_follow_renamed_parent(patterns: &[String]) {
+        let (_dir, root) = crate::test_support::temp_dir();
+        fs::create_dir_all(root.join("parent/sub")).unwrap();
+        fs::write(root.join("parent/sub/file.txt"), "one").unwrap();
+        let (tx, rx) = channel();
+        let mut watcher = FileWatcher::new(move |event| { let _ = tx.send(event); }, |_| {}).unwrap();
+        watcher.watch_filtered(&root, patterns, None).unwrap();
+
+        rename_path(&root.join("parent"), &root.join("renamed")).unwrap();
+        watcher.remap(&root.join("parent"), &root.join("renamed")).unwrap();
+        while rx.recv_timeout(std::time::Duration::from_millis(200)).is_ok() {}
+
+        let moved = root.join("renamed/sub/file.txt");
+        fs::write(&moved, "two").unwrap();
+        assert!(wait_for_event(&rx, &moved), "no event for {} (patterns {:?})", moved.display(), patterns);
+    }
+
+    #[test]
+    fn per_directory_watches_follow_a_renamed_parent() {
+        assert_events_follow_renamed_parent(&["node_modules".to_string()]);
+    }
+
+    #[test]
+    fn recursive_watches_follow_a_renamed_parent() {
+        assert_events_follow_renamed_parent(&[]);
+    }
+
+    #[test]
+    fn directories_added_after_the_walk_are_watched_with_their_contents() {
+        let (_dir, root) = crate::test_support::temp_dir();
+        let (tx, rx) = channel();
+        let mut watcher = FileWatcher::new(move |event| { let _ = tx.send(event); }, |_| {}).unwrap();
+        watcher.watch_filtered(&root, &["node_modules".to_string()], None).unwrap();
+
+        fs::create_dir_all(root.join("unpacked/nested/deeper")).unwrap();
+        watcher.add_directory(&root.join("unpacked")).unwrap();
+        watcher.add_directory(&root.join("unpacked/nested")).unwrap();
+        assert_eq!(watcher.stats().total_watched_directories, 2);
+        while rx.recv_timeout(std::time::Duration::from_millis(200)).is_ok() {}
+
+        let file = root.join("unpacked/nested/deeper/file.txt");
+        fs::write(&file, "one").unwrap();
+        assert!(wait_for_event(&rx, &file), "no event for {}", file.display());
+    }
+
+    /// A path nested under `root` well past MAX_PATH (260)
+    fn deep_path(root: &Path) -> PathBuf {
+        let component = "nested_directory_with_a_fairly_long_name_0123456789";
+        let mut path = root.to_path_buf();
+        while path.to_string_lossy().len() <= 300 {
+            path.push(component);
+        }
+        path
+    }
+
+    #[test]
+    fn long_paths_round_trip_through_create_read_list_and_delete() {
+        let (_dir, root) = crate::test_support::temp_dir();
+        let deep = deep_path(&root);
+        let file = deep.join("index.js");
+        assert!(file.to_string_lossy().len() > 260);
+
+        create_file(&file, "module.exports = 1;").unwrap();
+        assert_eq!(read_file_content(&file).unwrap(), "module.exports = 1;");
+        write_file_content(&file, "module.exports = 2;").unwrap();
+        assert_eq!(read_file_content(&file).unwrap(), "module.exports = 2;");
+
+        let listing = list_directory(&deep).unwrap();
+        assert_eq!(listing.items.len(), 1);
+        assert_eq!(listing.items[0].path, display_path(&file));
+        assert!(!listing.items[0].path.starts_with(r"\\?\"));
+
+        let top = root.join(deep.strip_prefix(&root).unwrap().components().next().unwrap());
+        assert_eq!(preview_delete(&top).unwrap().total_files, 1);
+        delete_path(&top).unwrap();
+        assert!(!top.exists());
+    }
+
+    #[test]
+    fn display_path_strips_extended_length_prefixes() {
+        assert_eq!(display_path(Path::new(r"\\?\C:\projects\app")), r"C:\projects\app");
+        assert_eq!(display_path(Path::new(r"\\?\UNC\server\share\app")), r"\\server\share\app");
+        assert_eq!(display_path(Path::new(r"\\server\share\app")), r"\\server\share\app");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_path_prefixes_local_and_unc_paths() {
+        let local = PathBuf::from(format!(r"C:\{}", "a".repeat(LONG_PATH_THRESHOLD)));
+        assert!(long_path(&local).to_string_lossy().starts_with(r"\\?\C:\"));
+        let unc = PathBuf::from(format!(r"\\server\share\{}", "a".repeat(LONG_PATH_THRESHOLD)));
+        assert!(long_path(&unc).to_string_lossy().starts_with(r"\\?\UNC\server\share\"));
+        assert_eq!(long_path(Path::new(r"C:\short")), PathBuf::from(r"C:\short"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn list_directory_reads_unc_share_paths() {
+        let (_dir, root) = crate::test_support::temp_dir();
+        fs::write(root.join("shared.txt"), "x").unwrap();
+        let local = root.to_string_lossy().replace('/', "\\");
+        let Some((drive, rest)) = local.split_once(":\\") else { return };
+        let unc = PathBuf::from(format!(r"\\localhost\{}$\{}", drive, rest));
+
+        let listing = list_directory(&unc).unwrap();
+        let paths: Vec<&str> = listing.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec![format!(r"{}\shared.txt", unc.display())]);
+
+        let deep = deep_path(&unc);
+        create_file(&deep.join("file.txt"), "over UNC").unwrap();
+        assert_eq!(read_file_content(&deep.join("file.txt")).unwrap(), "over UNC");
+        assert!(!list_directory(&deep).unwrap().items[0].path.starts_with(r"\\?\"));
+    }
+}
@@ -1,13 +1,21 @@
 use git2::{Repository, Status, StatusOptions, PushOptions, RemoteCallbacks, Cred, ErrorCode, Config};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 use anyhow::{Result, anyhow};
 use git2::{BranchType};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sha2::{Digest, Sha256};
 
-// Secure credential storage via OS keychain
-use keyring::Entry;
+// Secure credential storage via OS keychain, with an encrypted-file fallback
+use crate::commit_message::{self, CommitMessage};
+use crate::credentials::{CredentialError, CredentialStore};
+use crate::prepush::PrePushStepResult;
+use crate::repo_health::{self, CorruptionKind};
+use crate::secrets::SecretFinding;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GitStatus {
@@ -15,13 +23,39 @@ pub struct GitStatus {
     pub modified: Vec<String>,
     pub untracked: Vec<String>,
     pub staged: Vec<String>,
+    /// Tracked files missing from the working tree
+    pub deleted: Vec<String>,
+    /// Display names for entries whose path is not valid Unicode; those entries are listed
+    /// above in the lossless form produced by `fs::encode_path`
+    #[serde(default)]
+    pub display_names: HashMap<String, String>,
+    /// Tracked files outside a sparse checkout; they are absent on purpose, not deleted
+    #[serde(default)]
+    pub not_materialized: usize,
+    /// Listed paths marked `linguist-generated` in .gitattributes
+    #[serde(default)]
+    pub generated: Vec<String>,
+    /// Listed paths whose diff .gitattributes suppresses (`-diff` or generated); collapsed by default
+    #[serde(default)]
+    pub diff_suppressed: Vec<String>,
+    /// Listed paths that are symlinks pointing outside the working tree; staging them needs
+    /// `allow_symlinks_outside_repo`
+    #[serde(default)]
+    pub escaping_symlinks: Vec<String>,
+    /// Added with `git add -N`: tracked, with no content staged yet. Listed here instead of
+    /// `staged` or `modified`.
+    #[serde(default)]
+    pub intent_to_add: Vec<String>,
     pub is_git_repo: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GitCommit {
     pub hash: String,
+    /// Raw message; superseded by `summary`/`body`/`trailers` and kept for one release
     pub message: String,
+    #[serde(flatten)]
+    pub parsed: CommitMessage,
     pub author: String,
     pub timestamp: i64,
     // Flags to help UI color commits based on local vs remote
@@ -29,6 +63,19 @@ pub struct GitCommit {
     pub is_on_upstream: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitBranch {
+    /// Short name: "main" for a local branch, "origin/main" for a remote one
+    pub name: String,
+    pub is_current: bool,
+    pub is_remote: bool,
+    /// Remote-tracking branch of a local branch, e.g. "origin/main"
+    pub upstream: Option<String>,
+    /// None for the current branch of an unborn HEAD
+    pub tip_short_hash: Option<String>,
+    pub tip_summary: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GitConfig {
     pub user_name: Option<String>,
@@ -44,6 +91,346 @@ pub struct GitInitResult {
     pub git_config: Option<GitConfig>,
 }
 
+/// The remote could not be reached at all, as opposed to rejecting the operation;
+/// callers can downcast to this to decide whether retrying later makes sense
+#[derive(Debug, Clone)]
+pub struct NetworkUnavailable {
+    pub message: String,
+}
+
+impl std::fmt::Display for NetworkUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Remote unreachable: {}", self.message)
+    }
+}
+
+impl std::error::Error for NetworkUnavailable {}
+
+/// The repository has no working tree (a bare repository), so the operation has nothing to act on
+#[derive(Debug, Clone)]
+pub struct BareRepository {
+    pub git_dir: String,
+}
+
+impl std::fmt::Display for BareRepository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is a bare repository without a working tree; only history can be browsed", self.git_dir)
+    }
+}
+
+impl std::error::Error for BareRepository {}
+
+/// The working tree, or a `BareRepository` error for operations that need one
+pub fn require_workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir().ok_or_else(|| anyhow!(BareRepository { git_dir: crate::fs::display_path(repo.path()) }))
+}
+
+fn is_network_error(error: &git2::Error) -> bool {
+    let message = error.message().to_lowercase();
+    let transport_class = matches!(error.class(), git2::ErrorClass::Net | git2::ErrorClass::Os | git2::ErrorClass::Http);
+    (transport_class && !message.contains("401") && !message.contains("403"))
+        || ["could not resolve", "failed to resolve", "timed out", "connection refused", "network is unreachable", "failed to connect"]
+            .iter()
+            .any(|needle| message.contains(needle))
+}
+
+/// Index paths flagged skip-worktree by a sparse checkout; empty when sparse checkout is off.
+/// libgit2 ignores the flag, so status would otherwise report these files as deleted.
+pub fn skip_worktree_paths(repo: &Repository) -> std::collections::HashSet<Vec<u8>> {
+    let sparse = repo.config().and_then(|c| c.get_bool("core.sparseCheckout")).unwrap_or(false);
+    if !sparse {
+        return Default::default();
+    }
+    let Ok(index) = repo.index() else { return Default::default() };
+    index
+        .iter()
+        .filter(|entry| entry.flags_extended & git2::IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0)
+        .map(|entry| entry.path)
+        .collect()
+}
+
+fn is_intent_to_add(entry: &git2::IndexEntry) -> bool {
+    entry.flags_extended & git2::IndexEntryExtendedFlag::INTENT_TO_ADD.bits() != 0
+}
+
+/// Index paths added with `git add -N`
+pub fn intent_to_add_paths(repo: &Repository) -> std::collections::HashSet<Vec<u8>> {
+    let Ok(index) = repo.index() else { return Default::default() };
+    index.iter().filter(is_intent_to_add).map(|entry| entry.path).collect()
+}
+
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+fn empty_blob_id() -> git2::Oid {
+    git2::Oid::hash_object(git2::ObjectType::Blob, b"").unwrap_or_else(|_| git2::Oid::zero())
+}
+
+/// Write `index` as a tree, leaving out intent-to-add entries that still have no staged
+/// content, so a commit never records them as empty files
+fn write_commit_tree(repo: &Repository, index: &mut git2::Index) -> Result<git2::Oid> {
+    let empty = empty_blob_id();
+    let placeholder = |entry: &git2::IndexEntry| is_intent_to_add(entry) && entry.id == empty;
+    if !index.iter().any(|entry| placeholder(&entry)) {
+        return Ok(index.write_tree()?);
+    }
+    let mut without = git2::Index::new()?;
+    for entry in index.iter().filter(|entry| !placeholder(entry)) {
+        without.add(&entry)?;
+    }
+    Ok(without.write_tree_to(repo)?)
+}
+
+/// Which changes `commit_changes` records
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitInclude {
+    /// Whatever is already staged
+    #[default]
+    Staged,
+    /// Stage every modified or deleted tracked file first, like `git commit -a`
+    AllTracked,
+    /// Only the given paths, like `git commit -- <paths>`
+    Paths,
+}
+
+/// Workdir-relative paths for a `CommitInclude::Paths` commit; directories expand to the files
+/// under them, including tracked files that were deleted
+fn expand_commit_paths(index: &git2::Index, workdir: &Path, paths: &[String]) -> Result<Vec<PathBuf>> {
+    let mut selected = std::collections::BTreeSet::new();
+    for path in paths {
+        let path = crate::fs::decode_path(path);
+        let relative = path.strip_prefix(workdir).unwrap_or(&path);
+        if workdir.join(relative).is_dir() {
+            for file in list_unignored_files(workdir, &workdir.join(relative))? {
+                selected.insert(crate::fs::decode_path(&file));
+            }
+            let prefix = git_dir_prefix(relative);
+            for entry in index.iter() {
+                if prefix == b"/" || entry.path.starts_with(&prefix) {
+                    selected.insert(crate::fs::path_from_git_bytes(&entry.path));
+                }
+            }
+        } else {
+            selected.insert(relative.to_path_buf());
+        }
+    }
+    Ok(selected.into_iter().collect())
+}
+
+/// A workdir-relative path as sent to the frontend, `/`-separated; names that are not valid
+/// Unicode go through `encode_path` so they can be decoded back to the same bytes
+fn git_path_string(relative: &Path) -> String {
+    match relative.to_str() {
+        Some(path) => path.replace('\\', "/"),
+        None => crate::fs::encode_path(relative),
+    }
+}
+
+/// Git bytes of a directory with a trailing `/`, for matching the index entries under it
+fn git_dir_prefix(relative: &Path) -> Vec<u8> {
+    let mut prefix = crate::fs::path_to_git_bytes(relative);
+    while prefix.last() == Some(&b'/') {
+        prefix.pop();
+    }
+    prefix.push(b'/');
+    prefix
+}
+
+/// Tracked-file count from which `get_status_batched` scans in batches
+const LARGE_STATUS_INDEX_ENTRIES: usize = 20_000;
+
+// Top-level files scanned together per status batch
+const STATUS_FILE_BATCH: usize = 500;
+
+/// Progress of a batched status scan, emitted as `git-status-progress`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitStatusProgress {
+    pub project_path: String,
+    pub batches_done: usize,
+    pub batches_total: usize,
+    pub entries_scanned: usize,
+    pub elapsed_ms: u64,
+    /// Buckets found by the batch that just finished, for the SCM view to merge; None on the final event
+    pub partial: Option<GitStatus>,
+    /// Set on the last event, whose `elapsed_ms` is the total scan time
+    pub complete: bool,
+}
+
+/// One status entry, detached from the `Statuses` list so batches can be merged
+#[derive(Clone)]
+struct RawStatus {
+    bytes: Vec<u8>,
+    path: Option<String>,
+    status: Status,
+}
+
+impl From<&git2::StatusEntry<'_>> for RawStatus {
+    fn from(entry: &git2::StatusEntry<'_>) -> Self {
+        Self { bytes: entry.path_bytes().to_vec(), path: entry.path().map(str::to_string), status: entry.status() }
+    }
+}
+
+fn status_options() -> StatusOptions {
+    let mut opts = StatusOptions::new();
+    opts.include_ignored(false)
+        .include_untracked(true)
+        .recurse_untracked_dirs(false);
+    opts
+}
+
+/// Current branch name; an unborn branch (newly initialized repo) reports "main"
+fn current_branch(repo: &Repository) -> Result<String> {
+    match repo.head() {
+        Ok(head) => Ok(head.shorthand().unwrap_or("HEAD").to_string()),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => Ok("main".to_string()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Sort status entries into the `GitStatus` buckets
+fn build_status(repo: &Repository, workdir: &Path, branch: String, entries: Vec<RawStatus>) -> GitStatus {
+    let mut modified = Vec::new();
+    let mut untracked = Vec::new();
+    let mut staged = Vec::new();
+    let mut deleted = Vec::new();
+
+    let mut display_names = HashMap::new();
+    let sparse = skip_worktree_paths(repo);
+    let mut not_materialized = 0;
+    let mut generated = Vec::new();
+    let mut diff_suppressed = Vec::new();
+    let mut escaping_symlinks = Vec::new();
+    let intent = intent_to_add_paths(repo);
+    let mut intent_to_add = Vec::new();
+    for entry in entries {
+        if entry.status == Status::WT_DELETED && sparse.contains(&entry.bytes) {
+            not_materialized += 1;
+            continue;
+        }
+        let path = match &entry.path {
+            Some(path) => path.clone(),
+            None => {
+                let raw = crate::fs::encode_path(&crate::fs::path_from_git_bytes(&entry.bytes));
+                display_names.insert(raw.clone(), String::from_utf8_lossy(&entry.bytes).to_string());
+                raw
+            }
+        };
+        if let Some(utf8_path) = &entry.path {
+            let attributes = diff_attributes(repo, Path::new(utf8_path));
+            if attributes.is_generated {
+                generated.push(path.clone());
+            }
+            if attributes.diff_suppressed {
+                diff_suppressed.push(path.clone());
+            }
+            if entry.status.intersects(Status::WT_NEW | Status::WT_MODIFIED | Status::WT_TYPECHANGE | Status::INDEX_NEW | Status::INDEX_TYPECHANGE)
+                && escaping_symlink(workdir, Path::new(utf8_path)).is_some()
+            {
+                escaping_symlinks.push(path.clone());
+            }
+        }
+
+        match entry.status {
+            s if intent.contains(&entry.bytes) && !s.contains(Status::WT_DELETED) => intent_to_add.push(path),
+            s if s.contains(Status::WT_MODIFIED) => modified.push(path),
+            s if s.contains(Status::WT_DELETED) => deleted.push(path),
+            s if s.contains(Status::WT_NEW) => untracked.push(path),
+            s if s.contains(Status::INDEX_MODIFIED) => staged.push(path),
+            s if s.contains(Status::INDEX_NEW) => staged.push(path),
+            _ => {}
+        }
+    }
+
+    GitStatus {
+        branch,
+        modified,
+        untracked,
+        staged,
+        deleted,
+        display_names,
+        not_materialized,
+        generated,
+        diff_suppressed,
+        escaping_symlinks,
+        intent_to_add,
+        is_git_repo: true,
+    }
+}
+
+/// The scan behind `get_status_batched`: one status pass per top-level directory and per
+/// `STATUS_FILE_BATCH` top-level files, merged back into single-pass order
+fn scan_status_in_batches(repo: &Repository, workdir: &Path, index: &git2::Index, repo_path: &Path, mut on_progress: impl FnMut(GitStatusProgress)) -> Result<GitStatus> {
+    let started = std::time::Instant::now();
+    let branch = current_branch(repo)?;
+
+    // Every status entry lives under exactly one top-level name, on disk or in the index
+    let mut top_level: std::collections::BTreeSet<PathBuf> = fs::read_dir(workdir)?
+        .filter_map(|e| e.ok())
+        .map(|e| PathBuf::from(e.file_name()))
+        .filter(|name| name != Path::new(".git"))
+        .collect();
+    for entry in index.iter() {
+        let first = entry.path.split(|b| *b == b'/').next().unwrap_or(&entry.path);
+        top_level.insert(crate::fs::path_from_git_bytes(first));
+    }
+    let (directories, files): (Vec<PathBuf>, Vec<PathBuf>) = top_level.into_iter().partition(|name| workdir.join(name).is_dir());
+    let mut batches: Vec<Vec<PathBuf>> = directories.into_iter().map(|dir| vec![dir]).collect();
+    batches.extend(files.chunks(STATUS_FILE_BATCH).map(|chunk| chunk.to_vec()));
+
+    let mut all = Vec::new();
+    let batches_total = batches.len();
+    for (done, batch) in batches.iter().enumerate() {
+        let mut opts = status_options();
+        opts.disable_pathspec_match(true);
+        for pathspec in batch {
+            opts.pathspec(pathspec.as_path());
+        }
+        let statuses = repo.statuses(Some(&mut opts))?;
+        let entries: Vec<RawStatus> = statuses.iter().map(|entry| RawStatus::from(&entry)).collect();
+        all.extend(entries.iter().cloned());
+        on_progress(GitStatusProgress {
+            project_path: crate::fs::display_path(repo_path),
+            batches_done: done + 1,
+            batches_total,
+            entries_scanned: all.len(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            partial: Some(build_status(repo, workdir, branch.clone(), entries)),
+            complete: false,
+        });
+    }
+
+    // A single pass lists entries in path order, case-insensitively where core.ignorecase is set
+    let ignore_case = repo.config().and_then(|c| c.get_bool("core.ignorecase")).unwrap_or(false);
+    if ignore_case {
+        all.sort_by(|a, b| a.bytes.to_ascii_lowercase().cmp(&b.bytes.to_ascii_lowercase()).then_with(|| a.bytes.cmp(&b.bytes)));
+    } else {
+        all.sort_by(|a, b| a.bytes.cmp(&b.bytes));
+    }
+    let entries_scanned = all.len();
+    let status = build_status(repo, workdir, branch, all);
+    on_progress(GitStatusProgress {
+        project_path: crate::fs::display_path(repo_path),
+        batches_done: batches_total,
+        batches_total,
+        entries_scanned,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        partial: None,
+        complete: true,
+    });
+    Ok(status)
+}
+
 pub struct GitManager {
     repo: Option<Repository>,
 }
@@ -58,6 +445,13 @@ impl GitManager {
         self.repo.is_some()
     }
 
+    /// Reload the index if it changed on disk, for a handle kept across commands
+    pub fn refresh_index(&self) {
+        if let Some(mut index) = self.repo.as_ref().and_then(|repo| repo.index().ok()) {
+            let _ = index.read(false);
+        }
+    }
+
     pub fn get_status(&self, _repo_path: &Path) -> Result<GitStatus> {
         let repo = if let Some(ref repo) = self.repo {
             repo
@@ -67,61 +461,53 @@ impl GitManager {
                 modified: Vec::new(),
                 untracked: Vec::new(),
                 staged: Vec::new(),
+                deleted: Vec::new(),
+                display_names: HashMap::new(),
+                not_materialized: 0,
+                generated: Vec::new(),
+                diff_suppressed: Vec::new(),
+                escaping_symlinks: Vec::new(),
+                intent_to_add: Vec::new(),
                 is_git_repo: false,
             });
         };
 
-        // Get current branch - handle unborn branch (newly initialized repo)
-        let branch = match repo.head() {
-            Ok(head) => head.shorthand().unwrap_or("HEAD").to_string(),
-            Err(e) => {
-                // Check if it's an unborn branch error
-                if e.code() == ErrorCode::UnbornBranch {
-                    "main".to_string() // Default to 'main' for new repositories
-                } else {
-                    return Err(e.into());
-                }
-            }
-        };
-
-        // Get status
-        let mut opts = StatusOptions::new();
-        opts.include_ignored(false)
-            .include_untracked(true)
-            .recurse_untracked_dirs(false);
+        let workdir = require_workdir(repo)?;
 
+        let branch = current_branch(repo)?;
+        let mut opts = status_options();
         let statuses = repo.statuses(Some(&mut opts))?;
+        let entries = statuses.iter().map(|entry| RawStatus::from(&entry)).collect();
+        Ok(build_status(repo, workdir, branch, entries))
+    }
 
-        let mut modified = Vec::new();
-        let mut untracked = Vec::new();
-        let mut staged = Vec::new();
-
-        for entry in statuses.iter() {
-            let path = entry.path().unwrap_or("").to_string();
-
-            match entry.status() {
-                s if s.contains(Status::WT_MODIFIED) => modified.push(path),
-                s if s.contains(Status::WT_NEW) => untracked.push(path),
-                s if s.contains(Status::INDEX_MODIFIED) => staged.push(path),
-                s if s.contains(Status::INDEX_NEW) => staged.push(path),
-                _ => {}
-            }
+    /// Like `get_status`, but for a repository with at least `LARGE_STATUS_INDEX_ENTRIES` tracked
+    /// files the scan runs per top-level entry, reporting each batch's buckets through `on_progress`
+    /// as it finishes. The result is the same as a single pass.
+    pub fn get_status_batched(&self, repo_path: &Path, on_progress: impl FnMut(GitStatusProgress)) -> Result<GitStatus> {
+        let Some(repo) = self.repo.as_ref() else { return self.get_status(repo_path) };
+        let workdir = require_workdir(repo)?;
+        let index = repo.index()?;
+        if index.len() < LARGE_STATUS_INDEX_ENTRIES {
+            return self.get_status(repo_path);
         }
-
-        Ok(GitStatus {
-            branch,
-            modified,
-            untracked,
-            staged,
-            is_git_repo: true,
-        })
+        scan_status_in_batches(repo, workdir, &index, repo_path, on_progress)
     }
 
-    pub fn stage_file(&self, file_path: &str) -> Result<()> {
+    /// Stage a file, or every non-ignored file under a directory. Unless `allow_symlinks_outside_repo`,
+    /// refuses with `SuspiciousSymlink` when that would stage a symlink pointing outside the working tree.
+    pub fn stage_file(&self, file_path: &str, allow_symlinks_outside_repo: bool) -> Result<()> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        let workdir = require_workdir(repo)?;
 
+        let target = crate::fs::decode_path(file_path);
+        let paths: Vec<PathBuf> = if workdir.join(&target).is_dir() {
+            list_unignored_files(workdir, &workdir.join(&target))?.iter().map(|p| crate::fs::decode_path(p)).collect()
+        } else {
+            vec![target]
+        };
         let mut index = repo.index()?;
-        index.add_path(Path::new(file_path))?;
+        add_to_index(&mut index, workdir, &paths, allow_symlinks_outside_repo)?;
         index.write()?;
 
         Ok(())
@@ -129,21 +515,201 @@ impl GitManager {
 
     pub fn unstage_file(&self, file_path: &str) -> Result<()> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        require_workdir(repo)?;
 
         let mut index = repo.index()?;
-        index.remove_path(Path::new(file_path))?;
+        index.remove_path(&crate::fs::decode_path(file_path))?;
         index.write()?;
 
         Ok(())
     }
 
-    pub fn commit(&self, message: &str) -> Result<String> {
+    /// Like `git add -N`: track untracked files (or every untracked, non-ignored file under a
+    /// directory) without staging their content. Returns the paths that were marked.
+    pub fn mark_intent_to_add(&self, paths: &[String]) -> Result<Vec<String>> {
+        let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        let workdir = require_workdir(repo)?;
+
+        let mut index = repo.index()?;
+        // The index only accepts entries whose object exists
+        let empty = repo.blob(b"")?;
+        let mut files = Vec::new();
+        for path in paths {
+            let target = crate::fs::decode_path(path);
+            if workdir.join(&target).is_dir() {
+                files.extend(list_unignored_files(workdir, &workdir.join(&target))?);
+            } else {
+                files.push(git_path_string(&target));
+            }
+        }
+        // Not `add_to_index`: the entries carry no content yet, but a later commit would follow the link
+        let decoded: Vec<PathBuf> = files.iter().map(|f| crate::fs::decode_path(f)).collect();
+        refuse_escaping_symlinks(workdir, &decoded)?;
+
+        let mut marked = Vec::new();
+        for file in files {
+            let file_path = crate::fs::decode_path(&file);
+            if index.get_path(&file_path, 0).is_some() {
+                continue;
+            }
+            let metadata = fs::symlink_metadata(workdir.join(&file_path)).map_err(|e| anyhow!("Failed to read {}: {}", file_path.display(), e))?;
+            let mode = if metadata.file_type().is_symlink() {
+                0o120000
+            } else if is_executable(&metadata) {
+                0o100755
+            } else {
+                0o100644
+            };
+            let entry_path = crate::fs::path_to_git_bytes(&file_path);
+            // Zero stat data, so the working tree copy is always compared by content
+            index.add(&git2::IndexEntry {
+                ctime: git2::IndexTime::new(0, 0),
+                mtime: git2::IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id: empty,
+                flags: entry_path.len().min(0xfff) as u16,
+                flags_extended: git2::IndexEntryExtendedFlag::INTENT_TO_ADD.bits(),
+                path: entry_path,
+            })?;
+            marked.push(file);
+        }
+        index.write()?;
+        Ok(marked)
+    }
+
+    /// Drop intent-to-add entries under `paths` from the index, leaving the files untracked.
+    /// Entries with staged content are left alone. Returns the paths that were unmarked.
+    pub fn unmark_intent_to_add(&self, paths: &[String]) -> Result<Vec<String>> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        require_workdir(repo)?;
 
         let mut index = repo.index()?;
-        // Ensure index is written to disk before creating tree
+        let empty = empty_blob_id();
+        let prefixes: Vec<Vec<u8>> = paths.iter().map(|p| git_dir_prefix(&crate::fs::decode_path(p))).collect();
+        let unmarked: Vec<PathBuf> = index
+            .iter()
+            .filter(|entry| is_intent_to_add(entry) && entry.id == empty)
+            .filter(|entry| {
+                prefixes.iter().any(|prefix| prefix == b"/" || entry.path.starts_with(prefix) || entry.path[..] == prefix[..prefix.len() - 1])
+            })
+            .map(|entry| crate::fs::path_from_git_bytes(&entry.path))
+            .collect();
+        for path in &unmarked {
+            index.remove_path(path)?;
+        }
         index.write()?;
-        let tree_id = index.write_tree()?;
+        Ok(unmarked.iter().map(|path| git_path_string(path)).collect())
+    }
+
+    pub fn commit(&self, message: &str) -> Result<String> {
+        self.commit_with(message, CommitInclude::Staged, &[])
+    }
+
+    /// Commit according to `include`; fails with `GitIndexError::NothingToCommit` rather than
+    /// recording a commit whose tree matches HEAD
+    pub fn commit_with(&self, message: &str, include: CommitInclude, paths: &[String]) -> Result<String> {
+        let tree_id = self.prepare_commit_tree(include, paths)?;
+        self.persist_index()?;
+        self.commit_tree(message, tree_id)
+    }
+
+    /// Stage what `include` selects in the in-memory index and write the tree the commit would
+    /// record. Nothing reaches the index file until `persist_index`; on error the in-memory
+    /// changes are already discarded.
+    pub fn prepare_commit_tree(&self, include: CommitInclude, paths: &[String]) -> Result<git2::Oid> {
+        let prepared = self.stage_commit_tree(include, paths);
+        if prepared.is_err() {
+            self.discard_index_changes();
+        }
+        prepared
+    }
+
+    /// Write the index `prepare_commit_tree` staged to disk
+    pub fn persist_index(&self) -> Result<()> {
+        let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        repo.index()?.write()?;
+        Ok(())
+    }
+
+    /// Drop staging `prepare_commit_tree` did in memory, going back to the index file on disk
+    pub fn discard_index_changes(&self) {
+        if let Some(mut index) = self.repo.as_ref().and_then(|repo| repo.index().ok()) {
+            let _ = index.read(true);
+        }
+    }
+
+    fn stage_commit_tree(&self, include: CommitInclude, paths: &[String]) -> Result<git2::Oid> {
+        let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+        require_workdir(repo)?;
+
+        let mut index = repo.index()?;
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let tree_id = match include {
+            CommitInclude::Staged => {
+                // Ensure index is written to disk before creating tree
+                index.write()?;
+                write_commit_tree(repo, &mut index)?
+            }
+            CommitInclude::AllTracked => {
+                // Files a sparse checkout left out are absent on disk, not deleted
+                let sparse = skip_worktree_paths(repo);
+                let mut skip_sparse = |path: &Path, _: &[u8]| -> i32 {
+                    let key = path.to_string_lossy().replace('\\', "/").into_bytes();
+                    if sparse.contains(&key) { 1 } else { 0 }
+                };
+                refuse_escaping_symlinks(require_workdir(repo)?, &changed_paths(repo, false)?)?;
+                index.update_all(["*"].iter(), Some(&mut skip_sparse))?;
+                write_commit_tree(repo, &mut index)?
+            }
+            CommitInclude::Paths => {
+                let workdir = require_workdir(repo)?;
+                let selected = expand_commit_paths(&index, workdir, paths)?;
+                if selected.is_empty() {
+                    return Err(anyhow!("No paths given to commit"));
+                }
+                let (present, missing): (Vec<&PathBuf>, Vec<&PathBuf>) = selected.iter().partition(|path| workdir.join(path).is_file());
+                add_to_index(&mut index, workdir, &present, false)?;
+                for path in missing {
+                    if index.get_path(path, 0).is_some() {
+                        index.remove_path(path)?;
+                    }
+                }
+
+                // Like `git commit -- <paths>`: only these paths change relative to HEAD, and
+                // anything else already staged stays staged for a later commit
+                let mut only = git2::Index::new()?;
+                if let Some(tree) = &head_tree {
+                    only.read_tree(tree)?;
+                }
+                for path in &selected {
+                    match index.get_path(path, 0) {
+                        Some(entry) => only.add(&entry)?,
+                        None => {
+                            let _ = only.remove_path(path);
+                        }
+                    }
+                }
+                only.write_tree_to(repo)?
+            }
+        };
+        let unchanged = match &head_tree {
+            Some(head_tree) => head_tree.id() == tree_id,
+            None => repo.find_tree(tree_id)?.is_empty(),
+        };
+        if unchanged {
+            return Err(GitIndexError::NothingToCommit.into());
+        }
+        Ok(tree_id)
+    }
+
+    /// Record `tree_id` as a commit on HEAD
+    pub fn commit_tree(&self, message: &str, tree_id: git2::Oid) -> Result<String> {
+        let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
         let tree = repo.find_tree(tree_id)?;
 
         let sig = repo.signature()?;
@@ -188,7 +754,8 @@ impl GitManager {
         Ok(commit_id.to_string())
     }
 
-    pub fn get_recent_commits(&self, limit: usize) -> Result<Vec<GitCommit>> {
+    /// `issue_patterns` recognise issue references ("Fixes #12") as trailers
+    pub fn get_recent_commits(&self, limit: usize, issue_patterns: &[String]) -> Result<Vec<GitCommit>> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
 
         // Check if we have any commits at all (handle unborn branch)
@@ -213,6 +780,8 @@ impl GitManager {
                     }
                 }
 
+                let issue_patterns = commit_message::compile_issue_patterns(issue_patterns);
+
                 // We have commits, proceed normally
                 let mut revwalk = repo.revwalk()?;
                 revwalk.push_head()?;
@@ -221,9 +790,11 @@ impl GitManager {
                     let oid = oid?;
                     let commit = repo.find_commit(oid)?;
 
+                    let message = commit.message().unwrap_or("").to_string();
                     commits.push(GitCommit {
                         hash: oid.to_string()[..8].to_string(), // Show short hash
-                        message: commit.message().unwrap_or("").to_string(),
+                        parsed: commit_message::parse(&message, &issue_patterns),
+                        message,
                         author: commit.author().name().unwrap_or("Unknown").to_string(),
                         timestamp: commit.time().seconds(),
                         is_on_head: true,
@@ -244,7 +815,62 @@ impl GitManager {
         Ok(commits)
     }
 
-    pub fn push(&self, remote_name: &str, branch_name: &str, username: Option<&str>, password: Option<&str>) -> Result<()> {
+    /// Local branches (current first, then by name) followed by remote ones. With an unborn
+    /// HEAD the list holds just the branch HEAD points at. Symbolic refs such as `origin/HEAD`
+    /// are left out, since they only repeat another branch.
+    pub fn list_branches(&self) -> Result<Vec<GitBranch>> {
+        let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
+
+        if matches!(repo.head(), Err(e) if e.code() == ErrorCode::UnbornBranch) {
+            let head = repo.find_reference("HEAD")?;
+            let name = head.symbolic_target().and_then(|t| t.strip_prefix("refs/heads/")).unwrap_or("main");
+            return Ok(vec![GitBranch {
+                name: name.to_string(),
+                is_current: true,
+                is_remote: false,
+                upstream: None,
+                tip_short_hash: None,
+                tip_summary: None,
+            }]);
+        }
+
+        let mut branches = Vec::new();
+        for (branch, branch_type) in repo.branches(None)?.flatten() {
+            let reference = branch.get();
+            if reference.symbolic_target().is_some() {
+                continue;
+            }
+            let Some(name) = branch.name().ok().flatten().map(str::to_string) else { continue };
+            let is_remote = branch_type == BranchType::Remote;
+            if is_remote && name.ends_with("/HEAD") {
+                continue;
+            }
+            let commit = reference.peel_to_commit().ok();
+            branches.push(GitBranch {
+                is_current: !is_remote && branch.is_head(),
+                upstream: if is_remote { None } else { branch.upstream().ok().and_then(|u| u.name().ok().flatten().map(str::to_string)) },
+                tip_short_hash: commit.as_ref().map(|c| c.id().to_string()[..8].to_string()),
+                tip_summary: commit.as_ref().map(|c| c.summary().unwrap_or("").to_string()),
+                is_remote,
+                name,
+            });
+        }
+        branches.sort_by(|a, b| a.is_remote.cmp(&b.is_remote).then(b.is_current.cmp(&a.is_current)).then_with(|| a.name.cmp(&b.name)));
+        Ok(branches)
+    }
+
+    /// `push_notes` also pushes the default notes ref when it exists locally
+    pub fn push(&self, remote_name: &str, branch_name: &str, username: Option<&str>, password: Option<&str>, store: &CredentialStore, push_notes: bool) -> Result<()> {
+        self.push_source(remote_name, branch_name, None, username, password, store, push_notes)
+    }
+
+    /// Push `commit` to the remote branch rather than whatever the local branch points at now
+    pub fn push_commit(&self, remote_name: &str, branch_name: &str, commit: git2::Oid, store: &CredentialStore) -> Result<()> {
+        self.push_source(remote_name, branch_name, Some(commit), None, None, store, false)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_source(&self, remote_name: &str, branch_name: &str, commit: Option<git2::Oid>, username: Option<&str>, password: Option<&str>, store: &CredentialStore, push_notes: bool) -> Result<()> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
 
         // Check if there are any commits to push
@@ -260,8 +886,11 @@ impl GitManager {
             Err(_) => return Err(anyhow!(format!("Remote '{}' not found. Make sure to add the remote first: git remote add {} <url>", remote_name, remote_name))),
         };
 
-        // Resolve credentials: explicit > stored > none
-        let stored = load_stored_credentials(repo, remote_name).ok();
+        // Resolve credentials: explicit > per-remote > profile; the callbacks then fall back to the SSH agent and defaults
+        let (stored, store_error) = match load_stored_credentials(store, repo, remote_name) {
+            Ok(stored) => (stored, None),
+            Err(e) => (None, e.downcast_ref::<CredentialError>().cloned()),
+        };
         let resolved_username = username
             .map(|s| s.to_string())
             .or_else(|| stored.as_ref().map(|(u, _)| u.clone()));
@@ -270,26 +899,7 @@ impl GitManager {
             .or_else(|| stored.as_ref().map(|(_, p)| p.clone()));
 
         // Set up callbacks for authentication (support SSH agent, HTTPS with user/pass or PAT, and default creds)
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.credentials(move |_url, username_from_url, allowed_types| {
-            // If caller provided or stored username/password (or token), prefer that for HTTPS
-            if allowed_types.is_user_pass_plaintext() {
-                if let (Some(u), Some(p)) = (resolved_username.as_deref(), resolved_password.as_deref()) {
-                    return Cred::userpass_plaintext(u, p);
-                }
-            }
-            // Try SSH agent if allowed
-            if allowed_types.is_ssh_key() {
-                if let Some(u) = username_from_url {
-                    if let Ok(cred) = Cred::ssh_key_from_agent(u) { return Ok(cred); }
-                }
-                if let Some(u) = resolved_username.as_deref() {
-                    if let Ok(cred) = Cred::ssh_key_from_agent(u) { return Ok(cred); }
-                }
-            }
-            // Fallback to default credentials (may use OS helpers)
-            Cred::default()
-        });
+        let callbacks = credential_callbacks(resolved_username, resolved_password);
 
         // Set up push options
         let mut push_options = PushOptions::new();
@@ -302,15 +912,27 @@ impl GitManager {
         }
 
         // Push the branch
-        let refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
-        if let Err(e) = remote.push(&[&refspec], Some(&mut push_options)) {
+        let source = match commit {
+            Some(oid) => oid.to_string(),
+            None => format!("refs/heads/{}", branch_name),
+        };
+        let mut refspecs = vec![format!("{}:refs/heads/{}", source, branch_name)];
+        if push_notes && repo.find_reference(DEFAULT_NOTES_REF).is_ok() {
+            refspecs.push(format!("{}:{}", DEFAULT_NOTES_REF, DEFAULT_NOTES_REF));
+        }
+        if let Err(e) = remote.push(&refspecs, Some(&mut push_options)) {
             let error_msg = e.message();
             if error_msg.contains("authentication") || error_msg.contains("403") || error_msg.contains("401") {
+                if let Some(store_error) = store_error {
+                    return Err(anyhow!("Authentication failed. Stored credentials could not be loaded: {}", store_error));
+                }
                 return Err(anyhow!("Authentication failed. Please check your credentials or Personal Access Token."));
             } else if error_msg.contains("non-fast-forward") {
                 return Err(anyhow!("Push rejected - remote branch has diverged. Pull first to merge changes."));
             } else if error_msg.contains("no upstream") {
                 return Err(anyhow!("Branch '{}' has no upstream branch set. The push may have succeeded but you should set the upstream: git branch --set-upstream-to=origin/{}", branch_name, branch_name));
+            } else if is_network_error(&e) {
+                return Err(NetworkUnavailable { message: error_msg.to_string() }.into());
             } else {
                 return Err(anyhow!("Push failed: {}", error_msg));
             }
@@ -325,7 +947,8 @@ impl GitManager {
         Ok(())
     }
 
-    pub fn pull(&self, remote_name: &str, _branch_name: &str) -> Result<()> {
+    /// `fetch_notes` also fetches the default notes ref; notes are never fetched implicitly
+    pub fn pull(&self, remote_name: &str, _branch_name: &str, fetch_notes: bool) -> Result<()> {
         let repo = self.repo.as_ref().ok_or_else(|| anyhow!("Not a git repository"))?;
 
         // Find the remote
@@ -339,8 +962,16 @@ impl GitManager {
 
         // Fetch from remote
         let refspecs = remote.fetch_refspecs()?;
-        let refspecs: Vec<&str> = refspecs.iter().filter_map(|s| s).collect();
+        let mut refspecs: Vec<String> = refspecs.iter().flatten().map(String::from).collect();
+        let tracking_notes = remote_notes_ref(remote_name, DEFAULT_NOTES_REF);
+        if fetch_notes {
+            // Forced into a tracking ref: the remote's notes may have diverged from ours
+            refspecs.push(format!("+{}:{}", DEFAULT_NOTES_REF, tracking_notes));
+        }
         remote.fetch(&refspecs, None, None)?;
+        if fetch_notes {
+            merge_notes(repo, &tracking_notes, DEFAULT_NOTES_REF)?;
+        }
 
         // For now, we'll just fetch. Merging would require more complex logic
         // to handle conflicts and different merge strategies
@@ -348,325 +979,4021 @@ impl GitManager {
     }
 }
 
-/// Create a keyring entry identifier based on remote URL and username
-fn keyring_entry(remote_url: &str, username: &str) -> Result<Entry, keyring::Error> {
-    // Use remote URL as service namespace; include app prefix
-    let service = format!("agentic-ide:git:{}", remote_url);
-    Entry::new(&service, username)
+// Attempts made while `.git/index.lock` is held by another process, and the pause between them
+const INDEX_LOCK_RETRIES: u32 = 5;
+const INDEX_LOCK_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Failures specific to reading and writing the index
+#[derive(Debug, Clone)]
+pub enum GitIndexError {
+    IndexLocked { holder_hint: String },
+    /// Files git has no committed copy of, or a project that is not a repository
+    NotRestorable { paths: Vec<String>, hint: String },
+    /// The commit would record no changes
+    NothingToCommit,
+    /// A merged file still contains conflict markers, so it was not written or staged
+    ConflictMarkersRemain { path: String, markers: Vec<crate::merge_editor::ConflictMarker> },
+    /// Staging would add symlinks pointing outside the working tree; retry with `allow_symlinks_outside_repo`
+    SuspiciousSymlink { links: Vec<EscapingSymlink> },
 }
 
-/// Load stored credentials (username, token) for a given remote
-fn load_stored_credentials(repo: &Repository, remote_name: &str) -> Result<(String, String)> {
-    let remote = repo.find_remote(remote_name)?;
-    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
+impl GitIndexError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GitIndexError::IndexLocked { .. } => "IndexLocked",
+            GitIndexError::NotRestorable { .. } => "NotRestorable",
+            GitIndexError::NothingToCommit => "NothingToCommit",
+            GitIndexError::ConflictMarkersRemain { .. } => "ConflictMarkersRemain",
+            GitIndexError::SuspiciousSymlink { .. } => "SuspiciousSymlink",
+        }
+    }
 
-    // Try common usernames: prefer stored list by probing a few likely usernames
-    // We don’t have a way to list keyring entries, so we try a set
-    let candidate_users = ["git", "github", "oauth", "token"];
-    for user in candidate_users.iter() {
-        if let Ok(entry) = keyring_entry(remote_url, user) {
-            if let Ok(secret) = entry.get_password() {
-                return Ok((user.to_string(), secret));
-            }
+    pub fn not_a_repository(path: &Path) -> Self {
+        GitIndexError::NotRestorable {
+            paths: Vec::new(),
+            hint: format!("'{}' is not a git repository, so {}", path.display(), RESTORE_HINT),
         }
     }
+}
 
-    Err(anyhow!("No stored credentials found"))
+impl std::fmt::Display for GitIndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitIndexError::IndexLocked { holder_hint } => write!(f, "The git index is locked. {}", holder_hint),
+            GitIndexError::NotRestorable { paths, hint } if paths.is_empty() => write!(f, "{}", hint),
+            GitIndexError::NotRestorable { paths, hint } => write!(f, "Cannot restore {}: {}", paths.join(", "), hint),
+            GitIndexError::NothingToCommit => write!(f, "Nothing to commit: the selected changes match HEAD"),
+            GitIndexError::ConflictMarkersRemain { path, markers } => write!(
+                f,
+                "'{}' still has conflict markers (first on line {}); resolve them before marking it resolved",
+                path,
+                markers.first().map(|m| m.line).unwrap_or(0)
+            ),
+            GitIndexError::SuspiciousSymlink { links } => write!(
+                f,
+                "Not staged: {} point{} outside the repository. Stage anyway only if the link is intended.",
+                links.iter().map(|l| format!("'{}' -> '{}'", l.path, l.target)).collect::<Vec<_>>().join(", "),
+                if links.len() == 1 { "s" } else { "" }
+            ),
+        }
+    }
 }
 
-/// Save credentials (username + token) to OS keychain for a given remote
-pub fn save_git_credentials(repo_path: &Path, remote_name: &str, username: &str, password: &str) -> Result<()> {
-    let repo = Repository::open(repo_path)?;
-    let remote = repo.find_remote(remote_name)?;
-    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
-    let entry = keyring_entry(remote_url, username)?;
-    entry.set_password(password)?;
-    Ok(())
+impl std::error::Error for GitIndexError {}
+
+/// What git commands report to the frontend: index failures plus the checks and repository
+/// problems that can stop any git operation
+#[derive(Debug, Clone)]
+pub enum GitError {
+    Index(GitIndexError),
+    /// The changes add likely secrets and `block_on_secrets` is set
+    SecretsDetected { findings: Vec<SecretFinding> },
+    /// A pre-push check failed, so nothing was pushed
+    PrePushChecksFailed { step: Box<PrePushStepResult> },
+    /// libgit2 failed in a way that points at a damaged `.git`; `check_repository_health` has details
+    RepositoryCorrupt { kind: CorruptionKind, details: String },
+    /// user.name or user.email would produce a signature git rejects
+    InvalidIdentity { errors: Vec<IdentityFieldError> },
+    /// The operation needs a working tree and the repository is bare
+    BareRepository { git_dir: String },
+    /// The target tree has paths this case-insensitive filesystem cannot keep apart; retry with
+    /// `allow_case_collisions`
+    CaseCollision { collisions: Vec<PathCollision> },
+    /// Switching branches would overwrite these locally changed files; retry with `force` or `auto_stash`
+    CheckoutBlocked { paths: Vec<String> },
+    Failed { message: String },
 }
 
-/// Clear stored credentials for a given remote and username
-pub fn clear_git_credentials(repo_path: &Path, remote_name: &str, username: &str) -> Result<()> {
-    let repo = Repository::open(repo_path)?;
-    let remote = repo.find_remote(remote_name)?;
-    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
-    let entry = keyring_entry(remote_url, username)?;
-    // Ignore not found errors
-    let _ = entry.delete_password();
-    Ok(())
+impl GitError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GitError::Index(e) => e.code(),
+            GitError::SecretsDetected { .. } => "SecretsDetected",
+            GitError::PrePushChecksFailed { .. } => "PrePushChecksFailed",
+            GitError::RepositoryCorrupt { .. } => "RepositoryCorrupt",
+            GitError::InvalidIdentity { .. } => "InvalidIdentity",
+            GitError::BareRepository { .. } => "BareRepository",
+            GitError::CaseCollision { .. } => "CaseCollision",
+            GitError::CheckoutBlocked { .. } => "CheckoutBlocked",
+            GitError::Failed { .. } => "Failed",
+        }
+    }
 }
 
-/// Check if a directory is already a Git repository
-pub fn is_git_repository(repo_path: &Path) -> bool {
-    Repository::open(repo_path).is_ok()
+impl From<GitIndexError> for GitError {
+    fn from(e: GitIndexError) -> Self {
+        GitError::Index(e)
+    }
 }
 
-/// Get Git configuration for the repository
-pub fn get_git_config(repo_path: &Path) -> Result<GitConfig> {
-    let repo = Repository::open(repo_path)?;
-    let config = repo.config()?;
-    
-    let user_name = config.get_string("user.name").ok();
-    let user_email = config.get_string("user.email").ok();
-    
-    let is_configured = user_name.is_some() && user_email.is_some();
-    
-    Ok(GitConfig {
-        user_name,
-        user_email,
-        is_configured,
-    })
+impl From<anyhow::Error> for GitError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(bare) = e.downcast_ref::<BareRepository>() {
+            return GitError::BareRepository { git_dir: bare.git_dir.clone() };
+        }
+        if let Some(index_error) = e.downcast_ref::<GitIndexError>() {
+            return GitError::Index(index_error.clone());
+        }
+        e.downcast::<GitError>().unwrap_or_else(|e| match e.downcast_ref::<git2::Error>() {
+            Some(git_error) => git_error.into(),
+            None => GitError::Failed { message: e.to_string() },
+        })
+    }
 }
 
-/// Set Git configuration for the repository
-pub fn set_git_config(repo_path: &Path, name: &str, email: &str) -> Result<()> {
-    let repo = Repository::open(repo_path)?;
-    let mut config = repo.config()?;
-    
-    config.set_str("user.name", name)?;
-    config.set_str("user.email", email)?;
-    
-    Ok(())
+impl From<&git2::Error> for GitError {
+    fn from(e: &git2::Error) -> Self {
+        match repo_health::classify(e) {
+            Some(kind) => GitError::RepositoryCorrupt { kind, details: e.message().to_string() },
+            None => GitError::Failed { message: e.to_string() },
+        }
+    }
 }
 
-/// Enhanced Git repository initialization with proper setup
-pub fn init_git_repo_enhanced(repo_path: &Path) -> Result<GitInitResult> {
-    // Check if already a git repository
-    if is_git_repository(repo_path) {
-        // Already a repository, check configuration
-        match get_git_config(repo_path) {
-            Ok(config) => {
-                if config.is_configured {
-                    return Ok(GitInitResult {
-                        success: true,
-                        message: "Git repository already exists and is properly configured".to_string(),
-                        needs_config: false,
-                        git_config: Some(config),
-                    });
-                } else {
-                    return Ok(GitInitResult {
-                        success: true,
-                        message: "Git repository exists but needs user configuration".to_string(),
-                        needs_config: true,
-                        git_config: Some(config),
-                    });
-                }
-            }
-            Err(_) => {
-                return Ok(GitInitResult {
-                    success: true,
-                    message: "Git repository exists but configuration could not be read".to_string(),
-                    needs_config: true,
-                    git_config: None,
-                });
-            }
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitError::Index(e) => write!(f, "{}", e),
+            GitError::SecretsDetected { findings } => write!(
+                f,
+                "The changes add {} possible secret(s); remove them or commit anyway to override",
+                findings.len()
+            ),
+            GitError::PrePushChecksFailed { step } => match (&step.error, step.exit_code) {
+                (Some(error), _) => write!(f, "Pre-push check '{}' could not run: {}", step.command_line, error),
+                (None, Some(code)) => write!(f, "Pre-push check '{}' failed with exit code {}", step.command_line, code),
+                (None, None) => write!(f, "Pre-push check '{}' was terminated", step.command_line),
+            },
+            GitError::RepositoryCorrupt { details, .. } => write!(
+                f,
+                "The repository looks damaged ({}). Run a repository health check for details and repairs.",
+                details
+            ),
+            GitError::InvalidIdentity { errors } => write!(
+                f,
+                "{}",
+                errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; ")
+            ),
+            GitError::BareRepository { git_dir } => write!(f, "{}", BareRepository { git_dir: git_dir.clone() }),
+            GitError::CaseCollision { collisions } => write!(
+                f,
+                "Not checked out: this filesystem would keep only one of {}. Check out anyway only if losing the others is acceptable.",
+                collisions.iter().map(|c| c.paths.join(" / ")).collect::<Vec<_>>().join(", ")
+            ),
+            GitError::CheckoutBlocked { paths } => write!(
+                f,
+                "Not checked out: local changes to {} would be overwritten. Commit or stash them, or force the checkout to discard them.",
+                paths.join(", ")
+            ),
+            GitError::Failed { message } => write!(f, "{}", message),
         }
     }
+}
 
-    // Initialize new repository
-    match Repository::init(repo_path) {
-        Ok(_) => {
-            // Create initial .gitignore file with common patterns
-            let gitignore_path = repo_path.join(".gitignore");
-            let gitignore_content = create_default_gitignore();
-            
-            if let Err(e) = fs::write(&gitignore_path, gitignore_content) {
-                eprintln!("Warning: Could not create .gitignore: {}", e);
-            }
+impl std::error::Error for GitError {}
 
-            // Check if git is configured globally
-            match Config::open_default() {
-                Ok(global_config) => {
-                    let has_global_name = global_config.get_string("user.name").is_ok();
-                    let has_global_email = global_config.get_string("user.email").is_ok();
-                    
-                    if has_global_name && has_global_email {
-                        // Global config exists, repository is ready
-                        Ok(GitInitResult {
-                            success: true,
-                            message: "Git repository initialized successfully with global configuration".to_string(),
-                            needs_config: false,
-                            git_config: get_git_config(repo_path).ok(),
-                        })
-                    } else {
-                        // No global config, need to set up user info
-                        Ok(GitInitResult {
-                            success: true,
-                            message: "Git repository initialized. Please configure user name and email.".to_string(),
-                            needs_config: true,
-                            git_config: Some(GitConfig {
-                                user_name: None,
-                                user_email: None,
-                                is_configured: false,
-                            }),
-                        })
-                    }
-                }
-                Err(_) => {
-                    // Could not read global config, assume we need local config
-                    Ok(GitInitResult {
-                        success: true,
-                        message: "Git repository initialized. Please configure user name and email.".to_string(),
-                        needs_config: true,
-                        git_config: Some(GitConfig {
-                            user_name: None,
-                            user_email: None,
-                            is_configured: false,
-                        }),
-                    })
-                }
-            }
-        }
-        Err(e) => Ok(GitInitResult {
-            success: false,
-            message: format!("Failed to initialize Git repository: {}", e),
-            needs_config: false,
-            git_config: None,
-        })
+impl Serialize for GitError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("GitError", 11)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        let (holder_hint, paths) = match self {
+            GitError::Index(GitIndexError::IndexLocked { holder_hint }) => (Some(holder_hint), None),
+            GitError::Index(GitIndexError::NotRestorable { paths, .. }) => (None, Some(paths.clone())),
+            GitError::Index(GitIndexError::ConflictMarkersRemain { path, .. }) => (None, Some(vec![path.clone()])),
+            GitError::CheckoutBlocked { paths } => (None, Some(paths.clone())),
+            _ => (None, None),
+        };
+        state.serialize_field("holder_hint", &holder_hint)?;
+        state.serialize_field("paths", &paths)?;
+        let findings = match self {
+            GitError::SecretsDetected { findings } => Some(findings),
+            _ => None,
+        };
+        state.serialize_field("findings", &findings)?;
+        let failed_check = match self {
+            GitError::PrePushChecksFailed { step } => Some(step),
+            _ => None,
+        };
+        state.serialize_field("failed_check", &failed_check)?;
+        let corruption = match self {
+            GitError::RepositoryCorrupt { kind, .. } => Some(kind),
+            _ => None,
+        };
+        state.serialize_field("corruption", &corruption)?;
+        let identity_errors = match self {
+            GitError::InvalidIdentity { errors } => Some(errors),
+            _ => None,
+        };
+        state.serialize_field("identity_errors", &identity_errors)?;
+        let conflict_markers = match self {
+            GitError::Index(GitIndexError::ConflictMarkersRemain { markers, .. }) => Some(markers),
+            _ => None,
+        };
+        state.serialize_field("conflict_markers", &conflict_markers)?;
+        let symlinks = match self {
+            GitError::Index(GitIndexError::SuspiciousSymlink { links }) => Some(links),
+            _ => None,
+        };
+        state.serialize_field("symlinks", &symlinks)?;
+        let case_collisions = match self {
+            GitError::CaseCollision { collisions } => Some(collisions),
+            _ => None,
+        };
+        state.serialize_field("case_collisions", &case_collisions)?;
+        state.end()
     }
 }
 
-/// Create a default .gitignore file with common patterns
-fn create_default_gitignore() -> String {
-    "# Dependencies
-node_modules/
-npm-debug.log*
-yarn-debug.log*
-yarn-error.log*
-package-lock.json
+fn is_index_locked(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<git2::Error>() {
+        Some(git_error) => git_error.code() == ErrorCode::Locked || git_error.message().contains("index.lock"),
+        None => e.to_string().contains("index.lock"),
+    }
+}
 
-# Build outputs
-dist/
-build/
-target/
-*.exe
-*.dll
-*.so
-*.dylib
+/// Describe who is likely holding `.git/index.lock`
+fn index_lock_hint(git_dir: &Path) -> String {
+    let lock = git_dir.join("index.lock");
+    let age = fs::metadata(&lock)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .map(|d| d.as_secs());
+    match age {
+        Some(secs) => format!(
+            "'{}' was created {}s ago by another git process (a terminal, editor or git GUI). If none is running, delete the file.",
+            lock.display(),
+            secs
+        ),
+        None => "Another git process held the index lock until just now; try again.".to_string(),
+    }
+}
 
-# Environment variables
-.env
-.env.local
-.env.development.local
-.env.test.local
-.env.production.local
+// A waiting background operation is promoted after this long so interactive work cannot starve it
+const STARVATION_LIMIT: Duration = Duration::from_secs(5);
 
-# IDE and editor files
-.vscode/
-.idea/
-*.swp
-*.swo
-*~
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationPriority {
+    /// Fetches, pushes and statistics the user is not blocked on
+    Background,
+    /// Status and history reads the UI is waiting for
+    Interactive,
+}
 
-# OS generated files
-.DS_Store
-.DS_Store?
-._*
-.Spotlight-V100
-.Trashes
-ehthumbs.db
-Thumbs.db
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitOperationEvent {
+    pub operation_id: String,
+    pub repository: String,
+    pub label: String,
+    /// "queued", "started" or "finished"
+    pub state: String,
+    /// Operations ahead of this one, including the running one; only set when queued
+    pub position: Option<usize>,
+    /// Label of the operation currently running; only set when queued
+    pub waiting_for: Option<String>,
+}
 
-# Logs
-*.log
-logs/
+struct Waiter {
+    id: u64,
+    label: String,
+    priority: OperationPriority,
+    enqueued: Instant,
+    start: tokio::sync::oneshot::Sender<()>,
+}
 
-# Runtime data
-pids
-*.pid
-*.seed
-*.pid.lock
+impl Waiter {
+    fn effective_priority(&self, now: Instant) -> OperationPriority {
+        if now.duration_since(self.enqueued) >= STARVATION_LIMIT { OperationPriority::Interactive } else { self.priority }
+    }
+}
 
-# Temporary folders
-tmp/
-temp/
-".to_string()
+#[derive(Default)]
+struct RepoQueue {
+    running: Option<String>,
+    waiting: Vec<Waiter>,
 }
 
-/// Configure or update a remote URL
-pub fn set_remote(repo_path: &Path, remote_name: &str, url: &str) -> Result<()> {
-    let repo = Repository::open(repo_path)?;
+impl RepoQueue {
+    /// Highest effective priority first, then first come first served
+    fn next(&self) -> Option<usize> {
+        self.next_at(Instant::now())
+    }
 
-    // Check if remote exists first, without borrowing the Remote
-    let remote_exists = repo.find_remote(remote_name).is_ok();
+    fn next_at(&self, now: Instant) -> Option<usize> {
+        self.waiting
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.effective_priority(now).cmp(&b.effective_priority(now)).then_with(|| b.enqueued.cmp(&a.enqueued))
+            })
+            .map(|(i, _)| i)
+    }
+}
 
-    if remote_exists {
-        repo.remote_set_url(remote_name, url)?;
-    } else {
-        repo.remote(remote_name, url)?;
+/// Per-repository index locks and the queue that runs heavy operations one at a time
+#[derive(Default)]
+pub struct GitState {
+    index_locks: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+    /// Shared with the slots of running operations, which release them from their blocking thread
+    queues: Arc<Mutex<HashMap<PathBuf, RepoQueue>>>,
+    next_operation: AtomicU64,
+    /// Index entries per repository, reused until the index file's mtime changes
+    tracked_files: Mutex<HashMap<PathBuf, CachedIndex>>,
+    /// Ahead/behind counts per commit pair; commits never change, so entries never go stale
+    ahead_behind: Mutex<HashMap<(git2::Oid, git2::Oid), (usize, usize)>>,
+    /// Repositories whose status was computed at least once this session
+    status_scanned: Mutex<std::collections::HashSet<PathBuf>>,
+}
+
+/// One path from the index, as read for the tracked-file finder
+#[derive(Clone, Debug)]
+pub struct IndexedPath {
+    pub path: String,
+    /// Not materialised in the working tree, e.g. outside a sparse checkout
+    pub skip_worktree: bool,
+    pub intent_to_add: bool,
+}
+
+/// An index's entries and the index file mtime they were read at
+type CachedIndex = (std::time::SystemTime, Arc<Vec<IndexedPath>>);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrackedFile {
+    /// Relative to the repository root, `/`-separated
+    pub path: String,
+    pub staged: bool,
+    pub modified: bool,
+    pub skip_worktree: bool,
+    /// Added with `git add -N`; neither `staged` nor `modified` is set for it
+    #[serde(default)]
+    pub intent_to_add: bool,
+    /// Fuzzy match score when a pattern was given
+    pub score: Option<i64>,
+}
+
+/// Releases the repository's queue slot when the operation ends, even if its task panicked.
+/// It moves into the blocking task, so a command future dropped mid-operation does not hand
+/// the repository to the next waiter while the operation is still running.
+struct QueueSlot {
+    queues: Arc<Mutex<HashMap<PathBuf, RepoQueue>>>,
+    key: PathBuf,
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        release(&self.queues, &self.key);
     }
+}
 
-    Ok(())
+/// Hand the repository to the next waiter, skipping any whose command was dropped
+fn release(queues: &Mutex<HashMap<PathBuf, RepoQueue>>, key: &Path) {
+    let mut queues = queues.lock().unwrap();
+    let Some(queue) = queues.get_mut(key) else { return };
+    queue.running = None;
+    while let Some(index) = queue.next() {
+        let waiter = queue.waiting.remove(index);
+        if waiter.start.send(()).is_ok() {
+            queue.running = Some(waiter.label);
+            break;
+        }
+    }
+    if queue.running.is_none() {
+        queues.remove(key);
+    }
 }
 
-/// Legacy function for backward compatibility
-pub fn init_git_repo(repo_path: &Path) -> Result<()> {
-    match init_git_repo_enhanced(repo_path) {
-        Ok(result) => {
-            if result.success {
-                Ok(())
-            } else {
-                Err(anyhow!(result.message))
+/// Removes a queued operation whose command future is dropped before it starts
+struct QueueWait<'a> {
+    state: &'a GitState,
+    key: PathBuf,
+    id: u64,
+    armed: bool,
+}
+
+impl Drop for QueueWait<'_> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let still_waiting = {
+            let mut queues = self.state.queues.lock().unwrap();
+            let queue = queues.get_mut(&self.key);
+            match queue.and_then(|q| q.waiting.iter().position(|w| w.id == self.id).map(|i| (q, i))) {
+                Some((queue, index)) => {
+                    queue.waiting.remove(index);
+                    true
+                }
+                None => false,
             }
+        };
+        // Already handed the slot but never took it: pass it on
+        if !still_waiting {
+            release(&self.state.queues, &self.key);
         }
-        Err(e) => Err(e)
     }
 }
 
-/// Git credentials structure for storage
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct GitCredentials {
-    pub username: String,
-    pub token: String,
-    pub remote_url: String,
-}
+// Cached commit pairs before the ahead/behind cache is dropped and rebuilt
+const AHEAD_BEHIND_CACHE_LIMIT: usize = 10_000;
 
-/// Get stored Git credentials for a project
-pub fn get_git_credentials(project_path: &Path) -> Result<Option<GitCredentials>> {
-    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
-    let username_entry = Entry::new(&service_name, "username")?;
-    let token_entry = Entry::new(&service_name, "token")?;
-    let remote_entry = Entry::new(&service_name, "remote_url")?;
+impl GitState {
+    /// True the first time it is asked about `repo_path`, for the initial status scan
+    pub fn first_status_scan(&self, repo_path: &Path) -> bool {
+        let key = fs::canonicalize(repo_path).unwrap_or_else(|_| repo_path.to_path_buf());
+        self.status_scanned.lock().unwrap().insert(key)
+    }
 
-    match (username_entry.get_password(), token_entry.get_password(), remote_entry.get_password()) {
-        (Ok(username), Ok(token), Ok(remote_url)) => {
-            Ok(Some(GitCredentials {
-                username,
-                token,
-                remote_url,
-            }))
+    /// Drop cached index entries and scan state of repositories at or below a closed workspace
+    pub fn forget(&self, root: &Path) {
+        self.tracked_files.lock().unwrap().retain(|index_file, _| !index_file.starts_with(root));
+        self.status_scanned.lock().unwrap().retain(|repo| !repo.starts_with(root));
+    }
+
+    /// `repo.graph_ahead_behind`, remembered per commit pair
+    pub fn ahead_behind(&self, repo: &Repository, local: git2::Oid, upstream: git2::Oid) -> Result<(usize, usize)> {
+        if let Some(counts) = self.ahead_behind.lock().unwrap().get(&(local, upstream)) {
+            return Ok(*counts);
         }
-        _ => Ok(None)
+        let counts = repo.graph_ahead_behind(local, upstream)?;
+        let mut cache = self.ahead_behind.lock().unwrap();
+        if cache.len() >= AHEAD_BEHIND_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert((local, upstream), counts);
+        Ok(counts)
     }
-}
 
-/// Store Git credentials for a project
-pub fn set_git_credentials(project_path: &Path, credentials: GitCredentials) -> Result<()> {
-    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
+    /// Paths in the repository's index, read once per index change
+    pub fn indexed_paths(&self, repo_path: &Path) -> Result<(Repository, Arc<Vec<IndexedPath>>)> {
+        let repo = Repository::discover(repo_path)?;
+        let index_file = repo.path().join("index");
+        let modified = fs::metadata(&index_file).and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
+
+        if let Some((cached_at, paths)) = self.tracked_files.lock().unwrap().get(&index_file) {
+            if *cached_at == modified {
+                return Ok((repo, paths.clone()));
+            }
+        }
 
-    let username_entry = Entry::new(&service_name, "username")?;
-    let token_entry = Entry::new(&service_name, "token")?;
-    let remote_entry = Entry::new(&service_name, "remote_url")?;
+        let index = repo.index()?;
+        let mut paths = Vec::with_capacity(index.len());
+        let mut last: Option<Vec<u8>> = None;
+        for entry in index.iter() {
+            // Conflicted paths appear once per stage
+            if last.as_deref() == Some(entry.path.as_slice()) {
+                continue;
+            }
+            paths.push(IndexedPath {
+                path: String::from_utf8_lossy(&entry.path).to_string(),
+                skip_worktree: entry.flags_extended & git2::IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0,
+                intent_to_add: is_intent_to_add(&entry),
+            });
+            last = Some(entry.path);
+        }
+        let paths = Arc::new(paths);
+        self.tracked_files.lock().unwrap().insert(index_file, (modified, paths.clone()));
+        Ok((repo, paths))
+    }
 
-    username_entry.set_password(&credentials.username)?;
-    token_entry.set_password(&credentials.token)?;
-    remote_entry.set_password(&credentials.remote_url)?;
+    /// Run a heavy operation on a blocking thread once no other heavy operation is running
+    /// against the same repository. Cheap reads should call git directly instead.
+    pub async fn run_queued<T, F, E>(&self, repo_path: &Path, label: &str, priority: OperationPriority, emit: E, op: F) -> std::result::Result<T, String>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+        E: Fn(GitOperationEvent),
+    {
+        let Ok(repo) = Repository::discover(repo_path) else {
+            // Not a repository: nothing to contend on
+            return tauri::async_runtime::spawn_blocking(op).await.map_err(|e| format!("Git task failed: {}", e));
+        };
+        let key = fs::canonicalize(repo.path()).unwrap_or_else(|_| repo.path().to_path_buf());
+        drop(repo);
 
-    Ok(())
+        let id = self.next_operation.fetch_add(1, Ordering::Relaxed) + 1;
+        let operation_id = format!("git-op-{}", id);
+        let event = |state: &str, position: Option<usize>, waiting_for: Option<String>| GitOperationEvent {
+            operation_id: operation_id.clone(),
+            repository: crate::fs::display_path(&key),
+            label: label.to_string(),
+            state: state.to_string(),
+            position,
+            waiting_for,
+        };
+
+        let waiting = {
+            let mut queues = self.queues.lock().unwrap();
+            let queue = queues.entry(key.clone()).or_default();
+            if queue.running.is_none() && queue.waiting.is_empty() {
+                queue.running = Some(label.to_string());
+                None
+            } else {
+                let ahead = queue.waiting.iter().filter(|w| w.priority >= priority).count() + 1;
+                let (start, started) = tokio::sync::oneshot::channel();
+                queue.waiting.push(Waiter { id, label: label.to_string(), priority, enqueued: Instant::now(), start });
+                Some((started, ahead, queue.running.clone()))
+            }
+        };
+        if let Some((started, ahead, running)) = waiting {
+            let mut wait = QueueWait { state: self, key: key.clone(), id, armed: true };
+            emit(event("queued", Some(ahead), running));
+            started.await.map_err(|_| "Git operation queue was dropped".to_string())?;
+            wait.armed = false;
+        }
+
+        let slot = QueueSlot { queues: self.queues.clone(), key: key.clone() };
+        emit(event("started", None, None));
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            let _slot = slot;
+            op()
+        })
+        .await
+        .map_err(|e| format!("Git task failed: {}", e));
+        emit(event("finished", None, None));
+        result
+    }
+
+    /// (repository, label) of each heavy operation running now
+    pub fn running_operations(&self) -> Vec<(String, String)> {
+        self.queues
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(key, queue)| Some((crate::fs::display_path(key), queue.running.clone()?)))
+            .collect()
+    }
+
+    /// Run `op` on a blocking thread while holding this repository's index lock, retrying
+    /// briefly when an external git process holds `.git/index.lock`
+    pub async fn with_index_lock<T, F>(&self, repo_path: &Path, mut op: F) -> std::result::Result<T, GitError>
+    where
+        F: FnMut() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let git_dir = Repository::discover(repo_path)
+            .map(|r| r.path().to_path_buf())
+            .map_err(|e| GitError::from(&e))?;
+        let key = fs::canonicalize(&git_dir).unwrap_or_else(|_| git_dir.clone());
+        let lock = self.index_locks.lock().unwrap().entry(key).or_default().clone();
+        let _guard = lock.lock().await;
+
+        let mut attempt = 0;
+        loop {
+            let (result, returned) = tauri::async_runtime::spawn_blocking(move || {
+                let result = op();
+                (result, op)
+            })
+            .await
+            .map_err(|e| GitError::Failed { message: format!("Git task failed: {}", e) })?;
+            op = returned;
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if is_index_locked(&e) && attempt < INDEX_LOCK_RETRIES => {
+                    attempt += 1;
+                    tokio::time::sleep(INDEX_LOCK_RETRY_DELAY).await;
+                }
+                Err(e) if is_index_locked(&e) => {
+                    return Err(GitIndexError::IndexLocked { holder_hint: index_lock_hint(&git_dir) }.into());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 }
 
-/// Delete stored Git credentials for a project
-pub fn delete_git_credentials(project_path: &Path) -> Result<()> {
-    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
+/// Remote callbacks resolving credentials: explicit user/pass (or PAT) for HTTPS, SSH agent, then defaults
+pub fn credential_callbacks<'a>(username: Option<String>, password: Option<String>) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |_url, username_from_url, allowed_types| {
+        // If caller provided or stored username/password (or token), prefer that for HTTPS
+        if allowed_types.is_user_pass_plaintext() {
+            if let (Some(u), Some(p)) = (username.as_deref(), password.as_deref()) {
+                return Cred::userpass_plaintext(u, p);
+            }
+        }
+        // Try SSH agent if allowed
+        if allowed_types.is_ssh_key() {
+            if let Some(u) = username_from_url {
+                if let Ok(cred) = Cred::ssh_key_from_agent(u) { return Ok(cred); }
+            }
+            if let Some(u) = username.as_deref() {
+                if let Ok(cred) = Cred::ssh_key_from_agent(u) { return Ok(cred); }
+            }
+        }
+        // Fallback to default credentials (may use OS helpers)
+        Cred::default()
+    });
+    callbacks
+}
 
-    let username_entry = Entry::new(&service_name, "username")?;
-    let token_entry = Entry::new(&service_name, "token")?;
-    let remote_entry = Entry::new(&service_name, "remote_url")?;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CloneProgress {
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+}
 
-    // Try to delete each entry, ignore errors if they don't exist
-    let _ = username_entry.delete_password();
-    let _ = token_entry.delete_password();
-    let _ = remote_entry.delete_password();
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct CloneOptions {
+    /// Partial-clone filter such as "blob:none"; needs the git CLI since libgit2 cannot do it
+    pub filter: Option<String>,
+    /// Shallow clone with only this many commits of history
+    pub depth: Option<u32>,
+}
 
-    Ok(())
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitCloneProgress {
+    pub target_path: String,
+    pub progress: CloneProgress,
 }
 
+/// Clone `url` into `target`, reporting transfer progress; returning false from `on_progress` cancels
+pub fn clone_repository<F>(url: &str, target: &Path, username: Option<String>, password: Option<String>, options: &CloneOptions, mut on_progress: F) -> Result<Repository>
+where
+    F: FnMut(CloneProgress) -> bool,
+{
+    if options.filter.is_some() {
+        return clone_with_cli(url, target, username, password, options, on_progress);
+    }
+
+    let mut callbacks = credential_callbacks(username, password);
+    callbacks.transfer_progress(move |stats| {
+        on_progress(CloneProgress {
+            received_objects: stats.received_objects(),
+            total_objects: stats.total_objects(),
+            received_bytes: stats.received_bytes(),
+        })
+    });
+
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    if let Some(depth) = options.depth {
+        fetch_options.depth(depth.min(i32::MAX as u32) as i32);
+    }
+
+    let repo = git2::build::RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(url, target)
+        .map_err(|e| {
+            if e.code() == ErrorCode::User {
+                anyhow!("Clone cancelled")
+            } else {
+                anyhow!("Clone failed: {}", e.message())
+            }
+        })?;
+    Ok(repo)
+}
+
+/// Accept only the filter specs git documents, so nothing else reaches the command line
+fn validate_clone_filter(filter: &str) -> Result<()> {
+    let valid = match filter.split_once(':') {
+        Some(("blob", "none")) => true,
+        Some(("blob", limit)) => limit
+            .strip_prefix("limit=")
+            .map(|n| {
+                let digits = n.trim_end_matches(['k', 'm', 'g', 'K', 'M', 'G']);
+                !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+            })
+            .unwrap_or(false),
+        Some(("tree", depth)) => depth.parse::<u32>().is_ok(),
+        _ => false,
+    };
+    if valid { Ok(()) } else { Err(anyhow!("Unsupported clone filter '{}'; use blob:none, blob:limit=<size> or tree:<depth>", filter)) }
+}
+
+/// "Receiving objects:  37% (370/1000), 2.00 MiB | 1.00 MiB/s" into counts and bytes
+fn parse_clone_progress(line: &str) -> Option<CloneProgress> {
+    let rest = line.strip_prefix("Receiving objects:")?;
+    let counts = &rest[rest.find('(')? + 1..rest.find(')')?];
+    let (received, total) = counts.split_once('/')?;
+    let received_bytes = rest
+        .split_once("), ")
+        .and_then(|(_, size)| {
+            let mut parts = size.split_whitespace();
+            let amount: f64 = parts.next()?.parse().ok()?;
+            let unit = match parts.next()? {
+                "KiB" => 1024.0,
+                "MiB" => 1024.0 * 1024.0,
+                "GiB" => 1024.0 * 1024.0 * 1024.0,
+                _ => 1.0,
+            };
+            Some((amount * unit) as usize)
+        })
+        .unwrap_or(0);
+    Some(CloneProgress {
+        received_objects: received.trim().parse().ok()?,
+        total_objects: total.trim().parse().ok()?,
+        received_bytes,
+    })
+}
+
+/// Askpass script that answers git's prompts from environment variables, so credentials
+/// never appear in a command line; deleted when dropped
+struct AskpassScript(PathBuf);
+
+const ASKPASS_USERNAME_ENV: &str = "AGENTIC_IDE_GIT_USERNAME";
+const ASKPASS_PASSWORD_ENV: &str = "AGENTIC_IDE_GIT_PASSWORD";
+
+impl AskpassScript {
+    fn create() -> Result<Self> {
+        let name = format!("agentic-ide-askpass-{}-{}", std::process::id(), chrono::Utc::now().timestamp_millis());
+        let (path, script) = if cfg!(windows) {
+            (
+                std::env::temp_dir().join(format!("{}.cmd", name)),
+                format!(
+                    "@echo off\r\necho %~1 | findstr /b /c:\"Username\" >nul && (echo %{}%) || (echo %{}%)\r\n",
+                    ASKPASS_USERNAME_ENV, ASKPASS_PASSWORD_ENV
+                ),
+            )
+        } else {
+            (
+                std::env::temp_dir().join(format!("{}.sh", name)),
+                format!(
+                    "#!/bin/sh\ncase \"$1\" in\n  Username*) printf '%s\\n' \"${}\" ;;\n  *) printf '%s\\n' \"${}\" ;;\nesac\n",
+                    ASKPASS_USERNAME_ENV, ASKPASS_PASSWORD_ENV
+                ),
+            )
+        };
+        fs::write(&path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(Self(path))
+    }
+}
+
+impl Drop for AskpassScript {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Partial or shallow clone through the system git, parsing progress from its stderr
+fn clone_with_cli<F>(url: &str, target: &Path, username: Option<String>, password: Option<String>, options: &CloneOptions, mut on_progress: F) -> Result<Repository>
+where
+    F: FnMut(CloneProgress) -> bool,
+{
+    let mut args = vec!["clone".to_string(), "--progress".to_string()];
+    if let Some(filter) = &options.filter {
+        validate_clone_filter(filter)?;
+        args.push(format!("--filter={}", filter));
+    }
+    if let Some(depth) = options.depth {
+        args.push(format!("--depth={}", depth));
+    }
+    args.push("--".to_string());
+    args.push(url.to_string());
+    args.push(target.to_string_lossy().to_string());
+
+    let mut env = vec![("GIT_TERMINAL_PROMPT", "0".to_string())];
+    let askpass = match (&username, &password) {
+        (Some(username), Some(password)) => {
+            let script = AskpassScript::create()?;
+            env.push(("GIT_ASKPASS", script.0.to_string_lossy().to_string()));
+            env.push((ASKPASS_USERNAME_ENV, username.clone()));
+            env.push((ASKPASS_PASSWORD_ENV, password.clone()));
+            Some(script)
+        }
+        _ => None,
+    };
+    let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+    let cwd = target.parent().filter(|p| p.is_dir()).map(Path::to_path_buf).unwrap_or_else(std::env::temp_dir);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut cancelled = false;
+    let result = run_git_cli_with_env(&cwd, &args, &env, |line| match parse_clone_progress(line) {
+        Some(progress) => {
+            cancelled = !on_progress(progress);
+            !cancelled
+        }
+        None => true,
+    });
+    drop(askpass);
+    match result {
+        Err(_) if cancelled => Err(anyhow!("Clone cancelled")),
+        Err(e) => Err(anyhow!("Clone failed: {}", e)),
+        Ok(_) => Ok(Repository::open(target)?),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CloneInfo {
+    /// Filter of a partial clone, e.g. "blob:none"; history views fetch missing objects on demand
+    pub partial_filter: Option<String>,
+    pub shallow: bool,
+}
+
+/// Whether the repository is a partial or shallow clone, from the metadata git records itself
+/// (`remote.<name>.partialclonefilter` and `.git/shallow`)
+pub fn get_clone_info(repo_path: &Path) -> Result<CloneInfo> {
+    let repo = Repository::discover(repo_path)?;
+    let config = repo.config()?;
+    let partial_filter = repo
+        .remotes()?
+        .iter()
+        .flatten()
+        .find_map(|remote| config.get_string(&format!("remote.{}.partialclonefilter", remote)).ok());
+    Ok(CloneInfo { partial_filter, shallow: repo.is_shallow() })
+}
+
+/// Fetch everything a partial or shallow clone left out and turn it into a full clone
+pub fn backfill_clone<F>(repo_path: &Path, mut on_line: F) -> Result<CloneInfo>
+where
+    F: FnMut(&str),
+{
+    let repo = Repository::discover(repo_path)?;
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+    let info = get_clone_info(&workdir)?;
+    if info.shallow {
+        run_git_cli(&workdir, &["fetch", "--progress", "--unshallow"], &mut on_line)?;
+    }
+    if info.partial_filter.is_some() {
+        let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+        for remote in repo.remotes()?.iter().flatten() {
+            if config.remove(&format!("remote.{}.partialclonefilter", remote)).is_ok() {
+                run_git_cli(&workdir, &["fetch", "--progress", "--refetch", remote], &mut on_line).map_err(|e| {
+                    anyhow!("Backfilling needs git 2.36 or newer for `fetch --refetch`: {}", e)
+                })?;
+            }
+        }
+    }
+    get_clone_info(&workdir)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscapingSymlink {
+    /// Repo-relative, `/`-separated
+    pub path: String,
+    /// Where the link points, as stored in the link
+    pub target: String,
+}
+
+/// The symlink (or Windows junction) at `relative` or on the way to it whose target lies
+/// outside `workdir`. Committing one lets a checkout follow it to files outside the repository.
+pub fn escaping_symlink(workdir: &Path, relative: &Path) -> Option<EscapingSymlink> {
+    let root = fs::canonicalize(workdir).unwrap_or_else(|_| workdir.to_path_buf());
+    let mut current = workdir.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        let Ok(metadata) = fs::symlink_metadata(&current) else { return None };
+        if !metadata.file_type().is_symlink() {
+            continue;
+        }
+        let target = fs::read_link(&current).ok()?;
+        let resolved = fs::canonicalize(&current).unwrap_or_else(|_| {
+            let parent = current.parent().and_then(|p| fs::canonicalize(p).ok()).unwrap_or_else(|| root.clone());
+            crate::fs::normalize_lexically(&parent.join(&target))
+        });
+        if !resolved.starts_with(&root) {
+            let path = current.strip_prefix(workdir).unwrap_or(&current).to_string_lossy().replace('\\', "/");
+            return Some(EscapingSymlink { path, target: target.to_string_lossy().to_string() });
+        }
+    }
+    None
+}
+
+fn refuse_escaping_symlinks<P: AsRef<Path>>(workdir: &Path, paths: &[P]) -> Result<()> {
+    let mut links: Vec<EscapingSymlink> = paths.iter().filter_map(|p| escaping_symlink(workdir, p.as_ref())).collect();
+    links.sort_by(|a, b| a.path.cmp(&b.path));
+    links.dedup_by(|a, b| a.path == b.path);
+    if links.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(GitIndexError::SuspiciousSymlink { links }))
+    }
+}
+
+/// Add workdir-relative `paths` to `index`, the way every staging path adds files. Unless
+/// `allow_symlinks_outside_repo`, refuses with `SuspiciousSymlink` before adding anything when
+/// a path is, or lies below, a symlink pointing outside the working tree.
+fn add_to_index<P: AsRef<Path>>(index: &mut git2::Index, workdir: &Path, paths: &[P], allow_symlinks_outside_repo: bool) -> Result<()> {
+    if !allow_symlinks_outside_repo {
+        refuse_escaping_symlinks(workdir, paths)?;
+    }
+    for path in paths {
+        index.add_path(path.as_ref())?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionKind {
+    /// The paths differ in letter case
+    Case,
+    /// The paths differ only in Unicode normalization (NFC vs NFD)
+    UnicodeNormalization,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PathCollision {
+    /// Repo-relative, `/`-separated paths that name one file on a folding filesystem
+    pub paths: Vec<String>,
+    pub kind: CollisionKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TreePortability {
+    pub reference: String,
+    pub commit: String,
+    pub paths_checked: usize,
+    pub collisions: Vec<PathCollision>,
+    /// What probing the working tree found; a checkout is only refused for collisions the
+    /// filesystem actually folds
+    pub case_insensitive_workdir: bool,
+    pub normalization_insensitive_workdir: bool,
+}
+
+/// Whether `dir`'s filesystem treats `name` and `alias` as one file, found by creating one and
+/// looking for the other. Errs on the side of "no" when the probe cannot be written.
+fn folds_names(dir: &Path, name: &str, alias: &str) -> bool {
+    let probe = dir.join(name);
+    if fs::write(&probe, b"").is_err() {
+        return false;
+    }
+    let folds = fs::symlink_metadata(dir.join(alias)).is_ok();
+    let _ = fs::remove_file(&probe);
+    folds
+}
+
+/// Probe the repository for case and Unicode normalization folding. The probe goes in the git
+/// directory so it never shows up as a working tree change.
+fn filesystem_folding(repo: &Repository) -> (bool, bool) {
+    let dir = repo.path();
+    let case = folds_names(dir, "agentic-case-probe", "AGENTIC-CASE-PROBE");
+    let normalization = folds_names(dir, "agentic-norm-probe-\u{e9}", "agentic-norm-probe-e\u{301}");
+    (case, normalization)
+}
+
+/// Paths in `tree` that collide once case and Unicode normalization are folded, and how many
+/// paths were looked at. Directories are included, since two spellings of one directory merge.
+fn tree_collisions(tree: &git2::Tree) -> Result<(usize, Vec<PathCollision>)> {
+    use unicode_normalization::UnicodeNormalization;
+
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut checked = 0;
+    tree.walk(git2::TreeWalkMode::PreOrder, |parent, entry| {
+        let path = format!("{}{}", parent, String::from_utf8_lossy(entry.name_bytes()));
+        checked += 1;
+        groups.entry(path.nfc().collect::<String>().to_lowercase()).or_default().push(path);
+        git2::TreeWalkResult::Ok
+    })?;
+    let collisions = groups
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|paths| {
+            let nfc: Vec<String> = paths.iter().map(|p| p.nfc().collect()).collect();
+            let kind = if nfc.iter().all(|p| p == &nfc[0]) { CollisionKind::UnicodeNormalization } else { CollisionKind::Case };
+            PathCollision { paths, kind }
+        })
+        .collect();
+    Ok((checked, collisions))
+}
+
+/// Every path collision in the tree of `reference` (HEAD when omitted), whatever the local
+/// filesystem, for checking a branch is portable before it reaches macOS or Windows users
+pub fn check_tree_portability(repo_path: &Path, reference: Option<&str>) -> Result<TreePortability> {
+    let repo = Repository::discover(repo_path)?;
+    let reference = reference.unwrap_or("HEAD");
+    let commit = repo.revparse_single(reference)?.peel_to_commit()?;
+    let (paths_checked, collisions) = tree_collisions(&commit.tree()?)?;
+    let (case_insensitive_workdir, normalization_insensitive_workdir) = filesystem_folding(&repo);
+    Ok(TreePortability {
+        reference: reference.to_string(),
+        commit: commit.id().to_string(),
+        paths_checked,
+        collisions,
+        case_insensitive_workdir,
+        normalization_insensitive_workdir,
+    })
+}
+
+/// Refuse to materialize `tree` when this filesystem would fold any of its paths together
+fn refuse_colliding_tree(repo: &Repository, tree: &git2::Tree) -> Result<()> {
+    let (case, normalization) = filesystem_folding(repo);
+    if !case && !normalization {
+        return Ok(());
+    }
+    let (_, collisions) = tree_collisions(tree)?;
+    let collisions: Vec<PathCollision> = collisions
+        .into_iter()
+        .filter(|c| match c.kind {
+            CollisionKind::Case => case,
+            CollisionKind::UnicodeNormalization => normalization,
+        })
+        .collect();
+    if collisions.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(GitError::CaseCollision { collisions }))
+    }
+}
+
+/// Working tree files whose content would be staged: modified or type-changed tracked files,
+/// plus non-ignored untracked files with `untracked`
+fn changed_paths(repo: &Repository, untracked: bool) -> Result<Vec<PathBuf>> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(untracked).recurse_untracked_dirs(untracked).include_ignored(false);
+    Ok(repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter(|entry| entry.status().intersects(Status::WT_NEW | Status::WT_MODIFIED | Status::WT_TYPECHANGE))
+        .map(|entry| crate::fs::path_from_git_bytes(entry.path_bytes()))
+        .collect())
+}
+
+/// Stage every change in the working tree (respecting .gitignore). Unless `allow_symlinks_outside_repo`,
+/// refuses with `SuspiciousSymlink` when a change is a symlink pointing outside the working tree.
+pub fn stage_all(repo: &Repository, allow_symlinks_outside_repo: bool) -> Result<()> {
+    if !allow_symlinks_outside_repo {
+        refuse_escaping_symlinks(require_workdir(repo)?, &changed_paths(repo, true)?)?;
+    }
+    let mut index = repo.index()?;
+    index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+    index.update_all(["*"].iter(), None)?;
+    index.write()?;
+    Ok(())
+}
+
+/// Tracked files under `path` with uncommitted changes (staged or not), as absolute paths
+pub fn modified_tracked_files(path: &Path) -> Vec<PathBuf> {
+    let Ok(repo) = Repository::discover(path) else { return Vec::new() };
+    let Some(workdir) = repo.workdir().map(|w| w.to_path_buf()) else { return Vec::new() };
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let workdir = fs::canonicalize(&workdir).unwrap_or(workdir);
+    let Ok(relative) = canonical.strip_prefix(&workdir) else { return Vec::new() };
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(false).include_ignored(false);
+    if !relative.as_os_str().is_empty() {
+        opts.pathspec(relative);
+    }
+
+    let changed = Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE
+        | Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE;
+    let files = match repo.statuses(Some(&mut opts)) {
+        Ok(statuses) => statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(changed))
+            .filter_map(|entry| entry.path().map(|p| workdir.join(p)))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files
+}
+
+/// Credential store service name for a remote URL
+fn credential_service(remote_url: &str) -> String {
+    // Use remote URL as service namespace; include app prefix
+    format!("agentic-ide:git:{}", remote_url)
+}
+
+/// Load stored credentials (username, token) for a given remote: the per-remote entry, then a matching profile
+pub(crate) fn load_stored_credentials(store: &CredentialStore, repo: &Repository, remote_name: &str) -> Result<Option<(String, String)>> {
+    let remote = repo.find_remote(remote_name)?;
+    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
+    let service = credential_service(remote_url);
+
+    // Try common usernames: prefer stored list by probing a few likely usernames
+    // We don’t have a way to list keyring entries, so we try a set
+    let candidate_users = ["git", "github", "oauth", "token"];
+    for user in candidate_users.iter() {
+        if let Some(secret) = store.get(&service, user)? {
+            return Ok(Some((user.to_string(), secret)));
+        }
+    }
+
+    // Then any credential profile whose host pattern covers this remote
+    Ok(store.profile_for_url(remote_url)?)
+}
+
+/// Save credentials (username + token) to the credential store for a given remote
+pub fn save_git_credentials(store: &CredentialStore, repo_path: &Path, remote_name: &str, username: &str, password: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    let remote = repo.find_remote(remote_name)?;
+    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
+    store.set(&credential_service(remote_url), username, password)?;
+    Ok(())
+}
+
+/// Clear stored credentials for a given remote and username
+pub fn clear_git_credentials(store: &CredentialStore, repo_path: &Path, remote_name: &str, username: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    let remote = repo.find_remote(remote_name)?;
+    let remote_url = remote.url().ok_or_else(|| anyhow!("Remote URL is missing or invalid"))?;
+    store.delete(&credential_service(remote_url), username)?;
+    Ok(())
+}
+
+/// Repo-relative, `/`-separated paths of the non-ignored files under `dir`
+fn list_unignored_files(workdir: &Path, dir: &Path) -> Result<Vec<String>> {
+    let matcher = crate::gitignore::IgnoreMatcher::new(workdir)?;
+    let walker = walkdir::WalkDir::new(dir).into_iter().filter_entry(|e| {
+        e.file_name() != ".git" && !matcher.is_ignored(e.path(), e.file_type().is_dir())
+    });
+
+    let mut files = Vec::new();
+    for entry in walker.filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(workdir).unwrap_or(entry.path());
+        files.push(git_path_string(relative));
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// List the files inside a directory that status reports as a single untracked entry, so they
+/// can be staged one by one. `dir_path` may be absolute or relative to the working tree.
+pub fn expand_untracked_directory(repo_path: &Path, dir_path: &str) -> Result<Vec<String>> {
+    let repo = Repository::open(repo_path)?;
+    let workdir = require_workdir(&repo)?;
+    let dir = workdir.join(crate::fs::decode_path(dir_path.trim_end_matches('/')));
+    if !dir.is_dir() {
+        return Err(anyhow!("'{}' is not a directory", dir_path));
+    }
+    // Only a repository-local path can be staged
+    let (workdir, dir) = (fs::canonicalize(workdir)?, fs::canonicalize(&dir)?);
+    if !dir.starts_with(&workdir) {
+        return Err(anyhow!("'{}' is outside the repository", dir_path));
+    }
+    list_unignored_files(&workdir, &dir)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DirectoryStatusCounts {
+    pub modified: usize,
+    pub untracked: usize,
+    pub staged: usize,
+    pub conflicted: usize,
+}
+
+/// Per-directory counts of changed files below each directory, keyed by the directory's
+/// absolute path in the same form `list_directory` returns. Ignored files are excluded.
+pub fn get_directory_status_rollup(repo_path: &Path) -> Result<BTreeMap<String, DirectoryStatusCounts>> {
+    let repo = Repository::open(repo_path)?;
+    let workdir = require_workdir(&repo)?.to_path_buf();
+
+    let mut opts = StatusOptions::new();
+    opts.include_ignored(false).include_untracked(true).recurse_untracked_dirs(false);
+    let statuses = repo.statuses(Some(&mut opts))?;
+
+    let modified_flags = Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+    let staged_flags = Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE;
+
+    let sparse = skip_worktree_paths(&repo);
+
+    // Accumulate by relative directory first; the root is ""
+    let mut rollup: HashMap<String, DirectoryStatusCounts> = HashMap::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        if status == Status::WT_DELETED && sparse.contains(entry.path_bytes()) {
+            continue;
+        }
+        let Some(path) = entry.path() else { continue };
+        // Untracked directories are reported once as "dir/" and count towards themselves too
+        let (path, is_dir) = match path.strip_suffix('/') {
+            Some(dir) => (dir, true),
+            None => (path, false),
+        };
+
+        let mut dirs: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+        dirs.push("");
+        if is_dir {
+            dirs.push(path);
+        }
+        for dir in dirs {
+            let counts = rollup.entry(dir.to_string()).or_default();
+            if status.contains(Status::CONFLICTED) {
+                counts.conflicted += 1;
+                continue;
+            }
+            if status.intersects(modified_flags) {
+                counts.modified += 1;
+            }
+            if status.contains(Status::WT_NEW) {
+                counts.untracked += 1;
+            }
+            if status.intersects(staged_flags) {
+                counts.staged += 1;
+            }
+        }
+    }
+
+    Ok(rollup
+        .into_iter()
+        .map(|(dir, counts)| {
+            let absolute = if dir.is_empty() { workdir.clone() } else { workdir.join(&dir) };
+            let key = crate::fs::display_path(&absolute);
+            (key.strip_suffix(['/', '\\']).map(str::to_string).unwrap_or(key), counts)
+        })
+        .collect())
+}
+
+/// Index entries at or under a path that is about to be renamed or moved
+#[derive(Clone, Debug)]
+pub struct TrackedPaths {
+    pub workdir: PathBuf,
+    /// `/`-separated path of the moved file or directory relative to `workdir`
+    pub relative: String,
+    pub entries: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct IndexMoveResult {
+    /// New paths now staged as the other half of a rename
+    pub staged: Vec<String>,
+    /// Paths git still sees as a delete or an untracked add
+    pub needs_staging: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl IndexMoveResult {
+    /// Nothing was staged; every affected path is left for the user
+    pub fn failed(tracked: &TrackedPaths, error: &GitError) -> Self {
+        IndexMoveResult { staged: Vec::new(), needs_staging: tracked.entries.clone(), error: Some(error.to_string()) }
+    }
+}
+
+fn canonical_relative(workdir: &Path, path: &Path) -> Option<String> {
+    let canonical = fs::canonicalize(path).ok()?;
+    let relative = canonical.strip_prefix(workdir).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Stage a single file by absolute path; false outside a repository or when git ignores it
+pub fn stage_path(path: &Path) -> Result<bool> {
+    let Ok(repo) = Repository::discover(path.parent().unwrap_or(path)) else { return Ok(false) };
+    let Some(workdir) = repo.workdir().and_then(|w| fs::canonicalize(w).ok()) else { return Ok(false) };
+    let Some(relative) = canonical_relative(&workdir, path) else { return Ok(false) };
+    if repo.is_path_ignored(Path::new(&relative)).unwrap_or(false) {
+        return Ok(false);
+    }
+    let mut index = repo.index()?;
+    add_to_index(&mut index, &workdir, &[Path::new(&relative)], false)?;
+    index.write()?;
+    Ok(true)
+}
+
+/// Collect the tracked files under `path` before it is moved; None outside a repository or
+/// when nothing there is tracked (untracked and ignored files need no index update)
+pub fn tracked_under(path: &Path) -> Option<TrackedPaths> {
+    let repo = Repository::discover(path.parent().unwrap_or(path)).ok()?;
+    let workdir = fs::canonicalize(repo.workdir()?).ok()?;
+    let relative = canonical_relative(&workdir, path)?;
+    let prefix = format!("{}/", relative);
+
+    let index = repo.index().ok()?;
+    let entries: Vec<String> = index
+        .iter()
+        .map(|e| String::from_utf8_lossy(&e.path).to_string())
+        .filter(|p| *p == relative || p.starts_with(&prefix))
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    Some(TrackedPaths { workdir, relative, entries })
+}
+
+/// After `tracked.relative` was moved to `to`, replace its index entries in one write so git
+/// reports staged renames instead of a delete plus untracked files
+pub fn stage_move(tracked: &TrackedPaths, to: &Path) -> Result<IndexMoveResult> {
+    let repo = Repository::open(&tracked.workdir)?;
+    let mut index = repo.index()?;
+    // Moved out of this repository: only the removal can be staged here
+    let target = canonical_relative(&tracked.workdir, to);
+
+    let mut result = IndexMoveResult::default();
+    for old in &tracked.entries {
+        index.remove_path(Path::new(old))?;
+        let Some(target) = &target else { continue };
+        let new = format!("{}{}", target, &old[tracked.relative.len()..]);
+        if !tracked.workdir.join(&new).is_file() {
+            result.needs_staging.push(new);
+            continue;
+        }
+        match index.add_path(Path::new(&new)) {
+            Ok(_) => result.staged.push(new),
+            Err(_) => result.needs_staging.push(new),
+        }
+    }
+    index.write()?;
+    Ok(result)
+}
+
+// Shown when git cannot help; the file may still be recoverable elsewhere
+const RESTORE_HINT: &str = "git has no committed copy. Check the system trash or the editor's undo history instead.";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestoreResult {
+    pub restored: Vec<String>,
+    /// Requested paths the index has no copy of (never added, or staged for deletion), left alone
+    pub not_committed: Vec<String>,
+}
+
+/// Relative, `/`-separated path of `path` inside the working tree
+fn workdir_relative(workdir: &Path, path: &str) -> String {
+    let path = crate::fs::decode_path(path);
+    git_path_string(path.strip_prefix(workdir).unwrap_or(&path))
+}
+
+/// Escape glob characters so a checkout pathspec names exactly one path
+fn literal_pathspec(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Check out files the index still tracks but that are missing from the working tree, from
+/// the index. With no `paths`, every such file is restored; files that are present, and staged
+/// deletions (`git rm`), are never touched.
+pub fn restore_deleted_files(repo_path: &Path, paths: Option<&[String]>) -> Result<RestoreResult> {
+    let not_restorable = |paths: Vec<String>| GitIndexError::NotRestorable { paths, hint: RESTORE_HINT.to_string() };
+    let Ok(repo) = Repository::open(repo_path) else {
+        return Err(GitIndexError::not_a_repository(repo_path).into());
+    };
+    let workdir = require_workdir(&repo)?.to_path_buf();
+
+    let candidates: Vec<String> = match paths {
+        Some(paths) => paths.iter().map(|p| workdir_relative(&workdir, p)).collect(),
+        None => {
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(false);
+            let sparse = skip_worktree_paths(&repo);
+            repo.statuses(Some(&mut opts))?
+                .iter()
+                .filter(|e| e.status().contains(Status::WT_DELETED) && !e.status().contains(Status::INDEX_DELETED))
+                .filter(|e| !sparse.contains(e.path_bytes()))
+                .filter_map(|e| e.path().map(|p| p.to_string()))
+                .collect()
+        }
+    };
+
+    let mut index = repo.index()?;
+    let mut restorable = Vec::new();
+    let mut not_committed = Vec::new();
+    for path in candidates {
+        if index.get_path(Path::new(&path), 0).is_none() {
+            not_committed.push(path);
+        } else if !workdir.join(&path).exists() {
+            restorable.push(path);
+        }
+    }
+    if restorable.is_empty() && !not_committed.is_empty() {
+        return Err(not_restorable(not_committed).into());
+    }
+
+    if !restorable.is_empty() {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force();
+        for path in &restorable {
+            checkout.path(literal_pathspec(path));
+        }
+        repo.checkout_index(Some(&mut index), Some(&mut checkout))?;
+    }
+
+    Ok(RestoreResult { restored: restorable, not_committed })
+}
+
+// Marks stashes made by `checkout_branch`; stash messages are the only tag git keeps
+const AUTO_STASH_MARKER: &str = "agentic-ide auto-stash";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckoutResult {
+    /// "checked_out", "reapplied", "reapply_conflicts" or "checkout_failed"
+    pub outcome: String,
+    pub stashed: bool,
+    /// Files left conflicted by reapplying the stash; the stash is kept in that case
+    pub conflicts: Vec<String>,
+    pub error: Option<String>,
+    /// The local branch checked out; differs from the name asked for when that was a
+    /// remote-tracking branch
+    #[serde(default)]
+    pub branch: String,
+    /// A local branch was created to track the remote-tracking branch asked for
+    #[serde(default)]
+    pub created_tracking_branch: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+    pub oid: String,
+    pub timestamp: i64,
+    pub auto_stash: bool,
+    /// Human label for stashes made by the IDE
+    pub label: Option<String>,
+}
+
+/// Check out a local branch. A safe checkout refuses with `CheckoutBlocked`, listing the files
+/// whose local changes it would overwrite; `force` discards those changes instead.
+fn switch_to(repo: &Repository, branch_name: &str, force: bool) -> Result<()> {
+    let branch = repo
+        .find_branch(branch_name, BranchType::Local)
+        .map_err(|_| anyhow!("Local branch '{}' does not exist", branch_name))?;
+    let reference = branch.get().name().ok_or_else(|| anyhow!("Branch name is not valid UTF-8"))?.to_string();
+    let target = branch.get().peel_to_commit()?;
+
+    // A checkout rewrites the working tree under index.lock for as long as it takes; journal it
+    // so a crash midway lets the next startup clear the lock
+    let _journaled = crate::shutdown::track(crate::shutdown::Artifact::IndexLock { git_dir: repo.path().to_path_buf() });
+    let mut blocking = Vec::new();
+    let checked_out = {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        if force {
+            checkout.force();
+        } else {
+            checkout.safe().notify_on(git2::CheckoutNotificationType::CONFLICT).notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    blocking.push(path.to_string_lossy().replace('\\', "/"));
+                }
+                true
+            });
+        }
+        repo.checkout_tree(target.as_object(), Some(&mut checkout))
+    };
+    if let Err(e) = checked_out {
+        if blocking.is_empty() {
+            return Err(e.into());
+        }
+        blocking.sort();
+        blocking.dedup();
+        return Err(anyhow!(GitError::CheckoutBlocked { paths: blocking }));
+    }
+    repo.set_head(&reference)?;
+    Ok(())
+}
+
+/// The local branch to check out for `name`. A remote-tracking name such as "origin/feature-x"
+/// resolves to the local "feature-x", which is created tracking it when missing; the flag says
+/// whether it was created.
+fn local_branch_for(repo: &Repository, name: &str) -> Result<(String, bool)> {
+    if repo.find_branch(name, BranchType::Local).is_ok() {
+        return Ok((name.to_string(), false));
+    }
+    let Ok(remote_branch) = repo.find_branch(name, BranchType::Remote) else { return Ok((name.to_string(), false)) };
+    let remotes = repo.remotes()?;
+    let local = remotes
+        .iter()
+        .flatten()
+        .find_map(|remote| name.strip_prefix(&format!("{}/", remote)))
+        .ok_or_else(|| anyhow!("'{}' does not name a branch of a configured remote", name))?
+        .to_string();
+    if repo.find_branch(&local, BranchType::Local).is_ok() {
+        return Ok((local, false));
+    }
+    let commit = remote_branch.get().peel_to_commit()?;
+    let mut branch = repo.branch(&local, &commit, false)?;
+    branch.set_upstream(Some(name))?;
+    Ok((local, true))
+}
+
+fn stash_index(repo: &mut Repository, oid: git2::Oid) -> Option<usize> {
+    let mut found = None;
+    let _ = repo.stash_foreach(|index, _, stash_oid| {
+        if *stash_oid == oid {
+            found = Some(index);
+        }
+        found.is_none()
+    });
+    found
+}
+
+fn conflicted_paths(repo: &Repository) -> Result<Vec<String>> {
+    let index = repo.index()?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<String> = index
+        .conflicts()?
+        .filter_map(|c| c.ok())
+        .filter_map(|c| c.our.or(c.their).or(c.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn has_local_changes(repo: &Repository) -> Result<bool> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+/// Switch to a local branch, or to a local branch tracking a remote-tracking one ("origin/x"
+/// checks out "x", creating it when missing). With `auto_stash`, local changes (untracked files
+/// included) are stashed first and reapplied on the new branch; a failed checkout puts them back
+/// where they were. Without it, local changes the switch would overwrite refuse the checkout
+/// with `CheckoutBlocked`, unless `force` discards them. Unless `allow_case_collisions`, refuses
+/// with `CaseCollision` when the branch has paths this filesystem would fold into one file.
+pub fn checkout_branch(repo_path: &Path, branch_name: &str, auto_stash: bool, allow_case_collisions: bool, force: bool) -> Result<CheckoutResult> {
+    let mut repo = Repository::open(repo_path)?;
+    require_workdir(&repo)?;
+    if !allow_case_collisions {
+        let tree = repo
+            .find_branch(branch_name, BranchType::Local)
+            .or_else(|_| repo.find_branch(branch_name, BranchType::Remote))
+            .ok()
+            .map(|branch| branch.get().peel_to_tree())
+            .transpose()?;
+        if let Some(tree) = tree {
+            refuse_colliding_tree(&repo, &tree)?;
+        }
+    }
+    let (local, created) = local_branch_for(&repo, branch_name)?;
+    let branch_name = local.as_str();
+    let result = |outcome: &str, stashed: bool, conflicts: Vec<String>, error: Option<String>| CheckoutResult {
+        outcome: outcome.to_string(),
+        stashed,
+        conflicts,
+        error,
+        branch: local.clone(),
+        created_tracking_branch: created,
+    };
+    // A tracking branch created for a checkout that then fails is removed again
+    let undo_created = |repo: &Repository| {
+        if created {
+            if let Ok(mut branch) = repo.find_branch(branch_name, BranchType::Local) {
+                let _ = branch.delete();
+            }
+        }
+    };
+
+    if !auto_stash || !has_local_changes(&repo)? {
+        if let Err(e) = switch_to(&repo, branch_name, force) {
+            undo_created(&repo);
+            return Err(e);
+        }
+        return Ok(result("checked_out", false, Vec::new(), None));
+    }
+
+    let from = repo.head().ok().and_then(|h| h.shorthand().map(str::to_string)).unwrap_or_else(|| "HEAD".to_string());
+    let signature = repo.signature().or_else(|_| git2::Signature::now("Agentic IDE", "agentic-ide@localhost"))?;
+    let message = format!("{}: branch switch from {} to {}", AUTO_STASH_MARKER, from, branch_name);
+    let stash = repo.stash_save(&signature, &message, Some(git2::StashFlags::INCLUDE_UNTRACKED))?;
+
+    if let Err(e) = switch_to(&repo, branch_name, force) {
+        undo_created(&repo);
+        let index = stash_index(&mut repo, stash).ok_or_else(|| anyhow!("Auto-stash disappeared after a failed checkout"))?;
+        repo.stash_pop(index, None)
+            .map_err(|pop_error| anyhow!("Checkout failed ({}) and the auto-stash could not be restored: {}", e, pop_error))?;
+        return Ok(result("checkout_failed", true, Vec::new(), Some(e.to_string())));
+    }
+
+    let index = stash_index(&mut repo, stash).ok_or_else(|| anyhow!("Auto-stash disappeared during checkout"))?;
+    // A failed apply can still leave conflicted entries behind; list them either way
+    let applied = repo.stash_apply(index, None);
+    let conflicts = conflicted_paths(&repo)?;
+    if let Err(e) = applied {
+        return Ok(result("reapply_conflicts", true, conflicts, Some(e.to_string())));
+    }
+    if !conflicts.is_empty() {
+        return Ok(result("reapply_conflicts", true, conflicts, None));
+    }
+    repo.stash_drop(index)?;
+    Ok(result("reapplied", true, Vec::new(), None))
+}
+
+/// Stash entries, newest first, with IDE-made auto-stashes labelled
+pub fn stash_list(repo_path: &Path) -> Result<Vec<StashEntry>> {
+    let mut repo = Repository::open(repo_path)?;
+    let mut raw = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        raw.push((index, message.to_string(), *oid));
+        true
+    })?;
+    Ok(raw
+        .into_iter()
+        .map(|(index, message, oid)| {
+            let auto_stash = message.contains(AUTO_STASH_MARKER);
+            StashEntry {
+                index,
+                timestamp: repo.find_commit(oid).map(|c| c.time().seconds()).unwrap_or(0),
+                label: auto_stash.then(|| "auto-stash from branch switch".to_string()),
+                auto_stash,
+                message,
+                oid: oid.to_string(),
+            }
+        })
+        .collect())
+}
+
+/// Drop auto-stashes older than `older_than_days`; returns how many were dropped
+pub fn drop_stale_auto_stashes(repo_path: &Path, older_than_days: u64) -> Result<usize> {
+    let cutoff = chrono::Utc::now().timestamp() - (older_than_days * 24 * 60 * 60) as i64;
+    let stale: Vec<usize> = stash_list(repo_path)?
+        .into_iter()
+        .filter(|entry| entry.auto_stash && entry.timestamp < cutoff)
+        .map(|entry| entry.index)
+        .collect();
+    let mut repo = Repository::open(repo_path)?;
+    // Highest index first so the remaining indices stay valid
+    for index in stale.iter().rev() {
+        repo.stash_drop(*index)?;
+    }
+    Ok(stale.len())
+}
+
+/// Files in the index, fuzzy-filtered by `pattern` when given and restricted to the absolute
+/// paths in `only` when given. Status flags are looked up only for the entries returned, so
+/// large indexes stay cheap. Files that are or sit under one of the absolute `pinned` paths
+/// come first, in pin order.
+pub fn list_tracked_files(state: &GitState, repo_path: &Path, pattern: Option<&str>, only: Option<&std::collections::HashSet<PathBuf>>, pinned: &[PathBuf], limit: usize) -> Result<Vec<TrackedFile>> {
+    let (repo, all_paths) = state.indexed_paths(repo_path)?;
+    let pattern = pattern.map(str::trim).filter(|p| !p.is_empty());
+    let workdir = repo.workdir();
+    let relative = |path: &PathBuf| {
+        let relative = path.strip_prefix(workdir?).ok()?;
+        Some(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+    };
+    let paths: Vec<&IndexedPath> = match only {
+        Some(only) => {
+            let only: std::collections::HashSet<String> = only.iter().filter_map(relative).collect();
+            all_paths.iter().filter(|entry| only.contains(&entry.path)).collect()
+        }
+        None => all_paths.iter().collect(),
+    };
+
+    let mut matches: Vec<(&IndexedPath, Option<i64>)> = match pattern {
+        Some(pattern) => {
+            let mut scored: Vec<(&IndexedPath, Option<i64>)> = paths
+                .into_iter()
+                .filter_map(|entry| {
+                    let name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+                    // Names count more than directories, so "main" finds src/main.rs before main/x.rs
+                    let score = crate::symbols::fuzzy_score(pattern, name)
+                        .map(|s| s + 100)
+                        .or_else(|| crate::symbols::fuzzy_score(pattern, &entry.path))?;
+                    Some((entry, Some(score)))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+            scored
+        }
+        None => paths.into_iter().map(|entry| (entry, None)).collect(),
+    };
+    let pinned: Vec<String> = pinned.iter().filter_map(relative).collect();
+    if !pinned.is_empty() {
+        let rank = |path: &str| pinned.iter().position(|pin| path == pin || path.strip_prefix(pin.as_str()).is_some_and(|rest| rest.starts_with('/')));
+        matches.sort_by_key(|(entry, _)| rank(&entry.path).unwrap_or(usize::MAX));
+    }
+    matches.truncate(limit);
+
+    Ok(matches
+        .into_iter()
+        .map(|(entry, score)| {
+            let status = if entry.skip_worktree { Status::CURRENT } else { repo.status_file(Path::new(&entry.path)).unwrap_or(Status::CURRENT) };
+            TrackedFile {
+                path: entry.path.clone(),
+                staged: !entry.intent_to_add && status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE),
+                modified: !entry.intent_to_add && status.intersects(Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE),
+                skip_worktree: entry.skip_worktree,
+                intent_to_add: entry.intent_to_add,
+                score,
+            }
+        })
+        .collect())
+}
+
+/// Check if a directory is already a Git repository
+pub fn is_git_repository(repo_path: &Path) -> bool {
+    Repository::open(repo_path).is_ok()
+}
+
+/// Get Git configuration for the repository
+pub fn get_git_config(repo_path: &Path) -> Result<GitConfig> {
+    let repo = Repository::open(repo_path)?;
+    let config = repo.config()?;
+    
+    let user_name = config.get_string("user.name").ok();
+    let user_email = config.get_string("user.email").ok();
+    
+    let is_configured = user_name.is_some() && user_email.is_some();
+    
+    Ok(GitConfig {
+        user_name,
+        user_email,
+        is_configured,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IdentityFieldError {
+    /// "name" or "email"
+    pub field: String,
+    pub message: String,
+}
+
+/// Problems that would make git reject `name <email>` as a signature
+pub fn identity_errors(name: &str, email: &str) -> Vec<IdentityFieldError> {
+    let mut errors = Vec::new();
+    let mut reject = |field: &str, message: &str| errors.push(IdentityFieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    });
+    if name.is_empty() {
+        reject("name", "Name cannot be empty");
+    }
+    if name.chars().any(char::is_control) {
+        reject("name", "Name cannot contain line breaks or control characters");
+    }
+    if name.contains(['<', '>']) {
+        reject("name", "Name cannot contain '<' or '>'");
+    }
+    if email.chars().any(|c| c.is_control() || c.is_whitespace() || c == '<' || c == '>') {
+        reject("email", "Email cannot contain spaces, control characters, '<' or '>'");
+    }
+    match email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() && !domain.contains('@') => {}
+        _ => reject("email", "Email must look like name@host"),
+    }
+    errors
+}
+
+/// Trim `name` and `email` and check they make a valid signature
+pub fn normalize_identity(name: &str, email: &str) -> std::result::Result<(String, String), GitError> {
+    let (name, email) = (name.trim(), email.trim());
+    let errors = identity_errors(name, email);
+    if !errors.is_empty() {
+        return Err(GitError::InvalidIdentity { errors });
+    }
+    Ok((name.to_string(), email.to_string()))
+}
+
+/// Validate and store user.name/user.email at `scope`, returning the effective configuration
+/// afterwards (a local value still wins over a newly written global one)
+pub fn set_git_config(repo_path: &Path, name: &str, email: &str, scope: ConfigScope) -> std::result::Result<GitConfig, GitError> {
+    let (name, email) = normalize_identity(name, email)?;
+    let write = || -> Result<()> {
+        let mut config = match scope {
+            ConfigScope::Local => Repository::open(repo_path)?.config()?.open_level(git2::ConfigLevel::Local)?,
+            ConfigScope::Global => git2::Config::open_default()?.open_level(git2::ConfigLevel::Global)?,
+        };
+        config.set_str("user.name", &name)?;
+        config.set_str("user.email", &email)?;
+        Ok(())
+    };
+    write()?;
+    Ok(get_git_config(repo_path)?)
+}
+
+/// A named user.name/user.email pair the user can switch between
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitIdentity {
+    pub label: String,
+    pub name: String,
+    pub email: String,
+    pub signing_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigValue {
+    pub value: String,
+    /// Config level the value came from: "local", "global", "xdg", "system", ...
+    pub origin: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EffectiveIdentity {
+    pub name: Option<ConfigValue>,
+    pub email: Option<ConfigValue>,
+    pub signing_key: Option<ConfigValue>,
+    /// Saved identity whose name and email match, if any
+    pub matched_label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigScope {
+    Local,
+    Global,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommitResult {
+    pub commit_id: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// Files the commit changed relative to its first parent
+    #[serde(default)]
+    pub files: Vec<DiffFileEntry>,
+    /// Likely secrets the commit added, when `block_on_secrets` only warns
+    #[serde(default)]
+    pub secret_findings: Vec<SecretFinding>,
+}
+
+fn level_name(level: git2::ConfigLevel) -> &'static str {
+    match level {
+        git2::ConfigLevel::ProgramData => "programdata",
+        git2::ConfigLevel::System => "system",
+        git2::ConfigLevel::XDG => "xdg",
+        git2::ConfigLevel::Global => "global",
+        git2::ConfigLevel::Local => "local",
+        git2::ConfigLevel::App => "app",
+        git2::ConfigLevel::Highest => "highest",
+    }
+}
+
+fn config_value(config: &git2::Config, key: &str) -> Option<ConfigValue> {
+    let entry = config.get_entry(key).ok()?;
+    Some(ConfigValue { value: entry.value()?.to_string(), origin: level_name(entry.level()).to_string() })
+}
+
+/// The identity a commit in `repo_path` would be made with right now, and where each part comes from
+pub fn get_effective_identity(repo_path: &Path, saved: &[GitIdentity]) -> Result<EffectiveIdentity> {
+    let repo = Repository::discover(repo_path)?;
+    let config = repo.config()?.snapshot()?;
+    let name = config_value(&config, "user.name");
+    let email = config_value(&config, "user.email");
+    let matched_label = saved
+        .iter()
+        .find(|identity| {
+            name.as_ref().map(|n| n.value == identity.name).unwrap_or(false)
+                && email.as_ref().map(|e| e.value == identity.email).unwrap_or(false)
+        })
+        .map(|identity| identity.label.clone());
+    Ok(EffectiveIdentity {
+        name,
+        email,
+        signing_key: config_value(&config, "user.signingkey"),
+        matched_label,
+    })
+}
+
+/// Write `identity` into the repository's own config or the user's global one
+pub fn apply_identity(repo_path: &Path, identity: &GitIdentity, scope: ConfigScope) -> Result<()> {
+    let mut config = match scope {
+        ConfigScope::Local => Repository::discover(repo_path)?.config()?.open_level(git2::ConfigLevel::Local)?,
+        ConfigScope::Global => git2::Config::open_default()?.open_level(git2::ConfigLevel::Global)?,
+    };
+    config.set_str("user.name", &identity.name)?;
+    config.set_str("user.email", &identity.email)?;
+    match &identity.signing_key {
+        Some(key) => config.set_str("user.signingkey", key)?,
+        // A stale key at this level would sign as the previous identity
+        None => {
+            let _ = config.remove("user.signingkey");
+        }
+    }
+    Ok(())
+}
+
+/// Author recorded on a commit, for "committed as ..." feedback
+pub fn committed_as(repo_path: &Path, commit_id: &str) -> Result<CommitResult> {
+    let repo = Repository::discover(repo_path)?;
+    let commit = repo.find_commit(git2::Oid::from_str(commit_id)?)?;
+    let author = commit.author();
+    let parent_tree = commit.parent(0).ok().map(|p| p.tree()).transpose()?;
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    diff.find_similar(None)?;
+    Ok(CommitResult {
+        commit_id: commit_id.to_string(),
+        author_name: author.name().unwrap_or_default().to_string(),
+        author_email: author.email().unwrap_or_default().to_string(),
+        files: diff_entries(&repo, &diff, false, false)?,
+        secret_findings: Vec::new(),
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchNameViolation {
+    pub rule: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchNameValidation {
+    pub valid: bool,
+    pub violations: Vec<BranchNameViolation>,
+}
+
+/// How generated branch names are shaped
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BranchNamingConvention {
+    /// Used when the caller gives no prefix, e.g. "feat/"
+    pub default_prefix: String,
+    /// Put the slugified `user.name` first, e.g. "jane-doe/feat/..."
+    pub include_username: bool,
+    pub max_length: usize,
+}
+
+impl Default for BranchNamingConvention {
+    fn default() -> Self {
+        Self {
+            default_prefix: String::new(),
+            include_username: false,
+            max_length: 60,
+        }
+    }
+}
+
+/// Check a branch name against git's ref-name rules (see `git check-ref-format`)
+pub fn validate_branch_name(name: &str) -> BranchNameValidation {
+    let mut violations = Vec::new();
+    let mut violate = |rule: &str, message: &str| violations.push(BranchNameViolation {
+        rule: rule.to_string(),
+        message: message.to_string(),
+    });
+
+    if name.is_empty() {
+        violate("empty", "Branch name cannot be empty");
+    }
+    if name == "@" || name == "HEAD" {
+        violate("reserved", "'@' and 'HEAD' are reserved names");
+    }
+    if name.chars().any(|c| c.is_whitespace()) {
+        violate("whitespace", "Branch name cannot contain spaces");
+    }
+    if name.chars().any(|c| c.is_control()) {
+        violate("control-character", "Branch name cannot contain control characters");
+    }
+    if name.contains("..") {
+        violate("double-dot", "Branch name cannot contain '..'");
+    }
+    if name.chars().any(|c| matches!(c, '~' | '^' | ':' | '?' | '*' | '[' | '\\')) {
+        violate("special-character", "Branch name cannot contain ~ ^ : ? * [ or \\");
+    }
+    if name.contains("@{") {
+        violate("at-brace", "Branch name cannot contain '@{'");
+    }
+    if name.starts_with('-') {
+        violate("leading-dash", "Branch name cannot start with '-'");
+    }
+    if name.starts_with('/') || name.ends_with('/') {
+        violate("edge-slash", "Branch name cannot start or end with '/'");
+    }
+    if name.contains("//") {
+        violate("double-slash", "Branch name cannot contain consecutive slashes");
+    }
+    if name.ends_with('.') {
+        violate("trailing-dot", "Branch name cannot end with '.'");
+    }
+    if name.split('/').any(|component| component.starts_with('.')) {
+        violate("dot-component", "No part of a branch name can start with '.'");
+    }
+    if name.split('/').any(|component| component.ends_with(".lock")) {
+        violate("lock-suffix", "No part of a branch name can end with '.lock'");
+    }
+
+    BranchNameValidation { valid: violations.is_empty(), violations }
+}
+
+fn slugify_branch_part(text: &str) -> String {
+    let slug: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+/// Build a valid, unused branch name from a free-form description
+pub fn suggest_branch_name(repo_path: &Path, description: &str, prefix: Option<&str>, convention: &BranchNamingConvention) -> Result<String> {
+    let repo = Repository::open(repo_path)?;
+
+    let mut head = String::new();
+    if convention.include_username {
+        if let Ok(user) = repo.config().and_then(|c| c.get_string("user.name")) {
+            let user = slugify_branch_part(&user);
+            if !user.is_empty() {
+                head.push_str(&user);
+                head.push('/');
+            }
+        }
+    }
+    let prefix = prefix.unwrap_or(&convention.default_prefix).trim().trim_matches('/');
+    let prefix: Vec<String> = prefix.split('/').map(slugify_branch_part).filter(|p| !p.is_empty()).collect();
+    if !prefix.is_empty() {
+        head.push_str(&prefix.join("/"));
+        head.push('/');
+    }
+
+    let mut slug = slugify_branch_part(description);
+    if slug.is_empty() {
+        slug = "branch".to_string();
+    }
+    // Leave room for a uniqueness counter
+    let budget = convention.max_length.saturating_sub(head.len() + 4).max(8);
+    if slug.len() > budget {
+        slug.truncate(budget);
+        slug = slug.trim_end_matches('-').to_string();
+    }
+
+    // Remote branches count as taken too, without their remote name
+    let remotes: Vec<String> = repo.remotes()?.iter().flatten().map(|r| format!("{}/", r)).collect();
+    let existing: Vec<String> = repo
+        .branches(None)?
+        .filter_map(|b| b.ok())
+        .filter_map(|(branch, kind)| {
+            let name = branch.name().ok().flatten()?.to_string();
+            match kind {
+                BranchType::Local => Some(name),
+                BranchType::Remote => remotes
+                    .iter()
+                    .find_map(|r| name.strip_prefix(r.as_str()).map(String::from))
+                    .or(Some(name)),
+            }
+        })
+        .collect();
+
+    let base = format!("{}{}", head, slug);
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while existing.contains(&candidate) {
+        candidate = format!("{}-{}", base, n);
+        n += 1;
+    }
+    Ok(candidate)
+}
+
+/// Notes ref used for agent metadata unless the caller names another namespace
+pub const DEFAULT_NOTES_REF: &str = "refs/notes/agentic-ide";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitNote {
+    pub commit_hash: String,
+    pub note: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommitDetails {
+    pub hash: String,
+    /// Raw message; superseded by `summary`/`body`/`trailers` and kept for one release
+    pub message: String,
+    #[serde(flatten)]
+    pub parsed: CommitMessage,
+    pub author: String,
+    pub author_email: String,
+    pub timestamp: i64,
+    pub parents: Vec<String>,
+    /// Note from the default notes namespace, if any
+    pub note: Option<String>,
+}
+
+/// Accept "agentic-ide" or a full "refs/notes/..." name
+fn notes_ref(namespace: Option<&str>) -> String {
+    match namespace.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(ns) if ns.starts_with("refs/notes/") => ns.to_string(),
+        Some(ns) => format!("refs/notes/{}", ns),
+        None => DEFAULT_NOTES_REF.to_string(),
+    }
+}
+
+/// Where `pull` fetches a remote's copy of `notes_ref`, e.g. "refs/notes/remotes/origin/agentic-ide"
+fn remote_notes_ref(remote_name: &str, notes_ref: &str) -> String {
+    let namespace = notes_ref.strip_prefix("refs/notes/").unwrap_or(notes_ref);
+    format!("refs/notes/remotes/{}/{}", remote_name, namespace)
+}
+
+/// Copy notes from `from` into `into`. Without a local ref the fetched one is taken as is;
+/// otherwise notes only the remote has are added and local notes win on the same commit.
+/// Returns how many notes were added.
+fn merge_notes(repo: &Repository, from: &str, into: &str) -> Result<usize> {
+    let fetched = match repo.find_reference(from) {
+        Ok(reference) => reference,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(target) = fetched.target() else { return Ok(0) };
+    if repo.find_reference(into).is_err() {
+        repo.reference(into, target, false, "notes: take fetched notes")?;
+        return Ok(repo.notes(Some(into))?.count());
+    }
+
+    let sig = repo.signature()?;
+    let mut added = 0;
+    for entry in repo.notes(Some(from))? {
+        let (note_id, commit_id) = entry?;
+        match repo.find_note(Some(into), commit_id) {
+            Ok(_) => continue,
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let blob = repo.find_blob(note_id)?;
+        repo.note(&sig, &sig, Some(into), commit_id, &String::from_utf8_lossy(blob.content()), false)?;
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// Attach (or replace) a note on a commit. Notes are keyed by commit id, so a commit that is
+/// rebased or amended away leaves its note orphaned rather than following the new commit.
+pub fn add_git_note(repo_path: &Path, commit_hash: &str, note: &str, namespace: Option<&str>) -> Result<String> {
+    let repo = Repository::open(repo_path)?;
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    let sig = repo.signature()?;
+    let notes_ref = notes_ref(namespace);
+    let note_id = repo.note(&sig, &sig, Some(&notes_ref), commit.id(), note, true)?;
+    Ok(note_id.to_string())
+}
+
+pub fn get_git_note(repo_path: &Path, commit_hash: &str, namespace: Option<&str>) -> Result<Option<String>> {
+    let repo = Repository::open(repo_path)?;
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    let notes_ref = notes_ref(namespace);
+    let message = match repo.find_note(Some(&notes_ref), commit.id()) {
+        Ok(note) => note.message().map(String::from),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(message)
+}
+
+/// Every note in the namespace, including orphaned ones whose commit no longer exists
+pub fn list_git_notes(repo_path: &Path, namespace: Option<&str>) -> Result<Vec<GitNote>> {
+    let repo = Repository::open(repo_path)?;
+    let notes_ref = notes_ref(namespace);
+    let iter = match repo.notes(Some(&notes_ref)) {
+        Ok(iter) => iter,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut notes = Vec::new();
+    for entry in iter {
+        let (note_id, commit_id) = entry?;
+        let blob = repo.find_blob(note_id)?;
+        notes.push(GitNote {
+            commit_hash: commit_id.to_string(),
+            note: String::from_utf8_lossy(blob.content()).to_string(),
+        });
+    }
+    Ok(notes)
+}
+
+pub fn get_commit_details(repo_path: &Path, commit_hash: &str, issue_patterns: &[String]) -> Result<CommitDetails> {
+    let repo = Repository::open(repo_path)?;
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    Ok(commit_details(&repo, &commit, &commit_message::compile_issue_patterns(issue_patterns)))
+}
+
+/// Details of an already loaded commit, for callers describing several from one open repository
+pub fn commit_details(repo: &Repository, commit: &git2::Commit, issue_patterns: &[regex::Regex]) -> CommitDetails {
+    let note = repo
+        .find_note(Some(DEFAULT_NOTES_REF), commit.id())
+        .ok()
+        .and_then(|n| n.message().map(String::from));
+
+    let message = commit.message().unwrap_or("").to_string();
+    CommitDetails {
+        hash: commit.id().to_string(),
+        parsed: commit_message::parse(&message, issue_patterns),
+        message,
+        author: commit.author().name().unwrap_or("Unknown").to_string(),
+        author_email: commit.author().email().unwrap_or("").to_string(),
+        timestamp: commit.time().seconds(),
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        note,
+    }
+}
+
+// Patches above this size are reported as truncated rather than returned
+const MAX_PATCH_BYTES: usize = 256 * 1024;
+
+// Once this much patch text has been returned, later files only get their stats
+const MAX_TOTAL_PATCH_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiffFileEntry {
+    pub path: String,
+    /// Set for renames and copies
+    pub old_path: Option<String>,
+    /// "added", "deleted", "modified", "renamed", "copied" or "typechange"
+    pub status: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+    pub patch: Option<String>,
+    /// The patch was left out because it, or the diff as a whole, was too large
+    pub truncated: bool,
+    /// Marked `linguist-generated` in .gitattributes
+    #[serde(default)]
+    pub is_generated: bool,
+    /// .gitattributes suppresses this file's diff, so no patch was produced
+    #[serde(default)]
+    pub diff_suppressed: bool,
+}
+
+pub const DIFF_SUPPRESSED_NOTICE: &str = "diff suppressed by .gitattributes";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct DiffAttributes {
+    pub is_generated: bool,
+    /// `-diff`, or generated: lockfiles and bundles whose diff is noise
+    pub diff_suppressed: bool,
+}
+
+/// Effective .gitattributes for `path` (relative to the work tree), checking the working
+/// tree's attribute files before the index's
+pub fn diff_attributes(repo: &Repository, path: &Path) -> DiffAttributes {
+    let attr = |name: &str| {
+        repo.get_attr(path, name, git2::AttrCheckFlags::FILE_THEN_INDEX)
+            .ok()
+            .map(git2::AttrValue::from_string)
+    };
+    let is_generated = matches!(attr("linguist-generated"), Some(git2::AttrValue::True))
+        || matches!(attr("linguist-generated"), Some(git2::AttrValue::String(value)) if value.eq_ignore_ascii_case("true"));
+    let no_diff = matches!(attr("diff"), Some(git2::AttrValue::False));
+    DiffAttributes { is_generated, diff_suppressed: no_diff || is_generated }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TreeDiff {
+    /// Commit the ref resolved to
+    pub base: String,
+    pub files: Vec<DiffFileEntry>,
+}
+
+pub(crate) fn delta_status(status: git2::Delta) -> &'static str {
+    match status {
+        git2::Delta::Added | git2::Delta::Untracked => "added",
+        git2::Delta::Deleted => "deleted",
+        git2::Delta::Renamed => "renamed",
+        git2::Delta::Copied => "copied",
+        git2::Delta::Typechange => "typechange",
+        _ => "modified",
+    }
+}
+
+/// Per-file entries for a diff, with patches up to the size limits when `include_patches`.
+/// Files .gitattributes suppresses get no patch unless `force`.
+fn diff_entries(repo: &Repository, diff: &git2::Diff, include_patches: bool, force: bool) -> Result<Vec<DiffFileEntry>> {
+    let mut files = Vec::new();
+    let mut patch_bytes = 0;
+    for (index, delta) in diff.deltas().enumerate() {
+        let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().replace('\\', "/"));
+        let path = path_of(delta.new_file()).or_else(|| path_of(delta.old_file())).unwrap_or_default();
+        let old_path = match delta.status() {
+            git2::Delta::Renamed | git2::Delta::Copied => path_of(delta.old_file()),
+            _ => None,
+        };
+
+        let mut entry = DiffFileEntry {
+            path,
+            old_path,
+            status: delta_status(delta.status()).to_string(),
+            additions: 0,
+            deletions: 0,
+            binary: delta.flags().is_binary(),
+            patch: None,
+            truncated: false,
+            is_generated: false,
+            diff_suppressed: false,
+        };
+        let attributes = diff_attributes(repo, Path::new(&entry.path));
+        entry.is_generated = attributes.is_generated;
+        entry.diff_suppressed = attributes.diff_suppressed && !force;
+        if let Some(mut patch) = git2::Patch::from_diff(diff, index)? {
+            let (_, additions, deletions) = patch.line_stats()?;
+            entry.additions = additions;
+            entry.deletions = deletions;
+            entry.binary |= patch.delta().flags().is_binary();
+            if include_patches && !entry.binary && !entry.diff_suppressed {
+                let text = String::from_utf8_lossy(&patch.to_buf()?).to_string();
+                if text.len() > MAX_PATCH_BYTES || patch_bytes + text.len() > MAX_TOTAL_PATCH_BYTES {
+                    entry.truncated = true;
+                } else {
+                    patch_bytes += text.len();
+                    entry.patch = Some(text);
+                }
+            }
+        }
+        files.push(entry);
+    }
+    Ok(files)
+}
+
+/// Everything that differs between `git_ref` and the working tree, committed or not, as one
+/// diff. `path_filter` is a pathspec, so a single file's combined diff stays cheap.
+pub fn diff_workdir_to_ref(repo_path: &Path, git_ref: &str, include_untracked: bool, path_filter: Option<&str>, include_patches: bool) -> Result<TreeDiff> {
+    let repo = Repository::discover(repo_path)?;
+    let commit = repo
+        .revparse_single(git_ref)
+        .map_err(|_| anyhow!("Cannot resolve '{}'", git_ref))?
+        .peel_to_commit()?;
+    let tree = commit.tree()?;
+
+    let mut options = git2::DiffOptions::new();
+    options
+        .include_untracked(include_untracked)
+        .recurse_untracked_dirs(include_untracked)
+        .show_untracked_content(include_untracked);
+    if let Some(filter) = path_filter.filter(|f| !f.is_empty()) {
+        options.pathspec(filter);
+    }
+    let mut diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+
+    Ok(TreeDiff {
+        base: commit.id().to_string(),
+        files: diff_entries(&repo, &diff, include_patches, false)?,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileDiff {
+    /// None when the file has no changes
+    pub entry: Option<DiffFileEntry>,
+    /// Shown instead of the patch when .gitattributes suppresses it
+    pub notice: Option<String>,
+}
+
+/// One file's unstaged (index to working tree) or staged (HEAD to index) diff.
+/// `force` computes the patch even when .gitattributes suppresses it. An intent-to-add file
+/// has no staged diff, and its whole content is an unstaged addition.
+pub fn get_file_diff(repo_path: &Path, file_path: &str, staged: bool, force: bool) -> Result<FileDiff> {
+    let repo = Repository::discover(repo_path)?;
+    let file_path = crate::fs::decode_path(file_path);
+    let intent_to_add = intent_to_add_paths(&repo).contains(&crate::fs::path_to_git_bytes(&file_path));
+    if staged && intent_to_add {
+        return Ok(FileDiff { entry: None, notice: None });
+    }
+    let mut options = git2::DiffOptions::new();
+    options
+        .pathspec(file_path.as_path())
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true);
+    let diff = if staged {
+        let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))?
+    } else {
+        repo.diff_index_to_workdir(None, Some(&mut options))?
+    };
+    let mut entry = diff_entries(&repo, &diff, true, force)?.into_iter().next();
+    if let Some(entry) = entry.as_mut().filter(|_| intent_to_add) {
+        entry.status = "added".to_string();
+    }
+    let notice = entry.as_ref().filter(|e| e.diff_suppressed).map(|_| DIFF_SUPPRESSED_NOTICE.to_string());
+    Ok(FileDiff { entry, notice })
+}
+
+/// A hunk of one side of `get_file_diff_both`, with lines for rendering and an id for staging
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StageableHunk {
+    /// Side plus a digest of the hunk's lines, so it survives other hunks being staged
+    pub id: String,
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<crate::diff::DiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FileDiffSide {
+    /// None when this side has no changes
+    pub entry: Option<DiffFileEntry>,
+    /// Empty when the patch was left out (binary, suppressed or too large)
+    pub hunks: Vec<StageableHunk>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileDiffBoth {
+    pub path: String,
+    /// HEAD to index
+    pub staged: FileDiffSide,
+    /// Index to working tree
+    pub unstaged: FileDiffSide,
+    pub staged_additions: usize,
+    pub staged_deletions: usize,
+    pub unstaged_additions: usize,
+    pub unstaged_deletions: usize,
+    /// Staged as deleted while a file is still present at the path
+    pub deleted_but_present: bool,
+    /// Added with `git add -N`: tracked, with nothing staged yet
+    pub intent_to_add: bool,
+    /// Kind change on either side, e.g. "file -> symlink"
+    pub type_change: Option<String>,
+    pub notice: Option<String>,
+}
+
+fn mode_kind(mode: git2::FileMode) -> &'static str {
+    match mode {
+        git2::FileMode::Link => "symlink",
+        git2::FileMode::Commit => "submodule",
+        git2::FileMode::Tree => "directory",
+        git2::FileMode::Unreadable => "missing",
+        _ => "file",
+    }
+}
+
+fn hunk_id(side: &str, lines: &[crate::diff::DiffLine]) -> String {
+    let mut hasher = Sha256::new();
+    for line in lines {
+        hasher.update(format!("{}\0{}\n", line.kind, line.content));
+    }
+    format!("{}:{}", side, &format!("{:x}", hasher.finalize())[..16])
+}
+
+/// One side of `get_file_diff_both`: the file's entry plus its hunks when a patch was produced
+fn diff_side(repo: &Repository, diff: &git2::Diff, side: &str, force: bool) -> Result<(FileDiffSide, Option<String>)> {
+    let Some(entry) = diff_entries(repo, diff, true, force)?.into_iter().next() else {
+        return Ok((FileDiffSide::default(), None));
+    };
+    let type_change = diff
+        .deltas()
+        .next()
+        .filter(|delta| delta.status() == git2::Delta::Typechange)
+        .map(|delta| format!("{} -> {}", mode_kind(delta.old_file().mode()), mode_kind(delta.new_file().mode())));
+
+    let mut hunks = Vec::new();
+    if entry.patch.is_some() {
+        if let Some(patch) = git2::Patch::from_diff(diff, 0)? {
+            for index in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(index)?;
+                let mut lines = Vec::new();
+                for line_index in 0..line_count {
+                    let line = patch.line_in_hunk(index, line_index)?;
+                    let kind = match line.origin() {
+                        '+' => "insert",
+                        '-' => "delete",
+                        ' ' => "context",
+                        _ => continue,
+                    };
+                    lines.push(crate::diff::DiffLine {
+                        kind: kind.to_string(),
+                        old_line: line.old_lineno().map(|n| n as usize),
+                        new_line: line.new_lineno().map(|n| n as usize),
+                        content: String::from_utf8_lossy(line.content()).trim_end_matches('\n').trim_end_matches('\r').to_string(),
+                    });
+                }
+                hunks.push(StageableHunk {
+                    id: hunk_id(side, &lines),
+                    header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                    old_start: hunk.old_start() as usize,
+                    old_lines: hunk.old_lines() as usize,
+                    new_start: hunk.new_start() as usize,
+                    new_lines: hunk.new_lines() as usize,
+                    lines,
+                });
+            }
+        }
+    }
+    Ok((FileDiffSide { entry: Some(entry), hunks }, type_change))
+}
+
+/// Staged and unstaged diffs of one file together, computed against a single read of the
+/// index, so the SCM view can switch between them without another round trip
+pub fn get_file_diff_both(repo_path: &Path, file_path: &str, force: bool) -> Result<FileDiffBoth> {
+    let repo = Repository::discover(repo_path)?;
+    let encoded_path = file_path;
+    let file_path = crate::fs::decode_path(file_path);
+    let workdir = require_workdir(&repo)?;
+    let index = repo.index()?;
+    let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let options = || {
+        let mut options = git2::DiffOptions::new();
+        options
+            .pathspec(file_path.as_path())
+            .disable_pathspec_match(true)
+            .include_untracked(true)
+            .show_untracked_content(true);
+        options
+    };
+    let intent_to_add = index.get_path(&file_path, 0).map(|entry| is_intent_to_add(&entry)).unwrap_or(false);
+    let staged_diff = repo.diff_tree_to_index(head.as_ref(), Some(&index), Some(&mut options()))?;
+    let unstaged_diff = repo.diff_index_to_workdir(Some(&index), Some(&mut options()))?;
+    // Nothing of an intent-to-add file is staged; all of it shows as an unstaged addition
+    let (staged, staged_type_change) = if intent_to_add { (FileDiffSide::default(), None) } else { diff_side(&repo, &staged_diff, "staged", force)? };
+    let (mut unstaged, unstaged_type_change) = diff_side(&repo, &unstaged_diff, "unstaged", force)?;
+    if let Some(entry) = unstaged.entry.as_mut().filter(|_| intent_to_add) {
+        entry.status = "added".to_string();
+    }
+    let deleted_but_present = staged.entry.as_ref().map(|e| e.status == "deleted").unwrap_or(false)
+        && workdir.join(&file_path).symlink_metadata().is_ok();
+    let suppressed = [&staged, &unstaged].iter().any(|side| side.entry.as_ref().map(|e| e.diff_suppressed).unwrap_or(false));
+
+    Ok(FileDiffBoth {
+        path: encoded_path.replace('\\', "/"),
+        staged_additions: staged.entry.as_ref().map(|e| e.additions).unwrap_or(0),
+        staged_deletions: staged.entry.as_ref().map(|e| e.deletions).unwrap_or(0),
+        unstaged_additions: unstaged.entry.as_ref().map(|e| e.additions).unwrap_or(0),
+        unstaged_deletions: unstaged.entry.as_ref().map(|e| e.deletions).unwrap_or(0),
+        staged,
+        unstaged,
+        deleted_but_present,
+        intent_to_add,
+        type_change: staged_type_change.or(unstaged_type_change),
+        notice: suppressed.then(|| DIFF_SUPPRESSED_NOTICE.to_string()),
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DiffTotals {
+    pub files: usize,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct WorktreeDiffStats {
+    /// Everything except suppressed files
+    pub totals: DiffTotals,
+    /// Files .gitattributes suppresses, counted apart so lockfile churn does not dominate
+    pub suppressed: DiffTotals,
+    pub suppressed_paths: Vec<String>,
+}
+
+/// Line counts for every change since HEAD, staged or not, untracked files included
+pub fn worktree_diff_stats(repo_path: &Path) -> Result<WorktreeDiffStats> {
+    let repo = Repository::discover(repo_path)?;
+    let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let mut options = git2::DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).show_untracked_content(true);
+    let diff = repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))?;
+
+    let mut stats = WorktreeDiffStats::default();
+    for entry in diff_entries(&repo, &diff, false, false)? {
+        let totals = if entry.diff_suppressed {
+            stats.suppressed_paths.push(entry.path.clone());
+            &mut stats.suppressed
+        } else {
+            &mut stats.totals
+        };
+        totals.files += 1;
+        totals.additions += entry.additions;
+        totals.deletions += entry.deletions;
+    }
+    Ok(stats)
+}
+
+/// Enhanced Git repository initialization with proper setup
+pub fn init_git_repo_enhanced(repo_path: &Path) -> Result<GitInitResult> {
+    // Check if already a git repository
+    if is_git_repository(repo_path) {
+        // Already a repository, check configuration
+        match get_git_config(repo_path) {
+            Ok(config) => {
+                if config.is_configured {
+                    return Ok(GitInitResult {
+                        success: true,
+                        message: "Git repository already exists and is properly configured".to_string(),
+                        needs_config: false,
+                        git_config: Some(config),
+                    });
+                } else {
+                    return Ok(GitInitResult {
+                        success: true,
+                        message: "Git repository exists but needs user configuration".to_string(),
+                        needs_config: true,
+                        git_config: Some(config),
+                    });
+                }
+            }
+            Err(_) => {
+                return Ok(GitInitResult {
+                    success: true,
+                    message: "Git repository exists but configuration could not be read".to_string(),
+                    needs_config: true,
+                    git_config: None,
+                });
+            }
+        }
+    }
+
+    // Initialize new repository
+    match Repository::init(repo_path) {
+        Ok(_) => {
+            // Create initial .gitignore file with common patterns
+            let gitignore_path = repo_path.join(".gitignore");
+            let gitignore_content = create_default_gitignore();
+            
+            // Keep an existing .gitignore (e.g. one that came with a template)
+            if !gitignore_path.exists() {
+                if let Err(e) = fs::write(&gitignore_path, gitignore_content) {
+                    eprintln!("Warning: Could not create .gitignore: {}", e);
+                }
+            }
+
+            // Check if git is configured globally
+            match Config::open_default() {
+                Ok(global_config) => {
+                    let has_global_name = global_config.get_string("user.name").is_ok();
+                    let has_global_email = global_config.get_string("user.email").is_ok();
+                    
+                    if has_global_name && has_global_email {
+                        // Global config exists, repository is ready
+                        Ok(GitInitResult {
+                            success: true,
+                            message: "Git repository initialized successfully with global configuration".to_string(),
+                            needs_config: false,
+                            git_config: get_git_config(repo_path).ok(),
+                        })
+                    } else {
+                        // No global config, need to set up user info
+                        Ok(GitInitResult {
+                            success: true,
+                            message: "Git repository initialized. Please configure user name and email.".to_string(),
+                            needs_config: true,
+                            git_config: Some(GitConfig {
+                                user_name: None,
+                                user_email: None,
+                                is_configured: false,
+                            }),
+                        })
+                    }
+                }
+                Err(_) => {
+                    // Could not read global config, assume we need local config
+                    Ok(GitInitResult {
+                        success: true,
+                        message: "Git repository initialized. Please configure user name and email.".to_string(),
+                        needs_config: true,
+                        git_config: Some(GitConfig {
+                            user_name: None,
+                            user_email: None,
+                            is_configured: false,
+                        }),
+                    })
+                }
+            }
+        }
+        Err(e) => Ok(GitInitResult {
+            success: false,
+            message: format!("Failed to initialize Git repository: {}", e),
+            needs_config: false,
+            git_config: None,
+        })
+    }
+}
+
+/// Create a default .gitignore file with common patterns
+fn create_default_gitignore() -> String {
+    "# Dependencies
+node_modules/
+npm-debug.log*
+yarn-debug.log*
+yarn-error.log*
+package-lock.json
+
+# Build outputs
+dist/
+build/
+target/
+*.exe
+*.dll
+*.so
+*.dylib
+
+# Environment variables
+.env
+.env.local
+.env.development.local
+.env.test.local
+.env.production.local
+
+# IDE and editor files
+.vscode/
+.idea/
+*.swp
+*.swo
+*~
+
+# OS generated files
+.DS_Store
+.DS_Store?
+._*
+.Spotlight-V100
+.Trashes
+ehthumbs.db
+Thumbs.db
+
+# Logs
+*.log
+logs/
+
+# Runtime data
+pids
+*.pid
+*.seed
+*.pid.lock
+
+# Temporary folders
+tmp/
+temp/
+".to_string()
+}
+
+/// Configure or update a remote URL
+pub fn set_remote(repo_path: &Path, remote_name: &str, url: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+
+    // Check if remote exists first, without borrowing the Remote
+    let remote_exists = repo.find_remote(remote_name).is_ok();
+
+    if remote_exists {
+        repo.remote_set_url(remote_name, url)?;
+    } else {
+        repo.remote(remote_name, url)?;
+    }
+
+    Ok(())
+}
+
+/// Legacy function for backward compatibility
+pub fn init_git_repo(repo_path: &Path) -> Result<()> {
+    match init_git_repo_enhanced(repo_path) {
+        Ok(result) => {
+            if result.success {
+                Ok(())
+            } else {
+                Err(anyhow!(result.message))
+            }
+        }
+        Err(e) => Err(e)
+    }
+}
+
+/// Git credentials structure for storage
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitCredentials {
+    pub username: String,
+    pub token: String,
+    pub remote_url: String,
+}
+
+/// Get stored Git credentials for a project
+pub fn get_git_credentials(store: &CredentialStore, project_path: &Path) -> Result<Option<GitCredentials>> {
+    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
+
+    match (store.get(&service_name, "username")?, store.get(&service_name, "token")?, store.get(&service_name, "remote_url")?) {
+        (Some(username), Some(token), Some(remote_url)) => {
+            Ok(Some(GitCredentials {
+                username,
+                token,
+                remote_url,
+            }))
+        }
+        _ => Ok(None)
+    }
+}
+
+/// Store Git credentials for a project
+pub fn set_git_credentials(store: &CredentialStore, project_path: &Path, credentials: GitCredentials) -> Result<()> {
+    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
+
+    store.set(&service_name, "username", &credentials.username)?;
+    store.set(&service_name, "token", &credentials.token)?;
+    store.set(&service_name, "remote_url", &credentials.remote_url)?;
+
+    Ok(())
+}
+
+/// Delete stored Git credentials for a project
+pub fn delete_git_credentials(store: &CredentialStore, project_path: &Path) -> Result<()> {
+    let service_name = format!("ide-terminal-git-{}", project_path.to_string_lossy());
+
+    // Missing entries are not an error
+    store.delete(&service_name, "username")?;
+    store.delete(&service_name, "token")?;
+    store.delete(&service_name, "remote_url")?;
+
+    Ok(())
+}
+
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RepoSizeInfo {
+    pub git_dir_bytes: u64,
+    pub loose_objects: u64,
+    pub pack_count: u64,
+    pub maintenance_advised: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaintenanceResult {
+    pub before: RepoSizeInfo,
+    pub after: RepoSizeInfo,
+    pub output: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaintenanceProgress {
+    pub workspace_root: String,
+    pub line: String,
+}
+
+// Same thresholds git uses for `gc.auto` and `gc.autoPackLimit`
+const GC_AUTO_LOOSE_OBJECTS: u64 = 6700;
+const GC_AUTO_PACK_LIMIT: u64 = 50;
+
+/// Measure the .git directory: total bytes, loose objects and packfiles
+pub fn get_repo_size_info(repo_path: &Path) -> Result<RepoSizeInfo> {
+    let repo = Repository::open(repo_path)?;
+    let git_dir = repo.path();
+
+    let git_dir_bytes = walkdir::WalkDir::new(git_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum();
+
+    let objects_dir = git_dir.join("objects");
+    let mut loose_objects = 0;
+    if let Ok(entries) = fs::read_dir(&objects_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Loose objects live in two-hex-digit fan-out directories
+            if name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+                loose_objects += fs::read_dir(entry.path()).map(|d| d.count() as u64).unwrap_or(0);
+            }
+        }
+    }
+
+    let pack_count = fs::read_dir(objects_dir.join("pack"))
+        .map(|d| {
+            d.filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().map(|x| x == "pack").unwrap_or(false))
+                .count() as u64
+        })
+        .unwrap_or(0);
+
+    Ok(RepoSizeInfo {
+        git_dir_bytes,
+        loose_objects,
+        pack_count,
+        maintenance_advised: loose_objects > GC_AUTO_LOOSE_OBJECTS || pack_count > GC_AUTO_PACK_LIMIT,
+    })
+}
+
+/// Run a git CLI command in `repo_path`, streaming each stderr/stdout progress line to `on_line`
+pub fn run_git_cli<F>(repo_path: &Path, args: &[&str], mut on_line: F) -> Result<Vec<String>>
+where
+    F: FnMut(&str),
+{
+    run_git_cli_with_env(repo_path, args, &[], |line| {
+        on_line(line);
+        true
+    })
+}
+
+/// `run_git_cli` with extra environment variables; returning false from `on_line` kills git
+pub fn run_git_cli_with_env<F>(repo_path: &Path, args: &[&str], env: &[(&str, &str)], mut on_line: F) -> Result<Vec<String>>
+where
+    F: FnMut(&str) -> bool,
+{
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+
+    let mut child = match Command::new("git")
+        .args(args)
+        .envs(env.iter().copied())
+        .current_dir(repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!("The git command-line tool was not found on PATH. Install git to use this feature."));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Drain stdout on a separate thread so a full pipe can't block the child
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to capture git output"))?;
+    let stdout_reader = std::thread::spawn(move || {
+        let mut raw = String::new();
+        let _ = stdout.read_to_string(&mut raw);
+        raw
+    });
+
+    // Progress is written to stderr using carriage returns, so split on both \r and \n
+    let mut lines = Vec::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        let mut buf = [0u8; 4096];
+        let mut pending = String::new();
+        loop {
+            let n = stderr.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            pending.push_str(&String::from_utf8_lossy(&buf[..n]));
+            while let Some(pos) = pending.find(['\r', '\n']) {
+                let line: String = pending.drain(..=pos).collect();
+                let line = line.trim_end();
+                if !line.is_empty() {
+                    lines.push(line.to_string());
+                    if !on_line(line) {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Err(anyhow!("git {} was cancelled", args.first().copied().unwrap_or_default()));
+                    }
+                }
+            }
+        }
+        if !pending.trim().is_empty() {
+            on_line(pending.trim());
+            lines.push(pending.trim().to_string());
+        }
+    }
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    for line in stdout.lines().filter(|l| !l.trim().is_empty()) {
+        on_line(line);
+        lines.push(line.to_string());
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("git {} failed ({}): {}", args.join(" "), status, lines.last().cloned().unwrap_or_default()));
+    }
+    Ok(lines)
+}
+
+/// Run `git gc` (libgit2 cannot repack) and report sizes before and after
+pub fn run_repo_maintenance<F>(repo_path: &Path, on_progress: F) -> Result<MaintenanceResult>
+where
+    F: FnMut(&str),
+{
+    let before = get_repo_size_info(repo_path)?;
+    let output = run_git_cli(repo_path, &["gc", "--progress"], on_progress)?;
+    let after = get_repo_size_info(repo_path)?;
+
+    Ok(MaintenanceResult { before, after, output })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SparseCheckoutInfo {
+    pub enabled: bool,
+    pub cone_mode: bool,
+    /// Directories in cone mode, gitignore-style patterns otherwise
+    pub patterns: Vec<String>,
+    /// Tracked files currently left out of the working tree
+    pub not_materialized: usize,
+}
+
+fn sparse_checkout_file(repo: &Repository) -> PathBuf {
+    repo.path().join("info").join("sparse-checkout")
+}
+
+/// Read the sparse-checkout state straight from config and `info/sparse-checkout`
+pub fn get_sparse_checkout(repo_path: &Path) -> Result<SparseCheckoutInfo> {
+    let repo = Repository::discover(repo_path)?;
+    let config = repo.config()?;
+    let enabled = config.get_bool("core.sparseCheckout").unwrap_or(false);
+    let cone_mode = config.get_bool("core.sparseCheckoutCone").unwrap_or(false);
+    let raw = fs::read_to_string(sparse_checkout_file(&repo)).unwrap_or_default();
+    let lines = raw.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+    // Cone mode stores each directory as "/dir/" plus parent bookkeeping lines ("/*", "!/*/")
+    let patterns = if cone_mode {
+        lines
+            .filter(|l| !l.starts_with('!') && *l != "/*" && !l.ends_with("/*"))
+            .map(|l| l.trim_matches('/').to_string())
+            .collect()
+    } else {
+        lines.map(str::to_string).collect()
+    };
+    Ok(SparseCheckoutInfo {
+        enabled,
+        cone_mode,
+        patterns,
+        not_materialized: if enabled { skip_worktree_paths(&repo).len() } else { 0 },
+    })
+}
+
+/// Explain CLI failures that mean "your git is too old" rather than "this went wrong"
+fn sparse_cli_error(e: anyhow::Error) -> anyhow::Error {
+    let message = e.to_string();
+    if message.contains("is not a git command") || message.contains("unknown option") {
+        anyhow!("The installed git does not support sparse-checkout (git 2.27 or newer is required): {}", message)
+    } else {
+        e
+    }
+}
+
+/// Limit the working tree to `patterns` through the git CLI, since libgit2 neither writes the
+/// cone-mode file format nor updates skip-worktree flags. Files with local changes that fall
+/// outside the new patterns are left on disk by git rather than removed.
+pub fn set_sparse_checkout(repo_path: &Path, patterns: &[String], cone_mode: bool) -> Result<SparseCheckoutInfo> {
+    let repo = Repository::discover(repo_path)?;
+    let workdir = require_workdir(&repo)?.to_path_buf();
+    if patterns.is_empty() {
+        return Err(anyhow!("At least one pattern is required; use disable_sparse_checkout to check out everything"));
+    }
+    if let Some(flag) = patterns.iter().find(|p| p.trim_start().starts_with('-')) {
+        return Err(anyhow!("Pattern '{}' would be read as a command-line option", flag));
+    }
+
+    let mut args = vec!["sparse-checkout", "set", if cone_mode { "--cone" } else { "--no-cone" }];
+    args.extend(patterns.iter().map(String::as_str));
+    run_git_cli(&workdir, &args, |_| {}).map_err(sparse_cli_error)?;
+    get_sparse_checkout(&workdir)
+}
+
+/// Turn sparse checkout off and materialize every tracked file again
+pub fn disable_sparse_checkout(repo_path: &Path) -> Result<SparseCheckoutInfo> {
+    let repo = Repository::discover(repo_path)?;
+    let workdir = require_workdir(&repo)?.to_path_buf();
+    run_git_cli(&workdir, &["sparse-checkout", "disable"], |_| {}).map_err(sparse_cli_error)?;
+    get_sparse_checkout(&workdir)
+}
+
+/// The branch others are compared against: origin's HEAD, else main or master
+pub fn default_branch(repo: &Repository) -> Option<String> {
+    if let Ok(reference) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(target) = reference.symbolic_target().and_then(|t| t.strip_prefix("refs/remotes/origin/")) {
+            return Some(target.to_string());
+        }
+    }
+    ["main", "master"].iter().find(|b| repo.find_branch(b, BranchType::Local).is_ok()).map(|b| b.to_string())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchOverview {
+    pub name: String,
+    pub tip: String,
+    pub is_head: bool,
+    pub is_default: bool,
+    /// Matches a `protected_branches` pattern, so cleanup never deletes it
+    pub protected: bool,
+    pub upstream: Option<String>,
+    /// An upstream is configured but its remote-tracking branch is gone, typically after a merged PR
+    pub upstream_gone: bool,
+    /// Relative to the default branch; None when there is no default branch
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    /// Every commit on the branch is reachable from the default branch
+    pub merged: bool,
+    pub last_commit_time: i64,
+    pub last_commit_author: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchesOverview {
+    pub default_branch: Option<String>,
+    pub branches: Vec<BranchOverview>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MergedBranch {
+    pub name: String,
+    /// Tip commit, so the branch can be recreated
+    pub commit: String,
+    pub upstream: Option<String>,
+    /// Set when deleting this branch failed; the others are still deleted
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MergedBranchCleanup {
+    pub dry_run: bool,
+    /// Deleted, or that would be deleted in a dry run
+    pub branches: Vec<MergedBranch>,
+}
+
+fn protected_matcher(patterns: &[String]) -> globset::GlobSet {
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = globset::GlobBuilder::new(pattern).literal_separator(true).build() {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| globset::GlobSet::empty())
+}
+
+/// Every local branch with its standing against the default branch, reusing cached
+/// ahead/behind counts for branches that have not moved
+pub fn get_branches_overview(state: &GitState, repo_path: &Path, protected_patterns: &[String]) -> Result<BranchesOverview> {
+    let repo = Repository::discover(repo_path)?;
+    let default_name = default_branch(&repo);
+    let default_tip = default_name.as_deref().and_then(|name| {
+        repo.find_branch(name, BranchType::Local)
+            .or_else(|_| repo.find_branch(&format!("origin/{}", name), BranchType::Remote))
+            .ok()
+            .and_then(|b| b.get().target())
+    });
+    let protected = protected_matcher(protected_patterns);
+    let config = repo.config()?;
+
+    let mut branches = Vec::new();
+    for (branch, _) in repo.branches(Some(BranchType::Local))?.flatten() {
+        let Some(name) = branch.name().ok().flatten().map(str::to_string) else { continue };
+        let Some(tip) = branch.get().target() else { continue };
+        let commit = repo.find_commit(tip)?;
+        let counts = match default_tip {
+            Some(default_tip) => Some(state.ahead_behind(&repo, tip, default_tip)?),
+            None => None,
+        };
+        let upstream = branch.upstream().ok().and_then(|u| u.name().ok().flatten().map(str::to_string));
+        let upstream_configured = config.get_string(&format!("branch.{}.merge", name)).is_ok();
+
+        branches.push(BranchOverview {
+            tip: tip.to_string(),
+            is_head: branch.is_head(),
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            protected: protected.is_match(&name),
+            upstream_gone: upstream_configured && upstream.is_none(),
+            upstream,
+            ahead: counts.map(|(ahead, _)| ahead),
+            behind: counts.map(|(_, behind)| behind),
+            merged: counts.map(|(ahead, _)| ahead == 0).unwrap_or(false),
+            last_commit_time: commit.time().seconds(),
+            last_commit_author: commit.author().name().unwrap_or("Unknown").to_string(),
+            name,
+        });
+    }
+    branches.sort_by(|a, b| b.last_commit_time.cmp(&a.last_commit_time).then_with(|| a.name.cmp(&b.name)));
+    Ok(BranchesOverview { default_branch: default_name, branches })
+}
+
+/// Delete local branches fully merged into the default branch, sparing the default branch,
+/// the checked-out branch and protected ones. A dry run only reports what would go.
+pub fn delete_merged_branches(state: &GitState, repo_path: &Path, protected_patterns: &[String], dry_run: bool) -> Result<MergedBranchCleanup> {
+    let overview = get_branches_overview(state, repo_path, protected_patterns)?;
+    if overview.default_branch.is_none() {
+        return Err(anyhow!("No default branch found to compare against"));
+    }
+    let repo = Repository::discover(repo_path)?;
+
+    let mut branches = Vec::new();
+    for candidate in overview.branches.iter().filter(|b| b.merged && !b.is_head && !b.is_default && !b.protected) {
+        let error = match repo.find_branch(&candidate.name, BranchType::Local) {
+            Ok(_) if dry_run => None,
+            Ok(mut branch) => branch.delete().err().map(|e| e.message().to_string()),
+            Err(e) => Some(e.message().to_string()),
+        };
+        branches.push(MergedBranch {
+            name: candidate.name.clone(),
+            commit: candidate.tip.clone(),
+            upstream: candidate.upstream.clone(),
+            error,
+        });
+    }
+    Ok(MergedBranchCleanup { dry_run, branches })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_all, git, init_repo, temp_dir, write};
+
+    fn rules(name: &str) -> Vec<String> {
+        validate_branch_name(name).violations.into_iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn validate_branch_name_follows_ref_format_rules() {
+        let cases: &[(&str, &[&str])] = &[
+            ("main", &[]),
+            ("feat/login-page", &[]),
+            ("fix/issue_42", &[]),
+            ("release/v1.2.3", &[]),
+            ("user@work", &[]),
+            ("", &["empty"]),
+            ("@", &["reserved"]),
+            ("HEAD", &["reserved"]),
+            ("my branch", &["whitespace"]),
+            ("tab\tbranch", &["whitespace", "control-character"]),
+            ("bell\u{7}", &["control-character"]),
+            ("a..b", &["double-dot"]),
+            ("a~1", &["special-character"]),
+            ("a^b", &["special-character"]),
+            ("a:b", &["special-character"]),
+            ("what?", &["special-character"]),
+            ("wild*", &["special-character"]),
+            ("[bracket", &["special-character"]),
+            ("back\\slash", &["special-character"]),
+            ("a@{1}", &["at-brace"]),
+            ("-leading", &["leading-dash"]),
+            ("/leading", &["edge-slash"]),
+            ("trailing/", &["edge-slash"]),
+            ("a//b", &["double-slash"]),
+            ("trailing.", &["trailing-dot"]),
+            (".hidden", &["dot-component"]),
+            ("feat/.hidden", &["dot-component"]),
+            ("topic.lock", &["lock-suffix"]),
+            ("topic.lock/child", &["lock-suffix"]),
+            ("-a..b.lock", &["double-dot", "leading-dash", "lock-suffix"]),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(rules(name), *expected, "{:?}", name);
+            assert_eq!(validate_branch_name(name).valid, expected.is_empty(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn suggest_branch_name_avoids_existing_names() {
+        let (_dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        commit_all(&root, "Initial commit");
+        let repo = Repository::open(&root).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feat/add-login-page", &head, false).unwrap();
+
+        let convention = BranchNamingConvention::default();
+        let name = suggest_branch_name(&root, "Add login page!", Some("feat"), &convention).unwrap();
+        assert_eq!(name, "feat/add-login-page-2");
+        assert!(validate_branch_name(&name).valid);
+        assert_eq!(suggest_branch_name(&root, "   ", None, &convention).unwrap(), "branch");
+    }
+
+    #[test]
+    fn concurrent_stage_unstage_and_commit_lose_no_updates() {
+        const WORKERS: usize = 8;
+        const ROUNDS: usize = 5;
+        let (_dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        commit_all(&root, "Initial commit");
+
+        let state = Arc::new(GitState::default());
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(4).enable_all().build().unwrap();
+        runtime.block_on(async {
+            let mut tasks = Vec::new();
+            for worker in 0..WORKERS {
+                let (state, root) = (state.clone(), root.clone());
+                tasks.push(tokio::spawn(async move {
+                    let file = format!("file-{}.txt", worker);
+                    for round in 0..ROUNDS {
+                        write(&root, &file, &format!("worker {} round {}", worker, round));
+                        let (repo, path) = (root.clone(), file.clone());
+                        state.with_index_lock(&root, move || GitManager::new(&repo).stage_file(&path, false)).await.unwrap();
+                        let (repo, path) = (root.clone(), file.clone());
+                        state.with_index_lock(&root, move || GitManager::new(&repo).unstage_file(&path)).await.unwrap();
+                        let (repo, path) = (root.clone(), file.clone());
+                        state.with_index_lock(&root, move || GitManager::new(&repo).stage_file(&path, false)).await.unwrap();
+                        let (repo, message) = (root.clone(), format!("worker {} round {}", worker, round));
+                        match state.with_index_lock(&root, move || GitManager::new(&repo).commit(&message)).await {
+                            // Another worker's commit already took this file's staged change
+                            Ok(_) | Err(GitError::Index(GitIndexError::NothingToCommit)) => {}
+                            Err(e) => panic!("commit failed: {:?}", e),
+                        }
+                    }
+                }));
+            }
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+
+        let repo = Repository::open(&root).unwrap();
+        assert!(!repo.path().join("index.lock").exists());
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let tree = head.tree().unwrap();
+        for worker in 0..WORKERS {
+            let entry = tree.get_path(Path::new(&format!("file-{}.txt", worker))).unwrap();
+            let blob = repo.find_blob(entry.id()).unwrap();
+            assert_eq!(blob.content(), format!("worker {} round {}", worker, ROUNDS - 1).as_bytes());
+        }
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        for id in walk {
+            assert!(repo.find_commit(id.unwrap()).unwrap().parent_count() <= 1);
+        }
+    }
+
+    fn waiter(id: u64, priority: OperationPriority, enqueued: Instant) -> Waiter {
+        let (start, _) = tokio::sync::oneshot::channel();
+        Waiter { id, label: format!("op-{}", id), priority, enqueued, start }
+    }
+
+    fn queue(waiting: Vec<Waiter>) -> RepoQueue {
+        RepoQueue { running: Some("running".to_string()), waiting }
+    }
+
+    #[test]
+    fn queue_runs_interactive_first_then_in_arrival_order() {
+        use OperationPriority::{Background, Interactive};
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+        let mut queue = queue(vec![
+            waiter(1, Background, at(0)),
+            waiter(2, Interactive, at(10)),
+            waiter(3, Background, at(20)),
+            waiter(4, Interactive, at(30)),
+        ]);
+
+        let mut order = Vec::new();
+        while let Some(index) = queue.next_at(at(40)) {
+            order.push(queue.waiting.remove(index).id);
+        }
+        assert_eq!(order, [2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn queue_promotes_background_work_that_waited_past_the_starvation_limit() {
+        use OperationPriority::{Background, Interactive};
+        let t0 = Instant::now();
+        let queue = queue(vec![waiter(1, Background, t0), waiter(2, Interactive, t0 + Duration::from_secs(1))]);
+
+        assert_eq!(queue.next_at(t0 + Duration::from_secs(2)), Some(1));
+        // Once starved, the background operation counts as interactive and arrived first
+        assert_eq!(queue.next_at(t0 + STARVATION_LIMIT), Some(0));
+        assert_eq!(queue.next_at(t0 + STARVATION_LIMIT * 10), Some(0));
+    }
+
+    #[test]
+    fn queue_never_starves_background_work_under_a_stream_of_interactive_operations() {
+        use OperationPriority::{Background, Interactive};
+        let t0 = Instant::now();
+        let mut queue = queue(vec![waiter(0, Background, t0)]);
+        let mut now = t0;
+        let mut next_id = 1;
+        // One interactive operation arrives and one runs every second
+        loop {
+            now += Duration::from_secs(1);
+            queue.waiting.push(waiter(next_id, Interactive, now));
+            next_id += 1;
+            let index = queue.next_at(now).unwrap();
+            if queue.waiting.remove(index).id == 0 {
+                break;
+            }
+            assert!(now - t0 <= STARVATION_LIMIT, "background operation starved");
+        }
+    }
+
+    #[test]
+    fn queue_slot_is_held_until_the_blocking_operation_finishes() {
+        let (_dir, root) = init_repo();
+        let state = GitState::default();
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+        let finished = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        runtime.block_on(async {
+            let first = {
+                let finished = finished.clone();
+                state.run_queued(&root, "slow", OperationPriority::Interactive, |_| {}, move || {
+                    std::thread::sleep(Duration::from_millis(300));
+                    finished.store(true, Ordering::SeqCst);
+                })
+            };
+            // The command is dropped while its operation is still running on the blocking pool
+            assert!(tokio::time::timeout(Duration::from_millis(50), first).await.is_err());
+
+            let finished = finished.clone();
+            let saw_finished = state
+                .run_queued(&root, "next", OperationPriority::Interactive, |_| {}, move || finished.load(Ordering::SeqCst))
+                .await
+                .unwrap();
+            assert!(saw_finished, "the next operation started before the first one finished");
+        });
+        assert!(state.running_operations().is_empty());
+    }
+
+    #[test]
+    fn list_tracked_files_puts_pinned_paths_first_in_pin_order() {
+        let (_dir, root) = init_repo();
+        for file in ["a.rs", "b.rs", "docs/guide.md", "docs/notes.md", "src/main.rs"] {
+            write(&root, file, "x");
+        }
+        commit_all(&root, "files");
+        let state = GitState::default();
+
+        let pinned = [root.join("src/main.rs"), root.join("docs")];
+        let paths: Vec<String> = list_tracked_files(&state, &root, None, None, &pinned, 10).unwrap().into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, ["src/main.rs", "docs/guide.md", "docs/notes.md", "a.rs", "b.rs"]);
+
+        let paths: Vec<String> = list_tracked_files(&state, &root, None, None, &pinned, 2).unwrap().into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, ["src/main.rs", "docs/guide.md"]);
+    }
+
+    // macOS filesystems refuse names that are not valid UTF-8
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn index_commands_round_trip_encoded_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let (_dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        commit_all(&root, "Initial commit");
+        let name = Path::new(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+        fs::write(root.join(name), "bytes\n").unwrap();
+        let encoded = crate::fs::encode_path(name);
+
+        let manager = GitManager::new(&root);
+        assert_eq!(manager.get_status(&root).unwrap().untracked, [encoded.as_str()]);
+
+        manager.stage_file(&encoded, false).unwrap();
+        assert!(Repository::open(&root).unwrap().index().unwrap().get_path(name, 0).is_some());
+        let diff = get_file_diff_both(&root, &encoded, false).unwrap();
+        assert_eq!((diff.path.as_str(), diff.staged_additions), (encoded.as_str(), 1));
+
+        manager.unstage_file(&encoded).unwrap();
+        assert!(Repository::open(&root).unwrap().index().unwrap().get_path(name, 0).is_none());
+
+        assert_eq!(manager.mark_intent_to_add(std::slice::from_ref(&encoded)).unwrap(), [encoded.as_str()]);
+        assert!(get_file_diff(&root, &encoded, false, false).unwrap().entry.is_some());
+        assert_eq!(manager.unmark_intent_to_add(std::slice::from_ref(&encoded)).unwrap(), [encoded.as_str()]);
+    }
+
+    #[test]
+    fn note_stays_on_commit_that_was_amended_away() {
+        let (_dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        let original = commit_all(&root, "Initial commit");
+        add_git_note(&root, &original.to_string(), "agent: drafted", None).unwrap();
+
+        let repo = Repository::open(&root).unwrap();
+        let commit = repo.find_commit(original).unwrap();
+        let amended = commit.amend(Some("HEAD"), None, None, None, Some("Amended commit"), None).unwrap();
+
+        assert_eq!(get_git_note(&root, &amended.to_string(), None).unwrap(), None);
+        assert_eq!(get_git_note(&root, &original.to_string(), None).unwrap().as_deref(), Some("agent: drafted"));
+        let notes = list_git_notes(&root, None).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].commit_hash, original.to_string());
+    }
+
+    #[test]
+    fn pull_merges_fetched_notes_into_diverged_local_notes() {
+        let (_upstream_dir, upstream) = init_repo();
+        write(&upstream, "README.md", "hello");
+        let first = commit_all(&upstream, "Initial commit");
+        write(&upstream, "README.md", "hello again");
+        let second = commit_all(&upstream, "Second commit");
+        add_git_note(&upstream, &first.to_string(), "remote note", None).unwrap();
+        add_git_note(&upstream, &second.to_string(), "remote wins?", None).unwrap();
+
+        let (_clone_dir, clone) = init_repo();
+        let repo = Repository::open(&clone).unwrap();
+        repo.remote("origin", upstream.to_str().unwrap()).unwrap();
+        repo.find_remote("origin").unwrap().fetch(&["refs/heads/*:refs/remotes/origin/*"], None, None).unwrap();
+        add_git_note(&clone, &second.to_string(), "local note", None).unwrap();
+
+        GitManager::new(&clone).pull("origin", "master", true).unwrap();
+
+        assert_eq!(get_git_note(&clone, &first.to_string(), None).unwrap().as_deref(), Some("remote note"));
+        assert_eq!(get_git_note(&clone, &second.to_string(), None).unwrap().as_deref(), Some("local note"));
+        assert!(repo.find_reference("refs/notes/remotes/origin/agentic-ide").is_ok());
+    }
+
+    /// A bare clone of a one-commit repository, and that commit
+    fn bare_fixture() -> (tempfile::TempDir, tempfile::TempDir, PathBuf, git2::Oid) {
+        let (source_dir, source) = init_repo();
+        write(&source, "README.md", "hello");
+        let head = commit_all(&source, "Initial commit");
+        let (bare_dir, parent) = temp_dir();
+        let bare = parent.join("repo.git");
+        git(&parent, &["clone", "--bare", source.to_str().unwrap(), bare.to_str().unwrap()]);
+        (source_dir, bare_dir, bare, head)
+    }
+
+    fn assert_bare(error: anyhow::Error) {
+        let error = GitError::from(error);
+        assert_eq!(error.code(), "BareRepository", "{}", error);
+    }
+
+    #[test]
+    fn working_tree_operations_report_bare_repository() {
+        let (_source_dir, _bare_dir, bare, _) = bare_fixture();
+        let manager = GitManager::new(&bare);
+        assert!(manager.is_git_repo());
+
+        assert_bare(manager.get_status(&bare).unwrap_err());
+        assert_bare(manager.get_status_batched(&bare, |_| {}).unwrap_err());
+        assert_bare(manager.stage_file("README.md", false).unwrap_err());
+        assert_bare(manager.unstage_file("README.md").unwrap_err());
+        assert_bare(manager.commit("Nothing to see").unwrap_err());
+        assert_bare(checkout_branch(&bare, "master", false, false, false).unwrap_err());
+        assert_bare(restore_deleted_files(&bare, None).unwrap_err());
+        assert_bare(get_directory_status_rollup(&bare).unwrap_err());
+        assert_bare(set_sparse_checkout(&bare, &["src".to_string()], true).unwrap_err());
+    }
+
+    #[test]
+    fn history_can_be_browsed_in_a_bare_repository() {
+        let (_source_dir, _bare_dir, bare, head) = bare_fixture();
+        let manager = GitManager::new(&bare);
+
+        let commits = manager.get_recent_commits(10, &[]).unwrap();
+        assert_eq!(commits.iter().map(|c| c.hash.as_str()).collect::<Vec<_>>(), [&head.to_string()[..8]]);
+        let branch = Repository::open(&bare).unwrap().head().unwrap().shorthand().unwrap().to_string();
+        let branches = manager.list_branches().unwrap();
+        assert!(branches.iter().any(|b| b.name == branch && b.is_current), "{:?}", branches.iter().map(|b| &b.name).collect::<Vec<_>>());
+        assert_eq!(get_commit_details(&bare, &head.to_string(), &[]).unwrap().parsed.summary, "Initial commit");
+
+        assert_eq!(crate::repo_health::find_git_dir(&bare), Some(bare.clone()));
+        assert!(crate::repo_events::is_repo_signal(&bare, &bare.join("refs/heads").join(&branch)));
+    }
+
+    #[test]
+    fn linked_worktree_stages_and_commits_on_its_own_branch() {
+        let (_dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        commit_all(&root, "Initial commit");
+        let (_worktree_dir, parent) = temp_dir();
+        let worktree = parent.join("feature-tree");
+        git(&root, &["worktree", "add", "-b", "feature", worktree.to_str().unwrap()]);
+        assert!(worktree.join(".git").is_file());
+
+        let manager = GitManager::new(&worktree);
+        assert_eq!(manager.get_status(&worktree).unwrap().branch, "feature");
+        write(&worktree, "feature.txt", "new");
+        assert_eq!(manager.get_status(&worktree).unwrap().untracked, ["feature.txt"]);
+        manager.stage_file("feature.txt", false).unwrap();
+        let commit = manager.commit("Add feature").unwrap();
+
+        let main = Repository::open(&root).unwrap();
+        assert_eq!(main.find_branch("feature", BranchType::Local).unwrap().get().target().unwrap().to_string(), commit);
+        assert!(!root.join("feature.txt").exists());
+        assert!(manager.get_status(&worktree).unwrap().untracked.is_empty());
+        assert_eq!(crate::repo_health::find_git_dir(&worktree), Some(root.join(".git/worktrees/feature-tree")));
+    }
+
+    fn assert_suspicious(error: anyhow::Error, expected: &[&str]) {
+        match GitError::from(error) {
+            GitError::Index(GitIndexError::SuspiciousSymlink { links }) => assert_eq!(links.iter().map(|l| l.path.as_str()).collect::<Vec<_>>(), expected),
+            other => panic!("expected SuspiciousSymlink, got {}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn staging_a_symlink_outside_the_repository_needs_explicit_consent() {
+        use std::os::unix::fs::symlink;
+        let (_dir, root) = init_repo();
+        let (_outside_dir, outside) = temp_dir();
+        write(&outside, "secret.txt", "secret");
+        write(&root, "README.md", "hello");
+        commit_all(&root, "Initial commit");
+        symlink(outside.join("secret.txt"), root.join("absolute")).unwrap();
+        symlink("../../../../../../../../etc/passwd", root.join("relative")).unwrap();
+        symlink(outside.join("missing.txt"), root.join("dangling")).unwrap();
+        symlink("README.md", root.join("inside")).unwrap();
+
+        let manager = GitManager::new(&root);
+        assert_eq!(manager.get_status(&root).unwrap().escaping_symlinks, ["absolute", "dangling", "relative"]);
+        for name in ["absolute", "relative", "dangling"] {
+            assert_suspicious(manager.stage_file(name, false).unwrap_err(), &[name]);
+        }
+        manager.stage_file("inside", false).unwrap();
+        assert_suspicious(stage_all(manager.repo.as_ref().unwrap(), false).unwrap_err(), &["absolute", "dangling", "relative"]);
+
+        manager.stage_file("absolute", true).unwrap();
+        let index = Repository::open(&root).unwrap().index().unwrap();
+        assert!(index.get_path(Path::new("absolute"), 0).is_some());
+        assert!(index.get_path(Path::new("relative"), 0).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn every_staging_path_refuses_escaping_symlinks() {
+        use std::os::unix::fs::symlink;
+        let (_dir, root) = init_repo();
+        let (_outside_dir, outside) = temp_dir();
+        write(&outside, "secret.txt", "secret");
+        write(&root, "README.md", "hello");
+        write(&root, "tracked", "plain file");
+        commit_all(&root, "Initial commit");
+        symlink(outside.join("secret.txt"), root.join("link")).unwrap();
+        let manager = GitManager::new(&root);
+
+        assert_suspicious(manager.prepare_commit_tree(CommitInclude::Paths, &["link".to_string()]).unwrap_err(), &["link"]);
+        assert_suspicious(manager.mark_intent_to_add(&["link".to_string()]).unwrap_err(), &["link"]);
+        // Resolved before staging, so the link's target is outside the repository
+        assert!(!stage_path(&root.join("link")).unwrap());
+
+        // A tracked file replaced by a link is a type change `commit -a` would pick up
+        std::fs::remove_file(root.join("tracked")).unwrap();
+        symlink(outside.join("secret.txt"), root.join("tracked")).unwrap();
+        assert_suspicious(manager.prepare_commit_tree(CommitInclude::AllTracked, &[]).unwrap_err(), &["tracked"]);
+
+        let index = Repository::open(&root).unwrap().index().unwrap();
+        assert!(index.get_path(Path::new("link"), 0).is_none());
+        assert_eq!(head_blob(&root, "tracked").as_deref(), Some("plain file"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_reached_through_a_symlinked_directory_report_the_link() {
+        use std::os::unix::fs::symlink;
+        let (_dir, root) = init_repo();
+        let (_outside_dir, outside) = temp_dir();
+        write(&outside, "secret.txt", "secret");
+        write(&root, "src/main.rs", "fn main() {}");
+        symlink(&outside, root.join("linked")).unwrap();
+        symlink("src", root.join("src-alias")).unwrap();
+
+        assert_eq!(escaping_symlink(&root, Path::new("linked/secret.txt")).unwrap().path, "linked");
+        assert!(escaping_symlink(&root, Path::new("src-alias/main.rs")).is_none());
+        assert!(escaping_symlink(&root, Path::new("src/main.rs")).is_none());
+        assert_suspicious(GitManager::new(&root).stage_file("linked", false).unwrap_err(), &["linked"]);
+    }
+
+    /// `mklink /J` needs no privileges, unlike symbolic links
+    #[cfg(windows)]
+    fn junction(link: &Path, target: &Path) {
+        let status = std::process::Command::new("cmd").arg("/C").arg("mklink").arg("/J").arg(link).arg(target).status().unwrap();
+        assert!(status.success());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn junctions_outside_the_repository_are_refused() {
+        let (_dir, root) = init_repo();
+        let (_outside_dir, outside) = temp_dir();
+        write(&outside, "secret.txt", "secret");
+        write(&root, "src/main.rs", "fn main() {}");
+        commit_all(&root, "Initial commit");
+        junction(&root.join("linked"), &outside);
+        junction(&root.join("src-alias"), &root.join("src"));
+
+        assert_eq!(escaping_symlink(&root, Path::new("linked\\secret.txt")).unwrap().path, "linked");
+        assert!(escaping_symlink(&root, Path::new("src-alias\\main.rs")).is_none());
+        assert_suspicious(GitManager::new(&root).stage_file("linked", false).unwrap_err(), &["linked"]);
+    }
+
+    #[test]
+    fn batched_status_matches_a_single_pass() {
+        let (_dir, root) = init_repo();
+        write(&root, ".gitignore", "*.log\n");
+        for n in 0..=STATUS_FILE_BATCH * 2 {
+            write(&root, &format!("file-{:04}.txt", n), "one\n");
+        }
+        write(&root, "src/main.rs", "fn main() {}\n");
+        write(&root, "src/lib.rs", "\n");
+        write(&root, "docs/guide/intro.md", "# Intro\n");
+        commit_all(&root, "Initial commit");
+
+        let manager = GitManager::new(&root);
+        write(&root, "file-0003.txt", "two\n");
+        fs::remove_file(root.join("file-0700.txt")).unwrap();
+        write(&root, "file-0999.txt", "staged\n");
+        manager.stage_file("file-0999.txt", false).unwrap();
+        write(&root, "src/main.rs", "fn main() { run() }\n");
+        fs::remove_dir_all(root.join("docs")).unwrap();
+        write(&root, "new-dir/a.txt", "new");
+        write(&root, "Zeta.txt", "new");
+        write(&root, "alpha.txt", "new");
+        write(&root, "staged.txt", "new");
+        manager.stage_file("staged.txt", false).unwrap();
+        write(&root, "build.log", "ignored");
+
+        let repo = Repository::open(&root).unwrap();
+        for ignore_case in [false, true] {
+            repo.config().unwrap().set_bool("core.ignorecase", ignore_case).unwrap();
+            let manager = GitManager::new(&root);
+            let single = manager.get_status(&root).unwrap();
+            let repo = manager.repo.as_ref().unwrap();
+            let mut events = Vec::new();
+            let batched = scan_status_in_batches(repo, &root, &repo.index().unwrap(), &root, |event| events.push(event)).unwrap();
+            assert_eq!(serde_json::to_value(&batched).unwrap(), serde_json::to_value(&single).unwrap(), "core.ignorecase={}", ignore_case);
+
+            // src and new-dir, then 1007 other top-level names (docs is only in the index now) in three chunks
+            let last = events.pop().unwrap();
+            assert!(last.complete && last.partial.is_none());
+            assert_eq!(last.batches_total, 2 + 3);
+            assert_eq!(events.len(), last.batches_total);
+            assert!(events.iter().all(|e| !e.complete));
+            let partial_entries: usize = events.iter().map(|e| {
+                let partial = e.partial.as_ref().unwrap();
+                partial.modified.len() + partial.untracked.len() + partial.staged.len() + partial.deleted.len()
+            }).sum();
+            assert_eq!(partial_entries, single.modified.len() + single.untracked.len() + single.staged.len() + single.deleted.len());
+            assert_eq!(events.last().unwrap().entries_scanned, last.entries_scanned);
+        }
+    }
+
+    /// Commit a tree that folds together on case- or normalization-insensitive filesystems
+    /// to a `colliding` branch, without ever writing it to the working tree
+    fn colliding_branch(root: &Path) -> git2::Oid {
+        let repo = Repository::open(root).unwrap();
+        let blob = |content: &str| repo.blob(content.as_bytes()).unwrap();
+        let dir = |name: &str| {
+            let mut builder = repo.treebuilder(None).unwrap();
+            builder.insert(name, blob(name), 0o100644).unwrap();
+            builder.write().unwrap()
+        };
+        let mut builder = repo.treebuilder(None).unwrap();
+        for name in ["README.md", "Readme.md", "cafe\u{301}.txt", "caf\u{e9}.txt", "\u{c4}.txt", "\u{e4}.txt", "main.rs"] {
+            builder.insert(name, blob(name), 0o100644).unwrap();
+        }
+        builder.insert("Docs", dir("b.md"), 0o040000).unwrap();
+        builder.insert("docs", dir("a.md"), 0o040000).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test User", "test@example.com").unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("refs/heads/colliding"), &signature, &signature, "Add colliding names", &tree, &[&head]).unwrap()
+    }
+
+    fn collision_groups(collisions: &[PathCollision]) -> Vec<(Vec<&str>, CollisionKind)> {
+        collisions.iter().map(|c| (c.paths.iter().map(String::as_str).collect(), c.kind)).collect()
+    }
+
+    #[test]
+    fn constructed_trees_report_case_and_normalization_collisions() {
+        let (_dir, root) = init_repo();
+        write(&root, "main.rs", "fn main() {}");
+        commit_all(&root, "Initial commit");
+        let commit = colliding_branch(&root);
+
+        let clean = check_tree_portability(&root, None).unwrap();
+        assert_eq!((clean.reference.as_str(), clean.paths_checked), ("HEAD", 1));
+        assert!(clean.collisions.is_empty());
+
+        let report = check_tree_portability(&root, Some("colliding")).unwrap();
+        assert_eq!(report.commit, commit.to_string());
+        // Seven files, two directories and a file in each
+        assert_eq!(report.paths_checked, 11);
+        assert_eq!(
+            collision_groups(&report.collisions),
+            vec![
+                (vec!["cafe\u{301}.txt", "caf\u{e9}.txt"], CollisionKind::UnicodeNormalization),
+                (vec!["Docs", "docs"], CollisionKind::Case),
+                (vec!["README.md", "Readme.md"], CollisionKind::Case),
+                (vec!["\u{c4}.txt", "\u{e4}.txt"], CollisionKind::Case),
+            ]
+        );
+
+        let error = GitError::CaseCollision { collisions: report.collisions };
+        let serialized = serde_json::to_value(&error).unwrap();
+        assert_eq!(serialized["code"], "CaseCollision");
+        assert_eq!(serialized["case_collisions"][1]["paths"], serde_json::json!(["Docs", "docs"]));
+    }
+
+    #[test]
+    fn index_errors_serialize_with_the_same_fields_as_other_git_errors() {
+        let locked = serde_json::to_value(GitError::from(GitIndexError::IndexLocked { holder_hint: "git gc".to_string() })).unwrap();
+        let failed = serde_json::to_value(GitError::Failed { message: "boom".to_string() }).unwrap();
+        let keys = |v: &serde_json::Value| v.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&locked), keys(&failed));
+        assert_eq!(keys(&locked).len(), 11);
+        assert_eq!(locked["code"], "IndexLocked");
+        assert_eq!(locked["holder_hint"], "git gc");
+    }
+
+    #[test]
+    fn checkout_refuses_colliding_trees_only_where_the_filesystem_folds_them() {
+        let (_dir, root) = init_repo();
+        write(&root, "main.rs", "fn main() {}");
+        commit_all(&root, "Initial commit");
+        colliding_branch(&root);
+        let (case, normalization) = filesystem_folding(&Repository::open(&root).unwrap());
+
+        let result = checkout_branch(&root, "colliding", false, false, false);
+        if case || normalization {
+            match GitError::from(result.unwrap_err()) {
+                GitError::CaseCollision { collisions } => {
+                    assert!(!collisions.is_empty());
+                    assert!(collisions.iter().all(|c| if c.kind == CollisionKind::Case { case } else { normalization }));
+                }
+                other => panic!("expected CaseCollision, got {}", other),
+            }
+            // The caller can still insist
+            assert_eq!(checkout_branch(&root, "colliding", false, true, false).unwrap().outcome, "checked_out");
+        } else {
+            assert_eq!(result.unwrap().outcome, "checked_out");
+            assert!(root.join("README.md").exists() && root.join("Readme.md").exists());
+        }
+        // The probes never show up as working tree changes
+        assert!(Repository::open(&root).unwrap().path().read_dir().unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().starts_with("agentic-")));
+    }
+
+    fn head_blob(root: &Path, path: &str) -> Option<String> {
+        let repo = Repository::open(root).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let entry = tree.get_path(Path::new(path)).ok()?;
+        let blob = repo.find_blob(entry.id()).unwrap();
+        Some(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    #[test]
+    fn commits_leave_out_intent_to_add_placeholders() {
+        let (_dir, root) = init_repo();
+        write(&root, "tracked.txt", "one\n");
+        commit_all(&root, "Initial commit");
+        write(&root, "new.txt", "hello\nworld\n");
+        write(&root, "docs/draft.md", "# Draft\n");
+        let manager = GitManager::new(&root);
+
+        let marked = manager.mark_intent_to_add(&["new.txt".to_string(), "docs".to_string()]).unwrap();
+        assert_eq!(marked, vec!["new.txt", "docs/draft.md"]);
+        // Already in the index, so marking again is a no-op
+        assert!(manager.mark_intent_to_add(&["new.txt".to_string()]).unwrap().is_empty());
+        let status = manager.get_status(&root).unwrap();
+        let mut intent = status.intent_to_add.clone();
+        intent.sort();
+        assert_eq!(intent, vec!["docs/draft.md", "new.txt"]);
+        assert!(status.untracked.is_empty() && status.staged.is_empty() && status.modified.is_empty(), "{:?}", status);
+
+        write(&root, "tracked.txt", "two\n");
+        manager.stage_file("tracked.txt", false).unwrap();
+        manager.commit("Update tracked").unwrap();
+        assert_eq!(head_blob(&root, "tracked.txt").as_deref(), Some("two\n"));
+        assert_eq!(head_blob(&root, "new.txt"), None, "an intent-to-add placeholder must not be committed as an empty file");
+        assert_eq!(head_blob(&root, "docs/draft.md"), None);
+        assert_eq!(manager.get_status(&root).unwrap().intent_to_add.len(), 2, "the marks outlive the commit");
+
+        // Once its content is staged the file is committed like any other
+        manager.stage_file("new.txt", false).unwrap();
+        manager.commit("Add new").unwrap();
+        assert_eq!(head_blob(&root, "new.txt").as_deref(), Some("hello\nworld\n"));
+        assert_eq!(head_blob(&root, "docs/draft.md"), None);
+
+        assert_eq!(manager.unmark_intent_to_add(&["docs".to_string()]).unwrap(), vec!["docs/draft.md"]);
+        let status = manager.get_status(&root).unwrap();
+        assert!(status.intent_to_add.is_empty(), "{:?}", status);
+        assert!(status.untracked.iter().any(|path| path.starts_with("docs")), "{:?}", status);
+    }
+
+    #[test]
+    fn intent_to_add_files_diff_as_unstaged_additions() {
+        let (_dir, root) = init_repo();
+        write(&root, "tracked.txt", "one\n");
+        commit_all(&root, "Initial commit");
+        write(&root, "new.txt", "hello\nworld\n");
+        let manager = GitManager::new(&root);
+        manager.mark_intent_to_add(&["new.txt".to_string()]).unwrap();
+
+        assert!(get_file_diff(&root, "new.txt", true, false).unwrap().entry.is_none());
+        let unstaged = get_file_diff(&root, "new.txt", false, false).unwrap().entry.unwrap();
+        assert_eq!((unstaged.status.as_str(), unstaged.additions, unstaged.deletions), ("added", 2, 0));
+        let patch = unstaged.patch.unwrap();
+        assert!(patch.contains("+hello") && patch.contains("+world"), "{}", patch);
+
+        let both = get_file_diff_both(&root, "new.txt", false).unwrap();
+        assert!(both.intent_to_add);
+        assert!(both.staged.entry.is_none() && both.staged.hunks.is_empty());
+        assert_eq!(both.unstaged.entry.as_ref().map(|e| e.status.as_str()), Some("added"));
+        assert_eq!((both.staged_additions, both.unstaged_additions, both.unstaged_deletions), (0, 2, 0));
+        assert!(!both.unstaged.hunks.is_empty());
+
+        // An ordinary tracked file keeps its staged and unstaged sides
+        write(&root, "tracked.txt", "two\n");
+        manager.stage_file("tracked.txt", false).unwrap();
+        write(&root, "tracked.txt", "three\n");
+        let tracked = get_file_diff_both(&root, "tracked.txt", false).unwrap();
+        assert!(!tracked.intent_to_add);
+        assert_eq!(tracked.staged.entry.as_ref().map(|e| e.status.as_str()), Some("modified"));
+        assert_eq!(tracked.unstaged.entry.as_ref().map(|e| e.status.as_str()), Some("modified"));
+    }
+
+    #[test]
+    fn restoring_deleted_files_keeps_staged_deletions_and_other_changes() {
+        let (_dir, root) = init_repo();
+        write(&root, "lost.txt", "committed\n");
+        write(&root, "removed.txt", "gone on purpose\n");
+        write(&root, "edited.txt", "one\n");
+        write(&root, "glob[1].txt", "bracketed\n");
+        write(&root, "glob1.txt", "plain\n");
+        commit_all(&root, "Initial commit");
+
+        git(&root, &["rm", "-q", "removed.txt"]);
+        write(&root, "lost.txt", "staged\n");
+        git(&root, &["add", "lost.txt"]);
+        std::fs::remove_file(root.join("lost.txt")).unwrap();
+        std::fs::remove_file(root.join("glob[1].txt")).unwrap();
+        write(&root, "glob1.txt", "unstaged edit\n");
+        write(&root, "edited.txt", "two\n");
+
+        let mut result = restore_deleted_files(&root, None).unwrap();
+        result.restored.sort();
+        assert_eq!(result.restored, vec!["glob[1].txt", "lost.txt"]);
+        assert_eq!(std::fs::read_to_string(root.join("lost.txt")).unwrap(), "staged\n", "restored from the index, not HEAD");
+        assert_eq!(std::fs::read_to_string(root.join("glob[1].txt")).unwrap(), "bracketed\n");
+        assert_eq!(std::fs::read_to_string(root.join("glob1.txt")).unwrap(), "unstaged edit\n");
+        assert_eq!(std::fs::read_to_string(root.join("edited.txt")).unwrap(), "two\n");
+        assert!(!root.join("removed.txt").exists());
+        assert!(git(&root, &["status", "--porcelain"]).contains("D  removed.txt"));
+
+        // Asking for the staged deletion by name still leaves it alone
+        match GitError::from(restore_deleted_files(&root, Some(&["removed.txt".to_string()])).unwrap_err()) {
+            GitError::Index(GitIndexError::NotRestorable { paths, .. }) => assert_eq!(paths, vec!["removed.txt"]),
+            other => panic!("expected NotRestorable, got {}", other),
+        }
+        assert!(!root.join("removed.txt").exists());
+    }
+
+    #[test]
+    fn preparing_a_commit_stages_nothing_until_persisted() {
+        let (_dir, root) = init_repo();
+        write(&root, "a.txt", "one\n");
+        write(&root, "b.txt", "one\n");
+        commit_all(&root, "Initial commit");
+        write(&root, "a.txt", "two\n");
+        write(&root, "b.txt", "two\n");
+        let manager = GitManager::new(&root);
+
+        for (include, paths) in [(CommitInclude::AllTracked, vec![]), (CommitInclude::Paths, vec!["a.txt".to_string()])] {
+            manager.prepare_commit_tree(include, &paths).unwrap();
+            // What a blocked commit does
+            manager.discard_index_changes();
+            assert!(manager.get_status(&root).unwrap().staged.is_empty(), "{:?}", include);
+            // A later index write through the same handle does not pick the staging up either
+            manager.stage_file("b.txt", false).unwrap();
+            assert_eq!(manager.get_status(&root).unwrap().staged, ["b.txt"], "{:?}", include);
+            manager.unstage_file("b.txt").unwrap();
+        }
+
+        let tree = manager.prepare_commit_tree(CommitInclude::Paths, &["a.txt".to_string()]).unwrap();
+        manager.persist_index().unwrap();
+        assert_eq!(manager.get_status(&root).unwrap().staged, ["a.txt"]);
+        manager.commit_tree("Update a", tree).unwrap();
+        assert_eq!(head_blob(&root, "a.txt").as_deref(), Some("two\n"));
+        assert_eq!(head_blob(&root, "b.txt").as_deref(), Some("one\n"));
+    }
+
+    // "main" and "feature" both change shared.txt from their common base
+    fn diverged_branches() -> (tempfile::TempDir, PathBuf) {
+        let (dir, root) = init_repo();
+        write(&root, "shared.txt", "base\n");
+        write(&root, "notes.txt", "notes\n");
+        commit_all(&root, "Base");
+        git(&root, &["branch", "-M", "main"]);
+        git(&root, &["branch", "feature"]);
+        write(&root, "shared.txt", "main\n");
+        commit_all(&root, "Change on main");
+        git(&root, &["checkout", "-q", "feature"]);
+        write(&root, "shared.txt", "feature\n");
+        commit_all(&root, "Change on feature");
+        git(&root, &["checkout", "-q", "main"]);
+        (dir, root)
+    }
+
+    fn current_branch(root: &Path) -> String {
+        Repository::open(root).unwrap().head().unwrap().shorthand().unwrap().to_string()
+    }
+
+    #[test]
+    fn auto_stash_carries_local_changes_to_the_new_branch() {
+        let (_dir, root) = diverged_branches();
+        write(&root, "notes.txt", "edited\n");
+        write(&root, "untracked.txt", "new\n");
+
+        let result = checkout_branch(&root, "feature", true, false, false).unwrap();
+        assert_eq!((result.outcome.as_str(), result.stashed), ("reapplied", true));
+        assert_eq!(current_branch(&root), "feature");
+        assert_eq!(std::fs::read_to_string(root.join("shared.txt")).unwrap(), "feature\n");
+        assert_eq!(std::fs::read_to_string(root.join("notes.txt")).unwrap(), "edited\n");
+        assert_eq!(std::fs::read_to_string(root.join("untracked.txt")).unwrap(), "new\n");
+        assert!(stash_list(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn auto_stash_lists_conflicts_and_keeps_the_stash_when_reapplying_fails() {
+        let (_dir, root) = diverged_branches();
+        write(&root, "shared.txt", "local\n");
+
+        let result = checkout_branch(&root, "feature", true, false, false).unwrap();
+        assert_eq!((result.outcome.as_str(), result.stashed), ("reapply_conflicts", true));
+        assert_eq!(result.conflicts, ["shared.txt"]);
+        assert_eq!(current_branch(&root), "feature");
+        let stashes = stash_list(&root).unwrap();
+        assert_eq!(stashes.len(), 1);
+        assert!(stashes[0].auto_stash);
+        assert_eq!(stashes[0].label.as_deref(), Some("auto-stash from branch switch"));
+    }
+
+    #[test]
+    fn checkout_refuses_to_overwrite_local_changes_unless_forced() {
+        let (_dir, root) = diverged_branches();
+        write(&root, "shared.txt", "local\n");
+        write(&root, "notes.txt", "edited\n");
+
+        let error = checkout_branch(&root, "feature", false, false, false).unwrap_err();
+        match error.downcast_ref::<GitError>() {
+            Some(GitError::CheckoutBlocked { paths }) => assert_eq!(paths, &["shared.txt"]),
+            other => panic!("expected CheckoutBlocked, got {:?}", other),
+        }
+        assert_eq!(current_branch(&root), "main");
+        assert_eq!(std::fs::read_to_string(root.join("shared.txt")).unwrap(), "local\n");
+
+        let result = checkout_branch(&root, "feature", false, false, true).unwrap();
+        assert_eq!((result.outcome.as_str(), result.stashed), ("checked_out", false));
+        assert_eq!(current_branch(&root), "feature");
+        assert_eq!(std::fs::read_to_string(root.join("shared.txt")).unwrap(), "feature\n");
+    }
+
+    #[test]
+    fn remote_tracking_branches_check_out_as_tracking_local_branches() {
+        let (_dir, root) = diverged_branches();
+        git(&root, &["remote", "add", "origin", "https://example.com/repo.git"]);
+        git(&root, &["update-ref", "refs/remotes/origin/feature-x", "feature"]);
+
+        // A blocked checkout does not leave the branch it created behind
+        write(&root, "shared.txt", "local\n");
+        assert!(checkout_branch(&root, "origin/feature-x", false, false, false).is_err());
+        let repo = Repository::open(&root).unwrap();
+        assert!(repo.find_branch("feature-x", BranchType::Local).is_err());
+        git(&root, &["checkout", "--", "shared.txt"]);
+
+        let result = checkout_branch(&root, "origin/feature-x", false, false, false).unwrap();
+        assert_eq!((result.branch.as_str(), result.created_tracking_branch), ("feature-x", true));
+        assert_eq!(current_branch(&root), "feature-x");
+        let branch = repo.find_branch("feature-x", BranchType::Local).unwrap();
+        assert_eq!(branch.upstream().unwrap().name().unwrap(), Some("origin/feature-x"));
+
+        git(&root, &["checkout", "-q", "main"]);
+        let again = checkout_branch(&root, "origin/feature-x", false, false, false).unwrap();
+        assert_eq!((again.branch.as_str(), again.created_tracking_branch), ("feature-x", false));
+    }
+}
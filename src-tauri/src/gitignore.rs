@@ -0,0 +1,351 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use git2::{Config, Repository};
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Match;
+
+use crate::fs::display_path;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IgnoreExplanation {
+    pub path: String,
+    pub ignored: bool,
+    /// "gitignore", "info/exclude" or "core.excludesFile"; None when no rule matched
+    pub source: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub pattern: Option<String>,
+    /// The deciding rule was a `!pattern` re-include
+    pub negated: bool,
+}
+
+/// Git's ignore rules for a project: every `.gitignore`, `.git/info/exclude` and `core.excludesFile`
+pub struct IgnoreMatcher {
+    root: PathBuf,
+    nested: Vec<Gitignore>,
+    info_exclude: Gitignore,
+    global: Gitignore,
+}
+
+struct RuleMatch<'a> {
+    source: &'static str,
+    glob: &'a Glob,
+}
+
+fn build_single(root: &Path, file: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    if file.is_file() {
+        let _ = builder.add(file);
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// `core.excludesFile` from the repository or global config, else git's XDG default
+fn global_excludes_path(repo: Option<&Repository>) -> Option<PathBuf> {
+    let config = match repo {
+        Some(repo) => repo.config().ok(),
+        None => Config::open_default().ok(),
+    };
+    if let Some(path) = config.and_then(|c| c.get_path("core.excludesFile").ok()) {
+        return Some(path);
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(config_home.join("git").join("ignore"))
+}
+
+impl IgnoreMatcher {
+    /// Load the rules that apply to `project_path`, which may be a subdirectory of a repository
+    pub fn new(project_path: &Path) -> Result<Self> {
+        let repo = Repository::discover(project_path).ok();
+        let root = repo
+            .as_ref()
+            .and_then(|r| r.workdir())
+            .map(|w| w.to_path_buf())
+            .unwrap_or_else(|| project_path.to_path_buf());
+        let git_dir = repo.as_ref().map(|r| r.path().to_path_buf()).unwrap_or_else(|| root.join(".git"));
+
+        let mut matcher = Self {
+            info_exclude: build_single(&root, &git_dir.join("info").join("exclude")),
+            global: match global_excludes_path(repo.as_ref()) {
+                Some(path) => build_single(&root, &path),
+                None => Gitignore::empty(),
+            },
+            nested: Vec::new(),
+            root,
+        };
+
+        // .gitignore files between the repository root and the project apply too
+        let mut dir = project_path.parent();
+        while let Some(current) = dir {
+            if !current.starts_with(&matcher.root) {
+                break;
+            }
+            matcher.add_gitignore(current);
+            dir = current.parent();
+        }
+        matcher.collect(project_path);
+        Ok(matcher)
+    }
+
+    fn add_gitignore(&mut self, dir: &Path) {
+        let file = dir.join(".gitignore");
+        if file.is_file() {
+            self.nested.push(build_single(dir, &file));
+        }
+    }
+
+    /// Like git, never descend into ignored directories looking for more .gitignore files
+    fn collect(&mut self, dir: &Path) {
+        self.add_gitignore(dir);
+        let Ok(entries) = fs::read_dir(dir) else { return };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if !is_dir || entry.file_name() == ".git" || self.is_ignored(&path, true) {
+                continue;
+            }
+            self.collect(&path);
+        }
+    }
+
+    /// The rule deciding `path` on its own, checking the deepest .gitignore first
+    fn match_one(&self, path: &Path, is_dir: bool) -> Option<RuleMatch<'_>> {
+        let mut nested: Vec<&Gitignore> = self.nested.iter().filter(|g| path.starts_with(g.path())).collect();
+        nested.sort_by_key(|g| std::cmp::Reverse(g.path().components().count()));
+
+        let layers = nested
+            .into_iter()
+            .map(|g| ("gitignore", g))
+            .chain([("info/exclude", &self.info_exclude), ("core.excludesFile", &self.global)]);
+        for (source, gitignore) in layers {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(glob) | Match::Whitelist(glob) => return Some(RuleMatch { source, glob }),
+                Match::None => {}
+            }
+        }
+        None
+    }
+
+    /// A file inside an ignored directory is ignored regardless of later `!` rules
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<RuleMatch<'_>> {
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            let mut ancestor = self.root.clone();
+            let components: Vec<_> = relative.components().collect();
+            for component in components.iter().take(components.len().saturating_sub(1)) {
+                ancestor.push(component);
+                if let Some(rule) = self.match_one(&ancestor, true) {
+                    if !rule.glob.is_whitelist() {
+                        return Some(rule);
+                    }
+                }
+            }
+        }
+        self.match_one(path, is_dir)
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if !path.starts_with(&self.root) {
+            return false;
+        }
+        self.decide(path, is_dir).map(|rule| !rule.glob.is_whitelist()).unwrap_or(false)
+    }
+
+    /// Report the rule responsible for `path`, like `git check-ignore -v`
+    pub fn explain(&self, path: &Path) -> IgnoreExplanation {
+        let is_dir = path.is_dir();
+        let rule = if path.starts_with(&self.root) { self.decide(path, is_dir) } else { None };
+
+        let mut explanation = IgnoreExplanation {
+            path: display_path(path),
+            ignored: false,
+            source: None,
+            file: None,
+            line: None,
+            pattern: None,
+            negated: false,
+        };
+        if let Some(rule) = rule {
+            let negated = rule.glob.is_whitelist();
+            explanation.ignored = !negated;
+            explanation.negated = negated;
+            explanation.source = Some(rule.source.to_string());
+            explanation.pattern = Some(rule.glob.original().to_string());
+            if let Some(file) = rule.glob.from() {
+                explanation.line = rule_line(file, rule.glob.original());
+                explanation.file = Some(display_path(file));
+            }
+        }
+        explanation
+    }
+}
+
+/// Later lines win in gitignore files, so report the last line holding the pattern
+fn rule_line(file: &Path, pattern: &str) -> Option<usize> {
+    let content = fs::read_to_string(file).ok()?;
+    content.lines().enumerate().filter(|(_, line)| line.trim_end() == pattern).last().map(|(i, _)| i + 1)
+}
+
+pub fn explain_ignore(project_path: &Path, path: &Path) -> Result<IgnoreExplanation> {
+    let target = if path.is_absolute() { path.to_path_buf() } else { project_path.join(path) };
+    Ok(IgnoreMatcher::new(project_path)?.explain(&target))
+}
+
+// Directory entries examined when counting rule matches; larger trees report truncated counts
+const MAX_SCANNED_ENTRIES: usize = 200_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitignoreEntry {
+    /// 1-based line number in the file
+    pub line: usize,
+    pub text: String,
+    /// "pattern", "comment" or "blank"
+    pub kind: String,
+    pub pattern: Option<String>,
+    pub negated: bool,
+    /// Existing paths this rule matches on its own; contents of a matched directory are not counted
+    pub match_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitignoreFile {
+    pub path: String,
+    pub entries: Vec<GitignoreEntry>,
+    pub scanned_entries: usize,
+    /// The tree had more than the scan limit, so match counts are lower bounds
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GitignoreEdit {
+    Add { pattern: String, comment: Option<String> },
+    /// `pattern` must still be on `line`, so edits made against a stale parse are rejected
+    Remove { line: usize, pattern: String },
+    CommentOut { line: usize, pattern: String },
+}
+
+fn classify(text: &str) -> (&'static str, Option<String>, bool) {
+    let trimmed = text.trim_end();
+    if trimmed.trim_start().is_empty() {
+        ("blank", None, false)
+    } else if trimmed.starts_with('#') {
+        ("comment", None, false)
+    } else {
+        ("pattern", Some(trimmed.to_string()), trimmed.starts_with('!'))
+    }
+}
+
+/// Count, for every pattern line, the existing paths under the file's directory that it matches
+fn count_matches(dir: &Path, entries: &mut [GitignoreEntry]) -> (usize, bool) {
+    let rules: Vec<(usize, Gitignore)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let pattern = entry.pattern.as_ref()?;
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add_line(None, pattern).ok()?;
+            Some((i, builder.build().ok()?))
+        })
+        .collect();
+    if rules.is_empty() {
+        return (0, false);
+    }
+    let whole = build_single(dir, &dir.join(".gitignore"));
+
+    let mut scanned = 0;
+    let mut truncated = false;
+    let mut walker = walkdir::WalkDir::new(dir).min_depth(1).into_iter().filter_entry(|e| e.file_name() != ".git");
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        scanned += 1;
+        if scanned > MAX_SCANNED_ENTRIES {
+            truncated = true;
+            break;
+        }
+        let is_dir = entry.file_type().is_dir();
+        for (i, rule) in &rules {
+            if matches!(rule.matched(entry.path(), is_dir), Match::Ignore(_) | Match::Whitelist(_)) {
+                entries[*i].match_count += 1;
+            }
+        }
+        // Like git, nothing below an ignored directory can be re-included, so don't descend
+        if is_dir && whole.matched(entry.path(), true).is_ignore() {
+            walker.skip_current_dir();
+        }
+    }
+    (scanned.min(MAX_SCANNED_ENTRIES), truncated)
+}
+
+/// Parse a .gitignore into its lines, with per-rule match counts against the tree beside it
+pub fn parse_gitignore(path: &Path) -> Result<GitignoreFile> {
+    let content = fs::read_to_string(path)?;
+    let mut entries: Vec<GitignoreEntry> = content
+        .lines()
+        .enumerate()
+        .map(|(i, text)| {
+            let (kind, pattern, negated) = classify(text);
+            GitignoreEntry { line: i + 1, text: text.to_string(), kind: kind.to_string(), pattern, negated, match_count: 0 }
+        })
+        .collect();
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let (scanned_entries, truncated) = count_matches(dir, &mut entries);
+    Ok(GitignoreFile { path: display_path(path), entries, scanned_entries, truncated })
+}
+
+/// Apply edits to a .gitignore, keeping every untouched line and its order. Line numbers refer
+/// to the file as it was before any of these edits; additions go at the end.
+pub fn update_gitignore(path: &Path, edits: &[GitignoreEdit]) -> Result<GitignoreFile> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut lines: Vec<Option<String>> = content.lines().map(|l| Some(l.to_string())).collect();
+
+    let check = |lines: &[Option<String>], line: usize, pattern: &str| -> Result<usize> {
+        let index = line.checked_sub(1).filter(|i| *i < lines.len()).ok_or_else(|| anyhow::anyhow!("Line {} does not exist", line))?;
+        match &lines[index] {
+            Some(text) if text.trim_end() == pattern => Ok(index),
+            _ => Err(anyhow::anyhow!("Line {} no longer holds '{}'; reload the file and try again", line, pattern)),
+        }
+    };
+
+    let mut additions = Vec::new();
+    for edit in edits {
+        match edit {
+            GitignoreEdit::Remove { line, pattern } => {
+                let index = check(&lines, *line, pattern)?;
+                lines[index] = None;
+            }
+            GitignoreEdit::CommentOut { line, pattern } => {
+                let index = check(&lines, *line, pattern)?;
+                lines[index] = Some(format!("# {}", pattern));
+            }
+            GitignoreEdit::Add { pattern, comment } => {
+                let pattern = pattern.trim();
+                if pattern.is_empty() || pattern.contains('\n') {
+                    return Err(anyhow::anyhow!("'{}' is not a single gitignore pattern", pattern));
+                }
+                let present = lines.iter().flatten().chain(additions.iter()).any(|l| l.trim_end() == pattern);
+                if present {
+                    continue;
+                }
+                if let Some(comment) = comment.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+                    additions.push(format!("# {}", comment));
+                }
+                additions.push(pattern.to_string());
+            }
+        }
+    }
+
+    let mut output: Vec<String> = lines.into_iter().flatten().collect();
+    output.extend(additions);
+    let mut text = output.join(if content.contains("\r\n") { "\r\n" } else { "\n" });
+    if !text.is_empty() {
+        text.push_str(if content.contains("\r\n") { "\r\n" } else { "\n" });
+    }
+    crate::fs::write_atomic(path, text.as_bytes())?;
+    parse_gitignore(path)
+}
@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{InvokeBody, InvokeMessage};
+use tauri::{Manager, Runtime};
+
+use crate::workspace::WorkspaceRegistry;
+
+/// Commands that never write to the workspace, the keyring, settings, a repository or the OS
+/// clipboard. The only disk write allowed is an append to the command audit log, which
+/// `scan_for_secrets` makes so every scan leaves a trace. The only processes they spawn are
+/// read-only helpers: `clipboard_get_status` runs the platform's clipboard reader, since the
+/// clipboard cannot be read in-process everywhere, and `check_repository_health` with
+/// `run_fsck` runs `git fsck` and a process listing to name whoever holds a stale lock.
+/// Anything not listed is treated as mutating, so a new command is blocked in read-only mode
+/// until it is deliberately added here.
+pub(crate) const NON_MUTATING_COMMANDS: &[&str] = &[
+    "get_git_status",
+    "get_directory_status_rollup",
+    "expand_untracked_directory",
+    "get_recent_branches",
+    "list_deleted_branches",
+    "check_tree_portability",
+    "get_branches_overview",
+    "stash_list",
+    "get_recent_commits",
+    "list_branches",
+    "get_commit_details",
+    "list_tracked_files",
+    "get_sparse_checkout",
+    "get_clone_info",
+    "get_remote_host_info",
+    "build_remote_file_url",
+    "get_line_provenance",
+    "get_review_manifest_hash",
+    "diff_workdir_to_ref",
+    "get_git_note",
+    "list_git_notes",
+    "get_git_config",
+    "list_git_identities",
+    "get_effective_identity",
+    "is_git_repository",
+    "get_git_credentials",
+    "get_credential_store_info",
+    "list_credential_profiles",
+    "get_repo_size_info",
+    "get_pending_pushes",
+    "get_plan",
+    "scan_for_secrets",
+    "list_plans",
+    "validate_branch_name",
+    "suggest_branch_name",
+    "list_git_hooks",
+    "cancel_operation",
+    "get_shutdown_blockers",
+    "get_previous_session_leftovers",
+    "notify_user",
+    "list_directory",
+    "probe_paths",
+    "preview_delete",
+    "mark_file_open",
+    "mark_file_closed",
+    "read_file_content",
+    "get_local_history",
+    "read_local_history_version",
+    "open_workspace",
+    "close_workspace",
+    "set_watch_mode",
+    "open_project",
+    "close_project",
+    "get_last_bootstrap_timings",
+    "get_warmup_status",
+    "list_pinned",
+    "list_env_profiles",
+    "resolve_env_profile",
+    "get_file_tags",
+    "list_tags",
+    "find_files_by_tag",
+    "list_workspaces",
+    "get_drafts",
+    "get_watcher_stats",
+    "get_file_outline",
+    "query_workspace_symbols",
+    "build_word_index",
+    "query_word_index",
+    "get_word_index_stats",
+    "explain_ignore",
+    "parse_gitignore",
+    "suggest_gitignore_rules",
+    "get_cache_stats",
+    "get_settings",
+    "clipboard_get_status",
+    "tail_file",
+    "stop_tail",
+    "list_scratch_files",
+    "list_snippets",
+    "get_snippet",
+    "diff_files",
+    "diff_texts",
+    "analyze_line_endings",
+    "get_project_manifest",
+    "list_env_files",
+    "read_env_file",
+    "compare_env_files",
+    "get_read_only_mode",
+    "set_read_only_mode",
+    "run_diagnostics",
+    "get_command_metrics",
+    "check_repository_health",
+    "get_last_prepush_run",
+    "read_response_chunk",
+    "stream_response",
+    "release_response",
+    "find_file_references",
+    "get_file_diff",
+    "get_file_diff_both",
+    "check_text_policy",
+    "get_worktree_diff_stats",
+    "get_activity_feed",
+    "get_changed_paths_vs_ref",
+    "get_file_summary",
+    "get_asset_metadata",
+    "get_assets_metadata",
+    "get_files_summaries",
+    "read_file_lines",
+    "can_continue_operation",
+    "list_checkpoints",
+    "diff_checkpoints",
+    "diff_checkpoint_file",
+    "get_storage_info",
+    "compare_project_fingerprint",
+    "get_project_fingerprint",
+    "list_backend_modules",
+    "reset_command_metrics",
+    "record_command_timings",
+];
+
+// Commands that still make sense against a disconnected workspace
+const OFFLINE_COMMANDS: &[&str] = &["close_workspace", "close_project", "list_workspaces", "probe_paths"];
+
+// Argument names (as sent by the frontend) that carry paths a command may write to
+const PATH_ARGUMENTS: &[&str] = &[
+    "projectPath", "path", "filePath", "dirPath", "targetPath", "targetDir", "from", "to", "sourceRoot", "targetRoot", "outputPath", "root", "dir",
+];
+
+#[derive(Debug, Clone)]
+pub enum GuardError {
+    ReadOnlyMode { command: String, workspace: Option<String> },
+    /// The workspace's volume is disconnected; the command would only fail with raw IO errors
+    WorkspaceUnavailable { command: String, workspace: String },
+}
+
+impl GuardError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GuardError::ReadOnlyMode { .. } => "ReadOnlyMode",
+            GuardError::WorkspaceUnavailable { .. } => "WorkspaceUnavailable",
+        }
+    }
+}
+
+impl std::fmt::Display for GuardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuardError::ReadOnlyMode { command, workspace: Some(root) } => {
+                write!(f, "'{}' is not allowed: workspace '{}' is read-only", command, root)
+            }
+            GuardError::ReadOnlyMode { command, workspace: None } => {
+                write!(f, "'{}' is not allowed while the IDE is in read-only mode", command)
+            }
+            GuardError::WorkspaceUnavailable { command, workspace } => {
+                write!(f, "'{}' cannot run: workspace '{}' is disconnected", command, workspace)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuardError {}
+
+impl Serialize for GuardError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("GuardError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReadOnlyStatus {
+    pub enabled: bool,
+    pub read_only_workspaces: Vec<String>,
+}
+
+/// Global and per-workspace read-only switches
+#[derive(Default)]
+pub struct ReadOnlyState {
+    enabled: AtomicBool,
+    workspaces: Mutex<HashSet<PathBuf>>,
+}
+
+/// Resolve a possibly not-yet-existing path so it can be compared with canonical workspace roots
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return PathBuf::from(crate::fs::display_path(&canonical));
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => resolve(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl ReadOnlyState {
+    /// Toggle read-only mode globally, or for one workspace root when given
+    pub fn set(&self, enabled: bool, workspace_root: Option<&Path>) {
+        match workspace_root {
+            Some(root) => {
+                let mut workspaces = self.workspaces.lock().unwrap();
+                if enabled {
+                    workspaces.insert(resolve(root));
+                } else {
+                    workspaces.remove(&resolve(root));
+                }
+            }
+            None => self.enabled.store(enabled, Ordering::SeqCst),
+        }
+    }
+
+    pub fn status(&self) -> ReadOnlyStatus {
+        let mut read_only_workspaces: Vec<String> =
+            self.workspaces.lock().unwrap().iter().map(|p| crate::fs::display_path(p)).collect();
+        read_only_workspaces.sort();
+        ReadOnlyStatus { enabled: self.enabled.load(Ordering::SeqCst), read_only_workspaces }
+    }
+
+    /// Whether writes under `path` are currently blocked, globally or by its workspace
+    pub fn is_read_only(&self, path: &Path) -> bool {
+        self.enabled.load(Ordering::SeqCst) || self.read_only_root_for(path).is_some()
+    }
+
+    fn read_only_root_for(&self, path: &Path) -> Option<PathBuf> {
+        let workspaces = self.workspaces.lock().unwrap();
+        if workspaces.is_empty() {
+            return None;
+        }
+        let resolved = resolve(path);
+        workspaces.iter().find(|root| resolved.starts_with(root)).cloned()
+    }
+}
+
+pub fn is_mutating(command: &str) -> bool {
+    !NON_MUTATING_COMMANDS.contains(&command)
+}
+
+fn path_arguments(body: &InvokeBody) -> Vec<PathBuf> {
+    let InvokeBody::Json(serde_json::Value::Object(args)) = body else { return Vec::new() };
+    let mut paths = Vec::new();
+    for (key, value) in args {
+        if key == "paths" {
+            paths.extend(value.as_array().into_iter().flatten().filter_map(|v| v.as_str()).map(crate::fs::decode_path));
+        } else if PATH_ARGUMENTS.contains(&key.as_str()) {
+            if let Some(path) = value.as_str() {
+                paths.push(crate::fs::decode_path(path));
+            }
+        }
+    }
+    paths
+}
+
+/// Single choke point run before every command is dispatched
+pub fn check<R: Runtime>(message: &InvokeMessage<R>) -> Result<(), GuardError> {
+    let command = message.command();
+    let webview = message.webview();
+
+    if !OFFLINE_COMMANDS.contains(&command) {
+        let workspaces = webview.state::<WorkspaceRegistry>();
+        for path in path_arguments(message.payload()) {
+            if let Some(root) = workspaces.disconnected_root_for(&path) {
+                return Err(GuardError::WorkspaceUnavailable {
+                    command: command.to_string(),
+                    workspace: crate::fs::display_path(&root),
+                });
+            }
+        }
+    }
+    if !is_mutating(command) {
+        return Ok(());
+    }
+
+    let read_only = webview.state::<ReadOnlyState>();
+    if read_only.enabled.load(Ordering::SeqCst) {
+        return Err(GuardError::ReadOnlyMode { command: command.to_string(), workspace: None });
+    }
+    for path in path_arguments(message.payload()) {
+        if let Some(root) = read_only.read_only_root_for(&path) {
+            return Err(GuardError::ReadOnlyMode {
+                command: command.to_string(),
+                workspace: Some(crate::fs::display_path(&root)),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The raw form carries OS bytes, which differ on Windows
+    #[cfg(unix)]
+    #[test]
+    fn path_arguments_decode_encoded_paths() {
+        let raw = crate::fs::encode_path(Path::new("/work/plain.txt"));
+        let body = InvokeBody::Json(serde_json::json!({
+            "projectPath": "/work",
+            "filePath": raw,
+            "paths": ["/work/a", "\0raw:2f776f726b2f62"],
+            "message": "/not/a/path/argument",
+        }));
+        let mut paths = path_arguments(&body);
+        paths.sort();
+        assert_eq!(paths, ["/work", "/work/a", "/work/b", "/work/plain.txt"].map(PathBuf::from));
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use git2::Repository;
+
+use crate::fs::{display_path, write_atomic};
+
+/// Written on the line after the shebang of every hook we install
+const MANAGED_MARKER: &str = "# managed-by: agentic-ide";
+
+const KNOWN_HOOKS: [&str; 13] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "post-rewrite",
+    "pre-auto-gc",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitHook {
+    pub name: String,
+    pub path: String,
+    pub executable: bool,
+    pub first_line: Option<String>,
+    /// "agentic-ide", "husky", "pre-commit" or "lefthook" when recognised
+    pub managed_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GitHooksInfo {
+    pub hooks_dir: String,
+    /// Set when `core.hooksPath` redirects hooks away from `.git/hooks`
+    pub custom_hooks_path: bool,
+    pub hooks: Vec<GitHook>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HookInstallResult {
+    pub path: String,
+    pub backup_path: Option<String>,
+}
+
+/// The directory git runs hooks from, honouring `core.hooksPath`
+fn hooks_dir(repo: &Repository) -> (PathBuf, bool) {
+    let configured = repo.config().ok().and_then(|c| c.get_path("core.hooksPath").ok());
+    match configured {
+        Some(path) if path.is_absolute() => (path, true),
+        // Relative hooksPath is resolved against the working tree, like git does
+        Some(path) => (repo.workdir().unwrap_or_else(|| repo.path()).join(path), true),
+        None => (repo.path().join("hooks"), false),
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+fn set_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_mode(permissions.mode() | 0o755);
+        fs::set_permissions(path, permissions)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+fn detect_manager(content: &str, hooks_dir: &Path) -> Option<String> {
+    if content.lines().take(3).any(|line| line.trim() == MANAGED_MARKER) {
+        return Some("agentic-ide".to_string());
+    }
+    let lower = content.to_lowercase();
+    let in_husky_dir = hooks_dir.components().any(|c| c.as_os_str() == ".husky");
+    if in_husky_dir || lower.contains("husky") {
+        Some("husky".to_string())
+    } else if lower.contains("lefthook") {
+        Some("lefthook".to_string())
+    } else if lower.contains("pre-commit.com") || lower.contains("generated by pre-commit") {
+        Some("pre-commit".to_string())
+    } else {
+        None
+    }
+}
+
+fn validate_hook_name(name: &str) -> Result<()> {
+    if !KNOWN_HOOKS.contains(&name) {
+        return Err(anyhow!("'{}' is not a supported git hook", name));
+    }
+    Ok(())
+}
+
+pub fn list_git_hooks(project_path: &Path) -> Result<GitHooksInfo> {
+    let repo = Repository::open(project_path)?;
+    let (dir, custom_hooks_path) = hooks_dir(&repo);
+
+    let mut hooks = Vec::new();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !path.is_file() || name.ends_with(".sample") || name.contains(".backup-") {
+                continue;
+            }
+            let content = fs::read_to_string(&path).unwrap_or_default();
+            hooks.push(GitHook {
+                executable: is_executable(&path),
+                first_line: content.lines().next().map(|l| l.to_string()),
+                managed_by: detect_manager(&content, &dir),
+                path: display_path(&path),
+                name,
+            });
+        }
+    }
+    hooks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(GitHooksInfo { hooks_dir: display_path(&dir), custom_hooks_path, hooks })
+}
+
+/// Write a hook carrying our marker. An existing hook we did not write is only replaced with `force`,
+/// and is then kept as `<name>.backup-<timestamp>`.
+pub fn install_managed_hook(project_path: &Path, hook_name: &str, script_content: &str, force: bool) -> Result<HookInstallResult> {
+    validate_hook_name(hook_name)?;
+    let repo = Repository::open(project_path)?;
+    let (dir, _) = hooks_dir(&repo);
+    let path = dir.join(hook_name);
+
+    let mut backup_path = None;
+    if path.exists() {
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if detect_manager(&existing, &dir).as_deref() != Some("agentic-ide") {
+            if !force {
+                return Err(anyhow!(
+                    "A '{}' hook that was not installed by the IDE already exists. Use force to replace it (it will be backed up).",
+                    hook_name
+                ));
+            }
+            let backup = dir.join(format!("{}.backup-{}", hook_name, chrono::Utc::now().format("%Y%m%d%H%M%S")));
+            fs::copy(&path, &backup)?;
+            backup_path = Some(display_path(&backup));
+        }
+    }
+
+    let (shebang, body) = match script_content.strip_prefix("#!") {
+        Some(_) => script_content.split_once('\n').unwrap_or((script_content, "")),
+        None => ("#!/bin/sh", script_content),
+    };
+    let content = format!("{}\n{}\n{}", shebang.trim_end(), MANAGED_MARKER, body);
+
+    fs::create_dir_all(&dir)?;
+    write_atomic(&path, content.as_bytes())?;
+    set_executable(&path)?;
+
+    Ok(HookInstallResult { path: display_path(&path), backup_path })
+}
+
+/// Remove a hook we installed; hooks without our marker are left alone
+pub fn uninstall_managed_hook(project_path: &Path, hook_name: &str) -> Result<bool> {
+    validate_hook_name(hook_name)?;
+    let repo = Repository::open(project_path)?;
+    let (dir, _) = hooks_dir(&repo);
+    let path = dir.join(hook_name);
+
+    let Ok(existing) = fs::read_to_string(&path) else { return Ok(false) };
+    if detect_manager(&existing, &dir).as_deref() != Some("agentic-ide") {
+        return Err(anyhow!("The '{}' hook was not installed by the IDE and was left in place", hook_name));
+    }
+    fs::remove_file(&path)?;
+    Ok(true)
+}
@@ -0,0 +1,240 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::AppHandle;
+use crate::fs::display_path;
+use crate::settings::SettingsState;
+
+// Commands that do not count as interactive, so polling them does not hold warmup off
+const PASSIVE_COMMANDS: &[&str] = &["get_warmup_status", "get_command_metrics", "probe_paths"];
+
+/// When and for how long caches are warmed in the background after a project opens
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WarmupOptions {
+    pub enabled: bool,
+    /// How long no interactive command may have run before warmup work starts
+    pub idle_ms: u64,
+    /// Warmup time allowed per project; tasks still queued when it is spent are skipped
+    pub budget_ms: u64,
+}
+
+impl Default for WarmupOptions {
+    fn default() -> Self {
+        Self { enabled: true, idle_ms: 750, budget_ms: 15_000 }
+    }
+}
+
+/// What one slice of a warmup task achieved
+pub enum WarmupStep {
+    /// The cache is warm; the count of entries in it
+    Done(usize),
+    /// Stopped early because `should_yield` said so; the task runs again later
+    Yielded,
+    /// Nothing to warm for this project, e.g. a cache of git data outside a repository
+    #[cfg(feature = "git")]
+    NotApplicable,
+}
+
+/// Warms one cache for a project root. `should_yield` turns true as soon as an interactive
+/// command arrives or the project's budget runs out; long tasks check it between units of work.
+pub type WarmupFn = Arc<dyn Fn(&AppHandle, &Path, &dyn Fn() -> bool) -> Result<WarmupStep, String> + Send + Sync>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmupState {
+    Pending,
+    Running,
+    /// Interrupted by interactive work and queued again
+    Yielded,
+    Done,
+    NotApplicable,
+    /// Disabled or out of budget before it finished
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WarmupTaskStatus {
+    pub name: String,
+    pub state: WarmupState,
+    /// Entries in the warmed cache, once done
+    pub entries: Option<usize>,
+    pub spent_ms: u64,
+    pub yields: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WarmupStatus {
+    pub root: String,
+    pub enabled: bool,
+    pub budget_ms: u64,
+    pub spent_ms: u64,
+    pub tasks: Vec<WarmupTaskStatus>,
+}
+
+struct RootWarmup {
+    spent: Duration,
+    tasks: Vec<WarmupTaskStatus>,
+}
+
+/// Low-priority queue of cache warmups, drained on one background thread only while no
+/// interactive command has run for the configured idle time. Modules register their warmup
+/// tasks from `setup`; opening a project queues every task for its root.
+pub struct IdleScheduler {
+    tasks: Mutex<Vec<(&'static str, WarmupFn)>>,
+    queue: Mutex<VecDeque<(PathBuf, usize)>>,
+    wake: Condvar,
+    status: Mutex<HashMap<PathBuf, RootWarmup>>,
+    last_interactive: Mutex<Instant>,
+    /// Bumped by every interactive command, so a running slice notices one immediately
+    interactive: AtomicU64,
+}
+
+impl Default for IdleScheduler {
+    fn default() -> Self {
+        Self {
+            tasks: Mutex::new(Vec::new()),
+            queue: Mutex::new(VecDeque::new()),
+            wake: Condvar::new(),
+            status: Mutex::new(HashMap::new()),
+            last_interactive: Mutex::new(Instant::now()),
+            interactive: AtomicU64::new(0),
+        }
+    }
+}
+
+impl IdleScheduler {
+    pub fn register(&self, name: &'static str, task: WarmupFn) {
+        self.tasks.lock().unwrap().push((name, task));
+    }
+
+    /// Called from the invoke handler for every command
+    pub fn record_command(&self, command: &str) {
+        if PASSIVE_COMMANDS.contains(&command) {
+            return;
+        }
+        self.interactive.fetch_add(1, Ordering::Relaxed);
+        *self.last_interactive.lock().unwrap() = Instant::now();
+    }
+
+    /// Queue every registered task for `root`, replacing any earlier warmup of it
+    pub fn enqueue(&self, root: &Path) {
+        let names: Vec<&'static str> = self.tasks.lock().unwrap().iter().map(|(name, _)| *name).collect();
+        let tasks = names
+            .iter()
+            .map(|name| WarmupTaskStatus { name: name.to_string(), state: WarmupState::Pending, entries: None, spent_ms: 0, yields: 0, error: None })
+            .collect();
+        self.status.lock().unwrap().insert(root.to_path_buf(), RootWarmup { spent: Duration::ZERO, tasks });
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|(queued, _)| queued != root);
+        queue.extend((0..names.len()).map(|index| (root.to_path_buf(), index)));
+        self.wake.notify_one();
+    }
+
+    /// Drop a closed project's queued work and status
+    pub fn remove_root(&self, root: &Path) {
+        self.queue.lock().unwrap().retain(|(queued, _)| queued != root);
+        self.status.lock().unwrap().remove(root);
+    }
+
+    pub fn status(&self, root: &Path, options: &WarmupOptions) -> Option<WarmupStatus> {
+        let status = self.status.lock().unwrap();
+        let warmup = status.get(root)?;
+        Some(WarmupStatus {
+            root: display_path(root),
+            enabled: options.enabled,
+            budget_ms: options.budget_ms,
+            spent_ms: warmup.spent.as_millis() as u64,
+            tasks: warmup.tasks.clone(),
+        })
+    }
+
+    fn update(&self, root: &Path, index: usize, apply: impl FnOnce(&mut RootWarmup, &mut WarmupTaskStatus)) {
+        let mut status = self.status.lock().unwrap();
+        let Some(warmup) = status.get_mut(root) else { return };
+        let Some(mut task) = warmup.tasks.get(index).cloned() else { return };
+        apply(warmup, &mut task);
+        warmup.tasks[index] = task;
+    }
+
+    /// Next queued task once the app has been idle long enough; blocks until then
+    fn next_idle(&self, app: &AppHandle) -> (PathBuf, usize, WarmupOptions) {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if queue.is_empty() {
+                queue = self.wake.wait(queue).unwrap();
+                continue;
+            }
+            let options = app.state::<SettingsState>().get().warmup;
+            let idle_for = self.last_interactive.lock().unwrap().elapsed();
+            let wanted = Duration::from_millis(options.idle_ms);
+            if options.enabled && idle_for < wanted {
+                queue = self.wake.wait_timeout(queue, wanted - idle_for).unwrap().0;
+                continue;
+            }
+            let (root, index) = queue.pop_front().unwrap();
+            return (root, index, options);
+        }
+    }
+
+    fn run_next(&self, app: &AppHandle) {
+        let (root, index, options) = self.next_idle(app);
+        let budget = Duration::from_millis(options.budget_ms);
+        let spent = self.status.lock().unwrap().get(&root).map(|w| w.spent);
+        let Some(spent) = spent else { return };
+        if !options.enabled || spent >= budget {
+            self.update(&root, index, |_, task| task.state = WarmupState::Skipped);
+            return;
+        }
+        let Some(task) = self.tasks.lock().unwrap().get(index).map(|(_, task)| task.clone()) else { return };
+
+        self.update(&root, index, |_, task| task.state = WarmupState::Running);
+        let started = Instant::now();
+        let slice = budget - spent;
+        let generation = self.interactive.load(Ordering::Relaxed);
+        let should_yield = || self.interactive.load(Ordering::Relaxed) != generation || started.elapsed() >= slice;
+        let result = task(app, &root, &should_yield);
+        let elapsed = started.elapsed();
+
+        let yielded = matches!(result, Ok(WarmupStep::Yielded));
+        self.update(&root, index, |warmup, task| {
+            warmup.spent += elapsed;
+            task.spent_ms += elapsed.as_millis() as u64;
+            match result {
+                Ok(WarmupStep::Done(entries)) => {
+                    task.state = WarmupState::Done;
+                    task.entries = Some(entries);
+                }
+                Ok(WarmupStep::Yielded) => {
+                    task.state = WarmupState::Yielded;
+                    task.yields += 1;
+                }
+                #[cfg(feature = "git")]
+                Ok(WarmupStep::NotApplicable) => task.state = WarmupState::NotApplicable,
+                Err(e) => {
+                    task.state = WarmupState::Failed;
+                    task.error = Some(e);
+                }
+            }
+        });
+        if yielded && self.status.lock().unwrap().contains_key(&root) {
+            self.queue.lock().unwrap().push_back((root, index));
+        }
+    }
+
+    /// Start the thread that drains the queue
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        std::thread::spawn(move || loop {
+            app.state::<IdleScheduler>().run_next(&app);
+        });
+    }
+}
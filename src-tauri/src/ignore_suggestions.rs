@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use git2::{Repository, StatusOptions};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::gitignore::{self, GitignoreEdit, GitignoreFile};
+
+// Untracked files examined; past this the counts are lower bounds
+const MAX_UNTRACKED: usize = 200_000;
+
+// Example paths returned per suggestion
+const MAX_EXAMPLES: usize = 5;
+
+// Untracked files a directory or extension needs before it is suggested without a known pattern
+const MIN_CLUSTER_FILES: usize = 10;
+
+/// Well-known build, cache and tooling artifacts: `name/` patterns match a directory at any
+/// depth, `*.ext` an extension and a bare name a file
+const KNOWN_PATTERNS: &[(&str, &str)] = &[
+    ("node_modules/", "npm dependencies"),
+    ("coverage/", "test coverage reports"),
+    (".nyc_output/", "test coverage data"),
+    (".next/", "Next.js build output"),
+    (".nuxt/", "Nuxt build output"),
+    (".svelte-kit/", "SvelteKit build output"),
+    (".turbo/", "Turborepo cache"),
+    (".parcel-cache/", "Parcel cache"),
+    (".vite/", "Vite cache"),
+    (".cache/", "tool cache"),
+    ("dist/", "build output"),
+    ("build/", "build output"),
+    ("out/", "build output"),
+    ("target/", "Cargo or Maven build output"),
+    (".gradle/", "Gradle cache"),
+    ("__pycache__/", "Python bytecode"),
+    (".pytest_cache/", "pytest cache"),
+    (".mypy_cache/", "mypy cache"),
+    (".ruff_cache/", "Ruff cache"),
+    (".tox/", "tox environments"),
+    (".venv/", "Python virtualenv"),
+    ("venv/", "Python virtualenv"),
+    (".terraform/", "Terraform providers"),
+    (".idea/", "JetBrains project files"),
+    ("*.pyc", "Python bytecode"),
+    ("*.pyo", "Python bytecode"),
+    ("*.class", "Java bytecode"),
+    ("*.o", "object files"),
+    ("*.obj", "object files"),
+    ("*.log", "log files"),
+    ("*.tmp", "temporary files"),
+    ("*.swp", "editor swap files"),
+    ("*.tsbuildinfo", "TypeScript incremental build info"),
+    (".eslintcache", "ESLint cache"),
+    (".DS_Store", "macOS folder metadata"),
+    ("Thumbs.db", "Windows thumbnail cache"),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IgnoreSuggestion {
+    pub pattern: String,
+    pub reason: String,
+    /// From the list of well-known artifacts rather than inferred from a cluster
+    pub known: bool,
+    /// Untracked files the pattern would hide
+    pub file_count: usize,
+    pub examples: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IgnoreSuggestions {
+    pub untracked: usize,
+    /// More untracked files than were examined, so counts are lower bounds
+    pub truncated: bool,
+    /// Highest ranked first
+    pub suggestions: Vec<IgnoreSuggestion>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AppliedIgnoreSuggestions {
+    pub gitignore: GitignoreFile,
+    /// Untracked files left after the new rules
+    pub untracked: usize,
+}
+
+/// Untracked files as git reports them (ignore rules applied), repo-relative with `/`
+fn untracked_files(repo: &Repository) -> Result<(Vec<String>, bool)> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false).exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+    let mut files = Vec::new();
+    let mut truncated = false;
+    for entry in statuses.iter().filter(|e| e.status().is_wt_new()) {
+        if files.len() == MAX_UNTRACKED {
+            truncated = true;
+            break;
+        }
+        if let Some(path) = entry.path() {
+            files.push(path.to_string());
+        }
+    }
+    Ok((files, truncated))
+}
+
+fn tracked_files(repo: &Repository) -> Result<Vec<String>> {
+    Ok(repo.index()?.iter().map(|entry| String::from_utf8_lossy(&entry.path).to_string()).collect())
+}
+
+fn matcher(root: &Path, pattern: &str) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    builder.add_line(None, pattern).ok()?;
+    builder.build().ok()
+}
+
+fn hides(matcher: &Gitignore, root: &Path, path: &str) -> bool {
+    matcher.matched_path_or_any_parents(root.join(path), false).is_ignore()
+}
+
+/// A pattern that would hide any tracked file, e.g. `build/` in a repo that commits `docs/build/x`
+fn hides_tracked(matcher: &Gitignore, root: &Path, tracked: &[String]) -> bool {
+    tracked.iter().any(|path| hides(matcher, root, path))
+}
+
+fn extension(path: &str) -> Option<&str> {
+    let name = path.rsplit('/').next()?;
+    let (stem, ext) = name.rsplit_once('.')?;
+    (!stem.is_empty() && !ext.is_empty()).then_some(ext)
+}
+
+/// Candidate patterns beyond the known list: the shallowest directories that hold only
+/// untracked files, mostly of unfamiliar types, and extensions no tracked file has
+fn cluster_patterns(untracked: &[String], tracked: &[String]) -> Vec<(String, String)> {
+    let tracked_dirs: HashSet<&str> = tracked
+        .iter()
+        .flat_map(|path| path.match_indices('/').map(move |(i, _)| &path[..i]))
+        .collect();
+    let tracked_exts: HashSet<&str> = tracked.iter().filter_map(|path| extension(path)).collect();
+
+    // Per directory: untracked files, and those of them with an extension nothing tracked has
+    let mut dirs: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut exts: BTreeMap<&str, usize> = BTreeMap::new();
+    for path in untracked {
+        let foreign = extension(path).filter(|ext| !tracked_exts.contains(ext));
+        if let Some((i, _)) = path.match_indices('/').find(|(i, _)| !tracked_dirs.contains(&path[..*i])) {
+            let counts = dirs.entry(&path[..i]).or_default();
+            counts.0 += 1;
+            counts.1 += foreign.is_some() as usize;
+        }
+        if let Some(ext) = foreign {
+            *exts.entry(ext).or_default() += 1;
+        }
+    }
+
+    // A new directory of the project's own kinds of file is more likely unadded work than output
+    let dirs = dirs
+        .into_iter()
+        .filter(|(_, (count, foreign))| *count >= MIN_CLUSTER_FILES && foreign * 2 >= *count)
+        .map(|(dir, (count, _))| (format!("/{}/", dir), format!("{} untracked files and nothing tracked", count)));
+    let exts = exts
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_CLUSTER_FILES)
+        .map(|(ext, _)| (format!("*.{}", ext), format!("no tracked .{} files", ext)));
+    dirs.chain(exts).collect()
+}
+
+/// Patterns that would quiet the untracked list, ranked by how many files they hide. Known
+/// artifact patterns rank above inferred clusters with the same count, and no suggestion hides
+/// a file in the index.
+pub fn suggest_gitignore_rules(project_path: &Path) -> Result<IgnoreSuggestions> {
+    let repo = Repository::discover(project_path)?;
+    let root = crate::git::require_workdir(&repo)?.to_path_buf();
+    let (untracked, truncated) = untracked_files(&repo)?;
+    let tracked = tracked_files(&repo)?;
+
+    let known = KNOWN_PATTERNS.iter().map(|(pattern, reason)| (pattern.to_string(), reason.to_string(), true));
+    let clusters = cluster_patterns(&untracked, &tracked).into_iter().map(|(pattern, reason)| (pattern, reason, false));
+
+    // (count, first, last) of what each accepted suggestion hides
+    let mut seen: HashSet<(usize, String, String)> = HashSet::new();
+    let mut suggestions = Vec::new();
+    for (pattern, reason, is_known) in known.chain(clusters) {
+        let Some(matcher) = matcher(&root, &pattern) else { continue };
+        let hidden: Vec<&String> = untracked.iter().filter(|path| hides(&matcher, &root, path)).collect();
+        if hidden.is_empty() || hides_tracked(&matcher, &root, &tracked) {
+            continue;
+        }
+        // An inferred cluster hiding the same files as a known pattern adds nothing
+        let key = (hidden.len(), hidden[0].clone(), hidden[hidden.len() - 1].clone());
+        if !seen.insert(key) && !is_known {
+            continue;
+        }
+        suggestions.push(IgnoreSuggestion {
+            pattern,
+            reason,
+            known: is_known,
+            file_count: hidden.len(),
+            examples: hidden.iter().take(MAX_EXAMPLES).map(|p| p.to_string()).collect(),
+        });
+    }
+    suggestions.sort_by(|a, b| b.file_count.cmp(&a.file_count).then(b.known.cmp(&a.known)).then_with(|| a.pattern.cmp(&b.pattern)));
+
+    Ok(IgnoreSuggestions { untracked: untracked.len(), truncated, suggestions })
+}
+
+/// Append accepted patterns to the repository's root .gitignore through the gitignore editor.
+/// Patterns that would hide a tracked file are refused.
+pub fn apply_gitignore_suggestions(project_path: &Path, patterns: &[String]) -> Result<AppliedIgnoreSuggestions> {
+    let repo = Repository::discover(project_path)?;
+    let root = crate::git::require_workdir(&repo)?.to_path_buf();
+    let tracked = tracked_files(&repo)?;
+    for pattern in patterns {
+        let matcher = matcher(&root, pattern.trim()).ok_or_else(|| anyhow!("'{}' is not a valid gitignore pattern", pattern))?;
+        if hides_tracked(&matcher, &root, &tracked) {
+            return Err(anyhow!("'{}' would hide tracked files", pattern));
+        }
+    }
+
+    let edits: Vec<GitignoreEdit> = patterns.iter().map(|pattern| GitignoreEdit::Add { pattern: pattern.clone(), comment: None }).collect();
+    let gitignore = gitignore::update_gitignore(&root.join(".gitignore"), &edits)?;
+    // A fresh handle, so the status sees the new rules
+    let (untracked, _) = untracked_files(&Repository::open(&root)?)?;
+    Ok(AppliedIgnoreSuggestions { gitignore, untracked: untracked.len() })
+}
@@ -1,275 +1,4130 @@
+// Modules only the git or process commands use are compiled in with that feature
+#[cfg(feature = "git")]
 mod git;
 mod fs;
+mod cache;
+mod settings;
+mod clipboard;
+#[cfg(feature = "process")]
+mod tail;
+mod scratch;
+mod diff;
+mod operations;
+#[cfg(feature = "git")]
+mod template;
+mod manifest;
+mod env;
+mod workspace;
+mod credentials;
+mod gitignore;
+mod snippets;
+#[cfg(feature = "git")]
+mod hooks;
+mod duplicate;
+mod guard;
+mod diagnostics;
+mod editorconfig;
+mod line_endings;
+mod notifications;
+mod allowed_commands;
+#[cfg(feature = "git")]
+mod recent_branches;
+mod dialogs;
+mod pins;
+mod local_history;
+mod blobs;
+mod symbols;
+mod bulk_rename;
+#[cfg(feature = "git")]
+mod pending_push;
+#[cfg(feature = "git")]
+mod repo_events;
+mod word_index;
+mod save_hooks;
+#[cfg(feature = "git")]
+mod forge;
+mod plans;
+#[cfg(feature = "git")]
+mod secrets;
+mod restructure;
+mod save_as;
+mod metrics;
+#[cfg(feature = "git")]
+mod prepush;
+#[cfg(feature = "git")]
+mod repo_health;
+#[cfg(feature = "git")]
+mod commit_message;
+mod references;
+#[cfg(feature = "git")]
+mod chunked;
+#[cfg(feature = "git")]
+mod remote_branches;
+mod activity;
+#[cfg(feature = "git")]
+mod branch_changes;
+mod summaries;
+#[cfg(feature = "git")]
+mod merge_editor;
+mod checkpoints;
+mod storage;
+mod modules;
+mod fingerprint;
+mod text_policy;
+mod paths;
+#[cfg(feature = "git")]
+mod deleted_branches;
+mod asset_metadata;
+#[cfg(feature = "git")]
+mod provenance;
+mod idle;
+#[cfg(feature = "git")]
+mod review_bundle;
+#[cfg(feature = "git")]
+mod ignore_suggestions;
+mod shutdown;
+mod env_profiles;
+mod tags;
+#[cfg(test)]
+mod test_support;
 
-use std::path::Path;
-use git::{GitManager, GitStatus};
-use fs::FileItem;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashSet};
+#[cfg(feature = "git")]
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager, State};
+#[cfg(feature = "git")]
+use git::{GitManager, GitState, GitStatus};
+#[cfg(feature = "git")]
+use git::{ConfigScope, EffectiveIdentity, GitError, GitIdentity, GitIndexError, OperationPriority};
+use fs::{DeletePreview, DirectoryListing, FileWatcher, FsChangeEvent, FsError, WatchMode, WatchModeChanged, WatcherStats};
+use cache::{CacheStats, FileCache, FileCacheState};
+use settings::{AppSettings, SettingsState};
+use clipboard::{ClipboardState, ClipboardStatus, PasteResult};
+#[cfg(feature = "process")]
+use tail::{TailStart, TailState};
+use scratch::{ScratchFile, ScratchStore};
+use diff::TextDiffResult;
+use operations::OperationRegistry;
+use shutdown::{LeftoverReport, ShutdownBlocker, ShutdownCoordinator};
+#[cfg(feature = "git")]
+use template::{TemplateOptions, TemplateResult};
+use manifest::{ManifestCache, ProjectManifest};
+use env::{EnvComparison, EnvEntry, EnvFileInfo};
+use workspace::{BootstrapEvent, BootstrapPayload, BootstrapTimings, BootstrapTimingsState, OpenFileRegistry, PathsRemapped, PhaseTiming, WatchProbes, ProjectBootstrap, WorkspaceDisconnected, WorkspaceInfo, WorkspaceReconnected, WorkspaceRegistry};
+use credentials::{CredentialError, CredentialProfile, CredentialProfileSummary, CredentialStore, CredentialStoreInfo};
+use gitignore::{GitignoreEdit, GitignoreFile, IgnoreExplanation};
+#[cfg(feature = "git")]
+use ignore_suggestions::{AppliedIgnoreSuggestions, IgnoreSuggestions};
+use duplicate::{DuplicateOptions, DuplicateResult};
+use diagnostics::{DiagnosticsReport, ManagedStateCounts, RegisteredPlugins};
+use guard::{ReadOnlyState, ReadOnlyStatus};
+#[cfg(feature = "git")]
+use hooks::{GitHooksInfo, HookInstallResult};
+use snippets::{Snippet, SnippetInput, SnippetScope, SnippetStore};
+use line_endings::{LineEnding, LineEndingReport, NormalizeResult};
+use notifications::{NotificationKind, NotificationState};
+#[cfg(feature = "process")]
+use allowed_commands::{CommandError, CommandOutput};
+#[cfg(feature = "git")]
+use recent_branches::{RecentBranch, RecentBranchStore};
+#[cfg(feature = "git")]
+use deleted_branches::{DeletedBranch, DeletedBranchStore, RestoredBranch};
+use asset_metadata::AssetMetadata;
+#[cfg(feature = "git")]
+use provenance::{LineProvenance, ProvenanceCache};
+#[cfg(feature = "git")]
+use review_bundle::{ReviewBundleOptions, ReviewBundleOutput, ReviewScope};
+use idle::{IdleScheduler, WarmupStatus, WarmupStep};
+#[cfg(feature = "git")]
+use chunked::{MaybeChunked, ResponseChunkEvent, ResponseStore};
+#[cfg(feature = "git")]
+use branch_changes::{ChangedPaths, ChangedPathsCache};
+use summaries::{FileSummary, FilesSummaries, SummaryCache, SummaryMode};
+use activity::{ActivityEntry, ChangeJournal};
+#[cfg(feature = "git")]
+use remote_branches::{CreateBranchResult, NewBranchCheck, RemoteRefCache};
+use dialogs::{DialogDirectoryStore, PickKind, PickedPath};
+use pins::{PinStore, PinnedPath};
+use tags::{TagCount, TagStore, TaggedFile};
+use env_profiles::{EnvProfile, EnvProfileStore, ResolvedEnvProfile};
+use local_history::{LocalHistoryStore, LocalHistoryVersion};
+use blobs::BlobStore;
+use checkpoints::{Checkpoint, CheckpointDiff, CheckpointStore};
+use storage::{CompactionReport, StorageInfo, StorageLayout};
+use modules::{register_commands, BackendModule, BackendModuleInfo, BackendModules, InvokeRegistrar};
+use fingerprint::{FingerprintComparison, FingerprintStore, ProjectFingerprint};
+use text_policy::{PolicyFixReport, PolicyReport, TextPolicy};
+use paths::WsPath;
+use symbols::{Symbol, SymbolIndex, WorkspaceSymbol};
+use bulk_rename::{BulkRenamePlan, PatternKind};
+use restructure::{ConflictStrategy, PartitionRule, RestructurePlan};
+#[cfg(feature = "git")]
+use pending_push::{PendingPush, PendingPushStore, PushRetryEvent};
+#[cfg(feature = "git")]
+use repo_events::{RepoChangeKind, RepoChangeMonitor};
+use word_index::{WordCandidate, WordIndex, WordIndexOptions, WordIndexStats};
+use save_hooks::SaveWithHooksResult;
+use save_as::SaveAsResult;
+#[cfg(feature = "git")]
+use forge::RemoteHostInfo;
+#[cfg(feature = "git")]
+use secrets::{ScanScope, SecretFinding, SecretsMode};
+#[cfg(feature = "git")]
+use repo_health::{RepairAction, RepairResult, RepositoryHealth};
+#[cfg(feature = "git")]
+use prepush::{PrePushOutputEvent, PrePushResults, PrePushRun};
+use metrics::{CommandMetrics, CommandMetricsSnapshot, CommandTiming};
+use plans::{Plan, PlanProgressEvent, PlanStepInput, PlanStepStatus, PlanStepUpdate, PlanStore};
+
+/// The runtime commands run on; unit tests swap in tauri's mock runtime to invoke them
+#[cfg(not(test))]
+pub(crate) type AppRuntime = tauri::Wry;
+#[cfg(test)]
+pub(crate) type AppRuntime = tauri::test::MockRuntime;
+pub(crate) type AppHandle = tauri::AppHandle<AppRuntime>;
+type Window = tauri::Window<AppRuntime>;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  tauri::Builder::default()
-    .plugin(tauri_plugin_fs::init())
-    .plugin(tauri_plugin_dialog::init())
-    .plugin(tauri_plugin_shell::init())
-    .plugin(tauri_plugin_os::init())
-    .setup(|app| {
+  let modules = Arc::new(BackendModules::new(backend_modules()).expect("backend modules conflict"));
+  let dispatcher = modules.clone();
+  let lifecycle = modules.clone();
+  let plugins = RegisteredPlugins::default();
+
+  tauri::Builder::<AppRuntime>::new()
+    .plugin(plugins.record("fs", tauri_plugin_fs::init()))
+    .plugin(plugins.record("dialog", tauri_plugin_dialog::init()))
+    .plugin(plugins.record("shell", tauri_plugin_shell::init()))
+    .plugin(plugins.record("os", tauri_plugin_os::init()))
+    .plugin(plugins.record("notification", tauri_plugin_notification::init()))
+    .setup(move |app| {
       if cfg!(debug_assertions) {
-        app.handle().plugin(
+        app.handle().plugin(plugins.record(
+          "log",
           tauri_plugin_log::Builder::default()
             .level(log::LevelFilter::Info)
             .build(),
-        )?;
+        ))?;
+      }
+      app.manage(plugins);
+
+      let layout = StorageLayout::new(app.path().app_config_dir()?, app.path().app_data_dir()?);
+      let migration = layout.migrate();
+      if !migration.applied.is_empty() || migration.error.is_some() {
+        let _ = app.emit("storage-migration", &migration);
       }
+      manage_state(app.handle(), layout, modules)?;
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
+    .on_window_event(|window, event| {
+      // Closing the last window winds in-flight work down before the app is allowed to exit
+      if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+        let app = window.app_handle().clone();
+        if app.webview_windows().len() > 1 {
+          return;
+        }
+        api.prevent_close();
+        let grace = Duration::from_millis(app.state::<SettingsState>().get().shutdown_grace_ms);
+        std::thread::spawn(move || {
+          if app.state::<ShutdownCoordinator>().shutdown(&app, grace) {
+            app.exit(0);
+          }
+        });
+        return;
+      }
+      // A closed window no longer holds its workspaces open
+      if let tauri::WindowEvent::Destroyed = event {
+        for root in window.state::<WorkspaceRegistry>().release_window(window.label()) {
+          release_workspace_root(window.app_handle(), &root);
+        }
+        window.state::<OpenFileRegistry>().release_window(window.label());
+        window.state::<ChangeJournal>().flush();
+      }
+    })
+    .invoke_handler(move |invoke| handle_invoke(&dispatcher, invoke))
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(move |app, event| {
+      if let tauri::RunEvent::Exit = event {
+        lifecycle.shutdown(app);
+      }
+    });
+}
+
+/// Manage every store and piece of shared state, loading persisted stores from `layout`, then
+/// start the backend modules
+fn manage_state(app: &AppHandle, layout: StorageLayout, modules: Arc<BackendModules>) -> anyhow::Result<()> {
+  let config_dir = layout.config_dir().to_path_buf();
+  let data_dir = layout.data_dir().to_path_buf();
+  let leftovers = shutdown::recover_previous_session(&config_dir, &data_dir);
+  if !leftovers.is_empty() {
+    log::warn!("Cleaned up after the previous session: {:?}", leftovers);
+  }
+  app.manage(ShutdownCoordinator::new(leftovers));
+  app.manage(layout);
+  let settings = SettingsState::load(&config_dir);
+  let settings_snapshot = settings.get();
+  let cache = FileCache::new(settings_snapshot.file_cache_max_bytes);
+  app.manage(CredentialStore::new(&config_dir, settings_snapshot.credential_file_fallback));
+  app.manage(SnippetStore::new(&config_dir));
+  app.manage(settings);
+  app.manage(FileCacheState(Mutex::new(cache)));
+  app.manage(CommandMetrics::new(settings_snapshot.slow_command_threshold_ms, &modules.command_names()));
+  paths::set_include_absolute(settings_snapshot.event_absolute_paths);
+  app.manage(WorkspaceRegistry::default());
+  app.manage(SummaryCache::default());
+  app.manage(IdleScheduler::default());
+  app.manage(BootstrapTimingsState::default());
+  app.manage(OpenFileRegistry::default());
+  app.manage(WatchProbes::default());
+  app.manage(ReadOnlyState::default());
+  app.manage(NotificationState::default());
+  app.manage(ClipboardState::default());
+  app.manage(OperationRegistry::default());
+  app.manage(ManifestCache::default());
+  app.manage(SymbolIndex::default());
+  app.manage(WordIndex::default());
+
+  app.manage(DialogDirectoryStore::load(&data_dir));
+  app.manage(PinStore::load(&data_dir));
+  app.manage(TagStore::load(&data_dir));
+  app.manage(EnvProfileStore::load(&data_dir));
+  app.manage(PlanStore::load(&data_dir));
+  app.manage(ChangeJournal::load(&data_dir));
+
+  app.manage(LocalHistoryStore::load(&data_dir));
+  app.manage(CheckpointStore::load(&data_dir));
+  app.manage(FingerprintStore::load(&data_dir));
+  let history_handle = app.clone();
+  let history_policy = settings_snapshot.local_history.clone();
+  std::thread::spawn(move || {
+    if let Err(e) = history_handle.state::<LocalHistoryStore>().prune(&history_policy) {
+      log::warn!("Local history pruning failed: {}", e);
+    }
+    sweep_blobs(&history_handle);
+  });
+
+  let scratch = ScratchStore::load(&data_dir);
+  if let Err(e) = scratch.cleanup(&settings_snapshot.scratch_policy) {
+    log::warn!("Scratch cleanup failed: {}", e);
+  }
+  app.manage(scratch);
+
+  modules.setup(app)?;
+  app.manage(modules);
+  Ok(())
+}
+
+/// Record the invocation and run it past the guard, then hand it to the module that owns it
+fn handle_invoke(dispatcher: &BackendModules, invoke: tauri::ipc::Invoke<AppRuntime>) -> bool {
+  let webview = invoke.message.webview();
+  let metrics = webview.state::<CommandMetrics>();
+  metrics.record_invocation(invoke.message.command());
+  webview.state::<IdleScheduler>().record_command(invoke.message.command());
+  // Every command passes through the guard before dispatch
+  if let Err(e) = guard::check(&invoke.message) {
+    metrics.record_rejection(invoke.message.command());
+    invoke.resolver.reject(e);
+    return true;
+  }
+  dispatcher.dispatch(invoke)
+}
+
+// Backend Modules
+/// Workspaces, plans, symbols, diagnostics and the other commands every build needs
+struct WorkspaceModule;
+
+impl BackendModule for WorkspaceModule {
+  fn name(&self) -> &'static str {
+    "workspace"
+  }
+
+  fn register(&self, registrar: &mut InvokeRegistrar) {
+    register_commands!(registrar, [
+      get_activity_feed,
+      create_plan,
+      update_plan_step,
+      get_plan,
+      list_plans,
+      cancel_plan,
+      cancel_operation,
+      get_shutdown_blockers,
+      get_previous_session_leftovers,
+      notify_user,
+      open_workspace,
+      open_project,
+      close_project,
+      get_last_bootstrap_timings,
+      get_warmup_status,
+      pin_path,
+      unpin_path,
+      list_pinned,
+      reorder_pinned,
+      list_env_profiles,
+      save_env_profile,
+      delete_env_profile,
+      resolve_env_profile,
+      set_file_tags,
+      get_file_tags,
+      list_tags,
+      find_files_by_tag,
+      close_workspace,
+      list_workspaces,
+      update_draft,
+      discard_draft,
+      get_drafts,
+      set_watch_mode,
+      duplicate_workspace,
+      get_watcher_stats,
+      get_file_outline,
+      query_workspace_symbols,
+      build_word_index,
+      query_word_index,
+      get_word_index_stats,
+      get_project_manifest,
+      run_diagnostics,
+      export_diagnostics,
+      get_command_metrics,
+      reset_command_metrics,
+      record_command_timings,
+      list_backend_modules,
+    ]);
+    // Templates are cloned from a git URL
+    #[cfg(feature = "git")]
+    register_commands!(registrar, [create_project_from_template]);
+  }
+
+  fn setup(&self, app: &AppHandle) -> anyhow::Result<()> {
+    tauri::async_runtime::spawn(watch_workspace_availability(app.clone()));
+    app.state::<ShutdownCoordinator>().register("operations", Box::new(|app| {
+      let cancelled = app.state::<OperationRegistry>().cancel_all();
+      if cancelled > 0 {
+        log::info!("Shutdown: cancelled {} operations", cancelled);
+      }
+    }));
+    let idle = app.state::<IdleScheduler>();
+    idle.register("word_index", Arc::new(warm_word_index));
+    IdleScheduler::start(app);
+    Ok(())
+  }
+
+  fn shutdown(&self, app: &AppHandle) {
+    app.state::<ChangeJournal>().flush();
+  }
+}
+
+/// File reads and writes, history, checkpoints and the editor's file utilities
+struct FsModule;
+
+impl BackendModule for FsModule {
+  fn name(&self) -> &'static str {
+    "fs"
+  }
+
+  fn register(&self, registrar: &mut InvokeRegistrar) {
+    // Chunked responses only come from git commands
+    #[cfg(feature = "git")]
+    register_commands!(registrar, [
+      read_response_chunk,
+      stream_response,
+      release_response,
+    ]);
+    register_commands!(registrar, [
+      get_file_summary,
+      get_asset_metadata,
+      get_assets_metadata,
+      get_files_summaries,
+      read_file_lines,
+      create_checkpoint,
+      list_checkpoints,
+      diff_checkpoints,
+      diff_checkpoint_file,
+      compute_project_fingerprint,
+      compare_project_fingerprint,
+      get_project_fingerprint,
+      list_directory,
+      probe_paths,
+      create_file,
+      create_directory,
+      rename_path,
+      find_file_references,
+      bulk_rename,
+      flatten_directory,
+      partition_directory,
+      delete_path,
+      preview_delete,
+      mark_file_open,
+      mark_file_closed,
+      move_path,
+      read_file_content,
+      write_file_content,
+      save_with_hooks,
+      save_file_as,
+      get_local_history,
+      read_local_history_version,
+      restore_local_history_version,
+      pick_open_path,
+      pick_save_path,
+      get_cache_stats,
+      clear_file_cache,
+      clipboard_copy_paths,
+      clipboard_get_status,
+      clipboard_paste,
+      create_scratch_file,
+      list_scratch_files,
+      promote_scratch_file,
+      delete_scratch_file,
+      diff_files,
+      diff_texts,
+      analyze_line_endings,
+      normalize_line_endings,
+      check_text_policy,
+      fix_text_policy,
+      explain_ignore,
+      parse_gitignore,
+      update_gitignore,
+      list_env_files,
+      read_env_file,
+      set_env_value,
+      remove_env_key,
+      compare_env_files,
+    ]);
+  }
+}
+
+/// Settings, credentials, snippets and app storage
+struct SettingsModule;
+
+impl BackendModule for SettingsModule {
+  fn name(&self) -> &'static str {
+    "settings"
+  }
+
+  fn register(&self, registrar: &mut InvokeRegistrar) {
+    register_commands!(registrar, [
+      get_storage_info,
+      compact_storage,
+      get_credential_store_info,
+      save_credential_profile,
+      list_credential_profiles,
+      delete_credential_profile,
+      unlock_credential_file_store,
+      export_credentials,
+      import_credentials,
+      get_settings,
+      update_settings,
+      save_snippet,
+      list_snippets,
+      get_snippet,
+      delete_snippet,
+      export_snippets,
+      import_snippets,
+      get_read_only_mode,
+      set_read_only_mode,
+    ]);
+  }
+}
+
+#[cfg(feature = "git")]
+struct GitModule;
+
+#[cfg(feature = "git")]
+impl BackendModule for GitModule {
+  fn name(&self) -> &'static str {
+    "git"
+  }
+
+  fn register(&self, registrar: &mut InvokeRegistrar) {
+    register_commands!(registrar, [
       get_git_status,
       stage_file,
       unstage_file,
+      mark_intent_to_add,
+      unmark_intent_to_add,
       commit_changes,
+      list_git_identities,
+      save_git_identity,
+      get_effective_identity,
+      apply_identity,
+      restore_deleted_files,
+      get_directory_status_rollup,
+      expand_untracked_directory,
+      checkout_branch,
+      check_tree_portability,
+      create_branch,
+      checkout_remote_branch,
+      stash_list,
+      drop_stale_auto_stashes,
+      get_recent_branches,
+      get_branches_overview,
+      delete_merged_branches,
+      list_deleted_branches,
+      restore_deleted_branch,
       get_recent_commits,
+      list_branches,
+      get_commit_details,
+      list_tracked_files,
+      get_sparse_checkout,
+      set_sparse_checkout,
+      disable_sparse_checkout,
+      diff_workdir_to_ref,
+      get_file_diff,
+      get_file_diff_both,
+      get_changed_paths_vs_ref,
+      finalize_merged_file,
+      can_continue_operation,
+      get_worktree_diff_stats,
+      add_git_note,
+      get_git_note,
+      list_git_notes,
       init_git_repo,
       init_git_repo_enhanced,
       get_git_config,
       set_git_config,
       is_git_repository,
       git_push,
+      get_last_prepush_run,
+      get_pending_pushes,
+      cancel_pending_push,
+      scan_for_secrets,
       git_pull,
       get_git_credentials,
       set_git_credentials,
       delete_git_credentials,
-      list_directory,
-      create_file,
-      create_directory,
-      rename_path,
-      delete_path,
-      move_path,
-      read_file_content,
-      write_file_content
-    ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+      save_git_credentials_cmd,
+      clear_git_credentials_cmd,
+      get_repo_size_info,
+      run_repo_maintenance,
+      check_repository_health,
+      repair_repository,
+      validate_branch_name,
+      suggest_branch_name,
+      list_git_hooks,
+      install_managed_hook,
+      uninstall_managed_hook,
+      git_clone,
+      get_clone_info,
+      get_remote_host_info,
+      build_remote_file_url,
+      get_line_provenance,
+      export_review_bundle,
+      get_review_manifest_hash,
+      backfill_clone,
+      suggest_gitignore_rules,
+      apply_gitignore_suggestions,
+    ]);
+  }
+
+  fn setup(&self, app: &AppHandle) -> anyhow::Result<()> {
+    let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+    app.manage(GitState::default());
+    app.manage(RepoChangeMonitor::default());
+    app.manage(ResponseStore::default());
+    app.manage(PrePushResults::default());
+    app.manage(RemoteRefCache::default());
+    app.manage(ChangedPathsCache::default());
+    app.manage(ProvenanceCache::default());
+    app.manage(RecentBranchStore::load(&data_dir));
+    app.manage(DeletedBranchStore::load(&data_dir));
+    app.manage(PendingPushStore::load(&data_dir));
+    tauri::async_runtime::spawn(retry_pending_pushes(app.clone()));
+    app.state::<IdleScheduler>().register("file_index", Arc::new(warm_file_index));
+    Ok(())
+  }
+}
+
+/// Allow-listed commands and file tailing
+#[cfg(feature = "process")]
+struct ProcessModule;
+
+#[cfg(feature = "process")]
+impl BackendModule for ProcessModule {
+  fn name(&self) -> &'static str {
+    "process"
+  }
+
+  fn register(&self, registrar: &mut InvokeRegistrar) {
+    register_commands!(registrar, [
+      run_allowed_command,
+      tail_file,
+      stop_tail,
+    ]);
+  }
+
+  fn setup(&self, app: &AppHandle) -> anyhow::Result<()> {
+    app.manage(TailState::default());
+    app.state::<ShutdownCoordinator>().register("tails", Box::new(|app| app.state::<TailState>().stop_all()));
+    Ok(())
+  }
+
+  fn shutdown(&self, app: &AppHandle) {
+    app.state::<TailState>().stop_all();
+  }
+}
+
+fn backend_modules() -> Vec<Box<dyn BackendModule>> {
+  #[allow(unused_mut)]
+  let mut modules: Vec<Box<dyn BackendModule>> = vec![Box::new(WorkspaceModule), Box::new(FsModule), Box::new(SettingsModule)];
+  #[cfg(feature = "git")]
+  modules.push(Box::new(GitModule));
+  #[cfg(feature = "process")]
+  modules.push(Box::new(ProcessModule));
+  modules
+}
+
+/// Modules compiled into this build with their versions and commands, for the About screen
+#[tauri::command]
+async fn list_backend_modules(modules: State<'_, Arc<BackendModules>>) -> Result<Vec<BackendModuleInfo>, String> {
+  Ok(modules.info())
+}
+
+// Diagnostics Commands
+fn collect_diagnostics(app: &AppHandle) -> Result<DiagnosticsReport, String> {
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let workspaces = app.state::<WorkspaceRegistry>();
+  #[cfg(feature = "process")]
+  let active_tails = app.state::<TailState>().active_count();
+  #[cfg(not(feature = "process"))]
+  let active_tails = 0;
+  let state = ManagedStateCounts {
+    open_workspaces: workspaces.list().len(),
+    watched_directories: workspaces.watcher_stats().total_watched_directories,
+    active_tails,
+    running_operations: app.state::<OperationRegistry>().active_count(),
+    cached_files: app.state::<FileCacheState>().0.lock().unwrap().stats(true).entries,
+    #[cfg(feature = "git")]
+    pending_responses: app.state::<ResponseStore>().stats(),
+  };
+  let credential_backend = app.state::<CredentialStore>().info().active_backend;
+  let version = app.package_info().version.to_string();
+  let command_metrics = app.state::<CommandMetrics>().snapshot();
+  let modules = app.state::<Arc<BackendModules>>().info();
+  let plugins = app.state::<RegisteredPlugins>().list();
+  Ok(diagnostics::build_report(&version, &data_dir, credential_backend, state, command_metrics, modules, plugins))
+}
+
+#[tauri::command]
+async fn run_diagnostics(app: AppHandle) -> Result<DiagnosticsReport, String> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || collect_diagnostics(&handle))
+    .await
+    .map_err(|e| format!("Diagnostics task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn export_diagnostics(app: AppHandle, output_path: String) -> Result<String, String> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let report = collect_diagnostics(&handle)?;
+    let log_dir = handle.path().app_log_dir().map_err(|e| format!("Failed to resolve log dir: {}", e))?;
+    match diagnostics::export_diagnostics(&report, &log_dir, &fs::decode_path(&output_path)) {
+      Ok(path) => Ok(fs::display_path(&path)),
+      Err(e) => Err(format!("Failed to export diagnostics: {}", e)),
+    }
+  })
+  .await
+  .map_err(|e| format!("Diagnostics task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn get_command_metrics(metrics: State<'_, CommandMetrics>) -> Result<CommandMetricsSnapshot, String> {
+  Ok(metrics.snapshot())
+}
+
+#[tauri::command]
+async fn reset_command_metrics(metrics: State<'_, CommandMetrics>) -> Result<(), String> {
+  metrics.reset();
+  Ok(())
+}
+
+/// Durations of finished commands as the frontend timed them, sent in batches
+#[tauri::command]
+async fn record_command_timings(app: AppHandle, timings: Vec<CommandTiming>) -> Result<(), String> {
+  metrics::record_timings(&app, timings);
+  Ok(())
+}
+
+// Backend Mode Commands
+#[tauri::command]
+async fn get_read_only_mode(read_only: State<'_, ReadOnlyState>) -> Result<ReadOnlyStatus, String> {
+  Ok(read_only.status())
+}
+
+#[tauri::command]
+async fn set_read_only_mode(read_only: State<'_, ReadOnlyState>, enabled: bool, workspace_root: Option<String>) -> Result<ReadOnlyStatus, String> {
+  read_only.set(enabled, workspace_root.as_deref().map(fs::decode_path).as_deref());
+  Ok(read_only.status())
 }
 
 // Git Commands
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn get_git_status(project_path: String) -> Result<GitStatus, String> {
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.get_status(Path::new(&project_path)) {
-    Ok(status) => Ok(status),
+async fn get_git_status(app: AppHandle, git_state: State<'_, GitState>, project_path: String) -> Result<GitStatus, String> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  let batched = app.state::<SettingsState>().get().git_status_batched_scan && git_state.first_status_scan(&root);
+  let handle = app.clone();
+  let result = git_state.run_queued(&root, "status", OperationPriority::Interactive, git_operation_emitter(&app), move || {
+    with_git_manager(&handle, &path, |manager| {
+      if !batched {
+        return manager.get_status(&path);
+      }
+      manager.get_status_batched(&path, |progress| {
+        let _ = handle.emit("git-status-progress", progress);
+      })
+    })
+  }).await?;
+  match result {
+    Ok(status) => {
+      app.state::<WorkspaceRegistry>().store_status(&root, &status);
+      Ok(status)
+    }
     Err(e) => Err(format!("Failed to get git status: {}", e)),
   }
 }
 
-#[tauri::command]
-async fn stage_file(project_path: String, file_path: String) -> Result<(), String> {
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.stage_file(&file_path) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to stage file: {}", e)),
+/// Run `op` with the repository handle of the workspace rooted at `project_path`, or with a
+/// fresh one when no open workspace is
+#[cfg(feature = "git")]
+fn with_git_manager<T>(app: &AppHandle, project_path: &Path, op: impl FnOnce(&GitManager) -> T) -> T {
+  match app.state::<WorkspaceRegistry>().git_manager(project_path) {
+    Some(shared) => {
+      let manager = shared.lock().unwrap();
+      manager.refresh_index();
+      op(&manager)
+    }
+    None => op(&GitManager::new(project_path)),
   }
 }
 
-#[tauri::command]
-async fn unstage_file(project_path: String, file_path: String) -> Result<(), String> {
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.unstage_file(&file_path) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to unstage file: {}", e)),
+/// Forward queue lifecycle events for heavy git operations to the frontend
+#[cfg(feature = "git")]
+fn git_operation_emitter(app: &AppHandle) -> impl Fn(git::GitOperationEvent) {
+  let app = app.clone();
+  move |event| {
+    let _ = app.emit("git-operation", event);
   }
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn commit_changes(project_path: String, message: String) -> Result<String, String> {
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.commit(&message) {
-    Ok(hash) => Ok(hash),
-    Err(e) => Err(format!("Failed to commit: {}", e)),
-  }
+async fn stage_file(app: AppHandle, git_state: State<'_, GitState>, project_path: String, file_path: String, allow_symlinks_outside_repo: Option<bool>) -> Result<(), GitError> {
+  let allow_symlinks = allow_symlinks_outside_repo.unwrap_or(false);
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || {
+    with_git_manager(&app, &path, |manager| manager.stage_file(&file_path, allow_symlinks))
+  }).await
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn get_recent_commits(project_path: String, limit: usize) -> Result<Vec<git::GitCommit>, String> {
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.get_recent_commits(limit) {
-    Ok(commits) => Ok(commits),
-    Err(e) => Err(format!("Failed to get commits: {}", e)),
-  }
+async fn unstage_file(app: AppHandle, git_state: State<'_, GitState>, project_path: String, file_path: String) -> Result<(), GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || {
+    with_git_manager(&app, &path, |manager| manager.unstage_file(&file_path))
+  }).await
 }
 
+/// Like `git add -N`: the files show up in diffs without their content being staged
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn init_git_repo(project_path: String) -> Result<(), String> {
-  match git::init_git_repo(Path::new(&project_path)) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to initialize git repository: {}", e)),
-  }
+async fn mark_intent_to_add(app: AppHandle, git_state: State<'_, GitState>, project_path: String, paths: Vec<String>) -> Result<Vec<String>, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || {
+    with_git_manager(&app, &path, |manager| manager.mark_intent_to_add(&paths))
+  }).await
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn init_git_repo_enhanced(project_path: String) -> Result<git::GitInitResult, String> {
-  match git::init_git_repo_enhanced(Path::new(&project_path)) {
-    Ok(result) => Ok(result),
-    Err(e) => Err(format!("Failed to initialize git repository: {}", e)),
-  }
+async fn unmark_intent_to_add(app: AppHandle, git_state: State<'_, GitState>, project_path: String, paths: Vec<String>) -> Result<Vec<String>, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || {
+    with_git_manager(&app, &path, |manager| manager.unmark_intent_to_add(&paths))
+  }).await
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn get_git_config(project_path: String) -> Result<git::GitConfig, String> {
-  match git::get_git_config(Path::new(&project_path)) {
-    Ok(config) => Ok(config),
-    Err(e) => Err(format!("Failed to get git config: {}", e)),
-  }
+async fn commit_changes(app: AppHandle, project_path: String, message: String, include: Option<git::CommitInclude>, paths: Option<Vec<String>>, allow_secrets: Option<bool>) -> Result<git::CommitResult, GitError> {
+  let include = include.unwrap_or_default();
+  let paths = paths.unwrap_or_default();
+  let settings = app.state::<SettingsState>().get();
+  let scan = settings.secret_scan_for(&workspace::project_key(&project_path));
+  let data_dir = Some(app.state::<StorageLayout>().data_dir().to_path_buf());
+  let root = fs::decode_path(&project_path);
+  let (handle, path, project) = (app.clone(), root.clone(), project_path.clone());
+
+  let (commit_id, secret_findings) = app.state::<GitState>().with_index_lock(&root, move || with_git_manager(&handle, &path, |manager| {
+    // Staged in memory only, so a blocked commit leaves the user's index as it was
+    let tree = manager.prepare_commit_tree(include, &paths)?;
+    let scanned = (|| -> anyhow::Result<Vec<SecretFinding>> {
+      let findings = match settings.block_on_secrets {
+        SecretsMode::Off => Vec::new(),
+        _ => secrets::scan_commit_tree(&path, tree, &scan)?,
+      };
+      if !findings.is_empty() {
+        if let Some(data_dir) = &data_dir {
+          allowed_commands::record_audit_event(data_dir, &project, "secret-scan:commit", secrets::audit_summary(&findings));
+        }
+        if settings.block_on_secrets == SecretsMode::Block && !allow_secrets.unwrap_or(false) {
+          return Err(GitError::SecretsDetected { findings }.into());
+        }
+      }
+      Ok(findings)
+    })();
+    let findings = match scanned {
+      Ok(findings) => findings,
+      Err(e) => {
+        manager.discard_index_changes();
+        return Err(e);
+      }
+    };
+    manager.persist_index()?;
+    Ok((manager.commit_tree(&message, tree)?, findings))
+  })).await?;
+  let mut result = git::committed_as(&root, &commit_id)?;
+  result.secret_findings = secret_findings;
+  log::info!("Committed {} in {}", result.commit_id, project_path);
+  Ok(result)
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn set_git_config(project_path: String, name: String, email: String) -> Result<(), String> {
-  match git::set_git_config(Path::new(&project_path), &name, &email) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to set git config: {}", e)),
+async fn list_git_identities(settings: State<'_, SettingsState>) -> Result<Vec<GitIdentity>, String> {
+  Ok(settings.get().git_identities)
+}
+
+/// Add an identity, or replace the one with the same label
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn save_git_identity(settings: State<'_, SettingsState>, label: String, name: String, email: String, signing_key: Option<String>) -> Result<Vec<GitIdentity>, String> {
+  let mut current = settings.get();
+  let identity = GitIdentity { label, name, email, signing_key: signing_key.filter(|k| !k.trim().is_empty()) };
+  match current.git_identities.iter_mut().find(|i| i.label == identity.label) {
+    Some(existing) => *existing = identity,
+    None => current.git_identities.push(identity),
   }
+  let identities = current.git_identities.clone();
+  settings.update(current).map_err(|e| format!("Failed to save identity: {}", e))?;
+  Ok(identities)
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn is_git_repository(project_path: String) -> Result<bool, String> {
-  Ok(git::is_git_repository(Path::new(&project_path)))
+async fn get_effective_identity(settings: State<'_, SettingsState>, project_path: String) -> Result<EffectiveIdentity, String> {
+  git::get_effective_identity(&fs::decode_path(&project_path), &settings.get().git_identities)
+    .map_err(|e| format!("Failed to read git identity: {}", e))
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn git_push(project_path: String, remote_name: Option<String>, branch_name: Option<String>, username: Option<String>, password: Option<String>) -> Result<(), String> {
-  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
-  let branch = branch_name.unwrap_or_else(|| "main".to_string());
-  
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.push(&remote, &branch, username.as_deref(), password.as_deref()) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to push: {}", e)),
-  }
+async fn apply_identity(settings: State<'_, SettingsState>, project_path: String, label: String, scope: ConfigScope) -> Result<EffectiveIdentity, String> {
+  let identities = settings.get().git_identities;
+  let identity = identities.iter().find(|i| i.label == label).ok_or_else(|| format!("No saved identity named '{}'", label))?;
+  git::apply_identity(&fs::decode_path(&project_path), identity, scope).map_err(|e| format!("Failed to apply identity: {}", e))?;
+  git::get_effective_identity(&fs::decode_path(&project_path), &identities).map_err(|e| format!("Failed to read git identity: {}", e))
 }
 
+/// Finish one file in the merge editor: refuse leftover conflict markers, write the merged
+/// content and stage it, then report what is still conflicted
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn git_pull(project_path: String, remote_name: Option<String>, branch_name: Option<String>) -> Result<(), String> {
-  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
-  let branch = branch_name.unwrap_or_else(|| "main".to_string());
-  
-  let git_manager = GitManager::new(Path::new(&project_path));
-  match git_manager.pull(&remote, &branch) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to pull: {}", e)),
-  }
+async fn finalize_merged_file(app: AppHandle, git_state: State<'_, GitState>, project_path: String, file_path: String, merged_content: String) -> Result<merge_editor::OperationStatus, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || {
+    merge_editor::finalize_merged_file(&path, &file_path, &merged_content, |target| {
+      if let Err(e) = app.state::<LocalHistoryStore>().record(target, "finalize_merged_file", &app.state::<SettingsState>().get().local_history) {
+        log::warn!("Failed to keep local history for {}: {}", target.display(), e);
+      }
+    })
+  }).await
 }
 
-// Store credentials securely in OS keychain
+/// Which button finishes the in-progress merge, rebase or cherry-pick, if any
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn save_git_credentials_cmd(project_path: String, remote_name: Option<String>, username: String, password: String) -> Result<(), String> {
-  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
-  match crate::git::save_git_credentials(Path::new(&project_path), &remote, &username, &password) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to save credentials: {}", e)),
-  }
+async fn can_continue_operation(project_path: String) -> Result<merge_editor::OperationStatus, String> {
+  merge_editor::operation_status(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read operation state: {}", e))
 }
 
-// Clear stored credentials
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn clear_git_credentials_cmd(project_path: String, remote_name: Option<String>, username: String) -> Result<(), String> {
-  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
-  match crate::git::clear_git_credentials(Path::new(&project_path), &remote, &username) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to clear credentials: {}", e)),
+async fn restore_deleted_files(git_state: State<'_, GitState>, project_path: String, paths: Option<Vec<String>>) -> Result<git::RestoreResult, GitError> {
+  let root = fs::decode_path(&project_path);
+  if !git::is_git_repository(&root) {
+    return Err(GitIndexError::not_a_repository(&root).into());
   }
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || git::restore_deleted_files(&path, paths.as_deref())).await
 }
 
-// File System Commands
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn list_directory(path: String) -> Result<Vec<FileItem>, String> {
-  match fs::list_directory(Path::new(&path)) {
-    Ok(items) => Ok(items),
-    Err(e) => Err(format!("Failed to list directory: {}", e)),
+async fn expand_untracked_directory(app: AppHandle, project_path: String, dir_path: String) -> Result<MaybeChunked<Vec<String>>, String> {
+  match git::expand_untracked_directory(&fs::decode_path(&project_path), &dir_path) {
+    Ok(files) => respond(&app, files),
+    Err(e) => Err(format!("Failed to expand directory: {}", e)),
   }
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn create_file(file_path: String, content: String) -> Result<(), String> {
-  match fs::create_file(Path::new(&file_path), &content) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to create file: {}", e)),
+async fn get_directory_status_rollup(project_path: String) -> Result<std::collections::BTreeMap<String, git::DirectoryStatusCounts>, String> {
+  match git::get_directory_status_rollup(&fs::decode_path(&project_path)) {
+    Ok(rollup) => Ok(rollup),
+    Err(e) => Err(format!("Failed to get directory status: {}", e)),
   }
 }
 
+/// `branch_name` may be a remote-tracking branch ("origin/feature-x"), which checks out a local
+/// branch tracking it. Local changes the switch would overwrite fail with `CheckoutBlocked`
+/// unless `force` discards them.
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn create_directory(dir_path: String) -> Result<(), String> {
-  match fs::create_directory(Path::new(&dir_path)) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to create directory: {}", e)),
-  }
+async fn checkout_branch(git_state: State<'_, GitState>, recent: State<'_, RecentBranchStore>, project_path: String, branch_name: String, auto_stash: Option<bool>, allow_case_collisions: Option<bool>, force: Option<bool>) -> Result<git::CheckoutResult, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  let allow_case_collisions = allow_case_collisions.unwrap_or(false);
+  let force = force.unwrap_or(false);
+  let result = git_state.with_index_lock(&root, move || git::checkout_branch(&path, &branch_name, auto_stash.unwrap_or(false), allow_case_collisions, force)).await?;
+  recent.record_head(&root);
+  Ok(result)
 }
 
+/// Paths in `reference` (HEAD when omitted) that collide by case or Unicode normalization, and
+/// whether this working tree's filesystem would fold them
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn rename_path(from: String, to: String) -> Result<(), String> {
-  match fs::rename_path(Path::new(&from), Path::new(&to)) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to rename: {}", e)),
+async fn check_tree_portability(project_path: String, reference: Option<String>) -> Result<git::TreePortability, String> {
+  tauri::async_runtime::spawn_blocking(move || git::check_tree_portability(&fs::decode_path(&project_path), reference.as_deref()))
+    .await
+    .map_err(|e| format!("Failed to check tree portability: {}", e))?
+    .map_err(|e| format!("Failed to check tree portability: {}", e))
+}
+
+/// Create a branch, optionally refusing when the upstream remote already has one by that name.
+/// `force_local` creates it anyway.
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn create_branch(app: AppHandle, project_path: String, branch_name: String, start_point: Option<String>, checkout: Option<bool>, check_remote: Option<bool>, force_local: Option<bool>) -> Result<CreateBranchResult, GitError> {
+  let check_remote = check_remote.unwrap_or(false) && !force_local.unwrap_or(false);
+  let checkout = checkout.unwrap_or(true);
+  let handle = app.clone();
+  let root = fs::decode_path(&project_path);
+  let (path, name) = (root.clone(), branch_name.clone());
+  // The remote listing happens before the index lock so a slow remote does not hold it
+  let check = tauri::async_runtime::spawn_blocking(move || {
+    remote_branches::check_new_branch(&handle.state::<RemoteRefCache>(), &handle.state::<CredentialStore>(), &path, &name, check_remote)
+  })
+  .await
+  .map_err(|e| GitError::Failed { message: format!("Git task failed: {}", e) })??;
+  let remote_check_error = match check {
+    NewBranchCheck::OnRemote(result) => return Ok(result),
+    NewBranchCheck::Clear { remote_check_error } => remote_check_error,
+  };
+
+  let (path, name) = (root.clone(), branch_name.clone());
+  app.state::<GitState>().with_index_lock(&root, move || {
+    remote_branches::create_local_branch(&path, &name, start_point.as_deref(), checkout)
+  }).await?;
+  if checkout {
+    app.state::<RecentBranchStore>().record_head(&root);
   }
+  Ok(CreateBranchResult::Created { branch: branch_name, checked_out: checkout, remote_check_error })
 }
 
+/// Check out a branch that exists on `remote_name` as a local tracking branch
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn delete_path(path: String) -> Result<(), String> {
-  match fs::delete_path(Path::new(&path)) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to delete: {}", e)),
+async fn checkout_remote_branch(app: AppHandle, project_path: String, remote_name: String, branch_name: String) -> Result<(), GitError> {
+  let handle = app.clone();
+  let root = fs::decode_path(&project_path);
+  let (path, remote, branch) = (root.clone(), remote_name.clone(), branch_name.clone());
+  // Fetch before taking the index lock; the network call is not bounded
+  tauri::async_runtime::spawn_blocking(move || remote_branches::fetch_remote_branch(&handle.state::<CredentialStore>(), &path, &remote, &branch))
+    .await
+    .map_err(|e| GitError::Failed { message: format!("Git task failed: {}", e) })??;
+
+  let path = root.clone();
+  app.state::<GitState>().with_index_lock(&root, move || remote_branches::track_remote_branch(&path, &remote_name, &branch_name)).await?;
+  app.state::<RecentBranchStore>().record_head(&root);
+  Ok(())
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn stash_list(project_path: String) -> Result<Vec<git::StashEntry>, String> {
+  git::stash_list(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to list stashes: {}", e))
+}
+
+/// Drop IDE-made auto-stashes older than `older_than_days` (default 14)
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn drop_stale_auto_stashes(git_state: State<'_, GitState>, project_path: String, older_than_days: Option<u64>) -> Result<usize, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || git::drop_stale_auto_stashes(&path, older_than_days.unwrap_or(14))).await
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_sparse_checkout(project_path: String) -> Result<git::SparseCheckoutInfo, String> {
+  git::get_sparse_checkout(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read sparse checkout: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn set_sparse_checkout(git_state: State<'_, GitState>, project_path: String, patterns: Vec<String>, cone_mode: bool) -> Result<git::SparseCheckoutInfo, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || git::set_sparse_checkout(&path, &patterns, cone_mode)).await
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn disable_sparse_checkout(git_state: State<'_, GitState>, project_path: String) -> Result<git::SparseCheckoutInfo, GitError> {
+  let root = fs::decode_path(&project_path);
+  let path = root.clone();
+  git_state.with_index_lock(&root, move || git::disable_sparse_checkout(&path)).await
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_branches_overview(git_state: State<'_, GitState>, settings: State<'_, SettingsState>, project_path: String) -> Result<git::BranchesOverview, String> {
+  let protected = settings.get().protected_branches;
+  git::get_branches_overview(&git_state, &fs::decode_path(&project_path), &protected).map_err(|e| format!("Failed to get branches overview: {}", e))
+}
+
+/// Delete branches fully merged into the default branch; protected and checked-out ones are kept.
+/// Deleted branches are journaled for `restore_deleted_branch`.
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn delete_merged_branches(git_state: State<'_, GitState>, settings: State<'_, SettingsState>, deleted: State<'_, DeletedBranchStore>, project_path: String, dry_run: bool) -> Result<git::MergedBranchCleanup, String> {
+  let protected = settings.get().protected_branches;
+  let cleanup = git::delete_merged_branches(&git_state, &fs::decode_path(&project_path), &protected, dry_run).map_err(|e| format!("Failed to delete merged branches: {}", e))?;
+  if !cleanup.dry_run {
+    let journaled: Vec<(String, String, Option<String>)> = cleanup
+      .branches
+      .iter()
+      .filter(|b| b.error.is_none())
+      .map(|b| (b.name.clone(), b.commit.clone(), b.upstream.clone()))
+      .collect();
+    deleted.record(&fs::decode_path(&project_path), &journaled);
   }
+  Ok(cleanup)
 }
 
+/// Branches deleted through the IDE that can still be restored, newest first
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn move_path(from: String, to: String) -> Result<(), String> {
-  match fs::move_path(Path::new(&from), Path::new(&to)) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to move: {}", e)),
+async fn list_deleted_branches(deleted: State<'_, DeletedBranchStore>, settings: State<'_, SettingsState>, project_path: String) -> Result<Vec<DeletedBranch>, String> {
+  deleted
+    .list(&fs::decode_path(&project_path), settings.get().deleted_branch_retention_days)
+    .map_err(|e| format!("Failed to list deleted branches: {}", e))
+}
+
+/// Recreate a deleted branch at its recorded tip. If that commit was pruned, pass `use_reflog`
+/// to recreate it at the commit the HEAD reflog last saw on it instead.
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn restore_deleted_branch(deleted: State<'_, DeletedBranchStore>, project_path: String, name: String, use_reflog: Option<bool>) -> Result<RestoredBranch, String> {
+  deleted
+    .restore(&fs::decode_path(&project_path), &name, use_reflog.unwrap_or(false))
+    .map_err(|e| format!("Failed to restore branch: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_recent_branches(recent: State<'_, RecentBranchStore>, project_path: String, limit: Option<usize>) -> Result<Vec<RecentBranch>, String> {
+  match recent.recent(&fs::decode_path(&project_path), limit.unwrap_or(10)) {
+    Ok(branches) => Ok(branches),
+    Err(e) => Err(format!("Failed to get recent branches: {}", e)),
   }
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn read_file_content(file_path: String) -> Result<String, String> {
-  match fs::read_file_content(Path::new(&file_path)) {
-    Ok(content) => Ok(content),
-    Err(e) => Err(format!("Failed to read file: {}", e)),
+async fn get_recent_commits(app: AppHandle, git_state: State<'_, GitState>, project_path: String, limit: usize) -> Result<Vec<git::GitCommit>, String> {
+  let path = project_path.clone();
+  let issue_patterns = app.state::<SettingsState>().get().issue_trailer_patterns;
+  let result = git_state.run_queued(&fs::decode_path(&project_path), "history", OperationPriority::Interactive, git_operation_emitter(&app), move || {
+    GitManager::new(&fs::decode_path(&path)).get_recent_commits(limit, &issue_patterns)
+  }).await?;
+  match result {
+    Ok(commits) => Ok(commits),
+    Err(e) => Err(format!("Failed to get commits: {}", e)),
   }
 }
 
+/// Local and remote branches for the branch picker
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn write_file_content(file_path: String, content: String) -> Result<(), String> {
-  match fs::write_file_content(Path::new(&file_path), &content) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to write file: {}", e)),
+async fn list_branches(app: AppHandle, git_state: State<'_, GitState>, project_path: String) -> Result<Vec<git::GitBranch>, String> {
+  let path = project_path.clone();
+  let result = git_state.run_queued(&fs::decode_path(&project_path), "branches", OperationPriority::Interactive, git_operation_emitter(&app), move || {
+    GitManager::new(&fs::decode_path(&path)).list_branches()
+  }).await?;
+  result.map_err(|e| format!("Failed to list branches: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn diff_workdir_to_ref(app: AppHandle, project_path: String, git_ref: String, include_untracked: Option<bool>, path_filter: Option<String>, include_patches: Option<bool>) -> Result<MaybeChunked<git::TreeDiff>, String> {
+  let path = project_path.clone();
+  let result = app.state::<GitState>().run_queued(&fs::decode_path(&project_path), "diff", OperationPriority::Interactive, git_operation_emitter(&app), move || {
+    git::diff_workdir_to_ref(&fs::decode_path(&path), &git_ref, include_untracked.unwrap_or(false), path_filter.as_deref(), include_patches.unwrap_or(true))
+  }).await?;
+  let diff = result.map_err(|e| format!("Failed to diff against ref: {}", e))?;
+  respond(&app, diff)
+}
+
+/// Files changed on this branch: the merge-base of HEAD and `base_ref` against the working
+/// tree, for the explorer's "changed in this branch" filter and the compare view
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_changed_paths_vs_ref(app: AppHandle, project_path: String, base_ref: String) -> Result<ChangedPaths, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to compare with '{}': {}", base_ref, e))?;
+  let handle = app.clone();
+  let base = base_ref.clone();
+  tauri::async_runtime::spawn_blocking(move || handle.state::<ChangedPathsCache>().get_or_compute(&root, &base))
+    .await
+    .map_err(|e| format!("Failed to compare with '{}': {}", base_ref, e))?
+    .map_err(|e| format!("Failed to compare with '{}': {}", base_ref, e))
+}
+
+/// A cheap view of a file for agent context: head or tail lines, a symbol outline, or its
+/// import/export header, with the hash and line count needed to follow up with `read_file_lines`
+#[tauri::command]
+async fn get_file_summary(app: AppHandle, path: String, mode: SummaryMode, lines: Option<usize>) -> Result<FileSummary, String> {
+  let handle = app.clone();
+  let target = fs::decode_path(&path);
+  tauri::async_runtime::spawn_blocking(move || handle.state::<SummaryCache>().summarize(&target, mode, lines))
+    .await
+    .map_err(|e| format!("Failed to summarize file: {}", e))?
+    .map_err(|e| format!("Failed to summarize file: {}", e))
+}
+
+/// Summaries of several files sharing `budget_bytes` of content, truncating where needed
+#[tauri::command]
+async fn get_files_summaries(app: AppHandle, paths: Vec<String>, mode: SummaryMode, lines: Option<usize>, budget_bytes: usize) -> Result<FilesSummaries, String> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || summaries::summarize_files(&handle.state::<SummaryCache>(), &paths, mode, lines, budget_bytes))
+    .await
+    .map_err(|e| format!("Failed to summarize files: {}", e))
+}
+
+/// Size, mime type and header-derived details (dimensions, font family, duration) of an
+/// image, font or media file, reading at most a few kilobytes of it
+#[tauri::command]
+async fn get_asset_metadata(path: String) -> Result<AssetMetadata, FsError> {
+  let target = fs::decode_path(&path);
+  tauri::async_runtime::spawn_blocking(move || asset_metadata::asset_metadata(&target))
+    .await
+    .map_err(|e| FsError::Io { message: e.to_string() })?
+}
+
+/// `get_asset_metadata` for a folder's worth of files; unreadable ones report an `error`
+#[tauri::command]
+async fn get_assets_metadata(paths: Vec<String>) -> Result<Vec<AssetMetadata>, String> {
+  tauri::async_runtime::spawn_blocking(move || asset_metadata::assets_metadata(&paths))
+    .await
+    .map_err(|e| format!("Failed to read asset metadata: {}", e))
+}
+
+/// Lines `start_line..=end_line` (1-based) of a text file
+#[tauri::command]
+async fn read_file_lines(path: String, start_line: usize, end_line: usize) -> Result<String, String> {
+  summaries::read_lines(&fs::decode_path(&path), start_line, end_line).map_err(|e| format!("Failed to read lines: {}", e))
+}
+
+/// One file's diff; files whose diff .gitattributes suppresses come back with a notice instead
+/// of a patch unless `force`
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_file_diff(project_path: String, file_path: String, staged: Option<bool>, force: Option<bool>) -> Result<git::FileDiff, String> {
+  git::get_file_diff(&fs::decode_path(&project_path), &file_path, staged.unwrap_or(false), force.unwrap_or(false))
+    .map_err(|e| format!("Failed to diff file: {}", e))
+}
+
+/// Staged and unstaged diffs of one file in one response, with hunks for per-hunk staging
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_file_diff_both(project_path: String, file_path: String, force: Option<bool>) -> Result<git::FileDiffBoth, String> {
+  tauri::async_runtime::spawn_blocking(move || git::get_file_diff_both(&fs::decode_path(&project_path), &file_path, force.unwrap_or(false)))
+    .await
+    .map_err(|e| format!("Failed to diff file: {}", e))?
+    .map_err(|e| format!("Failed to diff file: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_worktree_diff_stats(project_path: String) -> Result<git::WorktreeDiffStats, String> {
+  let path = fs::decode_path(&project_path);
+  tauri::async_runtime::spawn_blocking(move || git::worktree_diff_stats(&path))
+    .await
+    .map_err(|e| format!("Failed to compute diff stats: {}", e))?
+    .map_err(|e| format!("Failed to compute diff stats: {}", e))
+}
+
+/// `tag` narrows the results to files carrying that tag
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn list_tracked_files(app: AppHandle, git_state: State<'_, GitState>, project_path: String, pattern: Option<String>, limit: Option<usize>, tag: Option<String>) -> Result<MaybeChunked<Vec<git::TrackedFile>>, String> {
+  let tagged = tag.map(|tag| app.state::<TagStore>().tagged_paths(&fs::decode_path(&project_path), &tag));
+  let pinned = app.state::<PinStore>().pinned_paths(&fs::decode_path(&project_path));
+  let files = git::list_tracked_files(&git_state, &fs::decode_path(&project_path), pattern.as_deref(), tagged.as_ref(), &pinned, limit.unwrap_or(200))
+    .map_err(|e| format!("Failed to list tracked files: {}", e))?;
+  respond(&app, files)
+}
+
+/// Hand `value` back inline, or as a chunk handle when it is over the configured IPC threshold
+#[cfg(feature = "git")]
+fn respond<T: serde::Serialize>(app: &AppHandle, value: T) -> Result<MaybeChunked<T>, String> {
+  let threshold = app.state::<SettingsState>().get().ipc_chunk_threshold_bytes;
+  app.state::<ResponseStore>().respond(value, threshold)
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn read_response_chunk(responses: State<'_, ResponseStore>, handle: String, index: usize) -> Result<String, String> {
+  responses.read_chunk(&handle, index)
+}
+
+/// Deliver every chunk of `handle` as ordered "response-chunk" events instead of pulling them
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn stream_response(app: AppHandle, handle: String) -> Result<(), String> {
+  let chunks = app.state::<ResponseStore>().take(&handle)?;
+  let chunk_count = chunks.len();
+  for (index, data) in chunks.into_iter().enumerate() {
+    app.emit("response-chunk", ResponseChunkEvent { handle: handle.clone(), index, chunk_count, data })
+      .map_err(|e| format!("Failed to send response chunk: {}", e))?;
   }
+  Ok(())
 }
 
-// Git Credentials Commands
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn get_git_credentials(project_path: String) -> Result<Option<git::GitCredentials>, String> {
-  match git::get_git_credentials(Path::new(&project_path)) {
-    Ok(credentials) => Ok(credentials),
-    Err(e) => Err(format!("Failed to get git credentials: {}", e)),
+async fn release_response(responses: State<'_, ResponseStore>, handle: String) -> Result<(), String> {
+  responses.release(&handle);
+  Ok(())
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_commit_details(settings: State<'_, SettingsState>, project_path: String, commit_hash: String) -> Result<git::CommitDetails, String> {
+  match git::get_commit_details(&fs::decode_path(&project_path), &commit_hash, &settings.get().issue_trailer_patterns) {
+    Ok(details) => Ok(details),
+    Err(e) => Err(format!("Failed to get commit details: {}", e)),
   }
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn set_git_credentials(project_path: String, username: String, token: String, remote_url: String) -> Result<(), String> {
-  let credentials = git::GitCredentials {
-    username,
-    token,
-    remote_url,
-  };
+async fn add_git_note(project_path: String, commit_hash: String, note: String, namespace: Option<String>) -> Result<String, String> {
+  match git::add_git_note(&fs::decode_path(&project_path), &commit_hash, &note, namespace.as_deref()) {
+    Ok(note_id) => Ok(note_id),
+    Err(e) => Err(format!("Failed to add git note: {}", e)),
+  }
+}
 
-  match git::set_git_credentials(Path::new(&project_path), credentials) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to set git credentials: {}", e)),
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_git_note(project_path: String, commit_hash: String, namespace: Option<String>) -> Result<Option<String>, String> {
+  match git::get_git_note(&fs::decode_path(&project_path), &commit_hash, namespace.as_deref()) {
+    Ok(note) => Ok(note),
+    Err(e) => Err(format!("Failed to get git note: {}", e)),
   }
 }
 
+#[cfg(feature = "git")]
 #[tauri::command]
-async fn delete_git_credentials(project_path: String) -> Result<(), String> {
-  match git::delete_git_credentials(Path::new(&project_path)) {
-    Ok(_) => Ok(()),
+async fn list_git_notes(project_path: String, namespace: Option<String>) -> Result<Vec<git::GitNote>, String> {
+  match git::list_git_notes(&fs::decode_path(&project_path), namespace.as_deref()) {
+    Ok(notes) => Ok(notes),
+    Err(e) => Err(format!("Failed to list git notes: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn init_git_repo(project_path: String) -> Result<(), String> {
+  match git::init_git_repo(&fs::decode_path(&project_path)) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to initialize git repository: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn init_git_repo_enhanced(project_path: String) -> Result<git::GitInitResult, String> {
+  match git::init_git_repo_enhanced(&fs::decode_path(&project_path)) {
+    Ok(result) => Ok(result),
+    Err(e) => Err(format!("Failed to initialize git repository: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_git_config(project_path: String) -> Result<git::GitConfig, String> {
+  match git::get_git_config(&fs::decode_path(&project_path)) {
+    Ok(config) => Ok(config),
+    Err(e) => Err(format!("Failed to get git config: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn set_git_config(project_path: String, name: String, email: String, scope: Option<ConfigScope>) -> Result<git::GitConfig, GitError> {
+  git::set_git_config(&fs::decode_path(&project_path), &name, &email, scope.unwrap_or(ConfigScope::Local))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn is_git_repository(project_path: String) -> Result<bool, String> {
+  Ok(git::is_git_repository(&fs::decode_path(&project_path)))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn git_push(app: AppHandle, project_path: String, remote_name: Option<String>, branch_name: Option<String>, username: Option<String>, password: Option<String>, push_notes: Option<bool>, run_prepush_checks: Option<bool>, skip_checks: Option<bool>) -> Result<(), GitError> {
+  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
+  let branch = branch_name.unwrap_or_else(|| "main".to_string());
+
+  run_prepush_pipeline(&app, &project_path, &remote, &branch, run_prepush_checks, skip_checks.unwrap_or(false)).await?;
+
+  let handle = app.clone();
+  let (path, remote_arg, branch_arg) = (project_path.clone(), remote.clone(), branch.clone());
+  let result = app.state::<GitState>().run_queued(&fs::decode_path(&project_path), "push", OperationPriority::Background, git_operation_emitter(&app), move || {
+    let store = handle.state::<CredentialStore>();
+    GitManager::new(&fs::decode_path(&path)).push(&remote_arg, &branch_arg, username.as_deref(), password.as_deref(), &store, push_notes.unwrap_or(false))
+  }).await.map_err(|message| GitError::Failed { message })?;
+  match result {
+    Ok(_) => {
+      notifications::notify_if_unfocused(&app, "Push finished", &format!("Pushed {} to {}", branch, remote), NotificationKind::GitRemote);
+      Ok(())
+    }
+    Err(e) if e.downcast_ref::<git::NetworkUnavailable>().is_some() => {
+      let message = match app.state::<PendingPushStore>().queue(&fs::decode_path(&project_path), &remote, &branch, &e.to_string()) {
+        Ok(pending) => {
+          let _ = app.emit("push-queued", &pending);
+          format!("Failed to push: {}. The push will be retried when the remote is reachable.", e)
+        }
+        Err(queue_error) => format!("Failed to push: {} (could not queue a retry: {})", e, queue_error),
+      };
+      Err(GitError::Failed { message })
+    }
+    Err(e) => {
+      notifications::notify_if_unfocused(&app, "Push failed", &format!("Pushing {} to {} failed", branch, remote), NotificationKind::GitRemote);
+      Err(GitError::Failed { message: format!("Failed to push: {}", e) })
+    }
+  }
+}
+
+/// Run the project's pre-push checks, streaming their output as "prepush-output" events.
+/// Turning off checks that settings enforce counts as skipping them: it needs
+/// `allow_skip_prepush_checks` and is recorded in the audit log.
+#[cfg(feature = "git")]
+async fn run_prepush_pipeline(app: &AppHandle, project_path: &str, remote: &str, branch: &str, run_checks: Option<bool>, skip_checks: bool) -> Result<(), GitError> {
+  let settings = app.state::<SettingsState>().get();
+  let project_key = workspace::project_key(project_path);
+  let checks = prepush::checks_for(&settings.project_prepush_checks, &project_key);
+  if checks.is_empty() {
+    return Ok(());
+  }
+  let data_dir = Some(app.state::<StorageLayout>().data_dir().to_path_buf());
+  let skip = skip_checks || (settings.run_prepush_checks && run_checks == Some(false));
+  if skip {
+    if !settings.allow_skip_prepush_checks {
+      return Err(GitError::Failed { message: "Skipping pre-push checks is not allowed; enable it in settings first".to_string() });
+    }
+    if let Some(data_dir) = &data_dir {
+      allowed_commands::record_audit_event(data_dir, project_path, "prepush:skipped", format!("push of {} to {} without {} check(s)", branch, remote, checks.len()));
+    }
+    app.state::<PrePushResults>().record(prepush::skipped_run(project_path, remote, branch));
+    return Ok(());
+  }
+  if !run_checks.unwrap_or(settings.run_prepush_checks) {
+    return Ok(());
+  }
+
+  let cwd = workspace::canonical_root(&fs::decode_path(project_path)).map_err(|e| GitError::Failed { message: format!("Failed to run pre-push checks: {}", e) })?;
+  let allowlist = settings.command_allowlist_for(&project_key);
+  let timeout = std::time::Duration::from_secs(settings.command_timeout_secs.max(1));
+  let handle = app.clone();
+  let (project, remote, branch) = (project_path.to_string(), remote.to_string(), branch.to_string());
+  let run = tauri::async_runtime::spawn_blocking(move || {
+    let (store, credentials) = (handle.state::<EnvProfileStore>(), handle.state::<CredentialStore>());
+    let profiles: HashMap<String, Result<env_profiles::ProfileEnvironment, String>> = checks
+      .iter()
+      .filter_map(|check| check.env_profile.clone())
+      .map(|name| {
+        let environment = store.environment(&fs::decode_path(&project), &name, &credentials).map_err(|e| e.to_string());
+        (name, environment)
+      })
+      .collect();
+    let env = prepush::CheckEnvironment { cwd: &cwd, allowlist: &allowlist, allow_shell: settings.allow_shell_commands, timeout, data_dir: data_dir.as_deref(), profiles: &profiles };
+    prepush::run_checks(&checks, &project, &remote, &branch, &env, |step, stderr, line| {
+      let _ = handle.emit("prepush-output", PrePushOutputEvent { project_path: project.clone(), step, stderr, line: line.to_string() });
+    })
+  })
+  .await
+  .map_err(|e| GitError::Failed { message: format!("Pre-push task failed: {}", e) })?;
+
+  let failed = run.failed_step.and_then(|index| run.steps.get(index).cloned());
+  app.state::<PrePushResults>().record(run);
+  match failed {
+    Some(step) => Err(GitError::PrePushChecksFailed { step: Box::new(step) }),
+    None => Ok(()),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_last_prepush_run(results: State<'_, PrePushResults>, project_path: String) -> Result<Option<PrePushRun>, String> {
+  Ok(results.last(&project_path))
+}
+
+/// Recent commits, file changes and IDE operations in one newest-first feed. `summarize`
+/// collapses bursts of file changes into one entry per burst.
+#[tauri::command]
+async fn get_activity_feed(app: AppHandle, project_path: String, since_timestamp: Option<i64>, limit: Option<usize>, summarize: Option<bool>) -> Result<Vec<ActivityEntry>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to build activity feed: {}", e))?;
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  // Default to the last 24 hours
+  let since = since_timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp_millis() - 24 * 60 * 60 * 1000);
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let from = chrono::DateTime::from_timestamp_millis(since).unwrap_or_default();
+    let audit = allowed_commands::audit_entries_between(&data_dir, &project_path, from, chrono::Utc::now());
+    activity::activity_feed(&handle.state::<ChangeJournal>(), &root, audit, since, limit.unwrap_or(100), summarize.unwrap_or(false))
+  })
+  .await
+  .map_err(|e| format!("Failed to build activity feed: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_pending_pushes(pending: State<'_, PendingPushStore>, project_path: Option<String>) -> Result<Vec<PendingPush>, String> {
+  Ok(pending.list(project_path.as_deref().map(fs::decode_path).as_deref()))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn cancel_pending_push(pending: State<'_, PendingPushStore>, id: String) -> Result<bool, String> {
+  Ok(pending.remove(&id))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn scan_for_secrets(app: AppHandle, project_path: String, scope: ScanScope, paths: Option<Vec<String>>) -> Result<Vec<SecretFinding>, String> {
+  let scan = app.state::<SettingsState>().get().secret_scan_for(&workspace::project_key(&project_path));
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let root = project_path.clone();
+  let findings = tauri::async_runtime::spawn_blocking(move || {
+    secrets::scan_for_secrets(&fs::decode_path(&root), scope, &paths.unwrap_or_default(), &scan)
+  })
+  .await
+  .map_err(|e| format!("Secret scan task failed: {}", e))?
+  .map_err(|e| format!("Failed to scan for secrets: {}", e))?;
+
+  let event = match scope {
+    ScanScope::Staged => "secret-scan:staged",
+    ScanScope::Paths => "secret-scan:paths",
+    ScanScope::Workspace => "secret-scan:workspace",
+  };
+  allowed_commands::record_audit_event(&data_dir, &project_path, event, secrets::audit_summary(&findings));
+  Ok(findings)
+}
+
+// Plan Commands
+#[tauri::command]
+async fn create_plan(plans: State<'_, PlanStore>, project_path: String, steps: Vec<PlanStepInput>, session_id: Option<String>) -> Result<Plan, String> {
+  plans.create(&project_path, steps, session_id).map_err(|e| format!("Failed to create plan: {}", e))
+}
+
+#[tauri::command]
+async fn update_plan_step(app: AppHandle, plan_id: String, step_index: usize, status: PlanStepStatus, result_summary: Option<String>, operation_id: Option<String>, checkpoints: Option<Vec<String>>) -> Result<Plan, String> {
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let update = PlanStepUpdate { status, result_summary, operation_id, checkpoints: checkpoints.unwrap_or_default() };
+  let plan = app.state::<PlanStore>().update_step(&data_dir, &plan_id, step_index, update).map_err(|e| format!("Failed to update plan: {}", e))?;
+  let _ = app.emit("plan-progress", PlanProgressEvent { plan_id, step_index: Some(step_index), plan: plan.clone() });
+  Ok(plan)
+}
+
+#[tauri::command]
+async fn get_plan(plans: State<'_, PlanStore>, plan_id: String) -> Result<Option<Plan>, String> {
+  Ok(plans.get(&plan_id))
+}
+
+#[tauri::command]
+async fn list_plans(plans: State<'_, PlanStore>, project_path: String) -> Result<Vec<Plan>, String> {
+  Ok(plans.list(&project_path))
+}
+
+/// Cancel the plan's unfinished steps and any operations they registered
+#[tauri::command]
+async fn cancel_plan(app: AppHandle, plan_id: String) -> Result<Plan, String> {
+  let (plan, operations) = app.state::<PlanStore>().cancel(&plan_id).map_err(|e| format!("Failed to cancel plan: {}", e))?;
+  let registry = app.state::<OperationRegistry>();
+  for operation_id in operations {
+    registry.cancel(&operation_id);
+  }
+  let _ = app.emit("plan-progress", PlanProgressEvent { plan_id, step_index: None, plan: plan.clone() });
+  Ok(plan)
+}
+
+// How often the queue of pending pushes is checked for entries whose backoff has elapsed
+#[cfg(feature = "git")]
+const PENDING_PUSH_POLL: Duration = Duration::from_secs(20);
+
+/// Retry queued pushes whose backoff has elapsed, probing the remote first
+#[cfg(feature = "git")]
+async fn retry_pending_pushes(app: AppHandle) {
+  loop {
+    tokio::time::sleep(PENDING_PUSH_POLL).await;
+    for pending in app.state::<PendingPushStore>().due() {
+      // Leave pushes for repositories on a disconnected volume queued until it returns
+      let repository = Path::new(&pending.repository);
+      if app.state::<WorkspaceRegistry>().disconnected_root_for(repository).is_some() || !repository.exists() {
+        continue;
+      }
+      // Read-only mode refuses pushes from the UI; queued ones wait until it is turned off
+      if app.state::<ReadOnlyState>().is_read_only(repository) {
+        continue;
+      }
+      retry_pending_push(&app, pending).await;
+    }
+  }
+}
+
+#[cfg(feature = "git")]
+async fn retry_pending_push(app: &AppHandle, pending: PendingPush) {
+  let store = app.state::<PendingPushStore>();
+  let abandon = |error: String| {
+    store.remove(&pending.id);
+    let _ = app.emit("push-retried", PushRetryEvent { pending: pending.clone(), outcome: "abandoned".to_string(), error: Some(error) });
+  };
+
+  let checked = pending.clone();
+  let check = tauri::async_runtime::spawn_blocking(move || {
+    pending_push::validate_retry(&checked).map(|queued| (queued, pending_push::remote_reachable(&checked)))
+  }).await;
+  let queued = match check {
+    Ok(Ok((queued, true))) => queued,
+    Ok(Ok((_, false))) => {
+      store.record_failure(&pending.id, "Remote still unreachable");
+      return;
+    }
+    Ok(Err(e)) => return abandon(e.to_string()),
+    Err(e) => return abandon(format!("Retry check failed: {}", e)),
+  };
+  // The same checks an interactive push runs; a failure drops the entry so the user pushes again
+  let project_path = if pending.project_path.is_empty() { &pending.repository } else { &pending.project_path };
+  if let Err(e) = run_prepush_pipeline(app, project_path, &pending.remote, &pending.branch, None, false).await {
+    return abandon(e.to_string());
+  }
+
+  let handle = app.clone();
+  let job = pending.clone();
+  let result = app.state::<GitState>().run_queued(Path::new(&pending.repository), "push", OperationPriority::Background, git_operation_emitter(app), move || {
+    let credentials = handle.state::<CredentialStore>();
+    // Only what was queued is pushed; commits made since then wait for the user's next push
+    GitManager::new(Path::new(&job.repository)).push_commit(&job.remote, &job.branch, queued, &credentials)
+  }).await;
+  match result {
+    Ok(Ok(())) => {
+      store.remove(&pending.id);
+      let _ = app.emit("push-completed", &pending);
+      notifications::notify_if_unfocused(app, "Queued push finished", &format!("Pushed {} to {}", pending.branch, pending.remote), NotificationKind::GitRemote);
+    }
+    Ok(Err(e)) if e.downcast_ref::<git::NetworkUnavailable>().is_some() => {
+      store.record_failure(&pending.id, &e.to_string());
+      let _ = app.emit("push-retried", PushRetryEvent { pending: pending.clone(), outcome: "failed".to_string(), error: Some(e.to_string()) });
+    }
+    Ok(Err(e)) => abandon(e.to_string()),
+    Err(e) => {
+      store.record_failure(&pending.id, &e);
+      let _ = app.emit("push-retried", PushRetryEvent { pending: pending.clone(), outcome: "failed".to_string(), error: Some(e) });
+    }
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn git_pull(app: AppHandle, git_state: State<'_, GitState>, project_path: String, remote_name: Option<String>, branch_name: Option<String>, fetch_notes: Option<bool>) -> Result<(), String> {
+  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
+  let branch = branch_name.unwrap_or_else(|| "main".to_string());
+  
+  let path = project_path.clone();
+  let result = git_state.run_queued(&fs::decode_path(&project_path), "pull", OperationPriority::Background, git_operation_emitter(&app), move || {
+    GitManager::new(&fs::decode_path(&path)).pull(&remote, &branch, fetch_notes.unwrap_or(false))
+  }).await?;
+  match result {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to pull: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_repo_size_info(app: AppHandle, git_state: State<'_, GitState>, project_path: String) -> Result<git::RepoSizeInfo, String> {
+  let path = project_path.clone();
+  let result = git_state.run_queued(&fs::decode_path(&project_path), "statistics", OperationPriority::Background, git_operation_emitter(&app), move || {
+    git::get_repo_size_info(&fs::decode_path(&path))
+  }).await?;
+  match result {
+    Ok(info) => Ok(info),
+    Err(e) => Err(format!("Failed to get repository size: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn validate_branch_name(name: String) -> Result<git::BranchNameValidation, String> {
+  Ok(git::validate_branch_name(&name))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn suggest_branch_name(settings: State<'_, SettingsState>, project_path: String, description: String, prefix: Option<String>) -> Result<String, String> {
+  let convention = settings.get().branch_naming;
+  match git::suggest_branch_name(&fs::decode_path(&project_path), &description, prefix.as_deref(), &convention) {
+    Ok(name) => Ok(name),
+    Err(e) => Err(format!("Failed to suggest branch name: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn list_git_hooks(project_path: String) -> Result<GitHooksInfo, String> {
+  match hooks::list_git_hooks(&fs::decode_path(&project_path)) {
+    Ok(info) => Ok(info),
+    Err(e) => Err(format!("Failed to list git hooks: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn install_managed_hook(project_path: String, hook_name: String, script_content: String, force: Option<bool>) -> Result<HookInstallResult, String> {
+  match hooks::install_managed_hook(&fs::decode_path(&project_path), &hook_name, &script_content, force.unwrap_or(false)) {
+    Ok(result) => Ok(result),
+    Err(e) => Err(format!("Failed to install hook: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn uninstall_managed_hook(project_path: String, hook_name: String) -> Result<bool, String> {
+  match hooks::uninstall_managed_hook(&fs::decode_path(&project_path), &hook_name) {
+    Ok(removed) => Ok(removed),
+    Err(e) => Err(format!("Failed to uninstall hook: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn run_repo_maintenance(app: AppHandle, project_path: String) -> Result<git::MaintenanceResult, String> {
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    git::run_repo_maintenance(&fs::decode_path(&project_path), |line| {
+      let _ = app.emit("repo-maintenance-progress", git::MaintenanceProgress {
+        workspace_root: project_path.clone(),
+        line: line.to_string(),
+      });
+    })
+  })
+  .await
+  .map_err(|e| format!("Maintenance task failed: {}", e))?;
+
+  match result {
+    Ok(summary) => Ok(summary),
+    Err(e) => Err(format!("Failed to run repository maintenance: {}", e)),
+  }
+}
+
+/// Look for a damaged `.git`: HEAD, refs, index, packs and, with `run_fsck`, `git fsck`
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn check_repository_health(project_path: String, run_fsck: Option<bool>) -> Result<RepositoryHealth, String> {
+  tauri::async_runtime::spawn_blocking(move || repo_health::check_repository_health(&fs::decode_path(&project_path), run_fsck.unwrap_or(false)))
+    .await
+    .map_err(|e| format!("Health check task failed: {}", e))
+}
+
+/// Apply one of the safe automated fixes; both change files under `.git`, so `confirm` is required
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn repair_repository(app: AppHandle, project_path: String, action: RepairAction, confirm: Option<bool>) -> Result<RepairResult, String> {
+  if !confirm.unwrap_or(false) {
+    return Err("Repairing a repository changes files under .git; confirm to proceed".to_string());
+  }
+  let path = project_path.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || repo_health::repair(&fs::decode_path(&path), action))
+    .await
+    .map_err(|e| format!("Repair task failed: {}", e))?;
+
+  let outcome = match &result {
+    Ok(repair) => repair.details.clone(),
+    Err(e) => format!("failed: {}", e),
+  };
+  let event = match action {
+    RepairAction::RebuildHead => "repair:rebuild_head",
+    RepairAction::RemoveStaleIndexLock => "repair:remove_stale_index_lock",
+  };
+  allowed_commands::record_audit_event(app.state::<StorageLayout>().data_dir(), &project_path, event, outcome);
+  result.map_err(|e| format!("Failed to repair repository: {}", e))
+}
+
+/// Clone `url` into `target_path`; a `filter` (e.g. "blob:none") makes a partial clone and
+/// `depth` a shallow one. Cancel through `cancel_operation` with "clone:<target_path>".
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn git_clone(app: AppHandle, url: String, target_path: String, username: Option<String>, password: Option<String>, filter: Option<String>, depth: Option<u32>) -> Result<git::CloneInfo, String> {
+  let operation_id = format!("clone:{}", target_path);
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+  let options = git::CloneOptions { filter, depth };
+
+  let handle = app.clone();
+  let target = fs::decode_path(&target_path);
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    let existed = target.exists();
+    let journaled = if existed {
+      None
+    } else {
+      Some(shutdown::create_tracked_directory(&target).map_err(|e| anyhow::anyhow!("Failed to create '{}': {}", fs::display_path(&target), e))?)
+    };
+    let cloned = git::clone_repository(&url, &target, username, password, &options, |progress| {
+      let _ = handle.emit("clone-progress", git::GitCloneProgress {
+        target_path: target_path.clone(),
+        progress,
+      });
+      !cancel.is_cancelled()
+    });
+    if cloned.is_err() && !existed {
+      let _ = std::fs::remove_dir_all(&target);
+    }
+    drop(journaled);
+    cloned.and_then(|_| git::get_clone_info(&target))
+  })
+  .await
+  .map_err(|e| format!("Clone task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  match result {
+    Ok(info) => {
+      notifications::notify_if_unfocused(&app, "Clone finished", "The repository is ready", NotificationKind::GitRemote);
+      Ok(info)
+    }
+    Err(e) => {
+      notifications::notify_if_unfocused(&app, "Clone failed", "Cloning the repository failed", NotificationKind::GitRemote);
+      Err(format!("Failed to clone repository: {}", e))
+    }
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_clone_info(project_path: String) -> Result<git::CloneInfo, String> {
+  git::get_clone_info(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read clone info: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_remote_host_info(settings: State<'_, SettingsState>, project_path: String, remote: Option<String>) -> Result<RemoteHostInfo, String> {
+  forge::get_remote_host_info(&fs::decode_path(&project_path), remote.as_deref(), &settings.get().forge_hosts)
+    .map_err(|e| format!("Failed to read remote: {}", e))
+}
+
+/// Permalink to `file_path` on the forge, pinned to the commit `git_ref` resolves to (HEAD by default)
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn build_remote_file_url(settings: State<'_, SettingsState>, project_path: String, file_path: String, line_range: Option<(u32, u32)>, git_ref: Option<String>) -> Result<String, String> {
+  forge::build_remote_file_url(&fs::decode_path(&project_path), &fs::decode_path(&file_path), line_range, git_ref.as_deref(), &settings.get().forge_hosts)
+    .map_err(|e| format!("Failed to build remote URL: {}", e))
+}
+
+/// Who last changed lines `start_line..=end_line` (1-based) and everything a hover needs about
+/// those commits: details, whether they are pushed, forge links and issue references
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_line_provenance(app: AppHandle, project_path: String, file_path: String, start_line: usize, end_line: usize) -> Result<LineProvenance, String> {
+  let settings = app.state::<SettingsState>().get();
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    handle.state::<ProvenanceCache>().line_provenance(
+      &fs::decode_path(&project_path),
+      &fs::decode_path(&file_path),
+      start_line,
+      end_line,
+      &settings.issue_trailer_patterns,
+      &settings.forge_hosts,
+    )
+  })
+  .await
+  .map_err(|e| format!("Failed to read line provenance: {}", e))?
+  .map_err(|e| format!("Failed to read line provenance: {}", e))
+}
+
+/// One document describing staged, working-tree or branch changes for review, written to
+/// `output_path` (plus a sibling `.md` when markdown is asked for) or returned, chunked when large
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn export_review_bundle(app: AppHandle, project_path: String, scope: ReviewScope, base_ref: Option<String>, output_path: Option<String>, options: Option<ReviewBundleOptions>) -> Result<ReviewBundleOutput, String> {
+  let options = options.unwrap_or_default();
+  let path = project_path.clone();
+  let result = app.state::<GitState>().run_queued(&fs::decode_path(&project_path), "review bundle", OperationPriority::Interactive, git_operation_emitter(&app), move || {
+    review_bundle::build_review_bundle(&fs::decode_path(&path), scope, base_ref.as_deref(), &options)
+  }).await?;
+  let bundle = result.map_err(|e| format!("Failed to export review bundle: {}", e))?;
+
+  let Some(output_path) = output_path else {
+    return Ok(ReviewBundleOutput::Inline { bundle: respond(&app, bundle)? });
+  };
+  let output = fs::decode_path(&output_path);
+  let json = serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to export review bundle: {}", e))?;
+  fs::write_atomic(&output, json.as_bytes()).map_err(|e| format!("Failed to write review bundle: {}", e))?;
+  let markdown_path = match &bundle.markdown {
+    Some(markdown) => {
+      let target = output.with_extension("md");
+      fs::write_atomic(&target, markdown.as_bytes()).map_err(|e| format!("Failed to write review bundle: {}", e))?;
+      Some(fs::display_path(&target))
+    }
+    None => None,
+  };
+  Ok(ReviewBundleOutput::Written {
+    path: fs::display_path(&output),
+    markdown_path,
+    manifest_hash: bundle.manifest_hash,
+    files: bundle.files.len(),
+    truncated: bundle.truncated,
+  })
+}
+
+/// The manifest hash a bundle exported now would carry; a mismatch with an earlier bundle's
+/// means the workspace changed since
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_review_manifest_hash(project_path: String, scope: ReviewScope, base_ref: Option<String>) -> Result<String, String> {
+  tauri::async_runtime::spawn_blocking(move || review_bundle::review_manifest_hash(&fs::decode_path(&project_path), scope, base_ref.as_deref()))
+    .await
+    .map_err(|e| format!("Failed to hash workspace: {}", e))?
+    .map_err(|e| format!("Failed to hash workspace: {}", e))
+}
+
+/// Fetch the history and blobs a partial or shallow clone skipped
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn backfill_clone(app: AppHandle, project_path: String) -> Result<git::CloneInfo, String> {
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    git::backfill_clone(&fs::decode_path(&project_path), |line| {
+      let _ = app.emit("repo-maintenance-progress", git::MaintenanceProgress {
+        workspace_root: project_path.clone(),
+        line: line.to_string(),
+      });
+    })
+  })
+  .await
+  .map_err(|e| format!("Backfill task failed: {}", e))?;
+  result.map_err(|e| format!("Failed to backfill clone: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn create_project_from_template(app: AppHandle, template_url: String, target_path: String, project_name: String, options: Option<TemplateOptions>, operation_id: Option<String>) -> Result<TemplateResult, String> {
+  let operation_id = operation_id.unwrap_or_else(|| format!("template:{}", target_path));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+
+  let handle = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    template::create_project_from_template(
+      &template_url,
+      &fs::decode_path(&target_path),
+      &project_name,
+      options.unwrap_or_default(),
+      cancel,
+      |progress| {
+        let _ = handle.emit("template-progress", progress);
+      },
+    )
+  })
+  .await
+  .map_err(|e| format!("Template task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  match result {
+    Ok(created) => {
+      notifications::notify_if_unfocused(&app, "Clone finished", &format!("{} is ready", created.project_path), NotificationKind::GitRemote);
+      Ok(created)
+    }
+    Err(e) => {
+      notifications::notify_if_unfocused(&app, "Clone failed", "Creating the project from the template failed", NotificationKind::GitRemote);
+      Err(format!("Failed to create project from template: {}", e))
+    }
+  }
+}
+
+// Quick Action Commands
+/// `profile` names an environment profile of the project to merge over the inherited
+/// environment; its secret values are masked in the output and the audit log.
+#[cfg(feature = "process")]
+#[tauri::command]
+async fn run_allowed_command(app: AppHandle, project_path: String, command_line: String, allow_shell: Option<bool>, profile: Option<String>) -> Result<CommandOutput, CommandError> {
+  let settings = app.state::<SettingsState>().get();
+  let allow_shell = allow_shell.unwrap_or(false) && settings.allow_shell_commands;
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let cwd = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| CommandError::InvalidCommand { message: e.to_string() })?;
+  let environment = match &profile {
+    Some(name) => app
+      .state::<EnvProfileStore>()
+      .environment(&fs::decode_path(&project_path), name, &app.state::<CredentialStore>())
+      .map_err(|e| CommandError::InvalidCommand { message: e.to_string() })?,
+    None => env_profiles::ProfileEnvironment::default(),
+  };
+  let environment = Arc::new(environment);
+
+  let allowlist = settings.command_allowlist_for(&workspace::project_key(&project_path));
+  let timeout = std::time::Duration::from_secs(settings.command_timeout_secs.max(1));
+  let line = command_line.clone();
+  let applied = environment.clone();
+  let mut result = tauri::async_runtime::spawn_blocking(move || {
+    let mut command = allowed_commands::prepare(&line, &allowlist, allow_shell)?;
+    applied.apply(&mut command);
+    allowed_commands::run(command, &cwd, timeout)
+  })
+  .await
+  .map_err(|e| CommandError::Failed { message: format!("Command task failed: {}", e) })?;
+
+  if let Ok(output) = &mut result {
+    environment.redact_output(output);
+  }
+  allowed_commands::record_audit(&data_dir, &project_path, &environment.redact(&command_line), allow_shell, &result);
+  result
+}
+
+// Notification Commands
+#[tauri::command]
+async fn notify_user(app: AppHandle, title: String, body: String, kind: Option<NotificationKind>) -> Result<bool, String> {
+  Ok(notifications::notify_user(&app, &title, &body, kind.unwrap_or(NotificationKind::General)))
+}
+
+/// Work a close would interrupt, so the frontend can warn first ("a push is in progress")
+#[tauri::command]
+async fn get_shutdown_blockers(app: AppHandle) -> Result<Vec<ShutdownBlocker>, String> {
+  Ok(app.state::<ShutdownCoordinator>().blockers(&app))
+}
+
+/// What startup cleaned up after the previous session exited uncleanly
+#[tauri::command]
+async fn get_previous_session_leftovers(coordinator: State<'_, ShutdownCoordinator>) -> Result<LeftoverReport, String> {
+  Ok(coordinator.leftovers())
+}
+
+#[tauri::command]
+async fn cancel_operation(operations: State<'_, OperationRegistry>, operation_id: String) -> Result<bool, String> {
+  Ok(operations.cancel(&operation_id))
+}
+
+// Store credentials securely in OS keychain
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn save_git_credentials_cmd(store: State<'_, CredentialStore>, project_path: String, remote_name: Option<String>, username: String, password: String) -> Result<(), String> {
+  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
+  match crate::git::save_git_credentials(&store, &fs::decode_path(&project_path), &remote, &username, &password) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to save credentials: {}", e)),
+  }
+}
+
+// Clear stored credentials
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn clear_git_credentials_cmd(store: State<'_, CredentialStore>, project_path: String, remote_name: Option<String>, username: String) -> Result<(), String> {
+  let remote = remote_name.unwrap_or_else(|| "origin".to_string());
+  match crate::git::clear_git_credentials(&store, &fs::decode_path(&project_path), &remote, &username) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to clear credentials: {}", e)),
+  }
+}
+
+// File System Commands
+#[tauri::command]
+async fn list_directory(path: String) -> Result<DirectoryListing, FsError> {
+  fs::list_directory(&fs::decode_path(&path))
+}
+
+/// Batch existence and type check for recents, pins and restored tabs. Per-path failures
+/// come back as error codes in that path's probe.
+#[tauri::command]
+async fn probe_paths(app: AppHandle, paths: Vec<String>) -> Result<Vec<fs::PathProbe>, FsError> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let workspaces = handle.state::<WorkspaceRegistry>();
+    fs::probe_paths(&paths, &|path| workspaces.root_for(path).is_some())
+  })
+  .await
+  .map_err(|e| FsError::Io { message: format!("Failed to probe paths: {}", e) })
+}
+
+#[tauri::command]
+async fn create_file(file_path: String, content: String) -> Result<(), FsError> {
+  fs::create_file(&fs::decode_path(&file_path), &content)
+}
+
+#[tauri::command]
+async fn create_directory(dir_path: String) -> Result<(), FsError> {
+  fs::create_directory(&fs::decode_path(&dir_path))
+}
+
+/// Update the index after a move; failures are reported, never propagated, since the move already happened
+#[cfg(feature = "git")]
+async fn stage_moved_paths(git_state: &GitState, tracked: &git::TrackedPaths, to: &Path) -> git::IndexMoveResult {
+  let (moved, to) = (tracked.clone(), to.to_path_buf());
+  match git_state.with_index_lock(&tracked.workdir, move || git::stage_move(&moved, &to)).await {
+    Ok(result) => result,
+    Err(e) => git::IndexMoveResult::failed(tracked, &e),
+  }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+struct PathMoveResult {
+  #[cfg(feature = "git")]
+  index: Option<git::IndexMoveResult>,
+  /// Present when `update_references` was requested
+  references: Option<references::ReferenceUpdateReport>,
+}
+
+/// Point managed state at a directory's new location after it was renamed or moved: watchers,
+/// indexes, open files, pins, tags and remembered dialog directories, then tell the frontend
+fn remap_moved_directory(app: &AppHandle, from: &Path, to: &Path) {
+  if let Err(e) = app.state::<WorkspaceRegistry>().remap_watched(from, to) {
+    log::warn!("Failed to move watches from {} to {}: {}", from.display(), to.display(), e);
+  }
+  app.state::<SymbolIndex>().remap(from, to);
+  app.state::<WordIndex>().remap(from, to);
+  app.state::<PinStore>().remap(from, to);
+  app.state::<TagStore>().remap(from, to);
+  app.state::<DialogDirectoryStore>().remap(from, to);
+  app.state::<FileCacheState>().0.lock().unwrap().invalidate(to);
+  #[cfg(feature = "git")]
+  if let Some(root) = app.state::<WorkspaceRegistry>().root_for(to) {
+    app.state::<ChangedPathsCache>().invalidate(&root);
+  }
+  let root = app
+    .state::<WorkspaceRegistry>()
+    .root_for(to)
+    .unwrap_or_else(|| to.parent().unwrap_or(to).to_path_buf());
+  let open_files = app
+    .state::<OpenFileRegistry>()
+    .remap(from, to)
+    .into_iter()
+    .map(|(old, new)| (WsPath::new(&root, &old), WsPath::new(&root, &new)))
+    .collect();
+  let _ = app.emit("paths-remapped", PathsRemapped { from: WsPath::new(&root, from), to: WsPath::new(&root, to), open_files });
+}
+
+/// Move `from` to `to` with `do_move`, then rewrite the relative imports that pointed at it.
+/// If the imports cannot all be written, the edits are reverted and the file is moved back.
+async fn move_with_references(
+  app: &AppHandle,
+  from: &Path,
+  to: &Path,
+  project_path: Option<&str>,
+  update_references: bool,
+  do_move: fn(&Path, &Path) -> Result<(), FsError>,
+) -> Result<Option<references::ReferenceUpdateReport>, FsError> {
+  if !update_references {
+    do_move(from, to)?;
+    return Ok(None);
+  }
+  if !from.is_file() {
+    return Err(FsError::Io { message: "Updating references is only supported when moving a single file".to_string() });
+  }
+  let project = project_path.ok_or_else(|| FsError::Io { message: "A project path is required to update references".to_string() })?;
+  let project = fs::decode_path(project);
+  let old_target = std::fs::canonicalize(from)?;
+  let new_target = match (to.parent().map(std::fs::canonicalize), to.file_name()) {
+    (Some(Ok(parent)), Some(name)) => parent.join(name),
+    _ => return Err(FsError::NotFound),
+  };
+  let (edits, report) = {
+    let (project, old_target, new_target) = (project.clone(), old_target.clone(), new_target.clone());
+    tauri::async_runtime::spawn_blocking(move || -> Result<_, String> {
+      let found = references::find_file_references(&project, &old_target).map_err(|e| e.to_string())?;
+      Ok(references::plan_updates(&found, &old_target, &new_target))
+    })
+    .await
+    .map_err(|e| FsError::Io { message: e.to_string() })?
+    .map_err(|message| FsError::Io { message })?
+  };
+
+  do_move(from, to)?;
+  let settings = app.state::<SettingsState>().get();
+  let history = app.state::<LocalHistoryStore>();
+  for edit in &edits {
+    if let Err(e) = history.record(&edit.path, "update_references", &settings.local_history) {
+      log::warn!("Failed to keep local history for {}: {}", edit.path.display(), e);
+    }
+  }
+  if let Err(e) = references::apply_edits(&edits) {
+    if let Err(undo) = do_move(to, from) {
+      log::error!("Failed to move '{}' back after a failed reference update: {}", to.display(), undo);
+    }
+    return Err(FsError::Io { message: e.to_string() });
+  }
+  let cache = app.state::<FileCacheState>();
+  for edit in &edits {
+    cache.0.lock().unwrap().invalidate(&edit.path);
+  }
+  Ok(Some(report))
+}
+
+#[tauri::command]
+async fn rename_path(app: AppHandle, cache: State<'_, FileCacheState>, from: String, to: String, git_aware: Option<bool>, project_path: Option<String>, update_references: Option<bool>) -> Result<PathMoveResult, FsError> {
+  let (from, to) = (fs::decode_path(&from), fs::decode_path(&to));
+  cache.0.lock().unwrap().invalidate(&from);
+  #[cfg(feature = "git")]
+  let tracked = if git_aware.unwrap_or(true) { git::tracked_under(&from) } else { None };
+  #[cfg(not(feature = "git"))]
+  let _ = git_aware;
+  let moved_dir = from.is_dir().then(|| std::fs::canonicalize(&from).unwrap_or_else(|_| from.clone()));
+  let moved_file = (!from.is_dir()).then(|| std::fs::canonicalize(&from).unwrap_or_else(|_| from.clone()));
+  let references = move_with_references(&app, &from, &to, project_path.as_deref(), update_references.unwrap_or(false), fs::rename_path).await?;
+  if let Some(old) = moved_dir {
+    remap_moved_directory(&app, &old, &std::fs::canonicalize(&to).unwrap_or_else(|_| to.clone()));
+  }
+  if let Some(old) = moved_file {
+    app.state::<TagStore>().remap(&old, &std::fs::canonicalize(&to).unwrap_or_else(|_| to.clone()));
+  }
+  #[cfg(feature = "git")]
+  let index = match tracked {
+    Some(tracked) => Some(stage_moved_paths(&app.state::<GitState>(), &tracked, &to).await),
+    None => None,
+  };
+  Ok(PathMoveResult {
+    #[cfg(feature = "git")]
+    index,
+    references,
+  })
+}
+
+/// Files in the project that import `file_path` (JS/TS, Rust and Python heuristics)
+#[tauri::command]
+async fn find_file_references(project_path: String, file_path: String) -> Result<Vec<references::FileReference>, String> {
+  let (project, file) = (fs::decode_path(&project_path), fs::decode_path(&file_path));
+  tauri::async_runtime::spawn_blocking(move || references::find_file_references(&project, &file))
+    .await
+    .map_err(|e| format!("Failed to find references: {}", e))?
+    .map_err(|e| format!("Failed to find references: {}", e))
+}
+
+/// Rename every file under `root` matching `match_pattern`. Dry runs return the mapping with
+/// conflicts; applying refuses while any conflict remains.
+#[tauri::command]
+async fn bulk_rename(app: AppHandle, root: String, match_pattern: String, rename_template: String, pattern_kind: Option<PatternKind>, dry_run: bool, git_aware: Option<bool>) -> Result<BulkRenamePlan, String> {
+  let root_path = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to plan rename: {}", e))?;
+  let mut plan = bulk_rename::plan(&root_path, &match_pattern, &rename_template, pattern_kind.unwrap_or_default())
+    .map_err(|e| format!("Failed to plan rename: {}", e))?;
+  if dry_run {
+    return Ok(plan);
+  }
+  if plan.conflicts > 0 {
+    return Err(format!("{} conflicting renames must be resolved before applying", plan.conflicts));
+  }
+
+  let cache = app.state::<FileCacheState>();
+  #[cfg(feature = "git")]
+  let git_state = app.state::<GitState>();
+  #[cfg(not(feature = "git"))]
+  let _ = git_aware;
+  let mut changed = Vec::new();
+  for item in plan.items.iter_mut() {
+    if item.error.is_some() {
+      item.status = Some("failed".to_string());
+      continue;
+    }
+    let (from, to) = (fs::decode_path(&item.from), fs::decode_path(&item.to));
+    let (from, to) = (from.as_path(), to.as_path());
+    cache.0.lock().unwrap().invalidate(from);
+    #[cfg(feature = "git")]
+    let tracked = if git_aware.unwrap_or(true) { git::tracked_under(from) } else { None };
+    let renamed = match to.parent() {
+      Some(parent) => std::fs::create_dir_all(parent).map_err(FsError::from).and_then(|_| fs::rename_path(from, to)),
+      None => fs::rename_path(from, to),
+    };
+    match renamed {
+      Ok(()) => {
+        item.status = Some("renamed".to_string());
+        #[cfg(feature = "git")]
+        if let Some(tracked) = tracked {
+          item.index = Some(stage_moved_paths(&git_state, &tracked, to).await);
+        }
+        changed.push(item.from.clone());
+        changed.push(item.to.clone());
+      }
+      Err(e) => {
+        item.status = Some("failed".to_string());
+        item.error = Some(e.to_string());
+      }
+    }
+  }
+  plan.applied = true;
+
+  let _ = app.emit("fs-change", FsChangeEvent::new(Path::new(&plan.root), "BulkRename", &changed));
+  Ok(plan)
+}
+
+/// Move every child of `dir` up one level and remove the emptied directory
+#[tauri::command]
+async fn flatten_directory(app: AppHandle, dir: String, conflict_strategy: ConflictStrategy, dry_run: Option<bool>, git_aware: Option<bool>) -> Result<RestructurePlan, String> {
+  let dir_path = workspace::canonical_root(&fs::decode_path(&dir)).map_err(|e| format!("Failed to plan flatten: {}", e))?;
+  let plan = restructure::plan_flatten(&dir_path, conflict_strategy).map_err(|e| format!("Failed to plan flatten: {}", e))?;
+  if dry_run.unwrap_or(false) {
+    return Ok(plan);
+  }
+  let mut plan = apply_restructure(&app, plan, git_aware.unwrap_or(true), "Flatten").await;
+  if plan.applied && std::fs::remove_dir(&dir_path).is_ok() {
+    plan.removed_directory = true;
+  }
+  Ok(plan)
+}
+
+/// Group the children of `dir` into subfolders by the first matching rule
+#[tauri::command]
+async fn partition_directory(app: AppHandle, dir: String, rules: Vec<PartitionRule>, conflict_strategy: Option<ConflictStrategy>, dry_run: bool, git_aware: Option<bool>) -> Result<RestructurePlan, String> {
+  let dir_path = workspace::canonical_root(&fs::decode_path(&dir)).map_err(|e| format!("Failed to plan partition: {}", e))?;
+  let plan = restructure::plan_partition(&dir_path, &rules, conflict_strategy.unwrap_or_default())
+    .map_err(|e| format!("Failed to plan partition: {}", e))?;
+  if dry_run {
+    return Ok(plan);
+  }
+  Ok(apply_restructure(&app, plan, git_aware.unwrap_or(true), "Partition").await)
+}
+
+/// Apply a flatten or partition plan as one changeset, then stage the moves in git and emit a
+/// single fs-change event for everything that moved
+async fn apply_restructure(app: &AppHandle, mut plan: RestructurePlan, git_aware: bool, kind: &str) -> RestructurePlan {
+  let cache = app.state::<FileCacheState>();
+  #[cfg(feature = "git")]
+  let tracked: Vec<Option<git::TrackedPaths>> = plan
+    .moves
+    .iter()
+    .map(|item| if git_aware && !item.skipped() { git::tracked_under(Path::new(&item.from)) } else { None })
+    .collect();
+  for item in plan.moves.iter().filter(|m| !m.skipped()) {
+    cache.0.lock().unwrap().invalidate(Path::new(&item.from));
+    cache.0.lock().unwrap().invalidate(Path::new(&item.to));
+  }
+
+  restructure::apply(&mut plan);
+  if !plan.applied {
+    return plan;
+  }
+
+  #[cfg(feature = "git")]
+  {
+    let git_state = app.state::<GitState>();
+    for (item, tracked) in plan.moves.iter_mut().zip(tracked) {
+      if let Some(tracked) = tracked.filter(|_| !item.skipped()) {
+        item.index = Some(stage_moved_paths(&git_state, &tracked, Path::new(&item.to)).await);
+      }
+    }
+  }
+  #[cfg(not(feature = "git"))]
+  let _ = git_aware;
+  let changed: Vec<String> = plan
+    .moves
+    .iter()
+    .filter(|item| !item.skipped())
+    .flat_map(|item| [item.from.clone(), item.to.clone()])
+    .collect();
+  let workspace_root = app
+    .state::<WorkspaceRegistry>()
+    .root_for(Path::new(&plan.directory))
+    .unwrap_or_else(|| PathBuf::from(&plan.directory));
+  let _ = app.emit("fs-change", FsChangeEvent::new(&workspace_root, kind, &changed));
+  plan
+}
+
+#[tauri::command]
+async fn delete_path(cache: State<'_, FileCacheState>, path: String, expected_file_count: Option<u64>) -> Result<(), FsError> {
+  cache.0.lock().unwrap().invalidate(&fs::decode_path(&path));
+  fs::delete_path_expecting(&fs::decode_path(&path), expected_file_count)
+}
+
+#[tauri::command]
+async fn preview_delete(workspaces: State<'_, WorkspaceRegistry>, open_files: State<'_, OpenFileRegistry>, path: String) -> Result<DeletePreview, FsError> {
+  let target = &fs::decode_path(&path);
+  let mut preview = fs::preview_delete(target)?;
+  #[cfg(feature = "git")]
+  {
+    preview.modified_tracked = git::modified_tracked_files(target).iter().map(|p| fs::display_path(p)).collect();
+  }
+  preview.open_files = open_files.open_under(target).iter().map(|p| fs::display_path(p)).collect();
+  preview.watched = std::fs::canonicalize(target).map(|p| workspaces.root_for(&p).is_some()).unwrap_or(false);
+  Ok(preview)
+}
+
+#[tauri::command]
+async fn mark_file_open(window: Window, open_files: State<'_, OpenFileRegistry>, path: String) -> Result<(), String> {
+  open_files.mark_open(&fs::decode_path(&path), window.label());
+  Ok(())
+}
+
+#[tauri::command]
+async fn mark_file_closed(window: Window, open_files: State<'_, OpenFileRegistry>, path: String) -> Result<(), String> {
+  open_files.mark_closed(&fs::decode_path(&path), window.label());
+  Ok(())
+}
+
+#[tauri::command]
+async fn move_path(app: AppHandle, cache: State<'_, FileCacheState>, from: String, to: String, git_aware: Option<bool>, project_path: Option<String>, update_references: Option<bool>) -> Result<PathMoveResult, FsError> {
+  let (from, to) = (fs::decode_path(&from), fs::decode_path(&to));
+  cache.0.lock().unwrap().invalidate(&from);
+  #[cfg(feature = "git")]
+  let tracked = if git_aware.unwrap_or(true) { git::tracked_under(&from) } else { None };
+  #[cfg(not(feature = "git"))]
+  let _ = git_aware;
+  let moved_dir = from.is_dir().then(|| std::fs::canonicalize(&from).unwrap_or_else(|_| from.clone()));
+  let moved_file = (!from.is_dir()).then(|| std::fs::canonicalize(&from).unwrap_or_else(|_| from.clone()));
+  let references = move_with_references(&app, &from, &to, project_path.as_deref(), update_references.unwrap_or(false), fs::move_path).await?;
+  if let Some(old) = moved_dir {
+    remap_moved_directory(&app, &old, &std::fs::canonicalize(&to).unwrap_or_else(|_| to.clone()));
+  }
+  if let Some(old) = moved_file {
+    app.state::<TagStore>().remap(&old, &std::fs::canonicalize(&to).unwrap_or_else(|_| to.clone()));
+  }
+  #[cfg(feature = "git")]
+  let index = match tracked {
+    Some(tracked) => Some(stage_moved_paths(&app.state::<GitState>(), &tracked, &to).await),
+    None => None,
+  };
+  Ok(PathMoveResult {
+    #[cfg(feature = "git")]
+    index,
+    references,
+  })
+}
+
+#[tauri::command]
+async fn read_file_content(settings: State<'_, SettingsState>, cache: State<'_, FileCacheState>, file_path: String) -> Result<String, FsError> {
+  let path = &fs::decode_path(&file_path);
+  let use_cache = settings.get().file_cache_enabled;
+
+  if use_cache {
+    if let Some(content) = cache.0.lock().unwrap().get(path) {
+      return Ok(content);
+    }
+  }
+
+  // Stat before reading so a write in between leaves the entry stale instead of masking it
+  let metadata = std::fs::metadata(path).ok();
+  match fs::read_file_content(path) {
+    Ok(content) => {
+      if use_cache {
+        cache.0.lock().unwrap().insert(path, &content, metadata.as_ref());
+      }
+      Ok(content)
+    }
+    Err(e) => Err(e),
+  }
+}
+
+#[tauri::command]
+async fn write_file_content(app: AppHandle, settings: State<'_, SettingsState>, cache: State<'_, FileCacheState>, history: State<'_, LocalHistoryStore>, file_path: String, content: String) -> Result<(), FsError> {
+  let path = &fs::decode_path(&file_path);
+  if let Err(e) = history.record(path, "write_file_content", &settings.get().local_history) {
+    log::warn!("Failed to keep local history for {}: {}", file_path, e);
+  }
+  match fs::write_file_content(path, &content) {
+    Ok(_) => {
+      if settings.get().file_cache_enabled {
+        cache.0.lock().unwrap().insert(path, &content, std::fs::metadata(path).ok().as_ref());
+      }
+      app.state::<WorkspaceRegistry>().discard_draft(path);
+      Ok(())
+    }
+    Err(e) => Err(e),
+  }
+}
+
+/// Run the project's save hooks over `content`, then write the result atomically unless a
+/// blocking hook failed or the file changed on disk since `expected_modified`
+#[tauri::command]
+async fn save_with_hooks(app: AppHandle, file_path: String, content: String, expected_modified: Option<i64>) -> Result<SaveWithHooksResult, FsError> {
+  let path = fs::decode_path(&file_path);
+  let settings = app.state::<SettingsState>().get();
+  let project = app.state::<WorkspaceRegistry>().root_for(&path).or_else(|| path.parent().map(Path::to_path_buf)).ok_or(FsError::NotFound)?;
+  let project_key = workspace::project_key(&fs::display_path(&project));
+  let hooks = save_hooks::hooks_for(&settings.project_save_hooks, &project_key);
+  let allowlist = settings.command_allowlist_for(&project_key);
+  let timeout = std::time::Duration::from_secs(settings.command_timeout_secs.max(1));
+
+  let pipeline_path = path.clone();
+  let original = content.clone();
+  let (formatted, steps, blocked_by) = tauri::async_runtime::spawn_blocking(move || {
+    save_hooks::run_pipeline(&hooks, &project, &pipeline_path, &original, &allowlist, timeout)
+  })
+  .await
+  .map_err(|e| FsError::Io { message: format!("Save hook task failed: {}", e) })?;
+
+  let content_modified = formatted != content;
+  if blocked_by.is_some() {
+    return Ok(SaveWithHooksResult { steps, content_modified, content: formatted, written: false, blocked_by });
+  }
+
+  if let Err(e) = app.state::<LocalHistoryStore>().record(&path, "save_with_hooks", &settings.local_history) {
+    log::warn!("Failed to keep local history for {}: {}", file_path, e);
+  }
+  fs::write_checked(&path, formatted.as_bytes(), expected_modified)?;
+  if settings.file_cache_enabled {
+    app.state::<FileCacheState>().0.lock().unwrap().insert(&path, &formatted, std::fs::metadata(&path).ok().as_ref());
+  }
+  Ok(SaveWithHooksResult { steps, content_modified, content: formatted, written: true, blocked_by: None })
+}
+
+/// Write `content` to a new location after checking it: inside an open workspace (unless
+/// allowed), a valid name, and not replacing an existing file without `overwrite`. With
+/// `save_copy` the editor keeps editing `source_path`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn save_file_as(window: Window, source_path: Option<String>, target_path: String, content: String, overwrite: bool, save_copy: Option<bool>, git_add: Option<bool>, allow_outside_workspace: Option<bool>) -> Result<SaveAsResult, FsError> {
+  let app = window.app_handle();
+  let settings = app.state::<SettingsState>().get();
+  let mut target = dialogs::resolve(&fs::decode_path(&target_path));
+
+  let mut language_id = None;
+  if settings.infer_save_extension {
+    if let Some((inferred, language)) = save_as::infer_extension(&target, &content) {
+      target = inferred;
+      language_id = Some(language.to_string());
+    }
+  }
+
+  let workspace_root = app.state::<WorkspaceRegistry>().root_for(&target);
+  if workspace_root.is_none() && !allow_outside_workspace.unwrap_or(false) {
+    return Err(FsError::OutsideWorkspace);
+  }
+  fs::check_target_name(&target)?;
+  let existing = std::fs::metadata(&target).ok();
+  if existing.as_ref().map(|m| m.is_dir()).unwrap_or(false) {
+    return Err(FsError::AlreadyExists);
+  }
+  let overwritten = existing.is_some();
+  if overwritten && !overwrite {
+    return Err(FsError::AlreadyExists);
+  }
+
+  if let Some(parent) = target.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  if overwritten {
+    if let Err(e) = app.state::<LocalHistoryStore>().record(&target, "save_file_as", &settings.local_history) {
+      log::warn!("Failed to keep local history for {}: {}", target.display(), e);
+    }
+  }
+  fs::write_atomic(&target, content.as_bytes())?;
+  // Resolve again now that the file exists, so the returned path is canonical
+  let target = dialogs::resolve(&target);
+  if settings.file_cache_enabled {
+    app.state::<FileCacheState>().0.lock().unwrap().insert(&target, &content, std::fs::metadata(&target).ok().as_ref());
+  }
+
+  let copy = save_copy.unwrap_or(false);
+  let open_files = app.state::<OpenFileRegistry>();
+  if !copy {
+    if let Some(source) = &source_path {
+      open_files.mark_closed(&fs::decode_path(source), window.label());
+    }
+    open_files.mark_open(&target, window.label());
+  }
+  if let Some(root) = &workspace_root {
+    app.state::<DialogDirectoryStore>().remember(root, &target);
+  }
+
+  #[cfg(feature = "git")]
+  let staged = if git_add.unwrap_or(false) {
+    let path = target.clone();
+    app.state::<GitState>().with_index_lock(target.parent().unwrap_or(&target), move || git::stage_path(&path)).await
+      .map_err(|e| FsError::Io { message: format!("Saved, but failed to stage: {}", e) })?
+  } else {
+    false
+  };
+  // Without the git module there is no index to stage into
+  #[cfg(not(feature = "git"))]
+  let staged = {
+    let _ = git_add;
+    false
+  };
+
+  Ok(SaveAsResult {
+    path: fs::display_path(&target),
+    raw_path: fs::encode_path(&target),
+    size: content.len() as u64,
+    modified: fs::modified_secs(&target),
+    extension_inferred: language_id.is_some(),
+    language_id,
+    overwritten,
+    staged,
+    copy,
+    workspace_root: workspace_root.map(|root| fs::display_path(&root)),
+  })
+}
+
+// Local History Commands
+#[tauri::command]
+async fn get_local_history(history: State<'_, LocalHistoryStore>, file_path: String) -> Result<Vec<LocalHistoryVersion>, FsError> {
+  Ok(history.versions(&fs::decode_path(&file_path)))
+}
+
+#[tauri::command]
+async fn read_local_history_version(history: State<'_, LocalHistoryStore>, file_path: String, version_id: String) -> Result<String, FsError> {
+  let content = history.read_version(&fs::decode_path(&file_path), &version_id)?;
+  Ok(String::from_utf8_lossy(&content).to_string())
+}
+
+/// Put an earlier version back, keeping the contents it replaces as a new history entry.
+/// Refuses like a save when the file changed on disk since `expected_modified`.
+#[tauri::command]
+async fn restore_local_history_version(app: AppHandle, settings: State<'_, SettingsState>, cache: State<'_, FileCacheState>, history: State<'_, LocalHistoryStore>, file_path: String, version_id: String, expected_modified: Option<i64>) -> Result<(), FsError> {
+  let path = &fs::decode_path(&file_path);
+  let content = history.read_version(path, &version_id)?;
+  history.record(path, "restore_local_history_version", &settings.get().local_history)?;
+  fs::write_checked(path, &content, expected_modified)?;
+  match std::str::from_utf8(&content) {
+    Ok(text) if settings.get().file_cache_enabled => cache.0.lock().unwrap().insert(path, text, std::fs::metadata(path).ok().as_ref()),
+    _ => cache.0.lock().unwrap().invalidate(path),
+  }
+  app.state::<WorkspaceRegistry>().discard_draft(path);
+  Ok(())
+}
+
+// Storage Commands
+#[tauri::command]
+async fn get_storage_info(app: AppHandle) -> Result<StorageInfo, String> {
+  tauri::async_runtime::spawn_blocking(move || app.state::<StorageLayout>().info())
+    .await
+    .map_err(|e| format!("Failed to read storage info: {}", e))
+}
+
+/// Delete blobs that neither local history nor a checkpoint refers to any more
+fn sweep_blobs(app: &AppHandle) -> usize {
+  let mut referenced = app.state::<LocalHistoryStore>().referenced_blobs();
+  referenced.extend(app.state::<CheckpointStore>().referenced_blobs());
+  BlobStore::load(app.state::<StorageLayout>().data_dir()).retain(&referenced)
+}
+
+/// Prune scratch files, local history, checkpoints and the command audit log by the limits in
+/// settings, plus all but the newest migration backups
+#[tauri::command]
+async fn compact_storage(app: AppHandle) -> Result<CompactionReport, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let settings = app.state::<SettingsState>().get();
+    let layout = app.state::<StorageLayout>();
+    let bytes_before = layout.total_bytes();
+    #[cfg(feature = "git")]
+    let deleted_branches = app.state::<DeletedBranchStore>().prune(settings.deleted_branch_retention_days);
+    #[cfg(not(feature = "git"))]
+    let deleted_branches = 0;
+    let report = CompactionReport {
+      scratch_files: app.state::<ScratchStore>().cleanup(&settings.scratch_policy).map_err(|e| format!("Failed to clean up scratch files: {}", e))?,
+      history_versions: app.state::<LocalHistoryStore>().prune(&settings.local_history).map_err(|e| format!("Failed to prune local history: {}", e))?,
+      checkpoints: app.state::<CheckpointStore>().prune(settings.checkpoint_max_age_days).map_err(|e| format!("Failed to prune checkpoints: {}", e))?,
+      blobs: sweep_blobs(&app),
+      audit_entries: allowed_commands::prune_audit(layout.data_dir(), settings.audit_log_max_age_days),
+      deleted_branches,
+      migration_backups: layout.prune_migration_backups(),
+      bytes_before,
+      bytes_after: layout.total_bytes(),
+    };
+    Ok(report)
+  })
+  .await
+  .map_err(|e| format!("Storage compaction failed: {}", e))?
+}
+
+// Checkpoint Commands
+fn checkpoint_root(project_path: &str) -> Result<PathBuf, FsError> {
+  workspace::canonical_root(&fs::decode_path(project_path)).map_err(|e| FsError::Io { message: e.to_string() })
+}
+
+/// Snapshot every non-ignored file in the workspace
+#[tauri::command]
+async fn create_checkpoint(app: AppHandle, project_path: String, label: Option<String>) -> Result<Checkpoint, FsError> {
+  let root = checkpoint_root(&project_path)?;
+  tauri::async_runtime::spawn_blocking(move || app.state::<CheckpointStore>().create(&root, label))
+    .await
+    .map_err(|e| FsError::Io { message: e.to_string() })?
+}
+
+#[tauri::command]
+async fn list_checkpoints(checkpoints: State<'_, CheckpointStore>, project_path: String) -> Result<Vec<Checkpoint>, FsError> {
+  Ok(checkpoints.list(&checkpoint_root(&project_path)?))
+}
+
+/// Files added, removed and modified between two checkpoints, or from a checkpoint to the
+/// working tree when `to_checkpoint_id` is omitted
+#[tauri::command]
+async fn diff_checkpoints(app: AppHandle, project_path: String, from_checkpoint_id: String, to_checkpoint_id: Option<String>) -> Result<CheckpointDiff, FsError> {
+  let root = checkpoint_root(&project_path)?;
+  tauri::async_runtime::spawn_blocking(move || app.state::<CheckpointStore>().diff(&root, &from_checkpoint_id, to_checkpoint_id.as_deref()))
+    .await
+    .map_err(|e| FsError::Io { message: e.to_string() })?
+}
+
+/// Line diff of one file from its checkpointed version to the working tree
+#[tauri::command]
+async fn diff_checkpoint_file(checkpoints: State<'_, CheckpointStore>, project_path: String, checkpoint_id: String, file_path: String, context_lines: Option<usize>) -> Result<TextDiffResult, FsError> {
+  checkpoints.diff_file(&checkpoint_root(&project_path)?, &checkpoint_id, &file_path, context_lines.unwrap_or(3))
+}
+
+// Fingerprint Commands
+/// Fingerprint the workspace and store it for a later `compare_project_fingerprint`. Cancel
+/// through `cancel_operation` with "fingerprint:<project_path>".
+#[tauri::command]
+async fn compute_project_fingerprint(app: AppHandle, project_path: String, content_hashes: Option<bool>, operation_id: Option<String>) -> Result<ProjectFingerprint, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to fingerprint project: {}", e))?;
+  let operation_id = operation_id.unwrap_or_else(|| format!("fingerprint:{}", project_path));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+
+  let handle = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    handle.state::<FingerprintStore>().compute(&root, content_hashes.unwrap_or(false), &cancel, |progress| {
+      let _ = handle.emit("fingerprint-progress", progress);
+    })
+  })
+  .await
+  .map_err(|e| format!("Fingerprint task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  result.map_err(|e| format!("Failed to fingerprint project: {}", e))
+}
+
+/// Paths added, removed and changed since `previous_fingerprint`, a digest returned earlier by
+/// `compute_project_fingerprint`
+#[tauri::command]
+async fn compare_project_fingerprint(app: AppHandle, project_path: String, previous_fingerprint: String, operation_id: Option<String>) -> Result<FingerprintComparison, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to compare fingerprints: {}", e))?;
+  let operation_id = operation_id.unwrap_or_else(|| format!("fingerprint:{}", project_path));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+
+  let handle = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    handle.state::<FingerprintStore>().compare(&root, &previous_fingerprint, &cancel, |progress| {
+      let _ = handle.emit("fingerprint-progress", progress);
+    })
+  })
+  .await
+  .map_err(|e| format!("Fingerprint task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  result.map_err(|e| format!("Failed to compare fingerprints: {}", e))
+}
+
+/// The fingerprint most recently computed for the project, if any
+#[tauri::command]
+async fn get_project_fingerprint(fingerprints: State<'_, FingerprintStore>, project_path: String) -> Result<Option<ProjectFingerprint>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read project fingerprint: {}", e))?;
+  Ok(fingerprints.latest(&root))
+}
+
+// Project Commands
+#[tauri::command]
+async fn get_project_manifest(manifests: State<'_, ManifestCache>, project_path: String) -> Result<Vec<ProjectManifest>, String> {
+  Ok(manifests.get_project_manifests(&fs::decode_path(&project_path)))
+}
+
+// Environment File Commands
+#[tauri::command]
+async fn list_env_files(project_path: String) -> Result<Vec<EnvFileInfo>, String> {
+  Ok(env::list_env_files(&fs::decode_path(&project_path)))
+}
+
+#[tauri::command]
+async fn read_env_file(path: String, reveal_values: Option<bool>) -> Result<Vec<EnvEntry>, FsError> {
+  env::read_env_file(&fs::decode_path(&path), reveal_values.unwrap_or(false))
+}
+
+#[tauri::command]
+async fn set_env_value(cache: State<'_, FileCacheState>, path: String, key: String, value: String) -> Result<(), FsError> {
+  cache.0.lock().unwrap().invalidate(&fs::decode_path(&path));
+  env::set_env_value(&fs::decode_path(&path), &key, &value)
+}
+
+#[tauri::command]
+async fn remove_env_key(cache: State<'_, FileCacheState>, path: String, key: String) -> Result<bool, FsError> {
+  cache.0.lock().unwrap().invalidate(&fs::decode_path(&path));
+  env::remove_env_key(&fs::decode_path(&path), &key)
+}
+
+#[tauri::command]
+async fn compare_env_files(path_a: String, path_b: String) -> Result<EnvComparison, FsError> {
+  env::compare_env_files(&fs::decode_path(&path_a), &fs::decode_path(&path_b))
+}
+
+// Diff Commands
+#[tauri::command]
+async fn diff_files(left_path: String, right_path: String, context_lines: Option<usize>) -> Result<TextDiffResult, FsError> {
+  diff::diff_files(&fs::decode_path(&left_path), &fs::decode_path(&right_path), context_lines.unwrap_or(3))
+}
+
+#[tauri::command]
+async fn diff_texts(left: String, right: String, context_lines: Option<usize>) -> Result<TextDiffResult, String> {
+  Ok(diff::diff_texts(&left, &right, "a", "b", context_lines.unwrap_or(3)))
+}
+
+// Line Ending Commands
+#[tauri::command]
+async fn analyze_line_endings(paths: Vec<String>, include_ignored: Option<bool>) -> Result<Vec<LineEndingReport>, String> {
+  let paths: Vec<std::path::PathBuf> = paths.iter().map(|p| fs::decode_path(p)).collect();
+  let files = line_endings::collect_files(&paths, include_ignored.unwrap_or(false));
+  Ok(line_endings::analyze_line_endings(&files))
+}
+
+#[tauri::command]
+async fn normalize_line_endings(cache: State<'_, FileCacheState>, paths: Vec<String>, target: Option<LineEnding>, dry_run: bool, include_ignored: Option<bool>) -> Result<Vec<NormalizeResult>, String> {
+  let paths: Vec<std::path::PathBuf> = paths.iter().map(|p| fs::decode_path(p)).collect();
+  let files = line_endings::collect_files(&paths, include_ignored.unwrap_or(false));
+  let results = line_endings::normalize_line_endings(&files, target, dry_run);
+  if !dry_run {
+    let mut cache = cache.0.lock().unwrap();
+    for result in results.iter().filter(|r| r.status == "changed") {
+      cache.invalidate(Path::new(&result.path));
+    }
+  }
+  Ok(results)
+}
+
+// Text Policy Commands
+/// The policy for a check or fix: the caller's, else `.agentic-ide/policy.json`, else settings
+fn resolve_text_policy(app: &AppHandle, root: &Path, policy: Option<TextPolicy>) -> Result<TextPolicy, String> {
+  match policy {
+    Some(policy) => Ok(policy),
+    None => text_policy::load_policy(root, &app.state::<SettingsState>().get().text_policy).map_err(|e| e.to_string()),
+  }
+}
+
+/// Check the workspace's text files for encoding, line ending, final newline and trailing
+/// whitespace violations. Cancel through `cancel_operation` with "text-policy:<project_path>".
+#[tauri::command]
+async fn check_text_policy(app: AppHandle, project_path: String, policy: Option<TextPolicy>, operation_id: Option<String>) -> Result<PolicyReport, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to check text policy: {}", e))?;
+  let policy = resolve_text_policy(&app, &root, policy).map_err(|e| format!("Failed to check text policy: {}", e))?;
+  let operation_id = operation_id.unwrap_or_else(|| format!("text-policy:{}", project_path));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+
+  let handle = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    text_policy::check_text_policy(&root, &policy, &cancel, |progress| {
+      let _ = handle.emit("text-policy-progress", progress);
+    })
+  })
+  .await
+  .map_err(|e| format!("Text policy task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  result.map_err(|e| format!("Failed to check text policy: {}", e))
+}
+
+/// Apply the policy's mechanical fixes as one changeset: every write goes through the atomic
+/// path, and a failed write puts back the files already changed
+#[tauri::command]
+async fn fix_text_policy(app: AppHandle, project_path: String, policy: Option<TextPolicy>, dry_run: bool, operation_id: Option<String>) -> Result<PolicyFixReport, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to fix text policy: {}", e))?;
+  let policy = resolve_text_policy(&app, &root, policy).map_err(|e| format!("Failed to fix text policy: {}", e))?;
+  let operation_id = operation_id.unwrap_or_else(|| format!("text-policy:{}", project_path));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+
+  let handle = app.clone();
+  let planned = tauri::async_runtime::spawn_blocking(move || {
+    text_policy::plan_fixes(&root, &policy, &cancel, |progress| {
+      let _ = handle.emit("text-policy-progress", progress);
+    })
+    .map(|planned| (root, planned))
+  })
+  .await
+  .map_err(|e| format!("Text policy task failed: {}", e))?;
+  app.state::<OperationRegistry>().finish(&operation_id);
+  let (root, planned) = planned.map_err(|e| format!("Failed to fix text policy: {}", e))?;
+
+  if !dry_run {
+    let settings = app.state::<SettingsState>().get();
+    let history = app.state::<LocalHistoryStore>();
+    for fix in &planned {
+      if let Err(e) = history.record(&fix.path, "fix_text_policy", &settings.local_history) {
+        log::warn!("Failed to keep local history for {}: {}", fix.path.display(), e);
+      }
+    }
+  }
+  let report = text_policy::apply_fixes(&root, &planned, dry_run);
+  if !dry_run {
+    let cache = app.state::<FileCacheState>();
+    let mut cache = cache.0.lock().unwrap();
+    for fix in &planned {
+      cache.invalidate(&fix.path);
+    }
+  }
+  Ok(report)
+}
+
+// Clipboard Commands
+#[tauri::command]
+async fn clipboard_copy_paths(clipboard: State<'_, ClipboardState>, paths: Vec<String>, cut: bool) -> Result<(), String> {
+  let paths = paths.iter().map(|p| fs::decode_path(p)).collect();
+  clipboard.copy_paths(paths, cut);
+  Ok(())
+}
+
+#[tauri::command]
+async fn clipboard_get_status(clipboard: State<'_, ClipboardState>) -> Result<ClipboardStatus, String> {
+  Ok(clipboard.status())
+}
+
+#[tauri::command]
+async fn clipboard_paste(app: AppHandle, clipboard: State<'_, ClipboardState>, cache: State<'_, FileCacheState>, target_dir: String) -> Result<PasteResult, FsError> {
+  let status = clipboard.status();
+  if status.cut {
+    let mut cache = cache.0.lock().unwrap();
+    for path in &status.paths {
+      cache.invalidate(Path::new(path));
+    }
+  }
+
+  clipboard.paste(&fs::decode_path(&target_dir), |progress| {
+    let _ = app.emit("clipboard-paste-progress", progress);
+  })
+}
+
+// Log Tail Commands
+#[cfg(feature = "process")]
+#[tauri::command]
+async fn tail_file(app: AppHandle, tails: State<'_, TailState>, path: String, from_end_bytes: u64, follow: bool, tail_id: String) -> Result<TailStart, FsError> {
+  tails.start(&fs::decode_path(&path), from_end_bytes, follow, tail_id, move |chunk| {
+    let _ = app.emit("file-tail", chunk);
+  })
+}
+
+#[cfg(feature = "process")]
+#[tauri::command]
+async fn stop_tail(tails: State<'_, TailState>, tail_id: String) -> Result<bool, String> {
+  Ok(tails.stop(&tail_id))
+}
+
+// Scratch File Commands
+#[tauri::command]
+async fn create_scratch_file(scratch: State<'_, ScratchStore>, project_path: Option<String>, language_id: String) -> Result<ScratchFile, FsError> {
+  scratch.create(project_path, &language_id)
+}
+
+#[tauri::command]
+async fn list_scratch_files(scratch: State<'_, ScratchStore>) -> Result<Vec<ScratchFile>, String> {
+  Ok(scratch.list())
+}
+
+#[tauri::command]
+async fn promote_scratch_file(scratch: State<'_, ScratchStore>, scratch_id: String, target_path: String) -> Result<String, FsError> {
+  scratch.promote(&scratch_id, &fs::decode_path(&target_path))
+}
+
+#[tauri::command]
+async fn delete_scratch_file(scratch: State<'_, ScratchStore>, scratch_id: String) -> Result<(), FsError> {
+  scratch.delete(&scratch_id)
+}
+
+// Snippet Commands
+#[tauri::command]
+async fn save_snippet(snippets: State<'_, SnippetStore>, scope: SnippetScope, project_path: Option<String>, snippet: SnippetInput) -> Result<Snippet, FsError> {
+  snippets.save(scope, project_path.as_deref().map(fs::decode_path).as_deref(), snippet)
+}
+
+#[tauri::command]
+async fn list_snippets(snippets: State<'_, SnippetStore>, scope: Option<SnippetScope>, project_path: Option<String>, filter: Option<String>) -> Result<Vec<Snippet>, FsError> {
+  Ok(snippets.list(scope, project_path.as_deref().map(fs::decode_path).as_deref(), filter.as_deref()))
+}
+
+#[tauri::command]
+async fn get_snippet(snippets: State<'_, SnippetStore>, id: String, project_path: Option<String>) -> Result<Snippet, FsError> {
+  snippets.get(&id, project_path.as_deref().map(fs::decode_path).as_deref())
+}
+
+#[tauri::command]
+async fn delete_snippet(snippets: State<'_, SnippetStore>, id: String, project_path: Option<String>) -> Result<(), FsError> {
+  snippets.delete(&id, project_path.as_deref().map(fs::decode_path).as_deref())
+}
+
+#[tauri::command]
+async fn export_snippets(snippets: State<'_, SnippetStore>, scope: SnippetScope, project_path: Option<String>, output_path: String) -> Result<usize, FsError> {
+  snippets.export_pack(scope, project_path.as_deref().map(fs::decode_path).as_deref(), &fs::decode_path(&output_path))
+}
+
+#[tauri::command]
+async fn import_snippets(snippets: State<'_, SnippetStore>, scope: SnippetScope, project_path: Option<String>, input_path: String) -> Result<usize, FsError> {
+  snippets.import_pack(scope, project_path.as_deref().map(fs::decode_path).as_deref(), &fs::decode_path(&input_path))
+}
+
+// Workspace Commands
+#[tauri::command]
+async fn open_workspace(app: AppHandle, window: Window, path: String) -> Result<WorkspaceInfo, String> {
+  register_workspace(&app, window.label(), &path)
+}
+
+// Critical bootstrap phases must finish quickly or the project fails to open
+const CRITICAL_PHASE_TIMEOUT: Duration = Duration::from_secs(10);
+const BACKGROUND_PHASE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Run one bootstrap phase on the blocking pool with a timeout, recording how long it took
+async fn timed_phase<T, F>(app: &AppHandle, root: &Path, phase: &str, limit: Duration, job: F) -> Result<T, String>
+where
+  T: Send + 'static,
+  F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+  let started = Instant::now();
+  let (result, outcome) = match tokio::time::timeout(limit, tauri::async_runtime::spawn_blocking(job)).await {
+    Err(_) => (Err(format!("{} did not finish within {} seconds", phase, limit.as_secs())), "timeout"),
+    Ok(Err(e)) => (Err(format!("{} task failed: {}", phase, e)), "error"),
+    Ok(Ok(Err(e))) => (Err(e), "error"),
+    Ok(Ok(Ok(value))) => (Ok(value), "ok"),
+  };
+  app.state::<BootstrapTimingsState>().record(root, PhaseTiming {
+    phase: phase.to_string(),
+    duration_ms: started.elapsed().as_millis() as u64,
+    outcome: outcome.to_string(),
+  });
+  result
+}
+
+fn emit_bootstrap(app: &AppHandle, root: &Path, phase: &str, result: Result<BootstrapPayload, String>) {
+  let payload = result.unwrap_or_else(|message| BootstrapPayload::Failed { failed_phase: phase.to_string(), message });
+  let _ = app.emit("workspace-bootstrap", BootstrapEvent { root: fs::display_path(root), payload });
+}
+
+/// Validate a folder and return its top-level listing and branch as soon as they are ready;
+/// the slower pieces run concurrently afterwards and arrive as `workspace-bootstrap` events
+#[tauri::command]
+async fn open_project(app: AppHandle, window: Window, path: String) -> Result<ProjectBootstrap, String> {
+  let started = Instant::now();
+  let root = workspace::validate_project_root(&fs::decode_path(&path)).map_err(|e| format!("Failed to open project: {}", e))?;
+  app.state::<BootstrapTimingsState>().start(&root);
+
+  let listing_root = root.clone();
+  let repository_root = root.clone();
+  let (listing, repository) = tokio::join!(
+    timed_phase(&app, &root, "listing", CRITICAL_PHASE_TIMEOUT, move || {
+      fs::list_directory(&listing_root).map_err(|e| format!("Failed to list project: {}", e))
+    }),
+    timed_phase(&app, &root, "repository", CRITICAL_PHASE_TIMEOUT, move || {
+      let Ok(repo) = git2::Repository::discover(&repository_root) else { return Ok((None, None)) };
+      let branch = repo.head().ok().filter(|h| h.is_branch()).and_then(|h| h.shorthand().map(str::to_string));
+      Ok((repo.workdir().map(|w| w.to_path_buf()), branch))
+    }),
+  );
+  let listing = listing?;
+  let (repository_root, branch) = repository.unwrap_or((None, None));
+
+  let handle = app.clone();
+  let background_root = root.clone();
+  let window_label = window.label().to_string();
+  let background_repository = repository_root.clone();
+  tauri::async_runtime::spawn(async move {
+    bootstrap_background(handle, background_root, window_label, background_repository).await;
+  });
+
+  app.state::<BootstrapTimingsState>().record_critical(&root, started.elapsed().as_millis() as u64);
+  Ok(ProjectBootstrap {
+    root: fs::display_path(&root),
+    repository_root: repository_root.as_deref().map(fs::display_path),
+    branch,
+    listing,
+    read_only: app.state::<ReadOnlyState>().is_read_only(&root),
+  })
+}
+
+/// The non-critical bootstrap phases, each emitted as soon as it completes
+async fn bootstrap_background(app: AppHandle, root: PathBuf, window_label: String, repository_root: Option<PathBuf>) {
+  let watcher = async {
+    let (handle, display_root) = (app.clone(), fs::display_path(&root));
+    let result = timed_phase(&app, &root, "watcher", BACKGROUND_PHASE_TIMEOUT, move || {
+      register_workspace(&handle, &window_label, &display_root)
+    }).await;
+    emit_bootstrap(&app, &root, "watcher", result.map(|workspace| BootstrapPayload::Watcher { workspace }));
+  };
+  #[cfg(feature = "git")]
+  let status = async {
+    let workdir = repository_root.clone();
+    let handle = app.clone();
+    let result = timed_phase(&app, &root, "git_status", BACKGROUND_PHASE_TIMEOUT, move || match workdir {
+      // Another window already has this workspace open; reuse its status
+      Some(w) => match handle.state::<WorkspaceRegistry>().cached_status(&w) {
+        Some(status) => Ok(Some(status)),
+        None => with_git_manager(&handle, &w, |manager| manager.get_status(&w)).map(Some).map_err(|e| format!("Failed to get git status: {}", e)),
+      },
+      None => Ok(None),
+    }).await;
+    emit_bootstrap(&app, &root, "git_status", result.map(|status| BootstrapPayload::GitStatus { status: status.map(Box::new) }));
+  };
+  #[cfg(feature = "git")]
+  let rollup = async {
+    let workdir = repository_root.clone();
+    let result = timed_phase(&app, &root, "status_rollup", BACKGROUND_PHASE_TIMEOUT, move || match workdir {
+      Some(w) => git::get_directory_status_rollup(&w).map_err(|e| format!("Failed to get status rollup: {}", e)),
+      None => Ok(Default::default()),
+    }).await;
+    emit_bootstrap(&app, &root, "status_rollup", result.map(|rollup| BootstrapPayload::StatusRollup { rollup }));
+  };
+  let manifests = async {
+    let (handle, manifest_root) = (app.clone(), root.clone());
+    let result = timed_phase(&app, &root, "manifests", BACKGROUND_PHASE_TIMEOUT, move || {
+      Ok(handle.state::<ManifestCache>().get_project_manifests(&manifest_root))
+    }).await;
+    emit_bootstrap(&app, &root, "manifests", result.map(|manifests| BootstrapPayload::Manifests { manifests }));
+  };
+  #[cfg(feature = "git")]
+  tokio::join!(watcher, status, rollup, manifests);
+  #[cfg(not(feature = "git"))]
+  {
+    let _ = repository_root;
+    tokio::join!(watcher, manifests);
+  }
+  app.state::<IdleScheduler>().enqueue(&root);
+}
+
+/// Idle-time warmup of the word index completion queries use, with the options they build it with
+fn warm_word_index(app: &AppHandle, root: &Path, should_yield: &dyn Fn() -> bool) -> Result<WarmupStep, String> {
+  let words = app.state::<WordIndex>();
+  if let Some(stats) = words.stats(root) {
+    return Ok(WarmupStep::Done(stats.distinct_words));
+  }
+  Ok(match words.build_until(root, WordIndexOptions::default(), should_yield) {
+    Some(stats) => WarmupStep::Done(stats.distinct_words),
+    None => WarmupStep::Yielded,
+  })
+}
+
+/// Idle-time read of the repository index behind the tracked-file finder
+#[cfg(feature = "git")]
+fn warm_file_index(app: &AppHandle, root: &Path, _should_yield: &dyn Fn() -> bool) -> Result<WarmupStep, String> {
+  if git2::Repository::discover(root).is_err() {
+    return Ok(WarmupStep::NotApplicable);
+  }
+  match app.state::<GitState>().indexed_paths(root) {
+    Ok((_, paths)) => Ok(WarmupStep::Done(paths.len())),
+    Err(e) => Err(format!("Failed to read the index: {}", e)),
+  }
+}
+
+#[tauri::command]
+async fn get_warmup_status(idle: State<'_, IdleScheduler>, settings: State<'_, SettingsState>, project_path: String) -> Result<Option<WarmupStatus>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read warmup status: {}", e))?;
+  Ok(idle.status(&root, &settings.get().warmup))
+}
+
+#[tauri::command]
+async fn get_last_bootstrap_timings(timings: State<'_, BootstrapTimingsState>, project_path: String) -> Result<Option<BootstrapTimings>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read timings: {}", e))?;
+  Ok(timings.get(&root))
+}
+
+#[tauri::command]
+async fn close_project(window: Window, path: String) -> Result<bool, String> {
+  close_workspace_root(&window, &path).map_err(|e| format!("Failed to close project: {}", e))
+}
+
+// Pinned Path Commands
+#[tauri::command]
+async fn pin_path(pins: State<'_, PinStore>, project_path: String, path: String) -> Result<Vec<PinnedPath>, String> {
+  pins.pin(&fs::decode_path(&project_path), &fs::decode_path(&path)).map_err(|e| format!("Failed to pin path: {}", e))
+}
+
+#[tauri::command]
+async fn unpin_path(pins: State<'_, PinStore>, project_path: String, path: String) -> Result<Vec<PinnedPath>, String> {
+  Ok(pins.unpin(&fs::decode_path(&project_path), &path))
+}
+
+#[tauri::command]
+async fn list_pinned(pins: State<'_, PinStore>, project_path: String) -> Result<Vec<PinnedPath>, String> {
+  Ok(pins.list(&fs::decode_path(&project_path)))
+}
+
+/// `order` lists the pins' relative paths in their new order
+#[tauri::command]
+async fn reorder_pinned(pins: State<'_, PinStore>, project_path: String, order: Vec<String>) -> Result<Vec<PinnedPath>, String> {
+  pins.reorder(&fs::decode_path(&project_path), &order).map_err(|e| format!("Failed to reorder pins: {}", e))
+}
+
+// File Tag Commands
+#[tauri::command]
+async fn set_file_tags(store: State<'_, TagStore>, project_path: String, path: String, tags: Vec<String>) -> Result<Vec<String>, String> {
+  store.set_tags(&fs::decode_path(&project_path), &path, &tags).map_err(|e| format!("Failed to tag file: {}", e))
+}
+
+#[tauri::command]
+async fn get_file_tags(tags: State<'_, TagStore>, project_path: String, path: String) -> Result<Vec<String>, String> {
+  Ok(tags.get_tags(&fs::decode_path(&project_path), &path))
+}
+
+#[tauri::command]
+async fn list_tags(tags: State<'_, TagStore>, project_path: String) -> Result<Vec<TagCount>, String> {
+  Ok(tags.list_tags(&fs::decode_path(&project_path)))
+}
+
+/// Files carrying `tag`, with those that no longer exist marked `missing`
+#[tauri::command]
+async fn find_files_by_tag(tags: State<'_, TagStore>, project_path: String, tag: String) -> Result<Vec<TaggedFile>, String> {
+  Ok(tags.find_files_by_tag(&fs::decode_path(&project_path), &tag))
+}
+
+// Environment Profile Commands
+#[tauri::command]
+async fn list_env_profiles(profiles: State<'_, EnvProfileStore>, project_path: String) -> Result<Vec<EnvProfile>, String> {
+  Ok(profiles.list(&fs::decode_path(&project_path)))
+}
+
+/// Values of `secret_keys` go to the credential store; a secret key missing from `vars`
+/// keeps its stored value. The audit log records key names only.
+#[tauri::command]
+async fn save_env_profile(app: AppHandle, project_path: String, name: String, vars: BTreeMap<String, String>, secret_keys: Vec<String>) -> Result<EnvProfile, CredentialError> {
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let handle = app.clone();
+  let profile = tauri::async_runtime::spawn_blocking(move || {
+    handle.state::<EnvProfileStore>().save(&fs::decode_path(&project_path), &name, &vars, &secret_keys, &handle.state::<CredentialStore>()).map(|profile| (project_path, profile))
+  })
+  .await
+  .map_err(|e| CredentialError::Other { message: format!("Failed to save environment profile: {}", e) })?;
+  let (project_path, profile) = profile?;
+  let keys: Vec<&str> = profile.vars.keys().chain(&profile.secret_keys).map(String::as_str).collect();
+  allowed_commands::record_audit_event(&data_dir, &project_path, "env_profile:saved", format!("{} ({})", profile.name, keys.join(", ")));
+  Ok(profile)
+}
+
+#[tauri::command]
+async fn delete_env_profile(app: AppHandle, project_path: String, name: String) -> Result<bool, CredentialError> {
+  let data_dir = app.state::<StorageLayout>().data_dir().to_path_buf();
+  let deleted = app.state::<EnvProfileStore>().delete(&fs::decode_path(&project_path), &name, &app.state::<CredentialStore>())?;
+  if deleted {
+    allowed_commands::record_audit_event(&data_dir, &project_path, "env_profile:deleted", name);
+  }
+  Ok(deleted)
+}
+
+/// The profile's variables as a process would get them, with secret values masked
+#[tauri::command]
+async fn resolve_env_profile(app: AppHandle, project_path: String, name: String) -> Result<ResolvedEnvProfile, CredentialError> {
+  app.state::<EnvProfileStore>().resolve(&fs::decode_path(&project_path), &name, &app.state::<CredentialStore>())
+}
+
+// Dialog Commands
+#[tauri::command]
+async fn pick_open_path(app: AppHandle, project_path: String, kind: PickKind, filters: Option<Vec<String>>) -> Result<Option<PickedPath>, String> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let project = &fs::decode_path(&project_path);
+    let start_dir = handle.state::<DialogDirectoryStore>().start_dir(project);
+    let picked = dialogs::pick_open(&handle, &start_dir, kind, &filters.unwrap_or_default());
+    picked.map(|path| remember_pick(&handle, project, &path))
+  })
+  .await
+  .map_err(|e| format!("Dialog task failed: {}", e))
+}
+
+#[tauri::command]
+async fn pick_save_path(app: AppHandle, project_path: String, default_name: Option<String>, filters: Option<Vec<String>>) -> Result<Option<PickedPath>, String> {
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let project = &fs::decode_path(&project_path);
+    let start_dir = handle.state::<DialogDirectoryStore>().start_dir(project);
+    let picked = dialogs::pick_save(&handle, &start_dir, default_name.as_deref(), &filters.unwrap_or_default());
+    picked.map(|path| remember_pick(&handle, project, &path))
+  })
+  .await
+  .map_err(|e| format!("Dialog task failed: {}", e))
+}
+
+/// Remember where the user went and flag picks that leave every open workspace
+fn remember_pick(app: &AppHandle, project: &Path, picked: &Path) -> PickedPath {
+  app.state::<DialogDirectoryStore>().remember(project, picked);
+  PickedPath {
+    path: fs::encode_path(picked),
+    outside_workspace: app.state::<WorkspaceRegistry>().root_for(&dialogs::resolve(picked)).is_none(),
+  }
+}
+
+/// Watcher for an open workspace, feeding caches, indexes and repository-change tracking
+fn workspace_watcher(app: &AppHandle, root: &Path, mode: WatchMode) -> anyhow::Result<FileWatcher> {
+  let handle = app.clone();
+  let root = root.to_path_buf();
+  let on_error = {
+    let (handle, root) = (handle.clone(), root.clone());
+    move |e: notify::Error| {
+      let unavailable = matches!(&e.kind, notify::ErrorKind::Io(io) if workspace::is_unavailable_error(io));
+      if unavailable || !root.exists() {
+        disconnect_workspace(&handle, &root, &e.to_string());
+      } else {
+        log::warn!("Watch error: {:?}", e);
+      }
+    }
+  };
+  let on_event = move |event: notify::Event| {
+    // The self-test probe is only for the watcher health check
+    if handle.state::<WatchProbes>().observe(&root, &event.paths) {
+      return;
+    }
+
+    // The root itself disappearing means the volume went away, not a user deletion
+    if matches!(event.kind, notify::EventKind::Remove(_)) && event.paths.contains(&root) && !root.exists() {
+      disconnect_workspace(&handle, &root, "The workspace folder disappeared");
+      return;
+    }
+
+    let cache = handle.state::<FileCacheState>();
+    for changed in &event.paths {
+      cache.0.lock().unwrap().invalidate(changed);
+    }
+    #[cfg(feature = "git")]
+    handle.state::<WorkspaceRegistry>().invalidate_status(&root);
+
+    // Directories created under a filtered root need their own watch
+    if matches!(event.kind, notify::EventKind::Create(_)) {
+      let workspaces = handle.state::<WorkspaceRegistry>();
+      for dir in event.paths.iter().filter(|p| p.is_dir()) {
+        workspaces.add_watched_directory(&root, dir);
+      }
+    }
+
+    let symbols = handle.state::<SymbolIndex>();
+    for changed in event.paths.iter().filter(|p| symbols::parser_for(p).is_some()) {
+      symbols.update_file(changed);
+    }
+
+    let words = handle.state::<WordIndex>();
+    if words.is_built(&root) {
+      for changed in &event.paths {
+        words.update_file(changed);
+      }
+    }
+
+    // Writes under .git from an external terminal settle into one classified event
+    #[cfg(feature = "git")]
+    if event.paths.iter().any(|p| repo_events::is_repo_signal(&root, p)) && handle.state::<RepoChangeMonitor>().signal(&root) {
+      let handle = handle.clone();
+      let root = root.clone();
+      std::thread::spawn(move || {
+        let Some(change) = handle.state::<RepoChangeMonitor>().settle(&root) else { return };
+        if change.changes.contains(&RepoChangeKind::BranchSwitched) {
+          handle.state::<RecentBranchStore>().record_head(&root);
+        }
+        let _ = handle.emit("repo-external-change", change);
+      });
+    }
+
+    handle.state::<ChangeJournal>().record(&root, &event.kind, &event.paths);
+    #[cfg(feature = "git")]
+    handle.state::<ChangedPathsCache>().invalidate(&root);
+    let _ = handle.emit("fs-change", FsChangeEvent::from_event(&root, &event));
+  };
+  match mode {
+    WatchMode::Native => FileWatcher::new(on_event, on_error),
+    WatchMode::Polling => {
+      let settings = app.state::<SettingsState>().get();
+      let interval = Duration::from_millis(settings.watcher_poll_interval_ms.max(250));
+      FileWatcher::polling(on_event, on_error, interval, settings.watcher_poll_max_directories)
+    }
+  }
+}
+
+/// Swap the watcher of the workspace at `root` to `mode` and tell the frontend
+fn switch_watch_mode(app: &AppHandle, root: &Path, mode: WatchMode, reason: &str) -> anyhow::Result<bool> {
+  let (changed, limit) = app.state::<WorkspaceRegistry>().set_watch_mode(root, mode, |root, mode| workspace_watcher(app, root, mode))?;
+  if changed {
+    let _ = app.emit("watch-mode-changed", WatchModeChanged { root: fs::display_path(root), mode, reason: reason.to_string() });
+  }
+  if let Some(limit) = limit {
+    let _ = app.emit("watch-limit-reached", limit);
+  }
+  Ok(changed)
+}
+
+/// Write a probe file into a freshly watched workspace and fall back to polling when the
+/// watcher does not report it in time, as happens on some network filesystems and WSL mounts
+fn probe_watcher(app: &AppHandle, root: &Path) {
+  let timeout = app.state::<SettingsState>().get().watcher_probe_timeout_ms;
+  if timeout == 0 {
+    return;
+  }
+  let (handle, root) = (app.clone(), root.to_path_buf());
+  std::thread::spawn(move || {
+    let probes = handle.state::<WatchProbes>();
+    if let Err(e) = probes.begin(&root) {
+      log::warn!("Watch self-test could not write its probe in {}: {}", root.display(), e);
+      return;
+    }
+    std::thread::sleep(Duration::from_millis(timeout));
+    if probes.finish(&root) {
+      return;
+    }
+    let reason = format!("No file events arrived within {} ms of a test write", timeout);
+    if let Err(e) = switch_watch_mode(&handle, &root, WatchMode::Polling, &reason) {
+      log::warn!("Failed to switch {} to polling: {}", root.display(), e);
+    }
+  });
+}
+
+/// Take a workspace whose volume went away offline: its watcher stops, commands against it
+/// fail with WorkspaceUnavailable and the availability probe waits for it to come back
+fn disconnect_workspace(app: &AppHandle, root: &Path, reason: &str) {
+  if app.state::<WorkspaceRegistry>().disconnect(root) {
+    let _ = app.emit("workspace-disconnected", WorkspaceDisconnected {
+      root: fs::display_path(root),
+      reason: reason.to_string(),
+    });
+  }
+}
+
+/// Restart a returning workspace's watcher and report what changed while it was away
+fn reconnect_workspace(app: &AppHandle, root: &Path) -> anyhow::Result<()> {
+  let Some(since) = app.state::<WorkspaceRegistry>().reconnect(root, |root, mode| workspace_watcher(app, root, mode))? else {
+    return Ok(());
+  };
+  let _ = app.emit("workspace-reconnected", WorkspaceReconnected {
+    root: fs::display_path(root),
+    away_secs: chrono::Utc::now().timestamp() - since,
+    #[cfg(feature = "git")]
+    repository: app.state::<RepoChangeMonitor>().refresh(root),
+    #[cfg(feature = "git")]
+    status: git2::Repository::discover(root)
+      .ok()
+      .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+      .and_then(|workdir| GitManager::new(&workdir).get_status(&workdir).ok()),
+  });
+  Ok(())
+}
+
+// How often open workspaces are checked for a vanished or returning volume
+const AVAILABILITY_POLL: Duration = Duration::from_secs(5);
+const AVAILABILITY_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+async fn watch_workspace_availability(app: AppHandle) {
+  // Probes stuck on a hung network share; such a root counts as unavailable until it answers
+  let pending: Arc<Mutex<HashSet<PathBuf>>> = Default::default();
+  loop {
+    tokio::time::sleep(AVAILABILITY_POLL).await;
+    for (root, disconnected) in app.state::<WorkspaceRegistry>().availability() {
+      let available = if pending.lock().unwrap().contains(&root) {
+        false
+      } else {
+        pending.lock().unwrap().insert(root.clone());
+        let (probe, pending) = (root.clone(), pending.clone());
+        let probed = tauri::async_runtime::spawn_blocking(move || {
+          let available = workspace::probe_root(&probe);
+          pending.lock().unwrap().remove(&probe);
+          available
+        });
+        matches!(tokio::time::timeout(AVAILABILITY_PROBE_TIMEOUT, probed).await, Ok(Ok(true)))
+      };
+
+      match (disconnected, available) {
+        (false, false) => disconnect_workspace(&app, &root, "The workspace folder is no longer reachable"),
+        (true, true) => {
+          let handle = app.clone();
+          let reconnected = tauri::async_runtime::spawn_blocking(move || reconnect_workspace(&handle, &root)).await;
+          if let Ok(Err(e)) = reconnected {
+            log::warn!("Failed to reconnect workspace: {}", e);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+}
+
+/// Attach `window` to the workspace at `path`, starting its watcher on first open
+/// Detach `window` from the workspace at `path`, releasing the workspace once no window has it open
+fn close_workspace_root(window: &Window, path: &str) -> anyhow::Result<bool> {
+  let workspaces = window.state::<WorkspaceRegistry>();
+  let decoded = fs::decode_path(path);
+  // A disconnected workspace can no longer be resolved, so it is matched as given
+  let root = match workspace::canonical_root(&decoded) {
+    Ok(root) => root,
+    Err(e) => workspaces.disconnected_root_for(&decoded).ok_or(e)?,
+  };
+  let released = workspaces.close(&root, window.label());
+  if released {
+    release_workspace_root(window.app_handle(), &root);
+  }
+  Ok(released)
+}
+
+/// Drop the indexes, idle tasks and git state kept for a workspace whose bundle was released
+fn release_workspace_root(app: &AppHandle, root: &Path) {
+  app.state::<SymbolIndex>().remove_root(root);
+  app.state::<WordIndex>().remove_root(root);
+  app.state::<IdleScheduler>().remove_root(root);
+  #[cfg(feature = "git")]
+  {
+    app.state::<RepoChangeMonitor>().forget(root);
+    app.state::<GitState>().forget(root);
+  }
+}
+
+fn register_workspace(app: &AppHandle, window_label: &str, path: &str) -> Result<WorkspaceInfo, String> {
+  let root = workspace::canonical_root(&fs::decode_path(path)).map_err(|e| format!("Failed to open workspace: {}", e))?;
+  let settings = app.state::<SettingsState>().get();
+  let exclusions = settings.watcher_exclusions_for(&workspace::project_key(path));
+  let workspaces = app.state::<WorkspaceRegistry>();
+
+  let opened = workspaces.open(&root, window_label, &exclusions, settings.watcher_respect_gitignore, |root, mode| workspace_watcher(app, root, mode));
+
+  match opened {
+    Ok((info, limit)) => {
+      #[cfg(feature = "git")]
+      app.state::<RepoChangeMonitor>().track(&root);
+      if info.watch_mode == WatchMode::Native {
+        probe_watcher(app, &root);
+      }
+      if let Some(limit) = limit {
+        let _ = app.emit("watch-limit-reached", limit);
+      }
+      if app.state::<SymbolIndex>().begin_root(&root) {
+        let handle = app.clone();
+        std::thread::spawn(move || {
+          let emitter = handle.clone();
+          let started = Instant::now();
+          let indexed = handle.state::<SymbolIndex>().index_root(&root, |progress| {
+            let _ = emitter.emit("symbol-index-progress", progress);
+          });
+          handle.state::<BootstrapTimingsState>().record(&root, PhaseTiming {
+            phase: "symbol_index".to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            outcome: "ok".to_string(),
+          });
+          emit_bootstrap(&handle, &root, "symbol_index", Ok(BootstrapPayload::IndexReady { indexed }));
+        });
+      }
+      Ok(info)
+    }
+    Err(e) => Err(format!("Failed to open workspace: {}", e)),
+  }
+}
+
+// Symbol Commands
+/// Symbol tree of one file; empty for unsupported, oversized or unreadable files
+#[tauri::command]
+async fn get_file_outline(symbols: State<'_, SymbolIndex>, path: String) -> Result<Vec<Symbol>, String> {
+  Ok(symbols.outline(&fs::decode_path(&path)).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn query_workspace_symbols(symbols: State<'_, SymbolIndex>, root: String, query: String, limit: Option<usize>) -> Result<Vec<WorkspaceSymbol>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to query symbols: {}", e))?;
+  Ok(symbols.query(&root, &query, limit.unwrap_or(100)))
+}
+
+// Word Index Commands
+#[tauri::command]
+async fn build_word_index(app: AppHandle, root: String, options: Option<WordIndexOptions>) -> Result<WordIndexStats, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to build word index: {}", e))?;
+  tauri::async_runtime::spawn_blocking(move || app.state::<WordIndex>().build(&root, options.unwrap_or_default()))
+    .await
+    .map_err(|e| format!("Word index task failed: {}", e))
+}
+
+/// Completion candidates for `prefix`; the index is built with default options on first use
+#[tauri::command]
+async fn query_word_index(app: AppHandle, root: String, prefix: String, limit: Option<usize>) -> Result<Vec<WordCandidate>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to query word index: {}", e))?;
+  let limit = limit.unwrap_or(50);
+  tauri::async_runtime::spawn_blocking(move || {
+    let words = app.state::<WordIndex>();
+    if !words.is_built(&root) {
+      words.build(&root, WordIndexOptions::default());
+    }
+    words.query(&root, &prefix, limit).unwrap_or_default()
+  })
+  .await
+  .map_err(|e| format!("Word index task failed: {}", e))
+}
+
+#[tauri::command]
+async fn get_word_index_stats(words: State<'_, WordIndex>, root: String) -> Result<Option<WordIndexStats>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to read word index stats: {}", e))?;
+  Ok(words.stats(&root))
+}
+
+#[tauri::command]
+async fn duplicate_workspace(app: AppHandle, window: Window, source_root: String, target_root: String, include_git: bool, respect_gitignore: bool, operation_id: Option<String>) -> Result<DuplicateResult, String> {
+  let operation_id = operation_id.unwrap_or_else(|| format!("duplicate:{}", target_root));
+  let cancel = app.state::<OperationRegistry>().register(&operation_id);
+  let options = DuplicateOptions { include_git, respect_gitignore };
+
+  let handle = app.clone();
+  let target = target_root.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    duplicate::duplicate_workspace(&fs::decode_path(&source_root), &fs::decode_path(&target), &options, cancel, |progress| {
+      let _ = handle.emit("duplicate-progress", progress);
+    })
+  })
+  .await
+  .map_err(|e| format!("Duplicate task failed: {}", e))?;
+
+  app.state::<OperationRegistry>().finish(&operation_id);
+  let duplicated = result.map_err(|e| format!("Failed to duplicate workspace: {}", e))?;
+  register_workspace(&app, window.label(), &target_root)?;
+  Ok(duplicated)
+}
+
+#[tauri::command]
+async fn close_workspace(window: Window, path: String) -> Result<bool, String> {
+  close_workspace_root(&window, &path).map_err(|e| format!("Failed to close workspace: {}", e))
+}
+
+/// Keep unsaved editor contents in their workspace, so its other windows can pick them up.
+/// Returns false when no open workspace contains `path`.
+#[tauri::command]
+async fn update_draft(workspaces: State<'_, WorkspaceRegistry>, path: String, content: String) -> Result<bool, String> {
+  Ok(workspaces.set_draft(&fs::decode_path(&path), content))
+}
+
+#[tauri::command]
+async fn discard_draft(workspaces: State<'_, WorkspaceRegistry>, path: String) -> Result<bool, String> {
+  Ok(workspaces.discard_draft(&fs::decode_path(&path)))
+}
+
+#[tauri::command]
+async fn get_drafts(workspaces: State<'_, WorkspaceRegistry>, project_path: String) -> Result<BTreeMap<String, String>, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&project_path)).map_err(|e| format!("Failed to read drafts: {}", e))?;
+  Ok(workspaces.drafts(&root))
+}
+
+/// Force native or polling watching for an open workspace
+#[tauri::command]
+async fn set_watch_mode(app: AppHandle, root: String, mode: WatchMode) -> Result<bool, String> {
+  let root = workspace::canonical_root(&fs::decode_path(&root)).map_err(|e| format!("Failed to set watch mode: {}", e))?;
+  tauri::async_runtime::spawn_blocking(move || switch_watch_mode(&app, &root, mode, "Set by the user"))
+    .await
+    .map_err(|e| format!("Watch mode task failed: {}", e))?
+    .map_err(|e| format!("Failed to set watch mode: {}", e))
+}
+
+#[tauri::command]
+async fn list_workspaces(workspaces: State<'_, WorkspaceRegistry>) -> Result<Vec<WorkspaceInfo>, String> {
+  Ok(workspaces.list())
+}
+
+#[tauri::command]
+async fn get_watcher_stats(workspaces: State<'_, WorkspaceRegistry>) -> Result<WatcherStats, String> {
+  Ok(workspaces.watcher_stats())
+}
+
+#[tauri::command]
+async fn explain_ignore(project_path: String, path: String) -> Result<IgnoreExplanation, String> {
+  match gitignore::explain_ignore(&fs::decode_path(&project_path), &fs::decode_path(&path)) {
+    Ok(explanation) => Ok(explanation),
+    Err(e) => Err(format!("Failed to check ignore rules: {}", e)),
+  }
+}
+
+#[tauri::command]
+async fn parse_gitignore(path: String) -> Result<GitignoreFile, String> {
+  tauri::async_runtime::spawn_blocking(move || gitignore::parse_gitignore(&fs::decode_path(&path)))
+    .await
+    .map_err(|e| format!("Gitignore task failed: {}", e))?
+    .map_err(|e| format!("Failed to parse gitignore: {}", e))
+}
+
+#[tauri::command]
+async fn update_gitignore(path: String, edits: Vec<GitignoreEdit>) -> Result<GitignoreFile, String> {
+  tauri::async_runtime::spawn_blocking(move || gitignore::update_gitignore(&fs::decode_path(&path), &edits))
+    .await
+    .map_err(|e| format!("Gitignore task failed: {}", e))?
+    .map_err(|e| format!("Failed to update gitignore: {}", e))
+}
+
+/// Ranked patterns that would hide build output and other noise in the untracked list
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn suggest_gitignore_rules(project_path: String) -> Result<IgnoreSuggestions, String> {
+  tauri::async_runtime::spawn_blocking(move || ignore_suggestions::suggest_gitignore_rules(&fs::decode_path(&project_path)))
+    .await
+    .map_err(|e| format!("Gitignore task failed: {}", e))?
+    .map_err(|e| format!("Failed to suggest gitignore rules: {}", e))
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn apply_gitignore_suggestions(project_path: String, patterns: Vec<String>) -> Result<AppliedIgnoreSuggestions, String> {
+  tauri::async_runtime::spawn_blocking(move || ignore_suggestions::apply_gitignore_suggestions(&fs::decode_path(&project_path), &patterns))
+    .await
+    .map_err(|e| format!("Gitignore task failed: {}", e))?
+    .map_err(|e| format!("Failed to apply gitignore suggestions: {}", e))
+}
+
+// Cache Commands
+#[tauri::command]
+async fn get_cache_stats(settings: State<'_, SettingsState>, cache: State<'_, FileCacheState>) -> Result<CacheStats, String> {
+  let enabled = settings.get().file_cache_enabled;
+  Ok(cache.0.lock().unwrap().stats(enabled))
+}
+
+#[tauri::command]
+async fn clear_file_cache(cache: State<'_, FileCacheState>) -> Result<(), String> {
+  cache.0.lock().unwrap().clear();
+  Ok(())
+}
+
+// Settings Commands
+#[tauri::command]
+async fn get_settings(settings: State<'_, SettingsState>) -> Result<AppSettings, String> {
+  Ok(settings.get())
+}
+
+#[tauri::command]
+async fn update_settings(settings: State<'_, SettingsState>, cache: State<'_, FileCacheState>, store: State<'_, CredentialStore>, metrics: State<'_, CommandMetrics>, new_settings: AppSettings) -> Result<(), String> {
+  store.set_file_fallback_enabled(new_settings.credential_file_fallback);
+  metrics.set_slow_threshold_ms(new_settings.slow_command_threshold_ms);
+  paths::set_include_absolute(new_settings.event_absolute_paths);
+  {
+    let mut cache = cache.0.lock().unwrap();
+    cache.set_max_bytes(new_settings.file_cache_max_bytes);
+    if !new_settings.file_cache_enabled {
+      cache.clear();
+    }
+  }
+
+  match settings.update(new_settings) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to save settings: {}", e)),
+  }
+}
+
+// Git Credentials Commands
+#[tauri::command]
+async fn get_credential_store_info(store: State<'_, CredentialStore>) -> Result<CredentialStoreInfo, CredentialError> {
+  Ok(store.info())
+}
+
+#[tauri::command]
+async fn unlock_credential_file_store(store: State<'_, CredentialStore>, passphrase: String) -> Result<(), CredentialError> {
+  store.unlock_file_store(&passphrase)
+}
+
+#[tauri::command]
+async fn export_credentials(store: State<'_, CredentialStore>, output_path: String, passphrase: String) -> Result<usize, CredentialError> {
+  store.export(&fs::decode_path(&output_path), &passphrase)
+}
+
+#[tauri::command]
+async fn import_credentials(store: State<'_, CredentialStore>, input_path: String, passphrase: String) -> Result<usize, CredentialError> {
+  store.import(&fs::decode_path(&input_path), &passphrase)
+}
+
+#[tauri::command]
+async fn save_credential_profile(store: State<'_, CredentialStore>, name: String, host_pattern: String, username: String, secret: String) -> Result<CredentialProfile, CredentialError> {
+  store.save_profile(&name, &host_pattern, &username, &secret)
+}
+
+#[tauri::command]
+async fn list_credential_profiles(store: State<'_, CredentialStore>) -> Result<Vec<CredentialProfileSummary>, CredentialError> {
+  Ok(store.list_profiles())
+}
+
+#[tauri::command]
+async fn delete_credential_profile(store: State<'_, CredentialStore>, name: String) -> Result<bool, CredentialError> {
+  store.delete_profile(&name)
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn get_git_credentials(store: State<'_, CredentialStore>, project_path: String) -> Result<Option<git::GitCredentials>, String> {
+  match git::get_git_credentials(&store, &fs::decode_path(&project_path)) {
+    Ok(credentials) => Ok(credentials),
+    Err(e) => Err(format!("Failed to get git credentials: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn set_git_credentials(store: State<'_, CredentialStore>, project_path: String, username: String, token: String, remote_url: String) -> Result<(), String> {
+  let credentials = git::GitCredentials {
+    username,
+    token,
+    remote_url,
+  };
+
+  match git::set_git_credentials(&store, &fs::decode_path(&project_path), credentials) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Failed to set git credentials: {}", e)),
+  }
+}
+
+#[cfg(feature = "git")]
+#[tauri::command]
+async fn delete_git_credentials(store: State<'_, CredentialStore>, project_path: String) -> Result<(), String> {
+  match git::delete_git_credentials(&store, &fs::decode_path(&project_path)) {
+    Ok(_) => Ok(()),
     Err(e) => Err(format!("Failed to delete git credentials: {}", e)),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[cfg(all(feature = "git", feature = "process"))]
+  use crate::test_support::{commit_all, init_repo, temp_dir, write};
+  #[cfg(all(feature = "git", feature = "process"))]
+  use serde_json::json;
+
+  #[cfg(all(feature = "git", feature = "process"))]
+  fn registered_commands() -> Vec<String> {
+    let modules = BackendModules::new(backend_modules()).expect("backend commands should not conflict");
+    modules.info().into_iter().flat_map(|module| module.commands).collect()
+  }
+
+  // The guard's list names git and process commands too
+  #[cfg(all(feature = "git", feature = "process"))]
+  #[test]
+  fn non_mutating_commands_are_registered() {
+    let registered = registered_commands();
+    for command in guard::NON_MUTATING_COMMANDS {
+      assert!(registered.iter().any(|c| c == command), "'{}' is listed as non-mutating but no module registers it", command);
+    }
+  }
+
+  #[test]
+  fn writing_commands_are_blocked_in_read_only_mode() {
+    for command in ["compute_project_fingerprint", "write_file_content", "update_settings", "save_file_as", "stage_file", "commit_changes", "pin_path", "clipboard_copy_paths"] {
+      assert!(guard::is_mutating(command), "'{}' writes state but is listed as non-mutating", command);
+    }
+    for command in ["mark_file_open", "mark_file_closed", "read_file_content", "get_project_fingerprint", "set_read_only_mode"] {
+      assert!(!guard::is_mutating(command), "'{}' only reads or changes in-memory state", command);
+    }
+  }
+
+  #[cfg(all(feature = "git", feature = "process"))]
+  #[test]
+  fn read_only_mode_refuses_writers_before_they_touch_disk() {
+    let (_repo_dir, root) = init_repo();
+    write(&root, "notes.txt", "notes\n");
+    let (_storage_dir, storage) = temp_dir();
+    let app = mock_app(&storage);
+    let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default()).build().unwrap();
+    let root_str = root.to_string_lossy().to_string();
+    let notes = root.join("notes.txt").to_string_lossy().to_string();
+    invoke(&webview, "set_read_only_mode", json!({"enabled": true})).unwrap();
+
+    for (command, args) in [
+      ("compute_project_fingerprint", json!({"projectPath": root_str, "contentHashes": false})),
+      ("write_file_content", json!({"filePath": notes, "content": "changed\n"})),
+    ] {
+      let error = invoke(&webview, command, args).expect_err(command);
+      assert_eq!(error["code"], "ReadOnlyMode", "'{}' was not refused: {}", command, error);
+    }
+    assert!(!storage.join("data").join(fingerprint::FINGERPRINT_DIR).exists());
+    assert_eq!(std::fs::read_to_string(&notes).unwrap(), "notes\n");
+
+    for (command, args) in [("mark_file_open", json!({"path": notes})), ("mark_file_closed", json!({"path": notes})), ("read_file_content", json!({"filePath": notes}))] {
+      if let Err(error) = invoke(&webview, command, args) {
+        panic!("'{}' was refused in read-only mode: {}", command, error);
+      }
+    }
+    app.state::<Arc<BackendModules>>().shutdown(app.handle());
+  }
+
+  // Left out of the invoke test: they open native dialogs or overwrite the OS clipboard
+  #[cfg(all(feature = "git", feature = "process"))]
+  const NOT_INVOKED: &[&str] = &["pick_open_path", "pick_save_path", "clipboard_copy_paths"];
+
+  #[cfg(all(feature = "git", feature = "process"))]
+  fn mock_app(storage: &Path) -> tauri::App<AppRuntime> {
+    // Credential commands must never reach the developer's real keychain
+    keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+    let modules = Arc::new(BackendModules::new(backend_modules()).expect("backend commands should not conflict"));
+    let dispatcher = modules.clone();
+    let app = tauri::test::mock_builder()
+      .invoke_handler(move |invoke| handle_invoke(&dispatcher, invoke))
+      .build(tauri::test::mock_context(tauri::test::noop_assets()))
+      .unwrap();
+    app.manage(RegisteredPlugins::default());
+    manage_state(app.handle(), StorageLayout::new(storage.join("config"), storage.join("data")), modules).unwrap();
+    // The notification plugin is not installed on the mock app
+    let settings = app.state::<SettingsState>();
+    let mut quiet = settings.get();
+    quiet.notifications.do_not_disturb = true;
+    settings.update(quiet).unwrap();
+    app
+  }
+
+  #[cfg(all(feature = "git", feature = "process"))]
+  fn invoke(webview: &tauri::WebviewWindow<AppRuntime>, command: &str, args: serde_json::Value) -> Result<tauri::ipc::InvokeResponseBody, serde_json::Value> {
+    tauri::test::get_ipc_response(
+      webview,
+      tauri::webview::InvokeRequest {
+        cmd: command.into(),
+        callback: tauri::ipc::CallbackFn(0),
+        error: tauri::ipc::CallbackFn(1),
+        url: if cfg!(any(windows, target_os = "android")) { "http://tauri.localhost" } else { "tauri://localhost" }.parse().unwrap(),
+        body: tauri::ipc::InvokeBody::Json(args),
+        headers: Default::default(),
+        invoke_key: tauri::test::INVOKE_KEY.to_string(),
+      },
+    )
+  }
+
+  // Commands may fail against the fixture; what must never happen is the frontend's argument
+  // names not reaching the command, or a registered command not being found
+  #[cfg(all(feature = "git", feature = "process"))]
+  #[test]
+  fn every_command_accepts_its_arguments() {
+    let (_repo_dir, root) = init_repo();
+    write(&root, "README.md", "# Fixture\n");
+    write(&root, "notes.txt", "notes\n");
+    write(&root, ".env", "MODE=dev\n");
+    write(&root, ".gitignore", "*.log\n");
+    write(&root, "src/main.rs", "fn main() {}\n");
+    write(&root, "nested/inner.txt", "inner\n");
+    for name in ["rename-me.txt", "move-me.txt", "delete-me.txt"] {
+      write(&root, name, "scratch\n");
+    }
+    commit_all(&root, "Initial commit");
+    write(&root, "untracked/new.txt", "new\n");
+    write(&root, "build.log", "log\n");
+    let (_other_dir, other) = temp_dir();
+    let (_storage_dir, storage) = temp_dir();
+
+    let app = mock_app(&storage);
+    let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default()).build().unwrap();
+    let root_str = root.to_string_lossy().to_string();
+    let at = |relative: &str| root.join(relative).to_string_lossy().to_string();
+    let out = |relative: &str| other.join(relative).to_string_lossy().to_string();
+    let settings = serde_json::to_value(app.state::<SettingsState>().get()).unwrap();
+
+    let cases: Vec<(&str, serde_json::Value)> = vec![
+        ("open_project", json!({"path": root_str})),
+        ("open_workspace", json!({"path": root_str})),
+        ("get_activity_feed", json!({"projectPath": root_str})),
+        ("create_plan", json!({"projectPath": root_str, "steps": [{"description": "Check the build"}]})),
+        ("update_plan_step", json!({"planId": "missing-plan", "stepIndex": 0, "status": "running"})),
+        ("get_plan", json!({"planId": "missing-plan"})),
+        ("list_plans", json!({"projectPath": root_str})),
+        ("cancel_plan", json!({"planId": "missing-plan"})),
+        ("create_project_from_template", json!({"templateUrl": out("missing-template"), "targetPath": out("from-template"), "projectName": "from-template"})),
+        ("cancel_operation", json!({"operationId": "missing-operation"})),
+        ("get_shutdown_blockers", json!({})),
+        ("get_previous_session_leftovers", json!({})),
+        ("notify_user", json!({"title": "Title", "body": "Body"})),
+        ("get_last_bootstrap_timings", json!({"projectPath": root_str})),
+        ("get_warmup_status", json!({"projectPath": root_str})),
+        ("pin_path", json!({"projectPath": root_str, "path": at("notes.txt")})),
+        ("unpin_path", json!({"projectPath": root_str, "path": at("notes.txt")})),
+        ("list_pinned", json!({"projectPath": root_str})),
+        ("reorder_pinned", json!({"projectPath": root_str, "order": [at("notes.txt")]})),
+        ("list_env_profiles", json!({"projectPath": root_str})),
+        ("save_env_profile", json!({"projectPath": root_str, "name": "dev", "vars": {"MODE": "dev"}, "secretKeys": []})),
+        ("delete_env_profile", json!({"projectPath": root_str, "name": "dev"})),
+        ("resolve_env_profile", json!({"projectPath": root_str, "name": "dev"})),
+        ("set_file_tags", json!({"projectPath": root_str, "path": at("notes.txt"), "tags": ["todo"]})),
+        ("get_file_tags", json!({"projectPath": root_str, "path": at("notes.txt")})),
+        ("list_tags", json!({"projectPath": root_str})),
+        ("find_files_by_tag", json!({"projectPath": root_str, "tag": "todo"})),
+        ("list_workspaces", json!({})),
+        ("update_draft", json!({"path": at("notes.txt"), "content": "draft"})),
+        ("discard_draft", json!({"path": at("notes.txt")})),
+        ("get_drafts", json!({"projectPath": root_str})),
+        ("set_watch_mode", json!({"root": root_str, "mode": "native"})),
+        ("duplicate_workspace", json!({"sourceRoot": root_str, "targetRoot": out("copy"), "includeGit": false, "respectGitignore": true})),
+        ("get_watcher_stats", json!({})),
+        ("get_file_outline", json!({"path": at("src/main.rs")})),
+        ("query_workspace_symbols", json!({"root": root_str, "query": "main"})),
+        ("build_word_index", json!({"root": root_str})),
+        ("query_word_index", json!({"root": root_str, "prefix": "he"})),
+        ("get_word_index_stats", json!({"root": root_str})),
+        ("get_project_manifest", json!({"projectPath": root_str})),
+        ("run_diagnostics", json!({})),
+        ("export_diagnostics", json!({"outputPath": out("diagnostics.json")})),
+        ("get_command_metrics", json!({})),
+        ("reset_command_metrics", json!({})),
+        ("record_command_timings", json!({"timings": [{"command": "list_directory", "duration_ms": 1.5, "failed": false, "scope": ""}]})),
+        ("list_backend_modules", json!({})),
+        ("read_response_chunk", json!({"handle": "missing-handle", "index": 0})),
+        ("stream_response", json!({"handle": "missing-handle"})),
+        ("release_response", json!({"handle": "missing-handle"})),
+        ("get_file_summary", json!({"path": at("README.md"), "mode": "head", "lines": 5})),
+        ("get_asset_metadata", json!({"path": at("README.md")})),
+        ("get_assets_metadata", json!({"paths": [at("README.md")]})),
+        ("get_files_summaries", json!({"paths": [at("README.md")], "mode": "header", "budgetBytes": 4096})),
+        ("read_file_lines", json!({"path": at("README.md"), "startLine": 1, "endLine": 1})),
+        ("create_checkpoint", json!({"projectPath": root_str, "label": "start"})),
+        ("list_checkpoints", json!({"projectPath": root_str})),
+        ("diff_checkpoints", json!({"projectPath": root_str, "fromCheckpointId": "missing-checkpoint"})),
+        ("diff_checkpoint_file", json!({"projectPath": root_str, "checkpointId": "missing-checkpoint", "filePath": at("README.md")})),
+        ("compute_project_fingerprint", json!({"projectPath": root_str, "contentHashes": false})),
+        ("compare_project_fingerprint", json!({"projectPath": root_str, "previousFingerprint": "{}"})),
+        ("get_project_fingerprint", json!({"projectPath": root_str})),
+        ("list_directory", json!({"path": root_str})),
+        ("probe_paths", json!({"paths": [root_str, at("missing")]})),
+        ("create_file", json!({"filePath": at("created.txt"), "content": "created"})),
+        ("create_directory", json!({"dirPath": at("created-dir")})),
+        ("rename_path", json!({"from": at("rename-me.txt"), "to": at("renamed.txt"), "gitAware": false, "projectPath": root_str})),
+        ("find_file_references", json!({"projectPath": root_str, "filePath": at("src/main.rs")})),
+        ("bulk_rename", json!({"root": root_str, "matchPattern": "*.md", "renameTemplate": "{name}", "dryRun": true})),
+        ("flatten_directory", json!({"dir": at("nested"), "conflictStrategy": "skip", "dryRun": true})),
+        ("partition_directory", json!({"dir": at("nested"), "rules": [], "dryRun": true})),
+        ("delete_path", json!({"path": at("delete-me.txt")})),
+        ("preview_delete", json!({"path": at("nested")})),
+        ("mark_file_open", json!({"path": at("notes.txt")})),
+        ("mark_file_closed", json!({"path": at("notes.txt")})),
+        ("move_path", json!({"from": at("move-me.txt"), "to": at("nested/move-me.txt"), "gitAware": false, "projectPath": root_str})),
+        ("read_file_content", json!({"filePath": at("notes.txt")})),
+        ("write_file_content", json!({"filePath": at("notes.txt"), "content": "notes\n"})),
+        ("save_with_hooks", json!({"filePath": at("notes.txt"), "content": "notes\n"})),
+        ("save_file_as", json!({"sourcePath": at("notes.txt"), "targetPath": at("notes-copy.txt"), "content": "notes\n", "overwrite": false})),
+        ("get_local_history", json!({"filePath": at("notes.txt")})),
+        ("read_local_history_version", json!({"filePath": at("notes.txt"), "versionId": "missing-version"})),
+        ("restore_local_history_version", json!({"filePath": at("notes.txt"), "versionId": "missing-version"})),
+        ("get_cache_stats", json!({})),
+        ("clear_file_cache", json!({})),
+        ("clipboard_get_status", json!({})),
+        ("clipboard_paste", json!({"targetDir": out("pasted")})),
+        ("create_scratch_file", json!({"projectPath": root_str, "languageId": "rust"})),
+        ("list_scratch_files", json!({})),
+        ("promote_scratch_file", json!({"scratchId": "missing-scratch", "targetPath": at("promoted.rs")})),
+        ("delete_scratch_file", json!({"scratchId": "missing-scratch"})),
+        ("diff_files", json!({"leftPath": at("README.md"), "rightPath": at("notes.txt")})),
+        ("diff_texts", json!({"left": "a\n", "right": "b\n"})),
+        ("analyze_line_endings", json!({"paths": [root_str]})),
+        ("normalize_line_endings", json!({"paths": [root_str], "dryRun": true})),
+        ("check_text_policy", json!({"projectPath": root_str})),
+        ("fix_text_policy", json!({"projectPath": root_str, "dryRun": true})),
+        ("list_env_files", json!({"projectPath": root_str})),
+        ("read_env_file", json!({"path": at(".env")})),
+        ("set_env_value", json!({"path": at(".env"), "key": "MODE", "value": "test"})),
+        ("remove_env_key", json!({"path": at(".env"), "key": "MODE"})),
+        ("compare_env_files", json!({"pathA": at(".env"), "pathB": at(".env")})),
+        ("get_storage_info", json!({})),
+        ("compact_storage", json!({})),
+        ("get_credential_store_info", json!({})),
+        ("save_credential_profile", json!({"name": "invoke-test", "hostPattern": "example.invalid", "username": "user", "secret": "secret"})),
+        ("set_git_credentials", json!({"projectPath": root_str, "username": "user", "token": "token", "remoteUrl": "https://example.invalid/repo.git"})),
+        ("save_git_credentials_cmd", json!({"projectPath": root_str, "username": "user", "password": "secret"})),
+        ("export_credentials", json!({"outputPath": out("credentials.bin"), "passphrase": "test passphrase"})),
+        ("import_credentials", json!({"inputPath": out("credentials.bin"), "passphrase": "test passphrase"})),
+        ("delete_credential_profile", json!({"name": "invoke-test"})),
+        ("delete_git_credentials", json!({"projectPath": root_str})),
+        ("clear_git_credentials_cmd", json!({"projectPath": root_str, "username": "user"})),
+        ("list_credential_profiles", json!({})),
+        ("unlock_credential_file_store", json!({"passphrase": "test passphrase"})),
+        ("get_settings", json!({})),
+        ("update_settings", json!({"newSettings": settings})),
+        ("save_snippet", json!({"scope": "project", "projectPath": root_str, "snippet": {"title": "Hello", "language": "rust", "content": "fn hello() {}"}})),
+        ("list_snippets", json!({"projectPath": root_str})),
+        ("get_snippet", json!({"id": "missing-snippet", "projectPath": root_str})),
+        ("delete_snippet", json!({"id": "missing-snippet", "projectPath": root_str})),
+        ("export_snippets", json!({"scope": "project", "projectPath": root_str, "outputPath": out("snippets.json")})),
+        ("import_snippets", json!({"scope": "project", "projectPath": root_str, "inputPath": out("snippets.json")})),
+        ("get_read_only_mode", json!({})),
+        ("set_read_only_mode", json!({"enabled": false})),
+        ("get_git_status", json!({"projectPath": root_str})),
+        ("stage_file", json!({"projectPath": root_str, "filePath": "notes.txt"})),
+        ("unstage_file", json!({"projectPath": root_str, "filePath": "notes.txt"})),
+        ("mark_intent_to_add", json!({"projectPath": root_str, "paths": ["untracked/new.txt"]})),
+        ("unmark_intent_to_add", json!({"projectPath": root_str, "paths": ["untracked/new.txt"]})),
+        ("commit_changes", json!({"projectPath": root_str, "message": "Update notes", "include": "all_tracked"})),
+        ("list_git_identities", json!({})),
+        ("save_git_identity", json!({"label": "work", "name": "Test User", "email": "test@example.com"})),
+        ("get_effective_identity", json!({"projectPath": root_str})),
+        ("apply_identity", json!({"projectPath": root_str, "label": "work", "scope": "local"})),
+        ("restore_deleted_files", json!({"projectPath": root_str})),
+        ("get_directory_status_rollup", json!({"projectPath": root_str})),
+        ("expand_untracked_directory", json!({"projectPath": root_str, "dirPath": "untracked"})),
+        ("checkout_branch", json!({"projectPath": root_str, "branchName": "feature"})),
+        ("check_tree_portability", json!({"projectPath": root_str})),
+        ("create_branch", json!({"projectPath": root_str, "branchName": "feature", "checkout": false, "checkRemote": false})),
+        ("checkout_remote_branch", json!({"projectPath": root_str, "remoteName": "origin", "branchName": "missing"})),
+        ("stash_list", json!({"projectPath": root_str})),
+        ("drop_stale_auto_stashes", json!({"projectPath": root_str})),
+        ("get_recent_branches", json!({"projectPath": root_str})),
+        ("get_branches_overview", json!({"projectPath": root_str})),
+        ("delete_merged_branches", json!({"projectPath": root_str, "dryRun": true})),
+        ("list_deleted_branches", json!({"projectPath": root_str})),
+        ("restore_deleted_branch", json!({"projectPath": root_str, "name": "missing-branch"})),
+        ("get_recent_commits", json!({"projectPath": root_str, "limit": 5})),
+        ("list_branches", json!({"projectPath": root_str})),
+        ("get_commit_details", json!({"projectPath": root_str, "commitHash": "HEAD"})),
+        ("list_tracked_files", json!({"projectPath": root_str})),
+        ("get_sparse_checkout", json!({"projectPath": root_str})),
+        ("set_sparse_checkout", json!({"projectPath": root_str, "patterns": ["src"], "coneMode": true})),
+        ("disable_sparse_checkout", json!({"projectPath": root_str})),
+        ("diff_workdir_to_ref", json!({"projectPath": root_str, "gitRef": "HEAD"})),
+        ("get_file_diff", json!({"projectPath": root_str, "filePath": "README.md"})),
+        ("get_file_diff_both", json!({"projectPath": root_str, "filePath": "README.md"})),
+        ("get_changed_paths_vs_ref", json!({"projectPath": root_str, "baseRef": "HEAD"})),
+        ("finalize_merged_file", json!({"projectPath": root_str, "filePath": "notes.txt", "mergedContent": "notes\n"})),
+        ("can_continue_operation", json!({"projectPath": root_str})),
+        ("get_worktree_diff_stats", json!({"projectPath": root_str})),
+        ("add_git_note", json!({"projectPath": root_str, "commitHash": "HEAD", "note": "reviewed"})),
+        ("get_git_note", json!({"projectPath": root_str, "commitHash": "HEAD"})),
+        ("list_git_notes", json!({"projectPath": root_str})),
+        ("init_git_repo", json!({"projectPath": out("fresh")})),
+        ("init_git_repo_enhanced", json!({"projectPath": out("fresh-enhanced")})),
+        ("get_git_config", json!({"projectPath": root_str})),
+        ("set_git_config", json!({"projectPath": root_str, "name": "Test User", "email": "test@example.com", "scope": "local"})),
+        ("is_git_repository", json!({"projectPath": root_str})),
+        ("git_push", json!({"projectPath": root_str, "runPrepushChecks": false, "skipChecks": true})),
+        ("get_last_prepush_run", json!({"projectPath": root_str})),
+        ("get_pending_pushes", json!({"projectPath": root_str})),
+        ("cancel_pending_push", json!({"id": "missing-push"})),
+        ("scan_for_secrets", json!({"projectPath": root_str, "scope": "staged"})),
+        ("git_pull", json!({"projectPath": root_str})),
+        ("get_git_credentials", json!({"projectPath": root_str})),
+        ("get_repo_size_info", json!({"projectPath": root_str})),
+        ("run_repo_maintenance", json!({"projectPath": root_str})),
+        ("check_repository_health", json!({"projectPath": root_str, "runFsck": false})),
+        ("repair_repository", json!({"projectPath": root_str, "action": "remove_stale_index_lock"})),
+        ("validate_branch_name", json!({"name": "feature/login"})),
+        ("suggest_branch_name", json!({"projectPath": root_str, "description": "Add login form"})),
+        ("list_git_hooks", json!({"projectPath": root_str})),
+        ("install_managed_hook", json!({"projectPath": root_str, "hookName": "pre-commit", "scriptContent": "#!/bin/sh\nexit 0\n"})),
+        ("uninstall_managed_hook", json!({"projectPath": root_str, "hookName": "pre-commit"})),
+        ("git_clone", json!({"url": out("missing-remote"), "targetPath": out("clone")})),
+        ("get_clone_info", json!({"projectPath": root_str})),
+        ("get_remote_host_info", json!({"projectPath": root_str})),
+        ("build_remote_file_url", json!({"projectPath": root_str, "filePath": "README.md"})),
+        ("get_line_provenance", json!({"projectPath": root_str, "filePath": "README.md", "startLine": 1, "endLine": 1})),
+        ("export_review_bundle", json!({"projectPath": root_str, "scope": "worktree"})),
+        ("get_review_manifest_hash", json!({"projectPath": root_str, "scope": "worktree"})),
+        ("backfill_clone", json!({"projectPath": root_str})),
+        ("explain_ignore", json!({"projectPath": root_str, "path": at("build.log")})),
+        ("parse_gitignore", json!({"path": at(".gitignore")})),
+        ("update_gitignore", json!({"path": at(".gitignore"), "edits": []})),
+        ("suggest_gitignore_rules", json!({"projectPath": root_str})),
+        ("apply_gitignore_suggestions", json!({"projectPath": root_str, "patterns": []})),
+        ("run_allowed_command", json!({"projectPath": root_str, "commandLine": "git --version"})),
+        ("tail_file", json!({"path": at("notes.txt"), "fromEndBytes": 0, "follow": false, "tailId": "tail-1"})),
+        ("stop_tail", json!({"tailId": "tail-1"})),
+        ("close_workspace", json!({"path": root_str})),
+        ("close_project", json!({"path": root_str})),
+    ];
+
+    let mut expected: Vec<String> = cases.iter().map(|(command, _)| command.to_string()).chain(NOT_INVOKED.iter().map(|c| c.to_string())).collect();
+    let mut registered = registered_commands();
+    expected.sort();
+    registered.sort();
+    assert_eq!(registered, expected, "every registered command needs an entry in the invoke table");
+
+    for (command, args) in cases {
+      if let Err(error) = invoke(&webview, command, args) {
+        let message = error.to_string().to_lowercase();
+        assert!(!message.contains("invalid args"), "'{}' rejected its arguments: {}", command, error);
+        assert!(!message.contains(&format!("command {} not found", command)), "'{}' was not dispatched: {}", command, error);
+      }
+    }
+    app.state::<Arc<BackendModules>>().shutdown(app.handle());
+  }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::fs::{display_path, is_binary_file, write_atomic};
+use crate::gitignore::IgnoreMatcher;
+
+// Larger files are almost certainly generated; leave them alone
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    pub(crate) fn bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+            LineEnding::Cr => b"\r",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LineEndingReport {
+    pub path: String,
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+    /// Most frequent terminator; None for files without any
+    pub dominant: Option<LineEnding>,
+    pub mixed: bool,
+    /// Why the file was not analysed (binary, too large, unreadable)
+    pub skipped: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NormalizeResult {
+    pub path: String,
+    /// "changed", "unchanged" or "skipped"; "changed" is reported for dry runs too
+    pub status: String,
+    pub target: Option<LineEnding>,
+    pub reason: Option<String>,
+}
+
+pub(crate) fn count(bytes: &[u8]) -> (usize, usize, usize) {
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    (lf, crlf, cr)
+}
+
+fn dominant(lf: usize, crlf: usize, cr: usize) -> Option<LineEnding> {
+    if lf + crlf + cr == 0 {
+        return None;
+    }
+    // Ties go to LF, then CRLF
+    Some(if lf >= crlf && lf >= cr { LineEnding::Lf } else if crlf >= cr { LineEnding::Crlf } else { LineEnding::Cr })
+}
+
+pub(crate) fn convert(bytes: &[u8], target: LineEnding) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 32);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                out.extend_from_slice(target.bytes());
+                i += 1;
+            }
+            b'\r' | b'\n' => out.extend_from_slice(target.bytes()),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Read a file for line-ending work, or say why it was skipped. A UTF-8 BOM is kept
+/// untouched since only terminator bytes are rewritten; UTF-16 would need re-encoding.
+fn read_text(path: &Path) -> Result<Vec<u8>, String> {
+    let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(format!("larger than {} bytes", MAX_FILE_BYTES));
+    }
+    if is_binary_file(path).map_err(|e| e.to_string())? {
+        return Err("binary file".to_string());
+    }
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return Err("UTF-16 files are not supported".to_string());
+    }
+    Ok(bytes)
+}
+
+/// Expand directories into their files, dropping gitignored ones unless `include_ignored`
+pub fn collect_files(paths: &[PathBuf], include_ignored: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let matcher = if include_ignored { None } else { IgnoreMatcher::new(path).ok() };
+        let ignored = |p: &Path, is_dir: bool| matcher.as_ref().map(|m| m.is_ignored(p, is_dir)).unwrap_or(false);
+
+        if !path.is_dir() {
+            if !ignored(path, false) {
+                files.push(path.clone());
+            }
+            continue;
+        }
+        let walker = WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git" && !ignored(e.path(), e.file_type().is_dir()));
+        files.extend(walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()).map(|e| e.into_path()));
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+pub fn analyze_line_endings(files: &[PathBuf]) -> Vec<LineEndingReport> {
+    files
+        .iter()
+        .map(|path| match read_text(path) {
+            Ok(bytes) => {
+                let (lf, crlf, cr) = count(&bytes);
+                LineEndingReport {
+                    path: display_path(path),
+                    lf,
+                    crlf,
+                    cr,
+                    dominant: dominant(lf, crlf, cr),
+                    mixed: [lf, crlf, cr].iter().filter(|&&n| n > 0).count() > 1,
+                    skipped: None,
+                }
+            }
+            Err(reason) => LineEndingReport {
+                path: display_path(path),
+                lf: 0,
+                crlf: 0,
+                cr: 0,
+                dominant: None,
+                mixed: false,
+                skipped: Some(reason),
+            },
+        })
+        .collect()
+}
+
+/// Target for one file: the explicit one, else `.editorconfig`'s `end_of_line`, else the file's own dominant style
+fn resolve_target(path: &Path, explicit: Option<LineEnding>, bytes: &[u8]) -> Option<LineEnding> {
+    explicit
+        .or_else(|| match crate::editorconfig::properties_for(path).get("end_of_line").map(String::as_str) {
+            Some("lf") => Some(LineEnding::Lf),
+            Some("crlf") => Some(LineEnding::Crlf),
+            Some("cr") => Some(LineEnding::Cr),
+            _ => None,
+        })
+        .or_else(|| {
+            let (lf, crlf, cr) = count(bytes);
+            dominant(lf, crlf, cr)
+        })
+}
+
+fn normalize_one(path: &Path, target: Option<LineEnding>, dry_run: bool) -> NormalizeResult {
+    let result = |status: &str, target: Option<LineEnding>, reason: Option<String>| NormalizeResult {
+        path: display_path(path),
+        status: status.to_string(),
+        target,
+        reason,
+    };
+
+    let bytes = match read_text(path) {
+        Ok(bytes) => bytes,
+        Err(reason) => return result("skipped", None, Some(reason)),
+    };
+    let Some(target) = resolve_target(path, target, &bytes) else {
+        return result("unchanged", None, Some("no line terminators".to_string()));
+    };
+
+    let converted = convert(&bytes, target);
+    if converted == bytes {
+        return result("unchanged", Some(target), None);
+    }
+    if !dry_run {
+        if let Err(e) = write_atomic(path, &converted) {
+            return result("skipped", Some(target), Some(e.to_string()));
+        }
+    }
+    result("changed", Some(target), None)
+}
+
+/// Rewrite line terminators to `target` (or each file's configured/dominant style)
+pub fn normalize_line_endings(files: &[PathBuf], target: Option<LineEnding>, dry_run: bool) -> Vec<NormalizeResult> {
+    files.iter().map(|path| normalize_one(path, target, dry_run)).collect()
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use globset::{Glob, GlobSetBuilder};
+
+use crate::blobs::{hash_bytes, BlobStore};
+use crate::fs::{display_path, FsError, FsResult};
+
+pub(crate) const HISTORY_DIR: &str = "local-history";
+const INDEX_FILE: &str = "index.json";
+
+/// Limits for the local history kept of IDE-written files
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LocalHistoryPolicy {
+    pub enabled: bool,
+    pub max_versions_per_file: usize,
+    pub max_age_days: u64,
+    /// Cap on all stored versions together; the oldest go first
+    pub max_total_bytes: u64,
+    /// Files larger than this are not versioned
+    pub max_file_bytes: u64,
+    /// Globs matched against the file name and the full path
+    pub excluded_patterns: Vec<String>,
+}
+
+impl Default for LocalHistoryPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_versions_per_file: 50,
+            max_age_days: 14,
+            max_total_bytes: 200 * 1024 * 1024,
+            max_file_bytes: 2 * 1024 * 1024,
+            excluded_patterns: [
+                "*.lock", "package-lock.json", "pnpm-lock.yaml", "*.min.js", "*.map",
+                "*.png", "*.jpg", "*.jpeg", "*.gif", "*.ico", "*.pdf", "*.zip", "*.wasm",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredVersion {
+    id: String,
+    timestamp: i64,
+    size: u64,
+    hash: String,
+    command: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LocalHistoryVersion {
+    pub id: String,
+    pub timestamp: i64,
+    pub size: u64,
+    /// Command whose write replaced this version
+    pub command: String,
+    /// Lines added and removed going from this version to the current file; None when either is not text
+    pub insertions: Option<usize>,
+    pub deletions: Option<usize>,
+}
+
+/// Earlier contents of files the IDE overwrote, kept in the shared blob store
+pub struct LocalHistoryStore {
+    dir: PathBuf,
+    blobs: BlobStore,
+    entries: Mutex<HashMap<String, Vec<StoredVersion>>>,
+}
+
+fn is_excluded(path: &Path, patterns: &[String]) -> bool {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = Glob::new(pattern) {
+            builder.add(glob);
+        }
+    }
+    let Ok(set) = builder.build() else { return false };
+    set.is_match(path) || path.file_name().map(|name| set.is_match(name)).unwrap_or(false)
+}
+
+fn diff_stats(old: &[u8], new: &[u8]) -> Option<(usize, usize)> {
+    let (old, new) = (std::str::from_utf8(old).ok()?, std::str::from_utf8(new).ok()?);
+    let diff = TextDiff::from_lines(old, new);
+    let mut stats = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => stats.0 += 1,
+            ChangeTag::Delete => stats.1 += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    Some(stats)
+}
+
+impl LocalHistoryStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let dir = data_dir.join(HISTORY_DIR);
+        let entries = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { dir, blobs: BlobStore::load(data_dir), entries: Mutex::new(entries) }
+    }
+
+    fn save(&self, entries: &HashMap<String, Vec<StoredVersion>>) -> FsResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        let raw = serde_json::to_string(entries).map_err(|e| FsError::Io { message: e.to_string() })?;
+        crate::fs::write_atomic(&self.dir.join(INDEX_FILE), raw.as_bytes())
+    }
+
+    /// Keep the current contents of `path` before `command` overwrites it. Missing, excluded
+    /// and oversized files are skipped, as is a write that follows an unchanged version.
+    pub fn record(&self, path: &Path, command: &str, policy: &LocalHistoryPolicy) -> FsResult<()> {
+        if !policy.enabled || is_excluded(path, &policy.excluded_patterns) {
+            return Ok(());
+        }
+        let Ok(metadata) = fs::metadata(path) else { return Ok(()) };
+        if !metadata.is_file() || metadata.len() > policy.max_file_bytes {
+            return Ok(());
+        }
+        let content = fs::read(path)?;
+        let hash = hash_bytes(&content);
+
+        let mut entries = self.entries.lock().unwrap();
+        let versions = entries.entry(display_path(path)).or_default();
+        if versions.last().map(|v| v.hash == hash).unwrap_or(false) {
+            return Ok(());
+        }
+
+        self.blobs.write(&hash, &content)?;
+        let now = chrono::Utc::now();
+        versions.push(StoredVersion {
+            id: format!("{}-{}", now.timestamp_millis(), &hash[..12]),
+            timestamp: now.timestamp(),
+            size: content.len() as u64,
+            hash,
+            command: command.to_string(),
+        });
+        let excess = versions.len().saturating_sub(policy.max_versions_per_file);
+        versions.drain(..excess);
+        self.save(&entries)
+    }
+
+    /// Versions of `path`, newest first, with diff stats against its current contents
+    pub fn versions(&self, path: &Path) -> Vec<LocalHistoryVersion> {
+        let stored = self.entries.lock().unwrap().get(&display_path(path)).cloned().unwrap_or_default();
+        let current = fs::read(path).unwrap_or_default();
+        stored
+            .into_iter()
+            .rev()
+            .map(|version| {
+                let stats = self.blobs.read(&version.hash).ok().and_then(|old| diff_stats(&old, &current));
+                LocalHistoryVersion {
+                    id: version.id,
+                    timestamp: version.timestamp,
+                    size: version.size,
+                    command: version.command,
+                    insertions: stats.map(|(inserted, _)| inserted),
+                    deletions: stats.map(|(_, deleted)| deleted),
+                }
+            })
+            .collect()
+    }
+
+    pub fn read_version(&self, path: &Path, version_id: &str) -> FsResult<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        let version = entries
+            .get(&display_path(path))
+            .and_then(|versions| versions.iter().find(|v| v.id == version_id))
+            .ok_or(FsError::NotFound)?;
+        self.blobs.read(&version.hash)
+    }
+
+    /// Drop versions past the age and size limits. Their blobs are left to the shared sweep.
+    pub fn prune(&self, policy: &LocalHistoryPolicy) -> FsResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let cutoff = chrono::Utc::now().timestamp() - (policy.max_age_days * 24 * 60 * 60) as i64;
+        let before: usize = entries.values().map(Vec::len).sum();
+
+        for versions in entries.values_mut() {
+            versions.retain(|v| v.timestamp >= cutoff);
+        }
+
+        // Evict oldest-first across all files until the distinct blobs fit
+        let mut references: HashMap<String, (usize, u64)> = HashMap::new();
+        for version in entries.values().flatten() {
+            references.entry(version.hash.clone()).or_insert((0, version.size)).0 += 1;
+        }
+        let mut total: u64 = references.values().map(|(_, size)| size).sum();
+        let mut oldest: Vec<(i64, String, String, String)> = entries
+            .iter()
+            .flat_map(|(file, versions)| versions.iter().map(move |v| (v.timestamp, file.clone(), v.id.clone(), v.hash.clone())))
+            .collect();
+        oldest.sort();
+        for (_, file, id, hash) in oldest {
+            if total <= policy.max_total_bytes {
+                break;
+            }
+            if let Some(versions) = entries.get_mut(&file) {
+                versions.retain(|v| v.id != id);
+            }
+            if let Some(reference) = references.get_mut(&hash) {
+                reference.0 -= 1;
+                if reference.0 == 0 {
+                    total = total.saturating_sub(reference.1);
+                }
+            }
+        }
+        entries.retain(|_, versions| !versions.is_empty());
+
+        self.save(&entries)?;
+        Ok(before - entries.values().map(Vec::len).sum::<usize>())
+    }
+
+    /// Hashes of every blob a kept version refers to
+    pub fn referenced_blobs(&self) -> HashSet<String> {
+        self.entries.lock().unwrap().values().flatten().map(|v| v.hash.clone()).collect()
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use walkdir::WalkDir;
+
+const MANIFEST_NAMES: [&str; 4] = ["package.json", "Cargo.toml", "pyproject.toml", "go.mod"];
+
+// Manifests nested deeper than this are not considered part of the dashboard
+const MAX_SEARCH_DEPTH: usize = 3;
+
+const SKIPPED_DIRS: [&str; 8] = ["node_modules", "target", ".git", "dist", "build", ".venv", "venv", "vendor"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestScript {
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestDependency {
+    pub name: String,
+    pub requirement: String,
+    pub dev: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ProjectManifest {
+    /// "npm", "cargo", "python" or "go"
+    pub kind: String,
+    pub path: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub scripts: Vec<ManifestScript>,
+    pub dependencies: Vec<ManifestDependency>,
+    pub workspace_members: Vec<String>,
+    /// Set instead of failing the whole command when this file could not be parsed
+    pub error: Option<String>,
+}
+
+/// Parsed manifests cached by path and modification time
+#[derive(Default)]
+pub struct ManifestCache(Mutex<HashMap<PathBuf, (SystemTime, ProjectManifest)>>);
+
+impl ManifestCache {
+    /// Find and parse every known manifest in the project, reusing unchanged parses
+    pub fn get_project_manifests(&self, project_path: &Path) -> Vec<ProjectManifest> {
+        let mut manifests = Vec::new();
+
+        let walker = WalkDir::new(project_path)
+            .max_depth(MAX_SEARCH_DEPTH)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                !(e.file_type().is_dir() && SKIPPED_DIRS.iter().any(|d| *d == name))
+            });
+
+        for entry in walker.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy();
+            if !entry.file_type().is_file() || !MANIFEST_NAMES.iter().any(|m| *m == name) {
+                continue;
+            }
+
+            let path = entry.path().to_path_buf();
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+
+            if let Some(modified) = modified {
+                if let Some((cached_at, cached)) = self.0.lock().unwrap().get(&path) {
+                    if *cached_at == modified {
+                        manifests.push(cached.clone());
+                        continue;
+                    }
+                }
+            }
+
+            let manifest = parse_manifest(&path);
+            if let Some(modified) = modified {
+                self.0.lock().unwrap().insert(path, (modified, manifest.clone()));
+            }
+            manifests.push(manifest);
+        }
+
+        manifests.sort_by(|a, b| a.path.cmp(&b.path));
+        manifests
+    }
+}
+
+fn parse_manifest(path: &Path) -> ProjectManifest {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let kind = match file_name.as_str() {
+        "package.json" => "npm",
+        "Cargo.toml" => "cargo",
+        "pyproject.toml" => "python",
+        _ => "go",
+    };
+
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| anyhow!(e))
+        .and_then(|raw| match kind {
+            "npm" => parse_package_json(&raw),
+            "cargo" => parse_cargo_toml(&raw),
+            "python" => parse_pyproject(&raw),
+            _ => Ok(parse_go_mod(&raw)),
+        });
+
+    let mut manifest = match parsed {
+        Ok(manifest) => manifest,
+        Err(e) => ProjectManifest { error: Some(e.to_string()), ..Default::default() },
+    };
+    manifest.kind = kind.to_string();
+    manifest.path = path.to_string_lossy().to_string();
+    manifest
+}
+
+fn json_str(value: &serde_json::Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+fn parse_package_json(raw: &str) -> Result<ProjectManifest> {
+    let json: serde_json::Value = serde_json::from_str(raw)?;
+
+    let scripts = json
+        .get("scripts")
+        .and_then(|s| s.as_object())
+        .map(|s| {
+            s.iter()
+                .map(|(name, cmd)| ManifestScript { name: name.clone(), command: cmd.as_str().unwrap_or("").to_string() })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut dependencies = Vec::new();
+    for (key, dev) in [("dependencies", false), ("devDependencies", true)] {
+        if let Some(deps) = json.get(key).and_then(|d| d.as_object()) {
+            for (name, requirement) in deps {
+                dependencies.push(ManifestDependency {
+                    name: name.clone(),
+                    requirement: requirement.as_str().unwrap_or("").to_string(),
+                    dev,
+                });
+            }
+        }
+    }
+
+    let workspace_members = match json.get("workspaces") {
+        Some(serde_json::Value::Array(items)) => items.iter().filter_map(|v| v.as_str().map(String::from)).collect(),
+        Some(serde_json::Value::Object(obj)) => obj
+            .get("packages")
+            .and_then(|p| p.as_array())
+            .map(|items| items.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    Ok(ProjectManifest {
+        name: json_str(&json, "name"),
+        version: json_str(&json, "version"),
+        description: json_str(&json, "description"),
+        scripts,
+        dependencies,
+        workspace_members,
+        ..Default::default()
+    })
+}
+
+fn toml_str(table: Option<&toml::Value>, key: &str) -> Option<String> {
+    table.and_then(|t| t.get(key)).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+/// Cargo dependency specs are either `"1.0"` or `{ version = "1.0", ... }`
+fn cargo_requirement(spec: &toml::Value) -> String {
+    match spec {
+        toml::Value::String(v) => v.clone(),
+        toml::Value::Table(t) => t
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or_else(|| t.get("path").and_then(|v| v.as_str()).map(|p| format!("path:{}", p)))
+            .or_else(|| t.get("git").and_then(|v| v.as_str()).map(|g| format!("git:{}", g)))
+            .or_else(|| t.get("workspace").and_then(|v| v.as_bool()).filter(|w| *w).map(|_| "workspace".to_string()))
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+fn parse_cargo_toml(raw: &str) -> Result<ProjectManifest> {
+    let doc: toml::Value = toml::from_str(raw)?;
+    let package = doc.get("package");
+
+    let mut dependencies = Vec::new();
+    for (key, dev) in [("dependencies", false), ("dev-dependencies", true), ("build-dependencies", true)] {
+        if let Some(deps) = doc.get(key).and_then(|d| d.as_table()) {
+            for (name, spec) in deps {
+                dependencies.push(ManifestDependency { name: name.clone(), requirement: cargo_requirement(spec), dev });
+            }
+        }
+    }
+
+    let workspace_members = doc
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|items| items.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    Ok(ProjectManifest {
+        name: toml_str(package, "name"),
+        version: toml_str(package, "version"),
+        description: toml_str(package, "description"),
+        dependencies,
+        workspace_members,
+        ..Default::default()
+    })
+}
+
+/// Split a PEP 508 requirement like `requests>=2.0; python_version>"3"` into name and spec
+fn split_requirement(requirement: &str) -> (String, String) {
+    let requirement = requirement.split(';').next().unwrap_or("").trim();
+    let end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(requirement.len());
+    (requirement[..end].to_string(), requirement[end..].trim().to_string())
+}
+
+fn parse_pyproject(raw: &str) -> Result<ProjectManifest> {
+    let doc: toml::Value = toml::from_str(raw)?;
+    let project = doc.get("project");
+    let poetry = doc.get("tool").and_then(|t| t.get("poetry"));
+    let meta = project.or(poetry);
+
+    let mut dependencies = Vec::new();
+    if let Some(deps) = project.and_then(|p| p.get("dependencies")).and_then(|d| d.as_array()) {
+        for dep in deps.iter().filter_map(|d| d.as_str()) {
+            let (name, requirement) = split_requirement(dep);
+            dependencies.push(ManifestDependency { name, requirement, dev: false });
+        }
+    }
+    if let Some(groups) = project.and_then(|p| p.get("optional-dependencies")).and_then(|d| d.as_table()) {
+        for dep in groups.values().filter_map(|g| g.as_array()).flatten().filter_map(|d| d.as_str()) {
+            let (name, requirement) = split_requirement(dep);
+            dependencies.push(ManifestDependency { name, requirement, dev: true });
+        }
+    }
+    if let Some(deps) = poetry.and_then(|p| p.get("dependencies")).and_then(|d| d.as_table()) {
+        for (name, spec) in deps.iter().filter(|(name, _)| name.as_str() != "python") {
+            dependencies.push(ManifestDependency { name: name.clone(), requirement: cargo_requirement(spec), dev: false });
+        }
+    }
+
+    let scripts = meta
+        .and_then(|m| m.get("scripts"))
+        .and_then(|s| s.as_table())
+        .map(|s| {
+            s.iter()
+                .map(|(name, cmd)| ManifestScript { name: name.clone(), command: cmd.as_str().unwrap_or("").to_string() })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ProjectManifest {
+        name: toml_str(meta, "name"),
+        version: toml_str(meta, "version"),
+        description: toml_str(meta, "description"),
+        scripts,
+        dependencies,
+        ..Default::default()
+    })
+}
+
+fn parse_go_mod(raw: &str) -> ProjectManifest {
+    let mut manifest = ProjectManifest::default();
+    let mut in_require = false;
+
+    for line in raw.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if in_require {
+            if line == ")" {
+                in_require = false;
+                continue;
+            }
+            push_go_requirement(&mut manifest, line);
+        } else if let Some(module) = line.strip_prefix("module ") {
+            manifest.name = Some(module.trim().to_string());
+        } else if let Some(version) = line.strip_prefix("go ") {
+            manifest.version = Some(format!("go {}", version.trim()));
+        } else if line == "require (" {
+            in_require = true;
+        } else if let Some(req) = line.strip_prefix("require ") {
+            push_go_requirement(&mut manifest, req);
+        }
+    }
+    manifest
+}
+
+fn push_go_requirement(manifest: &mut ProjectManifest, line: &str) {
+    let mut parts = line.split_whitespace();
+    if let (Some(name), Some(version)) = (parts.next(), parts.next()) {
+        manifest.dependencies.push(ManifestDependency {
+            name: name.to_string(),
+            requirement: version.to_string(),
+            dev: false,
+        });
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use git2::{Repository, RepositoryState};
+use serde::{Deserialize, Serialize};
+
+use crate::git::{require_workdir, GitIndexError};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConflictMarker {
+    /// 1-based
+    pub line: usize,
+    /// "ours", "base", "separator" or "theirs"
+    pub kind: String,
+}
+
+/// Conflict markers git writes: `<<<<<<<`, `|||||||` (diff3), `=======` and `>>>>>>>` at the
+/// start of a line. A bare `=======` only counts inside an open conflict, since Markdown and
+/// reStructuredText use the same line for headings.
+pub fn conflict_markers(text: &str) -> Vec<ConflictMarker> {
+    let is_marker = |line: &str, prefix: &str| {
+        line.strip_prefix(prefix).map(|rest| rest.is_empty() || rest.starts_with([' ', '\t'])).unwrap_or(false)
+    };
+    let mut markers = Vec::new();
+    let mut open = false;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let kind = if is_marker(line, "<<<<<<<") {
+            open = true;
+            "ours"
+        } else if is_marker(line, ">>>>>>>") {
+            open = false;
+            "theirs"
+        } else if is_marker(line, "|||||||") {
+            "base"
+        } else if open && line == "=======" {
+            "separator"
+        } else {
+            continue;
+        };
+        markers.push(ConflictMarker { line: index + 1, kind: kind.to_string() });
+    }
+    markers
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OperationStatus {
+    /// "merge", "rebase", "cherry_pick", "revert", "bisect" or "am"; None when no operation is in progress
+    pub operation: Option<String>,
+    /// Repo-relative paths still conflicted in the index
+    pub conflicted: Vec<String>,
+    /// "commit_merge", "continue_rebase", "continue_cherry_pick" or "continue_revert" once every
+    /// conflict is resolved; None while conflicts remain or the operation has no such step
+    pub next_action: Option<String>,
+}
+
+fn conflicted_paths(repo: &Repository) -> Result<Vec<String>> {
+    let index = repo.index()?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let paths = index
+        .conflicts()?
+        .filter_map(|c| c.ok())
+        .filter_map(|c| c.our.or(c.their).or(c.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    Ok(paths)
+}
+
+/// What the merge editor can offer next: the in-progress operation, the files still conflicted,
+/// and which button finishes the operation once none are left
+pub fn operation_status(repo_path: &Path) -> Result<OperationStatus> {
+    let repo = Repository::discover(repo_path)?;
+    require_workdir(&repo)?;
+    let state = repo.state();
+    let conflicted = conflicted_paths(&repo)?;
+    let next_action = match state {
+        _ if !conflicted.is_empty() => None,
+        RepositoryState::Merge => Some("commit_merge"),
+        RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => Some("continue_rebase"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("continue_cherry_pick"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("continue_revert"),
+        _ => None,
+    };
+    Ok(OperationStatus {
+        operation: crate::repo_events::operation_name(state).map(str::to_string),
+        conflicted,
+        next_action: next_action.map(str::to_string),
+    })
+}
+
+/// Write the merge editor's result for `file_path` (repo-relative) and stage it, which clears
+/// the file's conflict in the index. Refuses while conflict markers remain in `merged_content`.
+/// `before_write` gets the file's absolute path once the content is accepted, to keep what it replaces.
+pub fn finalize_merged_file(repo_path: &Path, file_path: &str, merged_content: &str, before_write: impl FnOnce(&Path)) -> Result<OperationStatus> {
+    let markers = conflict_markers(merged_content);
+    if !markers.is_empty() {
+        return Err(anyhow!(GitIndexError::ConflictMarkersRemain { path: file_path.to_string(), markers }));
+    }
+    let repo = Repository::discover(repo_path)?;
+    let workdir = require_workdir(&repo)?;
+    let relative = crate::fs::decode_path(file_path);
+    let relative = relative.as_path();
+    if relative.is_absolute() || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err(anyhow!("'{}' is not a path inside the repository", file_path));
+    }
+    let target = workdir.join(relative);
+    before_write(&target);
+    crate::fs::write_atomic(&target, merged_content.as_bytes())?;
+
+    let mut index = repo.index()?;
+    index.add_path(relative)?;
+    index.write()?;
+    operation_status(repo_path)
+}
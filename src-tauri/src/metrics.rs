@@ -0,0 +1,316 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::AppHandle;
+use crate::workspace::WorkspaceRegistry;
+
+// Duration histogram buckets: bucket i counts durations under FIRST_BUCKET_MICROS << i,
+// the last one everything longer (~3.5 minutes and up)
+const BUCKETS: usize = 24;
+const FIRST_BUCKET_MICROS: u64 = 25;
+
+// Slow operations kept for the diagnostics export
+const MAX_SLOW_OPERATIONS: usize = 50;
+
+const MAX_SCOPE_CHARS: usize = 200;
+
+#[derive(Default)]
+struct CommandStats {
+    invocations: AtomicU64,
+    /// Refused by the guard before dispatch
+    rejected: AtomicU64,
+    /// Completions the frontend reported with their round-trip time
+    completed: AtomicU64,
+    errors: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl CommandStats {
+    fn reset(&self) {
+        for counter in [
+            &self.invocations,
+            &self.rejected,
+            &self.completed,
+            &self.errors,
+            &self.total_micros,
+            &self.max_micros,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Upper bound of the bucket holding the `quantile` of timed completions, in milliseconds
+    fn percentile_ms(&self, quantile: f64) -> Option<f64> {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((total as f64) * quantile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = if index + 1 == BUCKETS {
+                    self.max_micros.load(Ordering::Relaxed)
+                } else {
+                    FIRST_BUCKET_MICROS << index
+                };
+                return Some(bound as f64 / 1000.0);
+            }
+        }
+        None
+    }
+}
+
+fn bucket_for(micros: u64) -> usize {
+    (0..BUCKETS - 1).find(|&i| micros < FIRST_BUCKET_MICROS << i).unwrap_or(BUCKETS - 1)
+}
+
+struct Slot {
+    name: &'static str,
+    stats: CommandStats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommandMetricsEntry {
+    pub command: String,
+    pub invocations: u64,
+    pub rejected: u64,
+    pub completed: u64,
+    pub errors: u64,
+    /// Errors and guard rejections over invocations
+    pub error_rate: f64,
+    /// Durations are approximate (histogram bucket bounds) and None until a completion is reported
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SlowOperation {
+    pub command: String,
+    /// The path or arguments the command worked on
+    pub scope: String,
+    pub workspace: Option<String>,
+    pub duration_ms: u64,
+    pub failed: bool,
+    pub at: String,
+}
+
+/// One finished invocation as timed by the frontend, from `invoke` to its result
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommandTiming {
+    pub command: String,
+    pub duration_ms: f64,
+    pub failed: bool,
+    /// The path or arguments the command worked on
+    #[serde(default)]
+    pub scope: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommandMetricsSnapshot {
+    /// When counting started: app launch or the last reset
+    pub since: String,
+    pub slow_threshold_ms: u64,
+    pub commands: Vec<CommandMetricsEntry>,
+    pub slow_operations: Vec<SlowOperation>,
+}
+
+/// Per-command counters. Recording only touches atomics in a table with one slot per
+/// registered command; the mutexes are taken for slow operations, resets and snapshots.
+pub struct CommandMetrics {
+    slots: Box<[Slot]>,
+    index: HashMap<&'static str, usize>,
+    slow_threshold_ms: AtomicU64,
+    since: Mutex<String>,
+    slow_operations: Mutex<VecDeque<SlowOperation>>,
+}
+
+impl CommandMetrics {
+    /// `commands` are every registered command name; invocations of other names are not counted
+    pub fn new(slow_threshold_ms: u64, commands: &[&'static str]) -> Self {
+        let mut names = commands.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        Self {
+            index: names.iter().enumerate().map(|(i, name)| (*name, i)).collect(),
+            slots: names.into_iter().map(|name| Slot { name, stats: CommandStats::default() }).collect(),
+            slow_threshold_ms: AtomicU64::new(slow_threshold_ms),
+            since: Mutex::new(chrono::Utc::now().to_rfc3339()),
+            slow_operations: Mutex::new(VecDeque::with_capacity(MAX_SLOW_OPERATIONS)),
+        }
+    }
+
+    pub fn set_slow_threshold_ms(&self, threshold: u64) {
+        self.slow_threshold_ms.store(threshold, Ordering::Relaxed);
+    }
+
+    fn stats(&self, command: &str) -> Option<&CommandStats> {
+        self.index.get(command).map(|&index| &self.slots[index].stats)
+    }
+
+    /// Called from the invoke handler for every command, before the guard
+    pub fn record_invocation(&self, command: &str) {
+        if let Some(stats) = self.stats(command) {
+            stats.invocations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_rejection(&self, command: &str) {
+        if let Some(stats) = self.stats(command) {
+            stats.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a timed completion; returns whether it crossed the slow-operation threshold
+    pub fn record_completion(&self, command: &str, duration: Duration, failed: bool) -> bool {
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        if let Some(stats) = self.stats(command) {
+            stats.completed.fetch_add(1, Ordering::Relaxed);
+            if failed {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            stats.total_micros.fetch_add(micros, Ordering::Relaxed);
+            stats.max_micros.fetch_max(micros, Ordering::Relaxed);
+            stats.buckets[bucket_for(micros)].fetch_add(1, Ordering::Relaxed);
+        }
+        let threshold = self.slow_threshold_ms.load(Ordering::Relaxed);
+        threshold > 0 && duration.as_millis() >= threshold as u128
+    }
+
+    pub fn record_slow(&self, operation: SlowOperation) {
+        log::warn!(
+            target: "slow_op",
+            "{}",
+            serde_json::to_string(&operation).unwrap_or_else(|_| operation.command.clone())
+        );
+        let mut slow = self.slow_operations.lock().unwrap();
+        if slow.len() == MAX_SLOW_OPERATIONS {
+            slow.pop_front();
+        }
+        slow.push_back(operation);
+    }
+
+    pub fn snapshot(&self) -> CommandMetricsSnapshot {
+        let mut commands: Vec<CommandMetricsEntry> = self
+            .slots
+            .iter()
+            .filter_map(|slot| {
+                let name = slot.name;
+                let stats = &slot.stats;
+                let invocations = stats.invocations.load(Ordering::Relaxed);
+                let completed = stats.completed.load(Ordering::Relaxed);
+                if invocations == 0 && completed == 0 {
+                    return None;
+                }
+                let rejected = stats.rejected.load(Ordering::Relaxed);
+                let errors = stats.errors.load(Ordering::Relaxed);
+                let timed = completed > 0;
+                Some(CommandMetricsEntry {
+                    command: name.to_string(),
+                    invocations,
+                    rejected,
+                    completed,
+                    errors,
+                    error_rate: if invocations > 0 { (errors + rejected) as f64 / invocations as f64 } else { 0.0 },
+                    p50_ms: stats.percentile_ms(0.5),
+                    p95_ms: stats.percentile_ms(0.95),
+                    max_ms: timed.then(|| stats.max_micros.load(Ordering::Relaxed) as f64 / 1000.0),
+                    avg_ms: timed.then(|| stats.total_micros.load(Ordering::Relaxed) as f64 / completed as f64 / 1000.0),
+                })
+            })
+            .collect();
+        commands.sort_by(|a, b| b.invocations.cmp(&a.invocations).then_with(|| a.command.cmp(&b.command)));
+
+        CommandMetricsSnapshot {
+            since: self.since.lock().unwrap().clone(),
+            slow_threshold_ms: self.slow_threshold_ms.load(Ordering::Relaxed),
+            commands,
+            slow_operations: self.slow_operations.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    pub fn reset(&self) {
+        for slot in self.slots.iter() {
+            slot.stats.reset();
+        }
+        self.slow_operations.lock().unwrap().clear();
+        *self.since.lock().unwrap() = chrono::Utc::now().to_rfc3339();
+    }
+}
+
+/// Record completions the frontend timed; those over the slow threshold are kept as slow
+/// operations. Names that are not registered commands are ignored.
+pub fn record_timings(app: &AppHandle, timings: Vec<CommandTiming>) {
+    let metrics = app.state::<CommandMetrics>();
+    for timing in timings {
+        if !metrics.index.contains_key(timing.command.as_str()) {
+            continue;
+        }
+        let duration = Duration::try_from_secs_f64(timing.duration_ms.max(0.0) / 1000.0).unwrap_or_default();
+        if metrics.record_completion(&timing.command, duration, timing.failed) {
+            let workspace = app.state::<WorkspaceRegistry>().root_for(Path::new(&timing.scope)).map(|root| crate::fs::display_path(&root));
+            metrics.record_slow(SlowOperation {
+                command: timing.command,
+                scope: timing.scope.chars().take(MAX_SCOPE_CHARS).collect(),
+                workspace,
+                duration_ms: duration.as_millis() as u64,
+                failed: timing.failed,
+                at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_registered_commands_get_a_slot() {
+        let metrics = CommandMetrics::new(0, &["read_file_content", "list_directory", "read_file_content"]);
+        assert_eq!(metrics.slots.len(), 2);
+
+        metrics.record_invocation("list_directory");
+        metrics.record_invocation("not_a_command");
+        metrics.record_rejection("also_not_a_command");
+        assert!(!metrics.record_completion("not_a_command", Duration::from_secs(10), true));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.commands.len(), 1);
+        assert_eq!(snapshot.commands[0].command, "list_directory");
+        assert_eq!(snapshot.commands[0].invocations, 1);
+    }
+
+    #[test]
+    fn completions_fill_the_histogram_and_flag_slow_ones() {
+        let metrics = CommandMetrics::new(100, &["get_git_status"]);
+        for millis in [1, 2, 3, 250] {
+            let slow = metrics.record_completion("get_git_status", Duration::from_millis(millis), millis == 3);
+            assert_eq!(slow, millis >= 100);
+        }
+
+        let entry = metrics.snapshot().commands.remove(0);
+        assert_eq!((entry.completed, entry.errors), (4, 1));
+        assert_eq!(entry.max_ms, Some(250.0));
+        assert!(entry.p50_ms.unwrap() >= 2.0 && entry.p50_ms.unwrap() <= 3.2, "{:?}", entry.p50_ms);
+        // Bucket upper bound, not the exact duration
+        assert_eq!(entry.p95_ms, Some(409.6));
+
+        metrics.reset();
+        assert!(metrics.snapshot().commands.is_empty());
+    }
+}
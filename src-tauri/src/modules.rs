@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Invoke;
+use crate::{AppHandle, AppRuntime};
+
+pub type CommandHandler = Box<dyn Fn(Invoke<AppRuntime>) -> bool + Send + Sync>;
+
+/// A group of commands with the state and background work behind them. Optional modules are
+/// compiled in behind a cargo feature of the same name.
+pub trait BackendModule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    /// Add this module's commands
+    fn register(&self, registrar: &mut InvokeRegistrar);
+
+    /// Runs once from the app's setup hook, after shared state is managed
+    fn setup(&self, _app: &AppHandle) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs once when the app exits
+    fn shutdown(&self, _app: &AppHandle) {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BackendModuleInfo {
+    pub name: String,
+    pub version: String,
+    pub commands: Vec<String>,
+}
+
+/// Collects each module's command handlers and routes invocations to them by command name
+#[derive(Default)]
+pub struct InvokeRegistrar {
+    module: &'static str,
+    routes: HashMap<&'static str, usize>,
+    handlers: Vec<CommandHandler>,
+    commands: HashMap<&'static str, Vec<&'static str>>,
+    conflicts: Vec<String>,
+}
+
+/// Register commands defined with `#[tauri::command]`, keeping their names for routing
+macro_rules! register_commands {
+    ($registrar:expr, [$($command:ident),* $(,)?]) => {
+        $registrar.add(&[$(stringify!($command)),*], Box::new(tauri::generate_handler![$($command),*]))
+    };
+}
+pub(crate) use register_commands;
+
+impl InvokeRegistrar {
+    pub fn add(&mut self, names: &[&'static str], handler: CommandHandler) {
+        let index = self.handlers.len();
+        self.handlers.push(handler);
+        for name in names {
+            if self.routes.insert(name, index).is_some() {
+                self.conflicts.push(format!("'{}' is registered twice (last by module '{}')", name, self.module));
+            }
+            self.commands.entry(self.module).or_default().push(name);
+        }
+    }
+}
+
+/// The modules compiled into this build, in registration order
+pub struct BackendModules {
+    modules: Vec<Box<dyn BackendModule>>,
+    registrar: InvokeRegistrar,
+}
+
+impl BackendModules {
+    /// Register every module's commands; two modules claiming one command name is an error
+    pub fn new(modules: Vec<Box<dyn BackendModule>>) -> Result<Self> {
+        let mut registrar = InvokeRegistrar::default();
+        for module in &modules {
+            registrar.module = module.name();
+            module.register(&mut registrar);
+        }
+        if !registrar.conflicts.is_empty() {
+            return Err(anyhow!("Conflicting backend commands: {}", registrar.conflicts.join("; ")));
+        }
+        Ok(Self { modules, registrar })
+    }
+
+    /// Hand `invoke` to the module that registered its command; false when none did
+    pub fn dispatch(&self, invoke: Invoke<AppRuntime>) -> bool {
+        match self.registrar.routes.get(invoke.message.command()) {
+            Some(&index) => (self.registrar.handlers[index])(invoke),
+            None => false,
+        }
+    }
+
+    pub fn setup(&self, app: &AppHandle) -> Result<()> {
+        for module in &self.modules {
+            module.setup(app).map_err(|e| anyhow!("Module '{}' failed to start: {}", module.name(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Shut modules down in reverse registration order
+    pub fn shutdown(&self, app: &AppHandle) {
+        for module in self.modules.iter().rev() {
+            module.shutdown(app);
+        }
+    }
+
+    /// Every command some module registered
+    pub fn command_names(&self) -> Vec<&'static str> {
+        self.registrar.routes.keys().copied().collect()
+    }
+
+    pub fn info(&self) -> Vec<BackendModuleInfo> {
+        self.modules
+            .iter()
+            .map(|module| {
+                let mut commands: Vec<String> = self
+                    .registrar
+                    .commands
+                    .get(module.name())
+                    .map(|names| names.iter().map(|name| name.to_string()).collect())
+                    .unwrap_or_default();
+                commands.sort();
+                BackendModuleInfo { name: module.name().to_string(), version: module.version().to_string(), commands }
+            })
+            .collect()
+    }
+}
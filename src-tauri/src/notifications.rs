@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::settings::SettingsState;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A long-running task or operation finished (or failed)
+    TaskComplete,
+    /// Push, pull or clone finished
+    GitRemote,
+    /// A fetch found the branch diverged or conflicting
+    Conflict,
+    /// Anything else raised by the frontend
+    General,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Suppresses every notification regardless of category
+    pub do_not_disturb: bool,
+    pub task_complete: bool,
+    pub git_remote: bool,
+    pub conflict: bool,
+    pub general: bool,
+    /// Bursts beyond this many per category within a minute are coalesced
+    pub max_per_minute: u32,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            do_not_disturb: false,
+            task_complete: true,
+            git_remote: true,
+            conflict: true,
+            general: true,
+            max_per_minute: 3,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn allows(&self, kind: NotificationKind) -> bool {
+        !self.do_not_disturb
+            && match kind {
+                NotificationKind::TaskComplete => self.task_complete,
+                NotificationKind::GitRemote => self.git_remote,
+                NotificationKind::Conflict => self.conflict,
+                NotificationKind::General => self.general,
+            }
+    }
+}
+
+#[derive(Default)]
+struct CategoryWindow {
+    sent: VecDeque<Instant>,
+    /// Notifications dropped since the last one shown
+    coalesced: usize,
+}
+
+/// Per-category rate limiting for desktop notifications
+#[derive(Default)]
+pub struct NotificationState {
+    windows: Mutex<HashMap<NotificationKind, CategoryWindow>>,
+}
+
+impl NotificationState {
+    /// Whether a notification may be shown now; returns how many were coalesced before it
+    fn admit(&self, kind: NotificationKind, max_per_minute: u32) -> Option<usize> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(kind).or_default();
+        while window.sent.front().map(|t| now.duration_since(*t) > RATE_WINDOW).unwrap_or(false) {
+            window.sent.pop_front();
+        }
+        if window.sent.len() >= max_per_minute.max(1) as usize {
+            window.coalesced += 1;
+            return None;
+        }
+        window.sent.push_back(now);
+        Some(std::mem::take(&mut window.coalesced))
+    }
+}
+
+/// Show a desktop notification subject to settings and rate limits. Returns whether it was
+/// shown; a missing or failing platform notification API is treated as "not shown".
+pub fn notify_user<R: Runtime>(app: &AppHandle<R>, title: &str, body: &str, kind: NotificationKind) -> bool {
+    let settings = app.state::<SettingsState>().get().notifications;
+    if !settings.allows(kind) {
+        return false;
+    }
+    let Some(coalesced) = app.state::<NotificationState>().admit(kind, settings.max_per_minute) else {
+        return false;
+    };
+
+    let body = match coalesced {
+        0 => body.to_string(),
+        n => format!("{}\n(+{} more)", body, n),
+    };
+    match app.notification().builder().title(title).body(body).show() {
+        Ok(_) => true,
+        Err(e) => {
+            log::debug!("Notification not shown: {}", e);
+            false
+        }
+    }
+}
+
+/// For backend completions: only notify when the user is looking at another app
+#[cfg(feature = "git")]
+pub fn notify_if_unfocused<R: Runtime>(app: &AppHandle<R>, title: &str, body: &str, kind: NotificationKind) -> bool {
+    let focused = app.webview_windows().values().any(|w| w.is_focused().unwrap_or(false));
+    !focused && notify_user(app, title, body, kind)
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cancellation flags for long-running commands, keyed by a caller-chosen operation id
+#[derive(Default)]
+pub struct OperationRegistry(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+#[derive(Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl OperationRegistry {
+    pub fn register(&self, operation_id: &str) -> CancelToken {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().insert(operation_id.to_string(), flag.clone());
+        CancelToken(flag)
+    }
+
+    /// Request cancellation; returns false when no such operation is running
+    pub fn cancel(&self, operation_id: &str) -> bool {
+        match self.0.lock().unwrap().get(operation_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Request cancellation of every running operation; returns how many there were
+    pub fn cancel_all(&self) -> usize {
+        let operations = self.0.lock().unwrap();
+        for flag in operations.values() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        operations.len()
+    }
+
+    pub fn active(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.0.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn finish(&self, operation_id: &str) {
+        self.0.lock().unwrap().remove(operation_id);
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::fs::display_path;
+
+// Mirrors the `event_absolute_paths` setting, since event payloads are built in places that
+// never see the settings state
+static INCLUDE_ABSOLUTE: AtomicBool = AtomicBool::new(true);
+
+pub fn set_include_absolute(include: bool) {
+    INCLUDE_ABSOLUTE.store(include, Ordering::Relaxed);
+}
+
+/// A path in its event form: no extended-length prefix, forward slashes, and NFC on macOS,
+/// which hands out decomposed names, so without it the same file can arrive as two different
+/// strings. Elsewhere names differing only in normalization are different files and are kept.
+pub fn normalize(path: &Path) -> String {
+    let shown = display_path(path);
+    // Backslash is an ordinary file name character outside Windows
+    let shown = if cfg!(windows) { shown.replace('\\', "/") } else { shown };
+    if cfg!(target_os = "macos") {
+        shown.nfc().collect()
+    } else {
+        shown
+    }
+}
+
+/// The root's normalized form without a trailing separator, so "/project/" and "/project" get
+/// the same id; filesystem roots such as "/" and "C:/" keep theirs
+pub fn root_id(root: &Path) -> String {
+    let id = normalize(root);
+    let trimmed = id.trim_end_matches('/');
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        id
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// `full` relative to `root`, both already normalized; None when it is not below the root
+fn relative_to(root: &str, full: &str) -> Option<String> {
+    let root = root.trim_end_matches('/');
+    if full.len() < root.len() || !full.is_char_boundary(root.len()) {
+        return None;
+    }
+    let (prefix, rest) = full.split_at(root.len());
+    // Windows paths compare case-insensitively
+    let same = if cfg!(windows) { prefix.eq_ignore_ascii_case(root) } else { prefix == root };
+    if !same {
+        return None;
+    }
+    match rest {
+        "" => Some(String::new()),
+        _ => rest.strip_prefix('/').map(str::to_string),
+    }
+}
+
+/// A path in an event payload: relative to the workspace root and normalized like `normalize`,
+/// plus the root it is relative to. A path outside the root keeps its
+/// normalized absolute form.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WsPath {
+    /// Workspace root id: the root's normalized absolute path
+    pub root: String,
+    /// Empty for the root itself
+    pub path: String,
+    /// The OS path as before, while the `event_absolute_paths` compatibility setting is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absolute: Option<String>,
+}
+
+impl WsPath {
+    pub fn new(root: &Path, path: &Path) -> Self {
+        let root_id = root_id(root);
+        let full = normalize(path);
+        Self {
+            path: relative_to(&root_id, &full).unwrap_or(full),
+            root: root_id,
+            absolute: INCLUDE_ABSOLUTE.load(Ordering::Relaxed).then(|| display_path(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn round_trip(ws: &WsPath) -> WsPath {
+        serde_json::from_value(serde_json::to_value(ws).unwrap()).unwrap()
+    }
+
+    /// The OS path `ws` refers to
+    fn to_path(ws: &WsPath) -> PathBuf {
+        if Path::new(&ws.path).is_absolute() {
+            return PathBuf::from(&ws.path);
+        }
+        ws.path.split('/').filter(|part| !part.is_empty()).fold(PathBuf::from(&ws.root), |path, part| path.join(part))
+    }
+
+    fn assert_round_trips(root: &str, path: &str, expected_root: &str, expected_path: &str) {
+        let ws = WsPath::new(Path::new(root), Path::new(path));
+        assert_eq!(ws.root, expected_root);
+        assert_eq!(ws.path, expected_path);
+        assert_eq!(&round_trip(&ws), &ws);
+        assert_eq!(to_path(&ws), Path::new(path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_paths_round_trip() {
+        assert_round_trips("/home/dev/project", "/home/dev/project/src/main.rs", "/home/dev/project", "src/main.rs");
+        assert_round_trips("/home/dev/project/", "/home/dev/project/src", "/home/dev/project", "src");
+        assert_round_trips("/", "/etc/hosts", "/", "etc/hosts");
+        assert_round_trips("/home/dev/project", "/home/dev/project", "/home/dev/project", "");
+        // A sibling sharing the root's name as a prefix is outside the workspace
+        assert_round_trips("/home/dev/project", "/home/dev/project2/notes.md", "/home/dev/project", "/home/dev/project2/notes.md");
+        // Backslash is part of the file name here, not a separator
+        assert_round_trips("/home/dev/project", "/home/dev/project/a\\b.txt", "/home/dev/project", "a\\b.txt");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_paths_round_trip() {
+        assert_round_trips(r"C:\Users\dev\project", r"C:\Users\dev\project\src\main.rs", "C:/Users/dev/project", "src/main.rs");
+        assert_round_trips(r"C:\Users\dev\project", r"C:\Users\dev\project", "C:/Users/dev/project", "");
+        assert_round_trips(r"C:\Users\dev\project\", r"C:\Users\dev\project\src", "C:/Users/dev/project", "src");
+        assert_round_trips(r"C:\", r"C:\Windows", "C:/", "Windows");
+        assert_round_trips(r"C:\Users\dev\project", r"D:\elsewhere\notes.md", "C:/Users/dev/project", "D:/elsewhere/notes.md");
+
+        // Extended-length prefixes and differing case still resolve below the root
+        let ws = WsPath::new(Path::new(r"\\?\C:\Users\dev\project"), Path::new(r"c:\users\DEV\project\src\lib.rs"));
+        assert_eq!(ws.root, "C:/Users/dev/project");
+        assert_eq!(ws.path, "src/lib.rs");
+        assert_eq!(&round_trip(&ws), &ws);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn decomposed_and_composed_names_agree() {
+        let sep = std::path::MAIN_SEPARATOR;
+        // "café" and "résumé.md", decomposed the way macOS file systems report them
+        let decomposed_root = format!("{sep}Users{sep}dev{sep}cafe\u{301}");
+        let decomposed = format!("{decomposed_root}{sep}re\u{301}sume\u{301}.md");
+        let composed_root = format!("{sep}Users{sep}dev{sep}caf\u{e9}");
+        let composed = format!("{composed_root}{sep}r\u{e9}sum\u{e9}.md");
+
+        let from_decomposed = WsPath::new(Path::new(&decomposed_root), Path::new(&decomposed));
+        let from_composed = WsPath::new(Path::new(&composed_root), Path::new(&composed));
+        assert_eq!(from_decomposed.root, from_composed.root);
+        assert_eq!(from_decomposed.path, "r\u{e9}sum\u{e9}.md");
+        assert_eq!(from_composed.path, "r\u{e9}sum\u{e9}.md");
+
+        // A decomposed path below a composed root is still relative to it
+        let mixed = WsPath::new(Path::new(&composed_root), Path::new(&decomposed));
+        assert_eq!(mixed.path, "r\u{e9}sum\u{e9}.md");
+        assert_eq!(&round_trip(&mixed), &mixed);
+        assert!(to_path(&mixed).ends_with("r\u{e9}sum\u{e9}.md"));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn names_keep_their_normalization_off_macos() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let root = format!("{sep}home{sep}dev{sep}caf\u{e9}");
+        let decomposed = format!("{root}{sep}re\u{301}sume\u{301}.md");
+
+        let ws = WsPath::new(Path::new(&root), Path::new(&decomposed));
+        assert_eq!(ws.path, "re\u{301}sume\u{301}.md");
+        assert_eq!(to_path(&ws), Path::new(&decomposed));
+    }
+}
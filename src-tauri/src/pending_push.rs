@@ -0,0 +1,194 @@
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use git2::{BranchType, Repository};
+
+use crate::fs::display_path;
+
+pub(crate) const STORE_FILE: &str = "pending-pushes.json";
+
+// First retry waits this long; each failure doubles it up to MAX_BACKOFF_SECS
+const INITIAL_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 15 * 60;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingPush {
+    pub id: String,
+    pub repository: String,
+    /// Path the push was requested with; pre-push checks are configured under it
+    #[serde(default)]
+    pub project_path: String,
+    pub remote: String,
+    pub branch: String,
+    /// Commit the branch pointed at when the push was queued
+    pub local_commit: String,
+    pub created_at: i64,
+    pub last_error: String,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+}
+
+/// Pushes that failed because the remote was unreachable, retried once connectivity returns
+pub struct PendingPushStore {
+    path: PathBuf,
+    entries: Mutex<Vec<PendingPush>>,
+}
+
+fn backoff_secs(attempts: u32) -> i64 {
+    INITIAL_BACKOFF_SECS.saturating_mul(1 << attempts.min(10)).min(MAX_BACKOFF_SECS)
+}
+
+impl PendingPushStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &[PendingPush]) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    /// Remember a push to retry; a second failure for the same branch updates the existing entry
+    pub fn queue(&self, repo_path: &Path, remote: &str, branch: &str, error: &str) -> Result<PendingPush> {
+        let repo = Repository::discover(repo_path)?;
+        let repository = display_path(repo.workdir().unwrap_or_else(|| repo.path()));
+        let project_path = repo_path.to_string_lossy().to_string();
+        let local_commit = repo
+            .find_branch(branch, BranchType::Local)?
+            .get()
+            .target()
+            .ok_or_else(|| anyhow!("Branch '{}' has no commit", branch))?
+            .to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        let mut entries = self.entries.lock().unwrap();
+        let pending = match entries.iter_mut().find(|p| p.repository == repository && p.remote == remote && p.branch == branch) {
+            Some(existing) => {
+                existing.local_commit = local_commit;
+                existing.project_path = project_path;
+                existing.last_error = error.to_string();
+                existing.clone()
+            }
+            None => {
+                let pending = PendingPush {
+                    id: format!("push-{}", chrono::Utc::now().timestamp_millis()),
+                    repository,
+                    project_path,
+                    remote: remote.to_string(),
+                    branch: branch.to_string(),
+                    local_commit,
+                    created_at: now,
+                    last_error: error.to_string(),
+                    attempts: 0,
+                    next_attempt_at: now + backoff_secs(0),
+                };
+                entries.push(pending.clone());
+                pending
+            }
+        };
+        self.persist(&entries);
+        Ok(pending)
+    }
+
+    /// Pending pushes, for one repository or all of them
+    pub fn list(&self, repo_path: Option<&Path>) -> Vec<PendingPush> {
+        let repository = repo_path
+            .and_then(|p| Repository::discover(p).ok())
+            .map(|repo| display_path(repo.workdir().unwrap_or_else(|| repo.path())));
+        let entries = self.entries.lock().unwrap();
+        match (repo_path, repository) {
+            (None, _) => entries.clone(),
+            (Some(_), Some(repository)) => entries.iter().filter(|p| p.repository == repository).cloned().collect(),
+            (Some(_), None) => Vec::new(),
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|p| p.id != id);
+        let removed = entries.len() != before;
+        if removed {
+            self.persist(&entries);
+        }
+        removed
+    }
+
+    pub fn record_failure(&self, id: &str, error: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(pending) = entries.iter_mut().find(|p| p.id == id) {
+            pending.attempts += 1;
+            pending.last_error = error.to_string();
+            pending.next_attempt_at = chrono::Utc::now().timestamp() + backoff_secs(pending.attempts);
+        }
+        self.persist(&entries);
+    }
+
+    /// Entries whose backoff has elapsed
+    pub fn due(&self) -> Vec<PendingPush> {
+        let now = chrono::Utc::now().timestamp();
+        self.entries.lock().unwrap().iter().filter(|p| p.next_attempt_at <= now).cloned().collect()
+    }
+}
+
+/// Refuse a retry that would surprise the user: the branch was deleted, or it was reset or
+/// rewritten so it no longer contains what was queued, or what was queued no longer descends
+/// from the remote. Returns the queued commit, which is what gets pushed.
+pub fn validate_retry(pending: &PendingPush) -> Result<git2::Oid> {
+    let repo = Repository::open(&pending.repository)?;
+    let local = repo
+        .find_branch(&pending.branch, BranchType::Local)
+        .map_err(|_| anyhow!("Local branch '{}' no longer exists", pending.branch))?
+        .get()
+        .target()
+        .ok_or_else(|| anyhow!("Branch '{}' has no commit", pending.branch))?;
+
+    let queued = git2::Oid::from_str(&pending.local_commit)?;
+    if local != queued && !repo.graph_descendant_of(local, queued).unwrap_or(false) {
+        return Err(anyhow!("Branch '{}' was rewound since the push was queued", pending.branch));
+    }
+    let tracking = format!("refs/remotes/{}/{}", pending.remote, pending.branch);
+    if let Some(remote_oid) = repo.find_reference(&tracking).ok().and_then(|r| r.target()) {
+        if queued != remote_oid && !repo.graph_descendant_of(queued, remote_oid).unwrap_or(false) {
+            return Err(anyhow!("Branch '{}' is behind or diverged from {}/{}", pending.branch, pending.remote, pending.branch));
+        }
+    }
+    Ok(queued)
+}
+
+/// Cheap reachability check: can a TCP connection to the remote's host be opened at all
+pub fn remote_reachable(pending: &PendingPush) -> bool {
+    let Ok(repo) = Repository::open(&pending.repository) else { return false };
+    let Ok(remote) = repo.find_remote(&pending.remote) else { return false };
+    let Some(url) = remote.pushurl().or_else(|| remote.url()).map(str::to_string) else { return false };
+    let Some((host, port)) = crate::credentials::remote_host(&url) else {
+        // Local paths and file:// remotes need no network
+        return true;
+    };
+    let default_port = if url.starts_with("http://") { 80 } else if url.starts_with("https://") { 443 } else { 22 };
+    let Ok(addresses) = (host.as_str(), port.unwrap_or(default_port)).to_socket_addrs() else { return false };
+    addresses.into_iter().any(|address| TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok())
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PushRetryEvent {
+    pub pending: PendingPush,
+    /// "failed" (will retry) or "abandoned" (dropped from the queue)
+    pub outcome: String,
+    pub error: Option<String>,
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+
+use crate::fs::display_path;
+
+pub(crate) const STORE_FILE: &str = "pinned-paths.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PinnedPath {
+    pub path: String,
+    /// Relative to the project root, with `/` separators
+    pub relative_path: String,
+    pub is_directory: bool,
+    /// The pinned path no longer exists; kept so the UI can offer to fix or remove it
+    pub missing: bool,
+}
+
+/// Pinned files and folders per project, in user order, persisted in the app data dir
+pub struct PinStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<String>>>,
+}
+
+pub(crate) fn project_key(project: &Path) -> String {
+    display_path(&fs::canonicalize(project).unwrap_or_else(|_| project.to_path_buf()))
+}
+
+/// `path` relative to `project`, with `/` separators so the store is portable
+pub(crate) fn relative_to(project: &Path, path: &Path) -> Result<String> {
+    let project = fs::canonicalize(project)?;
+    let path = fs::canonicalize(path).map_err(|_| anyhow!("Path does not exist: {}", display_path(path)))?;
+    let relative = path
+        .strip_prefix(&project)
+        .map_err(|_| anyhow!("Path is outside the project: {}", display_path(&path)))?;
+    Ok(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+}
+
+/// An absolute or project-relative path as a store key, without touching the filesystem, so
+/// paths that no longer exist can still be named
+pub(crate) fn relative_key(root: &Path, path: &str) -> String {
+    crate::fs::decode_path(path)
+        .strip_prefix(root)
+        .map(|r| r.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        .unwrap_or_else(|_| path.replace('\\', "/"))
+}
+
+impl PinStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<String>>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    /// Pin `path` at the end of the project's list; pinning twice is a no-op
+    pub fn pin(&self, project: &Path, path: &Path) -> Result<Vec<PinnedPath>> {
+        let relative = relative_to(project, path)?;
+        let mut entries = self.entries.lock().unwrap();
+        let list = entries.entry(project_key(project)).or_default();
+        if !list.contains(&relative) {
+            list.push(relative);
+            self.persist(&entries);
+        }
+        drop(entries);
+        Ok(self.list(project))
+    }
+
+    /// Unpin by absolute or project-relative path; works for paths that no longer exist
+    pub fn unpin(&self, project: &Path, path: &str) -> Vec<PinnedPath> {
+        let key = project_key(project);
+        let relative = relative_key(Path::new(&key), path);
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(list) = entries.get_mut(&key) {
+            let before = list.len();
+            list.retain(|p| *p != relative);
+            if list.len() != before {
+                self.persist(&entries);
+            }
+        }
+        drop(entries);
+        self.list(project)
+    }
+
+    /// Replace the order of the project's pins; `order` must name exactly the pinned paths
+    pub fn reorder(&self, project: &Path, order: &[String]) -> Result<Vec<PinnedPath>> {
+        let key = project_key(project);
+        let mut entries = self.entries.lock().unwrap();
+        let list = entries.get(&key).cloned().unwrap_or_default();
+        let current: HashSet<&String> = list.iter().collect();
+        let requested: HashSet<&String> = order.iter().collect();
+        if current != requested || requested.len() != order.len() {
+            return Err(anyhow!("New order must list each pinned path exactly once"));
+        }
+        entries.insert(key, order.to_vec());
+        self.persist(&entries);
+        drop(entries);
+        Ok(self.list(project))
+    }
+
+    /// Pins in user order, each checked against the filesystem
+    pub fn list(&self, project: &Path) -> Vec<PinnedPath> {
+        let key = project_key(project);
+        let root = PathBuf::from(&key);
+        let list = self.entries.lock().unwrap().get(&key).cloned().unwrap_or_default();
+        list.into_iter()
+            .map(|relative| {
+                let absolute = root.join(&relative);
+                let metadata = fs::metadata(&absolute).ok();
+                PinnedPath {
+                    path: display_path(&absolute),
+                    is_directory: metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false),
+                    missing: metadata.is_none(),
+                    relative_path: relative,
+                }
+            })
+            .collect()
+    }
+
+    /// Rewrite pins at or below a renamed directory. `from` and `to` are resolved paths; pins
+    /// moved out of their project are left to show as missing.
+    pub fn remap(&self, from: &Path, to: &Path) {
+        let relative = |root: &Path, path: &Path| -> Option<String> {
+            let rest = path.strip_prefix(root).ok()?;
+            Some(rest.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let mut changed = false;
+        for (key, list) in entries.iter_mut() {
+            let root = PathBuf::from(key);
+            let (Some(old), Some(new)) = (relative(&root, from), relative(&root, to)) else { continue };
+            for pin in list.iter_mut() {
+                let rest = if *pin == old { Some("") } else { pin.strip_prefix(&format!("{}/", old)) };
+                if let Some(rest) = rest {
+                    *pin = if rest.is_empty() { new.clone() } else { format!("{}/{}", new, rest) };
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.persist(&entries);
+        }
+    }
+
+    /// Absolute paths of the project's pins in pin order, for boosting them in quick-open
+    #[cfg(feature = "git")]
+    pub fn pinned_paths(&self, project: &Path) -> Vec<PathBuf> {
+        let key = project_key(project);
+        let root = PathBuf::from(&key);
+        self.entries.lock().unwrap().get(&key).map(|list| list.iter().map(|relative| root.join(relative)).collect()).unwrap_or_default()
+    }
+}
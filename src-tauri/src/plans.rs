@@ -0,0 +1,304 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use git2::Repository;
+
+use crate::allowed_commands::AuditRecord;
+
+pub(crate) const STORE_FILE: &str = "plans.json";
+
+// Oldest finished plans are dropped beyond this many
+const MAX_PLANS: usize = 200;
+
+// Commits linked to one step, newest first
+const MAX_STEP_COMMITS: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStepStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Skipped,
+    Cancelled,
+    /// Was running when the app quit
+    Interrupted,
+}
+
+impl PlanStepStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Skipped | Self::Cancelled)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    Active,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Was active when the app quit; the next step update resumes it
+    Interrupted,
+}
+
+/// A step as the agent proposes it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlanStepInput {
+    pub description: String,
+    /// Tool the step is expected to use, e.g. "write_file_content" or "run_allowed_command"
+    #[serde(default)]
+    pub tool: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlanStep {
+    pub description: String,
+    pub tool: Option<String>,
+    pub status: PlanStepStatus,
+    pub result_summary: Option<String>,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    /// Cancellable operations started for this step
+    #[serde(default)]
+    pub operation_ids: Vec<String>,
+    /// HEAD when the step started, to find the commits it produced
+    #[serde(default)]
+    pub head_at_start: Option<String>,
+    #[serde(default)]
+    pub commits: Vec<String>,
+    #[serde(default)]
+    pub checkpoints: Vec<String>,
+    /// Allowlisted commands the step ran
+    #[serde(default)]
+    pub audit_entries: Vec<AuditRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Plan {
+    pub id: String,
+    pub project_path: String,
+    pub session_id: Option<String>,
+    pub status: PlanStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    /// Status implied by the steps; cancellation is sticky
+    fn derive_status(&mut self) {
+        if self.status == PlanStatus::Cancelled {
+            return;
+        }
+        self.status = if self.steps.iter().any(|s| s.status == PlanStepStatus::Failed) {
+            PlanStatus::Failed
+        } else if self.steps.iter().all(|s| matches!(s.status, PlanStepStatus::Completed | PlanStepStatus::Skipped)) {
+            PlanStatus::Completed
+        } else {
+            PlanStatus::Active
+        };
+    }
+}
+
+/// What `update_plan_step` reports about one step
+#[derive(Clone, Debug)]
+pub struct PlanStepUpdate {
+    pub status: PlanStepStatus,
+    pub result_summary: Option<String>,
+    /// Operation registered for this step, cancelled along with the plan
+    pub operation_id: Option<String>,
+    pub checkpoints: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlanProgressEvent {
+    pub plan_id: String,
+    /// None when the whole plan changed, e.g. on cancellation
+    pub step_index: Option<usize>,
+    pub plan: Plan,
+}
+
+/// Multi-step agent plans, persisted so a restart shows what was in flight
+pub struct PlanStore {
+    path: PathBuf,
+    plans: Mutex<Vec<Plan>>,
+}
+
+fn head_commit(project_path: &str) -> Option<String> {
+    let repo = Repository::discover(project_path).ok()?;
+    let head = repo.head().ok()?.target()?;
+    Some(head.to_string())
+}
+
+/// Commits reachable from HEAD but not from `since`, newest first
+fn commits_since(project_path: &str, since: Option<&str>) -> Vec<String> {
+    let Ok(repo) = Repository::discover(project_path) else { return Vec::new() };
+    let Ok(mut walk) = repo.revwalk() else { return Vec::new() };
+    if walk.push_head().is_err() {
+        return Vec::new();
+    }
+    match since.and_then(|s| git2::Oid::from_str(s).ok()) {
+        Some(oid) => {
+            if walk.hide(oid).is_err() {
+                return Vec::new();
+            }
+        }
+        // Without a baseline every commit in the repository would count
+        None => return Vec::new(),
+    }
+    walk.filter_map(|oid| oid.ok()).take(MAX_STEP_COMMITS).map(|oid| oid.to_string()).collect()
+}
+
+impl PlanStore {
+    /// Load persisted plans; anything that was active when the app quit becomes interrupted
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let mut plans: Vec<Plan> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        for plan in plans.iter_mut().filter(|p| p.status == PlanStatus::Active) {
+            plan.status = PlanStatus::Interrupted;
+            for step in plan.steps.iter_mut().filter(|s| s.status == PlanStepStatus::Running) {
+                step.status = PlanStepStatus::Interrupted;
+            }
+        }
+        Self { path, plans: Mutex::new(plans) }
+    }
+
+    fn persist(&self, plans: &[Plan]) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(plans) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    pub fn create(&self, project_path: &str, steps: Vec<PlanStepInput>, session_id: Option<String>) -> Result<Plan> {
+        if steps.is_empty() {
+            return Err(anyhow!("A plan needs at least one step"));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let plan = Plan {
+            id: format!("plan-{}", chrono::Utc::now().timestamp_millis()),
+            project_path: project_path.to_string(),
+            session_id,
+            status: PlanStatus::Active,
+            created_at: now,
+            updated_at: now,
+            steps: steps
+                .into_iter()
+                .map(|input| PlanStep {
+                    description: input.description,
+                    tool: input.tool,
+                    status: PlanStepStatus::Pending,
+                    result_summary: None,
+                    started_at: None,
+                    finished_at: None,
+                    operation_ids: Vec::new(),
+                    head_at_start: None,
+                    commits: Vec::new(),
+                    checkpoints: Vec::new(),
+                    audit_entries: Vec::new(),
+                })
+                .collect(),
+        };
+
+        let mut plans = self.plans.lock().unwrap();
+        plans.push(plan.clone());
+        if plans.len() > MAX_PLANS {
+            if let Some(oldest) = plans.iter().position(|p| !matches!(p.status, PlanStatus::Active | PlanStatus::Interrupted)) {
+                plans.remove(oldest);
+            }
+        }
+        self.persist(&plans);
+        Ok(plan)
+    }
+
+    /// Record a step's progress. Finishing a step links the commits made since it started
+    /// and the allowlisted commands it ran (read from the audit log under `data_dir`).
+    pub fn update_step(&self, data_dir: &Path, plan_id: &str, step_index: usize, update: PlanStepUpdate) -> Result<Plan> {
+        let PlanStepUpdate { status, result_summary, operation_id, checkpoints } = update;
+        let mut plans = self.plans.lock().unwrap();
+        let plan = plans.iter_mut().find(|p| p.id == plan_id).ok_or_else(|| anyhow!("Plan '{}' does not exist", plan_id))?;
+        if plan.status == PlanStatus::Cancelled {
+            return Err(anyhow!("Plan '{}' was cancelled", plan_id));
+        }
+        let project_path = plan.project_path.clone();
+        let step = plan.steps.get_mut(step_index).ok_or_else(|| anyhow!("Plan '{}' has no step {}", plan_id, step_index))?;
+        let now = chrono::Utc::now();
+
+        if status == PlanStepStatus::Running && step.status != PlanStepStatus::Running {
+            step.started_at = Some(now.timestamp());
+            step.finished_at = None;
+            step.head_at_start = head_commit(&project_path);
+        }
+        if status.is_finished() && !step.status.is_finished() {
+            step.finished_at = Some(now.timestamp());
+            let since = step.head_at_start.clone();
+            for commit in commits_since(&project_path, since.as_deref()) {
+                if !step.commits.contains(&commit) {
+                    step.commits.push(commit);
+                }
+            }
+            if let Some(started) = step.started_at.and_then(|t| chrono::DateTime::from_timestamp(t, 0)) {
+                step.audit_entries = crate::allowed_commands::audit_entries_between(data_dir, &project_path, started, now);
+            }
+        }
+        step.status = status;
+        if result_summary.is_some() {
+            step.result_summary = result_summary;
+        }
+        if let Some(operation_id) = operation_id {
+            if !step.operation_ids.contains(&operation_id) {
+                step.operation_ids.push(operation_id);
+            }
+        }
+        for checkpoint in checkpoints {
+            if !step.checkpoints.contains(&checkpoint) {
+                step.checkpoints.push(checkpoint);
+            }
+        }
+
+        plan.updated_at = now.timestamp();
+        plan.derive_status();
+        let updated = plan.clone();
+        self.persist(&plans);
+        Ok(updated)
+    }
+
+    pub fn get(&self, plan_id: &str) -> Option<Plan> {
+        self.plans.lock().unwrap().iter().find(|p| p.id == plan_id).cloned()
+    }
+
+    /// Plans for one project, newest first
+    pub fn list(&self, project_path: &str) -> Vec<Plan> {
+        let mut plans: Vec<Plan> = self.plans.lock().unwrap().iter().filter(|p| p.project_path == project_path).cloned().collect();
+        plans.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+        plans
+    }
+
+    /// Mark the plan and its unfinished steps cancelled. Returns the plan and the operation
+    /// ids of those steps, for the caller to cancel.
+    pub fn cancel(&self, plan_id: &str) -> Result<(Plan, Vec<String>)> {
+        let mut plans = self.plans.lock().unwrap();
+        let plan = plans.iter_mut().find(|p| p.id == plan_id).ok_or_else(|| anyhow!("Plan '{}' does not exist", plan_id))?;
+        let now = chrono::Utc::now().timestamp();
+        let mut operations = Vec::new();
+        for step in plan.steps.iter_mut().filter(|s| !s.status.is_finished()) {
+            operations.extend(step.operation_ids.iter().cloned());
+            step.status = PlanStepStatus::Cancelled;
+            step.finished_at = Some(now);
+        }
+        plan.status = PlanStatus::Cancelled;
+        plan.updated_at = now;
+        let cancelled = plan.clone();
+        self.persist(&plans);
+        Ok((cancelled, operations))
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::allowed_commands::{self, CommandError, CommandOutput, CommandResult};
+use crate::env_profiles::ProfileEnvironment;
+
+// Tail of a failing step's output kept in the result and error
+const MAX_SUMMARY_LINES: usize = 40;
+const MAX_SUMMARY_CHARS: usize = 4000;
+
+/// One step of a project's pre-push pipeline: an allowlisted command run in the project root
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrePushCheck {
+    pub command_line: String,
+    /// Shown in the SCM panel instead of the command line
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Environment profile of the project merged over the inherited environment
+    #[serde(default)]
+    pub env_profile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrePushStepResult {
+    pub index: usize,
+    pub name: Option<String>,
+    pub command_line: String,
+    pub passed: bool,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Why the command could not run or was stopped (not allowlisted, timeout)
+    pub error: Option<String>,
+    /// Last lines of its output
+    pub output_summary: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrePushRun {
+    pub project_path: String,
+    pub remote: String,
+    pub branch: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub passed: bool,
+    /// The checks were bypassed with `skip_checks`
+    pub skipped: bool,
+    pub failed_step: Option<usize>,
+    pub steps: Vec<PrePushStepResult>,
+}
+
+/// One output line of a running check, emitted as "prepush-output"
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrePushOutputEvent {
+    pub project_path: String,
+    pub step: usize,
+    pub stderr: bool,
+    pub line: String,
+}
+
+/// Last lines of stdout and stderr, capped in size
+fn summarize(output: &CommandOutput) -> String {
+    let combined = [output.stdout.trim_end(), output.stderr.trim_end()]
+        .iter()
+        .filter(|text| !text.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lines: Vec<&str> = combined.lines().collect();
+    let tail = lines[lines.len().saturating_sub(MAX_SUMMARY_LINES)..].join("\n");
+    let skip = tail.chars().count().saturating_sub(MAX_SUMMARY_CHARS);
+    tail.chars().skip(skip).collect()
+}
+
+fn step_result(index: usize, check: &PrePushCheck, result: &CommandResult<CommandOutput>) -> PrePushStepResult {
+    let mut step = PrePushStepResult {
+        index,
+        name: check.name.clone(),
+        command_line: check.command_line.clone(),
+        passed: false,
+        exit_code: None,
+        duration_ms: 0,
+        error: None,
+        output_summary: String::new(),
+    };
+    match result {
+        Ok(output) => {
+            step.passed = output.exit_code == Some(0);
+            step.exit_code = output.exit_code;
+            step.duration_ms = output.duration_ms;
+            step.output_summary = summarize(output);
+        }
+        Err(e) => step.error = Some(e.to_string()),
+    }
+    step
+}
+
+pub struct CheckEnvironment<'a> {
+    pub cwd: &'a Path,
+    pub allowlist: &'a [String],
+    pub allow_shell: bool,
+    pub timeout: Duration,
+    pub data_dir: Option<&'a Path>,
+    /// The checks' environment profiles by name, or why one could not be loaded
+    pub profiles: &'a HashMap<String, Result<ProfileEnvironment, String>>,
+}
+
+/// Run `checks` in order, stopping at the first failure. `on_line` receives each output line
+/// with the index of the step that produced it.
+pub fn run_checks(
+    checks: &[PrePushCheck],
+    project_path: &str,
+    remote: &str,
+    branch: &str,
+    env: &CheckEnvironment,
+    mut on_line: impl FnMut(usize, bool, &str),
+) -> PrePushRun {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let mut steps = Vec::new();
+    let mut failed_step = None;
+    for (index, check) in checks.iter().enumerate() {
+        let empty = ProfileEnvironment::default();
+        let profile = match check.env_profile.as_ref().map(|name| (name, env.profiles.get(name))) {
+            None => Ok(&empty),
+            Some((_, Some(Ok(profile)))) => Ok(profile),
+            Some((_, Some(Err(message)))) => Err(CommandError::InvalidCommand { message: message.clone() }),
+            Some((name, None)) => Err(CommandError::InvalidCommand { message: format!("No environment profile named '{}'", name) }),
+        };
+        let redactor = profile.as_ref().ok().copied().unwrap_or(&empty);
+        let mut result = profile.and_then(|profile| {
+            let mut command = allowed_commands::prepare(&check.command_line, env.allowlist, env.allow_shell)?;
+            profile.apply(&mut command);
+            allowed_commands::run_streaming(command, env.cwd, env.timeout, |stderr, line| on_line(index, stderr, &profile.redact(line)))
+        });
+        if let Ok(output) = &mut result {
+            redactor.redact_output(output);
+        }
+        if let Some(data_dir) = env.data_dir {
+            allowed_commands::record_audit(data_dir, project_path, &redactor.redact(&check.command_line), env.allow_shell, &result);
+        }
+        let step = step_result(index, check, &result);
+        let passed = step.passed;
+        steps.push(step);
+        if !passed {
+            failed_step = Some(index);
+            break;
+        }
+    }
+    PrePushRun {
+        project_path: project_path.to_string(),
+        remote: remote.to_string(),
+        branch: branch.to_string(),
+        started_at,
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        passed: failed_step.is_none(),
+        skipped: false,
+        failed_step,
+        steps,
+    }
+}
+
+pub fn skipped_run(project_path: &str, remote: &str, branch: &str) -> PrePushRun {
+    let now = chrono::Utc::now().to_rfc3339();
+    PrePushRun {
+        project_path: project_path.to_string(),
+        remote: remote.to_string(),
+        branch: branch.to_string(),
+        started_at: now.clone(),
+        finished_at: now,
+        passed: false,
+        skipped: true,
+        failed_step: None,
+        steps: Vec::new(),
+    }
+}
+
+/// Checks configured for the project at `project_key`
+pub fn checks_for(project_checks: &HashMap<String, Vec<PrePushCheck>>, project_key: &str) -> Vec<PrePushCheck> {
+    project_checks.get(project_key).cloned().unwrap_or_default()
+}
+
+/// The most recent pre-push run per project, for the SCM panel
+#[derive(Default)]
+pub struct PrePushResults(Mutex<HashMap<String, PrePushRun>>);
+
+impl PrePushResults {
+    pub fn record(&self, run: PrePushRun) {
+        self.0.lock().unwrap().insert(run.project_path.clone(), run);
+    }
+
+    pub fn last(&self, project_path: &str) -> Option<PrePushRun> {
+        self.0.lock().unwrap().get(project_path).cloned()
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use anyhow::{anyhow, Result};
+use git2::{BlameOptions, Oid, Repository, Status};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::content_hash;
+use crate::commit_message::{self, Trailer};
+use crate::forge::{self, ForgeKind, RemoteHostInfo};
+use crate::fs::display_path;
+use crate::git::{self, CommitDetails};
+
+// Cached blames kept before the cache is cleared
+const MAX_CACHED_BLAMES: usize = 500;
+
+// Widest selection described in one call
+const MAX_RANGE_LINES: usize = 2000;
+
+/// Lines `start_line..=end_line` (1-based, in the file as it is on disk) last changed by
+/// `commit`; None for changes not committed yet
+#[derive(Clone, Debug)]
+struct BlameHunk {
+    commit: Option<Oid>,
+    start_line: usize,
+    end_line: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProvenanceCommit {
+    #[serde(flatten)]
+    pub details: CommitDetails,
+    /// Parts of the selection this commit last changed, as inclusive 1-based line ranges
+    pub lines: Vec<(usize, usize)>,
+    /// Reachable from a remote-tracking branch of `remote`
+    pub pushed: bool,
+    pub commit_url: Option<String>,
+    /// Trailers matching one of the issue-reference patterns
+    pub issue_refs: Vec<Trailer>,
+    /// From a squash-merge summary (`... (#12)`) or a merge commit's message
+    pub pull_request: Option<u64>,
+    pub pull_request_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LineProvenance {
+    /// Repo-relative, `/`-separated
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Newest first
+    pub commits: Vec<ProvenanceCommit>,
+    /// Parts of the selection with uncommitted changes
+    pub uncommitted: Vec<(usize, usize)>,
+    /// None when the repository has no hosted remote
+    pub remote: Option<RemoteHostInfo>,
+    /// The blame was served from the cache
+    pub cached: bool,
+}
+
+/// File, content hash, HEAD and line range; any of them changing means a fresh blame
+type BlameKey = (String, String, String, usize, usize);
+
+fn pull_request_number(message: &str) -> Option<u64> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?m)\(#(\d+)\)\s*$|^Merge pull request #(\d+)|See merge request \S*!(\d+)|^Merged in \S+ \(pull request #(\d+)\)").unwrap()
+    });
+    let captures = pattern.captures(message)?;
+    captures.iter().skip(1).flatten().next()?.as_str().parse().ok()
+}
+
+fn is_issue_ref(trailer: &Trailer, patterns: &[Regex]) -> bool {
+    let line = format!("{} {}", trailer.key, trailer.value);
+    patterns.iter().any(|pattern| pattern.is_match(&line))
+}
+
+/// Blame `content` (the file as on disk) over the selection. A clean file's blame is limited to
+/// the selected lines; a modified one is blamed whole, since its line numbers no longer match
+/// the committed file.
+fn blame(repo: &Repository, relative: &Path, content: &[u8], start_line: usize, end_line: usize) -> Result<Vec<BlameHunk>> {
+    let status = repo.status_file(relative).unwrap_or(Status::CURRENT);
+    if status.intersects(Status::WT_NEW | Status::INDEX_NEW) {
+        return Ok(vec![BlameHunk { commit: None, start_line, end_line }]);
+    }
+    let mut options = BlameOptions::new();
+    if status.is_empty() {
+        options.min_line(start_line).max_line(end_line);
+    }
+    let committed = repo.blame_file(relative, Some(&mut options))?;
+    let blame = committed.blame_buffer(content)?;
+
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let first = hunk.final_start_line().max(start_line);
+        let last = (hunk.final_start_line() + hunk.lines_in_hunk()).saturating_sub(1).min(end_line);
+        if first > last {
+            continue;
+        }
+        let commit = Some(hunk.final_commit_id()).filter(|oid| !oid.is_zero());
+        match hunks.last_mut() {
+            Some(BlameHunk { commit: previous, end_line, .. }) if *previous == commit && *end_line + 1 == first => *end_line = last,
+            _ => hunks.push(BlameHunk { commit, start_line: first, end_line: last }),
+        }
+    }
+    Ok(hunks)
+}
+
+/// Blames keyed by file content and HEAD, so hovering the same lines again skips the blame
+#[derive(Default)]
+pub struct ProvenanceCache(Mutex<HashMap<BlameKey, Vec<BlameHunk>>>);
+
+impl ProvenanceCache {
+    /// Blame, commit details, push state, forge links and issue references for a selection,
+    /// from one repository open and one pass over the remote-tracking branches
+    pub fn line_provenance(
+        &self,
+        repo_path: &Path,
+        file_path: &Path,
+        start_line: usize,
+        end_line: usize,
+        issue_patterns: &[String],
+        forge_hosts: &HashMap<String, ForgeKind>,
+    ) -> Result<LineProvenance> {
+        let (start_line, end_line) = (start_line.min(end_line).max(1), start_line.max(end_line).max(1));
+        if end_line - start_line >= MAX_RANGE_LINES {
+            return Err(anyhow!("Select at most {} lines", MAX_RANGE_LINES));
+        }
+        let repo = Repository::discover(repo_path)?;
+        let workdir = git::require_workdir(&repo)?;
+        let canonical_workdir = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+        let canonical_file = file_path.canonicalize().unwrap_or_else(|_| file_path.to_path_buf());
+        let relative: PathBuf = canonical_file
+            .strip_prefix(&canonical_workdir)
+            .or_else(|_| file_path.strip_prefix(workdir))
+            .map_err(|_| anyhow!("{} is not inside the repository", file_path.display()))?
+            .to_path_buf();
+
+        let content = fs::read(file_path)?;
+        let line_count = content.split(|&b| b == b'\n').count() - content.ends_with(b"\n") as usize;
+        if start_line > line_count {
+            return Err(anyhow!("Line {} is past the end of the file ({} lines)", start_line, line_count));
+        }
+        let end_line = end_line.min(line_count);
+
+        let head = repo.head().ok().and_then(|h| h.target()).map(|oid| oid.to_string()).unwrap_or_default();
+        let key = (display_path(&canonical_file), content_hash(&String::from_utf8_lossy(&content)), head, start_line, end_line);
+        let cached = self.0.lock().unwrap().get(&key).cloned();
+        let (hunks, was_cached) = match cached {
+            Some(hunks) => (hunks, true),
+            None => {
+                let hunks = blame(&repo, &relative, &content, start_line, end_line)?;
+                let mut cache = self.0.lock().unwrap();
+                if cache.len() >= MAX_CACHED_BLAMES {
+                    cache.clear();
+                }
+                cache.insert(key, hunks.clone());
+                (hunks, false)
+            }
+        };
+
+        // Push state and links change without the file changing, so they are never cached
+        let remote = forge::remote_host_info(&repo, None, forge_hosts).ok();
+        let tips = remote.as_ref().and_then(|info| forge::remote_tips(&repo, &info.remote).ok()).unwrap_or_default();
+        let patterns = commit_message::compile_issue_patterns(issue_patterns);
+
+        let mut commits: Vec<ProvenanceCommit> = Vec::new();
+        let mut uncommitted = Vec::new();
+        for hunk in hunks {
+            let Some(oid) = hunk.commit else {
+                uncommitted.push((hunk.start_line, hunk.end_line));
+                continue;
+            };
+            if let Some(existing) = commits.iter_mut().find(|c| c.details.hash == oid.to_string()) {
+                existing.lines.push((hunk.start_line, hunk.end_line));
+                continue;
+            }
+            let details = git::commit_details(&repo, &repo.find_commit(oid)?, &patterns);
+            let pull_request = pull_request_number(&details.message);
+            commits.push(ProvenanceCommit {
+                lines: vec![(hunk.start_line, hunk.end_line)],
+                pushed: forge::reachable_from(&repo, &tips, oid),
+                commit_url: remote.as_ref().and_then(|info| forge::commit_url(info, &details.hash)),
+                issue_refs: details.parsed.trailers.iter().filter(|t| is_issue_ref(t, &patterns)).cloned().collect(),
+                pull_request,
+                pull_request_url: remote.as_ref().zip(pull_request).and_then(|(info, number)| forge::pull_request_url(info, number)),
+                details,
+            });
+        }
+        commits.sort_by_key(|c| std::cmp::Reverse(c.details.timestamp));
+
+        Ok(LineProvenance {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            start_line,
+            end_line,
+            commits,
+            uncommitted,
+            remote,
+            cached: was_cached,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_message::DEFAULT_ISSUE_TRAILER_PATTERNS;
+    use crate::test_support::{init_repo, temp_dir, write};
+
+    /// Commit everything with a fixed commit time, so "newest first" does not depend on the clock
+    fn commit_at(root: &Path, message: &str, seconds: i64) -> Oid {
+        let repo = Repository::open(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::new("Test User", "test@example.com", &git2::Time::new(seconds, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap()
+    }
+
+    /// lib.rs from two commits: the second rewrote line 2 and names a pull request and an issue
+    fn fixture(root: &Path) -> (Oid, Oid) {
+        write(root, "lib.rs", "one\ntwo\nthree\n");
+        let first = commit_at(root, "Add lib", 1_700_000_000);
+        write(root, "lib.rs", "one\nTWO\nthree\n");
+        let second = commit_at(root, "Fix parsing (#42)\n\nFixes: #7\n", 1_700_000_100);
+        (first, second)
+    }
+
+    fn provenance(cache: &ProvenanceCache, root: &Path, file: &str, start_line: usize, end_line: usize) -> Result<LineProvenance> {
+        let patterns = DEFAULT_ISSUE_TRAILER_PATTERNS.map(String::from);
+        cache.line_provenance(root, &root.join(file), start_line, end_line, &patterns, &HashMap::new())
+    }
+
+    fn blamed(provenance: &LineProvenance) -> Vec<(String, Vec<(usize, usize)>)> {
+        provenance.commits.iter().map(|c| (c.details.hash.clone(), c.lines.clone())).collect()
+    }
+
+    #[test]
+    fn bundles_blame_push_state_links_and_issue_references() {
+        let (_dir, root) = init_repo();
+        let (first, second) = fixture(&root);
+        let repo = Repository::open(&root).unwrap();
+        repo.remote("origin", "https://github.com/acme/widget.git").unwrap();
+        repo.reference("refs/remotes/origin/main", first, true, "fixture").unwrap();
+
+        let cache = ProvenanceCache::default();
+        // A reversed selection is read as the same range
+        let bundle = provenance(&cache, &root, "lib.rs", 3, 1).unwrap();
+        assert_eq!((bundle.path.as_str(), bundle.start_line, bundle.end_line, bundle.cached), ("lib.rs", 1, 3, false));
+        assert_eq!(blamed(&bundle), vec![(second.to_string(), vec![(2, 2)]), (first.to_string(), vec![(1, 1), (3, 3)])]);
+        assert!(bundle.uncommitted.is_empty());
+        assert_eq!(bundle.remote.as_ref().map(|r| r.remote.as_str()), Some("origin"));
+
+        let newest = &bundle.commits[0];
+        assert!(!newest.pushed);
+        assert_eq!(newest.commit_url, Some(format!("https://github.com/acme/widget/commit/{}", second)));
+        assert_eq!(newest.issue_refs, vec![Trailer { key: "Fixes".to_string(), value: "#7".to_string() }]);
+        assert_eq!(newest.pull_request, Some(42));
+        assert_eq!(newest.pull_request_url.as_deref(), Some("https://github.com/acme/widget/pull/42"));
+
+        let oldest = &bundle.commits[1];
+        assert!(oldest.pushed);
+        assert!(oldest.issue_refs.is_empty());
+        assert_eq!((oldest.pull_request, oldest.pull_request_url.as_deref()), (None, None));
+
+        // The same lines of the same content come from the cache, with push state looked up again
+        repo.reference("refs/remotes/origin/main", second, true, "fixture").unwrap();
+        let again = provenance(&cache, &root, "lib.rs", 1, 3).unwrap();
+        assert!(again.cached);
+        assert_eq!(blamed(&again), blamed(&bundle));
+        assert!(again.commits.iter().all(|c| c.pushed));
+    }
+
+    #[test]
+    fn uncommitted_lines_and_new_files_have_no_commit() {
+        let (_dir, root) = init_repo();
+        let (first, second) = fixture(&root);
+        let cache = ProvenanceCache::default();
+        assert!(!provenance(&cache, &root, "lib.rs", 1, 3).unwrap().cached);
+
+        write(&root, "lib.rs", "one\nTWO\nthree!\nfour\n");
+        let edited = provenance(&cache, &root, "lib.rs", 2, 4).unwrap();
+        assert!(!edited.cached, "an edit changes the cache key");
+        assert_eq!(blamed(&edited), vec![(second.to_string(), vec![(2, 2)])]);
+        assert_eq!(edited.uncommitted, vec![(3, 4)]);
+        assert!(edited.remote.is_none());
+        assert!(edited.commits.iter().all(|c| !c.pushed && c.commit_url.is_none()));
+
+        let unchanged = provenance(&cache, &root, "lib.rs", 1, 1).unwrap();
+        assert_eq!(blamed(&unchanged), vec![(first.to_string(), vec![(1, 1)])]);
+
+        write(&root, "new.rs", "fn new() {}\n");
+        let new = provenance(&cache, &root, "new.rs", 1, 5).unwrap();
+        assert_eq!((new.end_line, new.commits.len()), (1, 0));
+        assert_eq!(new.uncommitted, vec![(1, 1)]);
+    }
+
+    #[test]
+    fn selections_outside_the_file_or_repository_are_refused() {
+        let (_dir, root) = init_repo();
+        fixture(&root);
+        let cache = ProvenanceCache::default();
+        assert_eq!(provenance(&cache, &root, "lib.rs", 2, 99).unwrap().end_line, 3);
+        assert!(provenance(&cache, &root, "lib.rs", 4, 4).unwrap_err().to_string().contains("past the end"));
+        assert!(provenance(&cache, &root, "lib.rs", 1, 1 + MAX_RANGE_LINES).unwrap_err().to_string().contains("at most"));
+
+        let (_outside_dir, outside) = temp_dir();
+        write(&outside, "other.rs", "fn other() {}\n");
+        let patterns = DEFAULT_ISSUE_TRAILER_PATTERNS.map(String::from);
+        let error = cache.line_provenance(&root, &outside.join("other.rs"), 1, 1, &patterns, &HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains("not inside the repository"), "{}", error);
+    }
+
+    #[test]
+    fn pull_request_numbers_come_from_forge_merge_messages() {
+        let cases = [
+            ("Add parser (#12)", Some(12)),
+            ("Add parser (#12)\n\nLonger body", Some(12)),
+            ("Merge pull request #7 from acme/feature\n\nAdd parser", Some(7)),
+            ("Add parser\n\nSee merge request acme/widget!15", Some(15)),
+            ("Merged in feature/parser (pull request #3)\n\nAdd parser", Some(3)),
+            ("Mention #12 in passing", None),
+            ("(#5) is not a squash suffix", None),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(pull_request_number(message), expected, "{:?}", message);
+        }
+    }
+}
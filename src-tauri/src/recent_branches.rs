@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use crate::fs::display_path;
+
+pub(crate) const STORE_FILE: &str = "recent-branches.json";
+
+// Entries kept per repository
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Checkout {
+    branch: String,
+    timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecentBranch {
+    pub name: String,
+    pub last_checked_out: i64,
+    pub exists: bool,
+    /// Relative to the default branch; None when either side is missing
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+}
+
+/// Most-recently-checked-out branches per repository, persisted in the app data dir
+pub struct RecentBranchStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<Checkout>>>,
+}
+
+/// Key repositories by their canonical working tree so two spellings share one list
+pub(crate) fn repo_key(repo: &Repository) -> String {
+    let root = repo.workdir().unwrap_or_else(|| repo.path());
+    display_path(&fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()))
+}
+
+/// Branch switches recorded in the HEAD reflog ("checkout: moving from a to b"), newest first
+fn reflog_checkouts(repo: &Repository) -> Vec<Checkout> {
+    let Ok(reflog) = repo.reflog("HEAD") else { return Vec::new() };
+    reflog
+        .iter()
+        .filter_map(|entry| {
+            let message = entry.message()?;
+            let (_, to) = message.strip_prefix("checkout: moving from ")?.rsplit_once(" to ")?;
+            Some(Checkout { branch: to.to_string(), timestamp: entry.committer().when().seconds() })
+        })
+        .collect()
+}
+
+impl RecentBranchStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<Checkout>>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    fn push(&self, repo: &Repository, branch: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let list = entries.entry(repo_key(repo)).or_default();
+        if list.first().map(|c| c.branch == branch).unwrap_or(false) {
+            return;
+        }
+        list.retain(|c| c.branch != branch);
+        list.insert(0, Checkout { branch: branch.to_string(), timestamp: chrono::Utc::now().timestamp() });
+        list.truncate(MAX_ENTRIES);
+        self.persist(&entries);
+    }
+
+    /// Record whatever branch HEAD points at now; detached HEADs are not recorded
+    pub fn record_head(&self, repo_path: &Path) {
+        let Ok(repo) = Repository::discover(repo_path) else { return };
+        let Ok(head) = repo.head() else { return };
+        if !head.is_branch() {
+            return;
+        }
+        if let Some(branch) = head.shorthand() {
+            self.push(&repo, branch);
+        }
+    }
+
+    /// Recently checked-out branches, merging IDE checkouts with external ones from the reflog
+    pub fn recent(&self, repo_path: &Path, limit: usize) -> Result<Vec<RecentBranch>> {
+        let repo = Repository::discover(repo_path)?;
+        let stored = self.entries.lock().unwrap().get(&repo_key(&repo)).cloned().unwrap_or_default();
+
+        let mut latest: HashMap<String, i64> = HashMap::new();
+        for checkout in stored.into_iter().chain(reflog_checkouts(&repo)) {
+            let timestamp = latest.entry(checkout.branch).or_insert(checkout.timestamp);
+            *timestamp = (*timestamp).max(checkout.timestamp);
+        }
+        let mut ordered: Vec<(String, i64)> = latest.into_iter().collect();
+        ordered.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ordered.truncate(limit);
+
+        let default_oid = crate::git::default_branch(&repo)
+            .and_then(|b| repo.find_branch(&b, BranchType::Local).ok())
+            .and_then(|b| b.get().target());
+
+        Ok(ordered
+            .into_iter()
+            .map(|(name, last_checked_out)| {
+                let oid = repo.find_branch(&name, BranchType::Local).ok().and_then(|b| b.get().target());
+                let counts = match (oid, default_oid) {
+                    (Some(oid), Some(default_oid)) => repo.graph_ahead_behind(oid, default_oid).ok(),
+                    _ => None,
+                };
+                RecentBranch {
+                    exists: oid.is_some(),
+                    ahead: counts.map(|(ahead, _)| ahead),
+                    behind: counts.map(|(_, behind)| behind),
+                    name,
+                    last_checked_out,
+                }
+            })
+            .collect())
+    }
+}
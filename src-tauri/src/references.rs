@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{display_path, write_atomic};
+
+// Larger files are generated or bundled and do not import project modules by hand
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+const JS_EXTENSIONS: [&str; 10] = ["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue", "svelte"];
+
+// Extensions a JS/TS specifier may leave out, in resolution order
+const JS_RESOLVE_EXTENSIONS: [&str; 8] = ["ts", "tsx", "d.ts", "js", "jsx", "mjs", "cjs", "json"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceLanguage {
+    JavaScript,
+    Rust,
+    Python,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileReference {
+    pub file: String,
+    /// 1-based
+    pub line: usize,
+    /// The import specifier or module path as written
+    pub specifier: String,
+    pub language: ReferenceLanguage,
+    /// A JS/TS specifier relative to the importing file, which a move can rewrite
+    pub relative: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReferenceUpdate {
+    pub file: String,
+    pub line: usize,
+    pub old_specifier: String,
+    pub new_specifier: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SkippedReference {
+    pub file: String,
+    pub line: usize,
+    pub specifier: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ReferenceUpdateReport {
+    pub updated: Vec<ReferenceUpdate>,
+    /// Aliased, non-JS or no longer matching references that were left alone
+    pub skipped: Vec<SkippedReference>,
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().map(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x))).unwrap_or(false)
+}
+
+fn source_files(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkBuilder::new(root)
+        .filter_entry(|e| e.file_name() != ".git" && e.file_name() != "node_modules")
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|e| e.metadata().map(|m| m.len() <= MAX_FILE_BYTES).unwrap_or(false))
+        .map(|e| e.into_path())
+}
+
+// ---- JavaScript / TypeScript ----
+
+fn js_specifier_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            r#"\bfrom\s*['"]([^'"\n]+)['"]"#,
+            r#"\bimport\s*['"]([^'"\n]+)['"]"#,
+            r#"\brequire\(\s*['"]([^'"\n]+)['"]\s*\)"#,
+            r#"\bimport\(\s*['"]([^'"\n]+)['"]\s*\)"#,
+        ]
+        .iter()
+        .map(|p| Regex::new(p).expect("import pattern"))
+        .collect()
+    })
+}
+
+/// `compilerOptions.baseUrl` and `paths` from the project's tsconfig.json or jsconfig.json
+struct PathAliases {
+    base_url: Option<PathBuf>,
+    paths: Vec<(String, Vec<String>)>,
+}
+
+/// tsconfig allows comments and trailing commas; drop both so serde_json can read it
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek().copied()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    static TRAILING_COMMA: OnceLock<Regex> = OnceLock::new();
+    TRAILING_COMMA.get_or_init(|| Regex::new(r",(\s*[}\]])").expect("trailing comma")).replace_all(&out, "$1").to_string()
+}
+
+fn load_aliases(project: &Path) -> PathAliases {
+    let config = ["tsconfig.json", "jsconfig.json"]
+        .iter()
+        .find_map(|name| fs::read_to_string(project.join(name)).ok())
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&strip_jsonc(&raw)).ok());
+    let options = config.as_ref().and_then(|c| c.get("compilerOptions"));
+    let base_url = options.and_then(|o| o.get("baseUrl")).and_then(|b| b.as_str()).map(|b| normalize(&project.join(b)));
+    let paths = options
+        .and_then(|o| o.get("paths"))
+        .and_then(|p| p.as_object())
+        .map(|paths| {
+            paths
+                .iter()
+                .map(|(pattern, targets)| {
+                    let targets = targets.as_array().into_iter().flatten().filter_map(|t| t.as_str().map(str::to_string)).collect();
+                    (pattern.clone(), targets)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    PathAliases { base_url, paths }
+}
+
+impl PathAliases {
+    /// Where a bare specifier may point according to `paths`, then `baseUrl`
+    fn resolve(&self, project: &Path, specifier: &str) -> Vec<PathBuf> {
+        let base = self.base_url.clone().unwrap_or_else(|| project.to_path_buf());
+        let mut resolved = Vec::new();
+        for (pattern, targets) in &self.paths {
+            let captured = match pattern.split_once('*') {
+                Some((prefix, suffix)) => specifier
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_suffix(suffix))
+                    .map(str::to_string),
+                None => (pattern == specifier).then(String::new),
+            };
+            if let Some(captured) = captured {
+                resolved.extend(targets.iter().map(|t| normalize(&base.join(t.replace('*', &captured)))));
+            }
+        }
+        if self.base_url.is_some() {
+            resolved.push(normalize(&base.join(specifier)));
+        }
+        resolved
+    }
+}
+
+/// Files a resolved specifier path may mean: as written, with an implied extension, as a
+/// directory index, or a `.js` specifier standing for its TypeScript source
+fn js_candidates(base: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![base.to_path_buf()];
+    let text = base.to_string_lossy();
+    for extension in JS_RESOLVE_EXTENSIONS {
+        candidates.push(PathBuf::from(format!("{}.{}", text, extension)));
+        candidates.push(base.join(format!("index.{}", extension)));
+    }
+    for (js, ts) in [(".js", ".ts"), (".js", ".tsx"), (".jsx", ".tsx"), (".mjs", ".mts"), (".cjs", ".cts")] {
+        if let Some(stem) = text.strip_suffix(js) {
+            candidates.push(PathBuf::from(format!("{}{}", stem, ts)));
+        }
+    }
+    candidates
+}
+
+fn js_references(project: &Path, target: &Path, aliases: &PathAliases, file: &Path, text: &str, found: &mut Vec<FileReference>) {
+    let dir = file.parent().unwrap_or(project);
+    for (index, line) in text.lines().enumerate() {
+        for pattern in js_specifier_patterns() {
+            for captures in pattern.captures_iter(line) {
+                let specifier = &captures[1];
+                let relative = specifier.starts_with("./") || specifier.starts_with("../") || specifier == "." || specifier == "..";
+                let bases = if relative { vec![normalize(&dir.join(specifier))] } else { aliases.resolve(project, specifier) };
+                if bases.iter().any(|base| js_candidates(base).iter().any(|c| c == target)) {
+                    found.push(FileReference {
+                        file: display_path(file),
+                        line: index + 1,
+                        specifier: specifier.to_string(),
+                        language: ReferenceLanguage::JavaScript,
+                        relative,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// ---- Rust ----
+
+struct RustModule {
+    crate_src: PathBuf,
+    /// e.g. ["net", "client"]
+    path: Vec<String>,
+    crate_name: Option<String>,
+}
+
+fn rust_module(target: &Path) -> Option<RustModule> {
+    let crate_dir = target.ancestors().skip(1).find(|dir| dir.join("Cargo.toml").is_file())?;
+    let crate_src = crate_dir.join("src");
+    let relative = target.strip_prefix(&crate_src).ok()?;
+    let mut path: Vec<String> = relative.iter().map(|c| c.to_string_lossy().to_string()).collect();
+    let file = path.pop()?;
+    match file.as_str() {
+        "mod.rs" => {}
+        "lib.rs" | "main.rs" if path.is_empty() => return None,
+        _ => path.push(file.strip_suffix(".rs")?.to_string()),
+    }
+    if path.is_empty() {
+        return None;
+    }
+    let manifest = fs::read_to_string(crate_dir.join("Cargo.toml")).ok()?;
+    let crate_name = manifest
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|t| t.get("package")?.get("name")?.as_str().map(|n| n.replace('-', "_")));
+    Some(RustModule { crate_src, path, crate_name })
+}
+
+/// The files that may declare `mod <name>;` for the target module
+fn rust_parent_files(module: &RustModule) -> Vec<PathBuf> {
+    let parent = &module.path[..module.path.len() - 1];
+    if parent.is_empty() {
+        return vec![module.crate_src.join("lib.rs"), module.crate_src.join("main.rs")];
+    }
+    let dir = parent.iter().fold(module.crate_src.clone(), |dir, part| dir.join(part));
+    vec![dir.with_extension("rs"), dir.join("mod.rs")]
+}
+
+fn rust_references(module: &RustModule, file: &Path, text: &str, found: &mut Vec<FileReference>) {
+    let name = module.path.last().map(String::as_str).unwrap_or_default();
+    let joined = module.path.join("::");
+    let mut patterns = Vec::new();
+    if rust_parent_files(module).iter().any(|p| p == file) {
+        patterns.push(format!(r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+{}\s*;", regex::escape(name)));
+    }
+    if file.starts_with(&module.crate_src) {
+        patterns.push(format!(r"\bcrate::{}\b", regex::escape(&joined)));
+        // `super::name` from a sibling module
+        let parent_dir = module.path[..module.path.len() - 1].iter().fold(module.crate_src.clone(), |dir, part| dir.join(part));
+        let sibling = file.parent() == Some(parent_dir.as_path()) && file.file_name().map(|n| n != "mod.rs").unwrap_or(false);
+        if sibling {
+            patterns.push(format!(r"\bsuper::{}\b", regex::escape(name)));
+        }
+    }
+    if let Some(crate_name) = &module.crate_name {
+        patterns.push(format!(r"\b{}::{}\b", regex::escape(crate_name), regex::escape(&joined)));
+    }
+    let patterns: Vec<Regex> = patterns.iter().filter_map(|p| Regex::new(p).ok()).collect();
+    for (index, line) in text.lines().enumerate() {
+        if let Some(matched) = patterns.iter().find_map(|p| p.find(line)) {
+            found.push(FileReference {
+                file: display_path(file),
+                line: index + 1,
+                specifier: matched.as_str().trim().to_string(),
+                language: ReferenceLanguage::Rust,
+                relative: false,
+            });
+        }
+    }
+}
+
+// ---- Python ----
+
+/// Dotted module name of `path` below one of the import roots (the project or its `src`)
+fn python_module(project: &Path, path: &Path) -> Option<String> {
+    let relative = [project.join("src"), project.to_path_buf()].iter().find_map(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))?;
+    let mut parts: Vec<String> = relative.iter().map(|c| c.to_string_lossy().to_string()).collect();
+    let file = parts.pop()?;
+    if file != "__init__.py" {
+        parts.push(file.strip_suffix(".py")?.to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join("."))
+}
+
+fn python_patterns() -> &'static (Regex, Regex) {
+    static PATTERNS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        (
+            Regex::new(r"^\s*from\s+(\.*[\w.]*)\s+import\s+\(?\s*([^#)]*)").expect("from import"),
+            Regex::new(r"^\s*import\s+([\w.]+(?:\s+as\s+\w+)?(?:\s*,\s*[\w.]+(?:\s+as\s+\w+)?)*)").expect("import"),
+        )
+    })
+}
+
+/// Absolute module for a possibly relative `from` clause in `importer`
+fn python_absolute(project: &Path, importer: &Path, module: &str) -> Option<String> {
+    let dots = module.chars().take_while(|c| *c == '.').count();
+    if dots == 0 {
+        return Some(module.to_string());
+    }
+    let package = python_module(project, &importer.with_file_name("__init__.py")).unwrap_or_default();
+    let mut parts: Vec<&str> = package.split('.').filter(|p| !p.is_empty()).collect();
+    for _ in 1..dots {
+        parts.pop()?;
+    }
+    let rest = &module[dots..];
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+    Some(parts.join("."))
+}
+
+fn python_references(project: &Path, target_module: &str, file: &Path, text: &str, found: &mut Vec<FileReference>) {
+    let (from_import, import) = python_patterns();
+    for (index, line) in text.lines().enumerate() {
+        let specifier = if let Some(captures) = from_import.captures(line) {
+            let written = captures[1].to_string();
+            let Some(module) = python_absolute(project, file, &written) else { continue };
+            let names = captures[2].split(',').filter_map(|n| n.split_whitespace().next());
+            let matches = module == target_module
+                || names.into_iter().any(|name| if module.is_empty() { name == target_module } else { format!("{}.{}", module, name) == target_module });
+            matches.then_some(written)
+        } else if let Some(captures) = import.captures(line) {
+            captures[1]
+                .split(',')
+                .filter_map(|m| m.split_whitespace().next())
+                .find(|m| *m == target_module)
+                .map(str::to_string)
+        } else {
+            None
+        };
+        if let Some(specifier) = specifier {
+            found.push(FileReference {
+                file: display_path(file),
+                line: index + 1,
+                specifier,
+                language: ReferenceLanguage::Python,
+                relative: false,
+            });
+        }
+    }
+}
+
+/// Files under `project` that import `target`, with the line and specifier of each import
+pub fn find_file_references(project: &Path, target: &Path) -> Result<Vec<FileReference>> {
+    let project = fs::canonicalize(project)?;
+    let target = fs::canonicalize(target).unwrap_or_else(|_| normalize(target));
+    if !target.starts_with(&project) {
+        return Err(anyhow!("'{}' is not inside the project", target.display()));
+    }
+    let aliases = load_aliases(&project);
+    let rust = has_extension(&target, &["rs"]).then(|| rust_module(&target)).flatten();
+    let python = has_extension(&target, &["py"]).then(|| python_module(&project, &target)).flatten();
+
+    let mut found = Vec::new();
+    for file in source_files(&project) {
+        if file == target {
+            continue;
+        }
+        let is_js = has_extension(&file, &JS_EXTENSIONS);
+        let is_rust = rust.is_some() && has_extension(&file, &["rs"]);
+        let is_python = python.is_some() && has_extension(&file, &["py"]);
+        if !(is_js || is_rust || is_python) {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&file) else { continue };
+        if is_js {
+            js_references(&project, &target, &aliases, &file, &text, &mut found);
+        } else if let (true, Some(module)) = (is_rust, &rust) {
+            rust_references(module, &file, &text, &mut found);
+        } else if let (true, Some(module)) = (is_python, &python) {
+            python_references(&project, module, &file, &text, &mut found);
+        }
+    }
+    Ok(found)
+}
+
+// ---- Rewriting after a move ----
+
+/// Relative path from `dir` to `target`, both absolute and normalized
+fn relative_path(dir: &Path, target: &Path) -> String {
+    let from: Vec<Component> = dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()));
+    parts.join("/")
+}
+
+/// The specifier that reaches `new_target` from `importer` in the same style as `old`:
+/// extension kept or left out, directory index imports kept as directory imports
+fn new_js_specifier(importer: &Path, old: &str, old_target: &Path, new_target: &Path) -> Option<String> {
+    let dir = importer.parent()?;
+    let old_base = normalize(&dir.join(old));
+    let index_import = old_target.parent() == Some(old_base.as_path());
+    let new_stem_path = if index_import {
+        if new_target.file_stem() != old_target.file_stem() {
+            return None;
+        }
+        new_target.parent()?.to_path_buf()
+    } else if old_base == old_target {
+        new_target.to_path_buf()
+    } else if old_base.extension() != old_target.extension() && old_base.with_extension("") == old_target.with_extension("") && old_base.extension().is_some() {
+        // `./util.js` for util.ts: keep the written extension
+        new_target.with_extension(old_base.extension()?)
+    } else {
+        // The extension was left out
+        let name = new_target.file_name()?.to_string_lossy().to_string();
+        let old_name = old_target.file_name()?.to_string_lossy().to_string();
+        let suffix = old_name.strip_prefix(old_base.file_name()?.to_string_lossy().as_ref())?.to_string();
+        new_target.with_file_name(name.strip_suffix(&suffix)?)
+    };
+    let relative = relative_path(dir, &new_stem_path);
+    Some(if relative.starts_with("..") { relative } else if relative.is_empty() { ".".to_string() } else { format!("./{}", relative) })
+}
+
+pub struct PlannedEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub updated: String,
+}
+
+/// Work out how each reference to `old_target` changes once it lives at `new_target`.
+/// Nothing is written; `apply_edits` does that.
+pub fn plan_updates(references: &[FileReference], old_target: &Path, new_target: &Path) -> (Vec<PlannedEdit>, ReferenceUpdateReport) {
+    let mut report = ReferenceUpdateReport::default();
+    let mut by_file: HashMap<String, Vec<&FileReference>> = HashMap::new();
+    for reference in references {
+        if reference.language != ReferenceLanguage::JavaScript || !reference.relative {
+            let reason = if reference.language == ReferenceLanguage::JavaScript { "path alias" } else { "only JS/TS relative imports are rewritten" };
+            report.skipped.push(SkippedReference {
+                file: reference.file.clone(),
+                line: reference.line,
+                specifier: reference.specifier.clone(),
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+        by_file.entry(reference.file.clone()).or_default().push(reference);
+    }
+
+    let mut edits = Vec::new();
+    for (file, references) in by_file {
+        let path = PathBuf::from(&file);
+        let Ok(original) = fs::read_to_string(&path) else { continue };
+        let mut lines: Vec<String> = original.split_inclusive('\n').map(str::to_string).collect();
+        let mut changed = false;
+        for reference in references {
+            let skip = |reason: &str| SkippedReference {
+                file: file.clone(),
+                line: reference.line,
+                specifier: reference.specifier.clone(),
+                reason: reason.to_string(),
+            };
+            let Some(new_specifier) = new_js_specifier(&path, &reference.specifier, old_target, new_target) else {
+                report.skipped.push(skip("no equivalent specifier for the new location"));
+                continue;
+            };
+            let Some(line) = lines.get_mut(reference.line - 1) else {
+                report.skipped.push(skip("line no longer exists"));
+                continue;
+            };
+            let quoted = ['\'', '"'].iter().map(|q| (format!("{}{}{}", q, reference.specifier, q), *q)).find(|(quoted, _)| line.contains(quoted));
+            match quoted {
+                Some((quoted, quote)) => {
+                    *line = line.replacen(&quoted, &format!("{}{}{}", quote, new_specifier, quote), 1);
+                    changed = true;
+                    report.updated.push(ReferenceUpdate {
+                        file: file.clone(),
+                        line: reference.line,
+                        old_specifier: reference.specifier.clone(),
+                        new_specifier,
+                    });
+                }
+                None => report.skipped.push(skip("specifier not found on its line")),
+            }
+        }
+        if changed {
+            edits.push(PlannedEdit { path, updated: lines.concat(), original });
+        }
+    }
+    (edits, report)
+}
+
+/// Write every edit, or none: on a failure the files already written get their original text back
+pub fn apply_edits(edits: &[PlannedEdit]) -> Result<()> {
+    for (index, edit) in edits.iter().enumerate() {
+        if let Err(e) = write_atomic(&edit.path, edit.updated.as_bytes()) {
+            revert_edits(&edits[..index]);
+            return Err(anyhow!("Failed to update imports in '{}': {}", edit.path.display(), e));
+        }
+    }
+    Ok(())
+}
+
+pub fn revert_edits(edits: &[PlannedEdit]) {
+    for edit in edits.iter().rev() {
+        if let Err(e) = write_atomic(&edit.path, edit.original.as_bytes()) {
+            log::error!("Failed to restore '{}': {}", edit.path.display(), e);
+        }
+    }
+}
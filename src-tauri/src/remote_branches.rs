@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use git2::{BranchType, Direction, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::credentials::CredentialStore;
+use crate::git::{self, NetworkUnavailable};
+
+// Listing a remote is a round trip; the branch dialog asks on every keystroke
+const LISTING_TTL: Duration = Duration::from_secs(30);
+
+// A check that takes longer than this is abandoned rather than blocking branch creation
+const LISTING_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum CreateBranchResult {
+    Created {
+        branch: String,
+        checked_out: bool,
+        /// The remote could not be checked; the branch was created anyway
+        remote_check_error: Option<String>,
+    },
+    /// Nothing was created: the branch already exists on the remote. Use
+    /// `checkout_remote_branch`, or retry with `force_local`.
+    RemoteBranchExists {
+        remote: String,
+        branch: String,
+        tip: String,
+        /// Commit time of the tip, when that commit has been fetched
+        last_updated: Option<i64>,
+    },
+}
+
+/// Branch name to commit id, as listed by a remote
+type Heads = HashMap<String, String>;
+
+/// Remote branch heads per (repository, remote), kept for `LISTING_TTL`
+#[derive(Default)]
+pub struct RemoteRefCache(Mutex<HashMap<(PathBuf, String), (Instant, Heads)>>);
+
+impl RemoteRefCache {
+    fn get(&self, repo: &Path, remote: &str) -> Option<HashMap<String, String>> {
+        let cache = self.0.lock().unwrap();
+        let (fetched, heads) = cache.get(&(repo.to_path_buf(), remote.to_string()))?;
+        (fetched.elapsed() < LISTING_TTL).then(|| heads.clone())
+    }
+
+    fn insert(&self, repo: &Path, remote: &str, heads: HashMap<String, String>) {
+        let mut cache = self.0.lock().unwrap();
+        cache.retain(|_, (fetched, _)| fetched.elapsed() < LISTING_TTL);
+        cache.insert((repo.to_path_buf(), remote.to_string()), (Instant::now(), heads));
+    }
+}
+
+/// The remote new branches are checked against: the current branch's upstream remote, else "origin"
+pub fn upstream_remote(repo: &Repository) -> String {
+    repo.head()
+        .ok()
+        .and_then(|head| head.shorthand().map(str::to_string))
+        .and_then(|branch| repo.config().ok()?.get_string(&format!("branch.{}.remote", branch)).ok())
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+/// `git ls-remote --heads`: branch name to tip, without fetching any objects
+fn list_heads(repo_path: &Path, remote_name: &str, username: Option<String>, password: Option<String>) -> Result<HashMap<String, String>> {
+    let repo = Repository::open(repo_path)?;
+    let mut remote = repo.find_remote(remote_name)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(git::credential_callbacks(username, password)), None)
+        .map_err(|e| anyhow!(NetworkUnavailable { message: e.message().to_string() }))?;
+    let heads = connection
+        .list()?
+        .iter()
+        .filter_map(|head| Some((head.name().strip_prefix("refs/heads/")?.to_string(), head.oid().to_string())))
+        .collect();
+    Ok(heads)
+}
+
+/// Remote heads from the cache, or listed with a timeout using the stored credentials
+pub fn remote_heads(cache: &RemoteRefCache, store: &CredentialStore, repo_path: &Path, remote_name: &str) -> Result<HashMap<String, String>> {
+    if let Some(heads) = cache.get(repo_path, remote_name) {
+        return Ok(heads);
+    }
+    let repo = Repository::open(repo_path)?;
+    let (username, password) = git::load_stored_credentials(store, &repo, remote_name).ok().flatten().unzip();
+
+    // libgit2 has no connect timeout; the listing thread is left to finish on its own
+    let (tx, rx) = mpsc::channel();
+    let (path, remote) = (repo_path.to_path_buf(), remote_name.to_string());
+    std::thread::spawn(move || {
+        let _ = tx.send(list_heads(&path, &remote, username, password));
+    });
+    let heads = rx
+        .recv_timeout(LISTING_TIMEOUT)
+        .map_err(|_| anyhow!(NetworkUnavailable { message: format!("listing '{}' timed out", remote_name) }))??;
+    cache.insert(repo_path, remote_name, heads.clone());
+    Ok(heads)
+}
+
+/// What `check_new_branch` found before a branch is created
+pub enum NewBranchCheck {
+    /// Go ahead; `remote_check_error` is set when the remote could not be checked
+    Clear { remote_check_error: Option<String> },
+    /// Nothing should be created; this is the result to hand back
+    OnRemote(CreateBranchResult),
+}
+
+/// Refuse `branch_name` when it already exists locally and, with `check_remote`, look for it on
+/// the upstream remote. A failed remote check does not block creation; it is reported instead.
+/// Listing can take up to `LISTING_TIMEOUT`, so this runs before the index lock is taken.
+pub fn check_new_branch(cache: &RemoteRefCache, store: &CredentialStore, repo_path: &Path, branch_name: &str, check_remote: bool) -> Result<NewBranchCheck> {
+    let repo = Repository::open(repo_path)?;
+    if repo.find_branch(branch_name, BranchType::Local).is_ok() {
+        return Err(anyhow!("Branch '{}' already exists", branch_name));
+    }
+    if !check_remote {
+        return Ok(NewBranchCheck::Clear { remote_check_error: None });
+    }
+
+    let remote = upstream_remote(&repo);
+    match remote_heads(cache, store, repo_path, &remote) {
+        Ok(heads) => match heads.get(branch_name) {
+            Some(tip) => {
+                let last_updated = git2::Oid::from_str(tip).ok().and_then(|oid| repo.find_commit(oid).ok()).map(|c| c.time().seconds());
+                Ok(NewBranchCheck::OnRemote(CreateBranchResult::RemoteBranchExists {
+                    remote,
+                    branch: branch_name.to_string(),
+                    tip: tip.clone(),
+                    last_updated,
+                }))
+            }
+            None => Ok(NewBranchCheck::Clear { remote_check_error: None }),
+        },
+        Err(e) => {
+            log::warn!("Could not check '{}' for branch '{}': {}", remote, branch_name, e);
+            Ok(NewBranchCheck::Clear { remote_check_error: Some(e.to_string()) })
+        }
+    }
+}
+
+/// Create `branch_name` at `start_point` (HEAD by default) and optionally check it out. Runs
+/// under the index lock, which retries it when git's index is locked, so a branch that an
+/// earlier attempt already created at the same commit is reused.
+pub fn create_local_branch(repo_path: &Path, branch_name: &str, start_point: Option<&str>, checkout: bool) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    let target = match start_point {
+        Some(start) => repo.revparse_single(start)?.peel_to_commit()?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+    match repo.find_branch(branch_name, BranchType::Local) {
+        Ok(existing) if existing.get().target() == Some(target.id()) => {}
+        Ok(_) => return Err(anyhow!("Branch '{}' already exists", branch_name)),
+        Err(_) => {
+            repo.branch(branch_name, &target, false)?;
+        }
+    }
+    if checkout {
+        git::checkout_branch(repo_path, branch_name, false, false, false)?;
+    }
+    Ok(())
+}
+
+/// Fetch `remote/branch_name` so `track_remote_branch` can check it out. Refuses a branch that
+/// already exists locally. The fetch is a network call, so it runs before the index lock is taken.
+pub fn fetch_remote_branch(store: &CredentialStore, repo_path: &Path, remote_name: &str, branch_name: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    if repo.find_branch(branch_name, BranchType::Local).is_ok() {
+        return Err(anyhow!("Branch '{}' already exists locally", branch_name));
+    }
+    let (username, password) = git::load_stored_credentials(store, &repo, remote_name).ok().flatten().unzip();
+    let mut remote = repo.find_remote(remote_name)?;
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(git::credential_callbacks(username, password));
+    let refspec = format!("refs/heads/{0}:refs/remotes/{1}/{0}", branch_name, remote_name);
+    remote
+        .fetch(&[refspec.as_str()], Some(&mut fetch_options), None)
+        .map_err(|e| anyhow!(NetworkUnavailable { message: e.message().to_string() }))?;
+    Ok(())
+}
+
+/// Create a local branch tracking the fetched `remote/branch_name` and check it out. Safe to
+/// retry under the index lock: a local branch already tracking it is reused.
+pub fn track_remote_branch(repo_path: &Path, remote_name: &str, branch_name: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    let upstream = format!("{}/{}", remote_name, branch_name);
+    match repo.find_branch(branch_name, BranchType::Local) {
+        Ok(existing) if existing.upstream().ok().and_then(|u| u.name().ok().flatten().map(String::from)).as_deref() == Some(upstream.as_str()) => {}
+        Ok(_) => return Err(anyhow!("Branch '{}' already exists locally", branch_name)),
+        Err(_) => {
+            let commit = repo.find_branch(&upstream, BranchType::Remote)?.get().peel_to_commit()?;
+            let mut local = repo.branch(branch_name, &commit, false)?;
+            local.set_upstream(Some(&upstream))?;
+        }
+    }
+    git::checkout_branch(repo_path, branch_name, false, false, false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn create_local_branch_reuses_only_a_branch_at_the_same_commit() {
+        let (_dir, root) = init_repo();
+        write(&root, "a.txt", "one\n");
+        commit_all(&root, "first");
+
+        // A retry after a locked index finds the branch its first attempt created
+        create_local_branch(&root, "feature", None, false).unwrap();
+        create_local_branch(&root, "feature", None, true).unwrap();
+        assert_eq!(Repository::open(&root).unwrap().head().unwrap().shorthand(), Some("feature"));
+
+        write(&root, "a.txt", "two\n");
+        commit_all(&root, "second");
+        assert!(create_local_branch(&root, "feature", Some("HEAD"), false).is_ok());
+        assert!(create_local_branch(&root, "feature", Some("HEAD~1"), false).is_err());
+    }
+}
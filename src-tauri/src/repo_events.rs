@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use git2::{Repository, RepositoryState};
+
+use crate::fs::display_path;
+
+/// Quiet period after the last `.git` write before the change is classified; a single
+/// `git pull` touches refs, the index and HEAD in quick succession
+const DEBOUNCE: Duration = Duration::from_millis(400);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoChangeKind {
+    BranchSwitched,
+    /// HEAD moved forward on the same branch (commit, pull, merge)
+    CommitsAdded,
+    /// HEAD moved on the same branch without descending from the old commit (reset, rebase, amend)
+    HeadRewritten,
+    IndexChanged,
+    /// Other branches, tags or remote-tracking refs changed (fetch, branch -d)
+    RefsChanged,
+    OperationStarted,
+    OperationEnded,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+struct RepoSnapshot {
+    branch: Option<String>,
+    head: Option<String>,
+    index_modified: Option<std::time::SystemTime>,
+    /// Newest mtime and number of ref files; the count catches refs created or deleted within
+    /// the filesystem's timestamp granularity
+    refs: (Option<std::time::SystemTime>, usize),
+    operation: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RepoExternalChange {
+    pub root: String,
+    pub changes: Vec<RepoChangeKind>,
+    /// None when HEAD is detached
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub previous_head: Option<String>,
+    /// "merge", "rebase", "cherry_pick", "revert" or "bisect" while one is in progress
+    pub operation: Option<String>,
+    /// Relative to the upstream; None without one
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+}
+
+struct MonitoredRepo {
+    snapshot: RepoSnapshot,
+    last_signal: Option<Instant>,
+}
+
+/// Last known state of each open workspace's repository, so `.git` writes from an external
+/// terminal can be turned into one classified event
+#[derive(Default)]
+pub struct RepoChangeMonitor(Mutex<HashMap<PathBuf, MonitoredRepo>>);
+
+pub(crate) fn operation_name(state: RepositoryState) -> Option<&'static str> {
+    match state {
+        RepositoryState::Clean => None,
+        RepositoryState::Merge => Some("merge"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("cherry_pick"),
+        RepositoryState::Bisect => Some("bisect"),
+        RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => Some("rebase"),
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("am"),
+    }
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Newest mtime among the ref stores, and how many loose refs there are; loose refs live in
+/// nested directories
+fn refs_state(git_dir: &Path) -> (Option<std::time::SystemTime>, usize) {
+    let loose: Vec<_> = walkdir::WalkDir::new(git_dir.join("refs"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .collect();
+    let count = loose.len();
+    (loose.into_iter().chain(modified(&git_dir.join("packed-refs"))).max(), count)
+}
+
+fn snapshot(root: &Path) -> Option<RepoSnapshot> {
+    let repo = Repository::discover(root).ok()?;
+    let head = repo.head().ok();
+    Some(RepoSnapshot {
+        branch: head.as_ref().filter(|h| h.is_branch()).and_then(|h| h.shorthand()).map(str::to_string),
+        head: head.as_ref().and_then(|h| h.target()).map(|oid| oid.to_string()),
+        index_modified: modified(&repo.path().join("index")),
+        refs: refs_state(repo.path()),
+        operation: operation_name(repo.state()).map(str::to_string),
+    })
+}
+
+fn ahead_behind(root: &Path) -> Option<(usize, usize)> {
+    let repo = Repository::discover(root).ok()?;
+    let head = repo.head().ok()?;
+    let local = head.target()?;
+    let branch = git2::Branch::wrap(head);
+    let upstream = branch.upstream().ok()?.get().target()?;
+    repo.graph_ahead_behind(local, upstream).ok()
+}
+
+fn classify(root: &Path, before: &RepoSnapshot, after: &RepoSnapshot) -> Vec<RepoChangeKind> {
+    let mut changes = Vec::new();
+    if before.branch != after.branch {
+        changes.push(RepoChangeKind::BranchSwitched);
+    } else if before.head != after.head {
+        let descends = match (&before.head, &after.head, Repository::discover(root)) {
+            (Some(old), Some(new), Ok(repo)) => match (git2::Oid::from_str(old), git2::Oid::from_str(new)) {
+                (Ok(old), Ok(new)) => repo.graph_descendant_of(new, old).unwrap_or(false),
+                _ => false,
+            },
+            // First commit on an unborn branch
+            (None, Some(_), _) => true,
+            _ => false,
+        };
+        changes.push(if descends { RepoChangeKind::CommitsAdded } else { RepoChangeKind::HeadRewritten });
+    }
+    if before.index_modified != after.index_modified {
+        changes.push(RepoChangeKind::IndexChanged);
+    }
+    // A moved HEAD already implies its own ref changed; only report refs when nothing else explains it
+    if before.refs != after.refs && before.head == after.head {
+        changes.push(RepoChangeKind::RefsChanged);
+    }
+    match (&before.operation, &after.operation) {
+        (None, Some(_)) => changes.push(RepoChangeKind::OperationStarted),
+        (Some(_), None) => changes.push(RepoChangeKind::OperationEnded),
+        (Some(old), Some(new)) if old != new => {
+            changes.push(RepoChangeKind::OperationEnded);
+            changes.push(RepoChangeKind::OperationStarted);
+        }
+        _ => {}
+    }
+    changes
+}
+
+/// Whether a watcher event for `path` could mean the repository at `root` changed.
+/// Lock files are skipped; git renames them into place, which reports the real name.
+pub fn is_repo_signal(root: &Path, path: &Path) -> bool {
+    // A bare repository opened as the workspace is its own git directory
+    let git_dir = if crate::repo_health::is_bare_git_dir(root) { root.to_path_buf() } else { root.join(".git") };
+    let Ok(relative) = path.strip_prefix(git_dir) else { return false };
+    if path.extension().map(|e| e == "lock").unwrap_or(false) {
+        return false;
+    }
+    let mut components = relative.components().map(|c| c.as_os_str().to_string_lossy());
+    matches!(
+        components.next().as_deref(),
+        Some("HEAD" | "index" | "packed-refs" | "refs" | "MERGE_HEAD" | "CHERRY_PICK_HEAD" | "REVERT_HEAD"
+            | "BISECT_LOG" | "rebase-merge" | "rebase-apply")
+    )
+}
+
+impl RepoChangeMonitor {
+    /// Record the repository's current state as the baseline for `root`
+    pub fn track(&self, root: &Path) {
+        if let Some(snapshot) = snapshot(root) {
+            self.0.lock().unwrap().insert(root.to_path_buf(), MonitoredRepo { snapshot, last_signal: None });
+        }
+    }
+
+    pub fn forget(&self, root: &Path) {
+        self.0.lock().unwrap().remove(root);
+    }
+
+    /// Note a `.git` write under `root`. Returns true when no flush is pending yet, meaning
+    /// the caller should start one with `settle`.
+    pub fn signal(&self, root: &Path) -> bool {
+        let mut repos = self.0.lock().unwrap();
+        let Some(repo) = repos.get_mut(root) else { return false };
+        let idle = repo.last_signal.is_none();
+        repo.last_signal = Some(Instant::now());
+        idle
+    }
+
+    /// Block until `root` has been quiet for the debounce period, then classify everything
+    /// that changed since the last baseline. None when nothing observable changed.
+    pub fn settle(&self, root: &Path) -> Option<RepoExternalChange> {
+        loop {
+            std::thread::sleep(DEBOUNCE);
+            let mut repos = self.0.lock().unwrap();
+            // Gone when the workspace closed while waiting
+            let repo = repos.get_mut(root)?;
+            match repo.last_signal {
+                Some(last) if last.elapsed() < DEBOUNCE => continue,
+                _ => {
+                    // Cleared before reading so a write landing mid-snapshot starts a new flush
+                    repo.last_signal = None;
+                    break;
+                }
+            }
+        }
+        self.refresh(root)
+    }
+
+    /// Classify what changed since the last baseline right away and make the current state
+    /// the new baseline
+    pub fn refresh(&self, root: &Path) -> Option<RepoExternalChange> {
+        let after = snapshot(root)?;
+        let before = {
+            let mut repos = self.0.lock().unwrap();
+            let repo = repos.get_mut(root)?;
+            std::mem::replace(&mut repo.snapshot, after.clone())
+        };
+        let changes = classify(root, &before, &after);
+        if changes.is_empty() {
+            return None;
+        }
+        let (ahead, behind) = match ahead_behind(root) {
+            Some((ahead, behind)) => (Some(ahead), Some(behind)),
+            None => (None, None),
+        };
+        Some(RepoExternalChange {
+            root: display_path(root),
+            changes,
+            branch: after.branch,
+            head: after.head,
+            previous_head: before.head,
+            operation: after.operation,
+            ahead,
+            behind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git, init_repo, write};
+
+    fn repo_with_commit() -> (tempfile::TempDir, PathBuf, RepoChangeMonitor) {
+        let (dir, root) = init_repo();
+        write(&root, "README.md", "hello");
+        git(&root, &["add", "README.md"]);
+        git(&root, &["commit", "-m", "Initial commit"]);
+        let monitor = RepoChangeMonitor::default();
+        monitor.track(&root);
+        (dir, root, monitor)
+    }
+
+    fn changes(monitor: &RepoChangeMonitor, root: &Path) -> Vec<RepoChangeKind> {
+        monitor.refresh(root).map(|change| change.changes).unwrap_or_default()
+    }
+
+    #[test]
+    fn cli_commit_adds_commits_and_changes_the_index() {
+        let (_dir, root, monitor) = repo_with_commit();
+        let before = git(&root, &["rev-parse", "HEAD"]);
+        write(&root, "README.md", "hello again");
+        git(&root, &["commit", "-am", "Second commit"]);
+
+        let change = monitor.refresh(&root).unwrap();
+        assert_eq!(change.changes, [RepoChangeKind::CommitsAdded, RepoChangeKind::IndexChanged]);
+        assert_eq!(change.previous_head.as_deref(), Some(before.trim()));
+        assert_eq!(change.head.as_deref(), Some(git(&root, &["rev-parse", "HEAD"]).trim()));
+        // The new state is the baseline
+        assert!(monitor.refresh(&root).is_none());
+    }
+
+    #[test]
+    fn cli_checkout_switches_branch() {
+        let (_dir, root, monitor) = repo_with_commit();
+        git(&root, &["checkout", "-q", "-b", "feature"]);
+
+        let change = monitor.refresh(&root).unwrap();
+        assert_eq!(change.changes.first(), Some(&RepoChangeKind::BranchSwitched));
+        assert_eq!(change.branch.as_deref(), Some("feature"));
+    }
+
+    #[test]
+    fn cli_reset_and_amend_rewrite_head() {
+        let (_dir, root, monitor) = repo_with_commit();
+        write(&root, "README.md", "hello again");
+        git(&root, &["commit", "-qam", "Second commit"]);
+        monitor.refresh(&root);
+
+        git(&root, &["reset", "-q", "--hard", "HEAD~1"]);
+        assert!(changes(&monitor, &root).contains(&RepoChangeKind::HeadRewritten));
+
+        git(&root, &["commit", "-q", "--amend", "-m", "Reworded"]);
+        let amended = changes(&monitor, &root);
+        assert!(amended.contains(&RepoChangeKind::HeadRewritten), "{:?}", amended);
+        assert!(!amended.contains(&RepoChangeKind::BranchSwitched), "{:?}", amended);
+    }
+
+    #[test]
+    fn cli_branch_creation_only_changes_refs() {
+        let (_dir, root, monitor) = repo_with_commit();
+        git(&root, &["branch", "other"]);
+        assert_eq!(changes(&monitor, &root), [RepoChangeKind::RefsChanged]);
+
+        git(&root, &["tag", "v1"]);
+        assert_eq!(changes(&monitor, &root), [RepoChangeKind::RefsChanged]);
+    }
+
+    #[test]
+    fn cli_add_only_changes_the_index() {
+        let (_dir, root, monitor) = repo_with_commit();
+        write(&root, "new.txt", "new");
+        git(&root, &["add", "new.txt"]);
+        assert_eq!(changes(&monitor, &root), [RepoChangeKind::IndexChanged]);
+    }
+
+    #[test]
+    fn cli_merge_in_progress_starts_and_abort_ends_an_operation() {
+        let (_dir, root, monitor) = repo_with_commit();
+        let main = git(&root, &["rev-parse", "--abbrev-ref", "HEAD"]).trim().to_string();
+        git(&root, &["checkout", "-q", "-b", "topic"]);
+        write(&root, "topic.txt", "topic");
+        git(&root, &["add", "topic.txt"]);
+        git(&root, &["commit", "-qm", "Topic commit"]);
+        git(&root, &["checkout", "-q", &main]);
+        monitor.refresh(&root);
+
+        git(&root, &["merge", "--no-commit", "--no-ff", "topic"]);
+        let change = monitor.refresh(&root).unwrap();
+        assert!(change.changes.contains(&RepoChangeKind::OperationStarted), "{:?}", change.changes);
+        assert_eq!(change.operation.as_deref(), Some("merge"));
+
+        git(&root, &["merge", "--abort"]);
+        let change = monitor.refresh(&root).unwrap();
+        assert!(change.changes.contains(&RepoChangeKind::OperationEnded), "{:?}", change.changes);
+        assert_eq!(change.operation, None);
+    }
+
+    #[test]
+    fn repo_signals_cover_head_index_refs_and_operations() {
+        let (_dir, root) = init_repo();
+        let git_dir = root.join(".git");
+        for (relative, expected) in [
+            ("HEAD", true),
+            ("index", true),
+            ("packed-refs", true),
+            ("refs/heads/main", true),
+            ("MERGE_HEAD", true),
+            ("rebase-merge/done", true),
+            ("index.lock", false),
+            ("refs/heads/main.lock", false),
+            ("objects/ab/cdef", false),
+            ("logs/HEAD", false),
+        ] {
+            assert_eq!(is_repo_signal(&root, &git_dir.join(relative)), expected, "{}", relative);
+        }
+        assert!(!is_repo_signal(&root, &root.join("src/HEAD")));
+    }
+
+    #[test]
+    fn signal_asks_for_one_flush_until_it_settles() {
+        let (_dir, root, monitor) = repo_with_commit();
+        assert!(monitor.signal(&root));
+        assert!(!monitor.signal(&root));
+        write(&root, "new.txt", "new");
+        git(&root, &["add", "new.txt"]);
+        assert_eq!(monitor.settle(&root).unwrap().changes, [RepoChangeKind::IndexChanged]);
+        assert!(monitor.signal(&root));
+    }
+}
@@ -0,0 +1,423 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use git2::{ErrorClass, ErrorCode, Repository};
+
+use crate::fs::{display_path, write_atomic};
+use crate::git::{identity_errors, run_git_cli};
+
+// A younger index.lock most likely belongs to a git process that is still working
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+// References checked for missing targets; enough to spot a truncated clone without walking history
+const MAX_REFS_CHECKED: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptionKind {
+    MissingHead,
+    /// HEAD exists but is neither a symbolic ref nor an object id
+    InvalidHead,
+    /// Objects a reference or HEAD points at are not in the object database
+    MissingObjects,
+    /// A pack without its index, a leftover temporary pack or unreadable pack data
+    CorruptPack,
+    CorruptIndex,
+    BrokenRef,
+    StaleIndexLock,
+    /// A stored user.name/user.email that commit creation would reject; fix with `set_git_config`
+    InvalidIdentity,
+    /// Anything else libgit2 could not read
+    Unreadable,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairAction {
+    /// Point HEAD at the default branch again
+    RebuildHead,
+    /// Delete index.lock once no git process can be holding it
+    RemoveStaleIndexLock,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HealthIssue {
+    pub kind: CorruptionKind,
+    pub details: String,
+    /// The automated fix for this issue, when there is a safe one
+    pub repair: Option<RepairAction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FsckResult {
+    pub ok: bool,
+    pub output: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RepositoryHealth {
+    pub git_dir: Option<String>,
+    pub healthy: bool,
+    pub issues: Vec<HealthIssue>,
+    pub fsck: Option<FsckResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RepairResult {
+    pub action: RepairAction,
+    pub details: String,
+}
+
+/// Map a libgit2 failure to the corruption it indicates; None for ordinary errors
+/// (missing repository, network, conflicts)
+pub fn classify(error: &git2::Error) -> Option<CorruptionKind> {
+    let message = error.message().to_lowercase();
+    if message.contains("index.lock") {
+        return None;
+    }
+    match error.class() {
+        ErrorClass::Odb if message.contains("pack") => Some(CorruptionKind::CorruptPack),
+        ErrorClass::Odb if error.code() == ErrorCode::NotFound || message.contains("object not found") => Some(CorruptionKind::MissingObjects),
+        ErrorClass::Odb | ErrorClass::Zlib | ErrorClass::Object if message.contains("corrupt") || message.contains("inflate") => Some(CorruptionKind::Unreadable),
+        ErrorClass::Index if message.contains("data in index") || message.contains("index file") || message.contains("corrupt") => Some(CorruptionKind::CorruptIndex),
+        ErrorClass::Reference if message.contains("'head'") && error.code() == ErrorCode::NotFound => Some(CorruptionKind::MissingHead),
+        ErrorClass::Reference if message.contains("corrupt") || message.contains("failed to parse") => Some(CorruptionKind::BrokenRef),
+        ErrorClass::Repository if message.contains("head") => Some(CorruptionKind::InvalidHead),
+        _ => None,
+    }
+}
+
+/// The `.git` directory for `project_path`, found without libgit2 so a repository it refuses
+/// to open can still be inspected. Follows `gitdir:` files (worktrees, submodules) and
+/// accepts a bare repository opened directly.
+pub fn find_git_dir(project_path: &Path) -> Option<PathBuf> {
+    if is_bare_git_dir(project_path) {
+        return Some(project_path.to_path_buf());
+    }
+    for dir in project_path.ancestors() {
+        let candidate = dir.join(".git");
+        if candidate.is_dir() {
+            return Some(candidate);
+        }
+        if candidate.is_file() {
+            let content = fs::read_to_string(&candidate).ok()?;
+            let target = content.lines().find_map(|line| line.strip_prefix("gitdir:"))?.trim();
+            let target = Path::new(target);
+            return Some(if target.is_absolute() { target.to_path_buf() } else { dir.join(target) });
+        }
+    }
+    None
+}
+
+/// The layout of a bare repository: HEAD, objects and refs at the top level
+pub fn is_bare_git_dir(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+fn is_object_id(text: &str) -> bool {
+    (text.len() == 40 || text.len() == 64) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn check_head(git_dir: &Path, issues: &mut Vec<HealthIssue>) {
+    let head = git_dir.join("HEAD");
+    let Ok(content) = fs::read_to_string(&head) else {
+        issues.push(HealthIssue {
+            kind: CorruptionKind::MissingHead,
+            details: format!("'{}' is missing or unreadable", head.display()),
+            repair: Some(RepairAction::RebuildHead),
+        });
+        return;
+    };
+    let content = content.trim();
+    let valid = match content.strip_prefix("ref:") {
+        Some(target) => target.trim().starts_with("refs/"),
+        None => is_object_id(content),
+    };
+    if !valid {
+        let shown: String = content.chars().take(60).collect();
+        issues.push(HealthIssue {
+            kind: CorruptionKind::InvalidHead,
+            details: format!("HEAD does not name a branch or commit: '{}'", shown),
+            repair: Some(RepairAction::RebuildHead),
+        });
+    }
+}
+
+fn check_index(git_dir: &Path, issues: &mut Vec<HealthIssue>) {
+    let index = git_dir.join("index");
+    if let Ok(bytes) = fs::read(&index) {
+        // Header: "DIRC", version, entry count; a SHA trailer follows the entries
+        if bytes.len() < 32 || &bytes[..4] != b"DIRC" {
+            issues.push(HealthIssue {
+                kind: CorruptionKind::CorruptIndex,
+                details: format!("'{}' is truncated or not an index file", index.display()),
+                repair: None,
+            });
+        }
+    }
+
+    let lock = git_dir.join("index.lock");
+    let age = fs::metadata(&lock).and_then(|m| m.modified()).ok().and_then(|t| t.elapsed().ok());
+    if let Some(age) = age.filter(|age| *age >= STALE_LOCK_AGE) {
+        issues.push(HealthIssue {
+            kind: CorruptionKind::StaleIndexLock,
+            details: format!("'{}' has existed for {}s; git commands touching the index will fail", lock.display(), age.as_secs()),
+            repair: Some(RepairAction::RemoveStaleIndexLock),
+        });
+    }
+}
+
+/// Packs missing their .idx and temporary packs left by a clone or fetch that was killed
+fn check_packs(git_dir: &Path, issues: &mut Vec<HealthIssue>) {
+    let objects = git_dir.join("objects");
+    if !objects.is_dir() {
+        issues.push(HealthIssue {
+            kind: CorruptionKind::MissingObjects,
+            details: format!("'{}' does not exist", objects.display()),
+            repair: None,
+        });
+        return;
+    }
+    let Ok(entries) = fs::read_dir(objects.join("pack")) else { return };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("tmp_pack_") || name.starts_with("tmp_idx_") {
+            issues.push(HealthIssue {
+                kind: CorruptionKind::CorruptPack,
+                details: format!("Leftover temporary pack '{}' from an interrupted clone or fetch", name),
+                repair: None,
+            });
+        } else if path.extension().map(|e| e == "pack").unwrap_or(false) && !path.with_extension("idx").exists() {
+            issues.push(HealthIssue {
+                kind: CorruptionKind::CorruptPack,
+                details: format!("Pack '{}' has no index", name),
+                repair: None,
+            });
+        }
+    }
+}
+
+/// Open the repository and make sure HEAD and the references resolve to objects that exist
+fn check_objects(git_dir: &Path, issues: &mut Vec<HealthIssue>) {
+    let repo = match Repository::open(git_dir) {
+        Ok(repo) => repo,
+        Err(e) => {
+            // HEAD problems were already reported from the file itself
+            let kind = classify(&e).unwrap_or(CorruptionKind::Unreadable);
+            if !issues.iter().any(|issue| issue.kind == kind) {
+                issues.push(HealthIssue { kind, details: e.message().to_string(), repair: None });
+            }
+            return;
+        }
+    };
+
+    match repo.head() {
+        Ok(head) => {
+            if let Err(e) = head.peel_to_commit() {
+                issues.push(HealthIssue {
+                    kind: classify(&e).unwrap_or(CorruptionKind::MissingObjects),
+                    details: format!("HEAD cannot be read: {}", e.message()),
+                    repair: None,
+                });
+            }
+        }
+        // A new repository's branch has no commits yet
+        Err(e) if e.code() == ErrorCode::UnbornBranch => {}
+        Err(e) => {
+            if let Some(kind) = classify(&e).filter(|kind| !issues.iter().any(|issue| issue.kind == *kind)) {
+                issues.push(HealthIssue { kind, details: e.message().to_string(), repair: None });
+            }
+        }
+    }
+
+    let Ok(references) = repo.references() else { return };
+    for reference in references.take(MAX_REFS_CHECKED) {
+        let reference = match reference {
+            Ok(reference) => reference,
+            Err(e) => {
+                issues.push(HealthIssue { kind: CorruptionKind::BrokenRef, details: e.message().to_string(), repair: None });
+                continue;
+            }
+        };
+        let name = reference.name().unwrap_or("(invalid name)").to_string();
+        if let Some(target) = reference.target() {
+            if repo.find_object(target, None).is_err() {
+                issues.push(HealthIssue {
+                    kind: CorruptionKind::MissingObjects,
+                    details: format!("'{}' points at {}, which is not in the object database", name, target),
+                    repair: None,
+                });
+            }
+        }
+    }
+
+    if let Err(e) = repo.index() {
+        if !issues.iter().any(|issue| issue.kind == CorruptionKind::CorruptIndex) {
+            issues.push(HealthIssue { kind: CorruptionKind::CorruptIndex, details: e.message().to_string(), repair: None });
+        }
+    }
+}
+
+/// Inspect the repository's files directly, then through libgit2, and optionally `git fsck`
+/// Identities stored before `set_git_config` validated its input, at the levels this app writes
+fn check_identity(git_dir: &Path, issues: &mut Vec<HealthIssue>) {
+    let Ok(config) = Repository::open(git_dir).and_then(|repo| repo.config()) else { return };
+    for (level, label) in [(git2::ConfigLevel::Local, "repository"), (git2::ConfigLevel::Global, "global")] {
+        let Ok(level_config) = config.open_level(level) else { continue };
+        let name = level_config.get_string("user.name").ok();
+        let email = level_config.get_string("user.email").ok();
+        if name.is_none() && email.is_none() {
+            continue;
+        }
+        // Check only the fields set at this level; the other may come from elsewhere
+        let errors = identity_errors(name.as_deref().unwrap_or("placeholder"), email.as_deref().unwrap_or("placeholder@host"));
+        for error in errors {
+            issues.push(HealthIssue {
+                kind: CorruptionKind::InvalidIdentity,
+                details: format!("Stored {} user.{}: {}", label, error.field, error.message),
+                repair: None,
+            });
+        }
+    }
+}
+
+pub fn check_repository_health(project_path: &Path, run_fsck: bool) -> RepositoryHealth {
+    let Some(git_dir) = find_git_dir(project_path) else {
+        return RepositoryHealth { git_dir: None, healthy: true, issues: Vec::new(), fsck: None };
+    };
+    let mut issues = Vec::new();
+    check_head(&git_dir, &mut issues);
+    check_index(&git_dir, &mut issues);
+    check_packs(&git_dir, &mut issues);
+    check_objects(&git_dir, &mut issues);
+    check_identity(&git_dir, &mut issues);
+
+    let fsck = run_fsck.then(|| {
+        let workdir = git_dir.parent().unwrap_or(project_path);
+        let mut output = Vec::new();
+        let result = run_git_cli(workdir, &["fsck", "--no-progress", "--connectivity-only"], |line| output.push(line.to_string()));
+        if let Err(e) = &result {
+            output.push(e.to_string());
+        }
+        FsckResult { ok: result.is_ok(), output }
+    });
+
+    RepositoryHealth {
+        git_dir: Some(display_path(&git_dir)),
+        healthy: issues.is_empty() && fsck.as_ref().map(|f| f.ok).unwrap_or(true),
+        issues,
+        fsck,
+    }
+}
+
+/// Whether any git process is running: Some(false) only when that could be confirmed
+pub fn git_process_running() -> Option<bool> {
+    #[cfg(windows)]
+    {
+        let output = std::process::Command::new("tasklist").args(["/FI", "IMAGENAME eq git.exe", "/NH"]).output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).to_lowercase().contains("git.exe"))
+    }
+    #[cfg(not(windows))]
+    {
+        // pgrep exits 1 when nothing matches and 2+ on errors
+        let status = std::process::Command::new("pgrep").args(["-x", "git"]).status().ok()?;
+        match status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Branch HEAD should name: origin's default if known, else main or master, else any local branch
+fn branch_for_head(git_dir: &Path) -> String {
+    let origin_head = fs::read_to_string(git_dir.join("refs/remotes/origin/HEAD")).ok();
+    let packed = fs::read_to_string(git_dir.join("packed-refs")).unwrap_or_default();
+    let local_exists = |branch: &str| {
+        git_dir.join("refs/heads").join(branch).is_file() || packed.lines().any(|line| line.ends_with(&format!(" refs/heads/{}", branch)))
+    };
+
+    if let Some(branch) = origin_head.as_deref().and_then(|h| h.trim().strip_prefix("ref: refs/remotes/origin/")) {
+        if local_exists(branch) {
+            return branch.to_string();
+        }
+    }
+    if let Some(branch) = ["main", "master"].into_iter().find(|b| local_exists(b)) {
+        return branch.to_string();
+    }
+    let loose = fs::read_dir(git_dir.join("refs/heads"))
+        .ok()
+        .and_then(|entries| entries.filter_map(|e| e.ok()).filter(|e| e.path().is_file()).map(|e| e.file_name().to_string_lossy().to_string()).min());
+    let packed_branch = packed.lines().filter_map(|line| line.split_once(" refs/heads/").map(|(_, b)| b.to_string())).min();
+    loose.or(packed_branch).unwrap_or_else(|| "main".to_string())
+}
+
+pub fn repair(project_path: &Path, action: RepairAction) -> Result<RepairResult> {
+    let git_dir = find_git_dir(project_path).ok_or_else(|| anyhow!("'{}' is not inside a git repository", project_path.display()))?;
+    match action {
+        RepairAction::RebuildHead => {
+            // Diagnose again: a healthy HEAD may be detached or on a branch the user chose
+            let mut issues = Vec::new();
+            check_head(&git_dir, &mut issues);
+            if issues.is_empty() {
+                return Err(anyhow!("HEAD in '{}' is intact; refusing to rewrite it", display_path(&git_dir)));
+            }
+            let head = git_dir.join("HEAD");
+            let branch = branch_for_head(&git_dir);
+            // Keep whatever was there for inspection
+            if head.exists() {
+                let backup = git_dir.join(format!("HEAD.corrupt-{}", chrono::Utc::now().timestamp()));
+                fs::copy(&head, &backup)?;
+            }
+            write_atomic(&head, format!("ref: refs/heads/{}\n", branch).as_bytes()).map_err(|e| anyhow!(e.to_string()))?;
+            Ok(RepairResult { action, details: format!("HEAD now points at refs/heads/{}", branch) })
+        }
+        RepairAction::RemoveStaleIndexLock => {
+            let lock = git_dir.join("index.lock");
+            if !lock.exists() {
+                return Ok(RepairResult { action, details: "There is no index.lock".to_string() });
+            }
+            match git_process_running() {
+                Some(false) => {}
+                Some(true) => return Err(anyhow!("A git process is running and may hold '{}'; close it and try again", lock.display())),
+                None => return Err(anyhow!("Could not confirm that no git process holds '{}'", lock.display())),
+            }
+            fs::remove_file(&lock)?;
+            Ok(RepairResult { action, details: format!("Removed '{}'", lock.display()) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_all, git, init_repo, write};
+
+    #[test]
+    fn rebuild_head_refuses_to_touch_an_intact_head() {
+        let (_dir, root) = init_repo();
+        write(&root, "a.txt", "one");
+        commit_all(&root, "first");
+        git(&root, &["checkout", "-q", "-b", "feature"]);
+        git(&root, &["checkout", "-q", "--detach"]);
+        let before = fs::read_to_string(root.join(".git/HEAD")).unwrap();
+
+        assert!(repair(&root, RepairAction::RebuildHead).is_err());
+        assert_eq!(fs::read_to_string(root.join(".git/HEAD")).unwrap(), before);
+    }
+
+    #[test]
+    fn rebuild_head_repairs_a_corrupt_head() {
+        let (_dir, root) = init_repo();
+        write(&root, "a.txt", "one");
+        commit_all(&root, "first");
+        let branch = fs::read_to_string(root.join(".git/HEAD")).unwrap();
+        fs::write(root.join(".git/HEAD"), "garbage").unwrap();
+
+        repair(&root, RepairAction::RebuildHead).unwrap();
+        assert_eq!(fs::read_to_string(root.join(".git/HEAD")).unwrap(), branch);
+    }
+}
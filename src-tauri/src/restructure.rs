@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobMatcher};
+
+use crate::fs::{display_path, unique_destination_avoiding};
+#[cfg(feature = "git")]
+use crate::git::IndexMoveResult;
+
+/// What to do when a moved item would land on an existing name
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    /// Move under the next free "name copy" name
+    Rename,
+    /// Replace what is there; it is kept aside until the whole changeset succeeds
+    Overwrite,
+}
+
+/// Children of the partitioned directory whose name matches `glob` move into `target_subdir`.
+/// `{initial}` in `target_subdir` stands for the lower-cased first letter of the name.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartitionRule {
+    pub glob: String,
+    pub target_subdir: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestructureMove {
+    pub from: String,
+    pub to: String,
+    /// "target_exists", or "same_as_directory" for a child named like the flattened directory
+    pub conflict: Option<String>,
+    /// The strategy applied to the conflict; a rename is already reflected in `to`
+    pub resolution: Option<ConflictStrategy>,
+    /// After applying: "moved", "skipped", "failed" or "rolled_back"
+    pub status: Option<String>,
+    pub error: Option<String>,
+    #[cfg(feature = "git")]
+    pub index: Option<IndexMoveResult>,
+}
+
+impl RestructureMove {
+    pub fn skipped(&self) -> bool {
+        self.resolution == Some(ConflictStrategy::Skip)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestructurePlan {
+    pub directory: String,
+    pub moves: Vec<RestructureMove>,
+    pub conflicts: usize,
+    pub applied: bool,
+    /// A move failed and every earlier move of the changeset was undone
+    pub rolled_back: bool,
+    /// Flatten only: the emptied directory was removed
+    pub removed_directory: bool,
+}
+
+/// Immediate children of `dir`, sorted by name, without `.git`
+fn children(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut children: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.file_name().map(|n| n != ".git").unwrap_or(false))
+        .collect();
+    children.sort();
+    Ok(children)
+}
+
+/// Turn (from, to) pairs into moves, resolving collisions with `strategy`
+fn resolve(directory: &Path, pairs: Vec<(PathBuf, PathBuf)>, strategy: ConflictStrategy, flattened: Option<&Path>) -> RestructurePlan {
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    let mut moves = Vec::new();
+    for (from, mut to) in pairs {
+        let conflict = if Some(to.as_path()) == flattened {
+            Some("same_as_directory")
+        } else if to.exists() || taken.contains(&to) {
+            Some("target_exists")
+        } else {
+            None
+        };
+        let resolution = conflict.map(|reason| match (reason, strategy) {
+            // The directory being flattened cannot be overwritten by its own child
+            ("same_as_directory", ConflictStrategy::Overwrite) => ConflictStrategy::Rename,
+            // Nor can two items of this changeset land on the same name
+            (_, ConflictStrategy::Overwrite) if taken.contains(&to) => ConflictStrategy::Rename,
+            (_, strategy) => strategy,
+        });
+        if resolution == Some(ConflictStrategy::Rename) {
+            if let (Some(parent), Some(name)) = (to.parent(), to.file_name()) {
+                to = unique_destination_avoiding(parent, name, &taken);
+            }
+        }
+        if resolution != Some(ConflictStrategy::Skip) {
+            taken.insert(to.clone());
+        }
+        moves.push(RestructureMove {
+            from: display_path(&from),
+            to: display_path(&to),
+            conflict: conflict.map(str::to_string),
+            resolution,
+            status: None,
+            error: None,
+            #[cfg(feature = "git")]
+            index: None,
+        });
+    }
+    RestructurePlan {
+        directory: display_path(directory),
+        conflicts: moves.iter().filter(|m| m.conflict.is_some()).count(),
+        moves,
+        applied: false,
+        rolled_back: false,
+        removed_directory: false,
+    }
+}
+
+/// Move every child of `dir` into its parent
+pub fn plan_flatten(dir: &Path, strategy: ConflictStrategy) -> Result<RestructurePlan> {
+    let parent = dir.parent().ok_or_else(|| anyhow!("Cannot flatten a root directory"))?;
+    let pairs = children(dir)?
+        .into_iter()
+        .filter_map(|child| {
+            let name = child.file_name()?.to_os_string();
+            Some((child, parent.join(name)))
+        })
+        .collect();
+    Ok(resolve(dir, pairs, strategy, Some(dir)))
+}
+
+fn expand_subdir(template: &str, name: &str) -> String {
+    let initial = match name.trim_start_matches('.').chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_lowercase().to_string(),
+        Some(c) if c.is_ascii_digit() => "0-9".to_string(),
+        _ => "_".to_string(),
+    };
+    template.replace("{initial}", &initial)
+}
+
+/// Group the children of `dir` into subdirectories; the first matching rule wins and
+/// children no rule matches stay where they are
+pub fn plan_partition(dir: &Path, rules: &[PartitionRule], strategy: ConflictStrategy) -> Result<RestructurePlan> {
+    let matchers: Vec<(GlobMatcher, &str)> = rules
+        .iter()
+        .map(|rule| {
+            let subdir = rule.target_subdir.trim_matches('/');
+            if subdir.is_empty() || Path::new(subdir).is_absolute() || subdir.split('/').any(|part| part == ".." || part == ".") {
+                return Err(anyhow!("Target folder '{}' must be a relative path inside the directory", rule.target_subdir));
+            }
+            let matcher = Glob::new(&rule.glob).map_err(|e| anyhow!("Invalid glob '{}': {}", rule.glob, e))?.compile_matcher();
+            Ok((matcher, subdir))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut pairs = Vec::new();
+    for child in children(dir)? {
+        let Some(name) = child.file_name().map(|n| n.to_string_lossy().to_string()) else { continue };
+        let Some((_, template)) = matchers.iter().find(|(matcher, _)| matcher.is_match(&name)) else { continue };
+        let subdir = expand_subdir(template, &name);
+        // A target folder that already exists is not moved into itself
+        if subdir.split('/').next() == Some(name.as_str()) {
+            continue;
+        }
+        pairs.push((child.clone(), dir.join(&subdir).join(&name)));
+    }
+    Ok(resolve(dir, pairs, strategy, None))
+}
+
+struct DoneMove {
+    from: PathBuf,
+    to: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+fn undo(done: &[DoneMove], created: &[PathBuf]) {
+    for step in done.iter().rev() {
+        if let Err(e) = fs::rename(&step.to, &step.from) {
+            log::error!("Failed to move {} back to {}: {}", step.to.display(), step.from.display(), e);
+            continue;
+        }
+        if let Some(backup) = &step.backup {
+            if let Err(e) = fs::rename(backup, &step.to) {
+                log::error!("Failed to restore {} from {}: {}", step.to.display(), backup.display(), e);
+            }
+        }
+    }
+    for dir in created.iter().rev() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+/// Perform the plan's moves as one changeset: if any move fails, the earlier ones are undone,
+/// overwritten items are put back and `rolled_back` is set, with the failing move's error
+pub fn apply(plan: &mut RestructurePlan) {
+    let mut done: Vec<DoneMove> = Vec::new();
+    let mut created: Vec<PathBuf> = Vec::new();
+    let mut failure = None;
+
+    for (index, item) in plan.moves.iter_mut().enumerate() {
+        if item.skipped() {
+            item.status = Some("skipped".to_string());
+            continue;
+        }
+        let (from, to) = (PathBuf::from(&item.from), PathBuf::from(&item.to));
+        let result = (|| -> Result<Option<PathBuf>> {
+            if let Some(parent) = to.parent() {
+                let mut missing = Vec::new();
+                let mut ancestor = Some(parent);
+                while let Some(dir) = ancestor.filter(|d| !d.exists()) {
+                    missing.push(dir.to_path_buf());
+                    ancestor = dir.parent();
+                }
+                fs::create_dir_all(parent)?;
+                created.extend(missing.into_iter().rev());
+            }
+            let backup = if item.resolution == Some(ConflictStrategy::Overwrite) && fs::symlink_metadata(&to).is_ok() {
+                let name = to.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let backup = to.with_file_name(format!(".{}.restructure-{}.bak", name, std::process::id()));
+                fs::rename(&to, &backup)?;
+                Some(backup)
+            } else {
+                None
+            };
+            if let Err(e) = crate::fs::move_path(&from, &to) {
+                if let Some(backup) = &backup {
+                    let _ = fs::rename(backup, &to);
+                }
+                return Err(anyhow!(e));
+            }
+            Ok(backup)
+        })();
+        match result {
+            Ok(backup) => done.push(DoneMove { from, to, backup }),
+            Err(e) => {
+                item.status = Some("failed".to_string());
+                item.error = Some(e.to_string());
+                failure = Some(index);
+                break;
+            }
+        }
+    }
+
+    if let Some(failed_index) = failure {
+        undo(&done, &created);
+        for item in plan.moves.iter_mut().take(failed_index).filter(|m| !m.skipped()) {
+            item.status = Some("rolled_back".to_string());
+        }
+        plan.rolled_back = true;
+        return;
+    }
+
+    for step in &done {
+        if let Some(backup) = &step.backup {
+            if let Err(e) = crate::fs::delete_path(backup) {
+                log::warn!("Failed to remove overwritten {}: {}", backup.display(), e);
+            }
+        }
+    }
+    for item in plan.moves.iter_mut().filter(|m| !m.skipped()) {
+        item.status = Some("moved".to_string());
+    }
+    plan.applied = true;
+}
@@ -0,0 +1,379 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use git2::{Diff, DiffOptions, ObjectType, Oid, Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::chunked::MaybeChunked;
+use crate::fs::display_path;
+use crate::git::{self, DIFF_SUPPRESSED_NOTICE};
+
+// Commits listed for a branch scope; older ones are counted but not described
+const MAX_COMMITS: usize = 200;
+
+/// Which changes a review bundle describes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewScope {
+    /// HEAD to the index
+    Staged,
+    /// The index to the working tree, untracked files included
+    Worktree,
+    /// Commits on HEAD since it forked from the base ref, merge-base to HEAD
+    BranchVsRef,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ReviewBundleOptions {
+    /// Patch text kept per file; the rest is replaced with a truncation marker
+    pub max_file_bytes: usize,
+    /// Patch text across the bundle; later files keep only their stats
+    pub max_total_bytes: usize,
+    pub context_lines: u32,
+    /// Also render the bundle as markdown
+    pub markdown: bool,
+}
+
+impl Default for ReviewBundleOptions {
+    fn default() -> Self {
+        Self { max_file_bytes: 64 * 1024, max_total_bytes: 2 * 1024 * 1024, context_lines: 3, markdown: false }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReviewFile {
+    /// Repo-relative, `/`-separated; the new path for renames
+    pub path: String,
+    pub old_path: Option<String>,
+    /// "added", "deleted", "modified", "renamed", "copied" or "typechange"
+    pub status: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+    pub is_generated: bool,
+    /// .gitattributes suppresses the diff, so only the stats are included
+    pub diff_suppressed: bool,
+    /// Unified diff, possibly ending in a truncation marker
+    pub patch: Option<String>,
+    /// The patch was cut short or, past the total limit, left out
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReviewCommit {
+    pub hash: String,
+    pub author: String,
+    pub timestamp: i64,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReviewWorkspace {
+    pub root: String,
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    /// Uncommitted changes, staged or not, besides anything this bundle describes
+    pub dirty: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReviewBundle {
+    pub scope: ReviewScope,
+    pub base_ref: Option<String>,
+    /// For the branch scope: where HEAD forked from `base_ref`
+    pub merge_base: Option<String>,
+    pub workspace: ReviewWorkspace,
+    pub files: Vec<ReviewFile>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Branch scope only, newest first
+    pub commits: Vec<ReviewCommit>,
+    /// Commits past the listing limit
+    pub commits_omitted: usize,
+    /// Any file's patch was truncated or left out
+    pub truncated: bool,
+    /// Hash of HEAD, the base and every changed path's content; compare with
+    /// `review_manifest_hash` to tell whether the workspace moved on since the export
+    pub manifest_hash: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<String>,
+}
+
+/// Where `export_review_bundle` wrote the bundle, or the bundle itself
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReviewBundleOutput {
+    Written {
+        path: String,
+        markdown_path: Option<String>,
+        manifest_hash: String,
+        files: usize,
+        truncated: bool,
+    },
+    Inline {
+        bundle: MaybeChunked<ReviewBundle>,
+    },
+}
+
+/// Cut `patch` to at most `limit` bytes at a line end, noting what was dropped
+fn truncate_patch(patch: &str, limit: usize) -> String {
+    let mut end = limit.min(patch.len());
+    while !patch.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = patch[..end].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let dropped = patch.len() - end;
+    format!("{}[truncated: {} more bytes, {} more lines]\n", &patch[..end], dropped, patch[end..].lines().count())
+}
+
+fn review_files(repo: &Repository, diff: &Diff, options: &ReviewBundleOptions) -> Result<Vec<ReviewFile>> {
+    let mut files = Vec::new();
+    let mut total = 0;
+    for (index, delta) in diff.deltas().enumerate() {
+        let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().replace('\\', "/"));
+        let path = path_of(delta.new_file()).or_else(|| path_of(delta.old_file())).unwrap_or_default();
+        let old_path = matches!(delta.status(), git2::Delta::Renamed | git2::Delta::Copied).then(|| path_of(delta.old_file())).flatten();
+        let attributes = git::diff_attributes(repo, Path::new(&path));
+        let mut file = ReviewFile {
+            path,
+            old_path,
+            status: git::delta_status(delta.status()).to_string(),
+            additions: 0,
+            deletions: 0,
+            binary: delta.flags().is_binary(),
+            is_generated: attributes.is_generated,
+            diff_suppressed: attributes.diff_suppressed,
+            patch: None,
+            truncated: false,
+        };
+        if let Some(mut patch) = git2::Patch::from_diff(diff, index)? {
+            let (_, additions, deletions) = patch.line_stats()?;
+            file.additions = additions;
+            file.deletions = deletions;
+            file.binary |= patch.delta().flags().is_binary();
+            if !file.binary && !file.diff_suppressed {
+                let text = String::from_utf8_lossy(&patch.to_buf()?).to_string();
+                let room = options.max_file_bytes.min(options.max_total_bytes.saturating_sub(total));
+                if room == 0 {
+                    file.truncated = true;
+                } else if text.len() > room {
+                    let cut = truncate_patch(&text, room);
+                    total += cut.len();
+                    file.patch = Some(cut);
+                    file.truncated = true;
+                } else {
+                    total += text.len();
+                    file.patch = Some(text);
+                }
+            }
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Commits reachable from HEAD but not from the merge-base, newest first
+fn branch_commits(repo: &Repository, head: Oid, merge_base: Oid) -> Result<(Vec<ReviewCommit>, usize)> {
+    let mut walk = repo.revwalk()?;
+    walk.push(head)?;
+    walk.hide(merge_base)?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    let mut commits = Vec::new();
+    let mut omitted = 0;
+    for oid in walk {
+        let oid = oid?;
+        if commits.len() == MAX_COMMITS {
+            omitted += 1;
+            continue;
+        }
+        let commit = repo.find_commit(oid)?;
+        commits.push(ReviewCommit {
+            hash: oid.to_string(),
+            author: commit.author().name().unwrap_or("Unknown").to_string(),
+            timestamp: commit.time().seconds(),
+            message: commit.message().unwrap_or("").to_string(),
+        });
+    }
+    Ok((commits, omitted))
+}
+
+fn resolve_merge_base(repo: &Repository, base_ref: Option<&str>) -> Result<(String, Oid)> {
+    let base_ref = base_ref.filter(|r| !r.is_empty()).ok_or_else(|| anyhow!("The branch scope needs a base ref"))?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = repo
+        .revparse_single(base_ref)
+        .map_err(|_| anyhow!("Cannot resolve '{}'", base_ref))?
+        .peel_to_commit()?;
+    let merge_base = repo
+        .merge_base(head.id(), base.id())
+        .map_err(|_| anyhow!("HEAD and '{}' have no common history", base_ref))?;
+    Ok((base_ref.to_string(), merge_base))
+}
+
+/// Hash of everything a bundle for `scope` depends on: HEAD, the merge-base, and each changed
+/// path with its index and working-tree content. Cheaper than rebuilding the bundle.
+fn manifest_hash(repo: &Repository, scope: ReviewScope, merge_base: Option<Oid>) -> Result<String> {
+    let workdir = git::require_workdir(repo)?;
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}\n", scope));
+    if let Some(head) = repo.head().ok().and_then(|h| h.target()) {
+        hasher.update(format!("head {}\n", head));
+    }
+    if let Some(base) = merge_base {
+        hasher.update(format!("base {}\n", base));
+    }
+
+    let index = repo.index()?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    let mut entries: Vec<String> = statuses
+        .iter()
+        .filter_map(|entry| {
+            let path = entry.path()?.to_string();
+            let staged = index.get_path(Path::new(&path), 0).map(|e| e.id.to_string()).unwrap_or_default();
+            let on_disk = Oid::hash_file(ObjectType::Blob, workdir.join(&path)).map(|oid| oid.to_string()).unwrap_or_default();
+            Some(format!("{} {} {} {}\n", path, entry.status().bits(), staged, on_disk))
+        })
+        .collect();
+    entries.sort();
+    for entry in entries {
+        hasher.update(entry);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The current manifest hash for a bundle of `scope`, to compare with an exported bundle's
+pub fn review_manifest_hash(repo_path: &Path, scope: ReviewScope, base_ref: Option<&str>) -> Result<String> {
+    let repo = Repository::discover(repo_path)?;
+    let merge_base = match scope {
+        ReviewScope::BranchVsRef => Some(resolve_merge_base(&repo, base_ref)?.1),
+        _ => None,
+    };
+    manifest_hash(&repo, scope, merge_base)
+}
+
+/// File list, patches within the size limits, commits for the branch scope and workspace state
+pub fn build_review_bundle(repo_path: &Path, scope: ReviewScope, base_ref: Option<&str>, options: &ReviewBundleOptions) -> Result<ReviewBundle> {
+    let repo = Repository::discover(repo_path)?;
+    let workdir = git::require_workdir(&repo)?.to_path_buf();
+    let head = repo.head().ok();
+    let head_commit = head.as_ref().and_then(|h| h.peel_to_commit().ok());
+
+    let mut diff_options = DiffOptions::new();
+    diff_options.context_lines(options.context_lines);
+    let (diff, base_ref, merge_base) = match scope {
+        ReviewScope::Staged => {
+            let tree = head_commit.as_ref().map(|c| c.tree()).transpose()?;
+            (repo.diff_tree_to_index(tree.as_ref(), None, Some(&mut diff_options))?, None, None)
+        }
+        ReviewScope::Worktree => {
+            diff_options.include_untracked(true).recurse_untracked_dirs(true).show_untracked_content(true);
+            (repo.diff_index_to_workdir(None, Some(&mut diff_options))?, None, None)
+        }
+        ReviewScope::BranchVsRef => {
+            let (base_ref, merge_base) = resolve_merge_base(&repo, base_ref)?;
+            let head_tree = head_commit.as_ref().ok_or_else(|| anyhow!("HEAD has no commits"))?.tree()?;
+            let base_tree = repo.find_commit(merge_base)?.tree()?;
+            (repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut diff_options))?, Some(base_ref), Some(merge_base))
+        }
+    };
+    let mut diff = diff;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+    let files = review_files(&repo, &diff, options)?;
+
+    let (commits, commits_omitted) = match (merge_base, head_commit.as_ref()) {
+        (Some(base), Some(head)) => branch_commits(&repo, head.id(), base)?,
+        _ => (Vec::new(), 0),
+    };
+    let mut status_options = StatusOptions::new();
+    status_options.include_untracked(true).include_ignored(false);
+    let dirty = !repo.statuses(Some(&mut status_options))?.is_empty();
+
+    let mut bundle = ReviewBundle {
+        scope,
+        base_ref,
+        merge_base: merge_base.map(|oid| oid.to_string()),
+        workspace: ReviewWorkspace {
+            root: display_path(&workdir),
+            branch: head.as_ref().filter(|h| h.is_branch()).and_then(|h| h.shorthand().map(str::to_string)),
+            head: head_commit.as_ref().map(|c| c.id().to_string()),
+            dirty,
+        },
+        additions: files.iter().map(|f| f.additions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+        truncated: files.iter().any(|f| f.truncated),
+        files,
+        commits,
+        commits_omitted,
+        manifest_hash: manifest_hash(&repo, scope, merge_base)?,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        markdown: None,
+    };
+    if options.markdown {
+        bundle.markdown = Some(render_markdown(&bundle));
+    }
+    Ok(bundle)
+}
+
+/// The bundle as a markdown document: summary, commits, file table, then one diff block per file
+pub fn render_markdown(bundle: &ReviewBundle) -> String {
+    let mut out = String::new();
+    let scope = match bundle.scope {
+        ReviewScope::Staged => "staged changes".to_string(),
+        ReviewScope::Worktree => "working tree changes".to_string(),
+        ReviewScope::BranchVsRef => format!("branch changes since {}", bundle.base_ref.as_deref().unwrap_or("base")),
+    };
+    let _ = writeln!(out, "# Review: {}\n", scope);
+    let _ = writeln!(out, "- Workspace: `{}`", bundle.workspace.root);
+    if let Some(branch) = &bundle.workspace.branch {
+        let _ = writeln!(out, "- Branch: `{}`", branch);
+    }
+    if let Some(head) = &bundle.workspace.head {
+        let _ = writeln!(out, "- HEAD: `{}`", head);
+    }
+    let _ = writeln!(out, "- Uncommitted changes: {}", if bundle.workspace.dirty { "yes" } else { "no" });
+    let _ = writeln!(out, "- {} files, +{} -{}", bundle.files.len(), bundle.additions, bundle.deletions);
+    let _ = writeln!(out, "- Manifest: `{}`\n", bundle.manifest_hash);
+
+    if !bundle.commits.is_empty() {
+        let _ = writeln!(out, "## Commits\n");
+        for commit in &bundle.commits {
+            let summary = commit.message.lines().next().unwrap_or("");
+            let _ = writeln!(out, "- `{}` {} ({})", &commit.hash[..commit.hash.len().min(12)], summary, commit.author);
+        }
+        if bundle.commits_omitted > 0 {
+            let _ = writeln!(out, "- ... and {} older commits", bundle.commits_omitted);
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "## Files\n\n| File | Status | + | - |\n| --- | --- | --- | --- |");
+    for file in &bundle.files {
+        let name = match &file.old_path {
+            Some(old) => format!("{} → {}", old, file.path),
+            None => file.path.clone(),
+        };
+        let _ = writeln!(out, "| `{}` | {} | {} | {} |", name, file.status, file.additions, file.deletions);
+    }
+
+    let _ = writeln!(out, "\n## Diffs");
+    for file in &bundle.files {
+        let _ = writeln!(out, "\n### `{}`\n", file.path);
+        match &file.patch {
+            Some(patch) => {
+                let _ = writeln!(out, "```diff\n{}```", patch);
+            }
+            None if file.binary => out.push_str("_Binary file_\n"),
+            None if file.diff_suppressed => {
+                let _ = writeln!(out, "_{}_", DIFF_SUPPRESSED_NOTICE);
+            }
+            None if file.truncated => out.push_str("_Left out: the bundle's size limit was reached_\n"),
+            None => out.push_str("_No textual changes_\n"),
+        }
+    }
+    out
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::scratch::extension_for;
+
+// Names that are conventionally extensionless and must not get one appended
+const EXTENSIONLESS_NAMES: &[&str] = &[
+    "Makefile", "GNUmakefile", "Dockerfile", "Containerfile", "Jenkinsfile", "Vagrantfile", "Procfile", "Gemfile",
+    "Rakefile", "Brewfile", "LICENSE", "LICENCE", "README", "CHANGELOG", "NOTICE", "AUTHORS", "CODEOWNERS", "VERSION",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveAsResult {
+    pub path: String,
+    pub raw_path: String,
+    pub size: u64,
+    pub modified: Option<i64>,
+    /// Language detected from the content, when an extension had to be inferred
+    pub language_id: Option<String>,
+    pub extension_inferred: bool,
+    /// An existing file was replaced (its old contents are in local history)
+    pub overwritten: bool,
+    pub staged: bool,
+    /// Save-a-copy: the editor keeps its buffer pointed at the original file
+    pub copy: bool,
+    pub workspace_root: Option<String>,
+}
+
+fn shebang_language(first_line: &str) -> Option<&'static str> {
+    let interpreter = first_line.strip_prefix("#!")?;
+    let program = interpreter
+        .split_whitespace()
+        .find(|word| !word.ends_with("/env") && !word.starts_with('-'))?
+        .rsplit('/')
+        .next()?;
+    match program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" => Some("python"),
+        "node" | "deno" | "bun" => Some("javascript"),
+        "ts-node" | "tsx" => Some("typescript"),
+        "bash" | "sh" | "zsh" | "dash" | "ksh" => Some("shellscript"),
+        "ruby" => Some("ruby"),
+        "php" => Some("php"),
+        _ => None,
+    }
+}
+
+/// Best guess at the language of `content`, from a shebang, markup prologues or telltale
+/// syntax; None when nothing is distinctive enough
+pub fn detect_language(content: &str) -> Option<&'static str> {
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    let first_line = trimmed.lines().next().unwrap_or("");
+    if let Some(language) = shebang_language(first_line) {
+        return Some(language);
+    }
+    if trimmed.starts_with("<?xml") {
+        return Some("xml");
+    }
+    if trimmed.starts_with("<?php") {
+        return Some("php");
+    }
+    let lower_head: String = trimmed.chars().take(256).collect::<String>().to_lowercase();
+    if lower_head.starts_with("<!doctype html") || lower_head.starts_with("<html") {
+        return Some("html");
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) && serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
+        return Some("json");
+    }
+
+    let lines: Vec<&str> = trimmed.lines().take(200).map(str::trim_start).collect();
+    let any = |prefixes: &[&str]| lines.iter().any(|line| prefixes.iter().any(|p| line.starts_with(p)));
+    if any(&["fn ", "pub fn ", "use std::", "impl ", "pub struct ", "#[derive("]) {
+        return Some("rust");
+    }
+    if any(&["package main", "func "]) && any(&["package "]) {
+        return Some("go");
+    }
+    if any(&["def ", "from ", "class "]) && lines.iter().any(|line| line.ends_with(':')) && !any(&["function ", "const "]) {
+        return Some("python");
+    }
+    if any(&["interface ", "export interface ", "export type ", "type "]) && lines.iter().any(|line| line.contains(": ")) {
+        return Some("typescript");
+    }
+    if any(&["import ", "export ", "const ", "let ", "function ", "module.exports"]) {
+        return Some("javascript");
+    }
+    if any(&["SELECT ", "CREATE TABLE", "INSERT INTO", "select ", "create table"]) {
+        return Some("sql");
+    }
+    if any(&["# ", "## "]) || lines.iter().any(|line| line.starts_with("```")) {
+        return Some("markdown");
+    }
+    if trimmed.starts_with("---") || lines.iter().take(20).filter(|line| line.contains(": ") || line.ends_with(':')).count() >= 2 {
+        return Some("yaml");
+    }
+    None
+}
+
+/// `target` with an extension for the detected language appended, when it has none and is not
+/// a conventionally extensionless name. Returns the language used.
+pub fn infer_extension(target: &Path, content: &str) -> Option<(PathBuf, &'static str)> {
+    let name = target.file_name()?.to_string_lossy().to_string();
+    if target.extension().is_some() || name.starts_with('.') || EXTENSIONLESS_NAMES.contains(&name.as_str()) {
+        return None;
+    }
+    let language = detect_language(content)?;
+    Some((target.with_file_name(format!("{}.{}", name, extension_for(language))), language))
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use globset::{Glob, GlobSetBuilder};
+
+use crate::allowed_commands;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SaveAction {
+    /// Apply the file's .editorconfig: line endings, trailing whitespace and final newline
+    EditorConfig,
+    TrimTrailingWhitespace,
+    /// Allowlisted program, by name from PATH, run on a temporary sibling copy of the file, whose path is
+    /// appended as the last argument (e.g. `prettier --write`, `eslint --fix`)
+    Command { command_line: String },
+}
+
+/// One step of a project's save pipeline
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveHook {
+    #[serde(flatten)]
+    pub action: SaveAction,
+    /// Globs matched against the file name and the path relative to the project; empty matches every file
+    #[serde(default)]
+    pub globs: Vec<String>,
+    /// A failing blocking hook stops the pipeline and the file is not written
+    #[serde(default)]
+    pub blocking: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveStepResult {
+    pub action: SaveAction,
+    /// The glob filter excluded this file
+    pub skipped: bool,
+    pub modified: bool,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+    /// Captured stderr (or stdout) of a command step
+    pub output: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveWithHooksResult {
+    pub steps: Vec<SaveStepResult>,
+    /// Whether the hooks changed the content the editor sent
+    pub content_modified: bool,
+    /// What was written (or would have been), so the editor can show the formatted text
+    pub content: String,
+    pub written: bool,
+    /// Index of the blocking step whose failure prevented the write
+    pub blocked_by: Option<usize>,
+}
+
+fn hook_applies(hook: &SaveHook, project: &Path, path: &Path) -> bool {
+    if hook.globs.is_empty() {
+        return true;
+    }
+    let mut builder = GlobSetBuilder::new();
+    for glob in &hook.globs {
+        if let Ok(glob) = Glob::new(glob) {
+            builder.add(glob);
+        }
+    }
+    let Ok(set) = builder.build() else { return false };
+    let relative = path.strip_prefix(project).unwrap_or(path);
+    set.is_match(relative) || path.file_name().map(|name| set.is_match(name)).unwrap_or(false)
+}
+
+fn trim_trailing_whitespace(content: &str) -> String {
+    content
+        .split_inclusive('\n')
+        .map(|line| {
+            let (body, ending) = match line.strip_suffix("\r\n") {
+                Some(body) => (body, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(body) => (body, "\n"),
+                    None => (line, ""),
+                },
+            };
+            format!("{}{}", body.trim_end_matches([' ', '\t']), ending)
+        })
+        .collect()
+}
+
+fn apply_editorconfig(path: &Path, content: &str) -> String {
+    let properties = crate::editorconfig::properties_for(path);
+    let mut output = content.to_string();
+    if properties.get("trim_trailing_whitespace").map(|v| v == "true").unwrap_or(false) {
+        output = trim_trailing_whitespace(&output);
+    }
+    if let Some(ending) = properties.get("end_of_line") {
+        let newline = match ending.as_str() {
+            "crlf" => Some("\r\n"),
+            "lf" => Some("\n"),
+            "cr" => Some("\r"),
+            _ => None,
+        };
+        if let Some(newline) = newline {
+            output = output.replace("\r\n", "\n").replace('\r', "\n");
+            if newline != "\n" {
+                output = output.replace('\n', newline);
+            }
+        }
+    }
+    match properties.get("insert_final_newline").map(String::as_str) {
+        Some("true") if !output.is_empty() && !output.ends_with('\n') && !output.ends_with('\r') => {
+            let newline = match properties.get("end_of_line").map(String::as_str) {
+                Some("crlf") => "\r\n",
+                Some("cr") => "\r",
+                _ => "\n",
+            };
+            output.push_str(newline);
+        }
+        Some("false") => {
+            let trimmed = output.trim_end_matches(['\r', '\n']).len();
+            output.truncate(trimmed);
+        }
+        _ => {}
+    }
+    output
+}
+
+/// Temporary copy next to `path` with the same extension, so formatters pick up the
+/// project's config and infer the right parser
+fn temp_sibling(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.save-hook-{}.{}", stem, std::process::id(), extension.to_string_lossy()),
+        None => format!("{}.save-hook-{}", stem, std::process::id()),
+    };
+    path.with_file_name(name)
+}
+
+fn run_command(command_line: &str, project: &Path, path: &Path, content: &str, allowlist: &[String], timeout: Duration) -> Result<(String, Option<String>), (String, Option<String>)> {
+    let copy = temp_sibling(path);
+    fs::write(&copy, content).map_err(|e| (format!("Failed to write temporary copy: {}", e), None))?;
+
+    let result = allowed_commands::prepare(command_line, allowlist, false)
+        .and_then(|mut command| {
+            command.arg(&copy);
+            allowed_commands::run(command, project, timeout)
+        });
+    let transformed = fs::read_to_string(&copy);
+    let _ = fs::remove_file(&copy);
+
+    let output = result.map_err(|e| (e.to_string(), None))?;
+    let captured = [output.stderr.trim(), output.stdout.trim()].into_iter().find(|s| !s.is_empty()).map(str::to_string);
+    if output.exit_code != Some(0) {
+        let code = output.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "signal".to_string());
+        return Err((format!("Exited with {}", code), captured));
+    }
+    let transformed = transformed.map_err(|e| (format!("Failed to read the formatted copy: {}", e), captured.clone()))?;
+    Ok((transformed, captured))
+}
+
+/// Run `hooks` over `content` in order, each step seeing the previous step's output. Stops
+/// at the first failing blocking hook and returns its index.
+pub fn run_pipeline(hooks: &[SaveHook], project: &Path, path: &Path, content: &str, allowlist: &[String], timeout: Duration) -> (String, Vec<SaveStepResult>, Option<usize>) {
+    let mut current = content.to_string();
+    let mut steps = Vec::new();
+    for (index, hook) in hooks.iter().enumerate() {
+        let started = Instant::now();
+        let mut step = SaveStepResult {
+            action: hook.action.clone(),
+            skipped: false,
+            modified: false,
+            duration_ms: 0,
+            error: None,
+            output: None,
+        };
+        if !hook_applies(hook, project, path) {
+            step.skipped = true;
+            steps.push(step);
+            continue;
+        }
+
+        let outcome = match &hook.action {
+            SaveAction::EditorConfig => Ok((apply_editorconfig(path, &current), None)),
+            SaveAction::TrimTrailingWhitespace => Ok((trim_trailing_whitespace(&current), None)),
+            SaveAction::Command { command_line } => run_command(command_line, project, path, &current, allowlist, timeout),
+        };
+        step.duration_ms = started.elapsed().as_millis() as u64;
+        match outcome {
+            Ok((next, output)) => {
+                step.modified = next != current;
+                step.output = output;
+                current = next;
+            }
+            Err((error, output)) => {
+                step.error = Some(error);
+                step.output = output;
+                if hook.blocking {
+                    steps.push(step);
+                    return (current, steps, Some(index));
+                }
+            }
+        }
+        steps.push(step);
+    }
+    (current, steps, None)
+}
+
+/// Hooks configured for the project at `project_key`
+pub fn hooks_for(project_hooks: &HashMap<String, Vec<SaveHook>>, project_key: &str) -> Vec<SaveHook> {
+    project_hooks.get(project_key).cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write};
+
+    #[test]
+    fn command_hook_refuses_a_program_path_with_an_allowlisted_name() {
+        let (_dir, root) = temp_dir();
+        let path = write(&root, "main.js", "let a = 1;");
+        write(&root, "node", "#!/bin/sh\necho replaced > \"$1\"\n");
+        let allowlist = vec!["node".to_string()];
+
+        let (message, captured) = run_command("./node", &root, &path, "let a = 1;", &allowlist, Duration::from_secs(5)).unwrap_err();
+        assert!(message.contains("is a path"), "{}", message);
+        assert_eq!(captured, None);
+        assert!(!temp_sibling(&path).exists());
+    }
+}
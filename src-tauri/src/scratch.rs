@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{self as file_ops, FsError, FsResult};
+
+pub(crate) const SCRATCH_DIR: &str = "scratch";
+const INDEX_FILE: &str = "scratch.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScratchFile {
+    pub id: String,
+    pub path: String,
+    pub language_id: String,
+    pub project_path: Option<String>,
+    pub created: i64,
+}
+
+/// Cleanup policy applied to the scratch directory on startup
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScratchPolicy {
+    pub max_age_days: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for ScratchPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            max_total_bytes: 50 * 1024 * 1024,
+        }
+    }
+}
+
+/// Untitled-editor backing files kept in an app-managed directory outside any repo
+pub struct ScratchStore {
+    dir: PathBuf,
+    entries: Mutex<Vec<ScratchFile>>,
+}
+
+/// Map an editor language id to a file extension
+pub fn extension_for(language_id: &str) -> &'static str {
+    match language_id {
+        "typescript" => "ts",
+        "typescriptreact" => "tsx",
+        "javascript" => "js",
+        "javascriptreact" => "jsx",
+        "rust" => "rs",
+        "python" => "py",
+        "go" => "go",
+        "json" => "json",
+        "markdown" => "md",
+        "html" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "yaml" => "yaml",
+        "toml" => "toml",
+        "shellscript" | "shell" | "bash" => "sh",
+        "sql" => "sql",
+        "java" => "java",
+        "c" => "c",
+        "cpp" => "cpp",
+        "csharp" => "cs",
+        "ruby" => "rb",
+        "php" => "php",
+        "xml" => "xml",
+        _ => "txt",
+    }
+}
+
+impl ScratchStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let dir = data_dir.join(SCRATCH_DIR);
+        let entries: Vec<ScratchFile> = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        // Drop index entries whose file was removed behind our back
+        let entries = entries.into_iter().filter(|e| Path::new(&e.path).exists()).collect();
+
+        Self {
+            dir,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub fn create(&self, project_path: Option<String>, language_id: &str) -> FsResult<ScratchFile> {
+        fs::create_dir_all(&self.dir)?;
+        let mut entries = self.entries.lock().unwrap();
+
+        let created = chrono::Utc::now().timestamp();
+        let mut n = entries.len() + 1;
+        let (id, path) = loop {
+            let id = format!("untitled-{}", n);
+            let path = self.dir.join(format!("{}.{}", id, extension_for(language_id)));
+            if !path.exists() && !entries.iter().any(|e| e.id == id) {
+                break (id, path);
+            }
+            n += 1;
+        };
+
+        fs::write(&path, "")?;
+        let scratch = ScratchFile {
+            id,
+            path: file_ops::display_path(&path),
+            language_id: language_id.to_string(),
+            project_path,
+            created,
+        };
+        entries.push(scratch.clone());
+        self.save(&entries)?;
+        Ok(scratch)
+    }
+
+    pub fn list(&self) -> Vec<ScratchFile> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Move a scratch file into the workspace; refuses to overwrite an existing file
+    pub fn promote(&self, scratch_id: &str, target_path: &Path) -> FsResult<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|e| e.id == scratch_id).ok_or(FsError::NotFound)?;
+        if target_path.exists() {
+            return Err(FsError::AlreadyExists);
+        }
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        file_ops::move_path_with_fallback(Path::new(&entries[index].path), target_path, |_| {})?;
+        entries.remove(index);
+        self.save(&entries)?;
+        Ok(file_ops::display_path(target_path))
+    }
+
+    pub fn delete(&self, scratch_id: &str) -> FsResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|e| e.id == scratch_id).ok_or(FsError::NotFound)?;
+        let _ = fs::remove_file(&entries[index].path);
+        entries.remove(index);
+        self.save(&entries)
+    }
+
+    /// Remove scratch files older than the policy age, then oldest-first until under the size budget
+    pub fn cleanup(&self, policy: &ScratchPolicy) -> FsResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let cutoff = chrono::Utc::now().timestamp() - (policy.max_age_days * 24 * 60 * 60) as i64;
+        let before = entries.len();
+
+        entries.sort_by_key(|e| e.created);
+        entries.retain(|e| {
+            let expired = e.created < cutoff;
+            if expired {
+                let _ = fs::remove_file(&e.path);
+            }
+            !expired
+        });
+
+        let mut total: u64 = entries
+            .iter()
+            .filter_map(|e| fs::metadata(&e.path).ok())
+            .map(|m| m.len())
+            .sum();
+        while total > policy.max_total_bytes && !entries.is_empty() {
+            let oldest = entries.remove(0);
+            total = total.saturating_sub(fs::metadata(&oldest.path).map(|m| m.len()).unwrap_or(0));
+            let _ = fs::remove_file(&oldest.path);
+        }
+
+        self.save(&entries)?;
+        Ok(before - entries.len())
+    }
+
+    fn save(&self, entries: &[ScratchFile]) -> FsResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        let raw = serde_json::to_string_pretty(entries).map_err(|e| FsError::Io { message: e.to_string() })?;
+        fs::write(self.dir.join(INDEX_FILE), raw)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{anyhow, Result};
+use git2::{Oid, Repository};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::is_binary_content;
+
+// Larger files are not scanned; secrets live in config and source, not in generated blobs
+const MAX_SCAN_BYTES: u64 = 2 * 1024 * 1024;
+
+const MAX_EXCERPT_CHARS: usize = 160;
+
+// Bits per character above which an assigned value looks random rather than a word
+const MIN_GENERIC_ENTROPY: f64 = 3.5;
+
+/// Lines containing this marker are never reported
+const INLINE_ALLOW_MARKER: &str = "secret-scan:allow";
+
+/// What `commit_changes` does when the changes it is about to record contain secrets
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsMode {
+    Off,
+    /// Commit, and return the findings with the result
+    #[default]
+    Warn,
+    /// Refuse with `SecretsDetected` unless the caller explicitly overrides
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanScope {
+    /// Lines added by the staged changes
+    Staged,
+    /// The given files or directories in full
+    Paths,
+    /// Every non-ignored file in the project
+    Workspace,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SecretFinding {
+    /// Relative to the project, with forward slashes
+    pub file: String,
+    pub line: usize,
+    pub detector: String,
+    /// The line with the secret masked
+    pub excerpt: String,
+}
+
+/// Per-project exceptions
+#[derive(Clone, Debug, Default)]
+pub struct SecretScanConfig {
+    /// Regexes; a match whose text matches any of them is not reported (test fixtures, examples)
+    pub allowlist: Vec<String>,
+    /// Globs for files that are never scanned
+    pub ignore_paths: Vec<String>,
+}
+
+struct Detector {
+    id: &'static str,
+    pattern: Regex,
+    /// Capture group holding the secret itself; 0 for the whole match
+    group: usize,
+    /// Require this much entropy in the secret, for patterns that also match ordinary words
+    min_entropy: Option<f64>,
+}
+
+fn detectors() -> &'static [Detector] {
+    static DETECTORS: OnceLock<Vec<Detector>> = OnceLock::new();
+    DETECTORS.get_or_init(|| {
+        let detector = |id, pattern: &str, group, min_entropy| Detector {
+            id,
+            pattern: Regex::new(pattern).expect("secret pattern"),
+            group,
+            min_entropy,
+        };
+        vec![
+            detector("aws_access_key_id", r"\b((?:AKIA|ASIA)[0-9A-Z]{16})\b", 1, None),
+            detector("aws_secret_access_key", r#"(?i)aws.{0,20}(?:secret|sk).{0,20}?[=:]\s*["']?([A-Za-z0-9/+]{40})\b"#, 1, Some(3.5)),
+            detector("private_key", r"-----BEGIN (?:RSA |DSA |EC |OPENSSH |PGP |ENCRYPTED )?PRIVATE KEY(?: BLOCK)?-----", 0, None),
+            detector("github_token", r"\b((?:ghp|gho|ghu|ghs|ghr)_[A-Za-z0-9]{36}|github_pat_[A-Za-z0-9_]{80,})\b", 1, None),
+            detector("gitlab_token", r"\b(glpat-[A-Za-z0-9_-]{20,})", 1, None),
+            detector("slack_token", r"\b(xox[abposr]-[A-Za-z0-9-]{10,})", 1, None),
+            detector("stripe_key", r"\b((?:sk|rk)_live_[A-Za-z0-9]{24,})\b", 1, None),
+            detector("google_api_key", r"\b(AIza[0-9A-Za-z_-]{35})", 1, None),
+            detector("anthropic_api_key", r"\b(sk-ant-[A-Za-z0-9_-]{32,})", 1, None),
+            detector("openai_api_key", r"\b(sk-(?:proj-)?[A-Za-z0-9_-]{32,})", 1, Some(3.0)),
+            detector("npm_token", r"\b(npm_[A-Za-z0-9]{36})\b", 1, None),
+            detector(
+                "generic_secret_assignment",
+                r#"(?i)(?:secret|token|passwd|password|api[_-]?key|access[_-]?key|client[_-]?secret)["']?\s*[:=]\s*["']([A-Za-z0-9/+_=.-]{16,})["']"#,
+                1,
+                Some(MIN_GENERIC_ENTROPY),
+            ),
+        ]
+    })
+}
+
+/// Shannon entropy in bits per character
+fn entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let len = value.chars().count() as f64;
+    counts.values().map(|&n| {
+        let p = n as f64 / len;
+        -p * p.log2()
+    }).sum()
+}
+
+/// Keep the first four characters of the secret so a finding can be recognised, mask the rest
+fn redact(line: &str, start: usize, end: usize) -> String {
+    let secret = &line[start..end];
+    let kept: String = secret.chars().take(4).collect();
+    let masked = format!("{}{}", kept, "*".repeat(secret.chars().count().saturating_sub(4).min(32)));
+    let excerpt = format!("{}{}{}", &line[..start], masked, &line[end..]);
+    let excerpt = excerpt.trim();
+    if excerpt.chars().count() > MAX_EXCERPT_CHARS {
+        format!("{}…", excerpt.chars().take(MAX_EXCERPT_CHARS).collect::<String>())
+    } else {
+        excerpt.to_string()
+    }
+}
+
+struct Scanner {
+    allowlist: Vec<Regex>,
+    ignore: GlobSet,
+}
+
+impl Scanner {
+    fn new(config: &SecretScanConfig) -> Result<Self> {
+        let allowlist = config
+            .allowlist
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| anyhow!("Invalid secret allowlist pattern '{}': {}", p, e)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut ignore = GlobSetBuilder::new();
+        for pattern in &config.ignore_paths {
+            ignore.add(Glob::new(pattern).map_err(|e| anyhow!("Invalid secret ignore pattern '{}': {}", pattern, e))?);
+        }
+        Ok(Self { allowlist, ignore: ignore.build()? })
+    }
+
+    fn ignores(&self, relative: &str) -> bool {
+        self.ignore.is_match(relative)
+    }
+
+    fn scan_line(&self, file: &str, line_number: usize, line: &str, findings: &mut Vec<SecretFinding>) {
+        if line.contains(INLINE_ALLOW_MARKER) {
+            return;
+        }
+        for detector in detectors() {
+            for captures in detector.pattern.captures_iter(line) {
+                let Some(secret) = captures.get(detector.group) else { continue };
+                if let Some(min) = detector.min_entropy {
+                    if entropy(secret.as_str()) < min {
+                        continue;
+                    }
+                }
+                if self.allowlist.iter().any(|allowed| allowed.is_match(secret.as_str())) {
+                    continue;
+                }
+                // Detectors run most specific first and later ones overlap them (an Anthropic key
+                // also looks like an OpenAI one), so one finding per line is enough
+                if findings.iter().any(|f| f.file == file && f.line == line_number) {
+                    continue;
+                }
+                findings.push(SecretFinding {
+                    file: file.to_string(),
+                    line: line_number,
+                    detector: detector.id.to_string(),
+                    excerpt: redact(line, secret.start(), secret.end()),
+                });
+            }
+        }
+    }
+
+    fn scan_text(&self, file: &str, text: &str, findings: &mut Vec<SecretFinding>) {
+        for (index, line) in text.lines().enumerate() {
+            self.scan_line(file, index + 1, line, findings);
+        }
+    }
+
+    fn scan_file(&self, root: &Path, path: &Path, findings: &mut Vec<SecretFinding>) {
+        let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        if self.ignores(&relative) {
+            return;
+        }
+        let Ok(metadata) = fs::metadata(path) else { return };
+        if !metadata.is_file() || metadata.len() > MAX_SCAN_BYTES {
+            return;
+        }
+        let Ok(bytes) = fs::read(path) else { return };
+        if is_binary_content(&bytes) {
+            return;
+        }
+        self.scan_text(&relative, &String::from_utf8_lossy(&bytes), findings);
+    }
+}
+
+/// Lines added between `from` (None for an empty tree) and `to`
+fn scan_tree_diff(repo: &Repository, scanner: &Scanner, from: Option<&git2::Tree>, to: &git2::Tree) -> Result<Vec<SecretFinding>> {
+    let diff = repo.diff_tree_to_tree(from, Some(to), None)?;
+    let mut findings = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _hunk, line| {
+            if line.origin() != '+' || delta.new_file().size() > MAX_SCAN_BYTES {
+                return true;
+            }
+            let Some(path) = delta.new_file().path() else { return true };
+            let file = path.to_string_lossy().replace('\\', "/");
+            if scanner.ignores(&file) {
+                return true;
+            }
+            if let Some(line_number) = line.new_lineno() {
+                let text = String::from_utf8_lossy(line.content());
+                scanner.scan_line(&file, line_number as usize, text.trim_end_matches(['\r', '\n']), &mut findings);
+            }
+            true
+        }),
+    )?;
+    Ok(findings)
+}
+
+pub fn scan_for_secrets(project_path: &Path, scope: ScanScope, paths: &[String], config: &SecretScanConfig) -> Result<Vec<SecretFinding>> {
+    let scanner = Scanner::new(config)?;
+    match scope {
+        ScanScope::Staged => {
+            let repo = Repository::discover(project_path)?;
+            let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+            let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            scan_tree_diff(&repo, &scanner, head_tree.as_ref(), &index_tree)
+        }
+        ScanScope::Paths => {
+            let mut findings = Vec::new();
+            for path in paths {
+                let path = project_path.join(path);
+                if path.is_dir() {
+                    let walker = WalkBuilder::new(&path).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+                    for entry in walker.filter_map(|e| e.ok()) {
+                        scanner.scan_file(project_path, entry.path(), &mut findings);
+                    }
+                } else {
+                    scanner.scan_file(project_path, &path, &mut findings);
+                }
+            }
+            Ok(findings)
+        }
+        ScanScope::Workspace => {
+            let mut findings = Vec::new();
+            let walker = WalkBuilder::new(project_path).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+            for entry in walker.filter_map(|e| e.ok()) {
+                scanner.scan_file(project_path, entry.path(), &mut findings);
+            }
+            Ok(findings)
+        }
+    }
+}
+
+/// Lines a commit of `tree` would add relative to HEAD
+pub fn scan_commit_tree(repo_path: &Path, tree: Oid, config: &SecretScanConfig) -> Result<Vec<SecretFinding>> {
+    let scanner = Scanner::new(config)?;
+    let repo = Repository::discover(repo_path)?;
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let tree = repo.find_tree(tree)?;
+    scan_tree_diff(&repo, &scanner, head_tree.as_ref(), &tree)
+}
+
+/// One audit-log outcome line; findings are already redacted, and only locations are logged
+pub fn audit_summary(findings: &[SecretFinding]) -> String {
+    if findings.is_empty() {
+        return "no secrets found".to_string();
+    }
+    let locations: Vec<String> = findings.iter().map(|f| format!("{}:{} {}", f.file, f.line, f.detector)).collect();
+    format!("{} possible secret(s): {}", findings.len(), locations.join(", "))
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+use crate::allowed_commands::DEFAULT_ALLOWLIST;
+#[cfg(feature = "git")]
+use crate::commit_message::DEFAULT_ISSUE_TRAILER_PATTERNS;
+#[cfg(feature = "git")]
+use crate::forge::ForgeKind;
+#[cfg(feature = "git")]
+use crate::git::{BranchNamingConvention, GitIdentity};
+use crate::idle::WarmupOptions;
+use crate::local_history::LocalHistoryPolicy;
+use crate::notifications::NotificationSettings;
+#[cfg(feature = "git")]
+use crate::prepush::PrePushCheck;
+use crate::save_hooks::SaveHook;
+#[cfg(feature = "git")]
+use crate::secrets::{SecretScanConfig, SecretsMode};
+use crate::scratch::ScratchPolicy;
+use crate::text_policy::TextPolicy;
+
+pub(crate) const SETTINGS_FILE: &str = "settings.json";
+
+/// User-facing backend settings, persisted as JSON in the app config directory
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AppSettings {
+    pub file_cache_enabled: bool,
+    pub file_cache_max_bytes: u64,
+    pub watcher_exclusions: Vec<String>,
+    /// Extra watcher exclusions keyed by project path
+    pub project_watcher_exclusions: HashMap<String, Vec<String>>,
+    /// Skip directories git ignores (.gitignore, info/exclude, core.excludesFile) when watching
+    pub watcher_respect_gitignore: bool,
+    /// How long a newly opened workspace's watcher has to report a probe write before the
+    /// workspace falls back to polling; 0 skips the check
+    pub watcher_probe_timeout_ms: u64,
+    pub watcher_poll_interval_ms: u64,
+    /// Directories scanned per workspace in polling mode
+    pub watcher_poll_max_directories: usize,
+    pub scratch_policy: ScratchPolicy,
+    #[cfg(feature = "git")]
+    pub branch_naming: BranchNamingConvention,
+    /// Branch name globs that merged-branch cleanup never deletes
+    pub protected_branches: Vec<String>,
+    /// Store credentials in a passphrase-encrypted file when no OS keyring is available
+    pub credential_file_fallback: bool,
+    pub notifications: NotificationSettings,
+    /// Programs `run_allowed_command` may start
+    pub command_allowlist: Vec<String>,
+    /// Extra allowed programs keyed by project path
+    pub project_command_allowlist: HashMap<String, Vec<String>>,
+    pub command_timeout_secs: u64,
+    /// Lets callers opt in to pipes and redirection; off means such command lines are rejected
+    pub allow_shell_commands: bool,
+    pub local_history: LocalHistoryPolicy,
+    /// Checkpoints older than this are deleted by `compact_storage`
+    pub checkpoint_max_age_days: u64,
+    /// Command audit log lines older than this are dropped by `compact_storage`
+    pub audit_log_max_age_days: u64,
+    /// How long a deleted branch stays restorable; older journal entries are dropped by `compact_storage`
+    pub deleted_branch_retention_days: u64,
+    /// Whether `commit_changes` warns about or blocks commits that add likely secrets
+    #[cfg(feature = "git")]
+    pub block_on_secrets: SecretsMode,
+    /// Regexes for known-harmless matches (fixtures, examples), keyed by project path
+    #[cfg(feature = "git")]
+    pub project_secret_allowlist: HashMap<String, Vec<String>>,
+    /// Globs for files the secret scan skips, keyed by project path
+    #[cfg(feature = "git")]
+    pub project_secret_ignore_paths: HashMap<String, Vec<String>>,
+    /// Append an extension guessed from the content when save-as targets a name without one
+    pub infer_save_extension: bool,
+    /// Ordered save pipeline keyed by project path, run by `save_with_hooks`
+    pub project_save_hooks: HashMap<String, Vec<SaveHook>>,
+    /// Ordered checks `git_push` runs before pushing, keyed by project path
+    #[cfg(feature = "git")]
+    pub project_prepush_checks: HashMap<String, Vec<PrePushCheck>>,
+    /// Whether `git_push` runs the configured checks when the caller does not say
+    pub run_prepush_checks: bool,
+    /// Lets a push bypass failing or enforced checks with `skip_checks`; every bypass is audited
+    pub allow_skip_prepush_checks: bool,
+    /// Forge for self-hosted remotes, keyed by host pattern (`git.example.com`, `*.corp.net`)
+    #[cfg(feature = "git")]
+    pub forge_hosts: HashMap<String, ForgeKind>,
+    /// Regexes with `key` and `value` groups for issue references in a commit's trailer block
+    #[cfg(feature = "git")]
+    pub issue_trailer_patterns: Vec<String>,
+    /// Saved commit identities, switched between with `apply_identity`
+    #[cfg(feature = "git")]
+    pub git_identities: Vec<GitIdentity>,
+    /// Timed commands taking at least this long are logged as slow operations; 0 disables tracing
+    pub slow_command_threshold_ms: u64,
+    /// Listing and diff results whose JSON exceeds this many bytes are returned as a chunk handle; 0 disables chunking
+    pub ipc_chunk_threshold_bytes: usize,
+    /// Scan a large repository's first status in per-directory batches with `git-status-progress`
+    /// events; off forces a single pass
+    pub git_status_batched_scan: bool,
+    /// Text policy used when a workspace has no `.agentic-ide/policy.json`
+    pub text_policy: TextPolicy,
+    /// Also send each event path's absolute OS form in `absolute`, for listeners not yet on
+    /// workspace-relative paths
+    pub event_absolute_paths: bool,
+    /// Warm the file and word indexes in idle time after a project opens
+    pub warmup: WarmupOptions,
+    /// How long closing the last window waits for cancelled operations to stop before exiting
+    pub shutdown_grace_ms: u64,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            file_cache_enabled: true,
+            file_cache_max_bytes: 64 * 1024 * 1024,
+            watcher_exclusions: [".git/objects", "node_modules", "target", "dist", "build"]
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            project_watcher_exclusions: HashMap::new(),
+            watcher_respect_gitignore: true,
+            watcher_probe_timeout_ms: 5000,
+            watcher_poll_interval_ms: 2000,
+            watcher_poll_max_directories: 2000,
+            scratch_policy: ScratchPolicy::default(),
+            #[cfg(feature = "git")]
+            branch_naming: BranchNamingConvention::default(),
+            protected_branches: ["main", "master", "develop", "release/*"].iter().map(|p| p.to_string()).collect(),
+            credential_file_fallback: false,
+            notifications: NotificationSettings::default(),
+            command_allowlist: DEFAULT_ALLOWLIST.iter().map(|p| p.to_string()).collect(),
+            project_command_allowlist: HashMap::new(),
+            command_timeout_secs: 300,
+            allow_shell_commands: false,
+            local_history: LocalHistoryPolicy::default(),
+            checkpoint_max_age_days: 30,
+            audit_log_max_age_days: 90,
+            deleted_branch_retention_days: 14,
+            #[cfg(feature = "git")]
+            block_on_secrets: SecretsMode::default(),
+            #[cfg(feature = "git")]
+            project_secret_allowlist: HashMap::new(),
+            #[cfg(feature = "git")]
+            project_secret_ignore_paths: HashMap::new(),
+            infer_save_extension: true,
+            project_save_hooks: HashMap::new(),
+            #[cfg(feature = "git")]
+            project_prepush_checks: HashMap::new(),
+            run_prepush_checks: true,
+            allow_skip_prepush_checks: false,
+            #[cfg(feature = "git")]
+            forge_hosts: HashMap::new(),
+            #[cfg(feature = "git")]
+            issue_trailer_patterns: DEFAULT_ISSUE_TRAILER_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            #[cfg(feature = "git")]
+            git_identities: Vec::new(),
+            slow_command_threshold_ms: 500,
+            ipc_chunk_threshold_bytes: 2 * 1024 * 1024,
+            git_status_batched_scan: true,
+            text_policy: TextPolicy::default(),
+            event_absolute_paths: true,
+            warmup: WarmupOptions::default(),
+            shutdown_grace_ms: 5000,
+        }
+    }
+}
+
+impl AppSettings {
+    /// Global watcher exclusions plus any overrides for the given project
+    pub fn watcher_exclusions_for(&self, project_path: &str) -> Vec<String> {
+        let mut patterns = self.watcher_exclusions.clone();
+        if let Some(extra) = self.project_watcher_exclusions.get(project_path) {
+            patterns.extend(extra.iter().cloned());
+        }
+        patterns
+    }
+
+    /// Global command allowlist plus any additions for the given project
+    pub fn command_allowlist_for(&self, project_path: &str) -> Vec<String> {
+        let mut programs = self.command_allowlist.clone();
+        if let Some(extra) = self.project_command_allowlist.get(project_path) {
+            programs.extend(extra.iter().cloned());
+        }
+        programs
+    }
+
+    #[cfg(feature = "git")]
+    pub fn secret_scan_for(&self, project_path: &str) -> SecretScanConfig {
+        SecretScanConfig {
+            allowlist: self.project_secret_allowlist.get(project_path).cloned().unwrap_or_default(),
+            ignore_paths: self.project_secret_ignore_paths.get(project_path).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+pub struct SettingsState {
+    path: PathBuf,
+    settings: Mutex<AppSettings>,
+}
+
+impl SettingsState {
+    /// Load settings from the config directory, falling back to defaults
+    pub fn load(config_dir: &Path) -> Self {
+        let path = config_dir.join(SETTINGS_FILE);
+        let settings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, settings: AppSettings) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&settings)?)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+}
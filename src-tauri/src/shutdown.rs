@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "git")]
+use std::sync::atomic::AtomicU64;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::AppHandle;
+use crate::fs::display_path;
+#[cfg(feature = "git")]
+use crate::git::GitState;
+use crate::operations::OperationRegistry;
+
+pub(crate) const JOURNAL_FILE: &str = "session-artifacts.json";
+
+// How deep the app's own directories are searched for the atomic writer's temp files
+const TEMP_SCAN_DEPTH: usize = 4;
+
+// Pause between checks for in-flight work while the grace period runs
+const DRAIN_POLL: Duration = Duration::from_millis(50);
+
+// Installed once from setup, so code without an AppHandle (checkouts, templates) can journal too
+static JOURNAL: OnceLock<ArtifactJournal> = OnceLock::new();
+
+// Set once the shutdown begins; child-process loops poll it to kill what they started
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+pub fn shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Something this session is in the middle of creating, which a crash or forced exit would
+/// leave behind half-made
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Artifact {
+    /// A directory a clone or template is creating; it did not exist before. `stamp` identifies
+    /// the directory we created, so one made later at the same path is never removed.
+    PartialDirectory {
+        path: PathBuf,
+        #[serde(default)]
+        stamp: Option<String>,
+    },
+    /// A repository whose `index.lock` a checkout of ours may be holding
+    IndexLock { git_dir: PathBuf },
+}
+
+/// Artifacts in flight, persisted so the next startup can see what an unclean exit left
+struct ArtifactJournal {
+    path: PathBuf,
+    entries: Mutex<HashMap<u64, Artifact>>,
+    /// Only git work (clones, templates, checkouts) journals artifacts
+    #[cfg(feature = "git")]
+    next_id: AtomicU64,
+}
+
+impl ArtifactJournal {
+    fn persist(&self, entries: &HashMap<u64, Artifact>) {
+        let list: Vec<&Artifact> = entries.values().collect();
+        if let Ok(raw) = serde_json::to_string(&list) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+}
+
+/// Removes its artifact from the journal when the work that owns it finishes
+#[cfg(feature = "git")]
+pub struct ArtifactGuard(Option<u64>);
+
+#[cfg(feature = "git")]
+impl Drop for ArtifactGuard {
+    fn drop(&mut self) {
+        let (Some(id), Some(journal)) = (self.0, JOURNAL.get()) else { return };
+        let mut entries = journal.entries.lock().unwrap();
+        if entries.remove(&id).is_some() {
+            journal.persist(&entries);
+        }
+    }
+}
+
+/// Identifies one directory on disk, so a path that was removed and made again does not match
+#[cfg(unix)]
+fn directory_stamp(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(path).ok().filter(|m| m.is_dir())?;
+    Some(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn directory_stamp(path: &Path) -> Option<String> {
+    let metadata = fs::symlink_metadata(path).ok().filter(|m| m.is_dir())?;
+    let created = metadata.created().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(created.as_nanos().to_string())
+}
+
+/// Create the empty directory `path` and journal it until the guard drops; a clone or
+/// template fills it in
+#[cfg(feature = "git")]
+pub fn create_tracked_directory(path: &Path) -> std::io::Result<ArtifactGuard> {
+    fs::create_dir_all(path)?;
+    Ok(track(Artifact::PartialDirectory { path: path.to_path_buf(), stamp: directory_stamp(path) }))
+}
+
+/// Journal `artifact` for as long as the returned guard lives
+#[cfg(feature = "git")]
+pub fn track(artifact: Artifact) -> ArtifactGuard {
+    let Some(journal) = JOURNAL.get() else { return ArtifactGuard(None) };
+    let id = journal.next_id.fetch_add(1, Ordering::Relaxed);
+    let mut entries = journal.entries.lock().unwrap();
+    entries.insert(id, artifact);
+    journal.persist(&entries);
+    ArtifactGuard(Some(id))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LeftoverReport {
+    /// Half-made clone and template directories that were removed
+    pub partial_directories: Vec<String>,
+    /// `index.lock` files our index writes left, removed
+    pub index_locks: Vec<String>,
+    /// The atomic writer's temp files in the app's directories, removed
+    pub temp_files: Vec<String>,
+    /// Leftovers found but kept, with the reason
+    pub kept: Vec<String>,
+}
+
+impl LeftoverReport {
+    pub fn is_empty(&self) -> bool {
+        self.partial_directories.is_empty() && self.index_locks.is_empty() && self.temp_files.is_empty() && self.kept.is_empty()
+    }
+}
+
+fn remove_artifact(artifact: &Artifact, report: &mut LeftoverReport) {
+    match artifact {
+        Artifact::PartialDirectory { path, stamp } => {
+            if !path.exists() {
+                return;
+            }
+            if stamp.is_none() || directory_stamp(path) != *stamp {
+                report.kept.push(format!("{}: not the directory this app created", display_path(path)));
+                return;
+            }
+            match fs::remove_dir_all(path) {
+                Ok(()) => report.partial_directories.push(display_path(path)),
+                Err(e) => report.kept.push(format!("{}: {}", display_path(path), e)),
+            }
+        }
+        Artifact::IndexLock { git_dir } => {
+            let lock = git_dir.join("index.lock");
+            if !lock.exists() {
+                return;
+            }
+            #[cfg(feature = "git")]
+            let maybe_held = crate::repo_health::git_process_running() != Some(false);
+            // Builds without git cannot check for a running git process, so the lock stays
+            #[cfg(not(feature = "git"))]
+            let maybe_held = true;
+            if maybe_held {
+                report.kept.push(format!("{}: a git process may be holding it", display_path(&lock)));
+                return;
+            }
+            match fs::remove_file(&lock) {
+                Ok(()) => report.index_locks.push(display_path(&lock)),
+                Err(e) => report.kept.push(format!("{}: {}", display_path(&lock), e)),
+            }
+        }
+    }
+}
+
+/// `.name.<pid>.tmp` from `write_atomic`, written by a process other than this one
+fn is_stale_temp(name: &str) -> bool {
+    let Some(rest) = name.strip_prefix('.').and_then(|n| n.strip_suffix(".tmp")) else { return false };
+    let Some((_, pid)) = rest.rsplit_once('.') else { return false };
+    pid.parse::<u32>().map(|pid| pid != std::process::id()).unwrap_or(false)
+}
+
+fn remove_stale_temps(dir: &Path, report: &mut LeftoverReport) {
+    let walker = walkdir::WalkDir::new(dir).max_depth(TEMP_SCAN_DEPTH).into_iter().filter_map(|e| e.ok());
+    for entry in walker.filter(|e| e.file_type().is_file() && is_stale_temp(&e.file_name().to_string_lossy())) {
+        match fs::remove_file(entry.path()) {
+            Ok(()) => report.temp_files.push(display_path(entry.path())),
+            Err(e) => report.kept.push(format!("{}: {}", display_path(entry.path()), e)),
+        }
+    }
+}
+
+/// Clean up after the previous session: whatever its journal still listed, plus stale temp
+/// files in the app's own directories. Then start this session's journal.
+pub fn recover_previous_session(config_dir: &Path, data_dir: &Path) -> LeftoverReport {
+    let path = data_dir.join(JOURNAL_FILE);
+    let previous: Vec<Artifact> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+
+    let mut report = LeftoverReport::default();
+    for artifact in &previous {
+        remove_artifact(artifact, &mut report);
+    }
+    remove_stale_temps(config_dir, &mut report);
+    remove_stale_temps(data_dir, &mut report);
+    let _ = fs::remove_file(&path);
+
+    let _ = JOURNAL.set(ArtifactJournal {
+        path,
+        entries: Mutex::new(HashMap::new()),
+        #[cfg(feature = "git")]
+        next_id: AtomicU64::new(0),
+    });
+    report
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShutdownBlocker {
+    /// "operation" for cancellable commands, "git" for queued git work
+    pub kind: String,
+    /// The operation id, or the git operation's label ("push", "pull", ...)
+    pub label: String,
+    pub repository: Option<String>,
+}
+
+pub type CleanupHook = Box<dyn Fn(&AppHandle) + Send + Sync>;
+
+/// Runs when the last window is closing: modules' cleanup hooks first, then a bounded wait
+/// for in-flight work to wind down, then removal of the directories it still left half-made
+pub struct ShutdownCoordinator {
+    hooks: Mutex<Vec<(&'static str, CleanupHook)>>,
+    leftovers: LeftoverReport,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(leftovers: LeftoverReport) -> Self {
+        Self { hooks: Mutex::new(Vec::new()), leftovers }
+    }
+
+    /// Add a hook that cancels, kills or flushes a module's in-flight work
+    pub fn register(&self, name: &'static str, hook: CleanupHook) {
+        self.hooks.lock().unwrap().push((name, hook));
+    }
+
+    /// What startup found and cleaned from the previous session
+    pub fn leftovers(&self) -> LeftoverReport {
+        self.leftovers.clone()
+    }
+
+    pub fn blockers(&self, app: &AppHandle) -> Vec<ShutdownBlocker> {
+        let operations = app.state::<OperationRegistry>().active().into_iter().map(|id| ShutdownBlocker {
+            kind: "operation".to_string(),
+            label: id,
+            repository: None,
+        });
+        #[cfg(feature = "git")]
+        let operations = operations.chain(app.state::<GitState>().running_operations().into_iter().map(|(repository, label)| ShutdownBlocker {
+            kind: "git".to_string(),
+            label,
+            repository: Some(repository),
+        }));
+        operations.collect()
+    }
+
+    /// Run the cleanup hooks and wait up to `grace` for in-flight work to stop, then remove
+    /// the partial directories still journaled. Returns false when a shutdown had already begun.
+    pub fn shutdown(&self, app: &AppHandle, grace: Duration) -> bool {
+        if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        for (name, hook) in self.hooks.lock().unwrap().iter() {
+            log::info!("Shutdown: running '{}' cleanup", name);
+            hook(app);
+        }
+
+        let deadline = Instant::now() + grace;
+        while !self.blockers(app).is_empty() && Instant::now() < deadline {
+            std::thread::sleep(DRAIN_POLL);
+        }
+        let remaining = self.blockers(app);
+        if !remaining.is_empty() {
+            log::warn!("Shutdown: {} operations still running after the grace period", remaining.len());
+        }
+
+        // Index locks stay journaled: a checkout may still hold one, and the next startup checks
+        if let Some(journal) = JOURNAL.get() {
+            let mut entries = journal.entries.lock().unwrap();
+            let mut report = LeftoverReport::default();
+            for artifact in entries.values().filter(|a| matches!(a, Artifact::PartialDirectory { .. })) {
+                remove_artifact(artifact, &mut report);
+            }
+            entries.retain(|_, artifact| matches!(artifact, Artifact::IndexLock { .. }));
+            journal.persist(&entries);
+            for kept in &report.kept {
+                log::warn!("Shutdown: left {}", kept);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn partial_directories_are_removed_only_when_they_are_the_ones_created() {
+        let (_dir, root) = temp_dir();
+        let created = root.join("clone");
+        fs::create_dir(&created).unwrap();
+        fs::write(created.join("file.txt"), "partial").unwrap();
+        let stamp = directory_stamp(&created);
+        let replaced = root.join("replaced");
+        fs::create_dir(&replaced).unwrap();
+        let unstamped = root.join("unstamped");
+        fs::create_dir(&unstamped).unwrap();
+
+        let mut report = LeftoverReport::default();
+        remove_artifact(&Artifact::PartialDirectory { path: created.clone(), stamp }, &mut report);
+        remove_artifact(&Artifact::PartialDirectory { path: replaced.clone(), stamp: Some("0:0".to_string()) }, &mut report);
+        remove_artifact(&Artifact::PartialDirectory { path: unstamped.clone(), stamp: None }, &mut report);
+
+        assert!(!created.exists());
+        assert!(replaced.exists() && unstamped.exists());
+        assert_eq!(report.partial_directories, vec![display_path(&created)]);
+        assert_eq!(report.kept.len(), 2);
+    }
+
+    #[test]
+    fn partial_directory_entries_from_older_journals_still_parse() {
+        let previous: Vec<Artifact> = serde_json::from_str(r#"[{"kind":"partial_directory","path":"/tmp/clone"}]"#).unwrap();
+        assert!(matches!(&previous[0], Artifact::PartialDirectory { stamp: None, .. }));
+    }
+}
@@ -0,0 +1,278 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{self as file_ops, FsError, FsResult};
+
+pub(crate) const GLOBAL_SNIPPET_DIR: &str = "snippets";
+
+// Project snippets live inside the project so they can be committed alongside it
+const PROJECT_SNIPPET_DIR: &str = ".agentic-ide/snippets";
+
+const PACK_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetScope {
+    Global,
+    Project,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Snippet {
+    pub id: String,
+    pub scope: SnippetScope,
+    pub title: String,
+    pub language: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created: i64,
+    pub updated: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnippetInput {
+    pub id: Option<String>,
+    pub title: String,
+    pub language: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A set of snippets exported to a single JSON file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SnippetPack {
+    version: u32,
+    snippets: Vec<Snippet>,
+}
+
+/// Snippets stored one JSON file each, globally under the config dir or per project
+pub struct SnippetStore {
+    global_dir: PathBuf,
+    // Serializes id allocation and read-modify-write of individual snippet files
+    lock: Mutex<()>,
+}
+
+fn io_error<E: std::fmt::Display>(e: E) -> FsError {
+    FsError::Io { message: e.to_string() }
+}
+
+/// Ids become file names, so only allow a conservative character set
+fn validate_id(id: &str) -> FsResult<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(FsError::InvalidName { message: format!("'{}' is not a valid snippet id", id) });
+    }
+    Ok(())
+}
+
+fn slugify(title: &str) -> String {
+    let slug: String = title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+    let slug: String = slug.chars().take(40).collect();
+    if slug.is_empty() { "snippet".to_string() } else { slug }
+}
+
+fn matches_filter(snippet: &Snippet, filter: &str) -> bool {
+    let needle = filter.to_lowercase();
+    snippet.title.to_lowercase().contains(&needle)
+        || snippet.language.to_lowercase() == needle
+        || snippet.tags.iter().any(|t| t.to_lowercase() == needle)
+        || snippet.content.to_lowercase().contains(&needle)
+}
+
+impl SnippetStore {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            global_dir: config_dir.join(GLOBAL_SNIPPET_DIR),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn dir_for(&self, scope: SnippetScope, project_path: Option<&Path>) -> FsResult<PathBuf> {
+        match scope {
+            SnippetScope::Global => Ok(self.global_dir.clone()),
+            SnippetScope::Project => project_path
+                .map(|p| p.join(PROJECT_SNIPPET_DIR))
+                .ok_or_else(|| FsError::InvalidName { message: "Project snippets need a project path".to_string() }),
+        }
+    }
+
+    fn read_dir(dir: &Path) -> Vec<Snippet> {
+        let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().map(|x| x == "json").unwrap_or(false))
+            .filter_map(|e| fs::read_to_string(e.path()).ok())
+            .filter_map(|raw| serde_json::from_str(&raw).ok())
+            .collect()
+    }
+
+    /// `base`, or `base-2`, `base-3`, ... when a snippet already has that id
+    fn free_id(dir: &Path, base: &str) -> String {
+        let mut id = base.to_string();
+        let mut n = 2;
+        while dir.join(format!("{}.json", id)).exists() {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        id
+    }
+
+    fn write(dir: &Path, snippet: &Snippet) -> FsResult<()> {
+        fs::create_dir_all(dir)?;
+        let raw = serde_json::to_string_pretty(snippet).map_err(io_error)?;
+        file_ops::write_atomic(&dir.join(format!("{}.json", snippet.id)), raw.as_bytes())
+    }
+
+    /// Create a snippet, or update it in place when `input.id` names an existing one
+    pub fn save(&self, scope: SnippetScope, project_path: Option<&Path>, input: SnippetInput) -> FsResult<Snippet> {
+        let dir = self.dir_for(scope, project_path)?;
+        let _guard = self.lock.lock().unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        let (id, created) = match input.id {
+            Some(id) => {
+                validate_id(&id)?;
+                let existing = fs::read_to_string(dir.join(format!("{}.json", id)))
+                    .ok()
+                    .and_then(|raw| serde_json::from_str::<Snippet>(&raw).ok());
+                (id, existing.map(|s| s.created).unwrap_or(now))
+            }
+            None => (Self::free_id(&dir, &slugify(&input.title)), now),
+        };
+
+        let snippet = Snippet {
+            id,
+            scope,
+            title: input.title,
+            language: input.language,
+            content: input.content,
+            tags: input.tags,
+            created,
+            updated: now,
+        };
+        Self::write(&dir, &snippet)?;
+        Ok(snippet)
+    }
+
+    /// Snippets in `scope` (or both scopes when None) whose title, tags, language or body match `filter`
+    pub fn list(&self, scope: Option<SnippetScope>, project_path: Option<&Path>, filter: Option<&str>) -> Vec<Snippet> {
+        let mut snippets = Vec::new();
+        if scope != Some(SnippetScope::Project) {
+            snippets.extend(Self::read_dir(&self.global_dir));
+        }
+        if scope != Some(SnippetScope::Global) {
+            if let Some(project_path) = project_path {
+                snippets.extend(Self::read_dir(&project_path.join(PROJECT_SNIPPET_DIR)));
+            }
+        }
+
+        if let Some(filter) = filter.map(str::trim).filter(|f| !f.is_empty()) {
+            snippets.retain(|s| matches_filter(s, filter));
+        }
+        snippets.sort_by(|a, b| b.updated.cmp(&a.updated).then_with(|| a.title.cmp(&b.title)));
+        snippets
+    }
+
+    /// Look up a snippet by id, preferring the project copy over a global one with the same id
+    pub fn get(&self, id: &str, project_path: Option<&Path>) -> FsResult<Snippet> {
+        validate_id(id)?;
+        let mut dirs = Vec::new();
+        if let Some(project_path) = project_path {
+            dirs.push(project_path.join(PROJECT_SNIPPET_DIR));
+        }
+        dirs.push(self.global_dir.clone());
+
+        for dir in dirs {
+            if let Ok(raw) = fs::read_to_string(dir.join(format!("{}.json", id))) {
+                return serde_json::from_str(&raw).map_err(io_error);
+            }
+        }
+        Err(FsError::NotFound)
+    }
+
+    pub fn delete(&self, id: &str, project_path: Option<&Path>) -> FsResult<()> {
+        let snippet = self.get(id, project_path)?;
+        let dir = self.dir_for(snippet.scope, project_path)?;
+        let _guard = self.lock.lock().unwrap();
+        fs::remove_file(dir.join(format!("{}.json", id)))?;
+        Ok(())
+    }
+
+    /// Write every snippet in `scope` to a single pack file; returns how many were exported
+    pub fn export_pack(&self, scope: SnippetScope, project_path: Option<&Path>, output_path: &Path) -> FsResult<usize> {
+        let snippets = self.list(Some(scope), project_path, None);
+        let pack = SnippetPack { version: PACK_VERSION, snippets };
+        let raw = serde_json::to_string_pretty(&pack).map_err(io_error)?;
+        file_ops::write_atomic(output_path, raw.as_bytes())?;
+        Ok(pack.snippets.len())
+    }
+
+    /// Add the snippets from a pack to `scope`, replacing ones with the same id. Snippets without
+    /// a usable id get one from their title, suffixed so they never replace an existing snippet.
+    pub fn import_pack(&self, scope: SnippetScope, project_path: Option<&Path>, input_path: &Path) -> FsResult<usize> {
+        let raw = fs::read_to_string(input_path)?;
+        let pack: SnippetPack = serde_json::from_str(&raw).map_err(io_error)?;
+        let dir = self.dir_for(scope, project_path)?;
+        let _guard = self.lock.lock().unwrap();
+
+        let mut imported = 0;
+        for mut snippet in pack.snippets {
+            if validate_id(&snippet.id).is_err() {
+                snippet.id = Self::free_id(&dir, &slugify(&snippet.title));
+            }
+            snippet.scope = scope;
+            Self::write(&dir, &snippet)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(title: &str, content: &str) -> SnippetInput {
+        SnippetInput { id: None, title: title.to_string(), language: "rust".to_string(), content: content.to_string(), tags: Vec::new() }
+    }
+
+    #[test]
+    fn import_suffixes_generated_ids_instead_of_overwriting() {
+        let config = tempfile::tempdir().unwrap();
+        let store = SnippetStore::new(config.path());
+        store.save(SnippetScope::Global, None, input("Read File", "existing")).unwrap();
+
+        let mut pasted = store.get("read-file", None).unwrap();
+        pasted.id = "not a valid id".to_string();
+        let pack = SnippetPack { version: PACK_VERSION, snippets: vec![pasted.clone(), pasted] };
+        let pack_path = config.path().join("pack.json");
+        std::fs::write(&pack_path, serde_json::to_string(&pack).unwrap()).unwrap();
+
+        assert_eq!(store.import_pack(SnippetScope::Global, None, &pack_path).unwrap(), 2);
+        let mut ids: Vec<String> = store.list(Some(SnippetScope::Global), None, None).into_iter().map(|s| s.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["read-file", "read-file-2", "read-file-3"]);
+        assert_eq!(store.get("read-file", None).unwrap().content, "existing");
+    }
+
+    #[test]
+    fn import_replaces_snippets_with_the_same_valid_id() {
+        let config = tempfile::tempdir().unwrap();
+        let store = SnippetStore::new(config.path());
+        let mut snippet = store.save(SnippetScope::Global, None, input("Greeting", "hello")).unwrap();
+        snippet.content = "hello again".to_string();
+        let pack_path = config.path().join("pack.json");
+        std::fs::write(&pack_path, serde_json::to_string(&SnippetPack { version: PACK_VERSION, snippets: vec![snippet] }).unwrap()).unwrap();
+
+        store.import_pack(SnippetScope::Global, None, &pack_path).unwrap();
+        assert_eq!(store.list(Some(SnippetScope::Global), None, None).len(), 1);
+        assert_eq!(store.get("greeting", None).unwrap().content, "hello again");
+    }
+}
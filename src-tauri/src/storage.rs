@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::fs::display_path;
+
+/// Version of the on-disk layout this build reads and writes
+pub const SCHEMA_VERSION: u32 = 2;
+
+const META_FILE: &str = "meta.json";
+const BACKUP_DIR: &str = "migration-backups";
+
+// Migration backups kept by `compact_storage`, newest first
+const KEEP_MIGRATION_BACKUPS: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreLocation {
+    Config,
+    Data,
+}
+
+/// One persistent store and where it lives, relative to its base directory
+struct StoreEntry {
+    name: &'static str,
+    location: StoreLocation,
+    path: &'static str,
+}
+
+const STORES: &[StoreEntry] = &[
+    StoreEntry { name: "settings", location: StoreLocation::Config, path: crate::settings::SETTINGS_FILE },
+    StoreEntry { name: "credentials_index", location: StoreLocation::Config, path: crate::credentials::INDEX_FILE },
+    StoreEntry { name: "credential_profiles", location: StoreLocation::Config, path: crate::credentials::PROFILES_FILE },
+    StoreEntry { name: "credential_vault", location: StoreLocation::Config, path: crate::credentials::VAULT_FILE },
+    StoreEntry { name: "snippets", location: StoreLocation::Config, path: crate::snippets::GLOBAL_SNIPPET_DIR },
+    #[cfg(feature = "git")]
+    StoreEntry { name: "recent_branches", location: StoreLocation::Data, path: crate::recent_branches::STORE_FILE },
+    #[cfg(feature = "git")]
+    StoreEntry { name: "deleted_branches", location: StoreLocation::Data, path: crate::deleted_branches::STORE_FILE },
+    StoreEntry { name: "dialog_directories", location: StoreLocation::Data, path: crate::dialogs::STORE_FILE },
+    StoreEntry { name: "pinned_paths", location: StoreLocation::Data, path: crate::pins::STORE_FILE },
+    StoreEntry { name: "env_profiles", location: StoreLocation::Data, path: crate::env_profiles::STORE_FILE },
+    StoreEntry { name: "file_tags", location: StoreLocation::Data, path: crate::tags::STORE_FILE },
+    #[cfg(feature = "git")]
+    StoreEntry { name: "pending_pushes", location: StoreLocation::Data, path: crate::pending_push::STORE_FILE },
+    StoreEntry { name: "plans", location: StoreLocation::Data, path: crate::plans::STORE_FILE },
+    StoreEntry { name: "change_journal", location: StoreLocation::Data, path: crate::activity::JOURNAL_FILE },
+    StoreEntry { name: "command_audit", location: StoreLocation::Data, path: crate::allowed_commands::AUDIT_FILE },
+    StoreEntry { name: "local_history", location: StoreLocation::Data, path: crate::local_history::HISTORY_DIR },
+    StoreEntry { name: "checkpoints", location: StoreLocation::Data, path: crate::checkpoints::CHECKPOINT_DIR },
+    StoreEntry { name: "blobs", location: StoreLocation::Data, path: crate::blobs::BLOB_DIR },
+    StoreEntry { name: "fingerprints", location: StoreLocation::Data, path: crate::fingerprint::FINGERPRINT_DIR },
+    StoreEntry { name: "scratch", location: StoreLocation::Data, path: crate::scratch::SCRATCH_DIR },
+    StoreEntry { name: "session_artifacts", location: StoreLocation::Data, path: crate::shutdown::JOURNAL_FILE },
+    StoreEntry { name: "migration_backups", location: StoreLocation::Data, path: BACKUP_DIR },
+];
+
+/// A step from `version - 1` to `version`. `files` (relative to the data dir) are copied to a
+/// backup directory first and put back if `run` fails.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    files: &'static [&'static str],
+    run: fn(&StorageLayout) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Record the data layout version in meta.json",
+        files: &[],
+        run: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "Move local history and checkpoint contents into the shared blob store",
+        files: &[],
+        run: |layout| {
+            let blobs = crate::blobs::BlobStore::load(layout.data_dir());
+            for store in [crate::local_history::HISTORY_DIR, crate::checkpoints::CHECKPOINT_DIR] {
+                blobs.adopt(&layout.data_dir().join(store).join(crate::blobs::BLOB_DIR))?;
+            }
+            Ok(())
+        },
+    },
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StorageMeta {
+    schema_version: u32,
+    /// Unix seconds of the last migration
+    updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: String,
+    /// Files copied aside before the migration ran
+    pub backed_up: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<AppliedMigration>,
+    pub backup_dir: Option<String>,
+    /// The migration that failed, after its files were restored; later ones were not attempted
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoreUsage {
+    pub name: String,
+    pub location: StoreLocation,
+    pub path: String,
+    pub exists: bool,
+    pub bytes: u64,
+    pub files: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageInfo {
+    pub config_dir: String,
+    pub data_dir: String,
+    pub schema_version: u32,
+    /// What the running build expects; higher than `schema_version` only before migrations run
+    pub supported_version: u32,
+    pub stores: Vec<StoreUsage>,
+    pub total_bytes: u64,
+    pub last_migration: Option<MigrationReport>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CompactionReport {
+    pub scratch_files: usize,
+    pub history_versions: usize,
+    pub checkpoints: usize,
+    /// Stored contents no history version or checkpoint refers to any more
+    pub blobs: usize,
+    pub audit_entries: usize,
+    pub deleted_branches: usize,
+    pub migration_backups: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// The app's config and data directories. Stores take their base directory from here rather
+/// than resolving it themselves, so the whole layout can be pointed elsewhere.
+pub struct StorageLayout {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    last_migration: Mutex<Option<MigrationReport>>,
+}
+
+fn usage(path: &Path) -> (u64, usize) {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .fold((0, 0), |(bytes, files), m| (bytes + m.len(), files + 1))
+}
+
+impl StorageLayout {
+    pub fn new(config_dir: PathBuf, data_dir: PathBuf) -> Self {
+        Self { config_dir, data_dir, last_migration: Mutex::new(None) }
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn meta_path(&self) -> PathBuf {
+        self.data_dir.join(META_FILE)
+    }
+
+    /// Version recorded in meta.json; installs from before versioning have none and count as 0
+    pub fn schema_version(&self) -> u32 {
+        fs::read_to_string(self.meta_path())
+            .ok()
+            .and_then(|raw| serde_json::from_str::<StorageMeta>(&raw).ok())
+            .map(|meta| meta.schema_version)
+            .unwrap_or(0)
+    }
+
+    fn write_meta(&self, version: u32) -> Result<()> {
+        fs::create_dir_all(&self.data_dir)?;
+        let raw = serde_json::to_string_pretty(&StorageMeta { schema_version: version, updated_at: chrono::Utc::now().timestamp() })?;
+        crate::fs::write_atomic(&self.meta_path(), raw.as_bytes())?;
+        Ok(())
+    }
+
+    /// Run every migration above the recorded version, in order, recording each in meta.json as
+    /// it succeeds. A layout newer than this build is left untouched.
+    pub fn migrate(&self) -> MigrationReport {
+        let from_version = self.schema_version();
+        let mut report = MigrationReport { from_version, to_version: from_version, applied: Vec::new(), backup_dir: None, error: None };
+        if from_version > SCHEMA_VERSION {
+            report.error = Some(format!(
+                "The data layout is version {}, newer than this build supports ({}); it was not migrated",
+                from_version, SCHEMA_VERSION
+            ));
+        }
+        let pending = MIGRATIONS.iter().filter(|m| m.version > from_version);
+        let backup_root = self.data_dir.join(BACKUP_DIR).join(format!("v{}-{}", from_version, chrono::Utc::now().timestamp()));
+        for migration in pending {
+            let backup_dir = backup_root.join(format!("v{}", migration.version));
+            let backed_up = match self.back_up(migration, &backup_dir) {
+                Ok(backed_up) => backed_up,
+                Err(e) => {
+                    report.error = Some(format!("Could not back up files for migration {}: {}", migration.version, e));
+                    break;
+                }
+            };
+            if !backed_up.is_empty() {
+                report.backup_dir = Some(display_path(&backup_root));
+            }
+            if let Err(e) = (migration.run)(self).and_then(|_| self.write_meta(migration.version)) {
+                self.restore(&backed_up, &backup_dir);
+                report.error = Some(format!("Migration {} ({}) failed: {}", migration.version, migration.description, e));
+                break;
+            }
+            report.to_version = migration.version;
+            report.applied.push(AppliedMigration { version: migration.version, description: migration.description.to_string(), backed_up });
+        }
+        if let Some(error) = &report.error {
+            log::error!("Storage migration: {}", error);
+        } else if !report.applied.is_empty() {
+            log::info!("Storage migrated from version {} to {}", report.from_version, report.to_version);
+        }
+        *self.last_migration.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    fn back_up(&self, migration: &Migration, backup_dir: &Path) -> Result<Vec<String>> {
+        let mut backed_up = Vec::new();
+        for file in migration.files {
+            let source = self.data_dir.join(file);
+            if !source.is_file() {
+                continue;
+            }
+            let target = backup_dir.join(file);
+            fs::create_dir_all(target.parent().ok_or_else(|| anyhow!("invalid backup path"))?)?;
+            fs::copy(&source, &target)?;
+            backed_up.push(file.to_string());
+        }
+        Ok(backed_up)
+    }
+
+    fn restore(&self, backed_up: &[String], backup_dir: &Path) {
+        for file in backed_up {
+            if let Err(e) = fs::copy(backup_dir.join(file), self.data_dir.join(file)) {
+                log::error!("Could not restore {} after a failed migration: {}", file, e);
+            }
+        }
+    }
+
+    pub fn info(&self) -> StorageInfo {
+        let stores: Vec<StoreUsage> = STORES
+            .iter()
+            .map(|store| {
+                let base = match store.location {
+                    StoreLocation::Config => &self.config_dir,
+                    StoreLocation::Data => &self.data_dir,
+                };
+                let path = base.join(store.path);
+                let (bytes, files) = usage(&path);
+                StoreUsage { name: store.name.to_string(), location: store.location, path: display_path(&path), exists: path.exists(), bytes, files }
+            })
+            .collect();
+        StorageInfo {
+            config_dir: display_path(&self.config_dir),
+            data_dir: display_path(&self.data_dir),
+            schema_version: self.schema_version(),
+            supported_version: SCHEMA_VERSION,
+            total_bytes: stores.iter().map(|s| s.bytes).sum(),
+            stores,
+            last_migration: self.last_migration.lock().unwrap().clone(),
+        }
+    }
+
+    /// Bytes used by every store together
+    pub fn total_bytes(&self) -> u64 {
+        self.info().total_bytes
+    }
+
+    /// Delete all but the newest migration backups; returns how many were removed
+    pub fn prune_migration_backups(&self) -> usize {
+        let Ok(entries) = fs::read_dir(self.data_dir.join(BACKUP_DIR)) else { return 0 };
+        let mut backups: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+        backups
+            .into_iter()
+            .skip(KEEP_MIGRATION_BACKUPS)
+            .filter(|(_, path)| fs::remove_dir_all(path).is_ok())
+            .count()
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::content_hash;
+use crate::fs::{display_path, is_binary_content};
+use crate::symbols::{self, Symbol};
+
+// Files above this size are described by their metadata only
+const MAX_SUMMARY_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+pub const DEFAULT_SUMMARY_LINES: usize = 40;
+
+// Header lines kept at most, since some files import hundreds of modules
+const MAX_HEADER_LINES: usize = 200;
+
+// Cached summaries kept before the cache is cleared
+const MAX_CACHED_SUMMARIES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMode {
+    /// The first N lines
+    Head,
+    /// The last N lines
+    Tail,
+    /// The symbol tree from the symbols module
+    Outline,
+    /// Import, export and dependency lines
+    Header,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileSummary {
+    pub path: String,
+    pub mode: SummaryMode,
+    /// Content hash of the decoded text, as the file cache computes it
+    pub hash: String,
+    pub line_count: usize,
+    pub byte_size: u64,
+    /// "utf-8", "utf-16le", "utf-16be", or "utf-8-lossy" when invalid bytes were replaced
+    pub encoding: Option<String>,
+    pub binary: bool,
+    /// Head, tail or header lines
+    pub content: Option<String>,
+    pub outline: Option<Vec<Symbol>>,
+    /// 1-based line numbers `content` starts at, for head and tail
+    pub start_line: Option<usize>,
+    /// Cut short by the line count, the header cap or the byte budget
+    pub truncated: bool,
+    /// Why nothing was extracted: binary, too large, or no outline parser for the language
+    pub skipped_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FilesSummaries {
+    pub summaries: Vec<FileSummary>,
+    pub used_bytes: usize,
+    /// Paths that could not be read
+    pub failed: Vec<String>,
+}
+
+/// Decode BOM-marked UTF-16, else UTF-8 (lossily)
+fn decode(bytes: &[u8]) -> (String, &'static str) {
+    let utf16 = |body: &[u8], little: bool| -> String {
+        let units: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|pair| if little { u16::from_le_bytes([pair[0], pair[1]]) } else { u16::from_be_bytes([pair[0], pair[1]]) })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => (utf16(rest, true), "utf-16le"),
+        [0xFE, 0xFF, rest @ ..] => (utf16(rest, false), "utf-16be"),
+        _ => {
+            let body = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+            match std::str::from_utf8(body) {
+                Ok(text) => (text.to_string(), "utf-8"),
+                Err(_) => (String::from_utf8_lossy(body).to_string(), "utf-8-lossy"),
+            }
+        }
+    }
+}
+
+fn header_pattern(path: &Path) -> &'static Regex {
+    static SCRIPT: OnceLock<Regex> = OnceLock::new();
+    static RUST: OnceLock<Regex> = OnceLock::new();
+    static PYTHON: OnceLock<Regex> = OnceLock::new();
+    static GO: OnceLock<Regex> = OnceLock::new();
+    static C: OnceLock<Regex> = OnceLock::new();
+    static GENERIC: OnceLock<Regex> = OnceLock::new();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    let (cell, pattern) = match extension.as_str() {
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" | "vue" | "svelte" => {
+            (&SCRIPT, r"^\s*(import\b|export\b|(const|let|var)\s+.*=\s*require\()")
+        }
+        "rs" => (&RUST, r"^\s*(pub(\([^)]*\))?\s+)?(use|mod|extern\s+crate)\b"),
+        "py" | "pyi" => (&PYTHON, r"^\s*(import|from)\s+\S+|^__all__\s*="),
+        "go" => (&GO, r"^\s*(package|import)\b|^\s*\x22[^\x22]+\x22\s*$"),
+        "c" | "h" | "cc" | "cpp" | "hpp" | "cxx" | "m" | "mm" => (&C, r"^\s*#\s*(include|import)\b"),
+        _ => (&GENERIC, r"^\s*(import|export|use|require|include|from|package|using)\b"),
+    };
+    cell.get_or_init(|| Regex::new(pattern).expect("header pattern"))
+}
+
+/// Cut `text` to at most `max_bytes`, at a line boundary where possible
+fn truncate_text(text: &str, max_bytes: usize) -> (String, bool) {
+    if text.len() <= max_bytes {
+        return (text.to_string(), false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = text[..end].rfind('\n').map(|i| i + 1).unwrap_or(end);
+    (text[..cut].to_string(), true)
+}
+
+fn summarize_text(path: &Path, text: &str, mode: SummaryMode, lines: usize, summary: &mut FileSummary) {
+    let all: Vec<&str> = text.lines().collect();
+    match mode {
+        SummaryMode::Head => {
+            summary.content = Some(all[..lines.min(all.len())].join("\n"));
+            summary.start_line = Some(1);
+            summary.truncated = all.len() > lines;
+        }
+        SummaryMode::Tail => {
+            let start = all.len().saturating_sub(lines);
+            summary.content = Some(all[start..].join("\n"));
+            summary.start_line = Some(start + 1);
+            summary.truncated = start > 0;
+        }
+        SummaryMode::Header => {
+            let pattern = header_pattern(path);
+            let header: Vec<String> = all
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| pattern.is_match(line))
+                .map(|(index, line)| format!("{}: {}", index + 1, line.trim_end()))
+                .collect();
+            summary.truncated = header.len() > MAX_HEADER_LINES;
+            summary.content = Some(header.into_iter().take(MAX_HEADER_LINES).collect::<Vec<_>>().join("\n"));
+        }
+        SummaryMode::Outline => match symbols::parser_for(path) {
+            Some(parser) => summary.outline = Some(parser.outline(text)),
+            None => summary.skipped_reason = Some("no outline parser for this language".to_string()),
+        },
+    }
+}
+
+/// Summaries keyed by content hash and mode, so an unchanged file is not summarized twice
+#[derive(Default)]
+pub struct SummaryCache(Mutex<HashMap<(String, SummaryMode, usize), FileSummary>>);
+
+impl SummaryCache {
+    pub fn summarize(&self, path: &Path, mode: SummaryMode, lines: Option<usize>) -> Result<FileSummary> {
+        let lines = lines.unwrap_or(DEFAULT_SUMMARY_LINES).max(1);
+        let metadata = fs::metadata(path)?;
+        if metadata.is_dir() {
+            return Err(anyhow!("'{}' is a directory", path.display()));
+        }
+        let mut summary = FileSummary {
+            path: display_path(path),
+            mode,
+            hash: String::new(),
+            line_count: 0,
+            byte_size: metadata.len(),
+            encoding: None,
+            binary: false,
+            content: None,
+            outline: None,
+            start_line: None,
+            truncated: false,
+            skipped_reason: None,
+        };
+        if metadata.len() > MAX_SUMMARY_FILE_BYTES {
+            summary.skipped_reason = Some("file is too large to summarize".to_string());
+            return Ok(summary);
+        }
+
+        let bytes = fs::read(path)?;
+        let utf16 = bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]);
+        if !utf16 && is_binary_content(&bytes[..bytes.len().min(8192)]) {
+            summary.binary = true;
+            summary.skipped_reason = Some("binary file".to_string());
+            return Ok(summary);
+        }
+        let (text, encoding) = decode(&bytes);
+        let hash = content_hash(&text);
+
+        let key = (hash.clone(), mode, lines);
+        if let Some(cached) = self.0.lock().unwrap().get(&key) {
+            return Ok(FileSummary { path: summary.path, byte_size: summary.byte_size, ..cached.clone() });
+        }
+
+        summary.hash = hash;
+        summary.encoding = Some(encoding.to_string());
+        summary.line_count = text.lines().count();
+        summarize_text(path, &text, mode, lines, &mut summary);
+
+        let mut cache = self.0.lock().unwrap();
+        if cache.len() >= MAX_CACHED_SUMMARIES {
+            cache.clear();
+        }
+        cache.insert(key, summary.clone());
+        Ok(summary)
+    }
+}
+
+/// Approximate size of a summary's payload: its text, or its outline as JSON
+fn payload_bytes(summary: &FileSummary) -> usize {
+    summary.content.as_ref().map(String::len).unwrap_or(0)
+        + summary.outline.as_ref().and_then(|o| serde_json::to_string(o).ok()).map(|o| o.len()).unwrap_or(0)
+}
+
+/// Trim a summary's payload to `max_bytes`
+fn fit(summary: &mut FileSummary, max_bytes: usize) {
+    if let Some(content) = &summary.content {
+        let (cut, truncated) = truncate_text(content, max_bytes);
+        summary.content = Some(cut);
+        summary.truncated |= truncated;
+    }
+    if let Some(outline) = summary.outline.as_mut() {
+        while !outline.is_empty() && serde_json::to_string(&*outline).map(|o| o.len()).unwrap_or(0) > max_bytes {
+            outline.pop();
+            summary.truncated = true;
+        }
+    }
+}
+
+/// Summaries of `paths` sharing `budget_bytes`: each file gets an equal share of what is left,
+/// and what a short file leaves unused goes to the files after it
+pub fn summarize_files(cache: &SummaryCache, paths: &[String], mode: SummaryMode, lines: Option<usize>, budget_bytes: usize) -> FilesSummaries {
+    let mut summaries = Vec::new();
+    let mut failed = Vec::new();
+    let mut used = 0;
+    for (index, path) in paths.iter().enumerate() {
+        let mut summary = match cache.summarize(&crate::fs::decode_path(path), mode, lines) {
+            Ok(summary) => summary,
+            Err(e) => {
+                log::debug!("Skipping summary of {}: {}", path, e);
+                failed.push(path.clone());
+                continue;
+            }
+        };
+        let share = budget_bytes.saturating_sub(used) / (paths.len() - index);
+        if payload_bytes(&summary) > share {
+            fit(&mut summary, share);
+        }
+        used += payload_bytes(&summary);
+        summaries.push(summary);
+    }
+    FilesSummaries { summaries, used_bytes: used, failed }
+}
+
+/// Lines `start..=end` (1-based) of a text file, decoded like summaries are
+pub fn read_lines(path: &Path, start: usize, end: usize) -> Result<String> {
+    let bytes = fs::read(path)?;
+    let (text, _) = decode(&bytes);
+    let start = start.max(1);
+    Ok(text.lines().skip(start - 1).take(end.saturating_sub(start) + 1).collect::<Vec<_>>().join("\n"))
+}
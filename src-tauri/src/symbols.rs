@@ -0,0 +1,479 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::display_path;
+
+// Larger files are usually generated or vendored and not worth outlining
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+
+// A progress event is emitted every this many files
+const PROGRESS_INTERVAL: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Class,
+    Struct,
+    Enum,
+    Interface,
+    Trait,
+    Impl,
+    Module,
+    Constant,
+    Type,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+    /// 1-based column of the name on `start_line`
+    pub column: usize,
+    pub children: Vec<Symbol>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub path: String,
+    pub start_line: usize,
+    pub column: usize,
+    /// Name of the enclosing symbol, e.g. the class of a method
+    pub container: Option<String>,
+    pub score: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SymbolIndexProgress {
+    pub root: String,
+    pub indexed: usize,
+    pub done: bool,
+}
+
+/// Produces a symbol tree for one file. The regex heuristics below implement it today;
+/// a tree-sitter or LSP backed parser can replace them per language.
+pub trait OutlineParser: Send + Sync {
+    fn outline(&self, text: &str) -> Vec<Symbol>;
+}
+
+/// How a language delimits bodies, which is all the heuristics need to find symbol ranges
+#[derive(Clone, Copy)]
+enum Blocks {
+    Braces,
+    Indentation,
+}
+
+struct Rule {
+    pattern: Regex,
+    kind: SymbolKind,
+    /// Only counts when directly inside a symbol of this kind (e.g. JS methods in classes)
+    inside: Option<SymbolKind>,
+}
+
+struct HeuristicParser {
+    rules: Vec<Rule>,
+    blocks: Blocks,
+}
+
+fn rule(pattern: &str, kind: SymbolKind) -> Rule {
+    Rule { pattern: Regex::new(pattern).expect("symbol pattern"), kind, inside: None }
+}
+
+fn rule_inside(pattern: &str, kind: SymbolKind, inside: SymbolKind) -> Rule {
+    Rule { inside: Some(inside), ..rule(pattern, kind) }
+}
+
+fn rust_parser() -> HeuristicParser {
+    let vis = r"^\s*(?:pub(?:\([^)]*\))?\s+)?";
+    HeuristicParser {
+        rules: vec![
+            rule(&format!(r#"{}(?:default\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+"[^"]*"\s+)?fn\s+(?P<name>[A-Za-z_]\w*)"#, vis), SymbolKind::Function),
+            rule(&format!(r"{}struct\s+(?P<name>[A-Za-z_]\w*)", vis), SymbolKind::Struct),
+            rule(&format!(r"{}enum\s+(?P<name>[A-Za-z_]\w*)", vis), SymbolKind::Enum),
+            rule(&format!(r"{}(?:unsafe\s+)?trait\s+(?P<name>[A-Za-z_]\w*)", vis), SymbolKind::Trait),
+            rule(r"^\s*(?:unsafe\s+)?impl(?:<[^>]*>)?\s+(?:[\w:<>, ]+\s+for\s+)?(?P<name>[A-Za-z_][\w:]*)", SymbolKind::Impl),
+            rule(&format!(r"{}mod\s+(?P<name>[A-Za-z_]\w*)", vis), SymbolKind::Module),
+            rule(&format!(r"{}(?:const|static)\s+(?:mut\s+)?(?P<name>[A-Z_][A-Z0-9_]*)\s*:", vis), SymbolKind::Constant),
+            rule(&format!(r"{}type\s+(?P<name>[A-Za-z_]\w*)", vis), SymbolKind::Type),
+        ],
+        blocks: Blocks::Braces,
+    }
+}
+
+fn script_parser() -> HeuristicParser {
+    let export = r"^\s*(?:export\s+)?(?:default\s+)?";
+    HeuristicParser {
+        rules: vec![
+            rule(&format!(r"{}(?:async\s+)?function\s*\*?\s*(?P<name>[A-Za-z_$][\w$]*)", export), SymbolKind::Function),
+            rule(&format!(r"{}(?:abstract\s+)?class\s+(?P<name>[A-Za-z_$][\w$]*)", export), SymbolKind::Class),
+            rule(&format!(r"{}interface\s+(?P<name>[A-Za-z_$][\w$]*)", export), SymbolKind::Interface),
+            rule(&format!(r"{}(?:const\s+)?enum\s+(?P<name>[A-Za-z_$][\w$]*)", export), SymbolKind::Enum),
+            rule(&format!(r"{}type\s+(?P<name>[A-Za-z_$][\w$]*)\s*(?:<[^=]*>)?\s*=", export), SymbolKind::Type),
+            rule(
+                &format!(r"{}(?:const|let|var)\s+(?P<name>[A-Za-z_$][\w$]*)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|(?:\([^)]*\)|[A-Za-z_$][\w$]*)\s*(?::[^=]+)?=>)", export),
+                SymbolKind::Function,
+            ),
+            rule_inside(
+                r"^\s*(?:(?:public|private|protected|static|async|readonly|override|get|set)\s+)*(?P<name>[A-Za-z_$#][\w$]*)\s*(?:<[^>]*>)?\s*\([^)]*\)?\s*(?::[^{]*)?\{",
+                SymbolKind::Method,
+                SymbolKind::Class,
+            ),
+        ],
+        blocks: Blocks::Braces,
+    }
+}
+
+fn python_parser() -> HeuristicParser {
+    HeuristicParser {
+        rules: vec![
+            rule(r"^\s*(?:async\s+)?def\s+(?P<name>[A-Za-z_]\w*)", SymbolKind::Function),
+            rule(r"^\s*class\s+(?P<name>[A-Za-z_]\w*)", SymbolKind::Class),
+        ],
+        blocks: Blocks::Indentation,
+    }
+}
+
+fn go_parser() -> HeuristicParser {
+    HeuristicParser {
+        rules: vec![
+            rule(r"^func\s+\([^)]*\)\s*(?P<name>[A-Za-z_]\w*)", SymbolKind::Method),
+            rule(r"^func\s+(?P<name>[A-Za-z_]\w*)", SymbolKind::Function),
+            rule(r"^type\s+(?P<name>[A-Za-z_]\w*)\s+struct\b", SymbolKind::Struct),
+            rule(r"^type\s+(?P<name>[A-Za-z_]\w*)\s+interface\b", SymbolKind::Interface),
+            rule(r"^type\s+(?P<name>[A-Za-z_]\w*)", SymbolKind::Type),
+        ],
+        blocks: Blocks::Braces,
+    }
+}
+
+// Control-flow keywords the method heuristic would otherwise mistake for names
+const NOT_NAMES: &[&str] = &["if", "for", "while", "switch", "catch", "function", "return", "with", "else"];
+
+/// Net brace depth change of a line, ignoring braces inside simple string literals and `//` comments
+fn brace_delta(line: &str) -> (i32, bool) {
+    let (mut delta, mut opened) = (0, false);
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '`') => quote = Some(c),
+            // Only char literals ('x', '\n'); a lone quote is a Rust lifetime
+            (None, '\'') => {
+                let mut ahead = chars.clone();
+                if matches!((ahead.next(), ahead.next()), (Some('\\'), _) | (Some(_), Some('\''))) {
+                    quote = Some(c);
+                }
+            }
+            (None, '/') if chars.peek() == Some(&'/') => break,
+            (None, '{') => {
+                delta += 1;
+                opened = true;
+            }
+            (None, '}') => delta -= 1,
+            _ => {}
+        }
+    }
+    (delta, opened)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+impl HeuristicParser {
+    /// Last line (0-based) of the block starting at `start`
+    fn block_end(&self, lines: &[&str], start: usize) -> usize {
+        match self.blocks {
+            Blocks::Braces => {
+                let mut depth = 0;
+                let mut opened = false;
+                for (i, line) in lines.iter().enumerate().skip(start) {
+                    let (delta, opens) = brace_delta(line);
+                    depth += delta;
+                    opened |= opens;
+                    if opened && depth <= 0 {
+                        return i;
+                    }
+                    // A declaration without a body ends on its own line
+                    if !opened && line.trim_end().ends_with(';') {
+                        return i;
+                    }
+                }
+                if opened { lines.len().saturating_sub(1) } else { start }
+            }
+            Blocks::Indentation => {
+                let indent = indentation(lines[start]);
+                let mut end = start;
+                for (i, line) in lines.iter().enumerate().skip(start + 1) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    if indentation(line) <= indent {
+                        break;
+                    }
+                    end = i;
+                }
+                end
+            }
+        }
+    }
+}
+
+/// Nest symbols by range; `flat` must be sorted by start line
+fn build_tree(flat: Vec<(Symbol, Option<SymbolKind>)>) -> Vec<Symbol> {
+    fn attach(stack: &mut Vec<Symbol>, roots: &mut Vec<Symbol>) {
+        let done = stack.pop().expect("non-empty stack");
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(done),
+            None => roots.push(done),
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<Symbol> = Vec::new();
+    for (mut symbol, inside) in flat {
+        while stack.last().map(|open| symbol.start_line > open.end_line).unwrap_or(false) {
+            attach(&mut stack, &mut roots);
+        }
+        let parent = stack.last().map(|p| p.kind);
+        if inside.is_some() && inside != parent {
+            continue;
+        }
+        if symbol.kind == SymbolKind::Function && matches!(parent, Some(SymbolKind::Impl | SymbolKind::Trait | SymbolKind::Class)) {
+            symbol.kind = SymbolKind::Method;
+        }
+        stack.push(symbol);
+    }
+    while !stack.is_empty() {
+        attach(&mut stack, &mut roots);
+    }
+    roots
+}
+
+impl OutlineParser for HeuristicParser {
+    fn outline(&self, text: &str) -> Vec<Symbol> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut flat = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let Some((rule, name)) = self
+                .rules
+                .iter()
+                .find_map(|rule| rule.pattern.captures(line).and_then(|c| c.name("name")).map(|name| (rule, name)))
+            else {
+                continue;
+            };
+            if NOT_NAMES.contains(&name.as_str()) {
+                continue;
+            }
+            let symbol = Symbol {
+                name: name.as_str().to_string(),
+                kind: rule.kind,
+                start_line: i + 1,
+                end_line: self.block_end(&lines, i) + 1,
+                column: line[..name.start()].chars().count() + 1,
+                children: Vec::new(),
+            };
+            flat.push((symbol, rule.inside));
+        }
+        build_tree(flat)
+    }
+}
+
+/// The parser for a file, chosen by extension; None for languages without one
+pub fn parser_for(path: &Path) -> Option<&'static dyn OutlineParser> {
+    static RUST: OnceLock<HeuristicParser> = OnceLock::new();
+    static SCRIPT: OnceLock<HeuristicParser> = OnceLock::new();
+    static PYTHON: OnceLock<HeuristicParser> = OnceLock::new();
+    static GO: OnceLock<HeuristicParser> = OnceLock::new();
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let parser: &'static HeuristicParser = match extension.as_str() {
+        "rs" => RUST.get_or_init(rust_parser),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => SCRIPT.get_or_init(script_parser),
+        "py" | "pyi" => PYTHON.get_or_init(python_parser),
+        "go" => GO.get_or_init(go_parser),
+        _ => return None,
+    };
+    Some(parser)
+}
+
+/// Outline of one file, or None when it is unsupported, too large or not text
+pub fn outline_file(path: &Path) -> Option<Vec<Symbol>> {
+    let parser = parser_for(path)?;
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let text = fs::read_to_string(path).ok()?;
+    Some(parser.outline(&text))
+}
+
+/// Subsequence match of `query` in `name`, case-insensitive. Exact and prefix matches rank
+/// first, then contiguous ones; gaps and late starts cost points, word starts earn them.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let query_lower = query.to_lowercase();
+    let name_lower = name.to_lowercase();
+    if name_lower == query_lower {
+        return Some(1000);
+    }
+    if name_lower.starts_with(&query_lower) {
+        return Some(800 - name.len() as i64);
+    }
+    if let Some(position) = name_lower.find(&query_lower) {
+        return Some(600 - position as i64 - name.len() as i64);
+    }
+
+    let name_chars: Vec<char> = name.chars().collect();
+    let mut score = 400;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query_lower.chars() {
+        let found = (position..name_chars.len()).find(|&i| name_chars[i].to_lowercase().eq(std::iter::once(q)))?;
+        let at_word_start = found == 0
+            || name_chars[found].is_uppercase()
+            || matches!(name_chars[found - 1], '_' | '-' | '.' | '$');
+        if at_word_start {
+            score += 10;
+        }
+        if let Some(previous) = previous {
+            score -= (found - previous - 1) as i64;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score - name.len() as i64)
+}
+
+struct IndexedFile {
+    modified: Option<SystemTime>,
+    symbols: Vec<Symbol>,
+}
+
+/// Symbols of every supported file in the open workspaces, refreshed from watcher events
+#[derive(Default)]
+pub struct SymbolIndex {
+    files: Mutex<HashMap<PathBuf, IndexedFile>>,
+    indexed_roots: Mutex<HashSet<PathBuf>>,
+}
+
+impl SymbolIndex {
+    /// Claim `root` for a background indexing run; false if it is already indexed
+    pub fn begin_root(&self, root: &Path) -> bool {
+        self.indexed_roots.lock().unwrap().insert(root.to_path_buf())
+    }
+
+    /// Index every supported file under `root`, honouring .gitignore
+    pub fn index_root<F: Fn(SymbolIndexProgress)>(&self, root: &Path, progress: F) -> usize {
+        let mut indexed = 0;
+        let walker = WalkBuilder::new(root).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+        for entry in walker.filter_map(|e| e.ok()) {
+            if entry.file_type().map(|t| t.is_file()).unwrap_or(false) && self.update_file(entry.path()) {
+                indexed += 1;
+                if indexed % PROGRESS_INTERVAL == 0 {
+                    progress(SymbolIndexProgress { root: display_path(root), indexed, done: false });
+                }
+            }
+        }
+        progress(SymbolIndexProgress { root: display_path(root), indexed, done: true });
+        indexed
+    }
+
+    /// Re-parse `path` if it changed since it was indexed; removed files leave the index.
+    /// Returns whether the file is indexed afterwards.
+    pub fn update_file(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            self.files.lock().unwrap().remove(path);
+            return false;
+        };
+        let modified = metadata.modified().ok();
+        if let Some(existing) = self.files.lock().unwrap().get(path) {
+            if existing.modified.is_some() && existing.modified == modified {
+                return true;
+            }
+        }
+        match outline_file(path) {
+            Some(symbols) => {
+                self.files.lock().unwrap().insert(path.to_path_buf(), IndexedFile { modified, symbols });
+                true
+            }
+            None => {
+                self.files.lock().unwrap().remove(path);
+                false
+            }
+        }
+    }
+
+    /// Re-key indexed files below a renamed directory; their symbols are unchanged
+    pub fn remap(&self, from: &Path, to: &Path) {
+        let mut files = self.files.lock().unwrap();
+        let moved: Vec<(PathBuf, PathBuf)> = files
+            .keys()
+            .filter_map(|path| Some((path.clone(), crate::fs::remap_prefix(path, from, to)?)))
+            .collect();
+        for (old, new) in moved {
+            if let Some(file) = files.remove(&old) {
+                files.insert(new, file);
+            }
+        }
+    }
+
+    /// Forget a closed workspace's files
+    pub fn remove_root(&self, root: &Path) {
+        self.indexed_roots.lock().unwrap().remove(root);
+        self.files.lock().unwrap().retain(|path, _| !path.starts_with(root));
+    }
+
+    /// Outline from the index, re-parsing the file first if it changed
+    pub fn outline(&self, path: &Path) -> Option<Vec<Symbol>> {
+        if !self.update_file(path) {
+            return None;
+        }
+        self.files.lock().unwrap().get(path).map(|file| file.symbols.clone())
+    }
+
+    pub fn query(&self, root: &Path, query: &str, limit: usize) -> Vec<WorkspaceSymbol> {
+        fn collect(symbols: &[Symbol], container: Option<&str>, path: &Path, query: &str, out: &mut Vec<WorkspaceSymbol>) {
+            for symbol in symbols {
+                if let Some(score) = fuzzy_score(query, &symbol.name) {
+                    out.push(WorkspaceSymbol {
+                        name: symbol.name.clone(),
+                        kind: symbol.kind,
+                        path: display_path(path),
+                        start_line: symbol.start_line,
+                        column: symbol.column,
+                        container: container.map(str::to_string),
+                        score,
+                    });
+                }
+                collect(&symbol.children, Some(&symbol.name), path, query, out);
+            }
+        }
+
+        let mut matches = Vec::new();
+        for (path, file) in self.files.lock().unwrap().iter().filter(|(path, _)| path.starts_with(root)) {
+            collect(&file.symbols, None, path, query, &mut matches);
+        }
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)).then_with(|| a.path.cmp(&b.path)));
+        matches.truncate(limit);
+        matches
+    }
+}
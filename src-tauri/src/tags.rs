@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+
+use crate::fs::display_path;
+use crate::pins::{project_key, relative_key, relative_to};
+
+pub(crate) const STORE_FILE: &str = "file-tags.json";
+
+const MAX_TAG_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TaggedFile {
+    pub path: String,
+    /// Relative to the project root, with `/` separators
+    pub relative_path: String,
+    pub tags: Vec<String>,
+    /// The file no longer exists; its tags are kept so the UI can offer to retag or clear them
+    pub missing: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+    /// How many of `count` are files that no longer exist
+    pub missing: usize,
+}
+
+/// Free-form tags on files per project ("needs-review", "hot-path"), keyed by project-relative
+/// path and persisted in the app data dir
+pub struct TagStore {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, BTreeMap<String, Vec<String>>>>,
+}
+
+/// Trimmed, deduplicated and sorted; empty tags are dropped
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if tag.chars().count() > MAX_TAG_LENGTH || tag.chars().any(|c| c.is_control() || c == ',') {
+            return Err(anyhow!("Invalid tag '{}': tags are up to {} characters, without commas", tag, MAX_TAG_LENGTH));
+        }
+        normalized.push(tag.to_string());
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+impl TagStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(STORE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn persist(&self, entries: &HashMap<String, BTreeMap<String, Vec<String>>>) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(raw) = serde_json::to_string_pretty(entries) {
+            let _ = crate::fs::write_atomic(&self.path, raw.as_bytes());
+        }
+    }
+
+    /// Replace the tags of `path`. An empty list removes it from the store; that also works for
+    /// a file that no longer exists.
+    pub fn set_tags(&self, project: &Path, path: &str, tags: &[String]) -> Result<Vec<String>> {
+        let tags = normalize_tags(tags)?;
+        let key = project_key(project);
+        let relative = if tags.is_empty() {
+            relative_key(Path::new(&key), path)
+        } else {
+            relative_to(project, &crate::fs::decode_path(path))?
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        let files = entries.entry(key.clone()).or_default();
+        let changed = if tags.is_empty() {
+            files.remove(&relative).is_some()
+        } else {
+            files.insert(relative, tags.clone()).as_ref() != Some(&tags)
+        };
+        if files.is_empty() {
+            entries.remove(&key);
+        }
+        if changed {
+            self.persist(&entries);
+        }
+        Ok(tags)
+    }
+
+    /// Tags of an absolute or project-relative path
+    pub fn get_tags(&self, project: &Path, path: &str) -> Vec<String> {
+        let key = project_key(project);
+        let relative = relative_key(Path::new(&key), path);
+        self.entries.lock().unwrap().get(&key).and_then(|files| files.get(&relative)).cloned().unwrap_or_default()
+    }
+
+    /// Every tag in the project with how many files carry it, most used first
+    pub fn list_tags(&self, project: &Path) -> Vec<TagCount> {
+        let key = project_key(project);
+        let root = PathBuf::from(&key);
+        let files = self.entries.lock().unwrap().get(&key).cloned().unwrap_or_default();
+        let mut counts: BTreeMap<String, TagCount> = BTreeMap::new();
+        for (relative, tags) in &files {
+            let missing = fs::symlink_metadata(root.join(relative)).is_err();
+            for tag in tags {
+                let count = counts.entry(tag.clone()).or_insert_with(|| TagCount { tag: tag.clone(), count: 0, missing: 0 });
+                count.count += 1;
+                count.missing += missing as usize;
+            }
+        }
+        let mut counts: Vec<TagCount> = counts.into_values().collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        counts
+    }
+
+    /// Files carrying `tag`, by relative path, including ones that no longer exist
+    pub fn find_files_by_tag(&self, project: &Path, tag: &str) -> Vec<TaggedFile> {
+        let key = project_key(project);
+        let root = PathBuf::from(&key);
+        let files = self.entries.lock().unwrap().get(&key).cloned().unwrap_or_default();
+        files
+            .into_iter()
+            .filter(|(_, tags)| tags.iter().any(|t| t == tag.trim()))
+            .map(|(relative, tags)| {
+                let absolute = root.join(&relative);
+                TaggedFile {
+                    path: display_path(&absolute),
+                    missing: fs::symlink_metadata(&absolute).is_err(),
+                    relative_path: relative,
+                    tags,
+                }
+            })
+            .collect()
+    }
+
+    /// Absolute paths of the project's existing files carrying `tag`, for filtering search results
+    #[cfg(feature = "git")]
+    pub fn tagged_paths(&self, project: &Path, tag: &str) -> std::collections::HashSet<PathBuf> {
+        self.find_files_by_tag(project, tag).into_iter().filter(|f| !f.missing).map(|f| PathBuf::from(f.path)).collect()
+    }
+
+    /// Rewrite tags at or below a renamed file or directory. `from` and `to` are resolved
+    /// paths; tags moved out of their project are left to show as missing.
+    pub fn remap(&self, from: &Path, to: &Path) {
+        let relative = |root: &Path, path: &Path| -> Option<String> {
+            let rest = path.strip_prefix(root).ok()?;
+            Some(rest.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let mut changed = false;
+        for (key, files) in entries.iter_mut() {
+            let root = PathBuf::from(key);
+            let (Some(old), Some(new)) = (relative(&root, from), relative(&root, to)) else { continue };
+            let moved: Vec<String> = files
+                .keys()
+                .filter(|path| **path == old || path.starts_with(&format!("{}/", old)))
+                .cloned()
+                .collect();
+            for path in moved {
+                let Some(tags) = files.remove(&path) else { continue };
+                let rest = &path[old.len()..];
+                files.insert(format!("{}{}", new, rest), tags);
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist(&entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write};
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn tags_are_normalized_and_persisted() {
+        let data = tempfile::tempdir().unwrap();
+        let (_dir, project) = temp_dir();
+        let file = write(&project, "src/main.rs", "");
+        let store = TagStore::load(data.path());
+
+        let set = store.set_tags(&project, &display_path(&file), &tags(&[" hot-path", "needs-review", "hot-path", ""])).unwrap();
+        assert_eq!(set, ["hot-path", "needs-review"]);
+        assert!(store.set_tags(&project, &display_path(&file), &tags(&["a,b"])).is_err());
+
+        let reloaded = TagStore::load(data.path());
+        assert_eq!(reloaded.get_tags(&project, "src/main.rs"), ["hot-path", "needs-review"]);
+        reloaded.set_tags(&project, "src/main.rs", &[]).unwrap();
+        assert!(reloaded.get_tags(&project, "src/main.rs").is_empty());
+    }
+
+    #[test]
+    fn deleted_files_keep_their_tags_and_show_as_missing() {
+        let data = tempfile::tempdir().unwrap();
+        let (_dir, project) = temp_dir();
+        let kept = write(&project, "kept.rs", "");
+        let deleted = write(&project, "deleted.rs", "");
+        let store = TagStore::load(data.path());
+        store.set_tags(&project, &display_path(&kept), &tags(&["needs-review"])).unwrap();
+        store.set_tags(&project, &display_path(&deleted), &tags(&["needs-review", "refactor-target"])).unwrap();
+        std::fs::remove_file(&deleted).unwrap();
+
+        let counts: Vec<(String, usize, usize)> = store.list_tags(&project).into_iter().map(|c| (c.tag, c.count, c.missing)).collect();
+        assert_eq!(counts, [("needs-review".to_string(), 2, 1), ("refactor-target".to_string(), 1, 1)]);
+
+        let found: Vec<(String, bool)> = store.find_files_by_tag(&project, "needs-review").into_iter().map(|f| (f.relative_path, f.missing)).collect();
+        assert_eq!(found, [("deleted.rs".to_string(), true), ("kept.rs".to_string(), false)]);
+    }
+
+    #[test]
+    fn remap_follows_renamed_directories() {
+        let data = tempfile::tempdir().unwrap();
+        let (_dir, project) = temp_dir();
+        let file = write(&project, "old/inner/lib.rs", "");
+        write(&project, "older/lib.rs", "");
+        let store = TagStore::load(data.path());
+        store.set_tags(&project, &display_path(&file), &tags(&["hot-path"])).unwrap();
+        store.set_tags(&project, &display_path(&project.join("older/lib.rs")), &tags(&["hot-path"])).unwrap();
+
+        store.remap(&project.join("old"), &project.join("new"));
+        assert_eq!(store.get_tags(&project, "new/inner/lib.rs"), ["hot-path"]);
+        assert!(store.get_tags(&project, "old/inner/lib.rs").is_empty());
+        // A sibling sharing the directory's name as a prefix stays put
+        assert_eq!(store.get_tags(&project, "older/lib.rs"), ["hot-path"]);
+    }
+}
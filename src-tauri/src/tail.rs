@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher, recommended_watcher};
+
+use crate::fs::{self as file_ops, FsError, FsResult};
+
+/// Appended data for a followed file, emitted as `file-tail`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TailChunk {
+    pub tail_id: String,
+    pub lines: Vec<String>,
+    /// Set when the file shrank (truncated or rotated) and reading restarted from the top
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TailStart {
+    pub tail_id: String,
+    pub lines: Vec<String>,
+    pub offset: u64,
+    pub following: bool,
+}
+
+/// Active tails keyed by id; dropping a watcher ends its follow thread
+#[derive(Default)]
+pub struct TailState(Mutex<HashMap<String, RecommendedWatcher>>);
+
+/// Splits byte chunks into complete lines, carrying partial lines between reads
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn new() -> Self {
+        Self { pending: Vec::new() }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let text = String::from_utf8_lossy(&line[..line.len() - 1]);
+            lines.push(text.trim_end_matches('\r').to_string());
+        }
+        lines
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+fn read_range(path: &Path, from: u64) -> FsResult<Vec<u8>> {
+    let mut file = File::open(file_ops::long_path(path))?;
+    file.seek(SeekFrom::Start(from))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl TailState {
+    pub fn active_count(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    /// Read the trailing `from_end_bytes` of a file and optionally follow appended data
+    pub fn start<F>(&self, path: &Path, from_end_bytes: u64, follow: bool, tail_id: String, emit: F) -> FsResult<TailStart>
+    where
+        F: Fn(TailChunk) + Send + 'static,
+    {
+        if file_ops::is_binary_file(path)? {
+            return Err(FsError::Io { message: "Cannot tail a binary file".to_string() });
+        }
+        self.stop(&tail_id);
+
+        let size = std::fs::metadata(file_ops::long_path(path))?.len();
+        let start = size.saturating_sub(from_end_bytes);
+        let mut bytes = read_range(path, start)?;
+        // The file may have grown since `size` was read; follow on from what was actually read
+        let end = start + bytes.len() as u64;
+
+        // Drop the partial first line when starting mid-file
+        if start > 0 {
+            match bytes.iter().position(|&b| b == b'\n') {
+                Some(pos) => {
+                    bytes.drain(..=pos);
+                }
+                None => bytes.clear(),
+            }
+        }
+
+        let mut buffer = LineBuffer::new();
+        let mut lines = buffer.push(&bytes);
+        if !follow && !buffer.pending.is_empty() {
+            lines.push(String::from_utf8_lossy(&buffer.pending).to_string());
+            buffer.clear();
+        }
+        let offset = end - buffer.pending.len() as u64;
+
+        if follow {
+            let watcher = spawn_follower(path.to_path_buf(), offset, tail_id.clone(), emit)?;
+            self.0.lock().unwrap().insert(tail_id.clone(), watcher);
+        }
+
+        Ok(TailStart { tail_id, lines, offset, following: follow })
+    }
+
+    pub fn stop(&self, tail_id: &str) -> bool {
+        self.0.lock().unwrap().remove(tail_id).is_some()
+    }
+
+    pub fn stop_all(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+fn spawn_follower<F>(path: PathBuf, offset: u64, tail_id: String, emit: F) -> FsResult<RecommendedWatcher>
+where
+    F: Fn(TailChunk) + Send + 'static,
+{
+    let (tx, rx) = channel();
+    let mut watcher = recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    })
+    .map_err(|e| FsError::Io { message: e.to_string() })?;
+
+    // Watch the parent so rotation (rename + recreate) keeps producing events
+    let parent = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    watcher
+        .watch(&parent, RecursiveMode::NonRecursive)
+        .map_err(|e| FsError::Io { message: e.to_string() })?;
+
+    std::thread::spawn(move || {
+        let mut offset = offset;
+        let mut buffer = LineBuffer::new();
+
+        while let Ok(event) = rx.recv() {
+            if !event.paths.iter().any(|p| p.file_name() == path.file_name()) {
+                continue;
+            }
+            let size = match std::fs::metadata(file_ops::long_path(&path)) {
+                Ok(meta) => meta.len(),
+                Err(_) => continue,
+            };
+
+            let truncated = size < offset;
+            if truncated {
+                offset = 0;
+                buffer.clear();
+            }
+            if size == offset && !truncated {
+                continue;
+            }
+
+            let bytes = match read_range(&path, offset) {
+                Ok(bytes) => bytes,
+                Err(_) => continue,
+            };
+            offset += bytes.len() as u64;
+
+            let lines = buffer.push(&bytes);
+            if !lines.is_empty() || truncated {
+                emit(TailChunk { tail_id: tail_id.clone(), lines, truncated });
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_offset_covers_what_was_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "first\nsecond\nthird\npartial").unwrap();
+
+        let tails = TailState::default();
+        let start = tails.start(&path, 14, false, "t".to_string(), |_| {}).unwrap();
+        assert_eq!(start.lines, vec!["third", "partial"]);
+        assert_eq!(start.offset, 26);
+    }
+
+    #[test]
+    fn start_leaves_a_partial_line_pending_when_following() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "first\nsecond\npartial").unwrap();
+
+        let tails = TailState::default();
+        let start = tails.start(&path, 1024, true, "t".to_string(), |_| {}).unwrap();
+        assert_eq!(start.lines, vec!["first", "second"]);
+        assert_eq!(start.offset, 13);
+        assert!(tails.stop("t"));
+    }
+}
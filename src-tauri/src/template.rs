@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use walkdir::WalkDir;
+
+use crate::fs::is_binary_content;
+use crate::git::{self, CloneProgress, GitManager};
+use crate::operations::CancelToken;
+
+const MANIFEST_FILE: &str = "template.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TemplateOptions {
+    pub initial_commit: bool,
+    /// Values for `{{name}}` placeholders, overriding manifest defaults
+    pub variables: HashMap<String, String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Optional `template.json` at the template root
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct TemplateManifest {
+    variables: HashMap<String, String>,
+    /// Paths (relative to the root) that should not be rewritten
+    skip: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TemplateProgress {
+    pub workspace_root: String,
+    pub stage: String,
+    pub clone: Option<CloneProgress>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TemplateResult {
+    pub project_path: String,
+    pub files_rewritten: usize,
+    pub commit_hash: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+fn substitute(input: &str, variables: &HashMap<String, String>) -> String {
+    let mut output = input.to_string();
+    for (key, value) in variables {
+        output = output.replace(&format!("{{{{{}}}}}", key), value);
+    }
+    output
+}
+
+/// Clone a template, strip its history, fill in placeholders and start a fresh repository.
+/// The target directory is removed again if any stage fails or the operation is cancelled.
+pub fn create_project_from_template<F>(
+    template_url: &str,
+    target_path: &Path,
+    project_name: &str,
+    options: TemplateOptions,
+    cancel: CancelToken,
+    on_progress: F,
+) -> Result<TemplateResult>
+where
+    F: Fn(TemplateProgress),
+{
+    let existed = target_path.exists();
+    if existed && fs::read_dir(target_path)?.next().is_some() {
+        return Err(anyhow!("Target directory '{}' is not empty", target_path.display()));
+    }
+
+    let journaled = if existed { None } else { Some(crate::shutdown::create_tracked_directory(target_path)?) };
+    let result = build_from_template(template_url, target_path, project_name, options, &cancel, &on_progress);
+    if result.is_err() {
+        if existed {
+            if let Ok(entries) = fs::read_dir(target_path) {
+                for entry in entries.filter_map(|e| e.ok()) {
+                    let _ = crate::fs::delete_path(&entry.path());
+                }
+            }
+        } else {
+            let _ = fs::remove_dir_all(target_path);
+        }
+    }
+    drop(journaled);
+    result
+}
+
+fn build_from_template<F>(
+    template_url: &str,
+    target_path: &Path,
+    project_name: &str,
+    options: TemplateOptions,
+    cancel: &CancelToken,
+    on_progress: &F,
+) -> Result<TemplateResult>
+where
+    F: Fn(TemplateProgress),
+{
+    let workspace_root = target_path.to_string_lossy().to_string();
+    let stage = |name: &str| on_progress(TemplateProgress {
+        workspace_root: workspace_root.clone(),
+        stage: name.to_string(),
+        clone: None,
+    });
+    let check_cancel = || if cancel.is_cancelled() { Err(anyhow!("Template creation cancelled")) } else { Ok(()) };
+
+    stage("cloning");
+    git::clone_repository(template_url, target_path, options.username.clone(), options.password.clone(), &Default::default(), |progress| {
+        on_progress(TemplateProgress {
+            workspace_root: workspace_root.clone(),
+            stage: "cloning".to_string(),
+            clone: Some(progress),
+        });
+        !cancel.is_cancelled()
+    })?;
+    check_cancel()?;
+
+    stage("stripping history");
+    fs::remove_dir_all(target_path.join(".git"))?;
+
+    stage("applying template");
+    let manifest_path = target_path.join(MANIFEST_FILE);
+    let manifest: TemplateManifest = match fs::read_to_string(&manifest_path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(|e| anyhow!("Invalid {}: {}", MANIFEST_FILE, e))?,
+        Err(_) => TemplateManifest::default(),
+    };
+    let _ = fs::remove_file(&manifest_path);
+
+    let mut variables = manifest.variables;
+    variables.extend(options.variables);
+    variables.insert("project_name".to_string(), project_name.to_string());
+
+    let files_rewritten = apply_substitutions(target_path, &variables, &manifest.skip, cancel)?;
+    check_cancel()?;
+
+    stage("initializing repository");
+    let init = git::init_git_repo_enhanced(target_path)?;
+    if !init.success {
+        return Err(anyhow!(init.message));
+    }
+
+    let mut warnings = Vec::new();
+    let mut commit_hash = None;
+    if options.initial_commit {
+        stage("creating initial commit");
+        let repo = git2::Repository::open(target_path)?;
+        git::stage_all(&repo, false)?;
+        match GitManager::new(target_path).commit(&format!("Initial commit from template for {}", project_name)) {
+            Ok(hash) => commit_hash = Some(hash),
+            Err(e) => warnings.push(format!("Initial commit skipped: {}", e)),
+        }
+    }
+
+    Ok(TemplateResult {
+        project_path: target_path.to_string_lossy().to_string(),
+        files_rewritten,
+        commit_hash,
+        warnings,
+    })
+}
+
+/// `path` renamed to `name` in its own directory. Variable values come from the template and
+/// the caller, so a name that would move the entry anywhere else is refused.
+fn renamed_within(root: &Path, path: &Path, name: &str) -> Result<PathBuf> {
+    let escapes = || anyhow!("Template variables turn '{}' into '{}', which is not a plain file name", path.display(), name);
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) || Path::new(name).is_absolute() {
+        return Err(escapes());
+    }
+    let destination = path.with_file_name(name);
+    let parent = destination.parent().and_then(|p| fs::canonicalize(p).ok()).ok_or_else(escapes)?;
+    if !parent.starts_with(fs::canonicalize(root)?) {
+        return Err(escapes());
+    }
+    Ok(destination)
+}
+
+/// Rewrite placeholders in text file contents, then in file and directory names (deepest first)
+fn apply_substitutions(root: &Path, variables: &HashMap<String, String>, skip: &[String], cancel: &CancelToken) -> Result<usize> {
+    let mut rewritten = 0;
+    let skipped = |path: &Path| {
+        let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        skip.iter().any(|s| relative == *s || relative.starts_with(&format!("{}/", s.trim_end_matches('/'))))
+    };
+
+    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+        if cancel.is_cancelled() {
+            return Err(anyhow!("Template creation cancelled"));
+        }
+        if !entry.file_type().is_file() || skipped(entry.path()) {
+            continue;
+        }
+        let bytes = fs::read(entry.path())?;
+        if is_binary_content(&bytes) {
+            continue;
+        }
+        let Ok(text) = String::from_utf8(bytes) else { continue };
+        let replaced = substitute(&text, variables);
+        if replaced != text {
+            fs::write(entry.path(), replaced)?;
+            rewritten += 1;
+        }
+    }
+
+    let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for entry in WalkDir::new(root).contents_first(true).into_iter().filter_map(|e| e.ok()) {
+        if skipped(entry.path()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let replaced = substitute(&name, variables);
+        if replaced != name {
+            renames.push((entry.path().to_path_buf(), renamed_within(root, entry.path(), &replaced)?));
+        }
+    }
+    for (from, to) in renames {
+        fs::rename(from, to)?;
+    }
+
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::OperationRegistry;
+    use crate::test_support::{temp_dir, write};
+
+    fn substitute_names(root: &Path, value: &str) -> Result<usize> {
+        let variables = HashMap::from([("name".to_string(), value.to_string())]);
+        apply_substitutions(root, &variables, &[], &OperationRegistry::default().register("template"))
+    }
+
+    #[test]
+    fn placeholders_rename_files_in_place() {
+        let (_dir, root) = temp_dir();
+        write(&root, "src/{{name}}.rs", "mod {{name}};");
+        assert_eq!(substitute_names(&root, "widget").unwrap(), 1);
+        assert_eq!(fs::read_to_string(root.join("src/widget.rs")).unwrap(), "mod widget;");
+    }
+
+    #[test]
+    fn placeholders_cannot_move_files_out_of_the_project() {
+        for value in ["../escaped", "..", "sub/dir", "a\\b", "/tmp/absolute", ""] {
+            let (_dir, root) = temp_dir();
+            write(&root, "src/{{name}}", "content");
+            assert!(substitute_names(&root, value).is_err(), "{:?}", value);
+            assert!(root.join("src/{{name}}").exists(), "{:?}", value);
+            assert!(!root.join("escaped").exists());
+        }
+    }
+}
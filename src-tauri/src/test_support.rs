@@ -0,0 +1,61 @@
+//! Temp-directory repositories shared by the unit tests
+
+use std::path::{Path, PathBuf};
+use git2::Repository;
+use tempfile::TempDir;
+
+/// An empty directory, canonicalized so it compares equal to workspace roots
+pub fn temp_dir() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = crate::workspace::canonical_root(dir.path()).unwrap();
+    (dir, root)
+}
+
+/// A fresh repository with a committer identity configured
+pub fn init_repo() -> (TempDir, PathBuf) {
+    let (dir, root) = temp_dir();
+    let repo = Repository::init(&root).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Test User").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    (dir, root)
+}
+
+/// Write `content` to `relative` below `root`, creating parent directories
+pub fn write(root: &Path, relative: &str, content: &str) -> PathBuf {
+    let path = root.join(relative);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+/// Stage everything in the working tree and commit it on the current branch
+#[cfg(feature = "git")]
+pub fn commit_all(root: &Path, message: &str) -> git2::Oid {
+    let repo = Repository::open(root).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.update_all(["*"], None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("Test User", "test@example.com").unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap()
+}
+
+/// Run the git CLI in `root`, for fixtures libgit2 cannot build; panics when git fails
+#[cfg(feature = "git")]
+pub fn git(root: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .env("GIT_AUTHOR_NAME", "Test User")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test User")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .output()
+        .expect("git should be installed to run this test");
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
@@ -0,0 +1,421 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{display_path, is_binary_content, write_atomic};
+use crate::line_endings::{self, LineEnding};
+use crate::operations::CancelToken;
+
+// Read from the workspace root, taking precedence over the policy in settings
+const PROJECT_POLICY_FILE: &str = ".agentic-ide/policy.json";
+
+// Larger files are almost certainly generated; leave them alone
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+// Line-level violations reported per file and rule; the rest are only counted
+const MAX_LINES_PER_RULE: usize = 50;
+
+// Emit a progress event every this many checked files
+const PROGRESS_EVERY: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    Encoding,
+    EndOfLine,
+    FinalNewline,
+    TrailingWhitespace,
+}
+
+/// Rules switched off for files matching `glob` (relative to the root, `/` separators)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyException {
+    pub glob: String,
+    pub skip: Vec<PolicyRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TextPolicy {
+    /// Any of "utf-8" and "utf-8-bom"; files in other encodings are reported but never rewritten
+    pub allowed_encodings: Vec<String>,
+    /// None leaves line endings unchecked
+    pub end_of_line: Option<LineEnding>,
+    pub final_newline: bool,
+    pub trim_trailing_whitespace: bool,
+    /// Skip files marked `linguist-generated` in .gitattributes
+    pub skip_generated: bool,
+    pub exceptions: Vec<PolicyException>,
+}
+
+impl Default for TextPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_encodings: vec!["utf-8".to_string()],
+            end_of_line: Some(LineEnding::Lf),
+            final_newline: true,
+            trim_trailing_whitespace: true,
+            skip_generated: true,
+            exceptions: vec![PolicyException { glob: "*.md".to_string(), skip: vec![PolicyRule::TrailingWhitespace] }],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyViolation {
+    pub path: String,
+    /// 1-based; None for whole-file rules
+    pub line: Option<usize>,
+    pub message: String,
+    /// `fix_text_policy` can correct it
+    pub fixable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyReport {
+    pub root: String,
+    pub files_checked: usize,
+    /// Binary, generated, oversized or unreadable files
+    pub files_skipped: usize,
+    pub violations: BTreeMap<PolicyRule, Vec<PolicyViolation>>,
+    /// Line-level violations left out past the per-file limit
+    pub omitted: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyProgress {
+    pub root: String,
+    pub files_done: usize,
+    pub files_total: usize,
+    /// "checking" or "done"
+    pub stage: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyFix {
+    pub path: String,
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolicyFixReport {
+    pub root: String,
+    pub dry_run: bool,
+    pub fixes: Vec<PolicyFix>,
+    /// A write failed and every file written before it was put back
+    pub rolled_back: bool,
+    pub error: Option<String>,
+}
+
+/// One file's rewrite, planned before anything is written
+pub struct PlannedFix {
+    pub path: PathBuf,
+    original: Vec<u8>,
+    fixed: Vec<u8>,
+    rules: Vec<PolicyRule>,
+}
+
+/// The policy for `root`: `.agentic-ide/policy.json` when present, else `fallback` from settings
+pub fn load_policy(root: &Path, fallback: &TextPolicy) -> Result<TextPolicy> {
+    match fs::read_to_string(root.join(PROJECT_POLICY_FILE)) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(|e| anyhow!("{} is invalid: {}", PROJECT_POLICY_FILE, e)),
+        Err(_) => Ok(fallback.clone()),
+    }
+}
+
+struct Exceptions(Vec<(GlobSet, Vec<PolicyRule>)>);
+
+impl Exceptions {
+    fn new(policy: &TextPolicy) -> Result<Self> {
+        let mut exceptions = Vec::new();
+        for exception in &policy.exceptions {
+            let glob = Glob::new(&exception.glob).map_err(|e| anyhow!("Invalid exception glob '{}': {}", exception.glob, e))?;
+            let set = GlobSetBuilder::new().add(glob).build()?;
+            exceptions.push((set, exception.skip.clone()));
+        }
+        Ok(Self(exceptions))
+    }
+
+    fn skips(&self, relative: &str, rule: PolicyRule) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        self.0.iter().any(|(set, skip)| skip.contains(&rule) && (set.is_match(relative) || set.is_match(name)))
+    }
+}
+
+fn encoding_of(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        "utf-16"
+    } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        if std::str::from_utf8(&bytes[3..]).is_ok() { "utf-8-bom" } else { "unknown" }
+    } else if std::str::from_utf8(bytes).is_ok() {
+        "utf-8"
+    } else {
+        "unknown"
+    }
+}
+
+/// Each line of `bytes` as its content and its terminator (empty for an unterminated last line)
+fn split_lines(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut lines = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let end = rest.iter().position(|&b| b == b'\n' || b == b'\r').unwrap_or(rest.len());
+        let terminator = if rest[end..].starts_with(b"\r\n") { 2 } else { (end < rest.len()) as usize };
+        lines.push((&rest[..end], &rest[end..end + terminator]));
+        rest = &rest[end + terminator..];
+    }
+    lines
+}
+
+/// What one file breaks, and its contents with the fixable violations corrected
+struct FileCheck {
+    found: Vec<(PolicyRule, PolicyViolation)>,
+    omitted: usize,
+    original: Vec<u8>,
+    fixed: Vec<u8>,
+    fixed_rules: Vec<PolicyRule>,
+}
+
+fn check(relative: &str, bytes: Vec<u8>, policy: &TextPolicy, exceptions: &Exceptions) -> FileCheck {
+    let applies = |rule: PolicyRule| !exceptions.skips(relative, rule);
+    let violation = |line: Option<usize>, message: String, fixable: bool| PolicyViolation { path: relative.to_string(), line, message, fixable };
+    let mut result = FileCheck { found: Vec::new(), omitted: 0, fixed: bytes.clone(), original: bytes, fixed_rules: Vec::new() };
+
+    let encoding = encoding_of(&result.original);
+    if applies(PolicyRule::Encoding) && !policy.allowed_encodings.iter().any(|e| e.eq_ignore_ascii_case(encoding)) {
+        let fixable = encoding == "utf-8-bom" && policy.allowed_encodings.iter().any(|e| e.eq_ignore_ascii_case("utf-8"));
+        result.found.push((PolicyRule::Encoding, violation(None, format!("Encoded as {}", encoding), fixable)));
+        if fixable {
+            result.fixed.drain(..3);
+            result.fixed_rules.push(PolicyRule::Encoding);
+        }
+    }
+    // The remaining rules read lines, which only makes sense for text we can decode
+    if encoding == "utf-16" || encoding == "unknown" {
+        return result;
+    }
+
+    if let Some(target) = policy.end_of_line.filter(|_| applies(PolicyRule::EndOfLine)) {
+        let (lf, crlf, cr) = line_endings::count(&result.fixed);
+        let wrong = match target {
+            LineEnding::Lf => crlf + cr,
+            LineEnding::Crlf => lf + cr,
+            LineEnding::Cr => lf + crlf,
+        };
+        if wrong > 0 {
+            result.found.push((PolicyRule::EndOfLine, violation(None, format!("{} line endings are not {:?}", wrong, target), true)));
+            result.fixed = line_endings::convert(&result.fixed, target);
+            result.fixed_rules.push(PolicyRule::EndOfLine);
+        }
+    }
+
+    if policy.trim_trailing_whitespace && applies(PolicyRule::TrailingWhitespace) {
+        let lines = split_lines(&result.fixed);
+        let trailing: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, (content, _))| content.ends_with(b" ") || content.ends_with(b"\t"))
+            .map(|(index, _)| index + 1)
+            .collect();
+        if !trailing.is_empty() {
+            for &line in trailing.iter().take(MAX_LINES_PER_RULE) {
+                result.found.push((PolicyRule::TrailingWhitespace, violation(Some(line), "Trailing whitespace".to_string(), true)));
+            }
+            result.omitted += trailing.len().saturating_sub(MAX_LINES_PER_RULE);
+            let mut trimmed = Vec::with_capacity(result.fixed.len());
+            for (content, terminator) in lines {
+                let kept = content.len() - content.iter().rev().take_while(|&&b| b == b' ' || b == b'\t').count();
+                trimmed.extend_from_slice(&content[..kept]);
+                trimmed.extend_from_slice(terminator);
+            }
+            result.fixed = trimmed;
+            result.fixed_rules.push(PolicyRule::TrailingWhitespace);
+        }
+    }
+
+    let fixed = &result.fixed;
+    if policy.final_newline && applies(PolicyRule::FinalNewline) && !fixed.is_empty() && !fixed.ends_with(b"\n") && !fixed.ends_with(b"\r") {
+        result.found.push((PolicyRule::FinalNewline, violation(None, "No newline at end of file".to_string(), true)));
+        result.fixed.extend_from_slice(policy.end_of_line.unwrap_or(LineEnding::Lf).bytes());
+        result.fixed_rules.push(PolicyRule::FinalNewline);
+    }
+    result
+}
+
+/// Non-ignored files under `root`, with their paths relative to it
+fn policy_files(root: &Path) -> Vec<(String, PathBuf)> {
+    WalkBuilder::new(root)
+        .hidden(false)
+        .filter_entry(|e| e.file_name() != ".git")
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+            Some((relative, e.into_path()))
+        })
+        .collect()
+}
+
+/// Contents of a file the policy applies to, or None for binary, generated, oversized and
+/// unreadable ones
+fn read_checked(repo: Option<&git2::Repository>, path: &Path, policy: &TextPolicy) -> Option<Vec<u8>> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    if policy.skip_generated && is_generated(repo, path) {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    // UTF-16 looks binary to the NUL heuristic but is still text the encoding rule reports
+    let utf16 = bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]);
+    if !utf16 && is_binary_content(&bytes[..bytes.len().min(8192)]) {
+        return None;
+    }
+    Some(bytes)
+}
+
+/// Whether .gitattributes in the enclosing repository marks `path` generated
+#[cfg(feature = "git")]
+fn is_generated(repo: Option<&git2::Repository>, path: &Path) -> bool {
+    let in_repo = repo.and_then(|repo| Some((repo, path.strip_prefix(repo.workdir()?).ok()?)));
+    in_repo.is_some_and(|(repo, relative)| crate::git::diff_attributes(repo, relative).is_generated)
+}
+
+/// Attributes are only read by the git module, so builds without it treat nothing as generated
+#[cfg(not(feature = "git"))]
+fn is_generated(_repo: Option<&git2::Repository>, _path: &Path) -> bool {
+    false
+}
+
+/// Check every file in parallel, handing each file's result (None when skipped) to `visit`
+fn scan<F, V>(root: &Path, policy: &TextPolicy, cancel: &CancelToken, on_progress: &F, visit: V) -> Result<()>
+where
+    F: Fn(PolicyProgress) + Sync,
+    V: Fn(&Path, Option<FileCheck>) + Sync,
+{
+    let exceptions = Exceptions::new(policy)?;
+    let files = policy_files(root);
+    let files_total = files.len();
+    let done = AtomicUsize::new(0);
+    let progress = |files_done: usize, stage: &str| on_progress(PolicyProgress {
+        root: display_path(root),
+        files_done,
+        files_total,
+        stage: stage.to_string(),
+    });
+
+    progress(0, "checking");
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(8);
+    let chunk = files.len().div_ceil(workers).max(1);
+    std::thread::scope(|scope| {
+        for part in files.chunks(chunk) {
+            let (exceptions, done, visit, progress) = (&exceptions, &done, &visit, &progress);
+            scope.spawn(move || {
+                // Repositories can't be shared across threads, so each worker opens its own
+                let repo = git2::Repository::discover(root).ok();
+                for (relative, path) in part {
+                    if cancel.is_cancelled() {
+                        return;
+                    }
+                    visit(path, read_checked(repo.as_ref(), path, policy).map(|bytes| check(relative, bytes, policy, exceptions)));
+                    let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if finished % PROGRESS_EVERY == 0 {
+                        progress(finished, "checking");
+                    }
+                }
+            });
+        }
+    });
+    if cancel.is_cancelled() {
+        return Err(anyhow!("Policy check cancelled"));
+    }
+    progress(files_total, "done");
+    Ok(())
+}
+
+/// Check the non-ignored text files under `root` against `policy`
+pub fn check_text_policy<F>(root: &Path, policy: &TextPolicy, cancel: &CancelToken, on_progress: F) -> Result<PolicyReport>
+where
+    F: Fn(PolicyProgress) + Sync,
+{
+    let report = Mutex::new(PolicyReport {
+        root: display_path(root),
+        files_checked: 0,
+        files_skipped: 0,
+        violations: BTreeMap::new(),
+        omitted: 0,
+    });
+    scan(root, policy, cancel, &on_progress, |_, checked| {
+        let mut report = report.lock().unwrap();
+        let Some(checked) = checked else {
+            report.files_skipped += 1;
+            return;
+        };
+        report.files_checked += 1;
+        report.omitted += checked.omitted;
+        for (rule, violation) in checked.found {
+            report.violations.entry(rule).or_default().push(violation);
+        }
+    })?;
+    let mut report = report.into_inner().unwrap();
+    for violations in report.violations.values_mut() {
+        violations.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    }
+    Ok(report)
+}
+
+/// Work out the mechanical fixes for every file without writing anything
+pub fn plan_fixes<F>(root: &Path, policy: &TextPolicy, cancel: &CancelToken, on_progress: F) -> Result<Vec<PlannedFix>>
+where
+    F: Fn(PolicyProgress) + Sync,
+{
+    let planned = Mutex::new(Vec::new());
+    scan(root, policy, cancel, &on_progress, |path, checked| {
+        if let Some(checked) = checked.filter(|checked| checked.fixed != checked.original) {
+            planned.lock().unwrap().push(PlannedFix {
+                path: path.to_path_buf(),
+                original: checked.original,
+                fixed: checked.fixed,
+                rules: checked.fixed_rules,
+            });
+        }
+    })?;
+    let mut planned = planned.into_inner().unwrap();
+    planned.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(planned)
+}
+
+/// Write the planned fixes as one changeset through the atomic write path. If any write
+/// fails, the files already written get their original contents back.
+pub fn apply_fixes(root: &Path, planned: &[PlannedFix], dry_run: bool) -> PolicyFixReport {
+    let mut report = PolicyFixReport {
+        root: display_path(root),
+        dry_run,
+        fixes: planned.iter().map(|fix| PolicyFix { path: display_path(&fix.path), rules: fix.rules.clone() }).collect(),
+        rolled_back: false,
+        error: None,
+    };
+    if dry_run {
+        return report;
+    }
+    for (index, fix) in planned.iter().enumerate() {
+        if let Err(e) = write_atomic(&fix.path, &fix.fixed) {
+            report.error = Some(format!("Failed to write {}: {}", display_path(&fix.path), e));
+            for written in &planned[..index] {
+                if let Err(e) = write_atomic(&written.path, &written.original) {
+                    log::error!("Failed to restore {} after a failed policy fix: {}", written.path.display(), e);
+                }
+            }
+            report.rolled_back = true;
+            break;
+        }
+    }
+    report
+}
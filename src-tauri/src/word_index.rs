@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{display_path, is_binary_content};
+use crate::gitignore::IgnoreMatcher;
+
+/// Limits for the per-project word index behind buffer-less completion
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WordIndexOptions {
+    /// Also index the parts of camelCase and snake_case identifiers
+    pub split_identifiers: bool,
+    pub min_word_length: usize,
+    /// Files larger than this are skipped
+    pub max_file_bytes: u64,
+    /// Tokens read from one file; the rest of the file is ignored
+    pub max_tokens_per_file: usize,
+    /// Distinct words across the project; new words are dropped once it is reached
+    pub max_distinct_words: usize,
+}
+
+impl Default for WordIndexOptions {
+    fn default() -> Self {
+        Self {
+            split_identifiers: true,
+            min_word_length: 3,
+            max_file_bytes: 1024 * 1024,
+            max_tokens_per_file: 20_000,
+            max_distinct_words: 200_000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordCandidate {
+    pub word: String,
+    pub frequency: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordIndexStats {
+    pub root: String,
+    pub files: usize,
+    pub distinct_words: usize,
+    pub total_occurrences: u64,
+    /// Files that hit `max_tokens_per_file`
+    pub truncated_files: usize,
+    /// Whether `max_distinct_words` has dropped any words
+    pub budget_exhausted: bool,
+}
+
+struct FileWords {
+    modified: Option<SystemTime>,
+    counts: HashMap<String, u32>,
+    truncated: bool,
+}
+
+struct RootWords {
+    options: WordIndexOptions,
+    ignore: Option<IgnoreMatcher>,
+    files: HashMap<PathBuf, FileWords>,
+    totals: HashMap<String, u32>,
+    budget_exhausted: bool,
+}
+
+/// Identifier frequencies per project, built on demand and kept current from watcher events
+#[derive(Default)]
+pub struct WordIndex(Mutex<HashMap<PathBuf, RootWords>>);
+
+/// Split `fooBarBaz`, `foo_bar` and `HTTPServer` into their words
+fn identifier_parts(identifier: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    for piece in identifier.split('_').filter(|p| !p.is_empty()) {
+        let chars: Vec<(usize, char)> = piece.char_indices().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (at, c) = chars[i];
+            let previous = chars[i - 1].1;
+            let next_lower = chars.get(i + 1).map(|(_, n)| n.is_lowercase()).unwrap_or(false);
+            // A boundary before an upper-case letter that follows a lower-case one or starts a
+            // new word after an acronym
+            if c.is_uppercase() && (previous.is_lowercase() || previous.is_ascii_digit() || (previous.is_uppercase() && next_lower)) {
+                parts.push(&piece[start..at]);
+                start = at;
+            }
+        }
+        parts.push(&piece[start..]);
+    }
+    parts
+}
+
+/// Count identifiers in `text`, stopping after `max_tokens_per_file`. Returns the counts and
+/// whether the file was cut short.
+fn tokenize(text: &str, options: &WordIndexOptions) -> (HashMap<String, u32>, bool) {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    let mut tokens = 0;
+    let add = |word: &str, counts: &mut HashMap<String, u32>| {
+        if word.chars().count() >= options.min_word_length && !word.chars().all(|c| c.is_ascii_digit()) {
+            *counts.entry(word.to_string()).or_default() += 1;
+        }
+    };
+
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_alphabetic() || c == '_') {
+        let candidate = &rest[start..];
+        let end = candidate.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(candidate.len());
+        let identifier = &candidate[..end];
+        rest = &candidate[end..];
+
+        if tokens >= options.max_tokens_per_file {
+            return (counts, true);
+        }
+        tokens += 1;
+        add(identifier, &mut counts);
+        if options.split_identifiers {
+            let parts = identifier_parts(identifier);
+            if parts.len() > 1 {
+                for part in parts {
+                    add(part, &mut counts);
+                }
+            }
+        }
+    }
+    (counts, false)
+}
+
+fn read_words(path: &Path, options: &WordIndexOptions) -> Option<FileWords> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > options.max_file_bytes {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if is_binary_content(&bytes) {
+        return None;
+    }
+    let (counts, truncated) = tokenize(&String::from_utf8_lossy(&bytes), options);
+    Some(FileWords { modified: metadata.modified().ok(), counts, truncated })
+}
+
+impl RootWords {
+    fn remove_file(&mut self, path: &Path) {
+        let Some(old) = self.files.remove(path) else { return };
+        for (word, count) in old.counts {
+            if let Some(total) = self.totals.get_mut(&word) {
+                *total = total.saturating_sub(count);
+                if *total == 0 {
+                    self.totals.remove(&word);
+                }
+            }
+        }
+    }
+
+    fn add_file(&mut self, path: &Path, mut words: FileWords) {
+        let budget = self.options.max_distinct_words;
+        words.counts.retain(|word, count| {
+            if let Some(total) = self.totals.get_mut(word) {
+                *total += *count;
+                true
+            } else if self.totals.len() < budget {
+                self.totals.insert(word.clone(), *count);
+                true
+            } else {
+                self.budget_exhausted = true;
+                false
+            }
+        });
+        self.files.insert(path.to_path_buf(), words);
+    }
+
+    fn stats(&self, root: &Path) -> WordIndexStats {
+        WordIndexStats {
+            root: display_path(root),
+            files: self.files.len(),
+            distinct_words: self.totals.len(),
+            total_occurrences: self.totals.values().map(|&c| c as u64).sum(),
+            truncated_files: self.files.values().filter(|f| f.truncated).count(),
+            budget_exhausted: self.budget_exhausted,
+        }
+    }
+}
+
+impl WordIndex {
+    /// (Re)build the index for `root` from every non-ignored text file
+    pub fn build(&self, root: &Path, options: WordIndexOptions) -> WordIndexStats {
+        self.build_until(root, options, || false).expect("an uninterrupted build completes")
+    }
+
+    /// `build`, abandoned between files once `stop` returns true; None when it was, and the
+    /// previous index for `root` (if any) stays in place
+    pub fn build_until(&self, root: &Path, options: WordIndexOptions, stop: impl Fn() -> bool) -> Option<WordIndexStats> {
+        let mut words = RootWords {
+            ignore: IgnoreMatcher::new(root).ok(),
+            options,
+            files: HashMap::new(),
+            totals: HashMap::new(),
+            budget_exhausted: false,
+        };
+        let walker = WalkBuilder::new(root).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+        for entry in walker.filter_map(|e| e.ok()) {
+            if stop() {
+                return None;
+            }
+            if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+                continue;
+            }
+            if let Some(file) = read_words(entry.path(), &words.options) {
+                words.add_file(entry.path(), file);
+            }
+        }
+        let stats = words.stats(root);
+        self.0.lock().unwrap().insert(root.to_path_buf(), words);
+        Some(stats)
+    }
+
+    pub fn is_built(&self, root: &Path) -> bool {
+        self.0.lock().unwrap().contains_key(root)
+    }
+
+    /// Re-read `path` in every built index that covers it; deleted files drop out
+    pub fn update_file(&self, path: &Path) {
+        if path.components().any(|c| c.as_os_str() == ".git") {
+            return;
+        }
+        let mut roots = self.0.lock().unwrap();
+        for words in roots.iter_mut().filter(|(root, _)| path.starts_with(root)).map(|(_, words)| words) {
+            let ignored = words.ignore.as_ref().map(|m| m.is_ignored(path, false)).unwrap_or(false);
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            if let Some(existing) = words.files.get(path) {
+                if modified.is_some() && existing.modified == modified {
+                    continue;
+                }
+            }
+            words.remove_file(path);
+            if ignored {
+                continue;
+            }
+            if let Some(file) = read_words(path, &words.options) {
+                words.add_file(path, file);
+            }
+        }
+    }
+
+    /// Re-key indexed files below a renamed directory; word counts are unchanged
+    pub fn remap(&self, from: &Path, to: &Path) {
+        let mut roots = self.0.lock().unwrap();
+        for words in roots.iter_mut().filter(|(root, _)| from.starts_with(root)).map(|(_, words)| words) {
+            let moved: Vec<(PathBuf, PathBuf)> = words
+                .files
+                .keys()
+                .filter_map(|path| Some((path.clone(), crate::fs::remap_prefix(path, from, to)?)))
+                .collect();
+            for (old, new) in moved {
+                if let Some(file) = words.files.remove(&old) {
+                    words.files.insert(new, file);
+                }
+            }
+        }
+    }
+
+    pub fn remove_root(&self, root: &Path) {
+        self.0.lock().unwrap().remove(root);
+    }
+
+    /// Words starting with `prefix` (case-insensitive), most frequent first; None if `root`
+    /// has not been built
+    pub fn query(&self, root: &Path, prefix: &str, limit: usize) -> Option<Vec<WordCandidate>> {
+        let roots = self.0.lock().unwrap();
+        let words = roots.get(root)?;
+        let prefix_lower = prefix.to_lowercase();
+        let mut candidates: Vec<WordCandidate> = words
+            .totals
+            .iter()
+            .filter(|(word, _)| word.as_str() != prefix && word.to_lowercase().starts_with(&prefix_lower))
+            .map(|(word, &frequency)| WordCandidate { word: word.clone(), frequency })
+            .collect();
+        // Exact-case prefix matches first, then by frequency
+        candidates.sort_by(|a, b| {
+            b.word.starts_with(prefix).cmp(&a.word.starts_with(prefix))
+                .then_with(|| b.frequency.cmp(&a.frequency))
+                .then_with(|| a.word.len().cmp(&b.word.len()))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        candidates.truncate(limit);
+        Some(candidates)
+    }
+
+    pub fn stats(&self, root: &Path) -> Option<WordIndexStats> {
+        self.0.lock().unwrap().get(root).map(|words| words.stats(root))
+    }
+}
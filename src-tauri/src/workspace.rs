@@ -0,0 +1,711 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(feature = "git")]
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+
+use crate::fs::{display_path, DirectoryListing, FileWatcher, WatchLimitReached, WatchMode, WatcherStats};
+#[cfg(feature = "git")]
+use crate::git::{self, GitManager, GitStatus};
+use crate::gitignore::IgnoreMatcher;
+use crate::manifest::ProjectManifest;
+use crate::paths::WsPath;
+
+/// Per-workspace bundle; dropping it stops the watcher thread and releases its handles
+pub struct Workspace {
+    pub root: PathBuf,
+    windows: HashSet<String>,
+    watcher: Option<FileWatcher>,
+    exclusions: Vec<String>,
+    respect_gitignore: bool,
+    watch_mode: WatchMode,
+    /// When the volume holding the root went away; the watcher is dropped until it returns
+    disconnected_since: Option<i64>,
+    /// Repository handle shared by the workspace's git commands; None outside a repository
+    #[cfg(feature = "git")]
+    git: Option<Arc<Mutex<GitManager>>>,
+    /// Last git status of the root, dropped on every change the watcher reports
+    #[cfg(feature = "git")]
+    status: Option<GitStatus>,
+    /// Unsaved editor contents by file, shared by every window on the workspace
+    drafts: HashMap<PathBuf, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceInfo {
+    pub root: String,
+    pub windows: Vec<String>,
+    pub is_git_repo: bool,
+    pub watched_directories: usize,
+    pub disconnected: bool,
+    pub watch_mode: WatchMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceDisconnected {
+    pub root: String,
+    pub reason: String,
+}
+
+/// Sent after a directory is renamed or moved, so open tabs can be retargeted in place
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PathsRemapped {
+    pub from: WsPath,
+    pub to: WsPath,
+    /// Open files that were below `from`, as old and new path pairs
+    pub open_files: Vec<(WsPath, WsPath)>,
+}
+
+/// Sent once a disconnected workspace's volume is back and its services have restarted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceReconnected {
+    pub root: String,
+    pub away_secs: i64,
+    /// Fresh status from a rescan, so views can reconcile everything that changed meanwhile
+    #[cfg(feature = "git")]
+    pub status: Option<GitStatus>,
+    /// What happened to the repository while away (branch switch, new commits, ...)
+    #[cfg(feature = "git")]
+    pub repository: Option<crate::repo_events::RepoExternalChange>,
+}
+
+/// What the frontend needs to show a freshly opened project. Only the cheap parts are here;
+/// the watcher, git status, rollups, manifests and symbol index follow as `workspace-bootstrap` events.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectBootstrap {
+    pub root: String,
+    /// Working tree of the enclosing repository, which may be above the project root
+    pub repository_root: Option<String>,
+    /// None outside a repository or on a detached HEAD
+    pub branch: Option<String>,
+    pub listing: DirectoryListing,
+    pub read_only: bool,
+}
+
+/// One background piece of a project bootstrap, sent when it completes
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum BootstrapPayload {
+    Watcher { workspace: WorkspaceInfo },
+    #[cfg(feature = "git")]
+    GitStatus { status: Option<Box<GitStatus>> },
+    #[cfg(feature = "git")]
+    StatusRollup { rollup: BTreeMap<String, git::DirectoryStatusCounts> },
+    Manifests { manifests: Vec<ProjectManifest> },
+    IndexReady { indexed: usize },
+    /// The phase failed or ran past its timeout; the frontend can fall back to the on-demand command
+    Failed { failed_phase: String, message: String },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BootstrapEvent {
+    pub root: String,
+    #[serde(flatten)]
+    pub payload: BootstrapPayload,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub duration_ms: u64,
+    /// "ok", "error" or "timeout"
+    pub outcome: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BootstrapTimings {
+    pub root: String,
+    pub started_at: String,
+    /// Time until `open_project` returned
+    pub critical_ms: Option<u64>,
+    pub phases: Vec<PhaseTiming>,
+}
+
+/// Per-phase timings of the most recent bootstrap of each project
+#[derive(Default)]
+pub struct BootstrapTimingsState(Mutex<HashMap<PathBuf, BootstrapTimings>>);
+
+impl BootstrapTimingsState {
+    pub fn start(&self, root: &Path) {
+        let timings = BootstrapTimings {
+            root: display_path(root),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            critical_ms: None,
+            phases: Vec::new(),
+        };
+        self.0.lock().unwrap().insert(root.to_path_buf(), timings);
+    }
+
+    /// Ignored for projects that were not opened through `open_project`
+    pub fn record(&self, root: &Path, timing: PhaseTiming) {
+        if let Some(timings) = self.0.lock().unwrap().get_mut(root) {
+            timings.phases.push(timing);
+        }
+    }
+
+    pub fn record_critical(&self, root: &Path, duration_ms: u64) {
+        if let Some(timings) = self.0.lock().unwrap().get_mut(root) {
+            timings.critical_ms = Some(duration_ms);
+        }
+    }
+
+    pub fn get(&self, root: &Path) -> Option<BootstrapTimings> {
+        self.0.lock().unwrap().get(root).cloned()
+    }
+}
+
+/// Open workspaces keyed by canonical project root
+#[derive(Default)]
+pub struct WorkspaceRegistry(Mutex<HashMap<PathBuf, Workspace>>);
+
+/// IO errors meaning the volume itself is gone (unplugged drive, dropped network share)
+/// rather than a problem with one file
+pub fn is_unavailable_error(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    const UNAVAILABLE: &[i32] = &[
+        5,   // EIO
+        6,   // ENXIO
+        19,  // ENODEV
+        107, // ENOTCONN
+        112, // EHOSTDOWN
+        116, // ESTALE
+    ];
+    #[cfg(windows)]
+    const UNAVAILABLE: &[i32] = &[
+        21, // ERROR_NOT_READY
+        53, // ERROR_BAD_NETPATH
+        55, // ERROR_DEV_NOT_EXIST
+        64, // ERROR_NETNAME_DELETED
+        67, // ERROR_BAD_NET_NAME
+    ];
+    #[cfg(not(any(unix, windows)))]
+    const UNAVAILABLE: &[i32] = &[];
+    e.raw_os_error().map(|code| UNAVAILABLE.contains(&code)).unwrap_or(false)
+}
+
+/// Whether `root` can still be listed. Blocks as long as the filesystem does, which on a
+/// dead network share can be a while, so call it off the main threads with a timeout.
+pub fn probe_root(root: &Path) -> bool {
+    std::fs::read_dir(root).is_ok()
+}
+
+/// Canonicalize a project root so two spellings of the same folder share one workspace
+pub fn canonical_root(path: &Path) -> Result<PathBuf> {
+    let canonical = std::fs::canonicalize(path)?;
+    if !canonical.is_dir() {
+        return Err(anyhow!("'{}' is not a directory", path.display()));
+    }
+    // Strip the Windows extended-length prefix so keys match frontend paths
+    Ok(PathBuf::from(display_path(&canonical)))
+}
+
+/// Key that per-project settings are looked up by, so a raw-path token or another spelling of
+/// the same folder finds the same entries. Falls back to the path as given once it is gone.
+pub fn project_key(project_path: &str) -> String {
+    let path = crate::fs::decode_path(project_path);
+    display_path(&canonical_root(&path).unwrap_or(path))
+}
+
+/// Canonical root of a folder the user asked to open, with a readable error when it cannot be
+pub fn validate_project_root(path: &Path) -> Result<PathBuf> {
+    if !path.exists() {
+        return Err(anyhow!("'{}' does not exist", path.display()));
+    }
+    let root = canonical_root(path)?;
+    std::fs::read_dir(&root).map_err(|e| anyhow!("'{}' is not readable: {}", path.display(), e))?;
+    Ok(root)
+}
+
+impl Workspace {
+    fn info(&self) -> WorkspaceInfo {
+        let mut windows: Vec<String> = self.windows.iter().cloned().collect();
+        windows.sort();
+        #[cfg(feature = "git")]
+        let is_git_repo = self.disconnected_since.is_none() && git::is_git_repository(&self.root);
+        #[cfg(not(feature = "git"))]
+        let is_git_repo = false;
+        WorkspaceInfo {
+            root: display_path(&self.root),
+            windows,
+            is_git_repo,
+            watched_directories: self.watcher.as_ref().map(|w| w.stats().total_watched_directories).unwrap_or(0),
+            disconnected: self.disconnected_since.is_some(),
+            watch_mode: self.watch_mode,
+        }
+    }
+}
+
+impl WorkspaceRegistry {
+    /// Register `window` against the workspace at `root`, creating the bundle on first open.
+    /// `make_watcher` is only called for a new workspace, which starts with native watching.
+    pub fn open<F>(&self, root: &Path, window: &str, exclusions: &[String], respect_gitignore: bool, make_watcher: F) -> Result<(WorkspaceInfo, Option<WatchLimitReached>)>
+    where
+        F: FnOnce(&Path, WatchMode) -> Result<FileWatcher>,
+    {
+        // Build the bundle outside the lock: the watcher and the ignore rules both walk the
+        // whole tree, and every command path check waits on this lock
+        let mut created = None;
+        if !self.0.lock().unwrap().contains_key(root) {
+            let mut watcher = make_watcher(root, WatchMode::Native)?;
+            let ignore = if respect_gitignore { IgnoreMatcher::new(root).ok() } else { None };
+            let limit = watcher.watch_filtered(root, exclusions, ignore)?;
+            #[cfg(feature = "git")]
+            let git = GitManager::new(root);
+            let workspace = Workspace {
+                root: root.to_path_buf(),
+                windows: HashSet::new(),
+                watcher: Some(watcher),
+                exclusions: exclusions.to_vec(),
+                respect_gitignore,
+                watch_mode: WatchMode::Native,
+                disconnected_since: None,
+                #[cfg(feature = "git")]
+                git: git.is_git_repo().then(|| Arc::new(Mutex::new(git))),
+                #[cfg(feature = "git")]
+                status: None,
+                drafts: HashMap::new(),
+            };
+            created = Some((workspace, limit));
+        }
+
+        let mut workspaces = self.0.lock().unwrap();
+        let mut limit = None;
+        // A window opening the same root meanwhile already registered its bundle; keep that one
+        if let Some((workspace, created_limit)) = created {
+            if !workspaces.contains_key(root) {
+                workspaces.insert(root.to_path_buf(), workspace);
+                limit = created_limit;
+            }
+        }
+        let workspace = workspaces.get_mut(root).ok_or_else(|| anyhow!("Workspace disappeared while opening"))?;
+        workspace.windows.insert(window.to_string());
+        Ok((workspace.info(), limit))
+    }
+
+    /// Detach `window`; the bundle is torn down when no windows remain. Returns true if it was released.
+    pub fn close(&self, root: &Path, window: &str) -> bool {
+        let mut workspaces = self.0.lock().unwrap();
+        let empty = match workspaces.get_mut(root) {
+            Some(workspace) => {
+                workspace.windows.remove(window);
+                workspace.windows.is_empty()
+            }
+            None => return false,
+        };
+        if empty {
+            workspaces.remove(root);
+        }
+        empty
+    }
+
+    /// Detach a window from every workspace, e.g. when it is destroyed. Returns the roots whose
+    /// bundles were torn down because no windows remain.
+    pub fn release_window(&self, window: &str) -> Vec<PathBuf> {
+        let mut workspaces = self.0.lock().unwrap();
+        for workspace in workspaces.values_mut() {
+            workspace.windows.remove(window);
+        }
+        let released: Vec<PathBuf> = workspaces.iter().filter(|(_, w)| w.windows.is_empty()).map(|(root, _)| root.clone()).collect();
+        for root in &released {
+            workspaces.remove(root);
+        }
+        released
+    }
+
+    pub fn list(&self) -> Vec<WorkspaceInfo> {
+        self.0.lock().unwrap().values().map(|w| w.info()).collect()
+    }
+
+    /// Find the open workspace containing `path`, if any
+    pub fn root_for(&self, path: &Path) -> Option<PathBuf> {
+        self.0
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_os_str().len())
+            .cloned()
+    }
+
+    /// Start watching a directory created inside a workspace with filtered watching
+    pub fn add_watched_directory(&self, root: &Path, dir: &Path) {
+        if let Some(watcher) = self.0.lock().unwrap().get_mut(root).and_then(|w| w.watcher.as_mut()) {
+            let _ = watcher.add_directory(dir);
+        }
+    }
+
+    /// Move watches and drafts below a renamed directory inside an open workspace
+    pub fn remap_watched(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut workspaces = self.0.lock().unwrap();
+        for workspace in workspaces.values_mut() {
+            let moved: Vec<(PathBuf, PathBuf)> =
+                workspace.drafts.keys().filter_map(|path| Some((path.clone(), crate::fs::remap_prefix(path, from, to)?))).collect();
+            for (old, new) in moved {
+                if let Some(content) = workspace.drafts.remove(&old) {
+                    workspace.drafts.insert(new, content);
+                }
+            }
+        }
+        for watcher in workspaces.values_mut().filter(|w| from.starts_with(&w.root)).filter_map(|w| w.watcher.as_mut()) {
+            watcher.remap(from, to)?;
+        }
+        Ok(())
+    }
+
+    /// Mark the workspace at `root` disconnected and stop its watcher. Returns false if it was
+    /// already disconnected or is not open.
+    pub fn disconnect(&self, root: &Path) -> bool {
+        let mut workspaces = self.0.lock().unwrap();
+        let Some(workspace) = workspaces.get_mut(root) else { return false };
+        if workspace.disconnected_since.is_some() {
+            return false;
+        }
+        workspace.disconnected_since = Some(chrono::Utc::now().timestamp());
+        workspace.watcher = None;
+        true
+    }
+
+    /// Restart the watcher of a disconnected workspace; returns when it was disconnected
+    pub fn reconnect<F>(&self, root: &Path, make_watcher: F) -> Result<Option<i64>>
+    where
+        F: FnOnce(&Path, WatchMode) -> Result<FileWatcher>,
+    {
+        let (exclusions, respect_gitignore, mode) = match self.0.lock().unwrap().get(root) {
+            Some(workspace) if workspace.disconnected_since.is_some() => (workspace.exclusions.clone(), workspace.respect_gitignore, workspace.watch_mode),
+            _ => return Ok(None),
+        };
+        // Build the watcher outside the lock; walking the tree can take a while
+        let mut watcher = make_watcher(root, mode)?;
+        let ignore = if respect_gitignore { IgnoreMatcher::new(root).ok() } else { None };
+        watcher.watch_filtered(root, &exclusions, ignore)?;
+
+        let mut workspaces = self.0.lock().unwrap();
+        let Some(workspace) = workspaces.get_mut(root) else { return Ok(None) };
+        workspace.watcher = Some(watcher);
+        Ok(workspace.disconnected_since.take())
+    }
+
+    /// Replace the watcher of the workspace at `root` with one of the given mode. Returns false
+    /// when it already used that mode, is disconnected or is not open.
+    pub fn set_watch_mode<F>(&self, root: &Path, mode: WatchMode, make_watcher: F) -> Result<(bool, Option<WatchLimitReached>)>
+    where
+        F: FnOnce(&Path, WatchMode) -> Result<FileWatcher>,
+    {
+        let (exclusions, respect_gitignore) = match self.0.lock().unwrap().get(root) {
+            Some(workspace) if workspace.disconnected_since.is_none() && workspace.watch_mode != mode => (workspace.exclusions.clone(), workspace.respect_gitignore),
+            _ => return Ok((false, None)),
+        };
+        let mut watcher = make_watcher(root, mode)?;
+        let ignore = if respect_gitignore { IgnoreMatcher::new(root).ok() } else { None };
+        let limit = watcher.watch_filtered(root, &exclusions, ignore)?;
+
+        let mut workspaces = self.0.lock().unwrap();
+        let Some(workspace) = workspaces.get_mut(root) else { return Ok((false, None)) };
+        workspace.watcher = Some(watcher);
+        workspace.watch_mode = mode;
+        Ok((true, limit))
+    }
+
+    /// Open roots with whether each is currently disconnected
+    pub fn availability(&self) -> Vec<(PathBuf, bool)> {
+        self.0.lock().unwrap().iter().map(|(root, w)| (root.clone(), w.disconnected_since.is_some())).collect()
+    }
+
+    /// The disconnected workspace containing `path`, if any. Compares paths as given, since
+    /// resolving them would touch the missing volume.
+    pub fn disconnected_root_for(&self, path: &Path) -> Option<PathBuf> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(root, w)| w.disconnected_since.is_some() && path.starts_with(root))
+            .map(|(root, _)| root.clone())
+    }
+
+    /// Repository handle of the workspace rooted at `root`
+    #[cfg(feature = "git")]
+    pub fn git_manager(&self, root: &Path) -> Option<Arc<Mutex<GitManager>>> {
+        self.0.lock().unwrap().get(root).and_then(|w| w.git.clone())
+    }
+
+    #[cfg(feature = "git")]
+    pub fn cached_status(&self, root: &Path) -> Option<GitStatus> {
+        self.0.lock().unwrap().get(root).and_then(|w| w.status.clone())
+    }
+
+    /// Remember `status` for the workspace rooted at `root`; ignored when it is not open
+    #[cfg(feature = "git")]
+    pub fn store_status(&self, root: &Path, status: &GitStatus) {
+        if let Some(workspace) = self.0.lock().unwrap().get_mut(root) {
+            workspace.status = Some(status.clone());
+        }
+    }
+
+    /// Forget the cached status of every workspace containing `path`
+    #[cfg(feature = "git")]
+    pub fn invalidate_status(&self, path: &Path) {
+        for workspace in self.0.lock().unwrap().values_mut().filter(|w| path.starts_with(&w.root)) {
+            workspace.status = None;
+        }
+    }
+
+    /// Keep unsaved contents of `path` in the innermost workspace containing it. Returns false
+    /// when no open workspace does.
+    pub fn set_draft(&self, path: &Path, content: String) -> bool {
+        let mut workspaces = self.0.lock().unwrap();
+        let Some(workspace) = workspaces.values_mut().filter(|w| path.starts_with(&w.root)).max_by_key(|w| w.root.as_os_str().len()) else {
+            return false;
+        };
+        workspace.drafts.insert(path.to_path_buf(), content);
+        true
+    }
+
+    /// Returns true when `path` had a draft
+    pub fn discard_draft(&self, path: &Path) -> bool {
+        self.0.lock().unwrap().values_mut().filter_map(|w| w.drafts.remove(path)).count() > 0
+    }
+
+    /// Drafts of the workspace rooted at `root`, by display path
+    pub fn drafts(&self, root: &Path) -> BTreeMap<String, String> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(root)
+            .map(|w| w.drafts.iter().map(|(path, content)| (display_path(path), content.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn watcher_stats(&self) -> WatcherStats {
+        let workspaces = self.0.lock().unwrap();
+        let mut stats = WatcherStats { roots: Vec::new(), total_watched_directories: 0, limit_reached: false };
+        for watcher in workspaces.values().filter_map(|w| w.watcher.as_ref()) {
+            let s = watcher.stats();
+            stats.total_watched_directories += s.total_watched_directories;
+            stats.limit_reached |= s.limit_reached;
+            stats.roots.extend(s.roots);
+        }
+        stats
+    }
+}
+
+// Written by the watch self-test; inside .git when there is one, so it never shows as a change
+const WATCH_PROBE_FILE: &str = ".agentic-ide-watch-probe";
+
+/// Watch self-test probes, keyed by workspace root with whether the watcher reported the write
+#[derive(Default)]
+pub struct WatchProbes(Mutex<HashMap<PathBuf, (PathBuf, bool)>>);
+
+impl WatchProbes {
+    /// Write the probe file for `root`; its events are swallowed by `observe` from now on
+    pub fn begin(&self, root: &Path) -> Result<()> {
+        let dir = if root.join(".git").is_dir() { root.join(".git") } else { root.to_path_buf() };
+        let probe = dir.join(WATCH_PROBE_FILE);
+        self.0.lock().unwrap().insert(root.to_path_buf(), (probe.clone(), false));
+        std::fs::write(&probe, chrono::Utc::now().timestamp_millis().to_string())?;
+        Ok(())
+    }
+
+    /// Record probe events for `root`; true when the event touched nothing but the probe
+    pub fn observe(&self, root: &Path, paths: &[PathBuf]) -> bool {
+        let mut probes = self.0.lock().unwrap();
+        let Some((probe, seen)) = probes.get_mut(root) else { return false };
+        if !paths.iter().any(|p| p == probe) {
+            return false;
+        }
+        *seen = true;
+        paths.iter().all(|p| p == probe)
+    }
+
+    /// Remove the probe file and report whether the watcher saw it being written. The entry
+    /// stays, so the removal is swallowed as well.
+    pub fn finish(&self, root: &Path) -> bool {
+        let probes = self.0.lock().unwrap();
+        let Some((probe, seen)) = probes.get(root) else { return false };
+        let _ = std::fs::remove_file(probe);
+        *seen
+    }
+}
+
+/// Files the frontend has open in editors, keyed by path with the windows holding them
+#[derive(Default)]
+pub struct OpenFileRegistry(Mutex<HashMap<PathBuf, HashSet<String>>>);
+
+impl OpenFileRegistry {
+    pub fn mark_open(&self, path: &Path, window: &str) {
+        self.0.lock().unwrap().entry(path.to_path_buf()).or_default().insert(window.to_string());
+    }
+
+    pub fn mark_closed(&self, path: &Path, window: &str) {
+        let mut files = self.0.lock().unwrap();
+        if let Some(windows) = files.get_mut(path) {
+            windows.remove(window);
+            if windows.is_empty() {
+                files.remove(path);
+            }
+        }
+    }
+
+    pub fn release_window(&self, window: &str) {
+        let mut files = self.0.lock().unwrap();
+        for windows in files.values_mut() {
+            windows.remove(window);
+        }
+        files.retain(|_, w| !w.is_empty());
+    }
+
+    /// Re-key open files below a renamed directory; returns each old and new path
+    pub fn remap(&self, from: &Path, to: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let mut files = self.0.lock().unwrap();
+        let moved: Vec<(PathBuf, PathBuf)> = files
+            .keys()
+            .filter_map(|path| Some((path.clone(), crate::fs::remap_prefix(path, from, to)?)))
+            .collect();
+        for (old, new) in &moved {
+            if let Some(windows) = files.remove(old) {
+                files.insert(new.clone(), windows);
+            }
+        }
+        moved
+    }
+
+    /// Open files at or below `path`
+    pub fn open_under(&self, path: &Path) -> Vec<PathBuf> {
+        let mut open: Vec<PathBuf> = self.0.lock().unwrap().keys().filter(|p| p.starts_with(path)).cloned().collect();
+        open.sort();
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::{Duration, Instant};
+
+    /// A watcher factory reporting every event path tagged with the root it was made for
+    fn tagged_watcher(events: Sender<(PathBuf, PathBuf)>) -> impl FnOnce(&Path, WatchMode) -> Result<FileWatcher> {
+        move |root, _mode| {
+            let root = root.to_path_buf();
+            FileWatcher::new(
+                move |event: notify::Event| {
+                    for path in event.paths {
+                        let _ = events.send((root.clone(), path));
+                    }
+                },
+                |_| {},
+            )
+        }
+    }
+
+    #[test]
+    fn two_workspaces_keep_events_status_and_drafts_apart() {
+        let (_a_dir, a) = init_repo();
+        let (_b_dir, b) = init_repo();
+        let registry = WorkspaceRegistry::default();
+        let (tx, rx) = channel();
+        registry.open(&a, "main", &[], false, tagged_watcher(tx.clone())).unwrap();
+        registry.open(&b, "second", &[], false, tagged_watcher(tx)).unwrap();
+        assert_eq!(registry.list().len(), 2);
+
+        // Events from a write in `a` reach only `a`'s watcher
+        std::fs::write(a.join("only-a.txt"), "a").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut seen = Vec::new();
+        while Instant::now() < deadline && !seen.iter().any(|(_, path): &(PathBuf, PathBuf)| path.ends_with("only-a.txt")) {
+            if let Ok(event) = rx.recv_timeout(Duration::from_millis(100)) {
+                seen.push(event);
+            }
+        }
+        while let Ok(event) = rx.recv_timeout(Duration::from_millis(300)) {
+            seen.push(event);
+        }
+        assert!(seen.iter().any(|(root, path)| *root == a && path.ends_with("only-a.txt")), "{:?}", seen);
+        assert!(seen.iter().all(|(root, path)| *root == a && path.starts_with(&a)), "{:?}", seen);
+
+        // Each workspace has its own repository handle and status cache
+        #[cfg(feature = "git")]
+        {
+            let (git_a, git_b) = (registry.git_manager(&a).unwrap(), registry.git_manager(&b).unwrap());
+            assert!(!Arc::ptr_eq(&git_a, &git_b));
+            let status_a = git_a.lock().unwrap().get_status(&a).unwrap();
+            assert!(status_a.untracked.iter().any(|p| p.ends_with("only-a.txt")), "{:?}", status_a.untracked);
+            let status_b = git_b.lock().unwrap().get_status(&b).unwrap();
+            assert!(status_b.untracked.is_empty(), "{:?}", status_b.untracked);
+            registry.store_status(&a, &status_a);
+            registry.store_status(&b, &status_b);
+            registry.invalidate_status(&a.join("only-a.txt"));
+            assert!(registry.cached_status(&a).is_none());
+            assert!(registry.cached_status(&b).is_some());
+        }
+
+        // Drafts stay with the workspace holding the file
+        assert!(registry.set_draft(&a.join("only-a.txt"), "unsaved".to_string()));
+        assert!(!registry.set_draft(Path::new("/definitely/not/open.txt"), "x".to_string()));
+        assert_eq!(registry.drafts(&a).values().collect::<Vec<_>>(), vec!["unsaved"]);
+        assert!(registry.drafts(&b).is_empty());
+
+        // Closing the last window releases the bundle; other workspaces stay open
+        assert!(!registry.close(&a, "second"));
+        assert!(registry.close(&a, "main"));
+        #[cfg(feature = "git")]
+        assert!(registry.git_manager(&a).is_none());
+        assert!(registry.drafts(&a).is_empty());
+        assert_eq!(registry.list().len(), 1);
+        assert_eq!(registry.release_window("second"), [b.as_path()]);
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn project_key_is_shared_by_every_spelling_of_a_root() {
+        let (_dir, root) = init_repo();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        let key = display_path(&canonical_root(&root).unwrap());
+        assert_eq!(project_key(&display_path(&root)), key);
+        assert_eq!(project_key(&display_path(&root.join("sub").join(".."))), key);
+        let gone = root.join("missing");
+        assert_eq!(project_key(&display_path(&gone)), display_path(&gone));
+    }
+
+    #[test]
+    fn open_builds_the_watcher_without_holding_the_registry() {
+        let (_dir, root) = init_repo();
+        std::fs::write(root.join(".gitignore"), "ignored/\n").unwrap();
+        std::fs::create_dir_all(root.join("ignored/deep")).unwrap();
+        let registry = WorkspaceRegistry::default();
+        let (tx, _rx) = channel();
+        let make_watcher = tagged_watcher(tx);
+        // Would deadlock if `open` held the registry lock while building the watcher
+        registry
+            .open(&root, "main", &[], true, |root, mode| {
+                assert!(registry.list().is_empty());
+                assert!(registry.root_for(root).is_none());
+                make_watcher(root, mode)
+            })
+            .unwrap();
+        assert_eq!(registry.list().len(), 1);
+    }
+
+    #[test]
+    fn second_window_shares_the_workspace_bundle() {
+        let (_dir, root) = init_repo();
+        let registry = WorkspaceRegistry::default();
+        let (tx, _rx) = channel();
+        registry.open(&root, "main", &[], false, tagged_watcher(tx)).unwrap();
+        #[cfg(feature = "git")]
+        let git = registry.git_manager(&root).unwrap();
+        registry.set_draft(&root.join("file.txt"), "draft".to_string());
+
+        let (info, _) = registry.open(&root, "second", &[], false, |_, _| panic!("an open workspace needs no new watcher")).unwrap();
+        assert_eq!(info.windows, vec!["main", "second"]);
+        #[cfg(feature = "git")]
+        assert!(Arc::ptr_eq(&git, &registry.git_manager(&root).unwrap()));
+        assert_eq!(registry.drafts(&root).len(), 1);
+
+        assert!(!registry.close(&root, "main"));
+        assert!(registry.discard_draft(&root.join("file.txt")));
+        assert!(registry.close(&root, "second"));
+    }
+}
@@ -3,6 +3,7 @@ import {
   getGitStatus,
   stageFile,
   unstageFile,
+  restoreDeletedFiles,
   commitChanges,
   initGitRepo,
   gitPush,
@@ -15,6 +16,7 @@ interface GitStatus {
   modified: string[];
   untracked: string[];
   staged: string[];
+  deleted: string[];
   isGitRepo: boolean;
 }
 
@@ -75,6 +77,17 @@ const GitChanges: React.FC<GitChangesProps> = ({
     }
   };
 
+  const handleRestoreFile = async (filePath: string) => {
+    try {
+      await restoreDeletedFiles(currentProject, [filePath]);
+      await loadGitStatus();
+      onRefresh();
+    } catch (error: any) {
+      console.error('Failed to restore file:', error);
+      alert(error?.message || String(error));
+    }
+  };
+
   const handleCommit = async () => {
     if (!commitMessage.trim()) {
       alert('Please enter a commit message');
@@ -196,7 +209,7 @@ const GitChanges: React.FC<GitChangesProps> = ({
     );
   }
 
-  const totalChanges = gitStatus.staged.length + gitStatus.modified.length + gitStatus.untracked.length;
+  const totalChanges = gitStatus.staged.length + gitStatus.modified.length + gitStatus.deleted.length + gitStatus.untracked.length;
 
   return (
     <div className={compact ? "flex flex-col" : "h-full flex flex-col"}>
@@ -320,6 +333,31 @@ const GitChanges: React.FC<GitChangesProps> = ({
           </div>
         )}
 
+        {/* Deleted Files */}
+        {gitStatus.deleted.length > 0 && (
+          <div className="border-b border-border">
+            <div className="px-3 py-2 bg-red-50 dark:bg-red-900/20 border-b border-red-200 dark:border-red-800">
+              <h4 className="text-xs font-medium text-red-700 dark:text-red-300">
+                DELETED ({gitStatus.deleted.length})
+              </h4>
+            </div>
+            {gitStatus.deleted.map((file) => (
+              <div key={file} className="flex items-center px-3 py-2 hover:bg-accent/50 group">
+                <span className="mr-2">{getFileIcon(file)}</span>
+                <span className="flex-1 text-sm truncate line-through">{file}</span>
+                <span className="text-xs text-red-600 mr-2">D</span>
+                <button
+                  onClick={() => handleRestoreFile(file)}
+                  className="opacity-0 group-hover:opacity-100 p-1 rounded hover:bg-blue-500/20 hover:text-blue-600 text-xs"
+                  title="Restore from HEAD"
+                >
+                  ↺
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+
         {/* Untracked Changes */}
         {gitStatus.untracked.length > 0 && (
           <div>
@@ -54,7 +54,10 @@ const GitConfigDialog: React.FC<GitConfigDialogProps> = ({
       onClose();
     } catch (error) {
       console.error('Failed to set Git configuration:', error);
-      setError('Failed to set Git configuration. Please try again.');
+      const message = (error as { code?: string; message?: string })?.code === 'InvalidIdentity'
+        ? (error as { message: string }).message
+        : 'Failed to set Git configuration. Please try again.';
+      setError(message);
     } finally {
       setIsLoading(false);
     }
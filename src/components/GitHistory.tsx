@@ -4,6 +4,9 @@ import { getRecentCommits } from '../tauri-api';
 interface GitCommit {
   hash: string;
   message: string;
+  summary?: string;
+  body?: string;
+  trailers?: { key: string; value: string }[];
   author: string;
   timestamp: number;
   is_on_head?: boolean;
@@ -148,7 +151,7 @@ const GitHistory: React.FC<GitHistoryProps> = ({
                   <div className={`w-2 h-2 ${dotColor} rounded-full mt-1.5 flex-shrink-0`}></div>
                   <div className="flex-1 min-w-0">
                     <div className="text-sm font-medium text-foreground truncate">
-                      {truncateMessage(commit.message)}
+                      {truncateMessage(commit.summary ?? commit.message)}
                     </div>
                     <div className="flex items-center justify-between mt-1">
                       <div className="text-xs text-muted-foreground truncate">
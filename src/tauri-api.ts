@@ -1,5 +1,6 @@
 // Tauri API wrapper for Tauri v2
-import { invoke } from '@tauri-apps/api/core';
+import { invoke as tauriInvoke, type InvokeArgs } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { open as openDialog } from '@tauri-apps/plugin-dialog';
 import { readDir as fsReadDir, readTextFile as fsReadTextFile, writeTextFile as fsWriteTextFile, mkdir } from '@tauri-apps/plugin-fs';
 
@@ -12,6 +13,59 @@ declare global {
   }
 }
 
+// Finished commands are reported to the backend's command metrics in batches
+interface CommandTiming {
+  command: string;
+  duration_ms: number;
+  failed: boolean;
+  scope: string;
+}
+
+const TIMING_FLUSH_MS = 2000;
+const MAX_PENDING_TIMINGS = 100;
+let pendingTimings: CommandTiming[] = [];
+let timingFlush: ReturnType<typeof setTimeout> | undefined;
+
+const flushTimings = () => {
+  if (timingFlush !== undefined) {
+    clearTimeout(timingFlush);
+    timingFlush = undefined;
+  }
+  const timings = pendingTimings;
+  pendingTimings = [];
+  if (timings.length > 0) {
+    tauriInvoke('record_command_timings', { timings }).catch(() => {});
+  }
+};
+
+// The path a command works on, for slow-operation traces
+const timingScope = (args?: InvokeArgs): string => {
+  if (!args || Array.isArray(args) || args instanceof ArrayBuffer || args instanceof Uint8Array) {
+    return '';
+  }
+  const scope = args.projectPath ?? args.path ?? args.filePath ?? args.dirPath;
+  return typeof scope === 'string' ? scope : '';
+};
+
+// Every command goes through here, so each one is timed from call to result
+const invoke = async <T = unknown>(command: string, args?: InvokeArgs): Promise<T> => {
+  const started = performance.now();
+  let failed = false;
+  try {
+    return await tauriInvoke<T>(command, args);
+  } catch (e) {
+    failed = true;
+    throw e;
+  } finally {
+    pendingTimings.push({ command, duration_ms: performance.now() - started, failed, scope: timingScope(args) });
+    if (pendingTimings.length >= MAX_PENDING_TIMINGS) {
+      flushTimings();
+    } else if (timingFlush === undefined) {
+      timingFlush = setTimeout(flushTimings, TIMING_FLUSH_MS);
+    }
+  }
+};
+
 // Tauri detection - check multiple ways to ensure we detect Tauri properly
 const isTauri = (() => {
   if (typeof window === "undefined") {
@@ -359,19 +413,28 @@ export const readDir = async (path: string) => {
   // Try Tauri first, regardless of detection (in case detection is faulty)
   try {
     console.log('Attempting to use Tauri custom list_directory command for path:', path);
-    const entries = await invoke('list_directory', { path }) as Array<{
-      name: string;
-      path: string;
-      is_directory: boolean;
-      size?: number;
-      modified?: number;
-    }>;
+    const listing = await invoke('list_directory', { path }) as {
+      items: Array<{
+        name: string;
+        path: string;
+        raw_path: string;
+        is_directory: boolean;
+        size?: number;
+        modified?: number;
+      }>;
+      skipped: Array<{ path: string; reason: string }>;
+    };
+    const entries = listing.items;
+    if (listing.skipped.length > 0) {
+      console.warn('Skipped unreadable entries:', listing.skipped);
+    }
     console.log('✅ Tauri custom command successfully returned entries:', entries);
     
     // Transform backend structure to match frontend expectations
     const transformedEntries = entries.map((entry) => ({
       name: entry.name,
-      path: entry.path,
+      // raw_path round-trips file names that are not valid Unicode back to the backend
+      path: entry.raw_path ?? entry.path,
       children: entry.is_directory ? [] : undefined, // Empty array for directories, undefined for files
     }));
     console.log('Transformed entries:', transformedEntries);
@@ -453,27 +516,42 @@ export const getGitStatus = async (projectPath: string) => {
         modified: raw?.modified || [],
         untracked: raw?.untracked || [],
         staged: raw?.staged || [],
+        deleted: raw?.deleted || [],
+        escapingSymlinks: (raw?.escaping_symlinks || []) as string[],
+        intentToAdd: (raw?.intent_to_add || []) as string[],
         isGitRepo: Boolean(raw?.is_git_repo ?? raw?.isGitRepo ?? false),
       };
       console.log('🔧 Git status normalized:', normalized);
       return normalized;
     } catch (error) {
       console.error('❌ Failed to get Git status:', error);
-      return { branch: '', modified: [], untracked: [], staged: [], isGitRepo: false };
+      return { branch: '', modified: [], untracked: [], staged: [], deleted: [], isGitRepo: false };
     }
   }
   
   console.log('⚠️ Not in Tauri environment, returning mock Git status');
-  return { branch: '', modified: [], untracked: [], staged: [], isGitRepo: false };
+  return { branch: '', modified: [], untracked: [], staged: [], deleted: [], isGitRepo: false };
 };
 
-export const stageFile = async (projectPath: string, filePath: string) => {
+/** Payload of `git-status-progress`, emitted while the first status of a large repository is scanned */
+export interface GitStatusProgressEvent {
+  project_path: string;
+  batches_done: number;
+  batches_total: number;
+  entries_scanned: number;
+  elapsed_ms: number;
+  partial: any | null;
+  complete: boolean;
+}
+
+/** Rejects with code "SuspiciousSymlink" when the path is a symlink pointing outside the repository, unless `allowSymlinksOutsideRepo` */
+export const stageFile = async (projectPath: string, filePath: string, allowSymlinksOutsideRepo = false) => {
   console.log('📋 Staging file:', filePath, 'in project:', projectPath);
   
   if (isTauri) {
     try {
       console.log('📞 Invoking stage_file command');
-      const result = await invoke('stage_file', { projectPath, filePath });
+      const result = await invoke('stage_file', { projectPath, filePath, allowSymlinksOutsideRepo });
       console.log('✅ File staged successfully');
       return result;
     } catch (error) {
@@ -492,11 +570,1288 @@ export const unstageFile = async (projectPath: string, filePath: string) => {
   }
 };
 
-export const commitChanges = async (projectPath: string, message: string) => {
+export const markIntentToAdd = async (projectPath: string, paths: string[]) => {
+  return await invoke('mark_intent_to_add', { projectPath, paths }) as string[];
+};
+
+export const unmarkIntentToAdd = async (projectPath: string, paths: string[]) => {
+  return await invoke('unmark_intent_to_add', { projectPath, paths }) as string[];
+};
+
+export interface ChunkHandle {
+  handle: string;
+  chunk_count: number;
+  total_bytes: number;
+}
+
+const isChunked = (value: unknown): value is { chunked: ChunkHandle } =>
+  typeof value === 'object' && value !== null && !Array.isArray(value) && 'chunked' in value;
+
+/** Pull every chunk of a large response in order and parse the reassembled JSON */
+export const resolveChunked = async <T>(value: T | { chunked: ChunkHandle }): Promise<T> => {
+  if (!isChunked(value)) return value;
+  const { handle, chunk_count } = value.chunked;
+  const parts: string[] = [];
+  for (let index = 0; index < chunk_count; index++) {
+    parts.push(await invoke('read_response_chunk', { handle, index }) as string);
+  }
+  return JSON.parse(parts.join('')) as T;
+};
+
+/** Receive a large response as ordered "response-chunk" events instead of pulling chunks */
+export const streamResponse = async <T>(chunked: ChunkHandle): Promise<T> => {
+  const parts: string[] = new Array(chunked.chunk_count);
+  let received = 0;
+  let resolveAll: () => void = () => {};
+  const done = new Promise<void>((resolve) => { resolveAll = resolve; });
+  const unlisten = await listen<{ handle: string; index: number; data: string }>('response-chunk', (event) => {
+    if (event.payload.handle !== chunked.handle) return;
+    parts[event.payload.index] = event.payload.data;
+    received += 1;
+    if (received === chunked.chunk_count) resolveAll();
+  });
+  try {
+    await invoke('stream_response', { handle: chunked.handle });
+    await done;
+  } finally {
+    unlisten();
+  }
+  return JSON.parse(parts.join('')) as T;
+};
+
+export const expandUntrackedDirectory = async (projectPath: string, dirPath: string) => {
   if (isTauri) {
-    return await invoke('commit_changes', { projectPath, message });
+    return await resolveChunked(await invoke('expand_untracked_directory', { projectPath, dirPath }) as string[]);
   }
-  return 'mock-commit-hash';
+  return [];
+};
+
+export const analyzeLineEndings = async (paths: string[], includeIgnored = false) => {
+  if (isTauri) {
+    return await invoke('analyze_line_endings', { paths, includeIgnored });
+  }
+  return [];
+};
+
+export const normalizeLineEndings = async (paths: string[], target: 'lf' | 'crlf' | null, dryRun: boolean, includeIgnored = false) => {
+  if (isTauri) {
+    return await invoke('normalize_line_endings', { paths, target, dryRun, includeIgnored });
+  }
+  return [];
+};
+
+export const openProject = async (path: string) => {
+  if (isTauri) {
+    return await invoke('open_project', { path });
+  }
+  return null;
+};
+
+export const closeProject = async (path: string) => {
+  if (isTauri) {
+    return await invoke('close_project', { path });
+  }
+  return false;
+};
+
+export const runAllowedCommand = async (projectPath: string, commandLine: string, allowShell = false, profile?: string) => {
+  if (isTauri) {
+    return await invoke('run_allowed_command', { projectPath, commandLine, allowShell, profile });
+  }
+  throw new Error('Running commands is not available in development mode');
+};
+
+export interface CheckoutResult {
+  outcome: 'checked_out' | 'reapplied' | 'reapply_conflicts' | 'checkout_failed';
+  stashed: boolean;
+  conflicts: string[];
+  error: string | null;
+  branch: string;
+  created_tracking_branch: boolean;
+}
+
+/** Rejects with code "CaseCollision" (pairs in `case_collisions`) when the branch has paths this filesystem would fold together, unless `allowCaseCollisions`.
+ * Rejects with code "CheckoutBlocked" (files in `paths`) when local changes would be overwritten, unless `force` */
+export const checkoutBranch = async (projectPath: string, branchName: string, autoStash = false, allowCaseCollisions = false, force = false) => {
+  if (isTauri) {
+    return await invoke('checkout_branch', { projectPath, branchName, autoStash, allowCaseCollisions, force }) as CheckoutResult;
+  }
+};
+
+export interface PathCollision {
+  paths: string[];
+  kind: 'case' | 'unicode_normalization';
+}
+
+export const checkTreePortability = async (projectPath: string, reference?: string) => {
+  return await invoke('check_tree_portability', { projectPath, reference }) as {
+    reference: string;
+    commit: string;
+    paths_checked: number;
+    collisions: PathCollision[];
+    case_insensitive_workdir: boolean;
+    normalization_insensitive_workdir: boolean;
+  };
+};
+
+export type CreateBranchResult =
+  | { outcome: 'created'; branch: string; checked_out: boolean; remote_check_error: string | null }
+  | { outcome: 'remote_branch_exists'; remote: string; branch: string; tip: string; last_updated: number | null };
+
+export const createBranch = async (
+  projectPath: string,
+  branchName: string,
+  options?: { startPoint?: string; checkout?: boolean; checkRemote?: boolean; forceLocal?: boolean },
+) => {
+  return await invoke('create_branch', { projectPath, branchName, ...options }) as CreateBranchResult;
+};
+
+export const checkoutRemoteBranch = async (projectPath: string, remoteName: string, branchName: string) => {
+  return await invoke('checkout_remote_branch', { projectPath, remoteName, branchName });
+};
+
+export interface StashEntry {
+  index: number;
+  message: string;
+  oid: string;
+  timestamp: number;
+  auto_stash: boolean;
+  label: string | null;
+}
+
+export const stashList = async (projectPath: string) => {
+  return await invoke('stash_list', { projectPath }) as StashEntry[];
+};
+
+export const dropStaleAutoStashes = async (projectPath: string, olderThanDays?: number) => {
+  return await invoke('drop_stale_auto_stashes', { projectPath, olderThanDays }) as number;
+};
+
+export const getRecentBranches = async (projectPath: string, limit = 10) => {
+  if (isTauri) {
+    return await invoke('get_recent_branches', { projectPath, limit });
+  }
+  return [];
+};
+
+export const parseGitignore = async (path: string) => {
+  if (isTauri) {
+    return await invoke('parse_gitignore', { path });
+  }
+  return null;
+};
+
+export const updateGitignore = async (path: string, edits: Array<Record<string, unknown>>) => {
+  if (isTauri) {
+    return await invoke('update_gitignore', { path, edits });
+  }
+  return null;
+};
+
+export interface IgnoreSuggestion {
+  pattern: string;
+  reason: string;
+  known: boolean;
+  file_count: number;
+  examples: string[];
+}
+
+export const suggestGitignoreRules = async (projectPath: string) => {
+  return await invoke('suggest_gitignore_rules', { projectPath }) as {
+    untracked: number;
+    truncated: boolean;
+    suggestions: IgnoreSuggestion[];
+  };
+};
+
+/** Appends the accepted patterns to the root .gitignore; returns the file and the untracked count left */
+export const applyGitignoreSuggestions = async (projectPath: string, patterns: string[]) => {
+  return await invoke('apply_gitignore_suggestions', { projectPath, patterns }) as { gitignore: unknown; untracked: number };
+};
+
+export interface PickedPath {
+  path: string;
+  outside_workspace: boolean;
+}
+
+export const pickOpenPath = async (projectPath: string, kind: 'file' | 'folder', filters?: string[]) => {
+  return await invoke('pick_open_path', { projectPath, kind, filters }) as PickedPath | null;
+};
+
+export const pickSavePath = async (projectPath: string, defaultName?: string, filters?: string[]) => {
+  return await invoke('pick_save_path', { projectPath, defaultName, filters }) as PickedPath | null;
+};
+
+export interface PinnedPath {
+  path: string;
+  relative_path: string;
+  is_directory: boolean;
+  missing: boolean;
+}
+
+export const pinPath = async (projectPath: string, path: string) => {
+  return await invoke('pin_path', { projectPath, path }) as PinnedPath[];
+};
+
+export const unpinPath = async (projectPath: string, path: string) => {
+  return await invoke('unpin_path', { projectPath, path }) as PinnedPath[];
+};
+
+export const listPinned = async (projectPath: string) => {
+  return await invoke('list_pinned', { projectPath }) as PinnedPath[];
+};
+
+export const reorderPinned = async (projectPath: string, order: string[]) => {
+  return await invoke('reorder_pinned', { projectPath, order }) as PinnedPath[];
+};
+
+export interface TaggedFile {
+  path: string;
+  relative_path: string;
+  tags: string[];
+  missing: boolean;
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+  missing: number;
+}
+
+export const setFileTags = async (projectPath: string, path: string, tags: string[]) => {
+  return await invoke('set_file_tags', { projectPath, path, tags }) as string[];
+};
+
+export const getFileTags = async (projectPath: string, path: string) => {
+  return await invoke('get_file_tags', { projectPath, path }) as string[];
+};
+
+export const listTags = async (projectPath: string) => {
+  return await invoke('list_tags', { projectPath }) as TagCount[];
+};
+
+export const findFilesByTag = async (projectPath: string, tag: string) => {
+  return await invoke('find_files_by_tag', { projectPath, tag }) as TaggedFile[];
+};
+
+export interface EnvProfile {
+  name: string;
+  vars: Record<string, string>;
+  secret_keys: string[];
+  updated_at: string;
+}
+
+export interface ResolvedEnvVar {
+  key: string;
+  value: string;
+  secret: boolean;
+  missing: boolean;
+}
+
+export interface ResolvedEnvProfile {
+  name: string;
+  vars: ResolvedEnvVar[];
+}
+
+export const listEnvProfiles = async (projectPath: string) => {
+  return await invoke('list_env_profiles', { projectPath }) as EnvProfile[];
+};
+
+export const saveEnvProfile = async (projectPath: string, name: string, vars: Record<string, string>, secretKeys: string[]) => {
+  return await invoke('save_env_profile', { projectPath, name, vars, secretKeys }) as EnvProfile;
+};
+
+export const deleteEnvProfile = async (projectPath: string, name: string) => {
+  return await invoke('delete_env_profile', { projectPath, name }) as boolean;
+};
+
+export const resolveEnvProfile = async (projectPath: string, name: string) => {
+  return await invoke('resolve_env_profile', { projectPath, name }) as ResolvedEnvProfile;
+};
+
+export interface LocalHistoryVersion {
+  id: string;
+  timestamp: number;
+  size: number;
+  command: string;
+  insertions: number | null;
+  deletions: number | null;
+}
+
+export const getLocalHistory = async (filePath: string) => {
+  return await invoke('get_local_history', { filePath }) as LocalHistoryVersion[];
+};
+
+export const readLocalHistoryVersion = async (filePath: string, versionId: string) => {
+  return await invoke('read_local_history_version', { filePath, versionId }) as string;
+};
+
+export const restoreLocalHistoryVersion = async (filePath: string, versionId: string, expectedModified?: number) => {
+  return await invoke('restore_local_history_version', { filePath, versionId, expectedModified });
+};
+
+export interface MigrationReport {
+  from_version: number;
+  to_version: number;
+  applied: { version: number; description: string; backed_up: string[] }[];
+  backup_dir: string | null;
+  error: string | null;
+}
+
+export interface StorageInfo {
+  config_dir: string;
+  data_dir: string;
+  schema_version: number;
+  supported_version: number;
+  stores: { name: string; location: 'config' | 'data'; path: string; exists: boolean; bytes: number; files: number }[];
+  total_bytes: number;
+  /** Also emitted at startup as `storage-migration` when anything ran or failed */
+  last_migration: MigrationReport | null;
+}
+
+export const getStorageInfo = async () => {
+  return await invoke('get_storage_info') as StorageInfo;
+};
+
+export const compactStorage = async () => {
+  return await invoke('compact_storage') as {
+    scratch_files: number;
+    history_versions: number;
+    checkpoints: number;
+    blobs: number;
+    audit_entries: number;
+    deleted_branches: number;
+    migration_backups: number;
+    bytes_before: number;
+    bytes_after: number;
+  };
+};
+
+export interface Checkpoint {
+  id: string;
+  project_path: string;
+  label: string | null;
+  created_at: number;
+  file_count: number;
+  total_bytes: number;
+}
+
+export interface CheckpointFileChange {
+  path: string;
+  status: 'added' | 'removed' | 'modified';
+  old_size: number | null;
+  new_size: number | null;
+  old_hash: string | null;
+  new_hash: string | null;
+  /** Only hash and size changes are reported */
+  binary: boolean;
+}
+
+export interface TextDiffResult {
+  unified: string;
+  hunks: { old_start: number; old_lines: number; new_start: number; new_lines: number; header: string; lines: { kind: 'context' | 'insert' | 'delete'; old_line: number | null; new_line: number | null; content: string }[] }[];
+  identical: boolean;
+  binary: boolean;
+  too_large: boolean;
+}
+
+export const createCheckpoint = async (projectPath: string, label?: string) => {
+  return await invoke('create_checkpoint', { projectPath, label }) as Checkpoint;
+};
+
+export const listCheckpoints = async (projectPath: string) => {
+  return await invoke('list_checkpoints', { projectPath }) as Checkpoint[];
+};
+
+/** Omit `toCheckpointId` to compare with the current working tree */
+export const diffCheckpoints = async (projectPath: string, fromCheckpointId: string, toCheckpointId?: string) => {
+  return await invoke('diff_checkpoints', { projectPath, fromCheckpointId, toCheckpointId }) as { from: string; to: string | null; changes: CheckpointFileChange[]; unchanged: number };
+};
+
+export const diffCheckpointFile = async (projectPath: string, checkpointId: string, filePath: string, contextLines?: number) => {
+  return await invoke('diff_checkpoint_file', { projectPath, checkpointId, filePath, contextLines }) as TextDiffResult;
+};
+
+export interface ProjectFingerprint {
+  project_path: string;
+  digest: string;
+  computed_at: number;
+  file_count: number;
+  content_hashes: boolean;
+}
+
+export interface ShutdownBlocker {
+  kind: 'operation' | 'git';
+  label: string;
+  repository: string | null;
+}
+
+/** Work closing the app would interrupt; warn before closing when this is not empty */
+export const getShutdownBlockers = async () => {
+  return await invoke('get_shutdown_blockers') as ShutdownBlocker[];
+};
+
+export const getPreviousSessionLeftovers = async () => {
+  return await invoke('get_previous_session_leftovers') as {
+    partial_directories: string[];
+    index_locks: string[];
+    temp_files: string[];
+    kept: string[];
+  };
+};
+
+/** Progress is emitted as `fingerprint-progress`; cancel with `cancel_operation` and "fingerprint:<projectPath>" */
+export const computeProjectFingerprint = async (projectPath: string, contentHashes?: boolean, operationId?: string) => {
+  return await invoke('compute_project_fingerprint', { projectPath, contentHashes, operationId }) as ProjectFingerprint;
+};
+
+export const compareProjectFingerprint = async (projectPath: string, previousFingerprint: string, operationId?: string) => {
+  return await invoke('compare_project_fingerprint', { projectPath, previousFingerprint, operationId }) as {
+    previous: string;
+    current: ProjectFingerprint;
+    added: string[];
+    removed: string[];
+    changed: string[];
+    directories_compared: number;
+  };
+};
+
+export const getProjectFingerprint = async (projectPath: string) => {
+  return await invoke('get_project_fingerprint', { projectPath }) as ProjectFingerprint | null;
+};
+
+export type PolicyRule = 'encoding' | 'end_of_line' | 'final_newline' | 'trailing_whitespace';
+
+export interface TextPolicy {
+  allowed_encodings: string[];
+  end_of_line: 'lf' | 'crlf' | 'cr' | null;
+  final_newline: boolean;
+  trim_trailing_whitespace: boolean;
+  skip_generated: boolean;
+  exceptions: { glob: string; skip: PolicyRule[] }[];
+}
+
+export interface PolicyViolation {
+  path: string;
+  line: number | null;
+  message: string;
+  fixable: boolean;
+}
+
+/** Omit `policy` to use the project's `.agentic-ide/policy.json`, else the one in settings. Progress is emitted as `text-policy-progress`; cancel with "text-policy:<projectPath>" */
+export const checkTextPolicy = async (projectPath: string, policy?: TextPolicy, operationId?: string) => {
+  return await invoke('check_text_policy', { projectPath, policy, operationId }) as {
+    root: string;
+    files_checked: number;
+    files_skipped: number;
+    violations: Partial<Record<PolicyRule, PolicyViolation[]>>;
+    omitted: number;
+  };
+};
+
+export const fixTextPolicy = async (projectPath: string, dryRun: boolean, policy?: TextPolicy, operationId?: string) => {
+  return await invoke('fix_text_policy', { projectPath, policy, dryRun, operationId }) as {
+    root: string;
+    dry_run: boolean;
+    fixes: { path: string; rules: PolicyRule[] }[];
+    rolled_back: boolean;
+    error: string | null;
+  };
+};
+
+export type WatchMode = 'native' | 'polling';
+
+/** Payload of `watch-mode-changed`; polling mode still reports changes as `fs-change` events */
+export interface WatchModeChangedEvent {
+  root: string;
+  mode: WatchMode;
+  reason: string;
+}
+
+/** Returns false when the workspace already used `mode` */
+export const setWatchMode = async (root: string, mode: WatchMode) => {
+  return await invoke('set_watch_mode', { root, mode }) as boolean;
+};
+
+/** Backend modules compiled into this build, for the About screen */
+export const listBackendModules = async () => {
+  return await invoke('list_backend_modules') as { name: string; version: string; commands: string[] }[];
+};
+
+export interface OutlineSymbol {
+  name: string;
+  kind: string;
+  start_line: number;
+  end_line: number;
+  column: number;
+  children: OutlineSymbol[];
+}
+
+export interface WorkspaceSymbol {
+  name: string;
+  kind: string;
+  path: string;
+  start_line: number;
+  column: number;
+  container: string | null;
+  score: number;
+}
+
+export const getFileOutline = async (path: string) => {
+  return await invoke('get_file_outline', { path }) as OutlineSymbol[];
+};
+
+export const queryWorkspaceSymbols = async (root: string, query: string, limit?: number) => {
+  return await invoke('query_workspace_symbols', { root, query, limit }) as WorkspaceSymbol[];
+};
+
+export interface WordIndexOptions {
+  split_identifiers?: boolean;
+  min_word_length?: number;
+  max_file_bytes?: number;
+  max_tokens_per_file?: number;
+  max_distinct_words?: number;
+}
+
+export interface WordCandidate {
+  word: string;
+  frequency: number;
+}
+
+export interface WordIndexStats {
+  root: string;
+  files: number;
+  distinct_words: number;
+  total_occurrences: number;
+  truncated_files: number;
+  budget_exhausted: boolean;
+}
+
+export const buildWordIndex = async (root: string, options?: WordIndexOptions) => {
+  return await invoke('build_word_index', { root, options }) as WordIndexStats;
+};
+
+export const queryWordIndex = async (root: string, prefix: string, limit?: number) => {
+  return await invoke('query_word_index', { root, prefix, limit }) as WordCandidate[];
+};
+
+export const getWordIndexStats = async (root: string) => {
+  return await invoke('get_word_index_stats', { root }) as WordIndexStats | null;
+};
+
+export interface SaveAsResult {
+  path: string;
+  raw_path: string;
+  size: number;
+  modified: number | null;
+  language_id: string | null;
+  extension_inferred: boolean;
+  overwritten: boolean;
+  staged: boolean;
+  copy: boolean;
+  workspace_root: string | null;
+}
+
+export const saveFileAs = async (
+  targetPath: string,
+  content: string,
+  options: { sourcePath?: string; overwrite?: boolean; saveCopy?: boolean; gitAdd?: boolean; allowOutsideWorkspace?: boolean } = {}
+) => {
+  const { overwrite = false, ...rest } = options;
+  return await invoke('save_file_as', { targetPath, content, overwrite, ...rest }) as SaveAsResult;
+};
+
+export type SaveAction =
+  | { kind: 'editor_config' }
+  | { kind: 'trim_trailing_whitespace' }
+  | { kind: 'command'; command_line: string };
+
+export type SaveHook = SaveAction & {
+  globs?: string[];
+  blocking?: boolean;
+};
+
+export interface SaveStepResult {
+  action: SaveAction;
+  skipped: boolean;
+  modified: boolean;
+  duration_ms: number;
+  error: string | null;
+  output: string | null;
+}
+
+export interface SaveWithHooksResult {
+  steps: SaveStepResult[];
+  content_modified: boolean;
+  content: string;
+  written: boolean;
+  blocked_by: number | null;
+}
+
+export const saveWithHooks = async (filePath: string, content: string, expectedModified?: number) => {
+  return await invoke('save_with_hooks', { filePath, content, expectedModified }) as SaveWithHooksResult;
+};
+
+export interface BulkRenameItem {
+  from: string;
+  to: string;
+  conflict: 'duplicate_target' | 'target_exists' | null;
+  status: 'renamed' | 'failed' | null;
+  error: string | null;
+  index: { staged: string[]; needs_staging: string[]; error: string | null } | null;
+}
+
+export interface BulkRenamePlan {
+  root: string;
+  items: BulkRenameItem[];
+  conflicts: number;
+  applied: boolean;
+}
+
+export const bulkRename = async (
+  root: string,
+  matchPattern: string,
+  renameTemplate: string,
+  dryRun: boolean,
+  patternKind: 'glob' | 'regex' = 'glob',
+  gitAware = true,
+) => {
+  return await invoke('bulk_rename', { root, matchPattern, renameTemplate, patternKind, dryRun, gitAware }) as BulkRenamePlan;
+};
+
+export type ConflictStrategy = 'skip' | 'rename' | 'overwrite';
+
+export interface PartitionRule {
+  glob: string;
+  target_subdir: string;
+}
+
+export interface RestructureMove {
+  from: string;
+  to: string;
+  conflict: 'target_exists' | 'same_as_directory' | null;
+  resolution: ConflictStrategy | null;
+  status: 'moved' | 'skipped' | 'failed' | 'rolled_back' | null;
+  error: string | null;
+  index: BulkRenameItem['index'];
+}
+
+export interface RestructurePlan {
+  directory: string;
+  moves: RestructureMove[];
+  conflicts: number;
+  applied: boolean;
+  rolled_back: boolean;
+  removed_directory: boolean;
+}
+
+export const flattenDirectory = async (dir: string, conflictStrategy: ConflictStrategy, dryRun = false, gitAware = true) => {
+  return await invoke('flatten_directory', { dir, conflictStrategy, dryRun, gitAware }) as RestructurePlan;
+};
+
+export const partitionDirectory = async (
+  dir: string,
+  rules: PartitionRule[],
+  dryRun: boolean,
+  conflictStrategy: ConflictStrategy = 'skip',
+  gitAware = true,
+) => {
+  return await invoke('partition_directory', { dir, rules, conflictStrategy, dryRun, gitAware }) as RestructurePlan;
+};
+
+export interface GitIdentity {
+  label: string;
+  name: string;
+  email: string;
+  signing_key: string | null;
+}
+
+export interface ConfigValue {
+  value: string;
+  origin: string;
+}
+
+export interface EffectiveIdentity {
+  name: ConfigValue | null;
+  email: ConfigValue | null;
+  signing_key: ConfigValue | null;
+  matched_label: string | null;
+}
+
+export const listGitIdentities = async () => {
+  return await invoke('list_git_identities') as GitIdentity[];
+};
+
+export const saveGitIdentity = async (label: string, name: string, email: string, signingKey?: string) => {
+  return await invoke('save_git_identity', { label, name, email, signingKey }) as GitIdentity[];
+};
+
+export const getEffectiveIdentity = async (projectPath: string) => {
+  return await invoke('get_effective_identity', { projectPath }) as EffectiveIdentity;
+};
+
+export const applyIdentity = async (projectPath: string, label: string, scope: 'local' | 'global') => {
+  return await invoke('apply_identity', { projectPath, label, scope }) as EffectiveIdentity;
+};
+
+export interface BootstrapTimings {
+  root: string;
+  started_at: string;
+  critical_ms: number | null;
+  phases: Array<{ phase: string; duration_ms: number; outcome: 'ok' | 'error' | 'timeout' }>;
+}
+
+export const getLastBootstrapTimings = async (projectPath: string) => {
+  return await invoke('get_last_bootstrap_timings', { projectPath }) as BootstrapTimings | null;
+};
+
+export interface WarmupStatus {
+  root: string;
+  enabled: boolean;
+  budget_ms: number;
+  spent_ms: number;
+  tasks: Array<{
+    name: string;
+    state: 'pending' | 'running' | 'yielded' | 'done' | 'not_applicable' | 'skipped' | 'failed';
+    entries: number | null;
+    spent_ms: number;
+    yields: number;
+    error: string | null;
+  }>;
+}
+
+export const getWarmupStatus = async (projectPath: string) => {
+  return await invoke('get_warmup_status', { projectPath }) as WarmupStatus | null;
+};
+
+export interface PendingPush {
+  id: string;
+  repository: string;
+  remote: string;
+  branch: string;
+  local_commit: string;
+  created_at: number;
+  last_error: string;
+  attempts: number;
+  next_attempt_at: number;
+}
+
+export const getPendingPushes = async (projectPath?: string) => {
+  return await invoke('get_pending_pushes', { projectPath }) as PendingPush[];
+};
+
+export const cancelPendingPush = async (id: string) => {
+  return await invoke('cancel_pending_push', { id }) as boolean;
+};
+
+export type PlanStepStatus = 'pending' | 'running' | 'completed' | 'failed' | 'skipped' | 'cancelled' | 'interrupted';
+
+export type PlanStatus = 'active' | 'completed' | 'failed' | 'cancelled' | 'interrupted';
+
+export interface PlanStepInput {
+  description: string;
+  tool?: string;
+}
+
+export interface AuditRecord {
+  timestamp: string;
+  project_path: string;
+  command_line: string;
+  shell: boolean;
+  outcome: string;
+}
+
+export interface PlanStep {
+  description: string;
+  tool: string | null;
+  status: PlanStepStatus;
+  result_summary: string | null;
+  started_at: number | null;
+  finished_at: number | null;
+  operation_ids: string[];
+  head_at_start: string | null;
+  commits: string[];
+  checkpoints: string[];
+  audit_entries: AuditRecord[];
+}
+
+export interface Plan {
+  id: string;
+  project_path: string;
+  session_id: string | null;
+  status: PlanStatus;
+  created_at: number;
+  updated_at: number;
+  steps: PlanStep[];
+}
+
+export interface PlanProgressEvent {
+  plan_id: string;
+  step_index: number | null;
+  plan: Plan;
+}
+
+export const createPlan = async (projectPath: string, steps: PlanStepInput[], sessionId?: string) => {
+  return await invoke('create_plan', { projectPath, steps, sessionId }) as Plan;
+};
+
+export const updatePlanStep = async (
+  planId: string,
+  stepIndex: number,
+  status: PlanStepStatus,
+  options: { resultSummary?: string; operationId?: string; checkpoints?: string[] } = {}
+) => {
+  return await invoke('update_plan_step', { planId, stepIndex, status, ...options }) as Plan;
+};
+
+export const getPlan = async (planId: string) => {
+  return await invoke('get_plan', { planId }) as Plan | null;
+};
+
+export const listPlans = async (projectPath: string) => {
+  return await invoke('list_plans', { projectPath }) as Plan[];
+};
+
+export const cancelPlan = async (planId: string) => {
+  return await invoke('cancel_plan', { planId }) as Plan;
+};
+
+export interface DiffFileEntry {
+  path: string;
+  old_path: string | null;
+  status: 'added' | 'deleted' | 'modified' | 'renamed' | 'copied' | 'typechange';
+  additions: number;
+  deletions: number;
+  binary: boolean;
+  patch: string | null;
+  truncated: boolean;
+  is_generated: boolean;
+  /** .gitattributes suppresses this diff; collapse it by default */
+  diff_suppressed: boolean;
+}
+
+export const diffWorkdirToRef = async (
+  projectPath: string,
+  gitRef: string,
+  includeUntracked = false,
+  pathFilter?: string,
+  includePatches = true,
+) => {
+  return await resolveChunked(await invoke('diff_workdir_to_ref', { projectPath, gitRef, includeUntracked, pathFilter, includePatches }) as { base: string; files: DiffFileEntry[] });
+};
+
+export interface ChangedPath {
+  path: string;
+  old_path: string | null;
+  status: 'added' | 'deleted' | 'modified' | 'renamed' | 'copied' | 'typechange';
+  untracked: boolean;
+}
+
+export interface ChangedPaths {
+  base_ref: string;
+  merge_base: string;
+  files: ChangedPath[];
+  directories: Record<string, { added: number; modified: number; deleted: number; renamed: number; untracked: number }>;
+}
+
+export const getChangedPathsVsRef = async (projectPath: string, baseRef: string) => {
+  return await invoke('get_changed_paths_vs_ref', { projectPath, baseRef }) as ChangedPaths;
+};
+
+export type SummaryMode = 'head' | 'tail' | 'outline' | 'header';
+
+export interface FileSummary {
+  path: string;
+  mode: SummaryMode;
+  hash: string;
+  line_count: number;
+  byte_size: number;
+  encoding: string | null;
+  binary: boolean;
+  content: string | null;
+  outline: OutlineSymbol[] | null;
+  /** 1-based line `content` starts at, for head and tail */
+  start_line: number | null;
+  truncated: boolean;
+  skipped_reason: string | null;
+}
+
+export const getFileSummary = async (path: string, mode: SummaryMode, lines?: number) => {
+  return await invoke('get_file_summary', { path, mode, lines }) as FileSummary;
+};
+
+export const getFilesSummaries = async (paths: string[], mode: SummaryMode, budgetBytes: number, lines?: number) => {
+  return await invoke('get_files_summaries', { paths, mode, lines, budgetBytes }) as { summaries: FileSummary[]; used_bytes: number; failed: string[] };
+};
+
+export const readFileLines = async (path: string, startLine: number, endLine: number) => {
+  return await invoke('read_file_lines', { path, startLine, endLine }) as string;
+};
+
+export interface AssetMetadata {
+  path: string;
+  size: number;
+  kind: 'image' | 'font' | 'audio' | 'video' | 'other';
+  mime: string | null;
+  width: number | null;
+  height: number | null;
+  view_box: string | null;
+  font_family: string | null;
+  duration_secs: number | null;
+  error: string | null;
+}
+
+export const getAssetMetadata = async (path: string) => {
+  return await invoke('get_asset_metadata', { path }) as AssetMetadata;
+};
+
+export const getAssetsMetadata = async (paths: string[]) => {
+  return await invoke('get_assets_metadata', { paths }) as AssetMetadata[];
+};
+
+export const getFileDiff = async (projectPath: string, filePath: string, options?: { staged?: boolean; force?: boolean }) => {
+  return await invoke('get_file_diff', { projectPath, filePath, ...options }) as { entry: DiffFileEntry | null; notice: string | null };
+};
+
+export interface StageableHunk {
+  /** "staged:<digest>" or "unstaged:<digest>"; unchanged while the hunk's lines are */
+  id: string;
+  header: string;
+  old_start: number;
+  old_lines: number;
+  new_start: number;
+  new_lines: number;
+  lines: { kind: 'context' | 'insert' | 'delete'; old_line: number | null; new_line: number | null; content: string }[];
+}
+
+export interface FileDiffBoth {
+  path: string;
+  staged: { entry: DiffFileEntry | null; hunks: StageableHunk[] };
+  unstaged: { entry: DiffFileEntry | null; hunks: StageableHunk[] };
+  staged_additions: number;
+  staged_deletions: number;
+  unstaged_additions: number;
+  unstaged_deletions: number;
+  deleted_but_present: boolean;
+  intent_to_add: boolean;
+  type_change: string | null;
+  notice: string | null;
+}
+
+export const getFileDiffBoth = async (projectPath: string, filePath: string, force?: boolean) => {
+  return await invoke('get_file_diff_both', { projectPath, filePath, force }) as FileDiffBoth;
+};
+
+export interface DiffTotals {
+  files: number;
+  additions: number;
+  deletions: number;
+}
+
+export const getWorktreeDiffStats = async (projectPath: string) => {
+  return await invoke('get_worktree_diff_stats', { projectPath }) as { totals: DiffTotals; suppressed: DiffTotals; suppressed_paths: string[] };
+};
+
+export interface TrackedFile {
+  path: string;
+  staged: boolean;
+  modified: boolean;
+  skip_worktree: boolean;
+  intent_to_add: boolean;
+  score: number | null;
+}
+
+export const listTrackedFiles = async (projectPath: string, pattern?: string, limit = 200, tag?: string) => {
+  return await resolveChunked(await invoke('list_tracked_files', { projectPath, pattern, limit, tag }) as TrackedFile[]);
+};
+
+export interface SparseCheckoutInfo {
+  enabled: boolean;
+  cone_mode: boolean;
+  patterns: string[];
+  not_materialized: number;
+}
+
+export const getSparseCheckout = async (projectPath: string) => {
+  return await invoke('get_sparse_checkout', { projectPath }) as SparseCheckoutInfo;
+};
+
+export const setSparseCheckout = async (projectPath: string, patterns: string[], coneMode = true) => {
+  return await invoke('set_sparse_checkout', { projectPath, patterns, coneMode }) as SparseCheckoutInfo;
+};
+
+export const disableSparseCheckout = async (projectPath: string) => {
+  return await invoke('disable_sparse_checkout', { projectPath }) as SparseCheckoutInfo;
+};
+
+export interface CloneInfo {
+  partial_filter: string | null;
+  shallow: boolean;
+}
+
+export const gitClone = async (url: string, targetPath: string, options: { username?: string; password?: string; filter?: string; depth?: number } = {}) => {
+  return await invoke('git_clone', { url, targetPath, ...options }) as CloneInfo;
+};
+
+export const getCloneInfo = async (projectPath: string) => {
+  return await invoke('get_clone_info', { projectPath }) as CloneInfo;
+};
+
+export const backfillClone = async (projectPath: string) => {
+  return await invoke('backfill_clone', { projectPath }) as CloneInfo;
+};
+
+export type ForgeKind = 'github' | 'gitlab' | 'bitbucket' | 'other';
+
+export interface RemoteHostInfo {
+  remote: string;
+  url: string;
+  host: string;
+  port: number | null;
+  owner: string;
+  repo: string;
+  kind: ForgeKind;
+  web_url: string;
+}
+
+export const getRemoteHostInfo = async (projectPath: string, remote?: string) => {
+  return await invoke('get_remote_host_info', { projectPath, remote }) as RemoteHostInfo;
+};
+
+export const buildRemoteFileUrl = async (projectPath: string, filePath: string, lineRange?: [number, number], gitRef?: string) => {
+  return await invoke('build_remote_file_url', { projectPath, filePath, lineRange, gitRef }) as string;
+};
+
+export interface ProvenanceCommit {
+  hash: string;
+  message: string;
+  summary: string;
+  body: string;
+  trailers: { key: string; value: string }[];
+  author: string;
+  author_email: string;
+  timestamp: number;
+  parents: string[];
+  note: string | null;
+  /** Inclusive 1-based line ranges of the selection this commit last changed */
+  lines: [number, number][];
+  pushed: boolean;
+  commit_url: string | null;
+  issue_refs: { key: string; value: string }[];
+  pull_request: number | null;
+  pull_request_url: string | null;
+}
+
+export const getLineProvenance = async (projectPath: string, filePath: string, startLine: number, endLine: number) => {
+  return await invoke('get_line_provenance', { projectPath, filePath, startLine, endLine }) as {
+    path: string;
+    start_line: number;
+    end_line: number;
+    commits: ProvenanceCommit[];
+    uncommitted: [number, number][];
+    remote: RemoteHostInfo | null;
+    cached: boolean;
+  };
+};
+
+export type ReviewScope = 'staged' | 'worktree' | 'branch_vs_ref';
+
+export interface ReviewBundle {
+  scope: ReviewScope;
+  base_ref: string | null;
+  merge_base: string | null;
+  workspace: { root: string; branch: string | null; head: string | null; dirty: boolean };
+  files: Array<{
+    path: string;
+    old_path: string | null;
+    status: 'added' | 'deleted' | 'modified' | 'renamed' | 'copied' | 'typechange';
+    additions: number;
+    deletions: number;
+    binary: boolean;
+    is_generated: boolean;
+    diff_suppressed: boolean;
+    patch: string | null;
+    truncated: boolean;
+  }>;
+  additions: number;
+  deletions: number;
+  commits: Array<{ hash: string; author: string; timestamp: number; message: string }>;
+  commits_omitted: number;
+  truncated: boolean;
+  manifest_hash: string;
+  created_at: string;
+  markdown?: string;
+}
+
+export interface ReviewBundleOptions {
+  max_file_bytes?: number;
+  max_total_bytes?: number;
+  context_lines?: number;
+  markdown?: boolean;
+}
+
+export type ReviewBundleOutput =
+  | { kind: 'written'; path: string; markdown_path: string | null; manifest_hash: string; files: number; truncated: boolean }
+  | { kind: 'inline'; bundle: ReviewBundle };
+
+/** Without `outputPath` the bundle comes back inline, reassembled here when it was chunked */
+export const exportReviewBundle = async (
+  projectPath: string,
+  scope: ReviewScope,
+  baseRef?: string,
+  outputPath?: string,
+  options?: ReviewBundleOptions,
+): Promise<ReviewBundleOutput> => {
+  const output = await invoke('export_review_bundle', { projectPath, scope, baseRef, outputPath, options }) as
+    | Exclude<ReviewBundleOutput, { kind: 'inline' }>
+    | { kind: 'inline'; bundle: ReviewBundle | { chunked: ChunkHandle } };
+  if (output.kind === 'written') return output;
+  return { kind: 'inline', bundle: await resolveChunked(output.bundle) };
+};
+
+export const getReviewManifestHash = async (projectPath: string, scope: ReviewScope, baseRef?: string) => {
+  return await invoke('get_review_manifest_hash', { projectPath, scope, baseRef }) as string;
+};
+
+export interface BranchOverview {
+  name: string;
+  tip: string;
+  is_head: boolean;
+  is_default: boolean;
+  protected: boolean;
+  upstream: string | null;
+  upstream_gone: boolean;
+  ahead: number | null;
+  behind: number | null;
+  merged: boolean;
+  last_commit_time: number;
+  last_commit_author: string;
+}
+
+export interface MergedBranchCleanup {
+  dry_run: boolean;
+  branches: { name: string; commit: string; upstream: string | null; error: string | null }[];
+}
+
+export const getBranchesOverview = async (projectPath: string) => {
+  return await invoke('get_branches_overview', { projectPath }) as { default_branch: string | null; branches: BranchOverview[] };
+};
+
+export const deleteMergedBranches = async (projectPath: string, dryRun: boolean) => {
+  return await invoke('delete_merged_branches', { projectPath, dryRun }) as MergedBranchCleanup;
+};
+
+export interface DeletedBranch {
+  name: string;
+  oid: string;
+  upstream: string | null;
+  deleted_at: number;
+  commit_exists: boolean;
+  /** Set when the recorded tip was pruned and the HEAD reflog still has one for this branch */
+  reflog_candidate: string | null;
+}
+
+export const listDeletedBranches = async (projectPath: string) => {
+  return await invoke('list_deleted_branches', { projectPath }) as DeletedBranch[];
+};
+
+/** Pass `useReflog` to restore at `reflog_candidate` when the recorded tip is gone */
+export const restoreDeletedBranch = async (projectPath: string, name: string, useReflog?: boolean) => {
+  return await invoke('restore_deleted_branch', { projectPath, name, useReflog }) as { name: string; oid: string; from_reflog: boolean; upstream_restored: boolean };
+};
+
+export const getDirectoryStatusRollup = async (projectPath: string) => {
+  if (isTauri) {
+    return await invoke('get_directory_status_rollup', { projectPath }) as Record<string, { modified: number; untracked: number; staged: number; conflicted: number }>;
+  }
+  return {};
+};
+
+export const restoreDeletedFiles = async (projectPath: string, paths?: string[]) => {
+  if (isTauri) {
+    return await invoke('restore_deleted_files', { projectPath, paths: paths ?? null });
+  }
+  return { restored: [], not_committed: [] };
+};
+
+export interface OperationStatus {
+  operation: 'merge' | 'rebase' | 'cherry_pick' | 'revert' | 'bisect' | 'am' | null;
+  conflicted: string[];
+  /** Set once no conflicts remain: which button finishes the operation */
+  next_action: 'commit_merge' | 'continue_rebase' | 'continue_cherry_pick' | 'continue_revert' | null;
+}
+
+/** Rejects with code "ConflictMarkersRemain" and `conflict_markers` when markers are left in the content */
+export const finalizeMergedFile = async (projectPath: string, filePath: string, mergedContent: string) => {
+  return await invoke('finalize_merged_file', { projectPath, filePath, mergedContent }) as OperationStatus;
+};
+
+export const canContinueOperation = async (projectPath: string) => {
+  return await invoke('can_continue_operation', { projectPath }) as OperationStatus;
+};
+
+export interface CommitResult {
+  commit_id: string;
+  author_name: string;
+  author_email: string;
+  files: DiffFileEntry[];
+  secret_findings: SecretFinding[];
+}
+
+export type CommitInclude = 'staged' | 'all_tracked' | 'paths';
+
+export interface SecretFinding {
+  file: string;
+  line: number;
+  detector: string;
+  excerpt: string;
+}
+
+export type SecretScanScope = 'staged' | 'paths' | 'workspace';
+
+export const commitChanges = async (
+  projectPath: string,
+  message: string,
+  include?: CommitInclude,
+  paths?: string[],
+  allowSecrets?: boolean
+) => {
+  if (isTauri) {
+    return await invoke('commit_changes', { projectPath, message, include, paths, allowSecrets }) as CommitResult;
+  }
+  return { commit_id: 'mock-commit-hash', author_name: 'Mock User', author_email: 'mock@example.com', files: [], secret_findings: [] } as CommitResult;
+};
+
+export const scanForSecrets = async (projectPath: string, scope: SecretScanScope, paths?: string[]) => {
+  return await invoke('scan_for_secrets', { projectPath, scope, paths }) as SecretFinding[];
+};
+
+export type CorruptionKind =
+  | 'missing_head'
+  | 'invalid_head'
+  | 'missing_objects'
+  | 'corrupt_pack'
+  | 'corrupt_index'
+  | 'broken_ref'
+  | 'stale_index_lock'
+  | 'invalid_identity'
+  | 'unreadable';
+
+export type RepairAction = 'rebuild_head' | 'remove_stale_index_lock';
+
+export interface RepositoryHealth {
+  git_dir: string | null;
+  healthy: boolean;
+  issues: { kind: CorruptionKind; details: string; repair: RepairAction | null }[];
+  fsck: { ok: boolean; output: string[] } | null;
+}
+
+export const checkRepositoryHealth = async (projectPath: string, runFsck = false) => {
+  return await invoke('check_repository_health', { projectPath, runFsck }) as RepositoryHealth;
+};
+
+export const repairRepository = async (projectPath: string, action: RepairAction, confirm: boolean) => {
+  return await invoke('repair_repository', { projectPath, action, confirm }) as { action: RepairAction; details: string };
 };
 
 export const getRecentCommits = async (projectPath: string, limit = 10) => {
@@ -506,6 +1861,22 @@ export const getRecentCommits = async (projectPath: string, limit = 10) => {
   return [];
 };
 
+export interface GitBranch {
+  name: string;
+  is_current: boolean;
+  is_remote: boolean;
+  upstream: string | null;
+  tip_short_hash: string | null;
+  tip_summary: string | null;
+}
+
+export const listBranches = async (projectPath: string) => {
+  if (isTauri) {
+    return await invoke('list_branches', { projectPath }) as GitBranch[];
+  }
+  return [];
+};
+
 export const initGitRepo = async (projectPath: string) => {
   console.log('🔧 Initializing Git repository for:', projectPath);
   
@@ -566,13 +1937,13 @@ export const getGitConfig = async (projectPath: string) => {
 };
 
 // Set Git configuration
-export const setGitConfig = async (projectPath: string, name: string, email: string) => {
+export const setGitConfig = async (projectPath: string, name: string, email: string, scope: 'local' | 'global' = 'local') => {
   console.log('⚙️ Setting Git configuration for:', projectPath, { name, email });
   
   if (isTauri) {
     try {
       console.log('📞 Invoking set_git_config command');
-      const result = await invoke('set_git_config', { projectPath, name, email });
+      const result = await invoke('set_git_config', { projectPath, name, email, scope }) as { user_name: string | null; user_email: string | null; is_configured: boolean };
       console.log('✅ Git configuration set successfully');
       return result;
     } catch (error) {
@@ -606,7 +1977,53 @@ export const isGitRepository = async (projectPath: string) => {
 };
 
 // Git Push/Pull operations
-export const gitPush = async (projectPath: string, options?: { remoteName?: string; branchName?: string; username?: string; password?: string; }) => {
+export interface PrePushStepResult {
+  index: number;
+  name: string | null;
+  command_line: string;
+  passed: boolean;
+  exit_code: number | null;
+  duration_ms: number;
+  error: string | null;
+  output_summary: string;
+}
+
+export interface PrePushRun {
+  project_path: string;
+  remote: string;
+  branch: string;
+  started_at: string;
+  finished_at: string;
+  passed: boolean;
+  skipped: boolean;
+  failed_step: number | null;
+  steps: PrePushStepResult[];
+}
+
+export interface PrePushOutputEvent {
+  project_path: string;
+  step: number;
+  stderr: boolean;
+  line: string;
+}
+
+export type ActivityEntry =
+  | { type: 'commit'; timestamp: number; hash: string; summary: string; author: string }
+  | { type: 'file_change'; timestamp: number; kind: 'created' | 'modified' | 'removed' | 'renamed'; path: string }
+  | { type: 'operation'; timestamp: number; operation: string; outcome: string }
+  | { type: 'file_change_burst'; start: number; end: number; count: number; directory: string; description: string };
+
+export const getActivityFeed = async (projectPath: string, options?: { sinceTimestamp?: number; limit?: number; summarize?: boolean }) => {
+  if (!isTauri) return [];
+  return await invoke('get_activity_feed', { projectPath, ...options }) as ActivityEntry[];
+};
+
+export const getLastPrepushRun = async (projectPath: string) => {
+  if (!isTauri) return null;
+  return await invoke('get_last_prepush_run', { projectPath }) as PrePushRun | null;
+};
+
+export const gitPush = async (projectPath: string, options?: { remoteName?: string; branchName?: string; username?: string; password?: string; runPrepushChecks?: boolean; skipChecks?: boolean; }) => {
   const remoteName = options?.remoteName ?? 'origin';
   const branchName = options?.branchName ?? 'main';
   console.log('⬆️ Pushing to remote:', { projectPath, remoteName, branchName, withCredentials: Boolean(options?.password) });
@@ -620,6 +2037,8 @@ export const gitPush = async (projectPath: string, options?: { remoteName?: stri
         branchName: branchName === 'main' ? null : branchName,
         username: options?.username ?? null,
         password: options?.password ?? null,
+        runPrepushChecks: options?.runPrepushChecks ?? null,
+        skipChecks: options?.skipChecks ?? null,
       });
       console.log('✅ Push completed successfully');
       return result;
@@ -682,6 +2101,20 @@ export const listDirectory = async (path: string) => {
   return [];
 };
 
+export interface PathProbe {
+  path: string;
+  exists: boolean;
+  kind: 'file' | 'dir' | 'symlink' | 'missing';
+  size?: number;
+  modified?: number;
+  in_workspace: boolean;
+  error?: string;
+}
+
+export const probePaths = async (paths: string[]) => {
+  return await invoke('probe_paths', { paths }) as PathProbe[];
+};
+
 export const createNewFile = async (filePath: string, content = '') => {
   if (isTauri) {
     return await fsWriteTextFile(filePath, content);
@@ -694,14 +2127,71 @@ export const createNewDirectory = async (dirPath: string) => {
   }
 };
 
-export const renamePath = async (from: string, to: string) => {
+export interface FileReference {
+  file: string;
+  line: number;
+  specifier: string;
+  language: 'javascript' | 'rust' | 'python';
+  relative: boolean;
+}
+
+export interface ReferenceUpdateReport {
+  updated: { file: string; line: number; old_specifier: string; new_specifier: string }[];
+  skipped: { file: string; line: number; specifier: string; reason: string }[];
+}
+
+export interface PathMoveResult {
+  index: unknown | null;
+  references: ReferenceUpdateReport | null;
+}
+
+export interface PathMoveOptions {
+  gitAware?: boolean;
+  /** Rewrite relative JS/TS imports of the moved file; needs `projectPath` */
+  updateReferences?: boolean;
+  projectPath?: string;
+}
+
+export const findFileReferences = async (projectPath: string, filePath: string) => {
+  if (!isTauri) return [];
+  return await invoke('find_file_references', { projectPath, filePath }) as FileReference[];
+};
+
+/**
+ * A path in an event payload: `path` is relative to the workspace root `root`, with forward
+ * slashes and NFC normalization (absolute when outside the root). `absolute` is the OS path,
+ * sent while the `event_absolute_paths` setting is on.
+ */
+export interface WsPath {
+  root: string;
+  path: string;
+  absolute?: string;
+}
+
+/** Payload of `fs-change` */
+export interface FsChangeEvent {
+  workspace_root: string;
+  kind: string;
+  paths: WsPath[];
+}
+
+/** Payload of `paths-remapped`, emitted after a directory is renamed or moved */
+export interface PathsRemappedEvent {
+  from: WsPath;
+  to: WsPath;
+  /** [old, new] paths of open files that were below `from` */
+  open_files: [WsPath, WsPath][];
+}
+
+export const renamePath = async (from: string, to: string, options?: PathMoveOptions) => {
   console.log(`renamePath called with from: ${from}, to: ${to}, isTauri: ${isTauri}`);
 
   // Try Tauri first, regardless of detection (in case detection is faulty)
   try {
     console.log('Attempting to use Tauri custom rename_path command');
-    await invoke('rename_path', { from, to });
+    const result = await invoke('rename_path', { from, to, ...options }) as PathMoveResult;
     console.log('✅ Tauri custom command successfully renamed file');
+    return result;
   } catch (error) {
     console.error('❌ Tauri renamePath failed:', error);
 
@@ -740,14 +2230,15 @@ export const deletePath = async (path: string) => {
   }
 };
 
-export const movePath = async (from: string, to: string) => {
+export const movePath = async (from: string, to: string, options?: PathMoveOptions) => {
   console.log(`movePath called with from: ${from}, to: ${to}, isTauri: ${isTauri}`);
 
   // Try Tauri first, regardless of detection (in case detection is faulty)
   try {
     console.log('Attempting to use Tauri custom move_path command');
-    await invoke('move_path', { from, to });
+    const result = await invoke('move_path', { from, to, ...options }) as PathMoveResult;
     console.log('✅ Tauri custom command successfully moved path');
+    return result;
   } catch (error) {
     console.error('❌ Tauri movePath failed:', error);
 
@@ -838,3 +2329,17 @@ export const deleteGitCredentials = async (projectPath: string) => {
     throw new Error('Git credentials not available in development mode');
   }
 };
+
+/** Keep unsaved editor contents in the workspace; false when no open workspace contains `path` */
+export const updateDraft = async (path: string, content: string) => {
+  return await invoke('update_draft', { path, content }) as boolean;
+};
+
+export const discardDraft = async (path: string) => {
+  return await invoke('discard_draft', { path }) as boolean;
+};
+
+/** Unsaved contents by path, shared by every window on the workspace */
+export const getDrafts = async (projectPath: string) => {
+  return await invoke('get_drafts', { projectPath }) as Record<string, string>;
+};